@@ -0,0 +1,90 @@
+//! Benchmarks of each stage of the data pipeline at representative channel counts.
+//!
+//! Run with `cargo bench`, or `servo bench` for a quick measurement without criterion.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use servo::server::bench::{Workload, CHANNEL_COUNTS, EXPORT_SNAPSHOTS};
+use std::{env, fs, process};
+
+fn pipeline(criterion: &mut Criterion) {
+	let workloads = CHANNEL_COUNTS
+		.iter()
+		.map(|&channels| (channels, Workload::new(channels).expect("failed to generate workload")))
+		.collect::<Vec<_>>();
+
+	let hdf5_path = env::temp_dir().join(format!("servo-bench-{}.hdf5", process::id()));
+
+	let mut group = criterion.benchmark_group("postcard decode");
+	group.throughput(Throughput::Elements(1));
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.decode().unwrap())
+		});
+	}
+
+	group.finish();
+
+	let mut group = criterion.benchmark_group("state merge");
+	group.throughput(Throughput::Elements(1));
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.merge())
+		});
+	}
+
+	group.finish();
+
+	let mut group = criterion.benchmark_group("snapshot insert");
+	group.throughput(Throughput::Elements(1));
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.insert().unwrap())
+		});
+	}
+
+	group.finish();
+
+	// exports take long enough per iteration that the default sample size would take minutes
+	let mut group = criterion.benchmark_group("csv export");
+	group.throughput(Throughput::Elements(EXPORT_SNAPSHOTS as u64));
+	group.sample_size(10);
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.export_csv())
+		});
+	}
+
+	group.finish();
+
+	let mut group = criterion.benchmark_group("hdf5 export");
+	group.throughput(Throughput::Elements(EXPORT_SNAPSHOTS as u64));
+	group.sample_size(10);
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.export_hdf5(&hdf5_path).unwrap())
+		});
+	}
+
+	group.finish();
+
+	let mut group = criterion.benchmark_group("forward serialization");
+	group.throughput(Throughput::Elements(1));
+
+	for (channels, workload) in &workloads {
+		group.bench_with_input(BenchmarkId::from_parameter(channels), workload, |bencher, workload| {
+			bencher.iter(|| workload.serialize_forwarded().unwrap())
+		});
+	}
+
+	group.finish();
+
+	let _ = fs::remove_file(&hdf5_path);
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
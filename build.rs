@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+	// the gRPC service is only generated when the server is built
+	if std::env::var_os("CARGO_FEATURE_SERVER").is_none() {
+		return Ok(());
+	}
+
+	// a vendored protoc is used so that building servo does not require installing one
+	std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+	tonic_build::compile_protos("proto/servo.proto")?;
+
+	Ok(())
+}
@@ -0,0 +1,172 @@
+use common::comm::{CompositeValveState, Measurement};
+use std::collections::HashMap;
+
+/// A value along with the name it is looked up by
+pub(super) struct NamedValue<T : Clone> {
+    pub(super) name : String,
+    pub(super) value : T,
+}
+
+impl<T : Clone> NamedValue<T> {
+    fn new(new_name : String, new_value : T) -> NamedValue<T> {
+        NamedValue {
+            name : new_name,
+            value : new_value,
+        }
+    }
+}
+
+/// A fast and stable ordered vector of objects with a corresponding string key stored in a hashmap
+///
+/// Used in TUI to hold items grabbed from a hashmap / hashset for a constant ordering when iterated through
+/// and holding historic data
+///
+/// This should likely be moved to common if anything outside of the TUI ever needs it
+pub(super) struct StringLookupVector<T : Clone> {
+    lookup : HashMap<String, usize>,
+    pub(super) vector : Vec<NamedValue<T>>,
+}
+
+
+pub(super) struct StringLookupVectorIter<'a, T : Clone> {
+    reference : &'a StringLookupVector<T>,
+    index : usize,
+}
+
+impl<'a, T : Clone> Iterator for StringLookupVectorIter<'a, T> {
+    // we will be counting with usize
+    type Item = &'a NamedValue<T>;
+
+    // next() is the only required method
+    fn next(&mut self) -> Option<Self::Item> {
+        let out = self.reference.vector.get(self.index);
+
+        // Increment the index
+        self.index += 1;
+
+        out
+    }
+}
+
+impl<T : Clone> StringLookupVector<T> {
+    const DEFAULT_CAPACITY : usize = 8;
+    pub(super) fn len(&self) -> usize {
+        self.vector.len()
+    }
+    /// Creates a new StringLookupVector with a specified capacity
+    pub(super) fn with_capacity(capacity : usize) -> StringLookupVector<T> {
+        StringLookupVector {
+            lookup : HashMap::<String, usize>::with_capacity(capacity),
+            vector : Vec::<NamedValue<T>>::with_capacity(capacity),
+        }
+    }
+    /// Creates a new StringLookupVector with default capacity
+    pub(super) fn new() -> StringLookupVector<T> {
+        StringLookupVector::with_capacity(StringLookupVector::<T>::DEFAULT_CAPACITY)
+    }
+    /// Checks if a key is contained within the StringLookupVector
+    pub(super) fn contains_key(&self, key : &str) -> bool {
+        self.lookup.contains_key(key)
+    }
+
+    /// Returns the index of a key in the vector
+    pub(super) fn index_of(&self, key : &str) -> Option<usize> {
+        self.lookup.get(key).copied()
+    }
+
+    /// Adds an object under a key, replacing the object already there if there is one
+    pub(super) fn add(&mut self, name : &String, value : T) {
+        if self.contains_key(name) {
+            self.vector[self.lookup[name]].value = value;
+            return;
+        }
+        self.lookup.insert(name.clone(), self.vector.len());
+        self.vector.push(NamedValue::new(name.clone(), value));
+    }
+
+    /// Sorts the backing vector by name, meaning iterating through this structure will
+    /// go through alphabetical
+    pub(super) fn sort_by_name(&mut self) {
+        self.vector.sort_unstable_by_key(|x| x.name.to_string());
+        for i in 0..self.vector.len() {
+            *self.lookup.get_mut(&self.vector[i].name).unwrap() = i; // Key has to exist by the nature of this structure
+        }
+    }
+
+    /// Gets a mutable reference to the item with the given key, if there is one
+    pub(super) fn get_mut(&mut self, key : &str) -> Option<&mut NamedValue<T>> {
+        let index = self.lookup.get(key);
+        match index {
+            Some(&x) => self.vector.get_mut(x),
+            None => None
+        }
+    }
+
+    pub(super) fn iter(&self) -> StringLookupVectorIter<'_, T> {
+        StringLookupVectorIter::<T> {
+            reference : self,
+            index : 0,
+        }
+    }
+}
+
+/// The latest voltage, current, and state of a valve, along with the rolling averages of its readings
+#[derive(Clone)]
+pub(super) struct FullValveDatapoint {
+    pub(super) voltage : f64,
+    pub(super) current : f64,
+    pub(super) knows_voltage: bool,
+    pub(super) knows_current : bool,
+    pub(super) rolling_voltage_average : f64,
+    pub(super) rolling_current_average : f64,
+    pub(super) state : CompositeValveState,
+}
+
+impl FullValveDatapoint {
+    /// Creates a datapoint for a valve whose voltage and current are not yet known
+    pub(super) fn new(state : CompositeValveState) -> FullValveDatapoint {
+        FullValveDatapoint { voltage : 0.0, current : 0.0, knows_voltage : false, knows_current : false, rolling_voltage_average : 0.0, rolling_current_average : 0.0, state }
+    }
+}
+
+/// The latest measurement of a sensor, along with the rolling average of its value
+#[derive(Clone)]
+pub(super) struct SensorDatapoint {
+    pub(super) measurement : Measurement,
+    pub(super) rolling_average : f64
+}
+
+impl SensorDatapoint {
+    pub(super) fn new(first_measurement : &Measurement) -> SensorDatapoint {
+        SensorDatapoint { measurement : *first_measurement, rolling_average : first_measurement.value }
+    }
+}
+
+/// The usage of the host servo runs on
+#[derive(Clone)]
+pub(super) struct SystemDatapoint {
+    pub(super) cpu_usage : f32,
+    pub(super) mem_usage : f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_follow_items_when_sorted() {
+        let mut vector = StringLookupVector::<f64>::new();
+        vector.add(&"KBPT".to_owned(), 1.0);
+        vector.add(&"FMPT".to_owned(), 2.0);
+        vector.add(&"KBPT".to_owned(), 3.0);
+        assert_eq!(vector.len(), 2);
+
+        vector.sort_by_name();
+        let names : Vec<&str> = vector.iter().map(|pair| pair.name.as_str()).collect();
+        assert_eq!(names, ["FMPT", "KBPT"]);
+
+        assert_eq!(vector.index_of(&"KBPT".to_owned()), Some(1));
+        assert_eq!(vector.get_mut(&"KBPT".to_owned()).unwrap().value, 3.0);
+        assert!(vector.get_mut(&"WTPT".to_owned()).is_none());
+    }
+}
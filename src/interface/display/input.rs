@@ -0,0 +1,87 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::server::units::UnitSystem;
+
+use super::state::TuiData;
+
+/// What the display loop must do after a key is handled, for what cannot be done while drawing
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Action {
+    /// Keep drawing
+    Continue,
+
+    /// Leave the TUI, which shuts down the server
+    Quit,
+
+    /// Run the safing sequence, which must be done asynchronously
+    Safe,
+}
+
+/// Handles a single key pressed by the user.
+/// Pressing Ctrl+C quits.
+/// Pressing Shift+S safes the vehicle, deliberately without a confirmation prompt.
+/// Pressing N starts a note for the shift log, which takes every key until it is entered with Enter or discarded with Esc.
+/// Pressing U cycles the sensor table through the unit systems, which only changes how readings are drawn.
+pub(super) fn handle_key(tui_data : &mut TuiData, key : KeyEvent) -> Action {
+    if let KeyCode::Char('c') | KeyCode::Char('C') = key.code {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Action::Quit;
+        }
+    }
+
+    // While a note is being typed, keys go to the note rather than acting as commands
+    if let Some(draft) = &mut tui_data.note_draft {
+        match key.code {
+            KeyCode::Enter => {
+                tui_data.submitted_note = tui_data.note_draft.take();
+            },
+            KeyCode::Esc => tui_data.note_draft = None,
+            KeyCode::Backspace => {
+                draft.pop();
+            },
+            KeyCode::Char(character) => draft.push(character),
+            _ => {},
+        }
+        return Action::Continue;
+    }
+
+    match key.code {
+        KeyCode::Char('n') | KeyCode::Char('N') => {
+            tui_data.note_draft = Some(String::new());
+        },
+        KeyCode::Char('u') | KeyCode::Char('U') => {
+            let next = UnitSystem::ALL.iter().position(|&system| system == tui_data.units).map_or(0, |index| index + 1);
+            tui_data.units = UnitSystem::ALL[next % UnitSystem::ALL.len()];
+        },
+        KeyCode::Char('S') => return Action::Safe,
+        _ => {},
+    }
+
+    Action::Continue
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interface::theme::{Theme, TuiConfig};
+    use super::*;
+
+    #[test]
+    fn notes_take_every_key_until_entered() {
+        let mut tui_data = TuiData::new(TuiConfig::default(), Theme::STANDARD);
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert_eq!(handle_key(&mut tui_data, press(KeyCode::Char('u'))), Action::Continue);
+        assert_eq!(tui_data.units, UnitSystem::Metric);
+
+        handle_key(&mut tui_data, press(KeyCode::Char('n')));
+        for character in "Safe".chars() {
+            // Shift+S is typed into the note rather than safing the vehicle
+            assert_eq!(handle_key(&mut tui_data, press(KeyCode::Char(character))), Action::Continue);
+        }
+        handle_key(&mut tui_data, press(KeyCode::Enter));
+        assert_eq!(tui_data.submitted_note.as_deref(), Some("Safe"));
+        assert!(tui_data.note_draft.is_none());
+
+        assert_eq!(handle_key(&mut tui_data, press(KeyCode::Char('S'))), Action::Safe);
+        assert_eq!(handle_key(&mut tui_data, KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), Action::Quit);
+    }
+}
@@ -0,0 +1,112 @@
+/// The tables the TUI keeps its readings in, along with their rolling averages.
+mod data;
+
+/// How each key pressed acts on the TUI.
+mod input;
+
+/// Everything the TUI draws, and how it is updated from the server.
+mod state;
+
+/// The tabs, tables, and panels of the TUI, drawn from its state.
+mod widgets;
+
+use crate::server::{note, routes::run_safing_sequence, Shared};
+use std::{error::Error, io::{self, Stdout}, time::Duration};
+use sysinfo::{System, SystemExt};
+
+use tokio::time::sleep;
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::prelude::*;
+
+use input::Action;
+use state::TuiData;
+use super::theme::{Theme, TuiConfig};
+
+/// A function called every display round that draws the ui and handles user input
+/// removed from display due to certain functions returning generic errors, which cause the serializer to have an aneurysm and thus not work with async.
+/// Returns what the caller must do next, as keys are handled by input::handle_key.
+fn display_round(terminal : &mut Terminal<CrosstermBackend<Stdout>>, tui_data : &mut TuiData) -> Action {
+    // Draw the TUI
+	let _ = terminal.draw(|f| widgets::servo_ui(f, tui_data));
+
+    // Handle user input
+    // This is really overly drawn out, but it's manual error handling handled internally to ensure that the generic "Error" returned doesn't mess with async requirements
+    match event::poll(Duration::from_millis(0)) {
+        Ok(true) => {},
+        Ok(false) => return Action::Continue,
+        Err(error) => {
+            println!("Input polling failed : ");
+            println!("{error}");
+            return Action::Quit;
+        },
+    }
+
+    match event::read() {
+        Ok(Event::Key(key)) => input::handle_key(tui_data, key),
+        Ok(_) => Action::Continue,
+        Err(error) => {
+            println!("Input reading failed : ");
+            println!("{error}");
+            Action::Quit
+        },
+    }
+}
+
+/// Attempts to restore the terminal to the pre-servo TUI state
+fn restore_terminal(terminal : &mut Terminal<CrosstermBackend<Stdout>>) -> Result<(), Box<dyn Error>> {
+    // restore terminal
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+/// The async function that drives the entire TUI, drawn as set out in its configuration.
+/// Returns once it is manually quit (from within display_round)
+pub async fn display(shared: Shared, config: TuiConfig) -> io::Result<()> {
+    // setup terminal
+    enable_raw_mode()?;
+
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+	let mut system = System::new_all();
+
+    // create tui_data and run the TUI, whose colors were checked when its configuration was loaded
+    let tick_rate = config.tick_rate();
+    let theme = config.theme().unwrap_or(Theme::STANDARD);
+    let mut tui_data : TuiData = TuiData::new(config, theme);
+    loop {
+		state::update_information(&mut tui_data, &shared, &mut system).await;
+        // Draw the TUI and handle user input, return if told to.
+        match display_round(&mut terminal, &mut tui_data) {
+            Action::Continue => {},
+            Action::Quit => break,
+            // The outcome is recorded in the audit log, as printing would corrupt the TUI
+            Action::Safe => {
+                let _ = run_safing_sequence(&shared, None, "tui").await;
+            },
+        }
+        // Empty notes are dropped, and failures are not printed for the same reason as safing
+        if let Some(content) = tui_data.submitted_note.take().filter(|content| !content.trim().is_empty()) {
+            let _ = note::record(&*shared.database.connection.lock().await, Some("tui"), None, content.trim());
+        }
+        // Wait until next tick
+		sleep(tick_rate).await;
+    }
+
+    // Attempt to restore terminal
+	restore_terminal(&mut terminal).map_err(|error| io::Error::other(error.to_string()))
+}
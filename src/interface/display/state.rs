@@ -0,0 +1,185 @@
+use common::comm::VehicleState;
+use crate::{interface::theme::{Theme, TuiConfig}, server::{clock::{self, ClockSkew}, channel::{self, ValveReading}, config::{ChannelConfig, ValveReadingConfig}, metrics::LatencyReport, outbox::OutboxMessage, units::UnitSystem, Shared}};
+use std::{collections::BTreeMap, ops::Div};
+use sysinfo::{System, SystemExt, CpuExt};
+
+use super::data::{FullValveDatapoint, SensorDatapoint, StringLookupVector, SystemDatapoint};
+
+/// Everything the TUI draws, along with what the user has typed or toggled
+pub(super) struct TuiData {
+    pub(super) sensors : StringLookupVector<SensorDatapoint>,
+    pub(super) valves : StringLookupVector<FullValveDatapoint>,
+    pub(super) system_data : StringLookupVector<SystemDatapoint>,
+    pub(super) channels : BTreeMap<String, ChannelConfig>,
+    pub(super) latency : LatencyReport,
+    pub(super) clocks : BTreeMap<String, ClockSkew>,
+    pub(super) clock_skew_threshold : Option<f64>,
+    pub(super) outbox : Vec<OutboxMessage>,
+    pub(super) valve_readings : ValveReadingConfig,
+    pub(super) note_draft : Option<String>,
+    pub(super) submitted_note : Option<String>,
+    pub(super) selected_tab : usize,
+    pub(super) units : UnitSystem,
+    pub(super) config : TuiConfig,
+    pub(super) theme : Theme,
+}
+
+impl TuiData {
+    pub(super) fn new(config : TuiConfig, theme : Theme) -> TuiData {
+        TuiData {
+            sensors : StringLookupVector::<SensorDatapoint>::new(),
+            valves : StringLookupVector::<FullValveDatapoint>::new(),
+            system_data : StringLookupVector::<SystemDatapoint>::new(),
+            channels : BTreeMap::new(),
+            latency : LatencyReport::default(),
+            clocks : BTreeMap::new(),
+            clock_skew_threshold : None,
+            outbox : Vec::new(),
+            valve_readings : ValveReadingConfig::default(),
+            note_draft : None,
+            submitted_note : None,
+            selected_tab : 0,
+            units : config.units,
+            config,
+            theme,
+        }
+    }
+
+    /// Folds the latest vehicle state into the valve and sensor tables.
+    /// The voltage and current readings of a valve (named after it as configured in valve_readings) are
+    /// shown alongside its state rather than as sensors, and every reading keeps a rolling average.
+    pub(super) fn record_vehicle_state(&mut self, vehicle_state : &VehicleState) {
+        let mut sort_needed = false;
+        for (name, value) in &vehicle_state.valve_states {
+            match self.valves.get_mut(name) {
+                Some(x) => x.value.state = value.clone(),
+                None => {
+                    self.valves.add(name, FullValveDatapoint::new(value.clone()));
+                    sort_needed = true;
+                },
+            }
+        }
+        if sort_needed {
+            self.valves.sort_by_name();
+        }
+
+        sort_needed = false;
+        for (name, value) in &vehicle_state.sensor_readings {
+            let valves = &self.valves;
+            let reading = channel::valve_reading(&self.valve_readings, name, |valve| valves.contains_key(valve));
+
+            if let Some((valve_name, reading)) = reading {
+                let valve = &mut self.valves.get_mut(valve_name).unwrap().value; // The valve was just found by valve_reading
+                match reading {
+                    ValveReading::Current => {
+                        valve.current = value.value;
+                        if !valve.knows_current {
+                            valve.rolling_current_average = value.value;
+                            valve.knows_current = true;
+                        } else {
+                            valve.rolling_current_average *= 0.8;
+                            valve.rolling_current_average += 0.2 * value.value;
+                        }
+                    },
+                    ValveReading::Voltage => {
+                        valve.voltage = value.value;
+                        if !valve.knows_voltage {
+                            valve.rolling_voltage_average = value.value;
+                            valve.knows_voltage = true;
+                        } else {
+                            valve.rolling_voltage_average *= 0.8;
+                            valve.rolling_voltage_average += 0.2 * value.value;
+                        }
+                    },
+                }
+                continue;
+            }
+            match self.sensors.get_mut(name) {
+                Some(x) =>  {
+                    x.value.measurement = *value;
+                    x.value.rolling_average *= 0.8;
+                    x.value.rolling_average += 0.2 * value.value;
+                },
+                None => {
+                    self.sensors.add(name, SensorDatapoint::new(value));
+                    sort_needed = true;
+                },
+            }
+        }
+        if sort_needed {
+            self.sensors.sort_by_name();
+        }
+    }
+}
+
+/// Updates the backing tui_data instance that is used in the rendering functions
+pub(super) async fn update_information(tui_data : &mut TuiData, shared : &Shared, system : &mut System) {
+	// display system statistics
+	system.refresh_cpu();
+	system.refresh_memory();
+
+	let hostname = system.host_name()
+		.unwrap_or("\x1b[33mnone\x1b[0m".to_owned());
+
+    if !tui_data.system_data.contains_key(&hostname) {
+        tui_data.system_data.add(&hostname, SystemDatapoint { cpu_usage : 0.0, mem_usage : 0.0 });
+    }
+
+    let servo_usage : &mut SystemDatapoint = &mut tui_data.system_data.get_mut(&hostname).unwrap().value; // We literally just made sure it existed right above this. If it doesn't something has gone horribly wrong
+
+	servo_usage.cpu_usage = system
+		.cpus()
+		.iter()
+		.fold(0.0, |util, cpu| util + cpu.cpu_usage())
+		.div(system.cpus().len() as f32);
+	servo_usage.mem_usage = system.used_memory() as f32 / system.total_memory() as f32 * 100.0;
+
+	tui_data.latency = shared.metrics.latency.lock().await.report();
+	tui_data.clocks = shared.metrics.clocks.lock().await.current(clock::now());
+	tui_data.outbox = shared.outbox.messages();
+
+	// channels are shown under their display names, which may change on a reload
+	let config = shared.config.current();
+	tui_data.channels.clone_from(&config.channels);
+	tui_data.clock_skew_threshold = config.notifications.events.clock_skew_seconds;
+	tui_data.valve_readings.clone_from(&config.valve_readings);
+
+	// display sensor data
+	let vehicle_state = shared.vehicle.0
+		.lock()
+		.await
+		.clone();
+
+	tui_data.record_vehicle_state(&vehicle_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+    use super::*;
+
+    #[test]
+    fn valve_readings_are_shown_with_their_valves() {
+        let mut tui_data = TuiData::new(TuiConfig::default(), Theme::STANDARD);
+        let mut state = VehicleState::new();
+
+        state.valve_states.insert("BBV".to_owned(), CompositeValveState { commanded: ValveState::Open, actual: ValveState::Open });
+        state.sensor_readings.insert("BBV_V".to_owned(), Measurement { value: 24.0, unit: Unit::Volts });
+        state.sensor_readings.insert("BBV_I".to_owned(), Measurement { value: 0.5, unit: Unit::Amps });
+        state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 100.0, unit: Unit::Psi });
+        tui_data.record_vehicle_state(&state);
+
+        state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 200.0, unit: Unit::Psi });
+        tui_data.record_vehicle_state(&state);
+
+        let valve = &tui_data.valves.vector[0].value;
+        assert!(valve.knows_voltage && valve.knows_current);
+        assert_eq!(valve.voltage, 24.0);
+
+        // only the pressure is a sensor, averaged over both states
+        assert_eq!(tui_data.sensors.len(), 1);
+        let sensor = &tui_data.sensors.vector[0].value;
+        assert_eq!(sensor.measurement.value, 200.0);
+        assert!((sensor.rolling_average - 120.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,527 @@
+use crate::server::{channel, outbox::OutboxStatus, statistics::Statistics, units::UnitSystem, vector::{self, VectorChannel}};
+use ratatui::{prelude::*, widgets::*};
+use std::collections::{HashMap, HashSet};
+
+use crate::interface::theme::{Panel, SensorColumn, Theme, ValveColumn};
+use super::{data::{FullValveDatapoint, SensorDatapoint, StringLookupVector, SystemDatapoint}, state::TuiData};
+
+/// Basic overhead ui drawing function.
+/// Creates the main overarching tab and then draws the selected tab in the remaining space
+pub(super) fn servo_ui(f: &mut Frame, tui_data: &TuiData) {
+    let note_height = if tui_data.note_draft.is_some() { 3 } else { 0 };
+
+    let chunks: std::rc::Rc<[Rect]> = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Fill(1), Constraint::Length(note_height)])
+        .split(f.size());
+
+    let theme = &tui_data.theme;
+
+    let tab_menu = Tabs::new(vec!["Home", "Unused", "Unused"])
+        .block(Block::default().title("Tabs").borders(Borders::ALL))
+        .style(theme.base())
+        .highlight_style(theme.base().fg(theme.text).bold())
+        .select(tui_data.selected_tab)
+        .divider(symbols::line::VERTICAL);
+
+    
+    f.render_widget(tab_menu, chunks[0]);
+
+    match tui_data.selected_tab {
+        0 => home_menu(f, chunks[1], tui_data),
+        _ => bad_tab(f, chunks[1])
+    };
+
+    if let Some(draft) = &tui_data.note_draft {
+        draw_note_draft(f, chunks[2], draft, theme);
+    }
+}
+
+/// Draws the note being typed for the shift log, along with how to enter or discard it
+fn draw_note_draft(f: &mut Frame, area : Rect, draft : &str, theme : &Theme) {
+    let input = Paragraph::new(format!("{draft}_"))
+        .style(theme.base().fg(theme.text))
+        .block(
+            Block::default()
+                .title("Note (Enter to save, Esc to discard)")
+                .borders(Borders::ALL)
+                .style(theme.base())
+        );
+
+    f.render_widget(input, area);
+}
+
+/// Tab render function used when the selected tab is invalid
+fn bad_tab(_: &mut Frame, _ : Rect) {}
+
+/// Home tab render function displaying
+/// System, Valves, and Sensor Information in the configured panels
+fn home_menu(f: &mut Frame, area : Rect, tui_data: &TuiData) {
+    let panels = &tui_data.config.panels;
+
+    // Panels are centered between two fillers
+    let constraints : Vec<Constraint> = std::iter::once(Constraint::Fill(1))
+        .chain(panels.iter().map(|panel| match panel {
+            Panel::System => Constraint::Length(40),
+            Panel::Valves => Constraint::Length(valve_table_width(&tui_data.config.valve_columns)),
+            Panel::Sensors => Constraint::Length(45),
+        }))
+        .chain(std::iter::once(Constraint::Fill(1)))
+        .collect();
+
+    let horizontal  = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
+
+    draw_empty(f, horizontal[0], &tui_data.theme); // Filler for right side of screen to center actual data
+
+    for (panel, &panel_area) in panels.iter().zip(&horizontal[1..]) {
+        match panel {
+            Panel::System => draw_system_info(f, panel_area, tui_data), // System Info Column
+            Panel::Valves => draw_valves(f, panel_area, tui_data), // Valve Data Column
+            Panel::Sensors => draw_sensors(f, panel_area, tui_data), // Sensor Data Column
+        }
+    }
+
+    draw_empty(f, horizontal[panels.len() + 1], &tui_data.theme); // Filler for left side of screen to center actual data
+}
+
+/// Draws an empty table within an area. Used to fill a region with the theme's background
+fn draw_empty(f: &mut Frame, area : Rect, theme : &Theme) {
+    let widths = [
+        Constraint::Fill(1)
+    ];
+    
+    let empty_table: Table<'_> = Table::new(Vec::<Row>::new(), widths)
+        .style(theme.base())
+        .header(
+            Row::new(vec![Span::from("").into_centered_line()])
+                .style(Style::new().bold())
+        );
+
+    
+    f.render_widget(empty_table, area);
+}
+
+/// Draws system info as listed in tui_data.system_data
+/// See update_information for how this data is gathered
+fn draw_system_info(f: &mut Frame, area : Rect, tui_data: &TuiData) {
+	let all_systems : &StringLookupVector<SystemDatapoint> = &tui_data.system_data;
+
+    
+    // Styles used in table
+    let theme = &tui_data.theme;
+    let name_style = theme.base().bold();
+    let data_style = theme.base().fg(theme.text);
+
+    // Make rows
+    let mut rows : Vec<Row> = Vec::<Row>::with_capacity(all_systems.len() * 3);
+
+    for name_datapoint_pair in all_systems.iter() {
+        let name : &String = &name_datapoint_pair.name;
+        let datapoint : &SystemDatapoint = &name_datapoint_pair.value;
+
+        // Name of system
+        rows.push(Row::new(vec![
+            Cell::from(Span::from(name.clone()).into_centered_line()),
+            Cell::from(Span::from("")),
+            Cell::from(Span::from(""))
+        ]).style(name_style));
+        
+        //  CPU Usage
+        rows.push(Row::new(vec![
+            Cell::from(Span::from("CPU Usage").into_right_aligned_line()),
+            Cell::from(Span::from(format!("{:.1}", datapoint.cpu_usage)).into_right_aligned_line()),
+            Cell::from(Span::from("%"))
+        ]).style(data_style));
+        
+        //  Memory Usage
+        rows.push(Row::new(vec![
+            Cell::from(Span::from("Memory Usage").into_right_aligned_line()),
+            Cell::from(Span::from(format!("{:.1}", datapoint.mem_usage)).into_right_aligned_line()),
+            Cell::from(Span::from("%"))
+        ]).style(data_style));
+    }
+
+    // Latency through servo, as the median and 99th percentile of recent samples
+    rows.push(Row::new(vec![
+        Cell::from(Span::from("Latency (p50/p99)").into_centered_line()),
+        Cell::from(Span::from("")),
+        Cell::from(Span::from(""))
+    ]).style(name_style));
+
+    let latency = &tui_data.latency;
+
+    for (path, statistics) in [
+        ("Frame to Log", &latency.frame_to_commit_ms),
+        ("Frame to GUI", &latency.frame_to_forward_ms),
+        ("Command to FC", &latency.command_to_write_ms),
+    ] {
+        let value = statistics
+            .as_ref()
+            .map_or("-".to_owned(), |statistics : &Statistics| format!("{:.1}/{:.1}", statistics.p50, statistics.p99));
+
+        rows.push(Row::new(vec![
+            Cell::from(Span::from(path).into_right_aligned_line()),
+            Cell::from(Span::from(value).into_right_aligned_line()),
+            Cell::from(Span::from("ms"))
+        ]).style(data_style));
+    }
+
+    // Skew of the clocks of GUI clients and the flight computer, flagged once they drift too far
+    if !tui_data.clocks.is_empty() {
+        rows.push(Row::new(vec![
+            Cell::from(Span::from("Clock Skew").into_centered_line()),
+            Cell::from(Span::from("")),
+            Cell::from(Span::from(""))
+        ]).style(name_style));
+    }
+
+    for (participant, skew) in &tui_data.clocks {
+        let style = if skew.drifted(tui_data.clock_skew_threshold) {
+            theme.base().fg(theme.alarm).bold()
+        } else {
+            data_style
+        };
+
+        rows.push(Row::new(vec![
+            Cell::from(Span::from(participant.clone()).into_right_aligned_line()),
+            Cell::from(Span::from(format!("{:+.2}", skew.skew_seconds)).into_right_aligned_line()),
+            Cell::from(Span::from("s"))
+        ]).style(style));
+    }
+
+    // Messages to the vehicle computers still being written, and those which failed, with their age
+    if !tui_data.outbox.is_empty() {
+        rows.push(Row::new(vec![
+            Cell::from(Span::from("Outbox").into_centered_line()),
+            Cell::from(Span::from("")),
+            Cell::from(Span::from(""))
+        ]).style(name_style));
+    }
+
+    for message in &tui_data.outbox {
+        let (status, style) = match message.entry.status {
+            OutboxStatus::Pending => ("pending", data_style),
+            OutboxStatus::Failed => ("FAILED", theme.base().fg(theme.alarm).bold()),
+        };
+
+        rows.push(Row::new(vec![
+            Cell::from(Span::from(format!("{} {}", status, message.entry.description)).into_right_aligned_line()),
+            Cell::from(Span::from(format!("{:.1}", message.age_seconds)).into_right_aligned_line()),
+            Cell::from(Span::from("s"))
+        ]).style(style));
+    }
+
+    //  ~Fixed size widths that can scale to a smaller window
+    let widths = [
+        Constraint::Max(20),
+        Constraint::Max(12),
+        Constraint::Max(2)
+    ];
+
+    //  Make the table itself
+    let sensor_table: Table<'_> = Table::new(rows, widths)
+        .style(name_style)
+        // It has an optional header, which is simply a Row always visible at the top.
+        .header(
+            Row::new(vec![Span::from("Name").into_centered_line(), Span::from("Value").into_centered_line(), Line::from("")])
+                .style(Style::new().bold())
+                // To add space between the header and the rest of the rows, specify the margin
+                .bottom_margin(1),
+        )
+        // As any other widget, a Table can be wrapped in a Block.
+        .block(Block::default().title("Systems").borders(Borders::ALL))
+        // The selected row and its content can also be styled.
+        .highlight_style(Style::new().reversed())
+        // ...and potentially show a symbol in front of the selection.
+        .highlight_symbol(">>");
+
+
+    //  Render
+    f.render_widget(sensor_table, area);
+}
+
+/// The width of each column of the valve table after the name, along with its header
+fn valve_column(column : ValveColumn) -> (u16, Line<'static>) {
+    match column {
+        ValveColumn::Voltage => (7, Span::from("Voltage").into_right_aligned_line()),
+        ValveColumn::VoltageChange => (8, Line::from("")),
+        ValveColumn::Current => (8, Span::from("Current").into_right_aligned_line()),
+        ValveColumn::CurrentChange => (9, Line::from("")),
+        ValveColumn::Derived => (12, Span::from("Derived").into_centered_line()),
+        ValveColumn::Commanded => (12, Span::from("Commanded").into_centered_line()),
+    }
+}
+
+/// The width of the valve panel, fit to its columns, their spacing, and its borders
+fn valve_table_width(columns : &[ValveColumn]) -> u16 {
+    let name_width = 12;
+    columns.iter().map(|&column| valve_column(column).0 + 1).sum::<u16>() + name_width + 2
+}
+
+/// Draws valve states as listed in tui_data.valves
+/// See update_information for how this data is gathered
+fn draw_valves(f: &mut Frame, area : Rect, tui_data: &TuiData) {
+    //  Get valve states from TUI 
+	let full_valves : &StringLookupVector<FullValveDatapoint> = &tui_data.valves;
+    let theme = &tui_data.theme;
+    let columns = &tui_data.config.valve_columns;
+
+    // Make rows
+    let mut rows : Vec<Row> = Vec::<Row>::with_capacity(full_valves.len());
+    for pair in full_valves.iter() {
+        let name = &pair.name;
+        let datapoint = &pair.value;
+        
+        //  Get base style used in this row based on the actual (derived) state of the valve
+        let normal_style = theme.valve_row_style(datapoint.state.actual);
+        let name_style = theme.valve_name_style(datapoint.state.actual);
+
+        // Determine rolling change of voltage and current via value - rolling average of value as calculated by update_information
+        // And color code the change based on it's magnitude and sign (increasing / decreasing)
+        // Color coding is based on fixed thresholds set for voltage and current independently
+        let d_v = datapoint.voltage - datapoint.rolling_voltage_average;
+        let d_v_style = theme.change_style(normal_style, d_v, d_v.abs() >= 0.1);
+
+        let d_i: f64 = datapoint.current - datapoint.rolling_current_average;
+        let d_i_style = theme.change_style(normal_style, d_i, d_i.abs() >= 0.025);
+
+        // Name of Valve
+        let mut cells = vec![
+            Cell::from(Span::from(channel::label(&tui_data.channels, name).to_owned()).into_centered_line().style(name_style)),
+        ];
+
+        for column in columns {
+            cells.push(match column {
+                ValveColumn::Voltage if datapoint.knows_voltage => Cell::from(Span::from(format!("{:.2}", datapoint.voltage)).into_right_aligned_line()),
+                ValveColumn::VoltageChange if datapoint.knows_voltage => Cell::from(Span::from(format!("{:+.3}", d_v)).into_right_aligned_line()).style(d_v_style),    // Rolling change of voltage
+                ValveColumn::Current if datapoint.knows_current => Cell::from(Span::from(format!("{:.3}", datapoint.current)).into_right_aligned_line()),
+                ValveColumn::CurrentChange if datapoint.knows_current => Cell::from(Span::from(format!("{:+.3}", d_i)).into_right_aligned_line()).style(d_i_style),    // Rolling change of current
+                ValveColumn::Voltage | ValveColumn::VoltageChange | ValveColumn::Current | ValveColumn::CurrentChange => Cell::from(""),
+                ValveColumn::Derived => Cell::from(Span::from(format!("{}", datapoint.state.actual)).into_centered_line()).style(theme.state_style(datapoint.state.actual)),    // Actual / Derived state of valve
+                ValveColumn::Commanded => Cell::from(Span::from(format!("{}", datapoint.state.commanded)).into_centered_line()).style(theme.state_style(datapoint.state.commanded)),   // Commanded state of valve
+            });
+        }
+
+        // Make the actual row of info
+        rows.push(Row::new(cells).style(normal_style));
+    }
+
+    let widths : Vec<Constraint> = std::iter::once(Constraint::Length(12))
+        .chain(columns.iter().map(|&column| Constraint::Length(valve_column(column).0)))
+        .collect();
+
+    let header : Vec<Line> = std::iter::once(Span::from("Name").into_centered_line())
+        .chain(columns.iter().map(|&column| valve_column(column).1))
+        .collect();
+
+    let valve_table: Table<'_> = Table::new(rows, widths)
+    .style(theme.base())
+    // It has an optional header, which is simply a Row always visible at the top.
+    .header(
+        Row::new(header)
+            .style(Style::new().bold())
+            // To add space between the header and the rest of the rows, specify the margin
+            .bottom_margin(1),
+    )
+    // As any other widget, a Table can be wrapped in a Block.
+    .block(Block::default().title("Valves").borders(Borders::ALL))
+    // The selected row and its content can also be styled.
+    .highlight_style(Style::new().reversed())
+    // ...and potentially show a symbol in front of the selection.
+    .highlight_symbol(">>");
+
+
+    f.render_widget(valve_table, area);
+}
+
+/// The rolling change of a sensor in the unit system the sensor table is drawn in.
+/// Every conversion is linear, so the change is the difference of the converted value and average.
+fn converted_change(units : UnitSystem, datapoint : &SensorDatapoint) -> f64 {
+    let unit = datapoint.measurement.unit;
+    units.convert(datapoint.measurement.value, unit).0 - units.convert(datapoint.rolling_average, unit).0
+}
+
+/// Makes a single sensor table row for all of the components of a vector or quaternion channel.
+/// The values and rolling changes are listed in component order.
+fn vector_row<'a>(channel : &VectorChannel, full_sensors : &StringLookupVector<SensorDatapoint>, tui_data : &TuiData) -> Row<'a> {
+    let theme = &tui_data.theme;
+    let normal_style = theme.base();
+    let data_style = normal_style.fg(theme.text);
+
+    let datapoints : Vec<&SensorDatapoint> = channel.component_names()
+        .iter()
+        .filter_map(|component_name| full_sensors.index_of(component_name))
+        .map(|index| &full_sensors.vector[index].value)
+        .collect();
+
+    let units = tui_data.units;
+    let values : Vec<String> = datapoints.iter().map(|datapoint| format!("{:.3}", units.convert(datapoint.measurement.value, datapoint.measurement.unit).0)).collect();
+    let changes : Vec<String> = datapoints.iter().map(|datapoint| format!("{:+.3}", converted_change(units, datapoint))).collect();
+    let unit : String = datapoints.first().map(|datapoint| units.convert(0.0, datapoint.measurement.unit).1.to_owned()).unwrap_or_default();
+
+    // Channel Name
+    let mut cells = vec![
+        Cell::from(Span::from(channel.name.clone()).style(normal_style).bold().into_right_aligned_line()),
+    ];
+
+    for column in &tui_data.config.sensor_columns {
+        cells.push(match column {
+            SensorColumn::Value => Cell::from(Span::from(values.join(", ")).into_right_aligned_line().style(data_style)),    // Component values
+            SensorColumn::Unit => Cell::from(Span::from(unit.clone()).into_left_aligned_line().style(data_style.fg(theme.muted))),    // Measurement unit
+            SensorColumn::Change => Cell::from(Span::from(changes.join(", ")).into_left_aligned_line()).style(data_style), // Rolling Change of each component
+        });
+    }
+
+    Row::new(cells).style(normal_style)
+}
+
+/// Draws sensors as listed in tui_data.sensors
+/// See update_information for how this data is gathered
+fn draw_sensors(f: &mut Frame, area : Rect, tui_data: &TuiData) {
+    //  Get sensor measurements from TUI 
+    let full_sensors : &StringLookupVector<SensorDatapoint> = &tui_data.sensors;
+    let columns = &tui_data.config.sensor_columns;
+    
+    //  Styles used in table
+    let theme = &tui_data.theme;
+    let normal_style = theme.base();
+    let data_style = normal_style.fg(theme.text);
+
+    //  Make rows
+    let mut rows : Vec<Row> = Vec::<Row>::with_capacity(full_sensors.len());
+
+    // Vector and quaternion channels are drawn as a single row in place of their first component
+    let sensor_names : Vec<String> = full_sensors.iter().map(|pair| pair.name.clone()).collect();
+    let mut vector_channels : HashMap<String, VectorChannel> = HashMap::new();
+
+    for channel in vector::find_channels(&sensor_names) {
+        for component_name in channel.component_names() {
+            vector_channels.insert(component_name, channel.clone());
+        }
+    }
+
+    let mut drawn_channels : HashSet<String> = HashSet::new();
+
+    for name_datapoint_pair in full_sensors.iter() {
+        let name : &String = &name_datapoint_pair.name;
+        let datapoint : &SensorDatapoint = &name_datapoint_pair.value;
+
+        if let Some(channel) = vector_channels.get(name) {
+            if drawn_channels.insert(channel.name.clone()) {
+                rows.push(vector_row(channel, full_sensors, tui_data));
+            }
+
+            continue;
+        }
+
+        // Determine rolling change of the measurement value via value - rolling average of value as calculated by update_information
+        // And color code the change based on it's magnitude and sign (increasing / decreasing)
+        let d_v = datapoint.measurement.value - datapoint.rolling_average;
+
+        // As values can have vastly differing units, the color code change is 1% of the value, with a minimum change threshold of 0.01 if the value is less than 1
+        let value_magnitude_min : f64 = 1.0;
+        let value_magnitude : f64 = datapoint.rolling_average.abs().max(value_magnitude_min);
+        
+        // If the change is > 1% the rolling averages value, then it's considered significant enough to highlight.
+        // Since sensors have a bigger potential range, a flat delta threshold is a bad idea as it would require configuration.
+        // Significance is judged in the reported unit, so that switching unit systems never changes what is highlighted.
+        let d_v_style = theme.change_style(data_style, d_v, d_v.abs() / value_magnitude >= 0.01);
+        let (value, symbol) = tui_data.units.convert(datapoint.measurement.value, datapoint.measurement.unit);
+
+        // Sensor Name
+        let mut cells = vec![
+            Cell::from(Span::from(channel::label(&tui_data.channels, name).to_owned()).style(normal_style).bold().into_right_aligned_line()),
+        ];
+
+        for column in columns {
+            cells.push(match column {
+                SensorColumn::Value => Cell::from(Span::from(format!("{:.3}", value)).into_right_aligned_line().style(data_style)),    // Measurement value
+                SensorColumn::Unit => Cell::from(Span::from(symbol).into_left_aligned_line().style(data_style.fg(theme.muted))),    // Measurement unit
+                SensorColumn::Change => Cell::from(Span::from(format!("{:+.3}", converted_change(tui_data.units, datapoint))).into_left_aligned_line()).style(d_v_style), // Rolling Change of value (see update_information)
+            });
+        }
+
+        rows.push(Row::new(cells).style(normal_style));
+    }
+
+    //  ~Fixed Lengths with some room to expand
+    let widths : Vec<Constraint> = std::iter::once(Constraint::Min(12))
+        .chain(columns.iter().map(|column| match column {
+            SensorColumn::Value => Constraint::Min(10),
+            SensorColumn::Unit => Constraint::Length(5),
+            SensorColumn::Change => Constraint::Min(14),
+        }))
+        .collect();
+
+    let header : Vec<Line> = std::iter::once(Span::from("Name").into_right_aligned_line())
+        .chain(columns.iter().map(|column| match column {
+            SensorColumn::Value => Span::from("Value").into_right_aligned_line(),
+            SensorColumn::Unit => Span::from("Unit").into_centered_line(),
+            SensorColumn::Change => Span::from("Rolling Change").into_centered_line(),
+        }))
+        .collect();
+
+    //  Make the table itself
+    let sensor_table: Table<'_> = Table::new(rows, widths)
+        .style(normal_style)
+        // It has an optional header, which is simply a Row always visible at the top.
+        .header(
+            Row::new(header)
+                .style(Style::new().bold())
+                // To add space between the header and the rest of the rows, specify the margin
+                .bottom_margin(1),
+        )
+        // As any other widget, a Table can be wrapped in a Block.
+        .block(Block::default().title(format!("Sensors ({}, U to switch)", tui_data.units)).borders(Borders::ALL))
+        // The selected row and its content can also be styled.
+        .highlight_style(Style::new().reversed())
+        // ...and potentially show a symbol in front of the selection.
+        .highlight_symbol(">>");
+
+
+    //  Render
+    f.render_widget(sensor_table, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use common::comm::{CompositeValveState, Measurement, Unit, ValveState, VehicleState};
+    use crate::interface::theme::TuiConfig;
+    use ratatui::backend::TestBackend;
+    use super::*;
+
+    /// Draws the TUI on a test terminal, returning each row of the screen as text.
+    fn draw(tui_data : &TuiData) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(140, 20)).unwrap();
+        terminal.draw(|f| servo_ui(f, tui_data)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn panels_and_units_are_drawn_as_configured() {
+        let config : TuiConfig = toml::from_str("
+            units = 'metric'
+            panels = ['valves', 'sensors']
+            valve_columns = ['commanded']
+        ").unwrap();
+
+        let mut tui_data = TuiData::new(config, Theme::STANDARD);
+        let mut state = VehicleState::new();
+        state.valve_states.insert("BBV".to_owned(), CompositeValveState { commanded: ValveState::Closed, actual: ValveState::Closed });
+        state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 14.503_773_773, unit: Unit::Psi });
+        tui_data.record_vehicle_state(&state);
+
+        let screen = draw(&tui_data).join("\n");
+        assert!(screen.contains("Sensors (metric"));
+        assert!(screen.contains("1.000") && screen.contains("bar"));
+        assert!(screen.contains("Commanded") && !screen.contains("Voltage"));
+        assert!(!screen.contains("Systems"));
+    }
+}
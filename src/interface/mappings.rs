@@ -0,0 +1,488 @@
+use common::comm::NodeMapping;
+use crossterm::{
+	event::{self, Event, KeyCode, KeyEventKind},
+	execute,
+	terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{prelude::*, widgets::*};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, io};
+
+/// The fields of a mapping, in the order they are shown as columns.
+const COLUMNS: [&str; 10] = [
+	"text_id",
+	"board_id",
+	"sensor_type",
+	"channel",
+	"computer",
+	"min",
+	"max",
+	"calibrated_offset",
+	"powered_threshold",
+	"normally_closed",
+];
+
+/// The sensor types cycled through with the space bar.
+const SENSOR_TYPES: [&str; 7] = ["pt", "load_cell", "rail_voltage", "rail_current", "tc", "rtd", "valve"];
+
+/// The computers cycled through with the space bar.
+const COMPUTERS: [&str; 2] = ["flight", "ground"];
+
+/// The keys of the editor, shown when there is no message to show instead.
+const HELP: &str = "←↑↓→ move · enter edit · space cycle · a add · d delete · s save · q quit";
+
+/// Converts an enum of the common crate to the name it is serialized with.
+fn enum_name(value: &impl Serialize) -> String {
+	match serde_json::to_value(value) {
+		Ok(JsonValue::String(name)) => name,
+		_ => String::new(),
+	}
+}
+
+/// Parses an enum of the common crate from the name it is serialized with.
+fn parse_enum<T: DeserializeOwned>(name: &str, options: &[&str]) -> Result<T, String> {
+	serde_json::from_value(JsonValue::String(name.to_owned()))
+		.map_err(|_| format!("must be one of {}", options.join(", ")))
+}
+
+/// Parses an optional number, where an empty cell means none.
+fn parse_optional(text: &str) -> Result<Option<f64>, String> {
+	if text.is_empty() {
+		return Ok(None);
+	}
+
+	text.parse::<f64>()
+		.ok()
+		.filter(|value| value.is_finite())
+		.map(Some)
+		.ok_or_else(|| "must be a number, or empty for none".to_owned())
+}
+
+/// The text shown in a cell of a mapping.
+fn cell(mapping: &NodeMapping, column: usize) -> String {
+	let optional = |value: Option<f64>| value.map_or(String::new(), |value| value.to_string());
+
+	match COLUMNS[column] {
+		"text_id" => mapping.text_id.clone(),
+		"board_id" => mapping.board_id.clone(),
+		"sensor_type" => enum_name(&mapping.sensor_type),
+		"channel" => mapping.channel.to_string(),
+		"computer" => enum_name(&mapping.computer),
+		"min" => optional(mapping.min),
+		"max" => optional(mapping.max),
+		"calibrated_offset" => optional(mapping.calibrated_offset),
+		"powered_threshold" => optional(mapping.powered_threshold),
+		"normally_closed" => mapping.normally_closed.map_or(String::new(), |closed| closed.to_string()),
+		_ => unreachable!(),
+	}
+}
+
+/// Sets a cell of a mapping from the text entered into it.
+fn set_cell(mapping: &mut NodeMapping, column: usize, text: &str) -> Result<(), String> {
+	let text = text.trim();
+
+	match COLUMNS[column] {
+		"text_id" => mapping.text_id = text.to_owned(),
+		"board_id" => mapping.board_id = text.to_owned(),
+		"sensor_type" => mapping.sensor_type = parse_enum(text, &SENSOR_TYPES)?,
+		"channel" => mapping.channel = text.parse().map_err(|_| "must be a non-negative integer")?,
+		"computer" => mapping.computer = parse_enum(text, &COMPUTERS)?,
+		"min" => mapping.min = parse_optional(text)?,
+		"max" => mapping.max = parse_optional(text)?,
+		"calibrated_offset" => mapping.calibrated_offset = parse_optional(text)?,
+		"powered_threshold" => mapping.powered_threshold = parse_optional(text)?,
+		"normally_closed" => {
+			mapping.normally_closed = match text {
+				"" => None,
+				"true" => Some(true),
+				"false" => Some(false),
+				_ => return Err("must be true, false, or empty for none".to_owned()),
+			};
+		},
+		_ => unreachable!(),
+	}
+
+	Ok(())
+}
+
+/// Moves a cell with a fixed set of values on to the next, returning whether it has such values.
+fn cycle(mapping: &mut NodeMapping, column: usize) -> bool {
+	let next = |options: &[&str], current: &str| {
+		let index = options.iter().position(|option| *option == current).map_or(0, |index| index + 1);
+		options[index % options.len()].to_owned()
+	};
+
+	let text = match COLUMNS[column] {
+		"sensor_type" => next(&SENSOR_TYPES, &cell(mapping, column)),
+		"computer" => next(&COMPUTERS, &cell(mapping, column)),
+		"normally_closed" => next(&["", "true", "false"], &cell(mapping, column)),
+		_ => return false,
+	};
+
+	set_cell(mapping, column, &text).is_ok()
+}
+
+/// Checks a configuration's mappings for problems which would keep it from
+/// being used, returning a description of each.
+pub fn validate(mappings: &[NodeMapping]) -> Vec<String> {
+	let mut problems = Vec::new();
+	let mut text_ids = HashMap::new();
+	let mut channels = HashMap::new();
+
+	for (index, mapping) in mappings.iter().enumerate() {
+		let row = index + 1;
+
+		if mapping.text_id.is_empty() {
+			problems.push(format!("row {row} has no text ID"));
+		} else if let Some(first) = text_ids.get(mapping.text_id.as_str()) {
+			problems.push(format!("rows {first} and {row} are both named {}", mapping.text_id));
+		} else {
+			text_ids.insert(mapping.text_id.as_str(), row);
+		}
+
+		if mapping.board_id.is_empty() {
+			problems.push(format!("row {row} has no board ID"));
+		}
+
+		let channel = (mapping.board_id.as_str(), enum_name(&mapping.sensor_type), mapping.channel);
+
+		if let Some(first) = channels.get(&channel) {
+			problems.push(format!("rows {first} and {row} both read {} channel {} of {}", channel.1, channel.2, channel.0));
+		} else {
+			channels.insert(channel, row);
+		}
+
+		if let (Some(min), Some(max)) = (mapping.min, mapping.max) {
+			if min > max {
+				problems.push(format!("row {row} has a minimum above its maximum"));
+			}
+		}
+	}
+
+	problems
+}
+
+/// The state of the mapping editor between frames.
+struct Editor<'a> {
+	configuration_id: &'a str,
+	mappings: Vec<NodeMapping>,
+	table: TableState,
+	column: usize,
+	input: Option<String>,
+	message: Option<(String, bool)>,
+	unsaved: bool,
+	saved: bool,
+	confirm_quit: bool,
+}
+
+impl Editor<'_> {
+	fn row(&self) -> Option<usize> {
+		self.table.selected().filter(|row| *row < self.mappings.len())
+	}
+
+	fn error(&mut self, message: impl Into<String>) {
+		self.message = Some((message.into(), true));
+	}
+
+	fn info(&mut self, message: impl Into<String>) {
+		self.message = Some((message.into(), false));
+	}
+
+	/// Handles a key press, returning whether the editor should close.
+	fn handle(&mut self, key: KeyCode, save: &mut impl FnMut(&[NodeMapping]) -> anyhow::Result<()>) -> bool {
+		if let Some(input) = &mut self.input {
+			match key {
+				KeyCode::Enter => {
+					let text = self.input.take().unwrap_or_default();
+
+					if let Some(row) = self.row() {
+						let column = self.column;
+						let mut mapping = self.mappings[row].clone();
+
+						match set_cell(&mut mapping, column, &text) {
+							Ok(()) => {
+								self.unsaved |= mapping != self.mappings[row];
+								self.mappings[row] = mapping;
+								self.message = None;
+							},
+							Err(problem) => self.error(format!("{} {problem}", COLUMNS[column])),
+						}
+					}
+				},
+				KeyCode::Esc => self.input = None,
+				KeyCode::Backspace => {
+					input.pop();
+				},
+				KeyCode::Char(character) => input.push(character),
+				_ => {},
+			}
+
+			return false;
+		}
+
+		let rows = self.mappings.len();
+		let confirm_quit = std::mem::take(&mut self.confirm_quit);
+
+		match key {
+			KeyCode::Up | KeyCode::Char('k') => self.table.select(self.row().map(|row| row.saturating_sub(1))),
+			KeyCode::Down | KeyCode::Char('j') => self.table.select(self.row().map(|row| (row + 1).min(rows - 1))),
+			KeyCode::Left | KeyCode::Char('h') => self.column = self.column.saturating_sub(1),
+			KeyCode::Right | KeyCode::Char('l') => self.column = (self.column + 1).min(COLUMNS.len() - 1),
+			KeyCode::Enter | KeyCode::Char('e') => {
+				if let Some(row) = self.row() {
+					self.input = Some(cell(&self.mappings[row], self.column));
+				}
+			},
+			KeyCode::Char(' ') => {
+				if let Some(row) = self.row() {
+					if cycle(&mut self.mappings[row], self.column) {
+						self.unsaved = true;
+					} else {
+						self.error(format!("{} has no values to cycle through, so press enter to edit it", COLUMNS[self.column]));
+					}
+				}
+			},
+			KeyCode::Char('a') => {
+				// a new mapping most often reads the next channel of the same board
+				let mapping = match self.row() {
+					Some(row) => NodeMapping {
+						text_id: String::new(),
+						channel: self.mappings[row].channel + 1,
+						calibrated_offset: None,
+						..self.mappings[row].clone()
+					},
+					None => NodeMapping {
+						text_id: String::new(),
+						board_id: String::new(),
+						sensor_type: parse_enum(SENSOR_TYPES[0], &SENSOR_TYPES).expect("sensor type names should be valid"),
+						channel: 0,
+						computer: parse_enum(COMPUTERS[0], &COMPUTERS).expect("computer names should be valid"),
+						max: None,
+						min: None,
+						calibrated_offset: None,
+						powered_threshold: None,
+						normally_closed: None,
+					},
+				};
+
+				let row = self.row().map_or(0, |row| row + 1);
+				self.mappings.insert(row, mapping);
+				self.table.select(Some(row));
+				self.column = 0;
+				self.input = Some(String::new());
+				self.unsaved = true;
+			},
+			KeyCode::Char('d') => {
+				if let Some(row) = self.row() {
+					let removed = self.mappings.remove(row);
+					self.table.select(Some(row.min(self.mappings.len().saturating_sub(1))));
+					self.info(format!("Deleted {}.", if removed.text_id.is_empty() { "mapping" } else { &removed.text_id }));
+					self.unsaved = true;
+				}
+			},
+			KeyCode::Char('s') => {
+				let problems = validate(&self.mappings);
+
+				if let Some(problem) = problems.first() {
+					let others = problems.len() - 1;
+					let plural = if others == 1 { "" } else { "s" };
+
+					if others == 0 {
+						self.error(format!("Not saved: {problem}."));
+					} else {
+						self.error(format!("Not saved: {problem}, along with {others} other problem{plural}."));
+					}
+				} else {
+					match save(&self.mappings) {
+						Ok(()) => {
+							self.unsaved = false;
+							self.saved = true;
+							self.info(format!("Saved {} and sent it to the vehicle.", self.configuration_id));
+						},
+						Err(error) => self.error(format!("Not saved: {error}")),
+					}
+				}
+			},
+			KeyCode::Char('q') | KeyCode::Esc => {
+				if !self.unsaved || confirm_quit {
+					return true;
+				}
+
+				self.confirm_quit = true;
+				self.error("There are unsaved changes, so press q again to discard them.");
+			},
+			_ => {},
+		}
+
+		false
+	}
+
+	fn draw(&mut self, frame: &mut Frame) {
+		let [table_area, footer_area] = {
+			let areas = Layout::default()
+				.direction(Direction::Vertical)
+				.constraints([Constraint::Min(3), Constraint::Length(3)])
+				.split(frame.size());
+
+			[areas[0], areas[1]]
+		};
+
+		let selected_row = self.row();
+		let header = Row::new(COLUMNS).style(Style::new().bold().underlined());
+
+		let rows = self.mappings
+			.iter()
+			.enumerate()
+			.map(|(index, mapping)| {
+				Row::new((0..COLUMNS.len()).map(|column| {
+					let mut text = cell(mapping, column);
+					let mut style = Style::new();
+
+					if selected_row == Some(index) && column == self.column {
+						style = style.reversed();
+
+						if let Some(input) = &self.input {
+							text = format!("{input}▏");
+						}
+					}
+
+					Cell::from(text).style(style)
+				}))
+			});
+
+		let widths = [
+			Constraint::Min(12),
+			Constraint::Length(10),
+			Constraint::Length(12),
+			Constraint::Length(7),
+			Constraint::Length(8),
+			Constraint::Length(8),
+			Constraint::Length(8),
+			Constraint::Length(17),
+			Constraint::Length(17),
+			Constraint::Length(15),
+		];
+
+		let title = format!(
+			" {}{} · {} mapping{} ",
+			self.configuration_id,
+			if self.unsaved { "*" } else { "" },
+			self.mappings.len(),
+			if self.mappings.len() == 1 { "" } else { "s" },
+		);
+
+		let table = Table::new(rows, widths)
+			.header(header)
+			.block(Block::default().borders(Borders::ALL).title(title))
+			.highlight_style(Style::new().bold());
+
+		frame.render_stateful_widget(table, table_area, &mut self.table);
+
+		let footer = match (&self.input, &self.message) {
+			(Some(_), _) => Paragraph::new(format!("Editing {} · enter apply · esc cancel", COLUMNS[self.column])),
+			(None, Some((message, true))) => Paragraph::new(message.as_str()).style(Style::new().fg(Color::Red)),
+			(None, Some((message, false))) => Paragraph::new(message.as_str()).style(Style::new().fg(Color::Green)),
+			(None, None) => Paragraph::new(HELP).style(Style::new().fg(Color::DarkGray)),
+		};
+
+		frame.render_widget(footer.block(Block::default().borders(Borders::ALL)), footer_area);
+	}
+}
+
+/// Opens an interactive table of a configuration's mappings in the terminal,
+/// calling `save` with the edited mappings each time they are saved, once they
+/// pass [`validate`]. Returns whether they were saved at least once.
+pub fn edit_mappings(
+	configuration_id: &str,
+	mappings: Vec<NodeMapping>,
+	mut save: impl FnMut(&[NodeMapping]) -> anyhow::Result<()>,
+) -> io::Result<bool> {
+	let mut table = TableState::default();
+	table.select(Some(0));
+
+	let mut editor = Editor {
+		configuration_id,
+		mappings,
+		table,
+		column: 0,
+		input: None,
+		message: None,
+		unsaved: false,
+		saved: false,
+		confirm_quit: false,
+	};
+
+	if editor.mappings.is_empty() {
+		editor.info(format!("{configuration_id} has no mappings yet, so press a to add one."));
+	}
+
+	enable_raw_mode()?;
+	execute!(io::stdout(), EnterAlternateScreen)?;
+
+	let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+	// the terminal is restored even if drawing fails, so that the shell is left usable
+	let result = (|| loop {
+		terminal.draw(|frame| editor.draw(frame))?;
+
+		if let Event::Key(key) = event::read()? {
+			if key.kind == KeyEventKind::Press && editor.handle(key.code, &mut save) {
+				return Ok(editor.saved);
+			}
+		}
+	})();
+
+	disable_raw_mode()?;
+	execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+	terminal.show_cursor()?;
+
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn problems_are_found_before_saving() {
+		let mapping = |text_id: &str, channel| {
+			let mut mapping = NodeMapping {
+				text_id: text_id.to_owned(),
+				board_id: "sam-01".to_owned(),
+				sensor_type: parse_enum("pt", &SENSOR_TYPES).unwrap(),
+				channel,
+				computer: parse_enum("flight", &COMPUTERS).unwrap(),
+				max: None,
+				min: None,
+				calibrated_offset: None,
+				powered_threshold: None,
+				normally_closed: None,
+			};
+
+			set_cell(&mut mapping, 5, "0").unwrap();
+			set_cell(&mut mapping, 6, "1000").unwrap();
+			mapping
+		};
+
+		let mut mappings = vec![mapping("KBPT", 1), mapping("WTPT", 2)];
+		assert!(validate(&mappings).is_empty());
+
+		// cells are parsed as the column they are in, and left alone if invalid
+		assert!(set_cell(&mut mappings[1], 3, "-1").is_err());
+		assert!(set_cell(&mut mappings[1], 2, "thermistor").is_err());
+		assert!(cycle(&mut mappings[1], 2));
+		assert_eq!(cell(&mappings[1], 2), "load_cell");
+
+		mappings.push(mapping("KBPT", 1));
+		mappings.push(mapping("", 3));
+		set_cell(&mut mappings[3], 5, "2000").unwrap();
+
+		assert_eq!(validate(&mappings), [
+			"rows 1 and 3 are both named KBPT",
+			"rows 1 and 3 both read pt channel 1 of sam-01",
+			"row 4 has no text ID",
+			"row 4 has a minimum above its maximum",
+		]);
+	}
+}
@@ -1,2 +1,11 @@
+#[cfg(feature = "server")]
 mod display;
+
+mod mappings;
+mod theme;
+
+#[cfg(feature = "server")]
 pub use display::display;
+
+pub use mappings::edit_mappings;
+pub use theme::TuiConfig;
@@ -0,0 +1,361 @@
+use anyhow::anyhow;
+use common::comm::ValveState;
+use crate::server::units::UnitSystem;
+use ratatui::style::{Color, Style, Stylize};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, str::FromStr, time::Duration};
+
+/// Configuration of the TUI, loaded from `tui.toml` in the Servo directory.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TuiConfig {
+	/// Whether to use the high-contrast palette, which stays legible in direct sunlight at the pad.
+	pub high_contrast: bool,
+
+	/// The number of times per second the TUI is redrawn.
+	pub refresh_hz: f64,
+
+	/// The system of units the sensor table starts in, which U cycles through while it runs.
+	pub units: UnitSystem,
+
+	/// The panels of the home tab, from left to right.
+	pub panels: Vec<Panel>,
+
+	/// The columns of the valve table after the name of each valve, from left to right.
+	pub valve_columns: Vec<ValveColumn>,
+
+	/// The columns of the sensor table after the name of each sensor, from left to right.
+	pub sensor_columns: Vec<SensorColumn>,
+
+	/// Colors replacing those of the palette.
+	pub colors: ColorConfig,
+}
+
+impl Default for TuiConfig {
+	fn default() -> Self {
+		TuiConfig {
+			high_contrast: false,
+			refresh_hz: 10.0,
+			units: UnitSystem::Native,
+			panels: vec![Panel::System, Panel::Valves, Panel::Sensors],
+			valve_columns: vec![
+				ValveColumn::Voltage,
+				ValveColumn::VoltageChange,
+				ValveColumn::Current,
+				ValveColumn::CurrentChange,
+				ValveColumn::Derived,
+				ValveColumn::Commanded,
+			],
+			sensor_columns: vec![SensorColumn::Value, SensorColumn::Unit, SensorColumn::Change],
+			colors: ColorConfig::default(),
+		}
+	}
+}
+
+impl TuiConfig {
+	/// Loads the configuration at the given path, falling back on defaults if the file does not exist.
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		if !path.exists() {
+			return Ok(TuiConfig::default());
+		}
+
+		let config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+		if !(config.refresh_hz > 0.0 && config.refresh_hz.is_finite()) {
+			anyhow::bail!("refresh rate must be a positive number of hertz");
+		}
+
+		// colors are checked now so that a typo is reported before the TUI takes over the terminal
+		config.theme()?;
+
+		Ok(config)
+	}
+
+	/// The time between redraws of the TUI.
+	pub fn tick_rate(&self) -> Duration {
+		Duration::from_secs_f64(1.0 / self.refresh_hz)
+	}
+
+	/// The palette chosen by `high_contrast`, with any configured colors in place of its own.
+	pub fn theme(&self) -> anyhow::Result<Theme> {
+		let mut theme = if self.high_contrast { Theme::HIGH_CONTRAST } else { Theme::STANDARD };
+		let colors = &self.colors;
+
+		for (color, configured) in [
+			(&mut theme.accent, &colors.accent),
+			(&mut theme.background, &colors.background),
+			(&mut theme.text, &colors.text),
+			(&mut theme.muted, &colors.muted),
+			(&mut theme.open, &colors.open),
+			(&mut theme.closed, &colors.closed),
+			(&mut theme.fault, &colors.fault),
+			(&mut theme.undetermined, &colors.undetermined),
+			(&mut theme.disconnected, &colors.disconnected),
+			(&mut theme.unknown, &colors.unknown),
+			(&mut theme.alarm, &colors.alarm),
+			(&mut theme.rising, &colors.rising),
+			(&mut theme.falling, &colors.falling),
+		] {
+			if let Some(configured) = configured {
+				*color = Color::from_str(configured)
+					.map_err(|_| anyhow!("unrecognized color '{configured}', expected a name such as 'red' or hex such as '#ffe659'"))?;
+			}
+		}
+
+		Ok(theme)
+	}
+}
+
+/// A panel of the home tab.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Panel {
+	/// Usage of the host, latency through servo, and clock skew.
+	System,
+
+	/// The voltage, current, and states of each valve.
+	Valves,
+
+	/// The latest reading of each sensor.
+	Sensors,
+}
+
+/// A column of the valve table.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValveColumn {
+	/// The voltage across the valve.
+	Voltage,
+
+	/// How far the voltage has moved from its rolling average.
+	VoltageChange,
+
+	/// The current through the valve.
+	Current,
+
+	/// How far the current has moved from its rolling average.
+	CurrentChange,
+
+	/// The state of the valve derived from its current.
+	Derived,
+
+	/// The state the valve was last commanded to.
+	Commanded,
+}
+
+/// A column of the sensor table.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorColumn {
+	/// The latest reading.
+	Value,
+
+	/// The unit of the reading.
+	Unit,
+
+	/// How far the reading has moved from its rolling average.
+	Change,
+}
+
+/// Colors replacing those of the palette, each written as a name such as `red`
+/// or in hex such as `#ffe659`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColorConfig {
+	/// The color of titles, borders, and names.
+	pub accent: Option<String>,
+
+	/// The color behind everything.
+	pub background: Option<String>,
+
+	/// The color of values.
+	pub text: Option<String>,
+
+	/// The color of units.
+	pub muted: Option<String>,
+
+	/// The color of open valves.
+	pub open: Option<String>,
+
+	/// The color of closed valves.
+	pub closed: Option<String>,
+
+	/// The color of faulted valves.
+	pub fault: Option<String>,
+
+	/// The color of valves whose state is undetermined.
+	pub undetermined: Option<String>,
+
+	/// The color of disconnected valves.
+	pub disconnected: Option<String>,
+
+	/// The color of valve states this version of servo does not recognize.
+	pub unknown: Option<String>,
+
+	/// The color of values which need attention, such as drifted clocks.
+	pub alarm: Option<String>,
+
+	/// The color of values rising away from their rolling averages.
+	pub rising: Option<String>,
+
+	/// The color of values falling away from their rolling averages.
+	pub falling: Option<String>,
+}
+
+/// The colors the TUI is drawn in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Theme {
+	/// The color of titles, borders, and names.
+	pub accent: Color,
+
+	/// The color behind everything.
+	pub background: Color,
+
+	/// The color of values.
+	pub text: Color,
+
+	/// The color of units.
+	pub muted: Color,
+
+	/// The color of open valves.
+	pub open: Color,
+
+	/// The color of closed valves.
+	pub closed: Color,
+
+	/// The color of faulted valves.
+	pub fault: Color,
+
+	/// The color of valves whose state is undetermined.
+	pub undetermined: Color,
+
+	/// The color of disconnected valves.
+	pub disconnected: Color,
+
+	/// The color of valve states this version of servo does not recognize.
+	pub unknown: Color,
+
+	/// The color of values which need attention, such as drifted clocks.
+	pub alarm: Color,
+
+	/// The color of values rising away from their rolling averages.
+	pub rising: Color,
+
+	/// The color of values falling away from their rolling averages.
+	pub falling: Color,
+}
+
+impl Theme {
+	/// The YJSP palette, in yellow on black.
+	pub const STANDARD: Theme = Theme {
+		accent: Color::from_u32(0x00ffe659),
+		background: Color::from_u32(0),
+		text: Color::from_u32(0x00eeeeee),
+		muted: Color::from_u32(0x00bbbbbb),
+		open: Color::from_u32(0x007aff85),
+		closed: Color::from_u32(0x00ff5959),
+		fault: Color::from_u32(0x0075a8ff),
+		undetermined: Color::from_u32(0x00444444),
+		disconnected: Color::from_u32(0x00bbbbbb),
+		unknown: Color::from_u32(0x00da3ee6),
+		alarm: Color::from_u32(0x00db2c2c),
+		rising: Color::Green,
+		falling: Color::Red,
+	};
+
+	/// Pure white, black, and fully saturated colors, which stay legible when
+	/// sunlight washes out the softer tones of the standard palette.
+	pub const HIGH_CONTRAST: Theme = Theme {
+		accent: Color::from_u32(0x00ffff00),
+		background: Color::from_u32(0),
+		text: Color::from_u32(0x00ffffff),
+		muted: Color::from_u32(0x00ffffff),
+		open: Color::from_u32(0x0000ff00),
+		closed: Color::from_u32(0x00ff0000),
+		fault: Color::from_u32(0x0000ffff),
+		undetermined: Color::from_u32(0x00808080),
+		disconnected: Color::from_u32(0x00ffffff),
+		unknown: Color::from_u32(0x00ff00ff),
+		alarm: Color::from_u32(0x00ff0000),
+		rising: Color::from_u32(0x0000ff00),
+		falling: Color::from_u32(0x00ff0000),
+	};
+
+	/// The style everything is drawn in unless it stands out.
+	pub fn base(&self) -> Style {
+		Style::new().bg(self.background).fg(self.accent)
+	}
+
+	/// The style of a valve state, shown as a badge.
+	#[allow(unreachable_patterns)] // valve states may be added to common before servo recognizes them
+	pub fn state_style(&self, state: ValveState) -> Style {
+		match state {
+			ValveState::Undetermined => self.base().fg(self.text).bg(self.undetermined).bold(),
+			ValveState::Disconnected => self.base().fg(self.background).bg(self.disconnected).bold(),
+			ValveState::Open => self.base().fg(self.background).bg(self.open).bold(),
+			ValveState::Closed => self.base().fg(self.background).bg(self.closed).bold(),
+			ValveState::Fault => self.base().fg(self.background).bg(self.fault).bold(),
+			_ => self.base().fg(self.background).bg(self.unknown).bold(),
+		}
+	}
+
+	/// The style of a whole row of the valve table, by the actual state of the valve.
+	pub fn valve_row_style(&self, state: ValveState) -> Style {
+		match state {
+			ValveState::Undetermined => self.base().fg(self.text).bg(self.undetermined),
+			ValveState::Disconnected => self.base().fg(self.background).bg(self.disconnected),
+			ValveState::Fault => self.base().fg(self.background).bg(self.closed),
+			_ => self.base().fg(self.text),
+		}
+	}
+
+	/// The style of the name of a valve, by its actual state.
+	pub fn valve_name_style(&self, state: ValveState) -> Style {
+		match state {
+			ValveState::Undetermined => self.base().bg(self.undetermined).bold(),
+			ValveState::Disconnected => self.base().fg(self.background).bg(self.disconnected).bold(),
+			ValveState::Fault => self.base().bg(self.closed).bold(),
+			_ => self.base().bold(),
+		}
+	}
+
+	/// The style of a change from a rolling average, which stands out once it is significant.
+	pub fn change_style(&self, normal: Style, change: f64, significant: bool) -> Style {
+		if !significant {
+			normal
+		} else if change > 0.0 {
+			normal.fg(self.rising)
+		} else {
+			normal.fg(self.falling)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn configured_colors_replace_the_palette() {
+		let config: TuiConfig = toml::from_str("
+			high_contrast = true
+			units = 'metric'
+			panels = ['valves', 'sensors']
+
+			[colors]
+			open = 'blue'
+			closed = '#102030'
+		").unwrap();
+
+		let theme = config.theme().unwrap();
+		assert_eq!(theme.open, Color::Blue);
+		assert_eq!(theme.closed, Color::Rgb(0x10, 0x20, 0x30));
+		assert_eq!(theme.accent, Theme::HIGH_CONTRAST.accent);
+		assert_eq!(config.panels, [Panel::Valves, Panel::Sensors]);
+		assert_eq!(config.units, UnitSystem::Metric);
+		assert_eq!(config.valve_columns, TuiConfig::default().valve_columns);
+
+		let config: TuiConfig = toml::from_str("colors.fault = 'sunlight'").unwrap();
+		assert!(config.theme().is_err());
+	}
+}
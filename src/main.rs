@@ -1,6 +1,6 @@
 use clap::{builder::PossibleValuesParser, Arg, ArgAction, Command};
 use jeflog::fail;
-use servo::tool;
+use servo::{server::{export, import, retention, units::UnitSystem}, tool};
 use std::{env, fs, path::{Path, PathBuf}, process};
 
 fn main() -> anyhow::Result<()> {
@@ -19,10 +19,163 @@ fn main() -> anyhow::Result<()> {
 	let matches = Command::new("servo")
 		.about("Servo command line tool")
 		.subcommand_required(true)
+		.arg(
+			Arg::new("server")
+				.long("server")
+				.global(true)
+				.help("The base URL of the control server, such as https://server-01.local:7200. Overrides SERVO_SERVER and config.toml.")
+		)
+		.arg(
+			Arg::new("token")
+				.long("token")
+				.global(true)
+				.help("The session token to send with requests. Overrides SERVO_TOKEN and config.toml.")
+		)
+		.subcommand(
+			Command::new("archive")
+				.about("Archives the complete record of a session, or of a time range such as 1700000000..1700003600, for review after a test.")
+				.arg(
+					Arg::new("target")
+						.required(true)
+						.help("The ID of a session, or a range of Unix timestamps in which the end may be left out.")
+				)
+				.arg(
+					Arg::new("output")
+						.short('o')
+						.long("output")
+						.help("The gzipped tarball written, which defaults to archive-<target>.tar.gz.")
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+		)
+		.subcommand(
+			Command::new("backfill")
+				.about("Pushes logged vehicle data to the configured time-series database, such as InfluxDB.")
+				.arg(
+					Arg::new("from")
+						.long("from")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("to")
+						.long("to")
+						.value_parser(clap::value_parser!(f64))
+				)
+		)
+		.subcommand(
+			Command::new("bench")
+				.about("Measures the throughput of each stage of the data pipeline on this machine.")
+				.arg(
+					Arg::new("channels")
+						.long("channels")
+						.short('c')
+						.value_parser(clap::value_parser!(usize))
+				)
+				.arg(
+					Arg::new("time")
+						.long("time")
+						.short('t')
+						.value_parser(clap::value_parser!(f64))
+						.default_value("1.0")
+				)
+		)
+		.subcommand(
+			Command::new("capture")
+				.about("Examines telemetry frames recorded to a frame log or pcap capture.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("decode")
+						.about("Decodes each recorded frame, showing why any could not be decoded.")
+						.arg(
+							Arg::new("capture_path")
+								.required(true)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+						.arg(
+							Arg::new("port")
+								.long("port")
+								.help("The UDP port whose datagrams are read from a pcap capture.")
+								.value_parser(clap::value_parser!(u16))
+								.default_value("7201")
+						)
+						.arg(
+							Arg::new("failed")
+								.long("failed")
+								.help("Only shows frames which could not be decoded.")
+								.action(ArgAction::SetTrue)
+						)
+				)
+				.subcommand(
+					Command::new("bad-frames")
+						.about("Displays hexdumps of the most recent frames the control server could not decode.")
+						.arg(
+							Arg::new("limit")
+								.long("limit")
+								.short('n')
+								.value_parser(clap::value_parser!(usize))
+								.default_value("10")
+						)
+						.arg(
+							Arg::new("full")
+								.long("full")
+								.help("Shows every byte kept of each frame rather than only the first.")
+								.action(ArgAction::SetTrue)
+						)
+						.arg(
+							Arg::new("save")
+								.long("save")
+								.help("Saves the bytes and full hexdump of each frame to this directory.")
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+		)
 		.subcommand(
 			Command::new("clean")
 				.about("Cleans the Servo directory and database.")
 		)
+		.subcommand(
+			Command::new("db")
+				.about("Maintains the database of the control server.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("convert")
+						.about("Fully vacuums a database created by an older servo so that it can be vacuumed incrementally. Stop the server first.")
+						.arg(
+							Arg::new("database")
+								.long("database")
+								.help("The database to convert, which is the server's own by default.")
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+				.subcommand(
+					Command::new("backup")
+						.about("Backs up the database of the control server to another database, such as one on an attached drive.")
+						.arg(
+							Arg::new("destination")
+								.required(true)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+						.arg(
+							Arg::new("follow")
+								.long("follow")
+								.help("Keeps the backup up to date with the control server until interrupted.")
+								.action(ArgAction::SetTrue)
+						)
+						.arg(
+							Arg::new("interval")
+								.long("interval")
+								.help("The number of seconds between updates of the backup while following.")
+								.value_parser(clap::value_parser!(f64))
+								.default_value("2.0")
+						)
+						.arg(
+							Arg::new("batch")
+								.long("batch")
+								.help("The most rows fetched from the control server at once.")
+								.value_parser(clap::value_parser!(usize))
+								.default_value("5000")
+						)
+				)
+		)
 		.subcommand(
 			Command::new("deploy")
 				.about("Deploys YJSP software to all available computers on the network.")
@@ -58,7 +211,7 @@ fn main() -> anyhow::Result<()> {
 					Arg::new("component")
 						.required(true)
 						.ignore_case(true)
-						.value_parser(PossibleValuesParser::new(["flight", "sam"]))
+						.value_parser(PossibleValuesParser::new(["flight", "load", "sam"]))
 				)
 				.arg(
 					Arg::new("frequency")
@@ -73,13 +226,48 @@ fn main() -> anyhow::Result<()> {
 						.short('t')
 						.value_parser(clap::value_parser!(f64))
 				)
+				.arg(
+					Arg::new("transport")
+						.long("transport")
+						.required(false)
+						.default_value("udp")
+						.value_parser(PossibleValuesParser::new(["udp", "tcp"]))
+				)
+				.arg(
+					Arg::new("channels")
+						.long("channels")
+						.required(false)
+						.default_value("500")
+						.value_parser(clap::value_parser!(usize))
+				)
+				.arg(
+					Arg::new("rate")
+						.long("rate")
+						.required(false)
+						.default_value("200.0")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("physics")
+						.long("physics")
+						.required(false)
+						.num_args(0..=1)
+						.value_parser(clap::value_parser!(PathBuf))
+						.help("Fills and blows down tanks as valves are commanded, using the built-in model or the TOML model at the given path.")
+				)
+				.arg(
+					Arg::new("key")
+						.long("key")
+						.required(false)
+						.help("The pre-shared key to answer servo's handshake with, if it requires one.")
+				)
 		)
 		.subcommand(
 			Command::new("export")
 				.about("Exports vehicle state data from a specified timestamp to a specified timestamp.")
 				.arg(
 					Arg::new("output_path")
-						.required(true)
+						.required_unless_present("save_preset")
 						.short('o')
 				)
 				.arg(
@@ -94,6 +282,91 @@ fn main() -> anyhow::Result<()> {
 						.long("to")
 						.value_parser(clap::value_parser!(f64))
 				)
+				.arg(
+					Arg::new("preset")
+						.required(false)
+						.long("preset")
+						.conflicts_with("save_preset")
+						.help("Takes any options which are not given from the named export preset.")
+				)
+				.arg(
+					Arg::new("save_preset")
+						.required(false)
+						.long("save-preset")
+						.value_name("NAME")
+						.help("Saves the given options as a named export preset rather than exporting.")
+				)
+				.arg(
+					Arg::new("description")
+						.required(false)
+						.long("description")
+						.requires("save_preset")
+						.help("Describes what the saved preset is used for.")
+				)
+				.arg(
+					Arg::new("format")
+						.required(false)
+						.long("format")
+						.value_parser(PossibleValuesParser::new(export::FORMATS))
+						.value_delimiter(',')
+						.action(ArgAction::Append)
+						.help("The format exported, which otherwise follows the output file's extension. Several comma-separated formats are exported together as a zip archive.")
+				)
+				.arg(
+					Arg::new("channels")
+						.required(false)
+						.long("channels")
+						.help("A comma-separated list of the sensors and valves exported, rather than every channel. Each may be a name, a glob such as FU_* or *_PT, or a regular expression between slashes.")
+				)
+				.arg(
+					Arg::new("rate")
+						.required(false)
+						.long("rate")
+						.value_parser(clap::value_parser!(f64))
+						.help("The rate in hertz the export is downsampled to, such as 1 or 10 for a quick plot. Each interval is exported as the mean of each sensor, with its minimum and maximum as NAME.min and NAME.max.")
+				)
+				.arg(
+					Arg::new("units")
+						.required(false)
+						.long("units")
+						.value_parser(clap::value_parser!(UnitSystem))
+						.help("The unit system readings are converted into: native, metric, or si.")
+				)
+		)
+		.subcommand(
+			Command::new("import")
+				.about("Imports a CSV or HDF5 file of vehicle data, such as an earlier export or a DAQ log, into the control server.")
+				.arg(
+					Arg::new("path")
+						.required(true)
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("format")
+						.long("format")
+						.short('f')
+						.help("The format of the file, which is otherwise told from its extension.")
+						.value_parser(PossibleValuesParser::new(import::FORMATS))
+				)
+				.arg(
+					Arg::new("rename")
+						.long("rename")
+						.action(ArgAction::Append)
+						.help("Logs a column under another channel name, given as COLUMN=CHANNEL.")
+				)
+				.arg(
+					Arg::new("unit")
+						.long("unit")
+						.action(ArgAction::Append)
+						.help("Gives the unit of a column of bare numbers, such as ch0=psi or ch1=bar.")
+				)
+				.arg(
+					Arg::new("time_offset")
+						.long("time-offset")
+						.help("Seconds added to every time in the file, such as the Unix time a DAQ log started at.")
+						.value_parser(clap::value_parser!(f64))
+						.allow_negative_numbers(true)
+				)
 		)
 		.subcommand(
 			Command::new("locate")
@@ -104,12 +377,233 @@ fn main() -> anyhow::Result<()> {
 						.value_parser(PossibleValuesParser::new(["gui", "servo", "flight", "sam"]))
 				)
 		)
+		.subcommand(
+			Command::new("login")
+				.about("Logs into the control server, saving the session for later commands.")
+				.arg(
+					Arg::new("username")
+						.long("username")
+						.short('u')
+						.help("The user to log in as, which is asked for if not given.")
+				)
+		)
+		.subcommand(
+			Command::new("logout")
+				.about("Ends the session saved by login and forgets it.")
+		)
+		.subcommand(
+			Command::new("logs")
+				.about("Maintains the request logs and audit log of the control server.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("prune")
+						.about("Deletes the logs older than an age, although those from the last day are always kept.")
+						.arg(
+							Arg::new("before")
+								.long("before")
+								.required(true)
+								.help("The age of the oldest logs kept, such as 30d, 12h, or 2w.")
+								.value_parser(retention::parse_age)
+						)
+				)
+		)
+		.subcommand(
+			Command::new("mappings")
+				.about("Manages the mappings stored on the control server.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("edit")
+						.about("Edits a configuration's mappings in the terminal, pushing them to the server when saved.")
+						.arg(
+							Arg::new("configuration")
+								.required(false)
+								.help("The configuration to edit, or to create if it does not exist. Defaults to the active configuration.")
+						)
+				)
+		)
+		.subcommand(
+			Command::new("note")
+				.about("Enters a note into the shift log, or lists the shift log if no note is given.")
+				.arg(
+					Arg::new("content")
+						.num_args(1..)
+						.help("The text of the note, which need not be quoted.")
+				)
+				.arg(
+					Arg::new("from")
+						.long("from")
+						.help("Lists only notes entered at or after this Unix timestamp.")
+						.value_parser(clap::value_parser!(f64))
+						.conflicts_with("content")
+				)
+				.arg(
+					Arg::new("to")
+						.long("to")
+						.help("Lists only notes entered at or before this Unix timestamp.")
+						.value_parser(clap::value_parser!(f64))
+						.conflicts_with("content")
+				)
+		)
+		.subcommand(
+			Command::new("ping")
+				.about("Measures the latency and loss to a host, or to every known host, along with whether its servo and flight ports respond.")
+				.arg(
+					Arg::new("host")
+						.required_unless_present("all")
+						.help("The hostname or IP address to ping.")
+				)
+				.arg(
+					Arg::new("all")
+						.long("all")
+						.help("Pings every known hostname, as found by locate.")
+						.conflicts_with("host")
+						.action(ArgAction::SetTrue)
+				)
+				.arg(
+					Arg::new("count")
+						.long("count")
+						.short('c')
+						.help("The number of echoes to send to each host.")
+						.value_parser(clap::value_parser!(u32).range(1..))
+						.default_value("4")
+				)
+				.arg(
+					Arg::new("timeout")
+						.long("timeout")
+						.help("The number of seconds to wait for each echo and connection.")
+						.value_parser(clap::value_parser!(f64))
+						.default_value("1.0")
+				)
+		)
+		.subcommand(
+			Command::new("preflight")
+				.about("Checks that ports, the database, disk space, configuration, clock, and expected hosts are ready for a test.")
+				.arg(
+					Arg::new("json")
+						.long("json")
+						.action(ArgAction::SetTrue)
+				)
+		)
+		.subcommand(
+			Command::new("process")
+				.about("Replays a recorded capture through the telemetry pipeline offline, optionally comparing the result against golden output.")
+				.arg(
+					Arg::new("capture_path")
+						.required(true)
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("port")
+						.long("port")
+						.help("The UDP port whose datagrams are read from a pcap capture.")
+						.value_parser(clap::value_parser!(u16))
+						.default_value("7201")
+				)
+				.arg(
+					Arg::new("output")
+						.long("output")
+						.short('o')
+						.help("Writes the produced snapshots to a file, as golden output for later comparisons.")
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("golden")
+						.long("golden")
+						.short('g')
+						.help("Compares the produced snapshots against golden output, failing if they differ.")
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("tolerance")
+						.long("tolerance")
+						.help("The largest difference between numbers considered equal when comparing.")
+						.value_parser(clap::value_parser!(f64))
+						.default_value("0.0")
+				)
+		)
+		.subcommand(
+			Command::new("promote")
+				.about("Promotes the standby server on this machine to primary, fencing the old primary.")
+		)
+		.subcommand(
+			Command::new("report")
+				.about("Generates a data quality report of a session, or of a time range such as 1700000000..1700003600.")
+				.arg(
+					Arg::new("target")
+						.required(true)
+						.help("The ID of a session, or a range of Unix timestamps in which either bound may be left out.")
+				)
+				.arg(
+					Arg::new("output")
+						.short('o')
+						.long("output")
+						.help("Writes the report to a file, as HTML if it ends in .html and as Markdown otherwise. A run sheet ending in .pdf is written as a PDF.")
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("run_sheet")
+						.long("run-sheet")
+						.help("Generates a printable run sheet of the sequences run, commands sent, alerts, and operator notes instead.")
+						.action(ArgAction::SetTrue)
+				)
+				.arg(
+					Arg::new("t_zero")
+						.long("t-zero")
+						.help("The Unix timestamp of T-0 on the run sheet, which defaults to the start of the range.")
+						.value_parser(clap::value_parser!(f64))
+						.requires("run_sheet")
+				)
+		)
 		.subcommand(
 			Command::new("run")
 				.about("Sends a Python sequence to be run on the flight computer.")
 				.arg(
 					Arg::new("path")
 						.required(true)
+						.help("A local script to save and run as an ad-hoc sequence, or the name of a stored sequence.")
+				)
+				.arg(
+					Arg::new("simulate")
+						.long("simulate")
+						.help("Simulates the sequence, or a local script at the path, instead of running it.")
+						.action(ArgAction::SetTrue)
+				)
+				.arg(
+					Arg::new("assume")
+						.long("assume")
+						.help("Assumes a sensor reads within a range while simulating, such as KBPT=400:600 or KBPT=500.")
+						.requires("simulate")
+						.action(ArgAction::Append)
+				)
+		)
+		.subcommand(
+			Command::new("safe")
+				.about("Immediately runs the safing sequence of the active configuration on the flight computer.")
+		)
+		.subcommand(
+			Command::new("sequence")
+				.about("Displays and compares sequences stored on the control server.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("show")
+						.about("Displays a stored sequence with syntax highlighting.")
+						.arg(
+							Arg::new("name")
+								.required(true)
+						)
+				)
+				.subcommand(
+					Command::new("diff")
+						.about("Displays a unified diff from a stored sequence to a local script or bundle.")
+						.arg(
+							Arg::new("name")
+								.required(true)
+						)
+						.arg(
+							Arg::new("local_path")
+								.value_parser(clap::value_parser!(PathBuf))
+								.required(true)
+						)
 				)
 		)
 		.subcommand(
@@ -127,6 +621,36 @@ fn main() -> anyhow::Result<()> {
 						.action(ArgAction::SetTrue)
 				)
 		)
+		.subcommand(
+			Command::new("sniff")
+				.about("Decodes and prints telemetry frames as they arrive, tapping the frame log if the server holds the telemetry port.")
+				.arg(
+					Arg::new("port")
+						.long("port")
+						.help("The UDP port telemetry is received on.")
+						.value_parser(clap::value_parser!(u16))
+						.default_value("7201")
+				)
+				.arg(
+					Arg::new("capture")
+						.long("capture")
+						.help("Taps this frame log rather than receiving telemetry.")
+						.value_parser(clap::value_parser!(PathBuf))
+				)
+				.arg(
+					Arg::new("board")
+						.long("board")
+						.help("Only shows the channels mapped to this board. May be repeated.")
+						.action(ArgAction::Append)
+				)
+				.arg(
+					Arg::new("channel")
+						.long("channel")
+						.short('c')
+						.help("Only shows the channels matching this name, glob such as FU_*, or regular expression between slashes. May be repeated.")
+						.action(ArgAction::Append)
+				)
+		)
 		.subcommand(
 			Command::new("sql")
 				.about("Executes a SQL statement on the control server database and displays the result.")
@@ -135,9 +659,131 @@ fn main() -> anyhow::Result<()> {
 						.required(true)
 				)
 		)
+		.subcommand(
+			Command::new("stats")
+				.about("Displays the min, max, mean, standard deviation, and percentiles of a sensor over a time window.")
+				.arg(
+					Arg::new("channel")
+						.required(true)
+				)
+				.arg(
+					Arg::new("from")
+						.long("from")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("to")
+						.long("to")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("json")
+						.long("json")
+						.action(ArgAction::SetTrue)
+				)
+		)
+		.subcommand(
+			Command::new("status")
+				.about("Displays the status of the control server and the software running on the flight computer.")
+		)
+		.subcommand(
+			Command::new("sync")
+				.about("Pushes logged snapshots, configurations, sequences, and events to another servo, skipping what it already has.")
+				.arg(
+					Arg::new("remote")
+						.required(true)
+						.help("The address of the other server, such as archive.local:7200.")
+				)
+				.arg(
+					Arg::new("from")
+						.long("from")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("to")
+						.long("to")
+						.value_parser(clap::value_parser!(f64))
+				)
+				.arg(
+					Arg::new("pull")
+						.long("pull")
+						.action(ArgAction::SetTrue)
+						.help("Pulls from the other server into this one instead.")
+				)
+				.arg(
+					Arg::new("remote_token")
+						.long("remote-token")
+						.help("The session token to send to the other server, which must belong to an admin of whichever server receives.")
+				)
+		)
+		.subcommand(
+			Command::new("tail")
+				.about("Prints the latest vehicle state held by the control server, refreshing it until stopped.")
+				.arg(
+					Arg::new("channel")
+						.long("channel")
+						.short('c')
+						.help("Only shows the channels matching this name, glob such as FU_*, or regular expression between slashes. May be repeated.")
+						.action(ArgAction::Append)
+				)
+				.arg(
+					Arg::new("interval")
+						.long("interval")
+						.help("The number of seconds between refreshes.")
+						.value_parser(clap::value_parser!(f64))
+						.default_value("1")
+				)
+				.arg(
+					Arg::new("once")
+						.long("once")
+						.help("Prints the vehicle state once and exits.")
+						.action(ArgAction::SetTrue)
+				)
+		)
+		.subcommand(
+			Command::new("thresholds")
+				.about("Syncs redline files with the redlines stored on the control server.")
+				.subcommand_required(true)
+				.subcommand(
+					Command::new("pull")
+						.about("Writes the redlines stored on the server to a redline file, or prints them.")
+						.arg(
+							Arg::new("path")
+								.required(false)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+				.subcommand(
+					Command::new("push")
+						.about("Replaces the redlines stored on the server with those of a redline file.")
+						.arg(
+							Arg::new("path")
+								.required(true)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+				.subcommand(
+					Command::new("validate")
+						.about("Checks a redline file for problems without sending it to the server.")
+						.arg(
+							Arg::new("path")
+								.required(true)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+				.subcommand(
+					Command::new("diff")
+						.about("Shows how pushing a redline file would change the redlines stored on the server.")
+						.arg(
+							Arg::new("path")
+								.required(true)
+								.value_parser(clap::value_parser!(PathBuf))
+						)
+				)
+		)
 		.subcommand(
 			Command::new("upload")
-				.about("Uploads a Python sequence to the control server to be stored for future use.")
+				.about("Uploads a Python sequence, or a directory bundling main.py with helper files, to the control server to be stored for future use.")
 				.arg(
 					Arg::new("sequence_path")
 						.value_parser(clap::value_parser!(PathBuf))
@@ -145,23 +791,58 @@ fn main() -> anyhow::Result<()> {
 				)
 		)
 		.get_matches();
-	
+
+	tool::configure_client(&matches, &servo_dir)?;
+
 	match matches.subcommand() {
+		Some(("archive", args)) => tool::archive(args)?,
+		Some(("backfill", args)) => tool::backfill(args)?,
+		#[cfg(feature = "server")]
+		Some(("bench", args)) => tool::bench(args)?,
+		Some(("capture", args)) => tool::capture(args)?,
 		Some(("clean", _)) => tool::clean(&servo_dir)?,
+		#[cfg(feature = "server")]
+		Some(("db", args)) => tool::db(&servo_dir, args)?,
 		Some(("deploy", args)) => tool::deploy(args),
 		Some(("emulate", args)) => tool::emulate(args)?,
-		Some(("export", args)) => {
-			tool::export(
-				args.get_one::<f64>("from").copied(),
-				args.get_one::<f64>("to").copied(),
-				args.get_one::<String>("output_path").unwrap(),
-			)?;
-		},
+		Some(("export", args)) => tool::export(args)?,
+		Some(("import", args)) => tool::import(args)?,
 		Some(("locate", args)) => tool::locate(args)?,
-		Some(("run", args)) => tool::run(args.get_one::<String>("path").unwrap())?,
+		Some(("login", args)) => tool::login(args)?,
+		Some(("logout", _)) => tool::logout()?,
+		Some(("logs", args)) => tool::logs(args)?,
+		#[cfg(feature = "tui")]
+		Some(("mappings", args)) => tool::mappings(args)?,
+		#[cfg(not(feature = "tui"))]
+		Some(("mappings", _)) => {
+			fail!("Mappings are edited in the terminal interface, which this build of servo leaves out. Rebuild it with the tui feature.");
+			process::exit(1);
+		},
+		Some(("note", args)) => tool::note(args)?,
+		Some(("ping", args)) => tool::ping(args)?,
+		#[cfg(feature = "server")]
+		Some(("preflight", args)) => tool::preflight(&servo_dir, args)?,
+		Some(("process", args)) => tool::process(args)?,
+		Some(("promote", _)) => tool::promote()?,
+		Some(("report", args)) => tool::report(args)?,
+		Some(("run", args)) => tool::run(args)?,
+		Some(("safe", _)) => tool::safe()?,
+		Some(("sequence", args)) => tool::sequence(args)?,
+		#[cfg(feature = "server")]
 		Some(("serve", args)) => tool::serve(&servo_dir, args)?,
+		Some(("sniff", args)) => tool::sniff(&servo_dir, args)?,
 		Some(("sql", args)) => tool::sql(args.get_one::<String>("raw_sql").unwrap())?,
+		Some(("stats", args)) => tool::stats(args)?,
+		Some(("status", _)) => tool::status()?,
+		Some(("sync", args)) => tool::sync(args)?,
+		Some(("tail", args)) => tool::tail(args)?,
+		Some(("thresholds", args)) => tool::thresholds(args)?,
 		Some(("upload", args)) => tool::upload(args.get_one::<PathBuf>("sequence_path").unwrap())?,
+		#[cfg(not(feature = "server"))]
+		Some(("bench" | "db" | "preflight" | "serve", _)) => {
+			fail!("This build of servo leaves out the server, which the command needs. Rebuild it with the server feature.");
+			process::exit(1);
+		},
 		_ => {
 			fail!("Invalid command. Please check the command you entered.");
 			process::exit(1);
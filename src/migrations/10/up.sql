@@ -0,0 +1,6 @@
+CREATE TABLE Interlocks (
+	sequence_name TEXT NOT NULL,
+	condition TEXT NOT NULL,
+
+	PRIMARY KEY (sequence_name, condition)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE TelemetryGaps (
+	gap_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	first_missing INTEGER NOT NULL,
+	last_missing INTEGER NOT NULL CHECK(last_missing >= first_missing),
+	started_at REAL NOT NULL,
+	ended_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')) CHECK(ended_at >= started_at)
+);
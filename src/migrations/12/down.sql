@@ -0,0 +1,2 @@
+DROP INDEX PositionFixesByTime;
+DROP TABLE PositionFixes;
\ No newline at end of file
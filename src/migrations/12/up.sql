@@ -0,0 +1,11 @@
+CREATE TABLE PositionFixes (
+	fix_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	receiver TEXT NOT NULL,
+	latitude REAL NOT NULL CHECK(latitude BETWEEN -90 AND 90),
+	longitude REAL NOT NULL CHECK(longitude BETWEEN -180 AND 180),
+	altitude REAL,
+	fix_quality INTEGER NOT NULL CHECK(fix_quality > 0),
+	recorded_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')) CHECK(recorded_at > 0)
+);
+
+CREATE INDEX PositionFixesByTime ON PositionFixes (recorded_at);
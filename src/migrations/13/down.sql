@@ -0,0 +1,2 @@
+DROP INDEX AlertsByTime;
+DROP TABLE Alerts;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE Alerts (
+	alert_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	rule TEXT NOT NULL,
+	severity TEXT NOT NULL CHECK(severity IN ('info', 'warning', 'critical')),
+	message TEXT NOT NULL,
+	raised_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')) CHECK(raised_at > 0)
+);
+
+CREATE INDEX AlertsByTime ON Alerts (raised_at);
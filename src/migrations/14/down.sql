@@ -0,0 +1,2 @@
+DROP INDEX CaptureWindowsByTime;
+DROP TABLE CaptureWindows;
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE CaptureWindows (
+	capture_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	rule TEXT NOT NULL,
+	condition TEXT NOT NULL,
+	triggered_at REAL NOT NULL CHECK(triggered_at > 0),
+	started_at REAL NOT NULL,
+	ended_at REAL NOT NULL CHECK(ended_at >= started_at)
+);
+
+CREATE INDEX CaptureWindowsByTime ON CaptureWindows (started_at, ended_at);
@@ -0,0 +1,5 @@
+-- compressed snapshots cannot be decompressed in SQL, and are unreadable without this migration.
+DELETE FROM VehicleSnapshots WHERE compressed;
+
+DROP INDEX UncompressedSnapshots;
+ALTER TABLE VehicleSnapshots DROP COLUMN compressed;
\ No newline at end of file
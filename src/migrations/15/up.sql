@@ -0,0 +1,5 @@
+-- snapshots are logged compressed with zstd from now on, and those logged before
+-- are recompressed in the background by the server.
+ALTER TABLE VehicleSnapshots ADD compressed BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX UncompressedSnapshots ON VehicleSnapshots (snapshot_id) WHERE NOT compressed;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE BadFrames;
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- frames which could not be deserialized are quarantined here so that they may be
+-- examined later. the server keeps only the most recent of them.
+CREATE TABLE BadFrames (
+	frame_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	received_at REAL NOT NULL,
+	source TEXT,
+	size INTEGER NOT NULL CHECK(size >= 0),
+	frame BLOB NOT NULL,
+	error TEXT NOT NULL
+);
\ No newline at end of file
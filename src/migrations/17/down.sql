@@ -0,0 +1 @@
+DROP TABLE Promotions;
\ No newline at end of file
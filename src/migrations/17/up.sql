@@ -0,0 +1,7 @@
+-- each promotion of a standby to primary begins a new term, which fences off any
+-- former primary with a lower term. the current term is the highest promoted to.
+CREATE TABLE Promotions (
+	term INTEGER NOT NULL PRIMARY KEY CHECK(term > 0),
+	promoted_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')),
+	reason TEXT NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,6 @@
+DROP INDEX RequestLogsByRequestId;
+
+ALTER TABLE AuditLog DROP COLUMN request_id;
+ALTER TABLE RequestLogs DROP COLUMN duration_ms;
+ALTER TABLE RequestLogs DROP COLUMN method;
+ALTER TABLE RequestLogs DROP COLUMN request_id;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- every HTTP request is assigned an ID, returned in the X-Request-Id header,
+-- which ties it to the audit entries and flight messages it caused.
+ALTER TABLE RequestLogs ADD request_id TEXT;
+ALTER TABLE RequestLogs ADD method TEXT;
+ALTER TABLE RequestLogs ADD duration_ms REAL;
+ALTER TABLE AuditLog ADD request_id TEXT;
+
+CREATE INDEX RequestLogsByRequestId ON RequestLogs (request_id);
\ No newline at end of file
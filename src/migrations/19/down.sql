@@ -0,0 +1 @@
+DROP TABLE Profiles;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE Profiles (
+	username TEXT NOT NULL,
+	name TEXT NOT NULL,
+	content TEXT NOT NULL CHECK(json_valid(content)),
+	updated_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')),
+
+	PRIMARY KEY (username, name)
+);
\ No newline at end of file
@@ -0,0 +1,18 @@
+PRAGMA foreign_keys = OFF;
+
+DELETE FROM Sessions WHERE username IN (SELECT username FROM Users WHERE role = 'spectator');
+DELETE FROM Users WHERE role = 'spectator';
+
+CREATE TABLE NewUsers (
+	username TEXT NOT NULL PRIMARY KEY,
+	password_hash TEXT NOT NULL,
+	salt TEXT NOT NULL,
+	role TEXT NOT NULL DEFAULT 'operator' CHECK(role IN ('admin', 'operator')),
+	created_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec'))
+);
+
+INSERT INTO NewUsers SELECT username, password_hash, salt, role, created_at FROM Users;
+DROP TABLE Users;
+ALTER TABLE NewUsers RENAME TO Users;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,16 @@
+-- SQLite cannot alter a CHECK constraint, so the table is rebuilt to allow spectators
+PRAGMA foreign_keys = OFF;
+
+CREATE TABLE NewUsers (
+	username TEXT NOT NULL PRIMARY KEY,
+	password_hash TEXT NOT NULL,
+	salt TEXT NOT NULL,
+	role TEXT NOT NULL DEFAULT 'operator' CHECK(role IN ('admin', 'operator', 'spectator')),
+	created_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec'))
+);
+
+INSERT INTO NewUsers SELECT username, password_hash, salt, role, created_at FROM Users;
+DROP TABLE Users;
+ALTER TABLE NewUsers RENAME TO Users;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,9 @@
+CREATE TABLE Notes (
+	note_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	author TEXT,
+	session_id INTEGER REFERENCES Sessions(session_id) ON DELETE SET NULL,
+	content TEXT NOT NULL CHECK(length(content) > 0),
+	created_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec'))
+);
+
+CREATE INDEX NotesByTime ON Notes(created_at);
@@ -0,0 +1,65 @@
+CREATE TABLE Snippets (
+	name TEXT NOT NULL PRIMARY KEY,
+	description TEXT NOT NULL DEFAULT '',
+	parameters TEXT NOT NULL DEFAULT '[]' CHECK(json_valid(parameters)),
+	template TEXT NOT NULL
+);
+
+INSERT INTO Snippets (name, description, parameters, template) VALUES (
+	'purge',
+	'Opens a vent, then cycles a purge valve to flush a line.',
+	'[
+		{ "name": "vent_valve", "description": "The valve venting the line being purged." },
+		{ "name": "purge_valve", "description": "The valve admitting purge gas." },
+		{ "name": "cycles", "description": "How many times the purge valve is opened.", "default": "3" },
+		{ "name": "purge_seconds", "description": "How long the purge valve is held open.", "default": "5" },
+		{ "name": "dwell_seconds", "description": "How long to wait between purges.", "default": "2" }
+	]',
+	'{{vent_valve}}.open()
+
+for _ in range({{cycles}}):
+    {{purge_valve}}.open()
+    wait_for({{purge_seconds}} * s)
+    {{purge_valve}}.close()
+    wait_for({{dwell_seconds}} * s)
+
+{{vent_valve}}.close()
+'
+), (
+	'leak-check',
+	'Isolates a volume and aborts if its pressure drops too far over a hold.',
+	'[
+		{ "name": "isolation_valve", "description": "The valve isolating the volume being checked." },
+		{ "name": "sensor", "description": "The pressure transducer on the volume." },
+		{ "name": "settle_seconds", "description": "How long to let the pressure settle before the hold.", "default": "5" },
+		{ "name": "hold_seconds", "description": "How long the volume is held.", "default": "60" },
+		{ "name": "max_drop", "description": "The largest pressure drop allowed over the hold.", "default": "5" }
+	]',
+	'{{isolation_valve}}.close()
+wait_for({{settle_seconds}} * s)
+
+start = {{sensor}}
+wait_for({{hold_seconds}} * s)
+
+if start - {{sensor}} > {{max_drop}}:
+    print("leak check failed: {{sensor}} dropped more than {{max_drop}}")
+    abort()
+
+print("leak check passed")
+'
+), (
+	'valve-cycle',
+	'Opens and closes a valve repeatedly, such as to check its actuation.',
+	'[
+		{ "name": "valve", "description": "The valve to cycle." },
+		{ "name": "cycles", "description": "How many times the valve is opened.", "default": "5" },
+		{ "name": "open_seconds", "description": "How long the valve is held open.", "default": "1" },
+		{ "name": "closed_seconds", "description": "How long the valve is held closed.", "default": "1" }
+	]',
+	'for _ in range({{cycles}}):
+    {{valve}}.open()
+    wait_for({{open_seconds}} * s)
+    {{valve}}.close()
+    wait_for({{closed_seconds}} * s)
+'
+);
@@ -0,0 +1,9 @@
+CREATE TABLE Thresholds (
+	channel TEXT NOT NULL PRIMARY KEY,
+	unit TEXT,
+	low_critical REAL,
+	low_warning REAL,
+	high_warning REAL,
+	high_critical REAL,
+	description TEXT
+);
@@ -0,0 +1 @@
+ALTER TABLE Alerts DROP COLUMN causes;
\ No newline at end of file
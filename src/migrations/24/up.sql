@@ -0,0 +1,3 @@
+-- alerts are run through the fault tree in the configuration when raised, and
+-- the probable causes it suggests are kept with them as a JSON array.
+ALTER TABLE Alerts ADD causes TEXT NOT NULL DEFAULT('[]');
\ No newline at end of file
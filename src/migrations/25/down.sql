@@ -0,0 +1,17 @@
+CREATE TABLE AlertsWithoutAnomalies (
+	alert_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	rule TEXT NOT NULL,
+	severity TEXT NOT NULL CHECK(severity IN ('info', 'warning', 'critical')),
+	message TEXT NOT NULL,
+	raised_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')) CHECK(raised_at > 0),
+	causes TEXT NOT NULL DEFAULT('[]')
+);
+
+INSERT INTO AlertsWithoutAnomalies (alert_id, rule, severity, message, raised_at, causes)
+SELECT alert_id, rule, severity, message, raised_at, causes FROM Alerts WHERE severity != 'anomaly';
+
+DROP INDEX AlertsByTime;
+DROP TABLE Alerts;
+ALTER TABLE AlertsWithoutAnomalies RENAME TO Alerts;
+
+CREATE INDEX AlertsByTime ON Alerts (raised_at);
\ No newline at end of file
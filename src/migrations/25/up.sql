@@ -0,0 +1,19 @@
+-- alerts may now have the anomaly severity, raised when a reading departs from
+-- its channel's recent behavior, which needs the table rebuilt to widen its check.
+CREATE TABLE AlertsWithAnomalies (
+	alert_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	rule TEXT NOT NULL,
+	severity TEXT NOT NULL CHECK(severity IN ('info', 'anomaly', 'warning', 'critical')),
+	message TEXT NOT NULL,
+	raised_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')) CHECK(raised_at > 0),
+	causes TEXT NOT NULL DEFAULT('[]')
+);
+
+INSERT INTO AlertsWithAnomalies (alert_id, rule, severity, message, raised_at, causes)
+SELECT alert_id, rule, severity, message, raised_at, causes FROM Alerts;
+
+DROP INDEX AlertsByTime;
+DROP TABLE Alerts;
+ALTER TABLE AlertsWithAnomalies RENAME TO Alerts;
+
+CREATE INDEX AlertsByTime ON Alerts (raised_at);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE ExportPresets;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE ExportPresets (
+	name TEXT NOT NULL PRIMARY KEY,
+	description TEXT NOT NULL DEFAULT '',
+	format TEXT,
+	channels TEXT NOT NULL DEFAULT '[]' CHECK(json_valid(channels)),
+	downsample_hz REAL CHECK(downsample_hz > 0),
+	units TEXT NOT NULL DEFAULT 'native' CHECK(units IN ('native', 'metric', 'si'))
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE AuditLog DROP COLUMN severity;
+ALTER TABLE NodeMappings DROP COLUMN hazard_level;
\ No newline at end of file
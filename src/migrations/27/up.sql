@@ -0,0 +1,5 @@
+-- channels are classified by hazard in servo's own schema, which is never forwarded with mappings
+ALTER TABLE NodeMappings ADD hazard_level TEXT NOT NULL DEFAULT('none') CHECK(hazard_level IN ('none', 'caution', 'hazardous'));
+
+-- audited actions have a severity, so that those touching hazardous channels stand out
+ALTER TABLE AuditLog ADD severity TEXT NOT NULL DEFAULT('info') CHECK(severity IN ('info', 'anomaly', 'warning', 'critical'));
\ No newline at end of file
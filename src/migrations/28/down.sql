@@ -0,0 +1,7 @@
+DROP TRIGGER no_delete_recent_request_logs;
+
+CREATE TRIGGER no_delete_request_logs
+BEFORE DELETE ON RequestLogs
+BEGIN
+	SELECT RAISE(ABORT, 'Deleting request logs is not permitted.');
+END;
@@ -0,0 +1,9 @@
+-- request logs may be pruned by the retention policy, but the most recent are still protected from tampering
+DROP TRIGGER no_delete_request_logs;
+
+CREATE TRIGGER no_delete_recent_request_logs
+BEFORE DELETE ON RequestLogs
+WHEN old.timestamp > unixepoch('now', 'subsec') - 86400
+BEGIN
+	SELECT RAISE(ABORT, 'Deleting request logs from the last day is not permitted.');
+END;
@@ -0,0 +1,2 @@
+DROP INDEX SequenceRunsByTime;
+DROP TABLE SequenceRuns;
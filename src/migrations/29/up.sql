@@ -0,0 +1,10 @@
+-- sequences are recorded exactly as they were sent, since a stored sequence may be edited after it is run
+CREATE TABLE SequenceRuns (
+	run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+	sequence_name TEXT NOT NULL,
+	script TEXT NOT NULL,
+	username TEXT,
+	ran_at REAL NOT NULL DEFAULT (unixepoch('now', 'subsec'))
+);
+
+CREATE INDEX SequenceRunsByTime ON SequenceRuns (ran_at);
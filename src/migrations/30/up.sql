@@ -0,0 +1,9 @@
+-- the last subscription of each forwarding client, so that a client which reconnects may resume it
+CREATE TABLE ForwardingSubscriptions (
+	username TEXT NOT NULL,
+	host TEXT NOT NULL,
+	subscription TEXT NOT NULL,
+	updated_at REAL NOT NULL DEFAULT (unixepoch('now', 'subsec')),
+
+	PRIMARY KEY (username, host)
+);
@@ -0,0 +1,6 @@
+-- hosts added by an admin to those permitted to connect as the flight or ground computer
+CREATE TABLE FlightAllowlist (
+	host TEXT PRIMARY KEY NOT NULL,
+	added_by TEXT,
+	added_at REAL NOT NULL DEFAULT (unixepoch('now', 'subsec'))
+);
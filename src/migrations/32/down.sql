@@ -0,0 +1 @@
+ALTER TABLE Users DROP COLUMN iterations;
\ No newline at end of file
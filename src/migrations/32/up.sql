@@ -0,0 +1,3 @@
+-- passwords are stretched with PBKDF2, and the iteration count is kept with each hash so that it may be raised
+-- users hashed before stretching keep 0 iterations, meaning a single salted SHA-256 digest, until they next log in
+ALTER TABLE Users ADD iterations INTEGER NOT NULL DEFAULT(0);
\ No newline at end of file
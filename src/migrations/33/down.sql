@@ -0,0 +1 @@
+DROP TABLE AdhocSequences;
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- every ad-hoc script run is kept as a new version of its name rather than replacing the last
+CREATE TABLE AdhocSequences (
+	name TEXT NOT NULL,
+	version INTEGER NOT NULL,
+	script TEXT NOT NULL,
+	username TEXT,
+	created_at REAL NOT NULL DEFAULT (unixepoch('now', 'subsec')),
+
+	PRIMARY KEY (name, version)
+);
@@ -0,0 +1,35 @@
+DROP TRIGGER mirror_NodeMappings_insert;
+DROP TRIGGER mirror_NodeMappings_update;
+DROP TRIGGER mirror_NodeMappings_delete;
+DROP TRIGGER mirror_Sequences_insert;
+DROP TRIGGER mirror_Sequences_update;
+DROP TRIGGER mirror_Sequences_delete;
+DROP TRIGGER mirror_SequenceFiles_insert;
+DROP TRIGGER mirror_SequenceFiles_update;
+DROP TRIGGER mirror_SequenceFiles_delete;
+DROP TRIGGER mirror_Snippets_insert;
+DROP TRIGGER mirror_Snippets_update;
+DROP TRIGGER mirror_Snippets_delete;
+DROP TRIGGER mirror_ExportPresets_insert;
+DROP TRIGGER mirror_ExportPresets_update;
+DROP TRIGGER mirror_ExportPresets_delete;
+DROP TRIGGER mirror_Thresholds_insert;
+DROP TRIGGER mirror_Thresholds_update;
+DROP TRIGGER mirror_Thresholds_delete;
+DROP TRIGGER mirror_Triggers_insert;
+DROP TRIGGER mirror_Triggers_update;
+DROP TRIGGER mirror_Triggers_delete;
+DROP TRIGGER mirror_Interlocks_insert;
+DROP TRIGGER mirror_Interlocks_update;
+DROP TRIGGER mirror_Interlocks_delete;
+DROP TRIGGER mirror_Users_insert;
+DROP TRIGGER mirror_Users_update;
+DROP TRIGGER mirror_Users_delete;
+DROP TRIGGER mirror_Profiles_insert;
+DROP TRIGGER mirror_Profiles_update;
+DROP TRIGGER mirror_Profiles_delete;
+DROP TRIGGER mirror_FlightAllowlist_insert;
+DROP TRIGGER mirror_FlightAllowlist_update;
+DROP TRIGGER mirror_FlightAllowlist_delete;
+
+DROP TABLE MirrorVersions;
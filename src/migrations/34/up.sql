@@ -0,0 +1,205 @@
+-- counts the changes to each mirrored table, so that a primary sends its standbys only the tables which changed
+CREATE TABLE MirrorVersions (
+	name TEXT NOT NULL PRIMARY KEY,
+	version INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO MirrorVersions (name) VALUES ('NodeMappings'), ('Sequences'), ('SequenceFiles'), ('Snippets'), ('ExportPresets'), ('Thresholds'), ('Triggers'), ('Interlocks'), ('Users'), ('Profiles'), ('FlightAllowlist');
+
+CREATE TRIGGER mirror_NodeMappings_insert
+AFTER INSERT ON NodeMappings
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'NodeMappings';
+END;
+
+CREATE TRIGGER mirror_NodeMappings_update
+AFTER UPDATE ON NodeMappings
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'NodeMappings';
+END;
+
+CREATE TRIGGER mirror_NodeMappings_delete
+AFTER DELETE ON NodeMappings
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'NodeMappings';
+END;
+
+CREATE TRIGGER mirror_Sequences_insert
+AFTER INSERT ON Sequences
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Sequences';
+END;
+
+CREATE TRIGGER mirror_Sequences_update
+AFTER UPDATE ON Sequences
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Sequences';
+END;
+
+CREATE TRIGGER mirror_Sequences_delete
+AFTER DELETE ON Sequences
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Sequences';
+END;
+
+CREATE TRIGGER mirror_SequenceFiles_insert
+AFTER INSERT ON SequenceFiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'SequenceFiles';
+END;
+
+CREATE TRIGGER mirror_SequenceFiles_update
+AFTER UPDATE ON SequenceFiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'SequenceFiles';
+END;
+
+CREATE TRIGGER mirror_SequenceFiles_delete
+AFTER DELETE ON SequenceFiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'SequenceFiles';
+END;
+
+CREATE TRIGGER mirror_Snippets_insert
+AFTER INSERT ON Snippets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Snippets';
+END;
+
+CREATE TRIGGER mirror_Snippets_update
+AFTER UPDATE ON Snippets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Snippets';
+END;
+
+CREATE TRIGGER mirror_Snippets_delete
+AFTER DELETE ON Snippets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Snippets';
+END;
+
+CREATE TRIGGER mirror_ExportPresets_insert
+AFTER INSERT ON ExportPresets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'ExportPresets';
+END;
+
+CREATE TRIGGER mirror_ExportPresets_update
+AFTER UPDATE ON ExportPresets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'ExportPresets';
+END;
+
+CREATE TRIGGER mirror_ExportPresets_delete
+AFTER DELETE ON ExportPresets
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'ExportPresets';
+END;
+
+CREATE TRIGGER mirror_Thresholds_insert
+AFTER INSERT ON Thresholds
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Thresholds';
+END;
+
+CREATE TRIGGER mirror_Thresholds_update
+AFTER UPDATE ON Thresholds
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Thresholds';
+END;
+
+CREATE TRIGGER mirror_Thresholds_delete
+AFTER DELETE ON Thresholds
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Thresholds';
+END;
+
+CREATE TRIGGER mirror_Triggers_insert
+AFTER INSERT ON Triggers
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Triggers';
+END;
+
+CREATE TRIGGER mirror_Triggers_update
+AFTER UPDATE ON Triggers
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Triggers';
+END;
+
+CREATE TRIGGER mirror_Triggers_delete
+AFTER DELETE ON Triggers
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Triggers';
+END;
+
+CREATE TRIGGER mirror_Interlocks_insert
+AFTER INSERT ON Interlocks
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Interlocks';
+END;
+
+CREATE TRIGGER mirror_Interlocks_update
+AFTER UPDATE ON Interlocks
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Interlocks';
+END;
+
+CREATE TRIGGER mirror_Interlocks_delete
+AFTER DELETE ON Interlocks
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Interlocks';
+END;
+
+CREATE TRIGGER mirror_Users_insert
+AFTER INSERT ON Users
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Users';
+END;
+
+CREATE TRIGGER mirror_Users_update
+AFTER UPDATE ON Users
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Users';
+END;
+
+CREATE TRIGGER mirror_Users_delete
+AFTER DELETE ON Users
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Users';
+END;
+
+CREATE TRIGGER mirror_Profiles_insert
+AFTER INSERT ON Profiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Profiles';
+END;
+
+CREATE TRIGGER mirror_Profiles_update
+AFTER UPDATE ON Profiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Profiles';
+END;
+
+CREATE TRIGGER mirror_Profiles_delete
+AFTER DELETE ON Profiles
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'Profiles';
+END;
+
+CREATE TRIGGER mirror_FlightAllowlist_insert
+AFTER INSERT ON FlightAllowlist
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'FlightAllowlist';
+END;
+
+CREATE TRIGGER mirror_FlightAllowlist_update
+AFTER UPDATE ON FlightAllowlist
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'FlightAllowlist';
+END;
+
+CREATE TRIGGER mirror_FlightAllowlist_delete
+AFTER DELETE ON FlightAllowlist
+BEGIN
+	UPDATE MirrorVersions SET version = version + 1 WHERE name = 'FlightAllowlist';
+END;
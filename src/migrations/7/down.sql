@@ -0,0 +1,2 @@
+DROP TABLE Sessions;
+DROP TABLE Users;
@@ -0,0 +1,18 @@
+CREATE TABLE Users (
+	username TEXT NOT NULL PRIMARY KEY,
+	password_hash TEXT NOT NULL,
+	salt TEXT NOT NULL,
+	role TEXT NOT NULL DEFAULT 'operator' CHECK(role IN ('admin', 'operator')),
+	created_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec'))
+);
+
+CREATE TABLE Sessions (
+	session_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	token_hash TEXT NOT NULL UNIQUE,
+	username TEXT NOT NULL REFERENCES Users(username) ON DELETE CASCADE,
+	origin TEXT NOT NULL,
+	hostname TEXT,
+	created_at REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')),
+	last_active REAL NOT NULL DEFAULT(unixepoch('now', 'subsec')),
+	expires_at REAL NOT NULL CHECK(expires_at > created_at)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE SequenceFiles (
+	sequence_name TEXT NOT NULL,
+	path TEXT NOT NULL,
+	contents TEXT NOT NULL,
+
+	PRIMARY KEY (sequence_name, path)
+);
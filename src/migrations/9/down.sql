@@ -0,0 +1,3 @@
+DROP TABLE AuditLog;
+DROP INDEX SafingSequences;
+ALTER TABLE Sequences DROP COLUMN safing;
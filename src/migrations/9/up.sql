@@ -0,0 +1,12 @@
+ALTER TABLE Sequences ADD safing BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- only one safing sequence may exist for each configuration, including no configuration
+CREATE UNIQUE INDEX SafingSequences ON Sequences (IFNULL(configuration_id, '')) WHERE safing;
+
+CREATE TABLE AuditLog (
+	audit_id INTEGER PRIMARY KEY AUTOINCREMENT,
+	recorded_at REAL NOT NULL DEFAULT (unixepoch('now', 'subsec')),
+	username TEXT,
+	action TEXT NOT NULL,
+	detail TEXT
+);
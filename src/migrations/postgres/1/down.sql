@@ -0,0 +1 @@
+DROP TABLE vehicle_snapshots;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- snapshots streamed from servers in the field are kept here for the whole team to query
+CREATE TABLE vehicle_snapshots (
+	snapshot_id BIGSERIAL PRIMARY KEY,
+	recorded_at DOUBLE PRECISION NOT NULL CHECK(recorded_at > 0),
+	vehicle_state BYTEA NOT NULL
+);
+
+CREATE INDEX vehicle_snapshots_by_time ON vehicle_snapshots (recorded_at);
\ No newline at end of file
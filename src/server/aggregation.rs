@@ -0,0 +1,151 @@
+use common::comm::{Measurement, Unit, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, ptr, sync::{Arc, Weak}};
+
+use super::selector::Selection;
+
+/// The spread of a sensor's readings over a forwarding interval.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Envelope {
+	/// The smallest reading in the interval.
+	pub min: f64,
+
+	/// The largest reading in the interval.
+	pub max: f64,
+
+	/// The number of readings in the interval.
+	pub samples: u32,
+}
+
+/// A vehicle state whose sensor readings are the mean of every reading over a
+/// forwarding interval, with the envelope each mean was taken from.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AggregatedState {
+	/// The latest valve states, and the mean reading of each sensor.
+	#[serde(flatten)]
+	pub state: VehicleState,
+
+	/// The envelope of the readings of each sensor.
+	pub envelopes: BTreeMap<String, Envelope>,
+}
+
+impl AggregatedState {
+	/// Keeps only the selected sensors and valves.
+	pub fn retain(&mut self, channels: &Selection) {
+		channels.retain(&mut self.state);
+		self.envelopes.retain(|name, _| channels.matches(name));
+	}
+}
+
+/// The running sum and envelope of a sensor's readings.
+#[derive(Clone, Copy, Debug)]
+struct Accumulator {
+	sum: f64,
+	unit: Unit,
+	envelope: Envelope,
+}
+
+/// Aggregates every snapshot seen between forwarding ticks, so that a client
+/// sampling at a display rate sees transients which land between its samples.
+#[derive(Clone, Debug, Default)]
+pub struct Aggregator {
+	accumulators: HashMap<String, Accumulator>,
+
+	/// The last snapshot added, so that one seen twice is only counted once. It is
+	/// held weakly so that the aggregator does not force the next update to copy it.
+	last: Weak<VehicleState>,
+}
+
+impl Aggregator {
+	/// Adds the readings of a snapshot to the interval, unless it was the last one added.
+	pub fn add(&mut self, snapshot: &Arc<VehicleState>) {
+		if ptr::eq(self.last.as_ptr(), Arc::as_ptr(snapshot)) {
+			return;
+		}
+
+		for (name, reading) in &snapshot.sensor_readings {
+			if !reading.value.is_finite() {
+				continue;
+			}
+
+			let envelope = Envelope { min: reading.value, max: reading.value, samples: 1 };
+
+			match self.accumulators.get_mut(name) {
+				// readings in different units cannot be averaged, so only those in the latest are
+				Some(accumulator) if accumulator.unit == reading.unit => {
+					accumulator.sum += reading.value;
+					accumulator.envelope.min = accumulator.envelope.min.min(reading.value);
+					accumulator.envelope.max = accumulator.envelope.max.max(reading.value);
+					accumulator.envelope.samples += 1;
+				},
+				_ => {
+					self.accumulators.insert(name.clone(), Accumulator { sum: reading.value, unit: reading.unit, envelope });
+				},
+			}
+		}
+
+		self.last = Arc::downgrade(snapshot);
+	}
+
+	/// Ends the interval with the latest snapshot, returning its aggregate and starting the next.
+	///
+	/// Sensors without a new finite reading in the interval, such as when no
+	/// snapshot arrived since the last tick, keep their latest reading without an envelope.
+	pub fn finish(&mut self, latest: &Arc<VehicleState>) -> AggregatedState {
+		self.add(latest);
+
+		let mut aggregated = AggregatedState { state: (**latest).clone(), envelopes: BTreeMap::new() };
+
+		for (name, accumulator) in self.accumulators.drain() {
+			let mean = accumulator.sum / f64::from(accumulator.envelope.samples);
+
+			aggregated.state.sensor_readings.insert(name.clone(), Measurement { value: mean, unit: accumulator.unit });
+			aggregated.envelopes.insert(name, accumulator.envelope);
+		}
+
+		aggregated
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn snapshot(readings: &[(&str, f64)]) -> Arc<VehicleState> {
+		let mut state = VehicleState::new();
+
+		for &(name, value) in readings {
+			state.sensor_readings.insert(name.to_owned(), Measurement { value, unit: Unit::Psi });
+		}
+
+		Arc::new(state)
+	}
+
+	#[test]
+	fn transients_between_ticks_are_kept_in_the_envelope() {
+		let mut aggregator = Aggregator::default();
+		let spike = snapshot(&[("KBPT", 900.0), ("WTPT", 1.0)]);
+
+		aggregator.add(&snapshot(&[("KBPT", 100.0)]));
+		aggregator.add(&spike);
+		aggregator.add(&spike);
+
+		let aggregated = aggregator.finish(&snapshot(&[("KBPT", 200.0), ("WTPT", 3.0)]));
+
+		assert_eq!(aggregated.state.sensor_readings["KBPT"].value, 400.0);
+		assert_eq!(aggregated.envelopes["KBPT"], Envelope { min: 100.0, max: 900.0, samples: 3 });
+		assert_eq!(aggregated.envelopes["WTPT"], Envelope { min: 1.0, max: 3.0, samples: 2 });
+
+		// the next interval starts from nothing
+		let latest = snapshot(&[("KBPT", 50.0)]);
+		aggregator.add(&latest);
+
+		let aggregated = aggregator.finish(&latest);
+		assert_eq!(aggregated.envelopes["KBPT"], Envelope { min: 50.0, max: 50.0, samples: 1 });
+		assert!(!aggregated.envelopes.contains_key("WTPT"));
+
+		let aggregated = aggregator.finish(&latest);
+		assert!(aggregated.envelopes.is_empty());
+		assert_eq!(aggregated.state.sensor_readings["KBPT"].value, 50.0);
+	}
+}
@@ -0,0 +1,432 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+#[cfg(feature = "server")]
+use {
+	common::comm::VehicleState,
+	jeflog::{fail, warn},
+	rusqlite::OptionalExtension,
+	std::{collections::BTreeMap, future::Future, path::Path, sync::Arc},
+	tokio::time::MissedTickBehavior,
+	super::{clock, config::{AlertRuleConfig, NotificationConfig}, fault, interlock::{self, Condition, SystemState}, notification, preflight, Shared},
+};
+
+/// How often alert rules are evaluated against the system state.
+#[cfg(feature = "server")]
+const EVALUATION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often servo checks for system events, such as the disk running low.
+#[cfg(feature = "server")]
+const SYSTEM_EVENT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to wait before raising the same system event again while it persists.
+#[cfg(feature = "server")]
+const SYSTEM_EVENT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// How severe an alert is.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+	/// Worth knowing about, but requires no action.
+	Info,
+
+	/// A channel behaving unlike it has recently, which may or may not be a problem.
+	Anomaly,
+
+	/// Requires attention soon.
+	#[default]
+	Warning,
+
+	/// Requires attention immediately.
+	Critical,
+}
+
+impl Severity {
+	/// The name of the severity, as stored in the `Alerts` table.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Info => "info",
+			Self::Anomaly => "anomaly",
+			Self::Warning => "warning",
+			Self::Critical => "critical",
+		}
+	}
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl FromStr for Severity {
+	type Err = String;
+
+	fn from_str(severity: &str) -> Result<Self, Self::Err> {
+		match severity {
+			"info" => Ok(Self::Info),
+			"anomaly" => Ok(Self::Anomaly),
+			"warning" => Ok(Self::Warning),
+			"critical" => Ok(Self::Critical),
+			other => Err(format!("unrecognized severity '{other}'")),
+		}
+	}
+}
+
+/// An alert raised by a rule, as recorded and passed to notification actions.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alert {
+	/// The name of the rule which raised the alert.
+	pub rule: String,
+
+	/// How severe the alert is.
+	pub severity: Severity,
+
+	/// What caused the alert.
+	pub message: String,
+
+	/// When the alert was raised, as a Unix timestamp.
+	pub raised_at: f64,
+
+	/// The probable causes suggested by the fault tree, most specific first.
+	#[serde(default)]
+	pub causes: Vec<String>,
+}
+
+impl Alert {
+	/// Creates an alert raised now.
+	pub fn new(rule: &str, severity: Severity, message: String) -> Self {
+		let raised_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0.0, |duration| duration.as_secs_f64());
+
+		Alert {
+			rule: rule.to_owned(),
+			severity,
+			message,
+			raised_at,
+			causes: Vec::new(),
+		}
+	}
+}
+
+impl fmt::Display for Alert {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} alert, {}: {}", self.severity, self.rule, self.message)?;
+
+		if !self.causes.is_empty() {
+			write!(f, " (probable cause: {})", self.causes.join("; "))?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Tracks whether a rule's condition has held long enough to raise an alert.
+///
+/// An alert is raised once each time the condition begins to hold, after it
+/// has held continuously for the rule's duration, unless the previous alert
+/// was raised within the cooldown.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuleTracker {
+	holding_since: Option<Instant>,
+	raised: bool,
+	last_raised: Option<Instant>,
+}
+
+impl RuleTracker {
+	/// Records whether the condition holds at an instant, returning whether an alert should be raised.
+	pub fn update(&mut self, holds: bool, now: Instant, hold_for: Duration, cooldown: Duration) -> bool {
+		if !holds {
+			self.holding_since = None;
+			self.raised = false;
+			return false;
+		}
+
+		let holding_since = *self.holding_since.get_or_insert(now);
+
+		if self.raised || now.duration_since(holding_since) < hold_for {
+			return false;
+		}
+
+		if self.last_raised.is_some_and(|last_raised| now.duration_since(last_raised) < cooldown) {
+			return false;
+		}
+
+		self.raised = true;
+		self.last_raised = Some(now);
+		true
+	}
+}
+
+/// Records an alert in the `Alerts` table.
+pub fn record(connection: &Connection, alert: &Alert) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO Alerts (rule, severity, message, raised_at, causes) VALUES (?1, ?2, ?3, ?4, ?5)",
+		params![
+			alert.rule,
+			alert.severity.as_str(),
+			alert.message,
+			alert.raised_at,
+			serde_json::to_string(&alert.causes).unwrap_or_else(|_| "[]".to_owned()),
+		],
+	)?;
+
+	Ok(())
+}
+
+/// Records an alert, logs it, and notifies operators through the named actions.
+///
+/// The alert is first run through the fault tree, attaching the probable
+/// causes which fit the current system state.
+#[cfg(feature = "server")]
+pub async fn raise(shared: &Shared, mut alert: Alert, actions: &[String]) {
+	let causes = &shared.config.current().notifications.causes;
+
+	if !causes.is_empty() {
+		let (vehicle, flight_link_healthy, active_configuration) = current_state(shared).await;
+
+		let state = SystemState {
+			vehicle: &vehicle,
+			flight_link_healthy,
+			active_configuration: active_configuration.as_deref(),
+		};
+
+		alert.causes = fault::diagnose(causes, &alert.rule, &state);
+	}
+
+	match alert.severity {
+		Severity::Critical => fail!("{alert}"),
+		_ => warn!("{alert}"),
+	}
+
+	if let Err(error) = record(&*shared.database.connection.lock().await, &alert) {
+		warn!("Failed to record alert in database: {error}");
+	}
+
+	notification::notify(&shared.config.current().notifications, actions, &alert);
+}
+
+/// Raises an alert about a system event, notifying operators through the system event actions.
+///
+/// Events come from servo itself, or from outside scripts such as scheduled
+/// checkout tests through `POST /operator/alert`.
+#[cfg(feature = "server")]
+pub async fn raise_event(shared: &Shared, event: &str, severity: Severity, message: String) {
+	let config = shared.config.current();
+	raise(shared, Alert::new(event, severity, message), &config.notifications.events.actions).await;
+}
+
+/// Continuously checks for system events which operators off of the console
+/// should hear about, such as the flight computer disconnecting or the disk
+/// holding the database running low.
+#[cfg(feature = "server")]
+pub fn monitor_system(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let database_path = shared.database.connection
+			.lock()
+			.await
+			.path()
+			.filter(|path| !path.is_empty())
+			.map(|path| Path::new(path).to_path_buf());
+
+		let mut flight_connected_before = false;
+		let mut flight_tracker = RuleTracker::default();
+		let mut disk_tracker = RuleTracker::default();
+		let mut clock_trackers = BTreeMap::<String, RuleTracker>::new();
+
+		let mut interval = tokio::time::interval(SYSTEM_EVENT_INTERVAL);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			interval.tick().await;
+
+			let now = Instant::now();
+			let config = shared.config.current();
+			let config = &config.notifications.events;
+
+			if let Some(seconds) = config.flight_disconnected_seconds {
+				let healthy = interlock::flight_link_healthy(&shared).await;
+				flight_connected_before |= healthy;
+
+				// a flight computer which has never connected has not been lost
+				let disconnected = flight_connected_before && !healthy;
+
+				if flight_tracker.update(disconnected, now, Duration::from_secs_f64(seconds.max(0.0)), Duration::ZERO) {
+					let message = format!("flight computer has been disconnected for over {seconds} s");
+					raise_event(&shared, "flight computer disconnected", Severity::Critical, message).await;
+				}
+			}
+
+			if let (Some(minimum), Some(database_path)) = (config.disk_free_minimum_megabytes, &database_path) {
+				let available = preflight::free_megabytes(database_path);
+				let low = available.is_some_and(|available| available < minimum);
+
+				if disk_tracker.update(low, now, Duration::ZERO, SYSTEM_EVENT_COOLDOWN) {
+					let message = format!(
+						"only {} MB free on the disk holding {}",
+						available.unwrap_or(0),
+						database_path.display(),
+					);
+
+					raise_event(&shared, "database disk low", Severity::Warning, message).await;
+				}
+			}
+
+			let skews = shared.metrics.clocks.lock().await.current(clock::now());
+			clock_trackers.retain(|participant, _| skews.contains_key(participant));
+
+			for (participant, skew) in skews {
+				let tracker = clock_trackers.entry(participant.clone()).or_default();
+
+				if tracker.update(skew.drifted(config.clock_skew_seconds), now, Duration::ZERO, SYSTEM_EVENT_COOLDOWN) {
+					let message = format!("clock of {participant} is {}", skew.describe());
+					raise_event(&shared, "clock skew", Severity::Warning, message).await;
+				}
+			}
+		}
+	}
+}
+
+/// Continuously evaluates the configured alert rules, raising an alert each time one fires.
+///
+/// Rules are rebuilt whenever the configuration is reloaded. Rules whose
+/// conditions cannot be parsed are reported once and skipped.
+#[cfg(feature = "server")]
+pub fn monitor(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut config = shared.config.current();
+		let mut rules = build_rules(&config.notifications, Vec::new());
+
+		let mut interval = tokio::time::interval(EVALUATION_INTERVAL);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			interval.tick().await;
+
+			let current = shared.config.current();
+
+			if !Arc::ptr_eq(&current, &config) {
+				config = current;
+				rules = build_rules(&config.notifications, rules);
+			}
+
+			if rules.is_empty() {
+				continue;
+			}
+
+			let (vehicle, flight_link_healthy, active_configuration) = current_state(&shared).await;
+
+			let state = SystemState {
+				vehicle: &vehicle,
+				flight_link_healthy,
+				active_configuration: active_configuration.as_deref(),
+			};
+
+			let now = Instant::now();
+			let mut raised = Vec::new();
+
+			for (rule, condition, tracker) in &mut rules {
+				let holds = condition.check(&state).is_ok();
+				let hold_for = Duration::from_secs_f64(rule.for_seconds.max(0.0));
+				let cooldown = Duration::from_secs_f64(rule.cooldown_seconds.max(0.0));
+
+				if tracker.update(holds, now, hold_for, cooldown) {
+					let message = if rule.for_seconds > 0.0 {
+						format!("{condition} for {} s", rule.for_seconds)
+					} else {
+						condition.to_string()
+					};
+
+					raised.push((Alert::new(&rule.name, rule.severity, message), rule.actions.clone()));
+				}
+			}
+
+			for (alert, actions) in raised {
+				raise(&shared, alert, &actions).await;
+			}
+		}
+	}
+}
+
+/// The vehicle state, the health of the flight link, and the active
+/// configuration, from which a [`SystemState`] is borrowed.
+#[cfg(feature = "server")]
+async fn current_state(shared: &Shared) -> (Arc<VehicleState>, bool, Option<String>) {
+	let active_configuration = shared.database.connection
+		.lock()
+		.await
+		.query_row("SELECT configuration_id FROM NodeMappings WHERE active = TRUE LIMIT 1", [], |row| row.get::<_, String>(0))
+		.optional()
+		.unwrap_or(None);
+
+	let vehicle = shared.vehicle.0.lock().await.clone();
+	(vehicle, interlock::flight_link_healthy(shared).await, active_configuration)
+}
+
+/// Parses the configured alert rules, keeping the tracker of any previous rule
+/// with the same name and condition so that a reload does not raise it again.
+#[cfg(feature = "server")]
+fn build_rules(
+	config: &NotificationConfig,
+	mut previous: Vec<(AlertRuleConfig, Condition, RuleTracker)>,
+) -> Vec<(AlertRuleConfig, Condition, RuleTracker)> {
+	let mut rules = Vec::new();
+
+	for rule in &config.rules {
+		for action in &rule.actions {
+			if !config.actions.contains_key(action) {
+				fail!("Alert rule '{}' refers to undefined notification action '{action}'.", rule.name);
+			}
+		}
+
+		let condition = match rule.condition.parse::<Condition>() {
+			Ok(condition) => condition,
+			Err(error) => {
+				fail!("Alert rule '{}' has an invalid condition: {error}", rule.name);
+				continue;
+			},
+		};
+
+		let tracker = previous
+			.iter()
+			.position(|(previous, _, _)| previous.name == rule.name && previous.condition == rule.condition)
+			.map(|index| previous.swap_remove(index).2)
+			.unwrap_or_default();
+
+		rules.push((rule.clone(), condition, tracker));
+	}
+
+	rules
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rules_raise_once_per_episode() {
+		let start = Instant::now();
+		let at = |seconds: u64| start + Duration::from_secs(seconds);
+		let hold_for = Duration::from_secs(2);
+		let cooldown = Duration::from_secs(10);
+
+		let mut tracker = RuleTracker::default();
+
+		assert!(!tracker.update(true, at(0), hold_for, cooldown));
+		assert!(!tracker.update(true, at(1), hold_for, cooldown));
+		assert!(tracker.update(true, at(2), hold_for, cooldown));
+		assert!(!tracker.update(true, at(3), hold_for, cooldown));
+
+		// clearing and holding again within the cooldown waits out the cooldown
+		assert!(!tracker.update(false, at(4), hold_for, cooldown));
+		assert!(!tracker.update(true, at(5), hold_for, cooldown));
+		assert!(!tracker.update(true, at(8), hold_for, cooldown));
+		assert!(tracker.update(true, at(12), hold_for, cooldown));
+	}
+}
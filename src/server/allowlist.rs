@@ -0,0 +1,147 @@
+use rand::RngCore;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{net::IpAddr, time::Duration};
+use subtle::ConstantTimeEq;
+use tokio::{io::{self, AsyncReadExt, AsyncWriteExt}, net::{self, TcpStream}};
+
+/// The number of random bytes in the challenge of the pre-shared-key handshake.
+pub const CHALLENGE_SIZE: usize = 32;
+
+/// How long a connecting computer has to answer the challenge.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A host added to the allowlist through the API, as stored in the `FlightAllowlist` table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AllowedHost {
+	/// The IP address or hostname permitted to connect.
+	pub host: String,
+
+	/// The admin who added the host, if known.
+	pub added_by: Option<String>,
+
+	/// When the host was added, as a Unix timestamp.
+	pub added_at: f64,
+}
+
+/// Lists the hosts added to the allowlist through the API, in the order they were added.
+pub fn list(connection: &Connection) -> rusqlite::Result<Vec<AllowedHost>> {
+	connection
+		.prepare("SELECT host, added_by, added_at FROM FlightAllowlist ORDER BY added_at, host")?
+		.query_map([], |row| {
+			Ok(AllowedHost {
+				host: row.get(0)?,
+				added_by: row.get(1)?,
+				added_at: row.get(2)?,
+			})
+		})?
+		.collect()
+}
+
+/// Adds a host to the allowlist, returning whether it was not already on it.
+pub fn add(connection: &Connection, host: &str, added_by: Option<&str>) -> rusqlite::Result<bool> {
+	let inserted = connection.execute(
+		"INSERT OR IGNORE INTO FlightAllowlist (host, added_by) VALUES (?1, ?2)",
+		params![host, added_by],
+	)?;
+
+	Ok(inserted > 0)
+}
+
+/// Removes a host from the allowlist, returning whether it was on it.
+pub fn remove(connection: &Connection, host: &str) -> rusqlite::Result<bool> {
+	Ok(connection.execute("DELETE FROM FlightAllowlist WHERE host = ?1", [host])? > 0)
+}
+
+/// Whether a connection from an address is permitted by a list of IP addresses
+/// and hostnames, resolving each hostname as it is checked so that a computer
+/// which changes address is still recognized. An empty list permits anything.
+pub async fn permits(hosts: &[String], address: IpAddr) -> bool {
+	if hosts.is_empty() {
+		return true;
+	}
+
+	let address = address.to_canonical();
+
+	for host in hosts {
+		if let Ok(allowed) = host.parse::<IpAddr>() {
+			if allowed.to_canonical() == address {
+				return true;
+			}
+
+			continue;
+		}
+
+		if let Ok(mut resolved) = net::lookup_host((host.as_str(), 0)).await {
+			if resolved.any(|resolved| resolved.ip().to_canonical() == address) {
+				return true;
+			}
+		}
+	}
+
+	false
+}
+
+/// The answer to a challenge expected of a computer holding the pre-shared key,
+/// which is the SHA-256 digest of the key followed by the challenge.
+pub fn respond(key: &str, challenge: &[u8]) -> [u8; 32] {
+	let mut hasher = Sha256::new();
+	hasher.update(key.as_bytes());
+	hasher.update(challenge);
+	hasher.finalize().into()
+}
+
+/// Challenges a connecting computer to prove it holds the pre-shared key,
+/// returning whether it answered correctly and in time.
+///
+/// The key itself is never sent, so it cannot be read off of the network.
+pub async fn authenticate(stream: &mut TcpStream, key: &str) -> io::Result<bool> {
+	let mut challenge = [0; CHALLENGE_SIZE];
+	rand::thread_rng().fill_bytes(&mut challenge);
+	stream.write_all(&challenge).await?;
+
+	let mut answer = [0; 32];
+
+	match tokio::time::timeout(HANDSHAKE_TIMEOUT, stream.read_exact(&mut answer)).await {
+		Ok(result) => result?,
+		Err(_) => return Ok(false),
+	};
+
+	// compared in constant time, so that timing reveals nothing.
+	Ok(respond(key, &challenge).ct_eq(&answer).into())
+}
+
+#[cfg(test)]
+mod tests {
+	#[cfg(feature = "server")]
+	use crate::server::Database;
+	use super::*;
+
+	#[tokio::test]
+	async fn only_listed_hosts_are_permitted() {
+		let address = "192.168.1.10".parse().unwrap();
+		let mapped = "::ffff:192.168.1.10".parse().unwrap();
+
+		assert!(permits(&[], address).await);
+		assert!(permits(&["192.168.1.10".to_owned()], mapped).await);
+		assert!(!permits(&["192.168.1.11".to_owned()], address).await);
+		assert!(permits(&["localhost".to_owned()], "127.0.0.1".parse().unwrap()).await);
+
+		assert_ne!(respond("key", &[0; CHALLENGE_SIZE]), respond("other key", &[0; CHALLENGE_SIZE]));
+	}
+
+	#[test]
+	#[cfg(feature = "server")]
+	fn hosts_are_added_and_removed() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+		assert!(add(&connection, "flight.local", Some("jeff")).unwrap());
+		assert!(!add(&connection, "flight.local", None).unwrap());
+		assert_eq!(list(&connection).unwrap()[0].added_by.as_deref(), Some("jeff"));
+		assert!(remove(&connection, "flight.local").unwrap());
+		assert!(list(&connection).unwrap().is_empty());
+	}
+}
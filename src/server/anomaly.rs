@@ -0,0 +1,164 @@
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::{Duration, Instant}};
+
+use super::{alert::{self, Alert, RuleTracker, Severity}, config::AnomalyConfig, Shared};
+
+/// How often the configuration is checked while anomaly detection is disabled.
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The name of the rule anomaly alerts are raised under, which fault tree nodes may refer to.
+pub const ANOMALY_RULE: &str = "anomaly";
+
+/// The recent behavior of a channel, as an exponentially weighted moving
+/// average and variance whose weight halves over the configured half-life.
+#[derive(Clone, Copy, Debug)]
+pub struct Band {
+	mean: f64,
+	variance: f64,
+	first_sample: Instant,
+	last_sample: Instant,
+}
+
+impl Band {
+	/// Starts a band at a channel's first reading.
+	pub fn new(value: f64, now: Instant) -> Self {
+		Band {
+			mean: value,
+			variance: 0.0,
+			first_sample: now,
+			last_sample: now,
+		}
+	}
+
+	/// Folds a reading into the band, returning how many standard deviations it
+	/// lies from the band as it was before, or `None` while the band is warming up.
+	///
+	/// The deviation is measured against at least `min_deviation`, so that a
+	/// channel which has held perfectly still is not flagged for the slightest change.
+	pub fn update(&mut self, value: f64, now: Instant, config: &AnomalyConfig) -> Option<f64> {
+		let elapsed = now.saturating_duration_since(self.last_sample).as_secs_f64();
+		let warm = now.saturating_duration_since(self.first_sample).as_secs_f64() >= config.warmup_seconds;
+
+		let deviation = self.variance.sqrt().max(config.min_deviation).max(f64::EPSILON);
+		let score = (value - self.mean).abs() / deviation;
+
+		let weight = 1.0 - (-elapsed * std::f64::consts::LN_2 / config.half_life_seconds.max(f64::EPSILON)).exp();
+		let difference = value - self.mean;
+		let increment = weight * difference;
+
+		self.mean += increment;
+		self.variance = (1.0 - weight) * (self.variance + difference * increment);
+		self.last_sample = now;
+
+		warm.then_some(score)
+	}
+
+	/// The average of the channel's recent readings.
+	pub fn mean(&self) -> f64 {
+		self.mean
+	}
+}
+
+/// Continuously folds vehicle state into a band for each channel, raising an
+/// anomaly alert when a reading departs far from its channel's recent behavior.
+///
+/// Unlike alert rules and redlines, no limits are configured per channel, so
+/// failures which were never anticipated are still noticed. Each channel
+/// alerts once per departure, and not again within the cooldown.
+pub fn monitor(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut bands = BTreeMap::<String, Band>::new();
+		let mut trackers = BTreeMap::<String, RuleTracker>::new();
+
+		loop {
+			let config = shared.config.current();
+			let config = &config.notifications.anomalies;
+
+			if !config.enabled {
+				bands.clear();
+				trackers.clear();
+
+				tokio::time::sleep(DISABLED_POLL_INTERVAL).await;
+				continue;
+			}
+
+			if tokio::time::timeout(DISABLED_POLL_INTERVAL, shared.vehicle.1.notified()).await.is_err() {
+				continue;
+			}
+
+			let vehicle = Arc::clone(&*shared.vehicle.0.lock().await);
+			let now = Instant::now();
+			let cooldown = Duration::from_secs_f64(config.cooldown_seconds.max(0.0));
+			let mut raised = Vec::new();
+
+			for (channel, measurement) in &vehicle.sensor_readings {
+				if !config.channels.is_empty() && !config.channels.contains(channel) {
+					continue;
+				}
+
+				if !measurement.value.is_finite() {
+					continue;
+				}
+
+				let Some(band) = bands.get_mut(channel) else {
+					bands.insert(channel.clone(), Band::new(measurement.value, now));
+					continue;
+				};
+
+				let mean = band.mean();
+				let score = band.update(measurement.value, now, config);
+				let anomalous = score.is_some_and(|score| score > config.threshold_sigma);
+
+				if trackers.entry(channel.clone()).or_default().update(anomalous, now, Duration::ZERO, cooldown) {
+					let message = format!(
+						"{channel} read {:.3} {}, {:.1}σ from its recent average of {mean:.3}",
+						measurement.value,
+						measurement.unit,
+						score.unwrap_or_default(),
+					);
+
+					raised.push(Alert::new(ANOMALY_RULE, Severity::Anomaly, message));
+				}
+			}
+
+			for alert in raised {
+				alert::raise(&shared, alert, &config.actions).await;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn departures_from_recent_behavior_stand_out() {
+		let config = AnomalyConfig {
+			enabled: true,
+			warmup_seconds: 5.0,
+			min_deviation: 0.1,
+			..AnomalyConfig::default()
+		};
+
+		let start = Instant::now();
+		let at = |tenths: u64| start + Duration::from_millis(tenths * 100);
+
+		let mut band = Band::new(100.0, at(0));
+
+		// a noisy but steady channel is never flagged, and nothing is flagged while warming up
+		for tenth in 1..200 {
+			let value = 100.0 + if tenth % 2 == 0 { 1.0 } else { -1.0 };
+			let score = band.update(value, at(tenth), &config);
+
+			assert_eq!(score.is_some(), tenth >= 50);
+			assert!(score.unwrap_or_default() < config.threshold_sigma);
+		}
+
+		assert!((band.mean() - 100.0).abs() < 0.5);
+
+		let score = band.update(150.0, at(200), &config).unwrap();
+		assert!(score > config.threshold_sigma, "score of {score}");
+	}
+}
@@ -0,0 +1,434 @@
+use common::comm::{NodeMapping, Unit};
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{
+	alert::Alert,
+	clock::ClockSkew,
+	hazard::HazardLevel,
+	import::ImportOptions,
+	metrics::{LatencyReport, TelemetryMetrics},
+	note::Note,
+	report::TelemetryGap,
+	statistics::Statistics,
+};
+
+/// Request struct for logging in and creating a new session.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoginRequest {
+	/// The name of the user logging in.
+	pub username: String,
+
+	/// The plaintext password of the user logging in.
+	pub password: String,
+
+	/// The hostname of the machine logging in, as reported by the client.
+	pub hostname: Option<String>,
+}
+
+/// Response struct containing a newly created session token.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LoginResponse {
+	/// The bearer token to be supplied in the `Authorization` header of future requests.
+	pub token: String,
+
+	/// The role of the user who logged in.
+	pub role: String,
+
+	/// The Unix timestamp at which the session expires.
+	pub expires_at: f64,
+}
+
+/// Request struct for backfilling a time-series database.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackfillRequest {
+	/// The earliest time of a snapshot to push, as a Unix timestamp.
+	pub from: Option<f64>,
+
+	/// The latest time of a snapshot to push, as a Unix timestamp.
+	pub to: Option<f64>,
+}
+
+/// Response struct describing how much was pushed by a backfill.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BackfillResponse {
+	/// The number of snapshots pushed.
+	pub snapshots: usize,
+
+	/// The number of lines written, one for each reading of each snapshot.
+	pub lines: usize,
+}
+
+/// Response struct containing statistics of a sensor channel over a time window.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatsResponse {
+	/// The sensor channel the statistics are of.
+	pub channel: String,
+
+	/// The unit of the latest reading in the window.
+	pub unit: Unit,
+
+	/// The statistics of the readings in the window.
+	#[serde(flatten)]
+	pub statistics: Statistics,
+}
+
+/// Response struct describing the flight computer software and how it compares to what servo expects.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FlightInfoResponse {
+	/// Whether a flight computer is currently connected.
+	pub connected: bool,
+
+	/// The number of seconds the current flight computer has been connected.
+	pub connected_seconds: Option<f64>,
+
+	/// The version of servo itself.
+	pub servo_version: String,
+
+	/// The flight software version servo expects, if one is configured.
+	pub expected_version: Option<String>,
+
+	/// The latest information reported by the flight computer, if it has reported any.
+	pub info: Option<FlightInfo>,
+
+	/// Whether the reported flight software version differs from the expected version.
+	pub version_mismatch: bool,
+}
+
+/// Software information reported by the flight computer about itself in
+/// answer to a query over the control link.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FlightInfo {
+	/// The version of the flight software.
+	pub version: String,
+
+	/// The hash of the commit the flight software was built from.
+	pub build_hash: String,
+
+	/// The number of seconds the flight software had been running when it reported.
+	pub uptime_seconds: f64,
+
+	/// The time on the flight computer's clock when it reported, as a Unix
+	/// timestamp, from which the skew of its clock is tracked.
+	#[serde(default)]
+	pub reported_at: Option<f64>,
+
+	/// The values of the runtime parameters of the flight software which were queried.
+	#[serde(default)]
+	pub parameters: BTreeMap<String, serde_json::Value>,
+}
+
+/// Request struct for importing a file of vehicle data.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportRequest {
+	/// The name of the file, from which its format is told if not given.
+	#[serde(default)]
+	pub file_name: Option<String>,
+
+	/// The format of the file, which is either `csv` or `hdf5`.
+	#[serde(default)]
+	pub format: Option<String>,
+
+	/// The Base64-encoded content of the file.
+	pub content: String,
+
+	/// How the channels of the file map onto those of the vehicle.
+	#[serde(flatten)]
+	pub options: ImportOptions,
+}
+
+/// Response struct describing what an import added to the database.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ImportResponse {
+	/// The number of snapshots logged from the file.
+	pub snapshots: usize,
+
+	/// The number of snapshots in the file which were already logged, and so skipped.
+	pub skipped: usize,
+
+	/// The number of notes entered from the file.
+	pub notes: usize,
+
+	/// Every channel read from the file, by the name it was logged under.
+	pub channels: Vec<String>,
+
+	/// The time of the earliest snapshot in the file, as a Unix timestamp.
+	pub from: Option<f64>,
+
+	/// The time of the latest snapshot in the file, as a Unix timestamp.
+	pub to: Option<f64>,
+}
+
+/// Request struct for setting a mapping.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetMappingsRequest {
+	/// An ID uniquely identifying the configuration being set or modified
+	pub configuration_id: String,
+
+	/// Array of all mappings in no specific order
+	pub mappings: Vec<NodeMapping>,
+
+	/// The hazard levels of channels to set, keyed by text ID, which are kept by
+	/// servo rather than forwarded to the flight computer with the mappings.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub hazard_levels: BTreeMap<String, HazardLevel>,
+}
+
+/// Request/response struct for getting and setting the active configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ActiveConfiguration {
+	/// The ID of the active configuration.
+	pub configuration_id: String
+}
+
+/// Request struct for entering a note.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NoteRequest {
+	/// The text of the note.
+	pub content: String,
+
+	/// Who is entering the note, used only when the request has no session,
+	/// such as a note entered with `servo note`.
+	pub author: Option<String>,
+}
+
+/// Used in sequences response struct to attach the configuration ID.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SequenceWithConfiguration {
+	/// The name of the sequence.
+	pub name: String,
+
+	/// The Python sequence script.
+	pub script: String,
+
+	/// The ID of the configuration associated with the sequence.
+	pub configuration_id: Option<String>,
+
+	/// Whether this is the safing sequence of its configuration.
+	pub safing: bool,
+}
+
+/// Response struct for getting the sequences stored in the database.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RetrieveSequenceResponse {
+	/// The collection of all sequences present on the control server.
+	pub sequences: Vec<SequenceWithConfiguration>
+}
+
+/// Response struct identifying the version an ad-hoc script was stored as once it was run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunAdhocSequenceResponse {
+	/// The name the script was run and stored under, including its ad-hoc prefix.
+	pub name: String,
+
+	/// The version of the name the script was stored as, counting up from 1.
+	pub version: i64,
+}
+
+/// Response struct describing the role of the server in a primary and standby pair.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StandbyStatus {
+	/// The current role of the server.
+	pub role: Role,
+
+	/// The number of promotions the server knows of.
+	pub term: u64,
+
+	/// The base URL of the primary the server mirrors, if it was started as a standby.
+	pub primary: Option<String>,
+}
+
+/// The part a server plays in a primary and standby pair.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+	/// Accepting the flight computer and operator commands.
+	Primary,
+
+	/// Mirroring a primary, ready to be promoted should it die.
+	Standby,
+
+	/// A former primary taken over by a promoted standby, which refuses the
+	/// flight computer and operator commands until restarted as a standby.
+	Fenced,
+}
+
+/// Response struct containing the current server metrics.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MetricsResponse {
+	/// Statistics on the vehicle state frames received from the flight computer.
+	pub telemetry: TelemetryMetrics,
+
+	/// The fraction of sequenced vehicle state frames which were lost.
+	pub telemetry_loss_ratio: f64,
+
+	/// Statistics of the recent latencies through servo, in milliseconds.
+	#[serde(default)]
+	pub latency: LatencyReport,
+}
+
+/// Response struct describing how far the clocks of other participants have drifted from servo's.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClocksResponse {
+	/// The current time by servo's clock, as a Unix timestamp.
+	pub servo_time: f64,
+
+	/// The number of seconds a clock may drift before it is flagged, if drift is flagged at all.
+	pub threshold_seconds: Option<f64>,
+
+	/// The skew of each participant which has recently reported its clock, keyed by name.
+	pub participants: BTreeMap<String, ClockSkew>,
+
+	/// The names of the participants whose clocks have drifted beyond the threshold.
+	pub drifted: Vec<String>,
+}
+
+/// Response struct describing how the redlines changed when they were replaced.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ThresholdChanges {
+	/// One line for each channel whose redlines were added, removed, or changed.
+	pub changes: Vec<String>,
+}
+
+/// Where a snapshot falls in the order snapshots are read in: by the time it
+/// was logged, then by the order it was stored in, so that snapshots logged at
+/// the same time are still told apart when paging.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotKey {
+	/// When the snapshot was logged, as a Unix timestamp.
+	pub recorded_at: f64,
+
+	/// The ID the snapshot was stored under, which is unique within its backend.
+	pub id: i64,
+}
+
+impl SnapshotKey {
+	/// The key after every snapshot logged at or before the given time.
+	pub fn after_time(recorded_at: f64) -> Self {
+		SnapshotKey { recorded_at, id: i64::MAX }
+	}
+}
+
+/// A value of a column of a mirrored table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Field {
+	/// An SQL `NULL`.
+	Null,
+
+	/// An integer, which includes booleans.
+	Integer(i64),
+
+	/// A floating-point number.
+	Real(f64),
+
+	/// A string.
+	Text(String),
+
+	/// Arbitrary bytes.
+	Blob(Vec<u8>),
+}
+
+impl From<ValueRef<'_>> for Field {
+	fn from(value: ValueRef) -> Self {
+		match value {
+			ValueRef::Null => Field::Null,
+			ValueRef::Integer(value) => Field::Integer(value),
+			ValueRef::Real(value) => Field::Real(value),
+			ValueRef::Text(value) => Field::Text(String::from_utf8_lossy(value).into_owned()),
+			ValueRef::Blob(value) => Field::Blob(value.to_vec()),
+		}
+	}
+}
+
+impl ToSql for Field {
+	fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+		Ok(match self {
+			Field::Null => ToSqlOutput::Borrowed(ValueRef::Null),
+			Field::Integer(value) => ToSqlOutput::Borrowed(ValueRef::Integer(*value)),
+			Field::Real(value) => ToSqlOutput::Borrowed(ValueRef::Real(*value)),
+			Field::Text(value) => ToSqlOutput::Borrowed(ValueRef::Text(value.as_bytes())),
+			Field::Blob(value) => ToSqlOutput::Borrowed(ValueRef::Blob(value)),
+		})
+	}
+}
+
+/// Every row of a mirrored table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TableDump {
+	/// The name of the table, such as `NodeMappings`.
+	pub name: String,
+
+	/// The names of the columns of the table.
+	pub columns: Vec<String>,
+
+	/// Every row of the table, with a field for each column.
+	pub rows: Vec<Vec<Field>>,
+}
+
+/// A vehicle snapshot being synced, along with the time it was logged.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SyncedSnapshot {
+	/// When the snapshot was logged, as a Unix timestamp.
+	pub recorded_at: f64,
+
+	/// The vehicle state, encoded by [`snapshot::encode`](super::snapshot::encode) and then in base64,
+	/// since readings which are not finite cannot be written in JSON.
+	pub vehicle_state: String,
+}
+
+/// A batch of what one server syncs to another.
+///
+/// Snapshots are synced a range at a time, while configurations, sequences,
+/// and events are sent whole with the first batch of a range.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SyncBatch {
+	/// Snapshots logged over the range, oldest first.
+	#[serde(default)]
+	pub snapshots: Vec<SyncedSnapshot>,
+
+	/// The key of the last of the snapshots on the server they were read from,
+	/// after which the next batch starts.
+	#[serde(default)]
+	pub last: Option<SnapshotKey>,
+
+	/// Every row of each of the tables synced between servers.
+	#[serde(default)]
+	pub tables: Vec<TableDump>,
+
+	/// Alerts raised over the range.
+	#[serde(default)]
+	pub alerts: Vec<Alert>,
+
+	/// Notes entered over the range.
+	#[serde(default)]
+	pub notes: Vec<Note>,
+
+	/// Telemetry gaps which overlap the range.
+	#[serde(default)]
+	pub gaps: Vec<TelemetryGap>,
+}
+
+/// How much of a batch was new to the server receiving it, since anything it
+/// already had is skipped.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SyncCounts {
+	/// The number of snapshots inserted.
+	pub snapshots: usize,
+
+	/// The number of rows of synced tables inserted or updated.
+	pub rows: usize,
+
+	/// The number of alerts, notes, and telemetry gaps inserted.
+	pub events: usize,
+}
+
+impl SyncCounts {
+	/// Adds the counts of another batch to these.
+	pub fn add(&mut self, other: SyncCounts) {
+		self.snapshots += other.snapshots;
+		self.rows += other.rows;
+		self.events += other.events;
+	}
+}
@@ -0,0 +1,283 @@
+use flate2::{write::GzEncoder, Compression};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+use super::api::FlightInfo;
+
+/// A sequence as it was sent to the flight computer, as stored in the `SequenceRuns` table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecordedRun {
+	/// The unique ID of the run.
+	pub run_id: i64,
+
+	/// The name the sequence was stored under.
+	pub sequence_name: String,
+
+	/// The script exactly as it was sent, packaged with any bundled files.
+	pub script: String,
+
+	/// Who ran the sequence, if known.
+	pub username: Option<String>,
+
+	/// When the sequence was sent, as a Unix timestamp.
+	pub ran_at: f64,
+}
+
+/// The versions of the software involved in a test, as recorded in an archive.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SoftwareVersions {
+	/// The version of servo which made the archive.
+	pub servo: String,
+
+	/// The version of SQLite the database is stored with.
+	pub sqlite: String,
+
+	/// The migration the database is at.
+	pub migration: i32,
+
+	/// The latest information reported by the flight computer, if it is connected.
+	pub flight: Option<FlightInfo>,
+}
+
+/// Describes what an archive covers, written alongside its files.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchiveManifest {
+	/// The session the archive covers, if it covers a session rather than a time range.
+	pub session_id: Option<i64>,
+
+	/// The start of the time range covered, as a Unix timestamp.
+	pub from: f64,
+
+	/// The end of the time range covered, as a Unix timestamp.
+	pub to: f64,
+
+	/// When the archive was made, as a Unix timestamp.
+	pub created_at: f64,
+
+	/// The versions of the software involved.
+	pub versions: SoftwareVersions,
+
+	/// The names of the other files in the archive.
+	pub files: Vec<String>,
+}
+
+/// Records a sequence exactly as it was sent to the flight computer, so that
+/// it may be archived even if the stored sequence is later edited.
+pub fn record_run(connection: &Connection, sequence_name: &str, script: &str, username: Option<&str>) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO SequenceRuns (sequence_name, script, username) VALUES (?1, ?2, ?3)",
+		params![sequence_name, script, username],
+	)?;
+
+	Ok(())
+}
+
+/// Lists the sequences run between two Unix timestamps, oldest first.
+pub fn runs(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<RecordedRun>> {
+	connection
+		.prepare("
+			SELECT run_id, sequence_name, script, username, ran_at
+			FROM SequenceRuns
+			WHERE ran_at >= ?1 AND ran_at <= ?2
+			ORDER BY ran_at, run_id
+		")?
+		.query_map(params![from, to], |row| {
+			Ok(RecordedRun {
+				run_id: row.get(0)?,
+				sequence_name: row.get(1)?,
+				script: row.get(2)?,
+				username: row.get(3)?,
+				ran_at: row.get(4)?,
+			})
+		})?
+		.collect()
+}
+
+/// Reads the versions of the software involved in a test, other than that of
+/// the flight computer, which is only known while it is connected.
+pub fn versions(connection: &Connection, flight: Option<FlightInfo>) -> rusqlite::Result<SoftwareVersions> {
+	Ok(SoftwareVersions {
+		servo: env!("CARGO_PKG_VERSION").to_owned(),
+		sqlite: rusqlite::version().to_owned(),
+		migration: connection.query_row("SELECT MAX(migration_id) FROM Migrations", [], |row| row.get(0))?,
+		flight,
+	})
+}
+
+/// The file name each run's script is archived under, numbered in the order
+/// the sequences were run so that repeated runs of a sequence are kept apart.
+pub fn script_path(index: usize, run: &RecordedRun) -> String {
+	let name = run.sequence_name.replace(|character: char| !character.is_ascii_alphanumeric() && character != '-' && character != '_', "_");
+
+	// tar archives only hold names of up to 100 bytes without extensions
+	format!("sequences/{:03}-{}.py", index + 1, &name[..name.len().min(64)])
+}
+
+/// The size of the blocks which the headers and files of a tar archive are padded to.
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// The largest file size which fits the octal size field of a tar header.
+const MAX_OCTAL_SIZE: u64 = 0o77_777_777_777;
+
+/// A gzipped tar archive, written a file at a time, whose compressed bytes may
+/// be taken as they are written so that it can be streamed as it is built.
+pub struct Tarball {
+	encoder: GzEncoder<Vec<u8>>,
+	modified_at: u64,
+}
+
+impl Tarball {
+	/// Starts an archive whose files are all marked as modified at the given Unix timestamp.
+	pub fn new(modified_at: f64) -> Self {
+		Tarball {
+			encoder: GzEncoder::new(Vec::new(), Compression::default()),
+			modified_at: modified_at.max(0.0) as u64,
+		}
+	}
+
+	/// Writes the header of a file of the given size, whose contents must follow.
+	pub fn start_file(&mut self, name: &str, size: u64) -> io::Result<()> {
+		let header = tar_header(name, size, self.modified_at)?;
+		self.encoder.write_all(&header)
+	}
+
+	/// Writes part of the contents of the file last started.
+	pub fn write(&mut self, content: &[u8]) -> io::Result<()> {
+		self.encoder.write_all(content)
+	}
+
+	/// Pads out the contents of the file last started, once all of its given size is written.
+	pub fn end_file(&mut self, size: u64) -> io::Result<()> {
+		let remainder = (size % TAR_BLOCK_SIZE as u64) as usize;
+
+		if remainder != 0 {
+			self.encoder.write_all(&[0; TAR_BLOCK_SIZE][remainder..])?;
+		}
+
+		Ok(())
+	}
+
+	/// Writes a whole file.
+	pub fn add_file(&mut self, name: &str, content: &[u8]) -> io::Result<()> {
+		self.start_file(name, content.len() as u64)?;
+		self.write(content)?;
+		self.end_file(content.len() as u64)
+	}
+
+	/// Takes the compressed bytes written since they were last taken.
+	pub fn take(&mut self) -> Vec<u8> {
+		std::mem::take(self.encoder.get_mut())
+	}
+
+	/// Ends the archive, returning the last of its compressed bytes.
+	pub fn finish(mut self) -> io::Result<Vec<u8>> {
+		self.encoder.write_all(&[0; 2 * TAR_BLOCK_SIZE])?;
+		self.encoder.finish()
+	}
+}
+
+/// Builds the POSIX ustar header of a regular file.
+fn tar_header(path: &str, size: u64, modified_at: u64) -> io::Result<[u8; TAR_BLOCK_SIZE]> {
+	let mut header = [0; TAR_BLOCK_SIZE];
+
+	// names longer than the name field are split into a directory prefix and a name
+	let (prefix, name) = match path.len() {
+		0..=100 => ("", path),
+		_ => path
+			.char_indices()
+			.filter(|&(index, character)| character == '/' && index <= 155 && path.len() - index - 1 <= 100)
+			.map(|(index, _)| (&path[..index], &path[index + 1..]))
+			.next()
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("'{path}' is too long to be archived")))?,
+	};
+
+	header[..name.len()].copy_from_slice(name.as_bytes());
+	header[100..108].copy_from_slice(b"0000644\0");
+	header[108..116].copy_from_slice(b"0000000\0");
+	header[116..124].copy_from_slice(b"0000000\0");
+
+	// sizes too large for the octal field are written in binary, as GNU tar does
+	if size <= MAX_OCTAL_SIZE {
+		header[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+	} else {
+		header[124] = 0x80;
+		header[128..136].copy_from_slice(&size.to_be_bytes());
+	}
+
+	header[136..148].copy_from_slice(format!("{:011o}\0", modified_at.min(MAX_OCTAL_SIZE)).as_bytes());
+	header[156] = b'0';
+	header[257..263].copy_from_slice(b"ustar\0");
+	header[263..265].copy_from_slice(b"00");
+	header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+	// the checksum is summed as if its own field were spaces
+	header[148..156].copy_from_slice(b"        ");
+	let checksum = header.iter().map(|&byte| u32::from(byte)).sum::<u32>();
+	header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+	Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn runs_are_kept_as_they_were_sent() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+		record_run(&connection, "press/fill", "print('fill')", Some("jeff")).unwrap();
+		record_run(&connection, "press/fill", "print('fill faster')", None).unwrap();
+
+		let runs = runs(&connection, 0.0, f64::MAX).unwrap();
+		assert_eq!(runs.len(), 2);
+		assert_eq!(runs[0].script, "print('fill')");
+		assert_eq!(script_path(1, &runs[1]), "sequences/002-press_fill.py");
+
+		assert!(versions(&connection, None).unwrap().migration > 0);
+	}
+
+	#[test]
+	fn tarballs_hold_each_file() {
+		let mut tarball = Tarball::new(1_700_000_000.0);
+		let long_path = format!("sequences/{}.py", "a".repeat(90));
+
+		tarball.add_file("manifest.json", b"{}").unwrap();
+		tarball.start_file(&long_path, 5).unwrap();
+		tarball.write(b"pri").unwrap();
+		tarball.write(b"nt").unwrap();
+		tarball.end_file(5).unwrap();
+
+		let mut compressed = tarball.take();
+		compressed.extend(tarball.finish().unwrap());
+
+		let mut archive = Vec::new();
+		io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&compressed[..]), &mut archive).unwrap();
+		assert_eq!(archive.len(), 6 * TAR_BLOCK_SIZE);
+
+		let field = |block: usize, range: std::ops::Range<usize>| {
+			let field = &archive[block * TAR_BLOCK_SIZE..][range];
+			String::from_utf8_lossy(field).trim_end_matches('\0').to_owned()
+		};
+
+		assert_eq!(field(0, 0..100), "manifest.json");
+		assert_eq!(field(0, 124..136), "00000000002");
+		assert_eq!(&archive[TAR_BLOCK_SIZE..TAR_BLOCK_SIZE + 2], b"{}");
+		assert_eq!(field(2, 0..100), format!("{}.py", "a".repeat(90)));
+		assert_eq!(field(2, 345..500), "sequences");
+		assert_eq!(&archive[3 * TAR_BLOCK_SIZE..3 * TAR_BLOCK_SIZE + 5], b"print");
+
+		let checksum = archive[..TAR_BLOCK_SIZE]
+			.iter()
+			.enumerate()
+			.map(|(index, &byte)| if (148..156).contains(&index) { u32::from(b' ') } else { u32::from(byte) })
+			.sum::<u32>();
+
+		assert_eq!(u32::from_str_radix(&field(0, 148..154), 8).unwrap(), checksum);
+		assert!(archive[4 * TAR_BLOCK_SIZE..].iter().all(|&byte| byte == 0));
+	}
+}
@@ -0,0 +1,88 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::alert::Severity;
+
+#[cfg(feature = "server")]
+use super::trace;
+
+/// An entry in the audit log, as stored in the `AuditLog` table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AuditEntry {
+	/// The unique ID of the entry.
+	pub audit_id: i64,
+
+	/// When the action was taken, as a Unix timestamp.
+	pub recorded_at: f64,
+
+	/// Who took the action, if known.
+	pub username: Option<String>,
+
+	/// What was done, such as `safe` or `run sequence`.
+	pub action: String,
+
+	/// A description of the action.
+	pub detail: Option<String>,
+
+	/// How severe the action was.
+	pub severity: Severity,
+
+	/// The ID of the request the action was taken while handling, if any.
+	pub request_id: Option<String>,
+}
+
+/// Records an entry in the audit log.
+///
+/// Audit entries are kept for actions which deliberately bypass a safety check,
+/// so that who did what and when can be reconstructed after a test. Entries
+/// made while handling a request are tagged with its ID.
+#[cfg(feature = "server")]
+pub fn record(
+	connection: &Connection,
+	username: Option<&str>,
+	action: &str,
+	detail: &str,
+) -> rusqlite::Result<()> {
+	record_with_severity(connection, username, action, detail, Severity::Info)
+}
+
+/// Records an entry in the audit log with a severity above the usual, such as
+/// for a command touching a channel classified as hazardous.
+#[cfg(feature = "server")]
+pub fn record_with_severity(
+	connection: &Connection,
+	username: Option<&str>,
+	action: &str,
+	detail: &str,
+	severity: Severity,
+) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO AuditLog (username, action, detail, request_id, severity) VALUES (?1, ?2, ?3, ?4, ?5)",
+		params![username, action, detail, trace::current(), severity.as_str()],
+	)?;
+
+	Ok(())
+}
+
+/// Lists the entries recorded between two Unix timestamps, oldest first.
+pub fn list(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<AuditEntry>> {
+	connection
+		.prepare("
+			SELECT audit_id, recorded_at, username, action, detail, severity, request_id
+			FROM AuditLog
+			WHERE recorded_at >= ?1 AND recorded_at <= ?2
+			ORDER BY recorded_at, audit_id
+		")?
+		.query_map(params![from, to], |row| {
+			Ok(AuditEntry {
+				audit_id: row.get(0)?,
+				recorded_at: row.get(1)?,
+				username: row.get(2)?,
+				action: row.get(3)?,
+				detail: row.get(4)?,
+				severity: row.get::<_, String>(5)?.parse().unwrap_or(Severity::Info),
+				request_id: row.get(6)?,
+			})
+		})?
+		.collect()
+}
@@ -0,0 +1,230 @@
+use axum::{
+	async_trait,
+	extract::{ConnectInfo, FromRequestParts, Request, State},
+	http::{header, request::Parts},
+	middleware::Next,
+	response::Response,
+};
+use crate::server::{self, clock, error::{forbidden, internal, unauthorized, ErrorCode}, Shared};
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use subtle::ConstantTimeEq;
+
+/// An authenticated session attached to a request by the `authenticate` middleware.
+///
+/// Route functions which require authentication take a `Session` as an argument,
+/// which rejects the request with a 401 if no valid session token was supplied.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Session {
+	/// The unique ID of the session, as stored in the `Sessions` table.
+	pub session_id: i64,
+
+	/// The name of the user who owns the session.
+	pub username: String,
+
+	/// The role of the user who owns the session, such as `admin`, `operator`, or `spectator`.
+	pub role: String,
+}
+
+impl Session {
+	/// Whether the owner of the session has the admin role.
+	pub fn is_admin(&self) -> bool {
+		self.role == "admin"
+	}
+
+	/// Whether the owner of the session has the spectator role, which may not change anything.
+	pub fn is_spectator(&self) -> bool {
+		self.role == "spectator"
+	}
+
+	/// Returns a 403 error if the owner of the session is not an admin.
+	pub fn require_admin(&self) -> server::Result<()> {
+		if self.is_admin() {
+			Ok(())
+		} else {
+			Err(forbidden("admin role required").with_code(ErrorCode::AdminRequired))
+		}
+	}
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Session {
+	type Rejection = server::Error;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		parts.extensions
+			.get::<Session>()
+			.cloned()
+			.ok_or(unauthorized("authentication required"))
+	}
+}
+
+/// Generates a new random session token, encoded as URL-safe Base64.
+pub fn generate_token() -> String {
+	let mut bytes = [0_u8; 32];
+	rand::thread_rng().fill_bytes(&mut bytes);
+	base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Hashes a session token so that raw tokens are never stored in the database.
+pub fn hash_token(token: &str) -> String {
+	format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Generates a new random salt to be used with `hash_password`.
+pub fn generate_salt() -> String {
+	let mut bytes = [0_u8; 16];
+	rand::thread_rng().fill_bytes(&mut bytes);
+	base64::encode(bytes)
+}
+
+/// The number of PBKDF2 iterations new password hashes are stretched over.
+///
+/// The count is stored with each hash, so raising it leaves existing passwords
+/// valid; they are rehashed with the new count the next time their user logs in.
+pub const PASSWORD_ITERATIONS: u32 = 600_000;
+
+/// Hashes a password with the given salt using PBKDF2-HMAC-SHA256.
+///
+/// An iteration count of zero gives the single salted SHA-256 digest which
+/// passwords were stored as before they were stretched.
+pub fn hash_password(password: &str, salt: &str, iterations: u32) -> String {
+	if iterations == 0 {
+		let mut hasher = Sha256::new();
+		hasher.update(salt.as_bytes());
+		hasher.update(password.as_bytes());
+
+		return format!("{:x}", hasher.finalize());
+	}
+
+	let mut hash = [0_u8; 32];
+	pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), iterations, &mut hash);
+	hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Checks a password against its stored hash in constant time, so that the
+/// time taken reveals nothing about how much of the hash matched.
+pub fn verify_password(password: &str, salt: &str, iterations: u32, password_hash: &str) -> bool {
+	hash_password(password, salt, iterations)
+		.as_bytes()
+		.ct_eq(password_hash.as_bytes())
+		.into()
+}
+
+/// Middleware which validates the bearer token of a request, if one is present.
+///
+/// Requests without an `Authorization` header pass through untouched, while
+/// requests with an invalid or expired token are rejected with a 401. Valid
+/// sessions are attached to the request so that route functions may extract them.
+///
+/// Activity keeps a session alive: once fewer than the configured renewal
+/// threshold of minutes remain, the session's expiry is pushed out by the full
+/// expiry window. Renewing only near expiry avoids rewriting the expiry on
+/// every request.
+///
+/// Authenticated requests which report the client's clock through the
+/// `X-Client-Time` header also have the skew of that clock recorded.
+pub async fn authenticate(
+	State(shared): State<Shared>,
+	mut request: Request,
+	next: Next,
+) -> server::Result<Response> {
+	let token = request
+		.headers()
+		.get(header::AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.map(|token| token.trim().to_owned());
+
+	if let Some(token) = token {
+		let database = shared.database
+			.connection
+			.lock()
+			.await;
+
+		let session = database
+			.query_row("
+				SELECT Sessions.session_id, Users.username, Users.role
+				FROM Sessions
+				INNER JOIN Users ON Users.username = Sessions.username
+				WHERE Sessions.token_hash = ?1 AND Sessions.expires_at > unixepoch('now', 'subsec')
+			", [hash_token(&token)], |row| {
+				Ok(Session {
+					session_id: row.get(0)?,
+					username: row.get(1)?,
+					role: row.get(2)?,
+				})
+			})
+			.optional()
+			.map_err(internal)?
+			.ok_or(unauthorized("session is invalid or has expired").with_code(ErrorCode::SessionExpired))?;
+
+		let config = shared.config.current();
+		let session_config = &config.sessions;
+
+		database
+			.execute("
+				UPDATE Sessions
+				SET
+					last_active = unixepoch('now', 'subsec'),
+					expires_at = CASE
+						WHEN expires_at - unixepoch('now', 'subsec') < ?2 THEN unixepoch('now', 'subsec') + ?3
+						ELSE expires_at
+					END
+				WHERE session_id = ?1
+			", params![
+				session.session_id,
+				session_config.renewal_threshold_minutes * 60.0,
+				session_config.expiry_minutes * 60.0,
+			])
+			.map_err(internal)?;
+
+		drop(database);
+
+		let client_time = request
+			.headers()
+			.get(clock::CLIENT_TIME_HEADER)
+			.and_then(|value| value.to_str().ok())
+			.and_then(|value| value.trim().parse::<f64>().ok());
+
+		if let Some(client_time) = client_time {
+			let participant = match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+				Some(ConnectInfo(peer)) => format!("{}@{}", session.username, peer.ip()),
+				None => session.username.clone(),
+			};
+
+			shared.metrics.clocks.lock().await.record(&participant, client_time, clock::now());
+		}
+
+		request.extensions_mut().insert(session);
+	}
+
+	Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn passwords_are_verified_against_their_hashes() {
+		let salt = generate_salt();
+		let password_hash = hash_password("hunter2", &salt, 1000);
+
+		assert!(verify_password("hunter2", &salt, 1000, &password_hash));
+		assert!(!verify_password("hunter3", &salt, 1000, &password_hash));
+		assert!(!verify_password("hunter2", &salt, 999, &password_hash));
+		assert!(!verify_password("hunter2", &generate_salt(), 1000, &password_hash));
+	}
+
+	#[test]
+	fn unstretched_hashes_still_verify() {
+		let password_hash = format!("{:x}", Sha256::digest(b"salthunter2"));
+
+		assert!(verify_password("hunter2", "salt", 0, &password_hash));
+		assert_ne!(hash_password("hunter2", "salt", 1), password_hash);
+	}
+}
@@ -0,0 +1,223 @@
+use axum::{extract::{Request, State}, middleware::Next, response::Response};
+use jeflog::warn;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, time::{Duration, Instant}};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+
+use super::{audit, auth::Session, error::{locked, ErrorCode}, maintenance, routes::run_safing_sequence, Shared};
+
+/// How often the lease is checked for expiry.
+const EXPIRY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Requests which actuate the vehicle but never require the lease.
+///
+/// The path to safe is never locked out, and a hazardous command is confirmed
+/// by a second operator after the holder of the lease requested it.
+const EXEMPT_REQUESTS: [&str; 4] = [
+	"/operator/safe",
+	"/operator/abort",
+	"/operator/command/confirm",
+	"/servo.v1.Servo/Abort",
+];
+
+/// The session holding command authority and when its lease runs out.
+#[derive(Clone, Debug)]
+pub struct Lease {
+	/// The ID of the session holding the lease.
+	pub session_id: i64,
+
+	/// The name of the user who owns the session.
+	pub username: String,
+
+	/// When the lease expires unless renewed by a heartbeat.
+	pub expires_at: Instant,
+}
+
+/// The status of the lease, as sent to a GUI over its heartbeat WebSocket.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LeaseStatus {
+	/// Whether the receiving GUI holds the lease.
+	pub held: bool,
+
+	/// The name of the user holding the lease, if anyone does.
+	pub holder: Option<String>,
+
+	/// The number of seconds the lease lasts without a heartbeat.
+	pub lease_seconds: f64,
+}
+
+/// The lease on command authority, which at most one session holds at a time.
+#[derive(Debug, Default)]
+pub struct CommandAuthority {
+	lease: Mutex<Option<Lease>>,
+}
+
+impl CommandAuthority {
+	/// Takes the lease for a session, unless another session holds an unexpired
+	/// lease, in which case the name of its user is returned.
+	pub async fn acquire(&self, session: &Session, duration: Duration) -> Result<(), String> {
+		let mut lease = self.lease.lock().await;
+		let now = Instant::now();
+
+		if let Some(held) = lease.as_ref() {
+			if held.session_id != session.session_id && held.expires_at > now {
+				return Err(held.username.clone());
+			}
+		}
+
+		*lease = Some(Lease {
+			session_id: session.session_id,
+			username: session.username.clone(),
+			expires_at: now + duration,
+		});
+
+		Ok(())
+	}
+
+	/// Extends the lease of a session, returning whether it still held the lease.
+	pub async fn renew(&self, session_id: i64, duration: Duration) -> bool {
+		let mut lease = self.lease.lock().await;
+		let now = Instant::now();
+
+		match lease.as_mut() {
+			Some(held) if held.session_id == session_id && held.expires_at > now => {
+				held.expires_at = now + duration;
+				true
+			},
+			_ => false,
+		}
+	}
+
+	/// Gives up the lease of a session, if it holds it.
+	pub async fn release(&self, session_id: i64) {
+		let mut lease = self.lease.lock().await;
+
+		if lease.as_ref().is_some_and(|held| held.session_id == session_id) {
+			*lease = None;
+		}
+	}
+
+	/// Whether a session holds an unexpired lease.
+	pub async fn holds(&self, session_id: i64) -> bool {
+		self.lease
+			.lock()
+			.await
+			.as_ref()
+			.is_some_and(|held| held.session_id == session_id && held.expires_at > Instant::now())
+	}
+
+	/// The name of the user holding an unexpired lease, if anyone does.
+	pub async fn holder(&self) -> Option<String> {
+		self.lease
+			.lock()
+			.await
+			.as_ref()
+			.filter(|held| held.expires_at > Instant::now())
+			.map(|held| held.username.clone())
+	}
+
+	/// Removes and returns the lease if it has expired without being released.
+	async fn take_expired(&self) -> Option<Lease> {
+		let mut lease = self.lease.lock().await;
+
+		if lease.as_ref().is_some_and(|held| held.expires_at <= Instant::now()) {
+			lease.take()
+		} else {
+			None
+		}
+	}
+}
+
+/// Middleware which refuses requests that actuate the vehicle with a 423 unless
+/// they come from the session holding the lease, if the configuration requires it.
+pub async fn require_authority(State(shared): State<Shared>, request: Request, next: Next) -> super::Result<Response> {
+	let path = request.uri().path();
+
+	if !shared.config.current().authority.required
+		|| !maintenance::is_actuation(request.method(), path)
+		|| EXEMPT_REQUESTS.contains(&path)
+	{
+		return Ok(next.run(request).await);
+	}
+
+	let session_id = request.extensions().get::<Session>().map(|session| session.session_id);
+
+	if let Some(session_id) = session_id {
+		if shared.authority.holds(session_id).await {
+			return Ok(next.run(request).await);
+		}
+	}
+
+	let error = match shared.authority.holder().await {
+		Some(holder) => locked(format!("command authority is held by {holder}")).with_context("holder", holder),
+		None => locked("command authority must be held through /operator/authority to actuate the vehicle"),
+	};
+
+	Err(error.with_code(ErrorCode::AuthorityNotHeld))
+}
+
+/// Watches for the lease expiring without being released, such as when the GUI
+/// holding it freezes or loses its connection, running the safing sequence if
+/// the configuration asks for it.
+pub fn watch(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			interval.tick().await;
+
+			let Some(lease) = shared.authority.take_expired().await else {
+				continue;
+			};
+
+			warn!("Command authority lease held by \x1b[1m{}\x1b[0m expired.", lease.username);
+
+			let _ = audit::record(
+				&*shared.database.connection.lock().await,
+				Some(&lease.username),
+				"authority lease expired",
+				"the lease on command authority expired without being released",
+			);
+
+			// nothing may be actuated remotely while people are hands-on the vehicle
+			if !shared.config.current().authority.safe_on_expiry || shared.maintenance.is_enabled() {
+				continue;
+			}
+
+			if let Err(error) = run_safing_sequence(&shared, Some(&lease.username), "authority lease").await {
+				warn!("Failed to safe after the command authority lease expired: {error}");
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn session(session_id: i64, username: &str) -> Session {
+		Session { session_id, username: username.to_owned(), role: "operator".to_owned() }
+	}
+
+	#[tokio::test]
+	async fn only_one_session_holds_the_lease() {
+		let authority = CommandAuthority::default();
+		let (alice, bob) = (session(1, "alice"), session(2, "bob"));
+
+		authority.acquire(&alice, Duration::from_secs(60)).await.unwrap();
+		assert_eq!(authority.acquire(&bob, Duration::from_secs(60)).await, Err("alice".to_owned()));
+		assert!(authority.holds(1).await && !authority.holds(2).await);
+		assert!(!authority.renew(2, Duration::from_secs(60)).await);
+
+		authority.release(1).await;
+		authority.acquire(&bob, Duration::ZERO).await.unwrap();
+
+		// an expired lease is taken by the watcher rather than renewed
+		assert!(!authority.renew(2, Duration::from_secs(60)).await);
+		assert_eq!(authority.take_expired().await.unwrap().username, "bob");
+		assert!(authority.holder().await.is_none());
+	}
+}
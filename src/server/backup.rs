@@ -0,0 +1,466 @@
+use anyhow::anyhow;
+use jeflog::{pass, warn};
+use rusqlite::{backup::{Backup, StepResult}, params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, future::Future, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use super::{api::{Field, TableDump}, clock, config::BackupConfig, Shared};
+
+/// Tables which rows are only ever added to, and so are backed up a batch of
+/// new rows at a time. Rows later deleted from them, such as old bad frames,
+/// are kept in the backup.
+pub const APPENDED_TABLES: [&str; 11] = [
+	"VehicleSnapshots",
+	"RequestLogs",
+	"TelemetryGaps",
+	"PositionFixes",
+	"Alerts",
+	"BadFrames",
+	"AuditLog",
+	"Promotions",
+	"Notes",
+	"SequenceRuns",
+	"AdhocSequences",
+];
+
+/// The table of a backup which records how far each appended table has been
+/// backed up, so that a backup resumes where it left off.
+const CURSORS_TABLE: &str = "BackupCursors";
+
+/// Requests the rows added since a backup was last brought up to date.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BackupRequest {
+	/// The last row ID of each appended table already in the backup.
+	/// Tables which are missing have not been backed up at all.
+	pub cursors: BTreeMap<String, i64>,
+
+	/// The most rows to send of the appended tables, so that catching up on a
+	/// long test does not hold the database for long.
+	pub max_rows: usize,
+}
+
+/// The changes needed to bring a backup closer to up to date.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackupBatch {
+	/// The migration the database is at, which a backup must be at as well.
+	pub migration: i32,
+
+	/// New rows of the appended tables, without their row IDs.
+	pub appended: Vec<TableDump>,
+
+	/// Every row of the remaining tables, which replace those of the backup.
+	/// These are only sent in the batch which brings the backup up to date.
+	pub copied: Vec<TableDump>,
+
+	/// The last row ID of each appended table once the batch is applied.
+	pub cursors: BTreeMap<String, i64>,
+
+	/// Whether the backup is up to date once the batch is applied.
+	pub complete: bool,
+}
+
+/// Reads the next batch of changes for a backup which has reached the given cursors.
+pub fn changes(connection: &Connection, request: &BackupRequest) -> anyhow::Result<BackupBatch> {
+	let migration = connection.query_row("SELECT MAX(migration_id) FROM Migrations", [], |row| row.get(0))?;
+
+	let mut appended = Vec::new();
+	let mut cursors = request.cursors.clone();
+	let mut remaining = request.max_rows;
+	let mut complete = true;
+
+	for name in APPENDED_TABLES {
+		let cursor = cursors.get(name).copied().unwrap_or(0);
+
+		let latest = connection.query_row(&format!("SELECT COALESCE(MAX(rowid), 0) FROM {name}"), [], |row| row.get::<_, i64>(0))?;
+
+		if cursor > latest {
+			anyhow::bail!("backup of {name} is ahead of the database, which may have been replaced since the backup began");
+		}
+
+		if remaining == 0 {
+			complete &= cursor == latest;
+			continue;
+		}
+
+		let mut statement = connection.prepare(&format!("SELECT rowid, * FROM {name} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2"))?;
+
+		let columns = statement
+			.column_names()
+			.into_iter()
+			.skip(1)
+			.map(str::to_owned)
+			.collect::<Vec<_>>();
+
+		let rows = statement
+			.query_map(params![cursor, remaining], |row| {
+				let fields = (1..=columns.len())
+					.map(|column| Ok(Field::from(row.get_ref(column)?)))
+					.collect::<rusqlite::Result<Vec<_>>>()?;
+
+				Ok((row.get::<_, i64>(0)?, fields))
+			})?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+
+		if let Some((rowid, _)) = rows.last() {
+			cursors.insert(name.to_owned(), *rowid);
+			complete &= *rowid == latest;
+		}
+
+		remaining -= rows.len();
+
+		appended.push(TableDump {
+			name: name.to_owned(),
+			columns,
+			rows: rows.into_iter().map(|(_, fields)| fields).collect(),
+		});
+	}
+
+	let copied = if complete { copied_tables(connection)? } else { Vec::new() };
+
+	Ok(BackupBatch { migration, appended, copied, cursors, complete })
+}
+
+/// Reads every row of the tables which are not appended to.
+fn copied_tables(connection: &Connection) -> rusqlite::Result<Vec<TableDump>> {
+	let names = connection
+		.prepare("SELECT name FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != 'Migrations' ORDER BY name")?
+		.query_map([], |row| row.get::<_, String>(0))?
+		.collect::<rusqlite::Result<Vec<_>>>()?;
+
+	names
+		.into_iter()
+		.filter(|name| !APPENDED_TABLES.contains(&name.as_str()) && name != CURSORS_TABLE)
+		.map(|name| {
+			let mut statement = connection.prepare(&format!("SELECT * FROM {}", quote(&name)))?;
+
+			let columns = statement
+				.column_names()
+				.into_iter()
+				.map(str::to_owned)
+				.collect::<Vec<_>>();
+
+			let rows = statement
+				.query_map([], |row| (0..columns.len()).map(|column| Ok(Field::from(row.get_ref(column)?))).collect())?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			Ok(TableDump { name, columns, rows })
+		})
+		.collect()
+}
+
+/// Prepares a migrated database to hold a backup, returning how far it has been backed up.
+pub fn prepare(connection: &Connection) -> rusqlite::Result<BTreeMap<String, i64>> {
+	// rows are copied as they are, so the constraints they were checked against
+	// on the server need not hold partway through a backup
+	connection.execute_batch(&format!("
+		PRAGMA foreign_keys = OFF;
+
+		CREATE TABLE IF NOT EXISTS {CURSORS_TABLE} (
+			table_name TEXT NOT NULL PRIMARY KEY,
+			last_rowid INTEGER NOT NULL
+		);
+	"))?;
+
+	connection
+		.prepare(&format!("SELECT table_name, last_rowid FROM {CURSORS_TABLE}"))?
+		.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+		.collect()
+}
+
+/// Applies a batch of changes to a backup all at once, along with its cursors,
+/// so that an interrupted backup is never left partway through a batch.
+pub fn apply(connection: &mut Connection, batch: &BackupBatch) -> anyhow::Result<()> {
+	let migration = connection
+		.query_row("SELECT MAX(migration_id) FROM Migrations", [], |row| row.get::<_, i32>(0))
+		.optional()?
+		.ok_or_else(|| anyhow!("backup has not been migrated"))?;
+
+	if migration != batch.migration {
+		anyhow::bail!("server database is at migration {}, but the backup is at migration {migration}", batch.migration);
+	}
+
+	let transaction = connection.transaction()?;
+
+	for table in &batch.appended {
+		insert_rows(&transaction, table)?;
+	}
+
+	for table in &batch.copied {
+		transaction.execute(&format!("DELETE FROM {}", quote(&table.name)), [])?;
+		insert_rows(&transaction, table)?;
+	}
+
+	for (table, last_rowid) in &batch.cursors {
+		transaction.execute(
+			&format!("INSERT OR REPLACE INTO {CURSORS_TABLE} (table_name, last_rowid) VALUES (?1, ?2)"),
+			params![table, last_rowid],
+		)?;
+	}
+
+	transaction.commit()?;
+	Ok(())
+}
+
+/// Inserts every row of a dumped table into the table of the same name.
+fn insert_rows(connection: &Connection, table: &TableDump) -> rusqlite::Result<()> {
+	let columns = table.columns
+		.iter()
+		.map(|column| quote(column))
+		.collect::<Vec<_>>();
+
+	let placeholders = (1..=columns.len())
+		.map(|index| format!("?{index}"))
+		.collect::<Vec<_>>();
+
+	let mut insert = connection.prepare(&format!(
+		"INSERT INTO {} ({}) VALUES ({})",
+		quote(&table.name),
+		columns.join(", "),
+		placeholders.join(", "),
+	))?;
+
+	for row in &table.rows {
+		insert.execute(rusqlite::params_from_iter(row))?;
+	}
+
+	Ok(())
+}
+
+/// Quotes an identifier sent by the server so that it may be interpolated into SQL.
+fn quote(identifier: &str) -> String {
+	format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// How often the schedule of backups is checked against the configuration,
+/// so that a reloaded interval takes effect without a restart.
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A snapshot of the database written to a file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DatabaseBackup {
+	/// The file the snapshot was written to.
+	pub path: PathBuf,
+
+	/// The size of the file in bytes.
+	pub size_bytes: u64,
+
+	/// The time the snapshot was taken, in seconds since the Unix epoch.
+	pub created_at: f64,
+}
+
+/// The directory snapshots of the database are written to.
+pub fn directory(config: &BackupConfig) -> anyhow::Result<PathBuf> {
+	if let Some(directory) = &config.directory {
+		return Ok(directory.clone());
+	}
+
+	#[cfg(target_family = "windows")]
+	let home_path = env::var("USERPROFILE")?;
+
+	#[cfg(target_family = "unix")]
+	let home_path = env::var("HOME")?;
+
+	Ok(Path::new(&home_path).join(".servo").join("backups"))
+}
+
+/// Copies a database to a new file with SQLite's online backup API.
+///
+/// Unlike copying the file of a live database, this never captures a write
+/// partway through. The copy is made in one step while holding the source
+/// connection, so writes made through it wait until the copy is complete.
+pub fn copy(source: &Connection, path: &Path) -> anyhow::Result<()> {
+	let mut destination = Connection::open(path)?;
+
+	// a negative number of pages copies every page at once
+	let backup = Backup::new(source, &mut destination)?;
+	let result = backup.step(-1)?;
+
+	match result {
+		StepResult::Done => Ok(()),
+		result => Err(anyhow!("backup did not complete ({result:?})")),
+	}
+}
+
+/// Takes a snapshot of the database, named after the time it was taken, and
+/// deletes the oldest snapshots beyond the number configured to be kept.
+pub async fn snapshot(shared: &Shared) -> anyhow::Result<DatabaseBackup> {
+	let config = shared.config.current().backups.clone();
+	let directory = directory(&config)?;
+	fs::create_dir_all(&directory)?;
+
+	let connection = shared.database.connection.clone();
+
+	let path = tokio::task::spawn_blocking(move || -> anyhow::Result<PathBuf> {
+		let connection = connection.blocking_lock();
+
+		let timestamp = connection.query_row("SELECT strftime('%Y-%m-%dT%H-%M-%SZ', 'now')", [], |row| row.get::<_, String>(0))?;
+		let path = directory.join(format!("database-{timestamp}.sqlite"));
+
+		if path.exists() {
+			anyhow::bail!("backup '{}' already exists", path.display());
+		}
+
+		copy(&connection, &path)?;
+		Ok(path)
+	}).await??;
+
+	let size_bytes = fs::metadata(&path)?.len();
+
+	if let Some(keep) = config.keep {
+		prune(path.parent().unwrap_or(Path::new(".")), keep)?;
+	}
+
+	Ok(DatabaseBackup { path, size_bytes, created_at: clock::now() })
+}
+
+/// Deletes all but the newest snapshots in a directory, which sort by name
+/// in the order they were taken.
+fn prune(directory: &Path, keep: usize) -> anyhow::Result<()> {
+	let mut snapshots = fs::read_dir(directory)?
+		.map(|entry| Ok(entry?.path()))
+		.collect::<anyhow::Result<Vec<_>>>()?
+		.into_iter()
+		.filter(|path| {
+			path.file_name()
+				.and_then(|name| name.to_str())
+				.is_some_and(|name| name.starts_with("database-") && name.ends_with(".sqlite"))
+		})
+		.collect::<Vec<_>>();
+
+	snapshots.sort();
+
+	for path in snapshots.iter().rev().skip(keep) {
+		fs::remove_file(path)?;
+	}
+
+	Ok(())
+}
+
+/// Takes a snapshot of the database each time the configured interval elapses.
+///
+/// The interval is read from the configuration as it runs, so backups may be
+/// scheduled, rescheduled, or stopped by reloading it.
+pub fn schedule(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut last = Instant::now();
+
+		loop {
+			tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+
+			let Some(hours) = shared.config.current().backups.interval_hours else {
+				continue;
+			};
+
+			if last.elapsed() < Duration::from_secs_f64(hours * 3600.0) {
+				continue;
+			}
+
+			last = Instant::now();
+
+			match snapshot(&shared).await {
+				Ok(backup) => pass!("Backed up the database to \x1b[1m{}\x1b[0m.", backup.path.display()),
+				Err(error) => warn!("Failed to back up the database: {error}"),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn backups_catch_up_in_batches_and_resume() {
+		let server = Database::volatile().unwrap();
+		server.migrate().unwrap();
+
+		let backup = Database::volatile().unwrap();
+		backup.migrate().unwrap();
+
+		let insert_snapshots = |count: usize| {
+			let connection = server.connection.blocking_lock();
+
+			for _ in 0..count {
+				connection.execute("INSERT INTO VehicleSnapshots (vehicle_state) VALUES (x'0102')", []).unwrap();
+			}
+		};
+
+		insert_snapshots(5);
+		server.connection.blocking_lock().execute("INSERT INTO Triggers (name, condition, script) VALUES ('vent', 'true', '')", []).unwrap();
+
+		let mut batches = 0;
+
+		let mut catch_up = || loop {
+			let cursors = prepare(&backup.connection.blocking_lock()).unwrap();
+			let request = BackupRequest { cursors, max_rows: 2 };
+			let batch = changes(&server.connection.blocking_lock(), &request).unwrap();
+
+			apply(&mut backup.connection.blocking_lock(), &batch).unwrap();
+			batches += 1;
+
+			if batch.complete {
+				break;
+			}
+		};
+
+		catch_up();
+		insert_snapshots(1);
+		catch_up();
+
+		let count = |database: &Database, table: &str| {
+			database.connection
+				.blocking_lock()
+				.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get::<_, i64>(0))
+				.unwrap()
+		};
+
+		assert_eq!(batches, 4);
+		assert_eq!(count(&backup, "VehicleSnapshots"), 6);
+		assert_eq!(count(&backup, "Triggers"), 1);
+
+		// a server which has been wiped cannot continue a backup of its old database
+		let wiped = Database::volatile().unwrap();
+		wiped.migrate().unwrap();
+
+		let request = BackupRequest { cursors: prepare(&backup.connection.blocking_lock()).unwrap(), max_rows: 2 };
+		assert!(changes(&wiped.connection.blocking_lock(), &request).is_err());
+	}
+
+	#[test]
+	fn snapshots_hold_every_row_and_only_the_newest_are_kept() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let directory = env::temp_dir().join(format!("servo-backups-{}", std::process::id()));
+		fs::create_dir_all(&directory).unwrap();
+
+		let connection = database.connection.blocking_lock();
+		connection.execute("INSERT INTO Triggers (name, condition, script) VALUES ('vent', 'true', '')", []).unwrap();
+
+		for second in 0..3 {
+			copy(&connection, &directory.join(format!("database-2026-10-16T12-30-0{second}Z.sqlite"))).unwrap();
+		}
+
+		fs::write(directory.join("notes.txt"), "kept").unwrap();
+		prune(&directory, 2).unwrap();
+
+		let mut remaining = fs::read_dir(&directory)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+			.collect::<Vec<_>>();
+
+		remaining.sort();
+		assert_eq!(remaining, ["database-2026-10-16T12-30-01Z.sqlite", "database-2026-10-16T12-30-02Z.sqlite", "notes.txt"]);
+
+		let triggers = Connection::open(directory.join(&remaining[1]))
+			.unwrap()
+			.query_row("SELECT COUNT(*) FROM Triggers", [], |row| row.get::<_, i64>(0))
+			.unwrap();
+
+		fs::remove_dir_all(&directory).unwrap();
+		assert_eq!(triggers, 1);
+	}
+}
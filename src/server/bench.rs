@@ -0,0 +1,127 @@
+use common::comm::{CompositeValveState, Measurement, Unit, ValveState, VehicleState};
+use std::sync::Arc;
+
+#[cfg(feature = "hdf5")]
+use std::{collections::BTreeMap, path::Path};
+
+use super::{database::Database, decoder::DecodedPacket, routes, snapshot, units::UnitSystem};
+
+/// The channel counts the pipeline is measured at, from a single board up to a fully instrumented vehicle.
+pub const CHANNEL_COUNTS: [usize; 3] = [16, 64, 256];
+
+/// The number of snapshots in each measured export, which is 100 seconds of data at 10 Hz.
+pub const EXPORT_SNAPSHOTS: usize = 1_000;
+
+/// The namespace that decoded packets are merged under.
+const NAMESPACE: &str = "bench";
+
+/// Synthetic data for each stage of the data pipeline at one channel count.
+///
+/// Both the criterion benchmarks and `servo bench` run the stages through this
+/// struct so that their numbers are directly comparable. One in four channels is
+/// a valve and the rest are sensors, roughly matching the flight mappings.
+pub struct Workload {
+	sensor_names: Vec<String>,
+	valve_names: Vec<String>,
+	state: Arc<VehicleState>,
+	serialized: Vec<u8>,
+	packet: DecodedPacket,
+	snapshots: Vec<(f64, VehicleState)>,
+	database: Database,
+}
+
+impl Workload {
+	/// Generates the data for a channel count, along with an in-memory database to insert snapshots into.
+	pub fn new(channels: usize) -> anyhow::Result<Self> {
+		let valve_count = channels / 4;
+
+		let sensor_names = (0..channels - valve_count)
+			.map(|index| format!("PT{index:03}"))
+			.collect::<Vec<_>>();
+
+		let valve_names = (0..valve_count)
+			.map(|index| format!("V{index:03}"))
+			.collect::<Vec<_>>();
+
+		let snapshots = (0..EXPORT_SNAPSHOTS)
+			.map(|step| (step as f64 / 10.0, vehicle_state(&sensor_names, &valve_names, step)))
+			.collect::<Vec<_>>();
+
+		let state = Arc::new(snapshots[0].1.clone());
+		let serialized = postcard::to_allocvec(&*state)?;
+
+		let packet = DecodedPacket {
+			sensor_readings: state.sensor_readings
+				.iter()
+				.map(|(name, reading)| (name.clone(), *reading))
+				.collect(),
+		};
+
+		let database = Database::volatile()?;
+		database.migrate()?;
+
+		Ok(Workload { sensor_names, valve_names, state, serialized, packet, snapshots, database })
+	}
+
+	/// Decodes a Postcard frame, as received from the flight computer.
+	pub fn decode(&self) -> postcard::Result<VehicleState> {
+		postcard::from_bytes(&self.serialized)
+	}
+
+	/// Merges a decoded packet into the vehicle state while a consumer holds the
+	/// previous snapshot, so the state is copied as it is for a live server.
+	pub fn merge(&self) -> Arc<VehicleState> {
+		let mut snapshot = self.state.clone();
+		self.packet.clone().merge_into(Arc::make_mut(&mut snapshot), NAMESPACE);
+		snapshot
+	}
+
+	/// Encodes and inserts a snapshot into the database, as the logger does for each vehicle state.
+	pub fn insert(&self) -> anyhow::Result<usize> {
+		let encoded = snapshot::encode(&self.state)?;
+
+		Ok(self.database.connection
+			.blocking_lock()
+			.execute("INSERT INTO VehicleSnapshots (vehicle_state, compressed) VALUES (?1, TRUE)", [&encoded])?)
+	}
+
+	/// Exports every snapshot as CSV.
+	pub fn export_csv(&self) -> String {
+		routes::make_csv(&self.sensor_names, &self.valve_names, &self.snapshots, &[], UnitSystem::Native)
+	}
+
+	/// Exports every snapshot as an HDF5 file at a path.
+	#[cfg(feature = "hdf5")]
+	pub fn export_hdf5(&self, path: &Path) -> hdf5::Result<()> {
+		routes::make_hdf5_file(&self.sensor_names, &self.valve_names, &self.snapshots, &BTreeMap::new(), &[], UnitSystem::Native, path)
+	}
+
+	/// Serializes the vehicle state as JSON, as it is forwarded to clients.
+	pub fn serialize_forwarded(&self) -> serde_json::Result<String> {
+		serde_json::to_string(&*self.state)
+	}
+}
+
+/// A vehicle state with every channel reported, varying with the step so that snapshots differ.
+fn vehicle_state(sensor_names: &[String], valve_names: &[String], step: usize) -> VehicleState {
+	let mut state = VehicleState::new();
+	let units = [Unit::Psi, Unit::Kelvin, Unit::Pounds, Unit::Volts, Unit::Amps];
+
+	for (index, name) in sensor_names.iter().enumerate() {
+		state.sensor_readings.insert(name.clone(), Measurement {
+			value: (index * 31 + step) as f64 * 0.137,
+			unit: units[index % units.len()],
+		});
+	}
+
+	for (index, name) in valve_names.iter().enumerate() {
+		let valve_state = [ValveState::Closed, ValveState::Open][(index + step / 50) % 2];
+
+		state.valve_states.insert(name.clone(), CompositeValveState {
+			commanded: valve_state,
+			actual: valve_state,
+		});
+	}
+
+	state
+}
@@ -0,0 +1,256 @@
+use common::comm::VehicleState;
+use std::{net::{Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, sync::Arc};
+use tokio::sync::{Mutex, Notify};
+
+use super::{
+	hazard::Confirmations,
+	standby,
+	storage,
+	CommandAuthority,
+	Config,
+	ContentVersions,
+	Database,
+	FrameRecorder,
+	MaintenanceMode,
+	Metrics,
+	Outbox,
+	RoleState,
+	SequenceLockout,
+	Server,
+	Shared,
+	SharedConfig,
+	SpectatorMode,
+	Storage,
+	Supervisor,
+};
+
+/// The addresses the server listens on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BindAddresses {
+	/// The address of the HTTP API, and of the gRPC service alongside it.
+	pub http: SocketAddr,
+
+	/// The address vehicle computers connect to in order to be sent commands.
+	pub flight: SocketAddr,
+
+	/// The address telemetry is received on, over both UDP and TCP.
+	pub telemetry: SocketAddr,
+}
+
+impl Default for BindAddresses {
+	fn default() -> Self {
+		BindAddresses {
+			http: (Ipv4Addr::UNSPECIFIED, 7200).into(),
+			flight: (Ipv4Addr::UNSPECIFIED, 5025).into(),
+			telemetry: (Ipv4Addr::UNSPECIFIED, 7201).into(),
+		}
+	}
+}
+
+/// The optional parts of the server, each of which runs a group of background tasks.
+///
+/// Every feature is enabled by default, and those which are also configured,
+/// such as standby replication, only run when both enabled and configured.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Features {
+	/// Connecting to the vehicle computers and receiving their telemetry, over the network or serial.
+	pub vehicle: bool,
+
+	/// Receiving data directly from SAM boards and from configured decoder sources.
+	pub ingest: bool,
+
+	/// Logging vehicle state to storage and keeping the database backed up, pruned, and compact.
+	pub logging: bool,
+
+	/// Pushing live data to InfluxDB and MQTT as configured.
+	pub integrations: bool,
+
+	/// Alert rules, anomaly detection, channel pairs, and capture rules.
+	pub monitoring: bool,
+
+	/// Following the configured primary as a standby.
+	pub standby: bool,
+
+	/// Advertising the server over mDNS as configured.
+	pub discovery: bool,
+
+	/// Reloading the configuration when the process receives `SIGHUP`.
+	pub reload_on_hangup: bool,
+}
+
+impl Default for Features {
+	fn default() -> Self {
+		Features {
+			vehicle: true,
+			ingest: true,
+			logging: true,
+			integrations: true,
+			monitoring: true,
+			standby: true,
+			discovery: true,
+			reload_on_hangup: true,
+		}
+	}
+}
+
+/// Constructs a [`Server`], so that it may be embedded in other binaries
+/// rather than only run by `servo serve`.
+///
+/// Unless told otherwise, the server keeps its database in memory, uses the
+/// default configuration, and listens on the usual ports.
+#[derive(Debug, Default)]
+pub struct ServerBuilder {
+	database: Option<PathBuf>,
+	config: Option<SharedConfig>,
+	storage: Option<Arc<dyn Storage>>,
+	addresses: BindAddresses,
+	features: Features,
+}
+
+impl ServerBuilder {
+	/// Keeps the database in the file at the given path rather than in memory.
+	pub fn database(mut self, path: impl AsRef<Path>) -> Self {
+		self.database = Some(path.as_ref().to_owned());
+		self
+	}
+
+	/// Uses the given configuration, which is only reloadable if it was loaded from a file.
+	pub fn config(mut self, config: impl Into<SharedConfig>) -> Self {
+		self.config = Some(config.into());
+		self
+	}
+
+	/// Stores logged vehicle snapshots in the given backend rather than the configured one.
+	pub fn storage(mut self, storage: Arc<dyn Storage>) -> Self {
+		self.storage = Some(storage);
+		self
+	}
+
+	/// Listens on the given addresses.
+	pub fn addresses(mut self, addresses: BindAddresses) -> Self {
+		self.addresses = addresses;
+		self
+	}
+
+	/// Serves the HTTP API on the given address.
+	pub fn http_address(mut self, address: SocketAddr) -> Self {
+		self.addresses.http = address;
+		self
+	}
+
+	/// Accepts vehicle computers on the given address.
+	pub fn flight_address(mut self, address: SocketAddr) -> Self {
+		self.addresses.flight = address;
+		self
+	}
+
+	/// Receives telemetry on the given address.
+	pub fn telemetry_address(mut self, address: SocketAddr) -> Self {
+		self.addresses.telemetry = address;
+		self
+	}
+
+	/// Runs only the given features.
+	pub fn features(mut self, features: Features) -> Self {
+		self.features = features;
+		self
+	}
+
+	/// Opens and migrates the database, opens storage, and takes the role
+	/// configured for the server, constructing it ready to be run.
+	///
+	/// Storage is not migrated until the server runs, since it may need a runtime to reach.
+	pub fn build(self) -> anyhow::Result<Server> {
+		let database = match &self.database {
+			Some(path) => Database::open(path)?,
+			None => Database::volatile()?,
+		};
+
+		database.migrate()?;
+
+		let config = self.config.unwrap_or_else(|| Config::default().into());
+
+		let storage = match self.storage {
+			Some(storage) => storage,
+			None => storage::open(&config.current().storage, &database)?,
+		};
+
+		let shared = Shared {
+			config: Arc::new(config),
+			metrics: Arc::new(Metrics::default()),
+			supervisor: Arc::new(Supervisor::default()),
+			lockout: Arc::new(SequenceLockout::default()),
+			confirmations: Arc::new(Confirmations::default()),
+			database,
+			storage,
+			addresses: self.addresses,
+			flight: Arc::new((Mutex::new(None), Notify::new())),
+			ground: Arc::new((Mutex::new(None), Notify::new())),
+			recorder: Arc::new(FrameRecorder::default()),
+			role: Arc::new(RoleState::default()),
+			authority: Arc::new(CommandAuthority::default()),
+			outbox: Arc::new(Outbox::default()),
+			versions: Arc::new(ContentVersions::default()),
+			maintenance: Arc::new(MaintenanceMode::default()),
+			spectator: Arc::new(SpectatorMode::default()),
+			vehicle: Arc::new((Mutex::new(Arc::new(VehicleState::new())), Notify::new())),
+		};
+
+		standby::initialize(&shared)?;
+
+		Ok(Server { shared, features: self.features, tasks: Arc::default() })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn built_servers_are_ready_to_run() {
+		let server = ServerBuilder::default()
+			.http_address((Ipv4Addr::LOCALHOST, 0).into())
+			.features(Features { vehicle: false, ..Features::default() })
+			.build()
+			.unwrap();
+
+		assert_eq!(server.shared.addresses.http, (Ipv4Addr::LOCALHOST, 0).into());
+		assert_eq!(server.shared.addresses.telemetry, BindAddresses::default().telemetry);
+		assert!(!server.features.vehicle && server.features.logging);
+		assert!(server.shared.role.is_primary());
+
+		// the database is migrated, so the server may be queried straight away
+		server.shared.database.connection
+			.blocking_lock()
+			.query_row("SELECT COUNT(*) FROM VehicleSnapshots", [], |row| row.get::<_, i64>(0))
+			.unwrap();
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn stopped_servers_release_their_ports() {
+		let free_port = || std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap();
+
+		let addresses = BindAddresses { http: free_port(), flight: free_port(), telemetry: free_port() };
+		let features = Features {
+			vehicle: true,
+			ingest: false,
+			logging: false,
+			integrations: false,
+			monitoring: false,
+			standby: false,
+			discovery: false,
+			reload_on_hangup: false,
+		};
+
+		let server = ServerBuilder::default().addresses(addresses).features(features).build().unwrap();
+		let shutdown = tokio::time::sleep(std::time::Duration::from_millis(500));
+
+		server.run(shutdown).await.unwrap();
+
+		// the supervised listeners have stopped along with the routes, so every port may be bound again
+		tokio::net::TcpListener::bind(addresses.http).await.unwrap();
+		tokio::net::TcpListener::bind(addresses.flight).await.unwrap();
+		tokio::net::TcpListener::bind(addresses.telemetry).await.unwrap();
+		tokio::net::UdpSocket::bind(addresses.telemetry).await.unwrap();
+	}
+}
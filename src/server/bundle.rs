@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+/// A helper file (Python module or data file) packaged alongside a sequence script.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PackagedFile {
+	/// The path of the file relative to the root of the bundle, using `/` as a separator.
+	pub path: String,
+
+	/// The Base64-encoded contents of the file.
+	pub contents: String,
+}
+
+/// Checks that a packaged file path is relative and cannot escape the bundle directory.
+pub fn validate_path(path: &str) -> Result<(), String> {
+	if path.is_empty() {
+		return Err("packaged file path must not be empty".to_owned());
+	}
+
+	if path.contains('\\') {
+		return Err(format!("packaged file path '{path}' must use '/' as a separator"));
+	}
+
+	let escapes = Path::new(path)
+		.components()
+		.any(|component| !matches!(component, Component::Normal(_)));
+
+	if escapes {
+		return Err(format!("packaged file path '{path}' must be relative and may not contain '.' or '..'"));
+	}
+
+	Ok(())
+}
+
+/// Packages a sequence script and its helper files into a single self-contained script.
+///
+/// The flight computer only understands single-script sequences, so the bundle is
+/// shipped as a script with a preamble that unpacks the helper files into a
+/// temporary directory and puts it on the import path before the sequence runs.
+/// The directory is also exposed to the sequence as `BUNDLE_DIR` for data files,
+/// and is removed once the sequence finishes, however it finishes.
+///
+/// Paths are embedded Base64-encoded like the contents, since Base64 needs no
+/// escaping in a Python string literal while an arbitrary path might.
+pub fn package_script(script: &str, files: &[PackagedFile]) -> String {
+	if files.is_empty() {
+		return script.to_owned();
+	}
+
+	let entries = files
+		.iter()
+		.map(|file| format!("\t('{}', '{}'),\n", base64::encode(&file.path), file.contents))
+		.collect::<String>();
+
+	// the script runs inside the try block, so each of its lines is indented into it,
+	// including those within its multiline strings.
+	let body = if script.trim().is_empty() {
+		"\tpass".to_owned()
+	} else {
+		script
+			.lines()
+			.map(|line| if line.is_empty() { String::new() } else { format!("\t{line}") })
+			.collect::<Vec<_>>()
+			.join("\n")
+	};
+
+	format!(
+"import base64 as __servo_base64, os as __servo_os, shutil as __servo_shutil, sys as __servo_sys, tempfile as __servo_tempfile
+BUNDLE_DIR = __servo_tempfile.mkdtemp(prefix='servo-bundle-')
+try:
+	for __servo_path, __servo_contents in [
+	{entries}]:
+		__servo_full_path = __servo_os.path.join(BUNDLE_DIR, __servo_base64.b64decode(__servo_path).decode())
+		__servo_os.makedirs(__servo_os.path.dirname(__servo_full_path), exist_ok=True)
+		with open(__servo_full_path, 'wb') as __servo_file:
+			__servo_file.write(__servo_base64.b64decode(__servo_contents))
+	__servo_sys.path.insert(0, BUNDLE_DIR)
+
+{body}
+finally:
+	__servo_shutil.rmtree(BUNDLE_DIR, ignore_errors=True)
+"
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rejects_escaping_paths() {
+		assert!(validate_path("helpers.py").is_ok());
+		assert!(validate_path("lib/valves.py").is_ok());
+		assert!(validate_path("").is_err());
+		assert!(validate_path("/etc/passwd").is_err());
+		assert!(validate_path("../flight.py").is_err());
+		assert!(validate_path("lib/../../flight.py").is_err());
+		assert!(validate_path("./helpers.py").is_err());
+		assert!(validate_path("lib\\helpers.py").is_err());
+	}
+
+	#[test]
+	fn scripts_without_files_are_unchanged() {
+		assert_eq!(package_script("BBV.open()", &[]), "BBV.open()");
+	}
+
+	#[test]
+	fn packaged_script_runs_original_script_before_cleaning_up() {
+		let files = vec![PackagedFile { path: "helpers.py".to_owned(), contents: base64::encode("X = 1") }];
+		let packaged = package_script("import helpers", &files);
+
+		assert!(packaged.contains(&format!("'{}'", base64::encode("helpers.py"))));
+		assert!(packaged.contains("\n\timport helpers\nfinally:\n"));
+		assert!(packaged.ends_with("rmtree(BUNDLE_DIR, ignore_errors=True)\n"));
+	}
+
+	#[test]
+	fn paths_are_not_embedded_as_written() {
+		let files = vec![PackagedFile { path: "data/\u{7f}'\".csv".to_owned(), contents: base64::encode("1,2") }];
+		let packaged = package_script("pass", &files);
+
+		assert!(!packaged.contains("data/"));
+		assert!(packaged.contains(&base64::encode("data/\u{7f}'\".csv")));
+	}
+}
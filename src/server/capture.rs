@@ -0,0 +1,238 @@
+use jeflog::{fail, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{future::Future, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+use tokio::time::MissedTickBehavior;
+
+use super::{config::CaptureRuleConfig, interlock::{self, Condition, SystemState}, Shared};
+
+/// How often capture rules are evaluated against the system state.
+const EVALUATION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A window of high interest marked by a capture rule, within which every snapshot is kept at full rate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaptureWindow {
+	/// The unique ID of the window, as stored in the `CaptureWindows` table.
+	pub capture_id: i64,
+
+	/// The name of the rule which marked the window.
+	pub rule: String,
+
+	/// The condition of the rule when it marked the window.
+	pub condition: String,
+
+	/// When the condition began to hold, as a Unix timestamp.
+	pub triggered_at: f64,
+
+	/// The start of the window, as a Unix timestamp.
+	pub started_at: f64,
+
+	/// The end of the window, as a Unix timestamp. While the condition still
+	/// holds, this is extended with each evaluation.
+	pub ended_at: f64,
+}
+
+/// A change to the capture windows of a rule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowChange {
+	/// A new window should be opened.
+	Open {
+		/// The start of the new window, as a Unix timestamp.
+		started_at: f64,
+
+		/// The end of the new window, as a Unix timestamp.
+		ended_at: f64,
+	},
+
+	/// The latest window should be extended to a new end.
+	Extend {
+		/// The new end of the window, as a Unix timestamp.
+		ended_at: f64,
+	},
+}
+
+/// Tracks the latest window of a capture rule, so that a condition which holds
+/// again before the window has ended extends it rather than opening another.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WindowTracker {
+	ended_at: Option<f64>,
+}
+
+impl WindowTracker {
+	/// Records whether the condition holds at a Unix timestamp, returning how the windows should change.
+	pub fn update(&mut self, holds: bool, now: f64, pre_seconds: f64, post_seconds: f64) -> Option<WindowChange> {
+		if !holds {
+			return None;
+		}
+
+		let started_at = now - pre_seconds.max(0.0);
+		let ended_at = now + post_seconds.max(0.0);
+		let change = match self.ended_at {
+			Some(previous) if started_at <= previous => WindowChange::Extend { ended_at: ended_at.max(previous) },
+			_ => WindowChange::Open { started_at, ended_at },
+		};
+
+		self.ended_at = Some(ended_at.max(self.ended_at.unwrap_or(ended_at)));
+		Some(change)
+	}
+}
+
+/// Lists the capture windows overlapping a time range, optionally only those marked by one rule.
+pub fn list(connection: &Connection, from: Option<f64>, to: Option<f64>, rule: Option<&str>) -> rusqlite::Result<Vec<CaptureWindow>> {
+	connection
+		.prepare("
+			SELECT capture_id, rule, condition, triggered_at, started_at, ended_at
+			FROM CaptureWindows
+			WHERE (?1 IS NULL OR ended_at >= ?1)
+				AND (?2 IS NULL OR started_at <= ?2)
+				AND (?3 IS NULL OR rule = ?3)
+			ORDER BY started_at
+		")?
+		.query_map(params![from, to, rule], |row| {
+			Ok(CaptureWindow {
+				capture_id: row.get(0)?,
+				rule: row.get(1)?,
+				condition: row.get(2)?,
+				triggered_at: row.get(3)?,
+				started_at: row.get(4)?,
+				ended_at: row.get(5)?,
+			})
+		})?
+		.collect()
+}
+
+/// Whether a snapshot recorded at a Unix timestamp lies within a capture window,
+/// in which case it must be kept at full rate by any retention policy.
+pub fn is_captured(connection: &Connection, recorded_at: f64) -> rusqlite::Result<bool> {
+	connection
+		.query_row(
+			"SELECT 1 FROM CaptureWindows WHERE started_at <= ?1 AND ended_at >= ?1 LIMIT 1",
+			[recorded_at],
+			|_| Ok(()),
+		)
+		.optional()
+		.map(|found| found.is_some())
+}
+
+/// Continuously evaluates the configured capture rules, marking a capture window each time one holds.
+///
+/// Rules are rebuilt whenever the configuration is reloaded. Rules whose
+/// conditions cannot be parsed are reported once and skipped.
+pub fn monitor(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut config = shared.config.current();
+		let mut rules = build_rules(&config.captures, Vec::new());
+
+		let mut interval = tokio::time::interval(EVALUATION_INTERVAL);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			interval.tick().await;
+
+			let current = shared.config.current();
+
+			if !Arc::ptr_eq(&current, &config) {
+				config = current;
+				rules = build_rules(&config.captures, rules);
+			}
+
+			if rules.is_empty() {
+				continue;
+			}
+
+			let active_configuration = shared.database.connection
+				.lock()
+				.await
+				.query_row("SELECT configuration_id FROM NodeMappings WHERE active = TRUE LIMIT 1", [], |row| row.get::<_, String>(0))
+				.optional()
+				.unwrap_or(None);
+
+			let vehicle = shared.vehicle.0.lock().await.clone();
+
+			let state = SystemState {
+				vehicle: &vehicle,
+				flight_link_healthy: interlock::flight_link_healthy(&shared).await,
+				active_configuration: active_configuration.as_deref(),
+			};
+
+			let now = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map_or(0.0, |duration| duration.as_secs_f64());
+
+			let database = shared.database.connection.lock().await;
+
+			for (rule, condition, tracker) in &mut rules {
+				let holds = condition.check(&state).is_ok();
+
+				let result = match tracker.update(holds, now, rule.pre_seconds, rule.post_seconds) {
+					Some(WindowChange::Open { started_at, ended_at }) => database.execute(
+						"INSERT INTO CaptureWindows (rule, condition, triggered_at, started_at, ended_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+						params![rule.name, rule.condition, now, started_at, ended_at],
+					),
+					Some(WindowChange::Extend { ended_at }) => database.execute(
+						"UPDATE CaptureWindows SET ended_at = ?2 WHERE capture_id = (SELECT MAX(capture_id) FROM CaptureWindows WHERE rule = ?1)",
+						params![rule.name, ended_at],
+					),
+					None => continue,
+				};
+
+				if let Err(error) = result {
+					warn!("Failed to record capture window of rule '{}': {error}", rule.name);
+				}
+			}
+		}
+	}
+}
+
+/// Parses the configured capture rules, keeping the tracker of any previous
+/// rule with the same name so that a reload extends its open window.
+fn build_rules(
+	config: &[CaptureRuleConfig],
+	mut previous: Vec<(CaptureRuleConfig, Condition, WindowTracker)>,
+) -> Vec<(CaptureRuleConfig, Condition, WindowTracker)> {
+	let mut rules = Vec::new();
+
+	for rule in config {
+		let condition = match rule.condition.parse::<Condition>() {
+			Ok(condition) => condition,
+			Err(error) => {
+				fail!("Capture rule '{}' has an invalid condition: {error}", rule.name);
+				continue;
+			},
+		};
+
+		let tracker = previous
+			.iter()
+			.position(|(previous, _, _)| previous.name == rule.name)
+			.map(|index| previous.swap_remove(index).2)
+			.unwrap_or_default();
+
+		rules.push((rule.clone(), condition, tracker));
+	}
+
+	rules
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn windows_extend_while_condition_holds() {
+		let mut tracker = WindowTracker::default();
+
+		assert_eq!(tracker.update(false, 100.0, 10.0, 30.0), None);
+		assert_eq!(tracker.update(true, 100.0, 10.0, 30.0), Some(WindowChange::Open { started_at: 90.0, ended_at: 130.0 }));
+		assert_eq!(tracker.update(true, 101.0, 10.0, 30.0), Some(WindowChange::Extend { ended_at: 131.0 }));
+		assert_eq!(tracker.update(false, 102.0, 10.0, 30.0), None);
+
+		// holding again before the window ends, including its lead-in, extends it
+		assert_eq!(tracker.update(true, 140.0, 10.0, 30.0), Some(WindowChange::Extend { ended_at: 170.0 }));
+		assert_eq!(tracker.update(false, 141.0, 10.0, 30.0), None);
+
+		// but holding again after it has ended opens another
+		assert_eq!(tracker.update(true, 200.0, 10.0, 30.0), Some(WindowChange::Open { started_at: 190.0, ended_at: 230.0 }));
+	}
+}
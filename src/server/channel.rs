@@ -0,0 +1,169 @@
+use common::comm::{Unit, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::config::{ChannelConfig, ValveReadingConfig};
+
+/// Whether a channel is a sensor or a valve.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+	/// A sensor, reporting measurements.
+	Sensor,
+
+	/// A valve, reporting its commanded and actual states.
+	Valve,
+
+	/// A channel which is configured but has not been reported.
+	Unreported,
+}
+
+/// A channel in the catalog, along with how it is presented to operators.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChannelInfo {
+	/// The name the channel is reported under, such as `WTPT`.
+	pub name: String,
+
+	/// Whether the channel is a sensor or a valve.
+	pub kind: ChannelKind,
+
+	/// The human-friendly name of the channel, or its reported name if none is configured.
+	pub display_name: String,
+
+	/// The group the channel belongs to, if any.
+	pub group: Option<String>,
+
+	/// The unit of the latest reading of a sensor.
+	pub unit: Option<Unit>,
+
+	/// The valve a sensor is the voltage or current reading of, if any.
+	pub valve: Option<String>,
+
+	/// Which reading of its valve a sensor is, if it is one.
+	pub valve_reading: Option<ValveReading>,
+}
+
+/// Which reading of a valve a sensor is.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValveReading {
+	/// The voltage across the valve.
+	Voltage,
+
+	/// The current through the valve.
+	Current,
+}
+
+/// The name a channel is shown under: its display name if configured, otherwise its reported name.
+pub fn label<'a>(channels: &'a BTreeMap<String, ChannelConfig>, name: &'a str) -> &'a str {
+	channels
+		.get(name)
+		.and_then(|channel| channel.display_name.as_deref())
+		.unwrap_or(name)
+}
+
+/// The valve a reading is named after by one of the configured suffixes, along
+/// with which reading of it it is, if any.
+///
+/// Only names of which `is_valve` holds are taken to be valves, so a sensor such
+/// as `BATT_V` with no valve `BATT` stays a sensor.
+pub fn valve_reading<'a>(config: &ValveReadingConfig, name: &'a str, is_valve: impl Fn(&str) -> bool) -> Option<(&'a str, ValveReading)> {
+	let voltages = config.voltage_suffixes.iter().map(|suffix| (suffix, ValveReading::Voltage));
+	let currents = config.current_suffixes.iter().map(|suffix| (suffix, ValveReading::Current));
+
+	voltages
+		.chain(currents)
+		.filter_map(|(suffix, reading)| Some((name.strip_suffix(suffix.as_str())?, reading)))
+		.find(|(valve, _)| is_valve(valve))
+}
+
+/// Lists every channel which has been reported or configured, ordered by group
+/// and then by name, with ungrouped channels last.
+///
+/// The voltage and current readings of valves are listed as sensors, along with the valve each belongs to.
+pub fn catalog(channels: &BTreeMap<String, ChannelConfig>, valve_readings: &ValveReadingConfig, vehicle: &VehicleState) -> Vec<ChannelInfo> {
+	let mut catalog = BTreeMap::new();
+
+	let reported = vehicle.sensor_readings
+		.iter()
+		.map(|(name, reading)| (name, ChannelKind::Sensor, Some(reading.unit)))
+		.chain(vehicle.valve_states.keys().map(|name| (name, ChannelKind::Valve, None)))
+		.chain(channels.keys().map(|name| (name, ChannelKind::Unreported, None)));
+
+	for (name, kind, unit) in reported {
+		if catalog.contains_key(name) {
+			continue;
+		}
+
+		let config = channels.get(name);
+		let reading = match kind {
+			ChannelKind::Sensor => valve_reading(valve_readings, name, |valve| vehicle.valve_states.contains_key(valve)),
+			_ => None,
+		};
+
+		catalog.insert(name.clone(), ChannelInfo {
+			name: name.clone(),
+			kind,
+			display_name: label(channels, name).to_owned(),
+			group: config.and_then(|config| config.group.clone()),
+			unit,
+			valve: reading.map(|(valve, _)| valve.to_owned()),
+			valve_reading: reading.map(|(_, reading)| reading),
+		});
+	}
+
+	let mut catalog = catalog.into_values().collect::<Vec<_>>();
+	catalog.sort_by(|a, b| {
+		(a.group.is_none(), &a.group, &a.name).cmp(&(b.group.is_none(), &b.group, &b.name))
+	});
+	catalog
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{CompositeValveState, Measurement, ValveState};
+	use super::*;
+
+	#[test]
+	fn catalog_merges_reported_and_configured_channels() {
+		let mut vehicle = VehicleState::new();
+		vehicle.sensor_readings.insert("WTPT".to_owned(), Measurement { value: 1.0, unit: Unit::Psi });
+		vehicle.sensor_readings.insert("BATT".to_owned(), Measurement { value: 12.0, unit: Unit::Volts });
+		vehicle.sensor_readings.insert("FMV.I".to_owned(), Measurement { value: 0.1, unit: Unit::Amps });
+		vehicle.valve_states.insert("FMV".to_owned(), CompositeValveState {
+			commanded: ValveState::Closed,
+			actual: ValveState::Closed,
+		});
+
+		let fuel = |display_name: &str| ChannelConfig {
+			display_name: Some(display_name.to_owned()),
+			group: Some("Fuel".to_owned()),
+			..ChannelConfig::default()
+		};
+
+		let channels = BTreeMap::from([
+			("WTPT".to_owned(), fuel("Fuel Tank Pressure")),
+			("FMV".to_owned(), fuel("Fuel Main Valve")),
+			("FTC1".to_owned(), fuel("Fuel Tank Temperature")),
+		]);
+
+		let valve_readings = ValveReadingConfig {
+			current_suffixes: vec!["_I".to_owned(), ".I".to_owned()],
+			..ValveReadingConfig::default()
+		};
+
+		let catalog = catalog(&channels, &valve_readings, &vehicle);
+		let names = catalog.iter().map(|channel| channel.name.as_str()).collect::<Vec<_>>();
+		assert_eq!(names, ["FMV", "FTC1", "WTPT", "BATT", "FMV.I"]);
+
+		assert_eq!(catalog[4].valve.as_deref(), Some("FMV"));
+		assert_eq!(catalog[4].valve_reading, Some(ValveReading::Current));
+		assert_eq!(catalog[3].valve, None);
+
+		assert_eq!(catalog[0].kind, ChannelKind::Valve);
+		assert_eq!(catalog[1].kind, ChannelKind::Unreported);
+		assert_eq!(catalog[2].display_name, "Fuel Tank Pressure");
+		assert_eq!(catalog[3].display_name, "BATT");
+		assert_eq!(label(&channels, "BATT"), "BATT");
+	}
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::{SystemTime, UNIX_EPOCH}};
+
+/// The header through which clients report the time on their clock when they
+/// sent a request, as a Unix timestamp in seconds.
+pub const CLIENT_TIME_HEADER: &str = "x-client-time";
+
+/// The number of seconds after which a participant which has stopped
+/// reporting its clock is forgotten, so that a closed GUI is not flagged forever.
+const STALE_SECONDS: f64 = 300.0;
+
+/// The participant name under which the flight computer's clock is tracked.
+pub const FLIGHT_COMPUTER: &str = "flight computer";
+
+/// The skew of a participant's clock from servo's.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ClockSkew {
+	/// The number of seconds the participant's clock was ahead of servo's when
+	/// it last reported, or behind if negative. Network latency is included,
+	/// which makes the skew slightly more negative than it truly is.
+	pub skew_seconds: f64,
+
+	/// When the participant last reported its clock, as a Unix timestamp by servo's clock.
+	pub reported_at: f64,
+}
+
+impl ClockSkew {
+	/// Whether the skew exceeds the threshold, if there is one.
+	pub fn drifted(&self, threshold_seconds: Option<f64>) -> bool {
+		threshold_seconds.is_some_and(|threshold| self.skew_seconds.abs() > threshold)
+	}
+
+	/// Describes the skew relative to servo's clock, such as "2.50 s ahead of servo".
+	pub fn describe(&self) -> String {
+		let direction = if self.skew_seconds < 0.0 { "behind" } else { "ahead of" };
+		format!("{:.2} s {direction} servo", self.skew_seconds.abs())
+	}
+}
+
+/// The clock skews of the participants which report their clocks, such as GUI
+/// clients and the flight computer.
+#[derive(Debug, Default)]
+pub struct ClockSkews {
+	participants: BTreeMap<String, ClockSkew>,
+}
+
+impl ClockSkews {
+	/// Records the time a participant reported, which servo received at `now`.
+	pub fn record(&mut self, participant: &str, reported_time: f64, now: f64) {
+		if !reported_time.is_finite() {
+			return;
+		}
+
+		self.participants.insert(participant.to_owned(), ClockSkew {
+			skew_seconds: reported_time - now,
+			reported_at: now,
+		});
+	}
+
+	/// The skews of the participants which have reported recently, forgetting the rest.
+	pub fn current(&mut self, now: f64) -> BTreeMap<String, ClockSkew> {
+		self.participants.retain(|_, skew| now - skew.reported_at < STALE_SECONDS);
+		self.participants.clone()
+	}
+}
+
+/// The current time by servo's clock, as a Unix timestamp in seconds.
+pub fn now() -> f64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn skews_are_forgotten_once_stale() {
+		let mut skews = ClockSkews::default();
+
+		skews.record("gui@10.0.0.5", 1000.0, 1002.5);
+		skews.record(FLIGHT_COMPUTER, 1300.0, 1200.0);
+		skews.record("broken", f64::NAN, 1200.0);
+
+		let current = skews.current(1250.0);
+		assert_eq!(current.len(), 2);
+		assert_eq!(current["gui@10.0.0.5"].skew_seconds, -2.5);
+		assert_eq!(current[FLIGHT_COMPUTER].skew_seconds, 100.0);
+
+		let current = skews.current(1002.5 + STALE_SECONDS);
+		assert_eq!(current.keys().collect::<Vec<_>>(), [FLIGHT_COMPUTER]);
+	}
+}
@@ -0,0 +1,265 @@
+use common::comm::ValveState;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, io};
+
+use super::{hazard::HazardLevel, telemetry::MAX_FRAME_SIZE};
+
+/// The version of the framing, sent as the first byte of every frame so that
+/// either side can tell when the other frames messages differently.
+///
+/// Version 2 added the kind of message to the header.
+pub const FRAMING_VERSION: u8 = 2;
+
+/// The size of the header preceding each message: the version, the kind of
+/// message, and the length of the message as a big-endian `u32`.
+pub const HEADER_SIZE: usize = 6;
+
+/// The first version of the framing, which is still decoded for computers
+/// that have not been updated. Its header has no kind, since every message
+/// was a control message.
+pub const LEGACY_FRAMING_VERSION: u8 = 1;
+
+/// The size of the header of a frame of the first version: the version and
+/// the length of the message as a big-endian `u32`.
+pub const LEGACY_HEADER_SIZE: usize = 5;
+
+/// The kind of a Postcard-serialized `FlightControlMessage`.
+pub const CONTROL_MESSAGE: u8 = 0;
+
+/// The kind of a Postcard-serialized [`ChannelMetadataMessage`].
+pub const CHANNEL_METADATA: u8 = 1;
+
+/// The kind of a Postcard-serialized [`InfoQuery`], sent by servo to ask the
+/// computer about its software.
+pub const INFO_QUERY: u8 = 2;
+
+/// The kind of a Postcard-serialized [`InfoReport`], sent by the computer in
+/// answer to an [`InfoQuery`].
+pub const INFO_REPORT: u8 = 3;
+
+/// The version of the layout of [`ChannelMetadataMessage`], which is bumped
+/// whenever a field is added, removed, or reordered.
+pub const METADATA_FORMAT_VERSION: u16 = 1;
+
+/// What servo knows about a channel beyond its mapping.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelMetadata {
+	/// The name the channel is shown to operators under, if it has one.
+	pub display_name: Option<String>,
+
+	/// How hazardous it is to change the channel.
+	pub hazard_level: HazardLevel,
+
+	/// The state a valve is left in when safed, which is its unpowered state,
+	/// or `None` for channels which are not valves.
+	pub safing_state: Option<ValveState>,
+}
+
+/// The metadata of every channel of the active configuration mapped to a
+/// computer, sent alongside its mappings so that its autonomous safing logic
+/// works from the same source of truth as servo.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelMetadataMessage {
+	/// The version of this layout, which is always [`METADATA_FORMAT_VERSION`]
+	/// when sent, and must be checked before reading anything after it.
+	pub format_version: u16,
+
+	/// The metadata of each channel, keyed by its text ID.
+	pub channels: BTreeMap<String, ChannelMetadata>,
+}
+
+/// Asks a computer for its software information and the values of its runtime parameters.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct InfoQuery {
+	/// The runtime parameters whose values are reported, or all of them if empty.
+	pub parameters: Vec<String>,
+}
+
+/// The value of a runtime parameter of the flight software.
+///
+/// Postcard cannot deserialize self-describing values, so parameters are one
+/// of a fixed set of types rather than arbitrary JSON.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ParameterValue {
+	/// A flag which is either on or off.
+	Bool(bool),
+
+	/// A whole number.
+	Integer(i64),
+
+	/// A number which may have a fractional part.
+	Float(f64),
+
+	/// Anything else, as text.
+	Text(String),
+}
+
+/// A computer's answer to an [`InfoQuery`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct InfoReport {
+	/// The version of the flight software.
+	pub version: String,
+
+	/// The hash of the commit the flight software was built from.
+	pub build_hash: String,
+
+	/// The number of seconds the flight software had been running when it answered.
+	pub uptime_seconds: f64,
+
+	/// The time on the computer's clock when it answered, as a Unix timestamp.
+	pub reported_at: f64,
+
+	/// The values of the runtime parameters which were asked for.
+	pub parameters: BTreeMap<String, ParameterValue>,
+}
+
+/// Frames a message of a kind to be sent over the control link.
+///
+/// The header and message are returned as one buffer so that they are written
+/// together, rather than leaving a header without its message if a write fails.
+pub fn encode(kind: u8, message: &[u8]) -> io::Result<Vec<u8>> {
+	if message.len() > MAX_FRAME_SIZE {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("message of {} bytes exceeds the maximum of {MAX_FRAME_SIZE}", message.len()),
+		));
+	}
+
+	let mut frame = Vec::with_capacity(HEADER_SIZE + message.len());
+	frame.push(FRAMING_VERSION);
+	frame.push(kind);
+	frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+	frame.extend_from_slice(message);
+
+	Ok(frame)
+}
+
+/// Splits the bytes read from the control link back into the messages they frame,
+/// however the reads happen to divide them.
+#[derive(Clone, Debug, Default)]
+pub struct FrameDecoder {
+	buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+	/// Adds bytes read from the link.
+	pub fn push(&mut self, bytes: &[u8]) {
+		self.buffer.extend_from_slice(bytes);
+	}
+
+	/// Takes the next whole message and its kind, if one has arrived.
+	///
+	/// Frames of the first version are taken as control messages. A frame of
+	/// an unknown version or of an impossible length is an error, after which
+	/// the link cannot be trusted to be in step and should be closed. A message
+	/// of an unknown kind is returned all the same, to be skipped.
+	pub fn next_message(&mut self) -> io::Result<Option<(u8, Vec<u8>)>> {
+		let Some(&version) = self.buffer.first() else {
+			return Ok(None);
+		};
+
+		let header_size = match version {
+			FRAMING_VERSION => HEADER_SIZE,
+			LEGACY_FRAMING_VERSION => LEGACY_HEADER_SIZE,
+			_ => return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("frame has version {version}, but version {LEGACY_FRAMING_VERSION} or {FRAMING_VERSION} is expected"),
+			)),
+		};
+
+		let Some(header) = self.buffer.get(..header_size) else {
+			return Ok(None);
+		};
+
+		let (kind, length) = match version {
+			FRAMING_VERSION => (header[1], &header[2..]),
+			_ => (CONTROL_MESSAGE, &header[1..]),
+		};
+
+		let size = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+
+		if size > MAX_FRAME_SIZE {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("frame of {size} bytes exceeds the maximum of {MAX_FRAME_SIZE}"),
+			));
+		}
+
+		if self.buffer.len() < header_size + size {
+			return Ok(None);
+		}
+
+		let message = self.buffer[header_size..header_size + size].to_vec();
+		self.buffer.drain(..header_size + size);
+
+		Ok(Some((kind, message)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn messages_survive_arbitrary_reads() {
+		let metadata = ChannelMetadataMessage {
+			format_version: METADATA_FORMAT_VERSION,
+			channels: BTreeMap::from([(
+				"BBV".to_owned(),
+				ChannelMetadata {
+					display_name: Some("Bang-Bang Valve".to_owned()),
+					hazard_level: HazardLevel::Hazardous,
+					safing_state: Some(ValveState::Closed),
+				},
+			)]),
+		};
+
+		let mut stream = encode(CONTROL_MESSAGE, b"mappings").unwrap();
+		stream.extend(encode(CONTROL_MESSAGE, b"").unwrap());
+		stream.extend(encode(CHANNEL_METADATA, &postcard::to_allocvec(&metadata).unwrap()).unwrap());
+
+		let mut decoder = FrameDecoder::default();
+		let mut messages = Vec::new();
+
+		// one byte at a time is the worst a read can split them
+		for byte in stream {
+			decoder.push(&[byte]);
+
+			while let Some(message) = decoder.next_message().unwrap() {
+				messages.push(message);
+			}
+		}
+
+		assert_eq!(messages[..2], [(CONTROL_MESSAGE, b"mappings".to_vec()), (CONTROL_MESSAGE, Vec::new())]);
+		assert_eq!(messages[2].0, CHANNEL_METADATA);
+		assert_eq!(postcard::from_bytes::<ChannelMetadataMessage>(&messages[2].1).unwrap(), metadata);
+
+		decoder.push(&[FRAMING_VERSION + 1, CONTROL_MESSAGE, 0, 0, 0, 0]);
+		assert!(decoder.next_message().is_err());
+	}
+
+	#[test]
+	fn first_version_frames_are_control_messages() {
+		let mut stream = vec![LEGACY_FRAMING_VERSION, 0, 0, 0, 8];
+		stream.extend_from_slice(b"mappings");
+		stream.extend(encode(CHANNEL_METADATA, b"metadata").unwrap());
+		stream.extend([LEGACY_FRAMING_VERSION, 0, 0, 0, 0]);
+
+		let mut decoder = FrameDecoder::default();
+		let mut messages = Vec::new();
+
+		for byte in stream {
+			decoder.push(&[byte]);
+
+			while let Some(message) = decoder.next_message().unwrap() {
+				messages.push(message);
+			}
+		}
+
+		assert_eq!(messages, [
+			(CONTROL_MESSAGE, b"mappings".to_vec()),
+			(CHANNEL_METADATA, b"metadata".to_vec()),
+			(CONTROL_MESSAGE, Vec::new()),
+		]);
+	}
+}
@@ -0,0 +1,157 @@
+use axum::{
+	async_trait,
+	extract::FromRequestParts,
+	http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+	response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+	collections::HashMap,
+	convert::Infallible,
+	sync::{Mutex, PoisonError},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The validators a client sent with a GET, through which it asks to only be
+/// sent the resource if it changed since the copy the client already has.
+#[derive(Clone, Debug, Default)]
+pub struct Preconditions {
+	/// The entity tags of the copies the client has, from `If-None-Match`.
+	if_none_match: Option<String>,
+
+	/// When the copy the client has was last modified, from `If-Modified-Since`.
+	if_modified_since: Option<SystemTime>,
+}
+
+impl Preconditions {
+	/// Reads the validators from the headers of a request, ignoring any which are malformed.
+	pub fn from_headers(headers: &HeaderMap) -> Self {
+		let header = |name| headers.get(name).and_then(|value: &HeaderValue| value.to_str().ok());
+
+		Preconditions {
+			if_none_match: header(header::IF_NONE_MATCH).map(str::to_owned),
+			if_modified_since: header(header::IF_MODIFIED_SINCE).and_then(|date| httpdate::parse_http_date(date).ok()),
+		}
+	}
+
+	/// Whether the copy the client has is current, given the entity tag and
+	/// modification time of the resource.
+	///
+	/// As in RFC 9110, `If-Modified-Since` is only considered when there is no `If-None-Match`.
+	pub fn not_modified(&self, etag: &str, last_modified: SystemTime) -> bool {
+		if let Some(tags) = &self.if_none_match {
+			// tags are compared weakly, since a GET may be sent either kind
+			return tags
+				.split(',')
+				.map(|tag| tag.trim().trim_start_matches("W/"))
+				.any(|tag| tag == "*" || tag == etag);
+		}
+
+		// HTTP dates are only precise to the second
+		self.if_modified_since.is_some_and(|since| whole_seconds(last_modified) <= whole_seconds(since))
+	}
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Preconditions {
+	type Rejection = Infallible;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		Ok(Preconditions::from_headers(&parts.headers))
+	}
+}
+
+/// The entity tags of resources served conditionally and when each was first
+/// seen, which stands in for when the resource was last modified since the
+/// tables behind them do not record it.
+#[derive(Debug, Default)]
+pub struct ContentVersions {
+	seen: Mutex<HashMap<&'static str, (String, SystemTime)>>,
+}
+
+impl ContentVersions {
+	/// The time a resource was last modified, as far as this server knows, given its current entity tag.
+	pub fn last_modified(&self, resource: &'static str, etag: &str) -> SystemTime {
+		let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+
+		match seen.get(resource) {
+			Some((seen_etag, since)) if seen_etag == etag => *since,
+			_ => {
+				let now = SystemTime::now();
+				seen.insert(resource, (etag.to_owned(), now));
+				now
+			},
+		}
+	}
+
+	/// Responds to a GET with a resource serialized as JSON, or with `304 Not
+	/// Modified` and no body if the client's copy is current.
+	///
+	/// The entity tag is a hash of the JSON, so a resource which is rebuilt the
+	/// same way each time keeps its tag however often it is requested.
+	pub fn respond(&self, resource: &'static str, preconditions: &Preconditions, value: &impl Serialize) -> Response {
+		let json = match serde_json::to_vec(value) {
+			Ok(json) => json,
+			Err(error) => return super::error::internal(error).into_response(),
+		};
+
+		let digest = Sha256::digest(&json);
+		let etag = format!("\"{}\"", digest[..16].iter().map(|byte| format!("{byte:02x}")).collect::<String>());
+		let last_modified = self.last_modified(resource, &etag);
+
+		let headers = [
+			(header::ETAG, etag.clone()),
+			(header::LAST_MODIFIED, httpdate::fmt_http_date(last_modified)),
+			// clients may keep a copy, but must revalidate it before each use
+			(header::CACHE_CONTROL, "no-cache".to_owned()),
+		];
+
+		if preconditions.not_modified(&etag, last_modified) {
+			return (StatusCode::NOT_MODIFIED, headers).into_response();
+		}
+
+		(headers, [(header::CONTENT_TYPE, "application/json")], json).into_response()
+	}
+}
+
+/// The whole seconds since the Unix epoch of a time, as precise as an HTTP date.
+fn whole_seconds(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::*;
+
+	#[test]
+	fn unchanged_resources_are_not_resent() {
+		let versions = ContentVersions::default();
+		let response = versions.respond("/operator/sequence", &Preconditions::default(), &["fill", "vent"]);
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let etag = response.headers()[header::ETAG].clone();
+		let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+
+		let mut headers = HeaderMap::new();
+		headers.insert(header::IF_NONE_MATCH, etag.clone());
+		let preconditions = Preconditions::from_headers(&headers);
+
+		let response = versions.respond("/operator/sequence", &preconditions, &["fill", "vent"]);
+		assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+		assert_eq!(response.headers()[header::ETAG], etag);
+
+		let response = versions.respond("/operator/sequence", &preconditions, &["fill"]);
+		assert_eq!(response.status(), StatusCode::OK);
+
+		// the modification time is used only when no entity tag is sent
+		let mut headers = HeaderMap::new();
+		headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+		let preconditions = Preconditions::from_headers(&headers);
+
+		let since = httpdate::parse_http_date(headers[header::IF_MODIFIED_SINCE].to_str().unwrap()).unwrap();
+		assert!(preconditions.not_modified("\"other\"", since));
+		assert!(!preconditions.not_modified("\"other\"", since + Duration::from_secs(2)));
+	}
+}
@@ -0,0 +1,132 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{self, AllowOrigin, CorsLayer};
+
+use super::{clock, config::CorsConfig, trace};
+
+/// Builds the CORS layer applied to every route from the configured allowed origins.
+///
+/// If the allowed origins contain `*`, any origin is allowed but credentials are
+/// not, since browsers refuse credentialed requests to wildcard origins anyway.
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+	if config.allowed_origins.iter().any(|origin| origin == "*") {
+		return CorsLayer::new()
+			.allow_methods(cors::Any)
+			.allow_headers(cors::Any)
+			.allow_origin(cors::Any)
+			.expose_headers(exposed_headers());
+	}
+
+	let allowed = config.allowed_origins.clone();
+
+	CorsLayer::new()
+		.allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+		.allow_headers([
+			header::AUTHORIZATION,
+			header::CONTENT_TYPE,
+			HeaderName::from_static(clock::CLIENT_TIME_HEADER),
+			HeaderName::from_static(trace::REQUEST_ID_HEADER),
+			header::IF_NONE_MATCH,
+			header::IF_MODIFIED_SINCE,
+		])
+		.expose_headers(exposed_headers())
+		.allow_credentials(true)
+		.allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+			origin
+				.to_str()
+				.is_ok_and(|origin| is_allowed(origin, &allowed))
+		}))
+}
+
+/// The response headers a cross-origin GUI may read, which include the
+/// validators it sends back to make conditional requests.
+fn exposed_headers() -> [HeaderName; 3] {
+	[HeaderName::from_static(trace::REQUEST_ID_HEADER), header::ETAG, header::LAST_MODIFIED]
+}
+
+/// Checks whether an `Origin` header value is permitted by a list of allowed origins.
+///
+/// Entries containing a scheme (such as `http://gui-01.local:3000`) must match the
+/// origin exactly, while bare hostnames (such as `gui-01.local`) match that host on
+/// any scheme or port.
+pub fn is_allowed(origin: &str, allowed: &[String]) -> bool {
+	let host = origin_host(origin);
+
+	allowed.iter().any(|entry| {
+		if entry.contains("://") {
+			entry.eq_ignore_ascii_case(origin)
+		} else {
+			host.is_some_and(|host| entry.eq_ignore_ascii_case(host))
+		}
+	})
+}
+
+/// Extracts the hostname from an origin of the form `scheme://host[:port]`.
+fn origin_host(origin: &str) -> Option<&str> {
+	let (_, authority) = origin.split_once("://")?;
+	let host = authority.split([':', '/']).next()?;
+
+	(!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+	use axum::{body::Body, http::Request, routing::get, Router};
+	use tower::ServiceExt;
+	use super::*;
+
+	#[test]
+	fn bare_hostnames_match_any_scheme_and_port() {
+		let allowed = vec!["gui-01.local".to_owned()];
+
+		assert!(is_allowed("http://gui-01.local", &allowed));
+		assert!(is_allowed("http://gui-01.local:5173", &allowed));
+		assert!(is_allowed("tauri://GUI-01.local", &allowed));
+		assert!(!is_allowed("http://gui-02.local", &allowed));
+		assert!(!is_allowed("http://gui-01.local.evil.com", &allowed));
+		assert!(!is_allowed("null", &allowed));
+	}
+
+	#[test]
+	fn full_origins_match_exactly() {
+		let allowed = vec!["http://localhost:1420".to_owned()];
+
+		assert!(is_allowed("http://localhost:1420", &allowed));
+		assert!(!is_allowed("http://localhost:3000", &allowed));
+		assert!(!is_allowed("https://localhost:1420", &allowed));
+	}
+
+	#[tokio::test]
+	async fn conditional_requests_are_allowed_across_origins() {
+		let config = CorsConfig { allowed_origins: vec!["gui-01.local".to_owned()] };
+		let app = Router::new()
+			.route("/admin/export-presets", get(|| async { "[]" }))
+			.layer(layer(&config));
+
+		let preflight = Request::builder()
+			.method(Method::OPTIONS)
+			.uri("/admin/export-presets")
+			.header(header::ORIGIN, "http://gui-01.local:5173")
+			.header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+			.header(header::ACCESS_CONTROL_REQUEST_HEADERS, "if-none-match,if-modified-since")
+			.body(Body::empty())
+			.unwrap();
+
+		let response = app.clone().oneshot(preflight).await.unwrap();
+		let allowed = response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap();
+
+		assert!(allowed.contains("if-none-match"));
+		assert!(allowed.contains("if-modified-since"));
+
+		let request = Request::builder()
+			.uri("/admin/export-presets")
+			.header(header::ORIGIN, "http://gui-01.local:5173")
+			.body(Body::empty())
+			.unwrap();
+
+		let response = app.oneshot(request).await.unwrap();
+		let exposed = response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap();
+
+		assert!(exposed.contains("etag"));
+		assert!(exposed.contains("last-modified"));
+	}
+}
@@ -1,11 +1,11 @@
 use anyhow::anyhow;
 use include_dir::{include_dir, Dir};
 use jeflog::warn;
-use rusqlite::Connection as SqlConnection;
-use std::{future::Future, path::Path, sync::Arc};
+use rusqlite::{params, Connection as SqlConnection};
+use std::{collections::BTreeMap, future::Future, path::Path, sync::Arc, time::Instant};
 use tokio::sync::Mutex;
 
-use super::Shared;
+use super::{clock, deadband::DeadbandFilter, position, snapshot, vacuum, Shared};
 
 // include_dir is a separate library which evidently accesses files relative to
 // the project root, while include_str is a standard library macro which accesses
@@ -23,16 +23,26 @@ pub struct Database {
 
 impl Database {
 	/// Opens a new `Database` at the path, enclosing a raw SQL connection.
+	///
+	/// The database is opened in WAL mode, so that reads do not wait on the
+	/// vehicle state logger, and its log is checkpointed while idle by [`vacuum::maintain`].
 	pub fn open(path: &Path) -> rusqlite::Result<Self> {
+		let connection = SqlConnection::open(path)?;
+		connection.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+		vacuum::prepare(&connection)?;
+
 		Ok(Database {
-			connection: Arc::new(Mutex::new(SqlConnection::open(path)?))
+			connection: Arc::new(Mutex::new(connection))
 		})
 	}
 
 	/// Opens a new `Database` in memory, so if it is closed, it's not saved.
 	pub fn volatile() -> rusqlite::Result<Self> {
+		let connection = SqlConnection::open_in_memory()?;
+		vacuum::prepare(&connection)?;
+
 		Ok(Database {
-			connection: Arc::new(Mutex::new(SqlConnection::open_in_memory()?))
+			connection: Arc::new(Mutex::new(connection))
 		})
 	}
 
@@ -102,33 +112,69 @@ impl Database {
 		Ok(())
 	}
 
-	/// Continuously logs the vehicle state each time a new one arrives into the database.
+	/// Continuously logs the vehicle state each time a new one arrives into the
+	/// configured storage backend, which is this database unless configured otherwise.
+	///
+	/// Channels with deadbands configured are held back while they stay within
+	/// them, so not every vehicle state is logged.
 	pub fn log_vehicle_state(&self, shared: &Shared) -> impl Future<Output = ()> {
+		let config = shared.config.clone();
 		let vehicle_state = shared.vehicle.clone();
+		let metrics = shared.metrics.clone();
+		let storage = shared.storage.clone();
 		let connection = self.connection.clone();
 
 		async move {
-			let mut buffer = [0_u8; 10_000];
+			let mut last_fixes = BTreeMap::new();
+			let mut last_frame = None;
+			let mut deadbands = DeadbandFilter::default();
 
 			loop {
 				vehicle_state.1.notified().await;
+				let frame = *metrics.latest_frame.lock().await;
 				let vehicle_state = vehicle_state.0.lock().await.clone();
 
-				match postcard::to_slice(&vehicle_state, &mut buffer) {
-					Ok(serialized) => {
-						let query_result = connection
-						.lock()
-						.await
-						.execute("INSERT INTO VehicleSnapshots (vehicle_state) VALUES (?1)", [serialized.as_ref()]);
+				let logged = deadbands.apply(&vehicle_state, &config.current().channels, Instant::now());
+
+				match logged.map(|logged| snapshot::encode(&logged)) {
+					Some(Ok(encoded)) => {
+						let query_result = storage.insert_snapshot(clock::now(), encoded).await;
 
 						if let Err(error) = query_result {
-							warn!("Failed to insert vehicle state into database: {error}");
+							warn!("Failed to insert vehicle state into {} storage: {error}", storage.name());
+						} else if let Some(frame) = frame.filter(|frame| Some(frame.number) != last_frame) {
+							// state from other sources, such as SAM ingest, does not come with a frame
+							metrics.latency.lock().await.frame_to_commit.record(frame.received_at.elapsed());
+							last_frame = Some(frame.number);
 						}
 					},
-					Err(error) => {
-						warn!("Failed to serialize vehicle state into Postcard: {error}");
+					Some(Err(error)) => {
+						warn!("Failed to encode vehicle state: {error}");
 					},
+					None => {},
 				};
+
+				// position fixes are also logged on their own so that tracks may be
+				// queried without deserializing every snapshot, but only when they move.
+				for (receiver, fix) in position::find_fixes(&vehicle_state) {
+					if !fix.is_valid() || last_fixes.get(&receiver) == Some(&fix) {
+						continue;
+					}
+
+					let query_result = connection
+						.lock()
+						.await
+						.execute(
+							"INSERT INTO PositionFixes (receiver, latitude, longitude, altitude, fix_quality) VALUES (?1, ?2, ?3, ?4, ?5)",
+							params![receiver, fix.latitude, fix.longitude, fix.altitude, fix.fix_quality],
+						);
+
+					if let Err(error) = query_result {
+						warn!("Failed to insert position fix into database: {error}");
+					}
+
+					last_fixes.insert(receiver, fix);
+				}
 			}
 		}
 	}
@@ -0,0 +1,98 @@
+use common::comm::VehicleState;
+use std::{collections::BTreeMap, sync::Arc, time::{Duration, Instant}};
+
+use super::config::ChannelConfig;
+
+/// The longest time between logged snapshots while every channel holds within
+/// its deadband, so that a quiet vehicle still appears in the log.
+pub const MAX_HOLD: Duration = Duration::from_secs(1);
+
+/// Holds back changes to channels which stay within their deadbands before
+/// vehicle state is logged, so that noisy but static channels, such as battery
+/// voltages, do not cause a snapshot to be logged with every frame.
+///
+/// A channel with a deadband is logged at the value it was last logged at until
+/// it moves further than its deadband from that value. Snapshots in which no
+/// channel moved at all are not logged, up to [`MAX_HOLD`].
+#[derive(Debug, Default)]
+pub struct DeadbandFilter {
+	logged: Option<(Arc<VehicleState>, Instant)>,
+}
+
+impl DeadbandFilter {
+	/// Applies the configured deadbands to a new vehicle state, returning the
+	/// state to log, or `None` if it need not be logged.
+	pub fn apply(&mut self, state: &Arc<VehicleState>, channels: &BTreeMap<String, ChannelConfig>, now: Instant) -> Option<Arc<VehicleState>> {
+		if !channels.values().any(|channel| channel.deadband.is_some()) {
+			self.logged = None;
+			return Some(state.clone());
+		}
+
+		let Some((logged, logged_at)) = &self.logged else {
+			self.logged = Some((state.clone(), now));
+			return Some(state.clone());
+		};
+
+		let mut held = VehicleState::clone(state);
+
+		for (name, reading) in &mut held.sensor_readings {
+			let deadband = channels.get(name).and_then(|channel| channel.deadband);
+
+			if let (Some(deadband), Some(previous)) = (deadband, logged.sensor_readings.get(name)) {
+				if previous.unit == reading.unit && (reading.value - previous.value).abs() < deadband {
+					reading.value = previous.value;
+				}
+			}
+		}
+
+		if held == **logged && now.duration_since(*logged_at) < MAX_HOLD {
+			return None;
+		}
+
+		let held = Arc::new(held);
+		self.logged = Some((held.clone(), now));
+		Some(held)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+
+	#[test]
+	fn changes_within_deadbands_are_held_back() {
+		let channels = BTreeMap::from([
+			("BATT_V".to_owned(), ChannelConfig { deadband: Some(0.1), ..ChannelConfig::default() }),
+		]);
+
+		let state = |battery: f64, pressure: f64| {
+			let mut state = VehicleState::new();
+			state.sensor_readings.insert("BATT_V".to_owned(), Measurement { value: battery, unit: Unit::Volts });
+			state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: pressure, unit: Unit::Psi });
+			Arc::new(state)
+		};
+
+		let mut filter = DeadbandFilter::default();
+		let start = Instant::now();
+		let at = |milliseconds| start + Duration::from_millis(milliseconds);
+
+		assert!(filter.apply(&state(12.0, 500.0), &channels, at(0)).is_some());
+		assert!(filter.apply(&state(12.05, 500.0), &channels, at(10)).is_none());
+
+		// the undeadbanded channel moving logs a snapshot, but the battery is held
+		let logged = filter.apply(&state(12.08, 501.0), &channels, at(20)).unwrap();
+		assert_eq!(logged.sensor_readings["BATT_V"].value, 12.0);
+
+		// drift is measured from the logged value rather than the previous reading
+		let logged = filter.apply(&state(12.11, 501.0), &channels, at(30)).unwrap();
+		assert_eq!(logged.sensor_readings["BATT_V"].value, 12.11);
+
+		assert!(filter.apply(&state(12.12, 501.0), &channels, at(40)).is_none());
+		assert!(filter.apply(&state(12.12, 501.0), &channels, at(30) + MAX_HOLD).is_some());
+
+		// without any deadbands, every state is logged as it is
+		let logged = filter.apply(&state(12.12, 501.0), &BTreeMap::new(), at(2000)).unwrap();
+		assert_eq!(logged.sensor_readings["BATT_V"].value, 12.12);
+	}
+}
@@ -0,0 +1,206 @@
+use common::comm::{Measurement, VehicleState};
+use jeflog::{fail, pass, warn};
+use std::{collections::HashMap, future::Future, sync::Arc};
+use tokio::{io, net::UdpSocket, task::JoinSet};
+
+use super::{config::DecoderSourceConfig, interlock::parse_unit, supervisor::supervise, vector::VectorKind, Shared};
+
+/// Readings decoded from a single inbound packet, named by channel within the decoder's namespace.
+#[derive(Clone, Debug, Default)]
+pub struct DecodedPacket {
+	/// Sensor readings keyed by channel name, without the namespace.
+	pub sensor_readings: Vec<(String, Measurement)>,
+}
+
+impl DecodedPacket {
+	/// Merges the readings into the vehicle state as `<namespace>.<channel>`,
+	/// so that they cannot collide with mapped sensors or other sources.
+	pub fn merge_into(self, state: &mut VehicleState, namespace: &str) {
+		for (channel, measurement) in self.sensor_readings {
+			state.sensor_readings.insert(format!("{namespace}.{channel}"), measurement);
+		}
+	}
+}
+
+/// Decodes packets of an inbound format other than Postcard into vehicle state readings.
+///
+/// Ground support equipment which will never speak Postcard is supported by
+/// implementing this trait and registering it with a `DecoderRegistry`.
+pub trait TelemetryDecoder: Send + Sync {
+	/// Decodes a single packet, describing why if it is malformed.
+	fn decode(&self, packet: &[u8]) -> Result<DecodedPacket, String>;
+}
+
+/// Constructs a decoder for one configured source.
+pub type DecoderConstructor = fn(&DecoderSourceConfig) -> Arc<dyn TelemetryDecoder>;
+
+/// The set of inbound formats that sources may be configured to use.
+pub struct DecoderRegistry {
+	constructors: HashMap<String, DecoderConstructor>,
+}
+
+impl DecoderRegistry {
+	/// Registers a decoder under a format name, replacing any previously registered for it.
+	pub fn register(&mut self, format: &str, constructor: DecoderConstructor) {
+		self.constructors.insert(format.to_owned(), constructor);
+	}
+
+	/// Constructs the decoder for a configured source, if its format is registered.
+	pub fn build(&self, source: &DecoderSourceConfig) -> Option<Arc<dyn TelemetryDecoder>> {
+		self.constructors
+			.get(&source.format)
+			.map(|constructor| constructor(source))
+	}
+}
+
+impl Default for DecoderRegistry {
+	/// Creates a registry containing every decoder built into servo.
+	fn default() -> Self {
+		let mut registry = DecoderRegistry { constructors: HashMap::new() };
+		registry.register("json", |_| Arc::new(JsonDecoder));
+		registry
+	}
+}
+
+/// Decodes JSON objects mapping channel names to readings, as sent by lab DAQs.
+///
+/// Each reading is an object with a `value` and a `unit`, such as
+/// `{"KBPT": {"value": 50.2, "unit": "psi"}}`. A `value` may also be an array of
+/// three or four numbers for a vector or quaternion, which is stored by component.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonDecoder;
+
+impl TelemetryDecoder for JsonDecoder {
+	fn decode(&self, packet: &[u8]) -> Result<DecodedPacket, String> {
+		let object = serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(packet)
+			.map_err(|error| format!("packet is not a JSON object: {error}"))?;
+
+		let mut decoded = DecodedPacket::default();
+
+		for (channel, reading) in object {
+			let unit = reading
+				.get("unit")
+				.and_then(serde_json::Value::as_str)
+				.ok_or(format!("reading for '{channel}' has no unit"))?;
+
+			let unit = parse_unit(unit).ok_or(format!("reading for '{channel}' has unrecognized unit '{unit}'"))?;
+
+			// an array of values is a vector or quaternion, stored by component
+			if let Some(values) = reading.get("value").and_then(serde_json::Value::as_array) {
+				let values = values
+					.iter()
+					.map(serde_json::Value::as_f64)
+					.collect::<Option<Vec<f64>>>()
+					.ok_or(format!("reading for '{channel}' has a non-numeric component"))?;
+
+				let kind = VectorKind::with_length(values.len())
+					.ok_or(format!("reading for '{channel}' has {} components, not 3 or 4", values.len()))?;
+
+				for (component, value) in kind.components().iter().zip(values) {
+					decoded.sensor_readings.push((format!("{channel}.{component}"), Measurement { value, unit }));
+				}
+
+				continue;
+			}
+
+			let value = reading
+				.get("value")
+				.and_then(serde_json::Value::as_f64)
+				.ok_or(format!("reading for '{channel}' has no numeric value"))?;
+
+			decoded.sensor_readings.push((channel, Measurement { value, unit }));
+		}
+
+		Ok(decoded)
+	}
+}
+
+/// Spawns a supervised listener into the given set for every configured decoder source whose format is registered.
+pub fn spawn_sources(tasks: &mut JoinSet<()>, shared: &Shared, registry: &DecoderRegistry) {
+	for source in &shared.config.current().ingest.decoders {
+		let Some(decoder) = registry.build(source) else {
+			fail!("Decoder source '{}' uses unregistered format '{}'.", source.namespace, source.format);
+			continue;
+		};
+
+		let name = format!("decoder '{}'", source.namespace);
+		let source = source.clone();
+
+		supervise(tasks, shared, &name, move |shared| receive_decoded(shared, source.clone(), decoder.clone()));
+	}
+}
+
+/// Receives packets from a single decoder source over UDP, merging them into the vehicle state.
+pub fn receive_decoded(
+	shared: &Shared,
+	source: DecoderSourceConfig,
+	decoder: Arc<dyn TelemetryDecoder>,
+) -> impl Future<Output = io::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let socket = UdpSocket::bind(("0.0.0.0", source.port)).await?;
+		let mut buffer = vec![0; 65_536];
+
+		pass!("Listening for {} data as '{}' on port {}.", source.format, source.namespace, source.port);
+
+		loop {
+			let (size, address) = socket.recv_from(&mut buffer).await?;
+
+			match decoder.decode(&buffer[..size]) {
+				Ok(decoded) => {
+					// copied only if a consumer still holds the previous snapshot
+					decoded.merge_into(Arc::make_mut(&mut *shared.vehicle.0.lock().await), &source.namespace);
+					shared.vehicle.1.notify_waiters();
+				},
+				Err(error) => warn!("Failed to decode '{}' packet from {address}: {error}", source.namespace),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common::comm::Unit;
+
+	#[test]
+	fn json_readings_are_namespaced() {
+		let decoded = JsonDecoder
+			.decode(br#"{"KBPT": {"value": 50.5, "unit": "psi"}, "rail": {"value": 24, "unit": "V"}}"#)
+			.unwrap();
+
+		let mut state = VehicleState::new();
+		decoded.merge_into(&mut state, "daq");
+
+		let reading = |name: &str| {
+			let measurement = &state.sensor_readings[name];
+			(measurement.value, measurement.unit)
+		};
+
+		assert_eq!(reading("daq.KBPT"), (50.5, Unit::Psi));
+		assert_eq!(reading("daq.rail"), (24.0, Unit::Volts));
+		assert!(!state.sensor_readings.contains_key("KBPT"));
+	}
+
+	#[test]
+	fn json_arrays_are_stored_by_component() {
+		let decoded = JsonDecoder
+			.decode(br#"{"attitude": {"value": [1, 0, 0, 0], "unit": "V"}}"#)
+			.unwrap();
+
+		let mut state = VehicleState::new();
+		decoded.merge_into(&mut state, "ahrs");
+
+		assert_eq!(state.sensor_readings["ahrs.attitude.w"].value, 1.0);
+		assert_eq!(state.sensor_readings["ahrs.attitude.z"].value, 0.0);
+		assert!(JsonDecoder.decode(br#"{"attitude": {"value": [1, 0], "unit": "V"}}"#).is_err());
+	}
+
+	#[test]
+	fn malformed_json_is_rejected() {
+		assert!(JsonDecoder.decode(b"[1, 2]").is_err());
+		assert!(JsonDecoder.decode(br#"{"KBPT": 50}"#).is_err());
+		assert!(JsonDecoder.decode(br#"{"KBPT": {"value": 50, "unit": "furlongs"}}"#).is_err());
+	}
+}
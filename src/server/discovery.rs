@@ -0,0 +1,129 @@
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use std::{net::SocketAddr, time::{Duration, Instant}};
+
+#[cfg(feature = "server")]
+use {
+	mdns_sd::ServiceInfo,
+	std::future::Future,
+	sysinfo::{System, SystemExt},
+	super::{api::Role, Shared},
+};
+
+/// The mDNS service type servo advertises itself under.
+pub const SERVICE_TYPE: &str = "_servo._tcp.local.";
+
+/// How often the advertisement is checked against the role of the server.
+#[cfg(feature = "server")]
+const ROLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The name of a role as it appears in the `role` property of the advertisement.
+#[cfg(feature = "server")]
+fn role_name(role: Role) -> &'static str {
+	match role {
+		Role::Primary => "primary",
+		Role::Standby => "standby",
+		Role::Fenced => "fenced",
+	}
+}
+
+/// Advertises the server over mDNS as a `_servo._tcp` service, so that
+/// clients on the network can find it without being told its address.
+///
+/// The TXT record carries the `version` of servo and the `role` and `term`
+/// of the server, and is re-announced whenever the role changes.
+#[cfg(feature = "server")]
+pub fn advertise(shared: &Shared) -> impl Future<Output = anyhow::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let daemon = ServiceDaemon::new()?;
+		let hostname = System::new().host_name().unwrap_or_else(|| "servo".to_owned());
+		let mut advertised = None;
+
+		loop {
+			let current = (shared.role.role(), shared.role.term());
+
+			if advertised != Some(current) {
+				let (role, term) = current;
+				let term = term.to_string();
+
+				let properties = [
+					("version", env!("CARGO_PKG_VERSION")),
+					("role", role_name(role)),
+					("term", term.as_str()),
+				];
+
+				let service = ServiceInfo::new(SERVICE_TYPE, &hostname, &format!("{hostname}.local."), (), shared.addresses.http.port(), &properties[..])?
+					.enable_addr_auto();
+
+				daemon.register(service)?;
+				advertised = Some(current);
+			}
+
+			tokio::time::sleep(ROLE_CHECK_INTERVAL).await;
+		}
+	}
+}
+
+/// A server found advertising itself on the network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredServer {
+	/// The name the server advertised itself under, usually its hostname.
+	pub name: String,
+
+	/// The address of the HTTP API.
+	pub address: SocketAddr,
+
+	/// The version of servo the server runs, if advertised.
+	pub version: Option<String>,
+
+	/// The role of the server, such as `primary`, if advertised.
+	pub role: Option<String>,
+}
+
+impl DiscoveredServer {
+	/// The base URL of the HTTP API, such as `http://10.0.0.1:7200`.
+	pub fn url(&self) -> String {
+		format!("http://{}", self.address)
+	}
+}
+
+/// Browses the network for servers for up to the given time, stopping early once a primary is found.
+pub fn discover(timeout: Duration) -> anyhow::Result<Vec<DiscoveredServer>> {
+	let daemon = ServiceDaemon::new()?;
+	let events = daemon.browse(SERVICE_TYPE)?;
+	let deadline = Instant::now() + timeout;
+	let mut servers = Vec::<DiscoveredServer>::new();
+
+	while let Ok(event) = events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+		let ServiceEvent::ServiceResolved(info) = event else {
+			continue;
+		};
+
+		let addresses = info.get_addresses();
+
+		let Some(ip) = addresses.iter().find(|ip| ip.is_ipv4()).or(addresses.iter().next()) else {
+			continue;
+		};
+
+		let server = DiscoveredServer {
+			name: info.get_fullname().trim_end_matches(SERVICE_TYPE).trim_end_matches('.').to_owned(),
+			address: SocketAddr::new(*ip, info.get_port()),
+			version: info.get_property_val_str("version").map(str::to_owned),
+			role: info.get_property_val_str("role").map(str::to_owned),
+		};
+
+		let is_primary = server.role.as_deref() == Some("primary");
+
+		if !servers.iter().any(|known| known.name == server.name) {
+			servers.push(server);
+		}
+
+		if is_primary {
+			break;
+		}
+	}
+
+	let _ = daemon.shutdown();
+	Ok(servers)
+}
@@ -1,44 +1,323 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+#[cfg(feature = "server")]
+use axum::{http::StatusCode, response::IntoResponse, BoxError, Json};
+
+/// A machine-readable identifier of an error returned by the API, so that GUIs
+/// may decide what to show without matching on the text of the message.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+	/// The request was malformed or named something which does not exist.
+	BadRequest,
+
+	/// The request needs a session, but none was supplied.
+	Unauthenticated,
+
+	/// The username or password given to log in was wrong.
+	InvalidCredentials,
+
+	/// The session token supplied is unknown, revoked, or expired.
+	SessionExpired,
+
+	/// The session lacks permission for the request.
+	Forbidden,
+
+	/// The request needs a session with the admin role.
+	AdminRequired,
+
+	/// Nothing may be changed while the server is in spectator mode, or by a spectator.
+	SpectatorMode,
+
+	/// A hazardous command must be confirmed by an operator other than the one who requested it.
+	SameOperator,
+
+	/// The resource requested does not exist.
+	NotFound,
+
+	/// A command held for confirmation waited too long and was dropped.
+	ConfirmationExpired,
+
+	/// Fields of the request are missing, malformed, or out of range, as listed in its context.
+	ValidationFailed,
+
+	/// The request conflicts with the current state of the server.
+	Conflict,
+
+	/// The interlocks guarding a sequence do not hold.
+	InterlocksNotSatisfied,
+
+	/// A sequence is locked out by another which is running.
+	SequenceLockedOut,
+
+	/// This server is a standby or was fenced, so commands must go to the primary.
+	NotPrimary,
+
+	/// The server refuses to act while locked.
+	Locked,
+
+	/// Nothing may be actuated while the server is in maintenance mode.
+	MaintenanceMode,
+
+	/// Actuation requires the lease on command authority, which the session does not hold.
+	AuthorityNotHeld,
+
+	/// The request took longer than the server allows.
+	Timeout,
+
+	/// The body of the request is larger than the server allows for its route.
+	PayloadTooLarge,
+
+	/// The flight or ground computer a command is sent to is not connected.
+	ComputerNotConnected,
+
+	/// A query against the database failed.
+	Database,
+
+	/// Anything else that went wrong within the server.
+	Internal,
+}
+
+impl ErrorCode {
+	/// The name of the code, as sent to clients.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::BadRequest => "bad_request",
+			Self::Unauthenticated => "unauthenticated",
+			Self::InvalidCredentials => "invalid_credentials",
+			Self::SessionExpired => "session_expired",
+			Self::Forbidden => "forbidden",
+			Self::AdminRequired => "admin_required",
+			Self::SpectatorMode => "spectator_mode",
+			Self::SameOperator => "same_operator",
+			Self::NotFound => "not_found",
+			Self::ConfirmationExpired => "confirmation_expired",
+			Self::ValidationFailed => "validation_failed",
+			Self::Conflict => "conflict",
+			Self::InterlocksNotSatisfied => "interlocks_not_satisfied",
+			Self::SequenceLockedOut => "sequence_locked_out",
+			Self::NotPrimary => "not_primary",
+			Self::Locked => "locked",
+			Self::MaintenanceMode => "maintenance_mode",
+			Self::AuthorityNotHeld => "authority_not_held",
+			Self::Timeout => "timeout",
+			Self::PayloadTooLarge => "payload_too_large",
+			Self::ComputerNotConnected => "computer_not_connected",
+			Self::Database => "database",
+			Self::Internal => "internal",
+		}
+	}
+
+	/// The HTTP status an error with this code is returned with.
+	#[cfg(feature = "server")]
+	pub fn status(self) -> StatusCode {
+		match self {
+			Self::BadRequest => StatusCode::BAD_REQUEST,
+			Self::Unauthenticated | Self::InvalidCredentials | Self::SessionExpired => StatusCode::UNAUTHORIZED,
+			Self::Forbidden | Self::AdminRequired | Self::SpectatorMode | Self::SameOperator => StatusCode::FORBIDDEN,
+			Self::NotFound | Self::ConfirmationExpired => StatusCode::NOT_FOUND,
+			Self::ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+			Self::Conflict | Self::InterlocksNotSatisfied | Self::SequenceLockedOut | Self::NotPrimary => StatusCode::CONFLICT,
+			Self::Locked | Self::MaintenanceMode | Self::AuthorityNotHeld => StatusCode::LOCKED,
+			Self::Timeout => StatusCode::REQUEST_TIMEOUT,
+			Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+			Self::ComputerNotConnected | Self::Database | Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
+
+impl fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
 
 /// Any error that the server can throw in a route function.
 #[derive(Debug)]
 pub enum ServerError {
 	/// Error originating from a SQL query.
 	Sql(rusqlite::Error),
-	
-	/// Error that may be converted directly into a `Response`.
-	Raw(String, StatusCode)
+
+	/// Error returned to the client with a code, a message for people, and any
+	/// context a GUI may need to act on it, such as the name of a running sequence.
+	Api {
+		/// The machine-readable identifier of the error.
+		code: ErrorCode,
+
+		/// A description of the error for people.
+		message: String,
+
+		/// Values relevant to the error, keyed by name.
+		context: BTreeMap<String, serde_json::Value>,
+	},
 }
 
-impl Into<ServerError> for rusqlite::Error {
-	fn into(self) -> ServerError {
-		ServerError::Sql(self)
+impl ServerError {
+	/// Creates an error with a code and message, but no context.
+	pub fn new(code: ErrorCode, message: impl ToString) -> Self {
+		ServerError::Api {
+			code,
+			message: message.to_string(),
+			context: BTreeMap::new(),
+		}
+	}
+
+	/// The machine-readable identifier of the error.
+	pub fn code(&self) -> ErrorCode {
+		match self {
+			Self::Sql(_) => ErrorCode::Database,
+			Self::Api { code, .. } => *code,
+		}
+	}
+
+	/// Replaces the code of the error with a more specific one.
+	pub fn with_code(self, code: ErrorCode) -> Self {
+		match self {
+			Self::Sql(error) => Self::new(code, error),
+			Self::Api { message, context, .. } => Self::Api { code, message, context },
+		}
+	}
+
+	/// Attaches a value to the error, which is sent to the client alongside its message.
+	pub fn with_context(self, key: &str, value: impl Serialize) -> Self {
+		let mut error = match self {
+			Self::Sql(error) => Self::new(ErrorCode::Database, error),
+			error => error,
+		};
+
+		if let Self::Api { context, .. } = &mut error {
+			context.insert(key.to_owned(), serde_json::to_value(value).unwrap_or_default());
+		}
+
+		error
+	}
+
+	/// Converts the error into the body sent to the client.
+	pub fn body(self) -> ErrorBody {
+		match self {
+			Self::Sql(error) => ErrorBody {
+				code: ErrorCode::Database,
+				message: error.to_string(),
+				context: BTreeMap::new(),
+			},
+			Self::Api { code, message, context } => ErrorBody { code, message, context },
+		}
+	}
+}
+
+impl From<rusqlite::Error> for ServerError {
+	fn from(error: rusqlite::Error) -> Self {
+		ServerError::Sql(error)
 	}
 }
 
+impl fmt::Display for ServerError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Sql(error) => write!(f, "{error}"),
+			Self::Api { message, .. } => write!(f, "{message}"),
+		}
+	}
+}
+
+#[cfg(feature = "server")]
 impl IntoResponse for ServerError {
 	fn into_response(self) -> axum::response::Response {
-		match self {
-			Self::Sql(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
-			Self::Raw(message, status) => (status, message),
-		}.into_response()
+		let status = self.code().status();
+		(status, Json(self.body())).into_response()
+	}
+}
+
+/// The JSON body of every error response, which clients such as the command
+/// line tools parse to report the message and act on the code.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ErrorBody {
+	/// The machine-readable identifier of the error.
+	pub code: ErrorCode,
+
+	/// A description of the error for people.
+	pub message: String,
+
+	/// Values relevant to the error, keyed by name.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub context: BTreeMap<String, serde_json::Value>,
+}
+
+impl fmt::Display for ErrorBody {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.message)
 	}
 }
 
+impl std::error::Error for ErrorBody {}
+
 /// A `Result` type containing a `ServerError` as its `Err` variant.
 pub type ServerResult<T> = Result<T, ServerError>;
 
-/// Converts any arbitrary error type into a standardized `ServerError::Raw` for a bad request.
+/// Converts any arbitrary error type into a `ServerError` for a bad request.
 pub fn bad_request(message: impl ToString) -> ServerError {
-	ServerError::Raw(message.to_string(), StatusCode::BAD_REQUEST)
+	ServerError::new(ErrorCode::BadRequest, message)
 }
 
-/// Converts any arbitrary error type into a standardized `ServerError::Raw` when a resource is not found.
+/// Converts any arbitrary error type into a `ServerError` when a request is not authenticated.
+pub fn unauthorized(message: impl ToString) -> ServerError {
+	ServerError::new(ErrorCode::Unauthenticated, message)
+}
+
+/// Converts any arbitrary error type into a `ServerError` when a session lacks permission.
+pub fn forbidden(message: impl ToString) -> ServerError {
+	ServerError::new(ErrorCode::Forbidden, message)
+}
+
+/// Converts any arbitrary error type into a `ServerError` when a resource is not found.
 pub fn not_found(message: impl ToString) -> ServerError {
-	ServerError::Raw(message.to_string(), StatusCode::NOT_FOUND)
+	ServerError::new(ErrorCode::NotFound, message)
+}
+
+/// Converts any arbitrary error type into a `ServerError` when a request conflicts with the current state.
+pub fn conflict(message: impl ToString) -> ServerError {
+	ServerError::new(ErrorCode::Conflict, message)
+}
+
+/// Converts any arbitrary error type into a `ServerError` when the server refuses to act while locked.
+pub fn locked(message: impl ToString) -> ServerError {
+	ServerError::new(ErrorCode::Locked, message)
 }
 
-/// Converts any arbitrary error type into a standardized internal `ServerError`.
+/// Converts any arbitrary error type into an internal `ServerError`.
 pub fn internal(message: impl ToString) -> ServerError {
-	ServerError::Raw(message.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+	ServerError::new(ErrorCode::Internal, message)
+}
+
+/// Converts errors thrown by tower middleware, such as timeouts, into a `ServerError`.
+#[cfg(feature = "server")]
+pub async fn handle_middleware_error(error: BoxError) -> ServerError {
+	if error.is::<tower::timeout::error::Elapsed>() {
+		ServerError::new(ErrorCode::Timeout, "request timed out")
+	} else {
+		internal(format!("unhandled middleware error: {error}"))
+	}
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn errors_carry_codes_and_context() {
+		let error = conflict("sequence 'fill' cannot run while 'vent' is running")
+			.with_code(ErrorCode::SequenceLockedOut)
+			.with_context("running", ["vent"]);
+
+		assert_eq!(error.code().status(), StatusCode::CONFLICT);
+
+		let body = serde_json::to_value(error.body()).unwrap();
+		assert_eq!(body["code"], "sequence_locked_out");
+		assert_eq!(body["context"]["running"][0], "vent");
+
+		// context is left out entirely when there is none
+		let body = serde_json::to_value(not_found("no such sequence").body()).unwrap();
+		assert!(body.get("context").is_none());
+	}
 }
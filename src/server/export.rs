@@ -0,0 +1,320 @@
+use common::comm::{Measurement, VehicleState};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::{io::{Cursor, Write}, sync::Arc};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{aggregation::Aggregator, selector::Selection, units::UnitSystem, validation::{Validate, Validator}};
+
+/// The formats vehicle state may be exported in.
+#[cfg(feature = "hdf5")]
+pub const FORMATS: [&str; 3] = ["csv", "hdf5", "influx"];
+
+/// The formats vehicle state may be exported in, which leave out HDF5 when built without it.
+#[cfg(not(feature = "hdf5"))]
+pub const FORMATS: [&str; 2] = ["csv", "influx"];
+
+/// A named set of export options saved on the server, so that a recurring
+/// analysis is exported the same way every time without repeating its options.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ExportPreset {
+	/// The unique name of the preset, such as `propulsion-quicklook`.
+	pub name: String,
+
+	/// What the preset is used for.
+	#[serde(default)]
+	pub description: String,
+
+	/// The format exported, if the preset decides it rather than the file being exported to.
+	#[serde(default)]
+	pub format: Option<String>,
+
+	/// The selectors of the sensors and valves exported, such as `FU_*`, or every channel if empty.
+	#[serde(default)]
+	pub channels: Vec<String>,
+
+	/// The rate in hertz the export is downsampled to, or the full logged rate if `None`.
+	#[serde(default)]
+	pub downsample_hz: Option<f64>,
+
+	/// The unit system readings are converted into.
+	#[serde(default)]
+	pub units: UnitSystem,
+}
+
+impl Validate for ExportPreset {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+
+		if let Some(format) = &self.format {
+			validator.one_of("format", format, &FORMATS);
+		}
+
+		validator.selectors("channels", &self.channels);
+
+		if let Some(rate) = self.downsample_hz {
+			validator.positive("downsample_hz", rate);
+		}
+	}
+}
+
+/// Reads a preset from a row which selects every column of the `ExportPresets` table.
+pub fn preset_from_row(row: &Row) -> rusqlite::Result<ExportPreset> {
+	let channels = row.get::<_, String>(3)?;
+	let units = row.get::<_, String>(5)?;
+
+	Ok(ExportPreset {
+		name: row.get(0)?,
+		description: row.get(1)?,
+		format: row.get(2)?,
+		channels: serde_json::from_str(&channels)
+			.map_err(|error| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(error)))?,
+		downsample_hz: row.get(4)?,
+		units: units
+			.parse()
+			.map_err(|error: String| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, error.into()))?,
+	})
+}
+
+/// Lists every saved preset, by name.
+pub fn list_presets(connection: &Connection) -> rusqlite::Result<Vec<ExportPreset>> {
+	connection
+		.prepare("SELECT name, description, format, channels, downsample_hz, units FROM ExportPresets ORDER BY name")?
+		.query_map([], preset_from_row)?
+		.collect()
+}
+
+/// Reads the preset of a name, if one is saved.
+pub fn load_preset(connection: &Connection, name: &str) -> rusqlite::Result<Option<ExportPreset>> {
+	connection
+		.query_row(
+			"SELECT name, description, format, channels, downsample_hz, units FROM ExportPresets WHERE name = ?1",
+			[name],
+			preset_from_row,
+		)
+		.optional()
+}
+
+/// Saves a preset, replacing any of the same name.
+pub fn save_preset(connection: &Connection, preset: &ExportPreset) -> rusqlite::Result<()> {
+	let channels = serde_json::to_string(&preset.channels)
+		.map_err(|error| rusqlite::Error::ToSqlConversionFailure(Box::new(error)))?;
+
+	connection.execute(
+		"INSERT OR REPLACE INTO ExportPresets (name, description, format, channels, downsample_hz, units) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+		params![preset.name, preset.description, preset.format, channels, preset.downsample_hz, preset.units.as_str()],
+	)?;
+
+	Ok(())
+}
+
+/// Checks that a format may be exported.
+pub fn check_format(format: &str) -> Result<(), String> {
+	if FORMATS.contains(&format) {
+		Ok(())
+	} else {
+		Err(format!("invalid export format '{format}', expected one of {}", FORMATS.join(", ")))
+	}
+}
+
+/// The file extension of an export format.
+pub fn extension(format: &str) -> &str {
+	match format {
+		"influx" => "lp",
+		format => format,
+	}
+}
+
+/// Packs exported files into a zip archive, as pairs of file names and contents.
+pub fn archive(files: &[(String, Vec<u8>)]) -> zip::result::ZipResult<Vec<u8>> {
+	let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+	let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+	for (name, content) in files {
+		writer.start_file(name.as_str(), options)?;
+		writer.write_all(content)?;
+	}
+
+	Ok(writer.finish()?.into_inner())
+}
+
+/// Checks that a downsampling rate is a positive number of hertz.
+pub fn check_rate(rate: f64) -> Result<(), String> {
+	if rate > 0.0 && rate.is_finite() {
+		Ok(())
+	} else {
+		Err("downsampling rate must be a positive number of hertz".to_owned())
+	}
+}
+
+/// Keeps only the selected sensors and valves in each snapshot, or every one if none are selected.
+pub fn retain_channels(vehicle_states: &mut [(f64, VehicleState)], channels: &Selection) {
+	if channels.is_empty() {
+		return;
+	}
+
+	for (_, state) in vehicle_states {
+		channels.retain(state);
+	}
+}
+
+/// Downsamples snapshots to a rate, aggregating each interval into a single snapshot.
+pub fn downsample(vehicle_states: &mut Vec<(f64, VehicleState)>, rate: f64) {
+	let mut downsampler = Downsampler::new(rate);
+
+	downsampler.downsample_chunk(vehicle_states);
+	vehicle_states.extend(downsampler.finish());
+}
+
+/// Downsamples snapshots to a rate as they are read in order, so that an
+/// export may be downsampled a chunk at a time.
+///
+/// Each interval is aggregated into a single snapshot, logged at the time of
+/// its first, in which each sensor reads the mean of its readings over the
+/// interval and each valve its latest state. The envelope of each sensor is
+/// kept alongside its mean as the `NAME.min` and `NAME.max` channels, so that
+/// transients are not lost as they would be by dropping snapshots.
+#[derive(Clone, Debug)]
+pub struct Downsampler {
+	interval: f64,
+
+	/// The interval being aggregated, which is only complete once a snapshot after it is read.
+	bucket: Option<Bucket>,
+}
+
+/// The snapshots of an interval aggregated so far.
+#[derive(Clone, Debug)]
+struct Bucket {
+	/// The index of the interval, counted in intervals since the epoch.
+	index: f64,
+
+	/// The time the first snapshot in the interval was logged.
+	recorded_at: f64,
+
+	aggregator: Aggregator,
+	latest: Arc<VehicleState>,
+}
+
+impl Downsampler {
+	/// Downsamples snapshots to a rate in hertz.
+	pub fn new(rate: f64) -> Self {
+		Downsampler { interval: 1.0 / rate, bucket: None }
+	}
+
+	/// Adds the next snapshot, returning the aggregate of the interval before it if the snapshot starts another.
+	pub fn push(&mut self, timestamp: f64, state: VehicleState) -> Option<(f64, VehicleState)> {
+		let index = (timestamp / self.interval).floor();
+		let state = Arc::new(state);
+
+		if let Some(bucket) = &mut self.bucket {
+			if bucket.index == index {
+				bucket.aggregator.add(&state);
+				bucket.latest = state;
+				return None;
+			}
+		}
+
+		let mut aggregator = Aggregator::default();
+		aggregator.add(&state);
+
+		let bucket = Bucket { index, recorded_at: timestamp, aggregator, latest: state };
+		self.bucket.replace(bucket).map(Bucket::finish)
+	}
+
+	/// Replaces the snapshots of a chunk with the aggregates of the intervals they complete.
+	pub fn downsample_chunk(&mut self, vehicle_states: &mut Vec<(f64, VehicleState)>) {
+		*vehicle_states = std::mem::take(vehicle_states)
+			.into_iter()
+			.filter_map(|(timestamp, state)| self.push(timestamp, state))
+			.collect();
+	}
+
+	/// Ends the last interval once every snapshot has been added, returning its aggregate, if any.
+	pub fn finish(&mut self) -> Option<(f64, VehicleState)> {
+		self.bucket.take().map(Bucket::finish)
+	}
+}
+
+impl Bucket {
+	/// Aggregates the interval into a snapshot, with the envelope of each sensor as channels of its own.
+	fn finish(mut self) -> (f64, VehicleState) {
+		let aggregated = self.aggregator.finish(&self.latest);
+		let mut state = aggregated.state;
+
+		for (name, envelope) in aggregated.envelopes {
+			let unit = state.sensor_readings[&name].unit;
+
+			state.sensor_readings.insert(format!("{name}.min"), Measurement { value: envelope.min, unit });
+			state.sensor_readings.insert(format!("{name}.max"), Measurement { value: envelope.max, unit });
+		}
+
+		(self.recorded_at, state)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+	use crate::server::validation::validate;
+
+	#[test]
+	fn presets_downsample_and_filter_snapshots() {
+		let mut vehicle_states = (0..50)
+			.map(|index| {
+				let mut state = VehicleState::new();
+				state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: index as f64, unit: Unit::Psi });
+				state.sensor_readings.insert("WTPT".to_owned(), Measurement { value: 2.0, unit: Unit::Psi });
+				(index as f64 * 0.1, state)
+			})
+			.collect::<Vec<_>>();
+
+		retain_channels(&mut vehicle_states, &Selection::parse(["KB*"]).unwrap());
+		downsample(&mut vehicle_states, 2.0);
+
+		let timestamps = vehicle_states.iter().map(|(timestamp, _)| *timestamp).collect::<Vec<_>>();
+		assert_eq!(timestamps.len(), 10);
+		assert!((timestamps[1] - 0.5).abs() < 1e-9);
+
+		// each interval reads the mean of its snapshots, with their envelope alongside
+		let (_, second) = &vehicle_states[1];
+		let mut names = second.sensor_readings.keys().collect::<Vec<_>>();
+		names.sort();
+
+		assert_eq!(names, ["KBPT", "KBPT.max", "KBPT.min"]);
+		assert_eq!(second.sensor_readings["KBPT"].value, 7.0);
+		assert_eq!(second.sensor_readings["KBPT.min"].value, 5.0);
+		assert_eq!(second.sensor_readings["KBPT.max"].value, 9.0);
+
+		// the last interval is kept even though no snapshot follows it
+		assert_eq!(vehicle_states[9].1.sensor_readings["KBPT.max"].value, 49.0);
+
+		let preset = ExportPreset { name: "quicklook".to_owned(), format: Some("xlsx".to_owned()), ..ExportPreset::default() };
+		assert!(validate(&preset).is_err());
+		assert!(validate(&ExportPreset { downsample_hz: Some(0.0), ..preset.clone() }).is_err());
+		assert!(validate(&ExportPreset { channels: vec!["/(/".to_owned()], ..preset.clone() }).is_err());
+		assert!(validate(&ExportPreset { name: " ".to_owned(), ..preset.clone() }).is_err());
+		assert!(validate(&ExportPreset { format: Some("csv".to_owned()), ..preset.clone() }).is_ok());
+
+		#[cfg(feature = "hdf5")]
+		assert!(validate(&ExportPreset { format: Some("hdf5".to_owned()), ..preset }).is_ok());
+	}
+
+	#[test]
+	fn formats_are_archived_together() {
+		let files = vec![
+			("export.csv".to_owned(), b"timestamp,KBPT\n0,1 Psi\n".to_vec()),
+			(format!("export.{}", extension("influx")), b"KBPT value=1 0\n".to_vec()),
+		];
+
+		let mut archive = zip::ZipArchive::new(Cursor::new(archive(&files).unwrap())).unwrap();
+		assert_eq!(archive.len(), 2);
+
+		for (name, content) in &files {
+			let mut file = archive.by_name(name).unwrap();
+			let mut read = Vec::new();
+			std::io::Read::read_to_end(&mut file, &mut read).unwrap();
+			assert_eq!(&read, content);
+		}
+	}
+}
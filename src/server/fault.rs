@@ -0,0 +1,112 @@
+use super::{config::CauseConfig, interlock::{Condition, SystemState}};
+
+/// Suggests the probable causes of an alert by walking the configured fault tree.
+///
+/// A node applies when the alert came from one of its rules, or from any rule
+/// if it names none, and every one of its conditions holds. A node which
+/// applies suggests the causes of whichever of its refinements also apply,
+/// falling back to its own cause when none of them do, so the most specific
+/// explanation which fits the system state is the one given.
+pub fn diagnose(causes: &[CauseConfig], rule: &str, state: &SystemState) -> Vec<String> {
+	let mut suggestions = Vec::new();
+
+	for cause in causes {
+		if !cause.rules.is_empty() && !cause.rules.iter().any(|name| name == rule) {
+			continue;
+		}
+
+		let holds = cause.all.iter().all(|condition| {
+			condition
+				.parse::<Condition>()
+				.is_ok_and(|condition| condition.check(state).is_ok())
+		});
+
+		if !holds {
+			continue;
+		}
+
+		let refined = diagnose(&cause.refinements, rule, state);
+
+		if refined.is_empty() {
+			suggestions.push(cause.cause.clone());
+		} else {
+			suggestions.extend(refined);
+		}
+	}
+
+	suggestions.dedup();
+	suggestions
+}
+
+/// Checks that every condition in a fault tree can be parsed, returning the
+/// cause and error of each which cannot.
+pub fn invalid_conditions(causes: &[CauseConfig]) -> Vec<(String, String)> {
+	let mut invalid = Vec::new();
+
+	for cause in causes {
+		for condition in &cause.all {
+			if let Err(error) = condition.parse::<Condition>() {
+				invalid.push((cause.cause.clone(), error));
+			}
+		}
+
+		invalid.extend(invalid_conditions(&cause.refinements));
+	}
+
+	invalid
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit, VehicleState};
+	use super::*;
+
+	fn cause(cause: &str, rules: &[&str], all: &[&str], refinements: Vec<CauseConfig>) -> CauseConfig {
+		CauseConfig {
+			cause: cause.to_owned(),
+			rules: rules.iter().map(|rule| rule.to_string()).collect(),
+			all: all.iter().map(|condition| condition.to_string()).collect(),
+			refinements,
+		}
+	}
+
+	#[test]
+	fn most_specific_applicable_cause_is_suggested() {
+		let tree = vec![
+			cause("sensor fault", &["tank pressure lost"], &["KBPT < 1 psi"], vec![
+				cause("board power fault on SAM-2", &[], &["SAM2_RAIL < 20 V"], Vec::new()),
+				cause("cable unplugged", &[], &["KBPT < -10 psi"], Vec::new()),
+			]),
+			cause("flight computer offline", &[], &["not flight link healthy"], Vec::new()),
+		];
+
+		assert!(invalid_conditions(&tree).is_empty());
+		assert_eq!(invalid_conditions(&[cause("bad", &[], &["KBPT >>> 3"], Vec::new())]).len(), 1);
+
+		let mut vehicle = VehicleState::new();
+		vehicle.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 0.0, unit: Unit::Psi });
+		vehicle.sensor_readings.insert("SAM2_RAIL".to_owned(), Measurement { value: 24.0, unit: Unit::Volts });
+
+		let mut state = SystemState {
+			vehicle: &vehicle,
+			flight_link_healthy: true,
+			active_configuration: None,
+		};
+
+		// without a more specific explanation, the general cause is given
+		assert_eq!(diagnose(&tree, "tank pressure lost", &state), ["sensor fault"]);
+		assert!(diagnose(&tree, "other rule", &state).is_empty());
+
+		state.flight_link_healthy = false;
+		assert_eq!(diagnose(&tree, "other rule", &state), ["flight computer offline"]);
+
+		let mut vehicle = vehicle.clone();
+		vehicle.sensor_readings.insert("SAM2_RAIL".to_owned(), Measurement { value: 3.0, unit: Unit::Volts });
+		state.vehicle = &vehicle;
+
+		assert_eq!(diagnose(&tree, "tank pressure lost", &state), [
+			"board power fault on SAM-2",
+			"flight computer offline",
+		]);
+	}
+}
@@ -1,26 +1,196 @@
-use common::comm::{Computer, FlightControlMessage, NodeMapping, Sequence, Trigger, VehicleState};
-use jeflog::warn;
+use anyhow::bail;
+use common::comm::{Computer, FlightControlMessage, NodeMapping, SensorType, Sequence, Trigger, ValveState};
+use jeflog::{pass, warn};
 use postcard::experimental::max_size::MaxSize;
-use super::{Database, Shared};
-use std::future::Future;
-use tokio::{io::{self, AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream, UdpSocket}};
+use rusqlite::params;
+use sha2::{Digest, Sha256};
+use super::{
+	allowlist,
+	api::FlightInfo,
+	clock,
+	codec::{self, ChannelMetadata, ChannelMetadataMessage, FrameDecoder, InfoQuery, InfoReport, ParameterValue},
+	config::{ChannelConfig, Config},
+	error::{ErrorCode, ServerError},
+	hazard::HazardLevel,
+	metrics::Metrics,
+	outbox::Outbox,
+	quarantine,
+	recording::RecordedFrame,
+	serial,
+	telemetry::{self, Arrival, Frame, GapTracker, StreamTransport, TcpTransport, TelemetryTransport, UdpTransport},
+	trace,
+	Database,
+	Shared,
+};
+use std::{collections::{BTreeMap, HashMap}, future::Future, net::SocketAddr, sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use tokio::{
+	io::{self, AsyncReadExt, AsyncWriteExt},
+	net::{tcp::{OwnedReadHalf, OwnedWriteHalf}, TcpListener, TcpStream, UdpSocket},
+	sync::watch,
+};
+
+/// How long an accepted flight connection has to identify itself.
+const IDENTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the serial link checks whether it must take over from a lost network connection.
+const SERIAL_TAKEOVER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a sender of vehicle state goes unheard before its sequence numbers are forgotten.
+const SENDER_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The most senders of vehicle state whose sequence numbers are tracked at once.
+const MAX_SENDERS: usize = 64;
+
+/// The software information last reported by a computer, and when it was received.
+type Reports = watch::Receiver<Option<(FlightInfo, Instant)>>;
+
+/// The link over which messages are sent to a vehicle computer.
+#[derive(Debug)]
+enum ControlLink {
+	/// A TCP connection, made by the computer to servo over the network, whose
+	/// reading half is given to [`receive_reports`].
+	Tcp(OwnedWriteHalf),
+
+	/// A serial port, such as the umbilical hardline used when the network is down.
+	Serial(tokio::fs::File),
+}
+
+impl ControlLink {
+	async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+		match self {
+			ControlLink::Tcp(stream) => stream.write_all(bytes).await,
+			ControlLink::Serial(port) => {
+				// file writes are handed to a blocking thread, so they are only done once flushed.
+				port.write_all(bytes).await?;
+				port.flush().await
+			},
+		}
+	}
+}
 
 /// Struct capable of performing thread-safe operations on a flight computer
 /// connection, thus capable of being passed to route handlers.
 #[derive(Debug)]
 pub struct FlightComputer {
 	database: Database,
-	stream: TcpStream,
+	computer: Computer,
+	link: ControlLink,
+	connected_at: Instant,
+	reports: Reports,
+	sent_mappings: Option<[u8; 32]>,
+	sent_metadata: Option<[u8; 32]>,
+	interrupted_write: bool,
+	outbox: Arc<Outbox>,
 }
 
 impl FlightComputer {
-	/// Send a slice of bytes along the TCP connection to the flight computer.
-	pub async fn send_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
-		self.stream.write_all(bytes).await
+	/// Whether this connection is over a serial link rather than the network.
+	pub fn is_serial(&self) -> bool {
+		matches!(self.link, ControlLink::Serial(_))
 	}
 
-	/// Sends the given set of mappings to the flight computer.
-	pub async fn send_mappings(&mut self) -> anyhow::Result<()> {
+	/// Which computer of the vehicle this connection is to.
+	pub fn computer(&self) -> Computer {
+		self.computer
+	}
+
+	/// The number of seconds since the flight computer connected.
+	pub fn connected_seconds(&self) -> f64 {
+		self.connected_at.elapsed().as_secs_f64()
+	}
+
+	/// The latest software information reported by the flight computer, if any,
+	/// with its uptime brought up to date.
+	pub fn info(&self) -> Option<FlightInfo> {
+		self.reports.borrow().as_ref().map(|(info, reported_at)| {
+			FlightInfo {
+				uptime_seconds: info.uptime_seconds + reported_at.elapsed().as_secs_f64(),
+				..info.clone()
+			}
+		})
+	}
+
+	/// Asks the computer for its software information and the values of the
+	/// given runtime parameters, or all of them if none are given, returning a
+	/// receiver which changes once the answer arrives.
+	///
+	/// Vehicle state comes back over the serial link, so it carries no answers.
+	pub async fn query_info(&mut self, parameters: Vec<String>) -> anyhow::Result<Reports> {
+		if self.is_serial() {
+			bail!("the serial link cannot carry answers to queries");
+		}
+
+		let serialized = postcard::to_allocvec(&InfoQuery { parameters })?;
+
+		// only an answer arriving after the query is sent counts as a change.
+		let mut reports = self.reports.clone();
+		reports.borrow_and_update();
+
+		self.send_frame(codec::INFO_QUERY, &serialized, "query of software information").await?;
+		Ok(reports)
+	}
+
+	/// Frames a control message and sends it along the link to the flight computer.
+	pub async fn send_bytes(&mut self, bytes: &[u8], description: &str) -> io::Result<()> {
+		self.send_frame(codec::CONTROL_MESSAGE, bytes, description).await
+	}
+
+	/// Frames a message of a kind and sends it along the link to the flight computer,
+	/// keeping it in the outbox, described by `description`, until it is written.
+	///
+	/// A write which fails or is cancelled partway leaves the rest of a frame
+	/// unsent, after which the computer cannot find where messages begin, so the
+	/// connection refuses to send anything more and is replaced when it reconnects.
+	async fn send_frame(&mut self, kind: u8, bytes: &[u8], description: &str) -> io::Result<()> {
+		let id = self.outbox.begin(self.computer, description, trace::current());
+		let result = self.write_frame(kind, bytes).await;
+
+		// a cancelled write never gets here, so it stays pending in the outbox.
+		self.outbox.finish(id, result.as_ref().copied());
+		result
+	}
+
+	async fn write_frame(&mut self, kind: u8, bytes: &[u8]) -> io::Result<()> {
+		if self.interrupted_write {
+			return Err(io::Error::new(
+				io::ErrorKind::BrokenPipe,
+				"an earlier message was only partly sent, so the connection must be reestablished",
+			));
+		}
+
+		let frame = codec::encode(kind, bytes)?;
+
+		// cleared only once the whole frame is written, so this stays set if the write
+		// fails or its future is dropped partway, such as when a request times out.
+		self.interrupted_write = true;
+		self.link.write_all(&frame).await?;
+		self.interrupted_write = false;
+
+		Ok(())
+	}
+
+	/// Serializes and sends a message to the computer.
+	///
+	/// Messages sent while handling a request are logged with its ID, described
+	/// by `description`, so that a command can be traced from the GUI to the vehicle.
+	pub async fn send_message(&mut self, message: &FlightControlMessage, description: &str) -> anyhow::Result<()> {
+		let serialized = postcard::to_allocvec(message)?;
+		self.send_bytes(&serialized, description).await?;
+
+		if let Some(request_id) = trace::current() {
+			pass!("request_id={request_id} sent {description} to {} computer", computer_name(self.computer));
+		}
+
+		Ok(())
+	}
+
+	/// Sends the active mappings of the boards attached to this computer, unless
+	/// they are the same as those last sent over this connection, and returns
+	/// whether they were sent.
+	///
+	/// The whole set is sent whenever any mapping changes, since the protocol has
+	/// no message for a partial update.
+	pub async fn send_mappings(&mut self) -> anyhow::Result<bool> {
 		let mappings = self.database
 			.connection
 			.lock()
@@ -37,9 +207,10 @@ impl FlightComputer {
 					calibrated_offset,
 					powered_threshold,
 					normally_closed
-				FROM NodeMappings WHERE active = TRUE
+				FROM NodeMappings WHERE active = TRUE AND computer = ?1
+				ORDER BY text_id
 			")?
-			.query_and_then([], |row| {
+			.query_and_then([self.computer], |row| {
 				Ok(NodeMapping {
 					text_id: row.get(0)?,
 					board_id: row.get(1)?,
@@ -55,203 +226,722 @@ impl FlightComputer {
 			})?
 			.collect::<Result<Vec<NodeMapping>, rusqlite::Error>>()?;
 
-		let message = FlightControlMessage::Mappings(mappings);
-		let serialized = postcard::to_allocvec(&message)?;
+		let count = mappings.len();
+		let serialized = postcard::to_allocvec(&FlightControlMessage::Mappings(mappings))?;
+		let hash = Sha256::digest(&serialized).into();
 
-		self.send_bytes(&serialized).await?;
+		if self.sent_mappings == Some(hash) {
+			return Ok(false);
+		}
 
-		Ok(())
+		self.send_bytes(&serialized, &format!("{count} mappings")).await?;
+		self.sent_mappings = Some(hash);
+
+		let computer = computer_name(self.computer);
+
+		if let Some(request_id) = trace::current() {
+			pass!("request_id={request_id} sent {count} mappings to {computer} computer");
+		} else {
+			pass!("Sent {count} mappings to {computer} computer.");
+		}
+
+		Ok(true)
+	}
+
+	/// Sends the display names, hazard levels, and safing states of the channels
+	/// of the active configuration mapped to this computer, unless they are the
+	/// same as those last sent over this connection, and returns whether they were sent.
+	pub async fn send_metadata(&mut self, channels: &BTreeMap<String, ChannelConfig>) -> anyhow::Result<bool> {
+		let metadata = self.database
+			.connection
+			.lock()
+			.await
+			.prepare("
+				SELECT text_id, sensor_type, normally_closed, hazard_level
+				FROM NodeMappings WHERE active = TRUE AND computer = ?1
+			")?
+			.query_and_then([self.computer], |row| {
+				let text_id = row.get::<_, String>(0)?;
+				let normally_closed = row.get::<_, Option<bool>>(2)?;
+
+				// a valve is safed by cutting its power, which leaves it in its unpowered state.
+				let safing_state = matches!(row.get::<_, SensorType>(1)?, SensorType::Valve).then(|| {
+					if normally_closed.unwrap_or(true) { ValveState::Closed } else { ValveState::Open }
+				});
+
+				let metadata = ChannelMetadata {
+					display_name: channels.get(&text_id).and_then(|channel| channel.display_name.clone()),
+					// a level this server does not recognize is treated as the most hazardous
+					hazard_level: row.get::<_, String>(3)?.parse().unwrap_or(HazardLevel::Hazardous),
+					safing_state,
+				};
+
+				Ok((text_id, metadata))
+			})?
+			.collect::<Result<BTreeMap<_, _>, rusqlite::Error>>()?;
+
+		let count = metadata.len();
+
+		let serialized = postcard::to_allocvec(&ChannelMetadataMessage {
+			format_version: codec::METADATA_FORMAT_VERSION,
+			channels: metadata,
+		})?;
+
+		let hash = Sha256::digest(&serialized).into();
+
+		if self.sent_metadata == Some(hash) {
+			return Ok(false);
+		}
+
+		self.send_frame(codec::CHANNEL_METADATA, &serialized, &format!("metadata of {count} channels")).await?;
+		self.sent_metadata = Some(hash);
+
+		pass!("Sent metadata of {count} channels to {} computer.", computer_name(self.computer));
+		Ok(true)
 	}
 
 	/// Sends the given sequence to the flight computer to be executed.
 	pub async fn send_sequence(&mut self, sequence: Sequence) -> anyhow::Result<()> {
-		let message = FlightControlMessage::Sequence(sequence);
-		let serialized = postcard::to_allocvec(&message)?;
-
-		self.send_bytes(&serialized).await?;
-		Ok(())
+		let description = format!("sequence '{}'", sequence.name);
+		self.send_message(&FlightControlMessage::Sequence(sequence), &description).await
 	}
 
 	/// Instructs the flight computer to stop a sequence.
 	pub async fn stop_sequence(&mut self, name: String) -> anyhow::Result<()> {
-		let message = FlightControlMessage::StopSequence(name);
-		let serialized = postcard::to_allocvec(&message)?;
-
-		self.send_bytes(&serialized).await?;
-		Ok(())
+		let description = format!("stop of sequence '{name}'");
+		self.send_message(&FlightControlMessage::StopSequence(name), &description).await
 	}
 
 	/// Instructs the flight computer to abort.
 	pub async fn abort(&mut self) -> anyhow::Result<()> {
-		let message = FlightControlMessage::Abort;
-		let serialized = postcard::to_allocvec(&message)?;
-
-		self.send_bytes(&serialized).await?;
-		Ok(())
+		self.send_message(&FlightControlMessage::Abort, "abort").await
 	}
 
 	/// Sends all triggers stored in the database to the flight computer, active or not.
 	pub async fn send_trigger(&mut self, trigger: Trigger) -> anyhow::Result<()> {
-		let message = FlightControlMessage::Trigger(trigger);
-		let serialized = postcard::to_allocvec(&message)?;
-
-		self.send_bytes(&serialized).await?;
-		Ok(())
+		let description = format!("trigger '{}'", trigger.name);
+		self.send_message(&FlightControlMessage::Trigger(trigger), &description).await
 	}
 
-	/// Checks if the underlying TCP stream has been closed.
+	/// Checks if the underlying link has been closed, or can no longer be
+	/// sent messages because one was only partly written.
+	///
+	/// A network link is closed once [`receive_reports`] stops reading from it.
+	/// A serial port cannot tell whether anything is listening on the other end,
+	/// so it is never known to be closed.
 	pub fn check_closed(&self) -> bool {
-		let mut buffer = [0; 1];
-
-		// if the flight stream reads zero bytes, it's closed.
-		// this indicates that the current flight computer should not be there.
-		self.stream.try_read(&mut buffer).is_ok_and(|size| size == 0)
+		let link_closed = !self.is_serial() && self.reports.has_changed().is_err();
+		self.interrupted_write || link_closed
 	}
 
-	/// Sends a comprehensive update of mappings, triggers, and abort sequence to flight.
-	pub async fn update(&mut self) -> anyhow::Result<()> {
+	/// Sends a comprehensive update of mappings, triggers, and abort sequence to flight,
+	/// along with channel metadata if the configuration pushes it.
+	pub async fn update(&mut self, config: &Config) -> anyhow::Result<()> {
+		// mappings are always sent to a new connection, since none have been sent over it yet
 		self.send_mappings().await?;
-		
+
+		if config.flight.push_metadata {
+			self.send_metadata(&config.channels).await?;
+		}
+
 		// TODO: send triggers and abort sequence automatically
 
 		Ok(())
 	}
 }
 
+/// The name of a computer of the vehicle, as used in messages to operators.
+pub fn computer_name(computer: Computer) -> &'static str {
+	match computer {
+		Computer::Flight => "flight",
+		Computer::Ground => "ground",
+	}
+}
+
+/// The error returned when a command is sent to a computer which is not connected.
+pub fn not_connected(computer: Computer) -> ServerError {
+	ServerError::new(ErrorCode::ComputerNotConnected, format!("{} computer not connected", computer_name(computer)))
+		.with_context("computer", computer)
+}
+
+/// Sends the active mappings to every connected computer, each receiving only
+/// the mappings of the boards attached to it, and skipping any computer whose
+/// mappings did not change.
+///
+/// Channel metadata is sent the same way if the configuration pushes it.
+pub async fn send_mappings_to_all(shared: &Shared) -> anyhow::Result<()> {
+	let config = shared.config.current();
+
+	for computer in [Computer::Flight, Computer::Ground] {
+		if let Some(connection) = shared.connection(computer).0.lock().await.as_mut() {
+			connection.send_mappings().await?;
+
+			if config.flight.push_metadata {
+				connection.send_metadata(&config.channels).await?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
 /// A listener function which auto-connects to the flight computer.
 ///
 /// The flight computer is expected to fetch the IP address of the
 /// ground computer by hostname resolution, outside the scope of servo.
+///
+/// Each accepted connection is admitted on its own task, so that a slow or
+/// silent peer cannot hold up the connections behind it.
 pub fn auto_connect(server: &Shared) -> impl Future<Output = io::Result<()>> {
-	let database = server.database.clone();
-	let flight = server.flight.clone();
-	let ground = server.ground.clone();
+	let server = server.clone();
 
 	async move {
-		let listener = TcpListener::bind("0.0.0.0:5025").await?;
-		let mut buffer = [0; Computer::POSTCARD_MAX_SIZE];
+		let listener = TcpListener::bind(server.addresses.flight).await?;
 
 		loop {
-			let (mut stream, _) = listener.accept().await?;
+			let (stream, address) = listener.accept().await?;
 
-			let message_size = match stream.read(&mut buffer).await {
-				Ok(size) => size,
-				Err(error) => {
-					warn!("Received flight connection that failed to read from socket: {error}");
-					continue;
-				},
+			// only the primary talks to the vehicle, so a standby or fenced server closes the connection.
+			if !server.role.is_primary() {
+				continue;
+			}
+
+			tokio::spawn(admit(server.clone(), stream, address));
+		}
+	}
+}
+
+/// Identifies a connection accepted by [`auto_connect`] and installs it as the
+/// flight or ground computer.
+///
+/// Connections from hosts which are not allowed, or which fail the pre-shared-key
+/// handshake when a key is configured, are closed before they are identified.
+async fn admit(server: Shared, mut stream: TcpStream, address: SocketAddr) {
+	let Shared { config, database, flight, ground, lockout, metrics, outbox, .. } = server;
+
+	let flight_config = config.current().flight.clone();
+	let mut allowed_hosts = flight_config.allowed_hosts;
+
+	match allowlist::list(&*database.connection.lock().await) {
+		Ok(added) => allowed_hosts.extend(added.into_iter().map(|allowed| allowed.host)),
+		Err(error) => {
+			warn!("Refused flight connection from {address} because the allowlist could not be read: {error}");
+			return;
+		},
+	};
+
+	if !allowlist::permits(&allowed_hosts, address.ip()).await {
+		warn!("Refused flight connection from {address}, which is not on the allowlist.");
+		return;
+	}
+
+	let mut buffer = [0; Computer::POSTCARD_MAX_SIZE];
+
+	let message_size = match tokio::time::timeout(IDENTIFY_TIMEOUT, stream.read(&mut buffer)).await {
+		Ok(Ok(size)) => size,
+		Ok(Err(error)) => {
+			warn!("Received flight connection that failed to read from socket: {error}");
+			return;
+		},
+		Err(_) => {
+			warn!("Refused flight connection from {address}, which did not identify itself in time.");
+			return;
+		},
+	};
+
+	let computer = match postcard::from_bytes::<Computer>(&buffer[..message_size]) {
+		Ok(computer) => computer,
+		Err(error) => {
+			warn!("Failed to deserialize identity message: {error}");
+			return;
+		},
+	};
+
+	if let Some(key) = &flight_config.pre_shared_key {
+		match allowlist::authenticate(&mut stream, key).await {
+			Ok(true) => {},
+			Ok(false) => {
+				warn!("Refused flight connection from {address}, which failed the pre-shared-key handshake.");
+				return;
+			},
+			Err(error) => {
+				warn!("Refused flight connection from {address} after the handshake failed: {error}");
+				return;
+			},
+		}
+	}
+
+	match computer {
+		Computer::Flight => {
+			let mut flight = flight.0.lock().await;
+
+			// if there is a flight computer already in there, check if its stream is closed.
+			// the serial link is only a fallback, so a network connection takes over from it.
+			if let Some(existing) = &*flight {
+				if existing.check_closed() || existing.is_serial() {
+					*flight = None;
+				}
+			}
+
+			// only replace the flight connection with the new one if there isn't one there already.
+			// otherwise, this defaults to gracefully closing the new connection on drop.
+			if flight.is_none() {
+				let (reader, writer) = stream.into_split();
+
+				let mut new_flight = FlightComputer {
+					link: ControlLink::Tcp(writer),
+					database: database.clone(),
+					computer: Computer::Flight,
+					connected_at: Instant::now(),
+					reports: receive_reports(reader, Computer::Flight, metrics.clone()),
+					sent_mappings: None,
+					sent_metadata: None,
+					interrupted_write: false,
+					outbox: outbox.clone(),
+				};
+
+				if let Err(error) = new_flight.update(&config.current()).await {
+					warn!("Failed to send comprehensive update to new flight: {error}");
+					return;
+				}
+
+				// a newly connected flight computer is not running any sequences.
+				lockout.clear().await;
+				*flight = Some(new_flight);
+			}
+		},
+		Computer::Ground => {
+			let mut ground = ground.0.lock().await;
+
+			if let Some(existing) = &*ground {
+				if existing.check_closed() {
+					*ground = None;
+				}
+			}
+
+			if ground.is_none() {
+				let (reader, writer) = stream.into_split();
+
+				let mut new_ground = FlightComputer {
+					link: ControlLink::Tcp(writer),
+					database: database.clone(),
+					computer: Computer::Ground,
+					connected_at: Instant::now(),
+					reports: receive_reports(reader, Computer::Ground, metrics.clone()),
+					sent_mappings: None,
+					sent_metadata: None,
+					interrupted_write: false,
+					outbox: outbox.clone(),
+				};
+
+				if let Err(error) = new_ground.update(&config.current()).await {
+					warn!("Failed to send comprehensive update to new flight: {error}");
+					return;
+				}
+
+				*ground = Some(new_ground);
+			}
+		},
+	};
+}
+
+/// Reads what a computer sends back over its control link, keeping the latest
+/// answer to an [`InfoQuery`] in the returned receiver, until the link closes or
+/// its connection is replaced.
+///
+/// Messages of other kinds are skipped. Once the link cannot be framed, the
+/// reader stops, after which the connection counts as closed.
+fn receive_reports(mut reader: OwnedReadHalf, computer: Computer, metrics: Arc<Metrics>) -> Reports {
+	let (sender, receiver) = watch::channel(None);
+
+	tokio::spawn(async move {
+		let name = computer_name(computer);
+		let mut decoder = FrameDecoder::default();
+		let mut chunk = [0; 4096];
+
+		loop {
+			let read = tokio::select! {
+				read = reader.read(&mut chunk) => read,
+				_ = sender.closed() => return,
 			};
 
-			let computer = match postcard::from_bytes::<Computer>(&buffer[..message_size]) {
-				Ok(computer) => computer,
+			match read {
+				Ok(0) => return,
+				Ok(read) => decoder.push(&chunk[..read]),
 				Err(error) => {
-					warn!("Failed to deserialize identity message: {error}");
-					continue;
+					warn!("Failed to read from the control link to {name} computer: {error}");
+					return;
 				},
 			};
 
-			match computer {
-				Computer::Flight => {
-					let mut flight = flight.0.lock().await;
-
-					// if there is a flight computer already in there, check if its stream is closed.
-					if let Some(existing) = &*flight {
-						if existing.check_closed() {
-							*flight = None;
-						}
-					}
-
-					// only replace the flight connection with the new one if there isn't one there already.
-					// otherwise, this defaults to gracefully closing the new connection on drop.
-					if flight.is_none() {
-						let mut new_flight = FlightComputer {
-							stream,
-							database: database.clone(),
-						};
-
-						if let Err(error) = new_flight.update().await {
-							warn!("Failed to send comprehensive update to new flight: {error}");
-							continue;
-						}
-
-						*flight = Some(new_flight);
-					}
-				},
-				Computer::Ground => {
-					let mut ground = ground.0.lock().await;
-
-					if let Some(existing) = &*ground {
-						let mut buffer = [0; 1];
-
-						// if the flight stream reads zero bytes, it's closed.
-						// this indicates that the current flight computer should not be there.
-						if existing.stream.try_read(&mut buffer).is_ok_and(|size| size == 0) {
-							*ground = None;
-						}
-					}
-
-					if ground.is_none() {
-						let mut new_ground = FlightComputer {
-							stream,
-							database: database.clone(),
-						};
-
-						if let Err(error) = new_ground.update().await {
-							warn!("Failed to send comprehensive update to new flight: {error}");
-							continue;
-						}
-
-						*ground = Some(new_ground);
-					}
-				},
-			};
+			// a read may hold several messages or part of one
+			loop {
+				let report = match decoder.next_message() {
+					Ok(Some((codec::INFO_REPORT, report))) => report,
+					Ok(Some(_)) => continue,
+					Ok(None) => break,
+					Err(error) => {
+						warn!("Dropping the control link to {name} computer, whose messages cannot be framed: {error}");
+						return;
+					},
+				};
+
+				let report = match postcard::from_bytes::<InfoReport>(&report) {
+					Ok(report) => report,
+					Err(error) => {
+						warn!("Failed to deserialize software information from {name} computer: {error}");
+						continue;
+					},
+				};
+
+				if matches!(computer, Computer::Flight) {
+					metrics.clocks.lock().await.record(clock::FLIGHT_COMPUTER, report.reported_at, clock::now());
+				}
+
+				sender.send_replace(Some((flight_info(report), Instant::now())));
+			}
 		}
+	});
+
+	receiver
+}
+
+/// Converts the software information reported over the control link into
+/// that served by the API.
+fn flight_info(report: InfoReport) -> FlightInfo {
+	let parameters = report.parameters
+		.into_iter()
+		.map(|(name, value)| {
+			let value = match value {
+				ParameterValue::Bool(value) => serde_json::Value::from(value),
+				ParameterValue::Integer(value) => serde_json::Value::from(value),
+				ParameterValue::Float(value) => serde_json::Value::from(value),
+				ParameterValue::Text(value) => serde_json::Value::from(value),
+			};
+
+			(name, value)
+		})
+		.collect();
+
+	FlightInfo {
+		version: report.version,
+		build_hash: report.build_hash,
+		uptime_seconds: report.uptime_seconds,
+		reported_at: Some(report.reported_at),
+		parameters,
 	}
 }
 
-/// Repeatedly receives vehicle state information from the flight computer.
+/// Repeatedly receives vehicle state information from the flight computer over UDP.
 pub fn receive_vehicle_state(shared: &Shared) -> impl Future<Output = io::Result<()>> {
-	let vehicle_state = shared.vehicle.clone();
+	let shared = shared.clone();
+
+	async move {
+		let max_datagram_size = shared.config.current().flight.max_datagram_size();
+		let socket = UdpSocket::bind(shared.addresses.telemetry).await?;
+		receive_frames(UdpTransport::new(socket, max_datagram_size), &shared).await;
+
+		Ok(())
+	}
+}
+
+/// Accepts TCP telemetry connections from flight computers which opt into
+/// the reliable stream transport, receiving vehicle state from each.
+pub fn receive_vehicle_state_stream(shared: &Shared) -> impl Future<Output = io::Result<()>> {
+	let shared = shared.clone();
 
 	async move {
-		let socket = UdpSocket::bind("0.0.0.0:7201").await.unwrap();
-		let mut frame_buffer = vec![0; 20_000];
+		let listener = TcpListener::bind(shared.addresses.telemetry).await?;
 
 		loop {
-			match socket.recv_from(&mut frame_buffer).await {
-				Ok((datagram_size, _)) => {
-					if datagram_size == 0 {
-						// if the datagram size is zero, the connection has been closed
-						break;
-					} else if datagram_size == frame_buffer.len() {
-						frame_buffer.resize(frame_buffer.len() * 2, 0);
-						println!("resized buffer");
-						continue;
-					}
+			let (stream, address) = listener.accept().await?;
+			let shared = shared.clone();
 
-					let new_state = postcard::from_bytes::<VehicleState>(&frame_buffer[..datagram_size]);
+			tokio::spawn(async move {
+				receive_frames(TcpTransport::new(stream, address), &shared).await;
+				warn!("Vehicle state stream from {address} closed.");
+			});
+		}
+	}
+}
 
-					match new_state {
-						Ok(state) => {
-							*vehicle_state.0.lock().await = state;
-							vehicle_state.1.notify_waiters();
-						},
-						Err(error) => warn!("Failed to deserialize vehicle state: {error}"),
-					};
-				},
-				Err(error) => {
-					// Windows throws this error when the buffer is not large enough.
-					// Unix systems just log whatever they can.
-					if error.raw_os_error() == Some(10040) {
-						frame_buffer.resize(frame_buffer.len() * 2, 0);
-						continue;
-					}
+/// Opens the configured serial link to the flight computer, receiving vehicle
+/// state over it and sending messages over it whenever no network connection
+/// to the flight computer is up.
+///
+/// Vehicle state arrives as length-prefixed frames, as over TCP. The link is a
+/// dedicated hardline, so the flight computer is not asked to identify itself
+/// or complete the pre-shared-key handshake over it.
+pub fn connect_serial(shared: &Shared) -> impl Future<Output = io::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let serial = shared.config.current().flight.serial.clone();
+
+		let Some(device) = serial.device else {
+			return Err(io::Error::other("no serial device is configured"));
+		};
+
+		let port = tokio::task::spawn_blocking(move || serial::open(&device, serial.baud_rate).map(|port| (port, device)))
+			.await
+			.map_err(io::Error::other)?;
+
+		let (port, device) = port?;
+		let reader = tokio::fs::File::from_std(port.try_clone()?);
+		pass!("Opened serial link to flight computer on \x1b[1m{}\x1b[0m.", device.display());
+
+		let receiver = tokio::spawn({
+			let shared = shared.clone();
+			async move { receive_frames(StreamTransport::unaddressed(reader), &shared).await }
+		});
+
+		let mut interval = tokio::time::interval(SERIAL_TAKEOVER_INTERVAL);
+
+		while !receiver.is_finished() {
+			interval.tick().await;
+
+			// only the primary talks to the vehicle.
+			if !shared.role.is_primary() {
+				continue;
+			}
+
+			let mut flight = shared.flight.0.lock().await;
+
+			if flight.as_ref().is_some_and(|existing| !existing.check_closed()) {
+				continue;
+			}
+
+			let mut serial_flight = FlightComputer {
+				link: ControlLink::Serial(tokio::fs::File::from_std(port.try_clone()?)),
+				database: shared.database.clone(),
+				computer: Computer::Flight,
+				connected_at: Instant::now(),
+				// nothing is read back over the serial link, so no report ever arrives.
+				reports: watch::channel(None).1,
+				sent_mappings: None,
+				sent_metadata: None,
+				interrupted_write: false,
+				outbox: shared.outbox.clone(),
+			};
+
+			if let Err(error) = serial_flight.update(&shared.config.current()).await {
+				warn!("Failed to send comprehensive update to flight over serial: {error}");
+				continue;
+			}
+
+			warn!("Talking to flight computer over the serial link on \x1b[1m{}\x1b[0m.", device.display());
+			shared.lockout.clear().await;
+			*flight = Some(serial_flight);
+		}
+
+		Err(io::Error::other(format!("serial link on {} closed", device.display())))
+	}
+}
 
-					break;
+/// Deserializes frames from a telemetry transport into the vehicle state until the transport closes.
+///
+/// Sequenced frames are checked for gaps, which are counted in the telemetry
+/// metrics and recorded in the database with the time range they cover. Each
+/// sender counts its own sequence numbers, so each is tracked separately, until
+/// it goes unheard for [`SENDER_IDLE_TIMEOUT`] or is the longest unheard of more
+/// than [`MAX_SENDERS`].
+async fn receive_frames(mut transport: impl TelemetryTransport, shared: &Shared) {
+	let mut senders: HashMap<_, (GapTracker, Option<f64>, Instant)> = HashMap::new();
+
+	loop {
+		let Frame { source, bytes: frame, truncated } = match transport.receive_frame().await {
+			Ok(Some(received)) => received,
+			Ok(None) => break,
+			Err(error) => {
+				warn!("Failed to receive vehicle state: {error}");
+				break;
+			},
+		};
+
+		let received_instant = Instant::now();
+		let received_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0.0, |since_epoch| since_epoch.as_secs_f64());
+
+		let sender = source.map_or_else(|| "an unknown sender".to_owned(), |source| source.to_string());
+
+		// frames are recorded before they are decoded, so that malformed frames can be examined later
+		if shared.config.current().recording.path.is_some() {
+			shared.recorder.record(RecordedFrame { received_at, source, frame: frame.to_vec() });
+		}
+
+		// the rest of an oversized frame is gone, so what arrived is kept to be diagnosed
+		if truncated {
+			shared.metrics.telemetry.lock().await.oversized_frames += 1;
+			warn!("Received a vehicle state frame from {sender} larger than the {}-byte limit, quarantining its beginning.", frame.len());
+
+			let stored = quarantine::store(
+				&*shared.database.connection.lock().await,
+				received_at,
+				source,
+				frame,
+				&format!("frame exceeded the {}-byte datagram limit and was truncated", frame.len()),
+			);
+
+			if let Err(error) = stored {
+				warn!("Failed to quarantine oversized frame in database: {error}");
+			}
+
+			continue;
+		}
+
+		// a sender which restarts comes back on a new port, so senders are forgotten rather than kept forever.
+		if !senders.contains_key(&source) {
+			senders.retain(|_, (_, _, heard_at)| heard_at.elapsed() < SENDER_IDLE_TIMEOUT);
+
+			if senders.len() >= MAX_SENDERS {
+				let longest_unheard = senders
+					.iter()
+					.min_by_key(|(_, (_, _, heard_at))| *heard_at)
+					.map(|(sender, _)| *sender);
+
+				if let Some(longest_unheard) = longest_unheard {
+					senders.remove(&longest_unheard);
 				}
 			}
 		}
 
-		Ok(())
+		let (tracker, last_received_at, heard_at) = senders
+			.entry(source)
+			.or_insert_with(|| (GapTracker::default(), None, received_instant));
+
+		*heard_at = received_instant;
+		let (arrival, decoded) = telemetry::decode_frame(tracker, frame);
+
+		shared.metrics.telemetry.lock().await.record(arrival);
+
+		if let Some(Arrival::Gap { first_missing, last_missing }) = arrival {
+			warn!("Lost vehicle state frames {first_missing} through {last_missing}.");
+
+			let query_result = shared.database
+				.connection
+				.lock()
+				.await
+				.execute(
+					"INSERT INTO TelemetryGaps (first_missing, last_missing, started_at, ended_at) VALUES (?1, ?2, ?3, ?4)",
+					params![first_missing as i64, last_missing as i64, last_received_at.unwrap_or(received_at), received_at],
+				);
+
+			if let Err(error) = query_result {
+				warn!("Failed to record telemetry gap in database: {error}");
+			}
+		}
+
+		// an out-of-order frame is older than the current state, so it must not replace it.
+		let Some(decoded) = decoded else {
+			continue;
+		};
+
+		*last_received_at = Some(received_at);
+
+		match decoded {
+			Ok(state) => {
+				shared.metrics.frame_received(received_instant).await;
+				*shared.vehicle.0.lock().await = Arc::new(state);
+				shared.vehicle.1.notify_waiters();
+			},
+			Err(error) => {
+				warn!("Failed to deserialize {}-byte vehicle state frame from {sender}, quarantining it: {error}", frame.len());
+
+				let stored = quarantine::store(
+					&*shared.database.connection.lock().await,
+					received_at,
+					source,
+					frame,
+					&error.to_string(),
+				);
+
+				if let Err(error) = stored {
+					warn!("Failed to quarantine bad frame in database: {error}");
+				}
+			},
+		};
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::net::TcpStream;
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn unchanged_mappings_are_not_resent() {
+		let database = Database::volatile().unwrap();
+		tokio::task::block_in_place(|| database.migrate()).unwrap();
+
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let stream = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+		let (_flight, _) = listener.accept().await.unwrap();
+		let (_, writer) = stream.into_split();
+
+		let mut connection = FlightComputer {
+			database: database.clone(),
+			computer: Computer::Flight,
+			link: ControlLink::Tcp(writer),
+			connected_at: Instant::now(),
+			reports: watch::channel(None).1,
+			sent_mappings: None,
+			sent_metadata: None,
+			interrupted_write: false,
+			outbox: Arc::default(),
+		};
+
+		let insert = |text_id: &str| {
+			database.connection.blocking_lock().execute(
+				"INSERT INTO NodeMappings (text_id, configuration_id, channel, board_id, sensor_type, computer, active)
+				VALUES (?1, 'test', 1, 'sam-01', 'pt', 'flight', TRUE)",
+				[text_id],
+			)
+		};
+
+		tokio::task::block_in_place(|| insert("KBPT")).unwrap();
+		assert!(connection.send_mappings().await.unwrap());
+		assert!(!connection.send_mappings().await.unwrap());
+
+		tokio::task::block_in_place(|| insert("WTPT")).unwrap();
+		assert!(connection.send_mappings().await.unwrap());
+
+		assert!(connection.send_metadata(&BTreeMap::new()).await.unwrap());
+		assert!(!connection.send_metadata(&BTreeMap::new()).await.unwrap());
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn queries_are_answered_over_the_control_link() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let mut flight = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+		let (stream, _) = listener.accept().await.unwrap();
+		let (reader, _writer) = stream.into_split();
+
+		let metrics = Arc::new(Metrics::default());
+		let mut reports = receive_reports(reader, Computer::Flight, metrics.clone());
+
+		let report = InfoReport {
+			version: "1.2.3".to_owned(),
+			build_hash: "abc123".to_owned(),
+			uptime_seconds: 60.0,
+			reported_at: clock::now() + 100.0,
+			parameters: BTreeMap::from([("ignition_delay".to_owned(), ParameterValue::Float(0.5))]),
+		};
+
+		// a message of a kind servo does not expect is skipped
+		let mut frames = codec::encode(codec::CONTROL_MESSAGE, b"unexpected").unwrap();
+		frames.extend(codec::encode(codec::INFO_REPORT, &postcard::to_allocvec(&report).unwrap()).unwrap());
+		flight.write_all(&frames).await.unwrap();
+
+		reports.changed().await.unwrap();
+		let info = reports.borrow().as_ref().unwrap().0.clone();
+
+		assert_eq!(info.version, "1.2.3");
+		assert_eq!(info.parameters["ignition_delay"], serde_json::json!(0.5));
+		assert!(metrics.clocks.lock().await.current(clock::now()).contains_key(clock::FLIGHT_COMPUTER));
+
+		// the link counts as closed once the computer hangs up
+		drop(flight);
+		assert!(reports.changed().await.is_err());
 	}
 }
@@ -0,0 +1,266 @@
+use axum::{extract::State, http::StatusCode, Json};
+use common::comm::{NodeMapping, VehicleState};
+use futures_util::{stream, Stream};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{pin::Pin, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+use tokio::time::MissedTickBehavior;
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+
+use super::{api::SetMappingsRequest, auth::Session, error::{bad_request, ServerError}, routes, selector::Selection, validation::Valid, Shared};
+
+/// Types and services generated from `proto/servo.proto`.
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+	tonic::include_proto!("servo.v1");
+}
+
+use proto::{servo_server::Servo, Empty};
+
+/// The path under which gRPC requests are routed, alongside the REST API.
+pub const ROUTE: &str = "/servo.v1.Servo/*rpc";
+
+/// The metadata key carrying the code of an error, as in the REST API.
+const ERROR_CODE_KEY: &str = "servo-error-code";
+
+impl From<ServerError> for Status {
+	fn from(error: ServerError) -> Self {
+		let code = error.code();
+		let message = error.to_string();
+
+		let mut status = match code.status() {
+			StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+			StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+			StatusCode::FORBIDDEN => Status::permission_denied(message),
+			StatusCode::NOT_FOUND => Status::not_found(message),
+			StatusCode::CONFLICT | StatusCode::LOCKED => Status::failed_precondition(message),
+			StatusCode::REQUEST_TIMEOUT => Status::deadline_exceeded(message),
+			_ => Status::internal(message),
+		};
+
+		status.metadata_mut().insert(ERROR_CODE_KEY, MetadataValue::from_static(code.as_str()));
+		status
+	}
+}
+
+/// Writes a value the way it is written in the REST API, such as a unit or a valve state.
+fn rest_name(value: &impl Serialize) -> String {
+	match serde_json::to_value(value) {
+		Ok(serde_json::Value::String(name)) => name,
+		Ok(other) => other.to_string(),
+		Err(_) => String::new(),
+	}
+}
+
+/// Reads a value the way it is written in the REST API, such as a sensor type or a computer.
+fn from_rest_name<T: DeserializeOwned>(field: &str, name: &str) -> super::Result<T> {
+	serde_json::from_value(serde_json::Value::String(name.to_owned()))
+		.map_err(|_| bad_request(format!("invalid {field} '{name}'")))
+}
+
+/// Parses the channel selectors of a request, such as `FU_*`.
+fn channel_selection(channels: &[String]) -> super::Result<Selection> {
+	Selection::parse(channels).map_err(bad_request)
+}
+
+/// Converts a vehicle state into its message, keeping only the selected channels unless none are selected.
+fn vehicle_state_message(vehicle_state: &VehicleState, channels: &Selection) -> proto::VehicleState {
+	let selected = |channel: &String| channels.is_empty() || channels.matches(channel);
+
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |duration| duration.as_secs_f64());
+
+	proto::VehicleState {
+		timestamp,
+		sensor_readings: vehicle_state.sensor_readings
+			.iter()
+			.filter(|(channel, _)| selected(channel))
+			.map(|(channel, reading)| {
+				(channel.clone(), proto::Measurement { value: reading.value, unit: rest_name(&reading.unit) })
+			})
+			.collect(),
+		valve_states: vehicle_state.valve_states
+			.iter()
+			.filter(|(channel, _)| selected(channel))
+			.map(|(channel, valve_state)| {
+				let message = proto::ValveState {
+					commanded: rest_name(&valve_state.commanded),
+					actual: rest_name(&valve_state.actual),
+				};
+
+				(channel.clone(), message)
+			})
+			.collect(),
+	}
+}
+
+/// Converts a mapping message into a `NodeMapping`.
+fn node_mapping(mapping: proto::NodeMapping) -> super::Result<NodeMapping> {
+	Ok(NodeMapping {
+		sensor_type: from_rest_name("sensor type", &mapping.sensor_type)?,
+		computer: from_rest_name("computer", &mapping.computer)?,
+		text_id: mapping.text_id,
+		board_id: mapping.board_id,
+		channel: mapping.channel,
+		max: mapping.max,
+		min: mapping.min,
+		calibrated_offset: mapping.calibrated_offset,
+		powered_threshold: mapping.powered_threshold,
+		normally_closed: mapping.normally_closed,
+	})
+}
+
+/// The gRPC control API, which performs each operation through the same route
+/// function as the REST API so that both are subject to the same checks.
+#[derive(Clone)]
+pub struct ServoService {
+	shared: Shared,
+}
+
+impl ServoService {
+	/// Creates the service over the shared server state.
+	pub fn new(shared: &Shared) -> Self {
+		ServoService { shared: shared.clone() }
+	}
+}
+
+#[tonic::async_trait]
+impl Servo for ServoService {
+	type StreamStateStream = Pin<Box<dyn Stream<Item = Result<proto::VehicleState, Status>> + Send>>;
+
+	async fn stream_state(&self, request: Request<proto::StreamStateRequest>) -> Result<Response<Self::StreamStateStream>, Status> {
+		let request = request.into_inner();
+
+		// a rate of zero is the default in protobuf, so it stands for the configured rate
+		let requested_hz = (request.rate_hz != 0.0).then_some(request.rate_hz);
+		let rate_hz = self.shared.config.current().forwarding.rate_hz(requested_hz);
+
+		let period = Duration::try_from_secs_f64(1.0 / rate_hz)
+			.map_err(|_| Status::invalid_argument("rate must be a positive number of hertz"))?
+			.max(Duration::from_millis(1));
+
+		let mut interval = tokio::time::interval(period);
+		interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+		let shared = self.shared.clone();
+		let channels = Arc::new(channel_selection(&request.channels)?);
+
+		// a state is only sent once it has changed, like forwarding over WebSockets
+		let states = stream::unfold((interval, None::<Arc<VehicleState>>), move |(mut interval, mut last_sent)| {
+			let shared = shared.clone();
+			let channels = channels.clone();
+
+			async move {
+				loop {
+					interval.tick().await;
+					let snapshot = Arc::clone(&*shared.vehicle.0.lock().await);
+
+					if last_sent.as_ref().is_some_and(|last_sent| Arc::ptr_eq(last_sent, &snapshot)) {
+						continue;
+					}
+
+					let message = vehicle_state_message(&snapshot, &channels);
+					last_sent = Some(snapshot);
+
+					return Some((Ok(message), (interval, last_sent)));
+				}
+			}
+		});
+
+		Ok(Response::new(Box::pin(states)))
+	}
+
+	async fn get_state(&self, request: Request<proto::GetStateRequest>) -> Result<Response<proto::VehicleState>, Status> {
+		let channels = channel_selection(&request.into_inner().channels)?;
+		let snapshot = Arc::clone(&*self.shared.vehicle.0.lock().await);
+		Ok(Response::new(vehicle_state_message(&snapshot, &channels)))
+	}
+
+	async fn send_command(&self, request: Request<proto::CommandRequest>) -> Result<Response<Empty>, Status> {
+		let session = request.extensions().get::<Session>().cloned();
+		let request = request.into_inner();
+
+		let Json(response) = routes::dispatch_operator_command(State(self.shared.clone()), session, Valid::new(Json(routes::OperatorCommandRequest {
+			command: request.command,
+			target: request.target,
+			state: request.state,
+		}))?).await?;
+
+		// the command was held for a second operator, who confirms it through the REST API
+		if let Some(id) = response.awaiting_confirmation {
+			return Err(Status::failed_precondition(format!("hazardous command awaiting confirmation {id}")));
+		}
+
+		Ok(Response::new(Empty {}))
+	}
+
+	async fn run_sequence(&self, request: Request<proto::RunSequenceRequest>) -> Result<Response<Empty>, Status> {
+		// the session was attached by the same middleware which authenticates the REST API
+		let session = request.extensions().get::<Session>().cloned();
+		let request = request.into_inner();
+
+		routes::run_sequence(State(self.shared.clone()), session, Valid::new(Json(routes::RunSequenceRequest {
+			name: request.name,
+			force: Some(request.force),
+		}))?).await?;
+
+		Ok(Response::new(Empty {}))
+	}
+
+	async fn stop_sequence(&self, request: Request<proto::StopSequenceRequest>) -> Result<Response<Empty>, Status> {
+		let name = request.into_inner().name;
+		routes::stop_sequence(State(self.shared.clone()), Valid::new(Json(routes::StopSequenceRequest { name }))?).await?;
+
+		Ok(Response::new(Empty {}))
+	}
+
+	async fn abort(&self, _request: Request<Empty>) -> Result<Response<Empty>, Status> {
+		routes::abort(State(self.shared.clone())).await?;
+		Ok(Response::new(Empty {}))
+	}
+
+	async fn push_mappings(&self, request: Request<proto::PushMappingsRequest>) -> Result<Response<Empty>, Status> {
+		let request = request.into_inner();
+
+		let mappings = request.mappings
+			.into_iter()
+			.map(node_mapping)
+			.collect::<super::Result<Vec<_>>>()?;
+
+		routes::post_mappings(State(self.shared.clone()), Valid::new(Json(SetMappingsRequest {
+			configuration_id: request.configuration_id,
+			mappings,
+			hazard_levels: Default::default(),
+		}))?).await?;
+
+		Ok(Response::new(Empty {}))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Computer, SensorType};
+	use super::*;
+
+	#[test]
+	fn mappings_are_read_like_the_rest_api() {
+		let mut message = proto::NodeMapping {
+			text_id: "KBPT".to_owned(),
+			board_id: "sam-01".to_owned(),
+			sensor_type: rest_name(&SensorType::Pt),
+			channel: 3,
+			computer: rest_name(&Computer::Flight),
+			max: Some(1000.0),
+			..proto::NodeMapping::default()
+		};
+
+		let mapping = node_mapping(message.clone()).unwrap();
+		assert_eq!(mapping.sensor_type, SensorType::Pt);
+		assert_eq!(mapping.computer, Computer::Flight);
+		assert_eq!(mapping.max, Some(1000.0));
+
+		message.sensor_type = "not a sensor".to_owned();
+		let status = Status::from(node_mapping(message).unwrap_err());
+		assert_eq!(status.code(), tonic::Code::InvalidArgument);
+	}
+}
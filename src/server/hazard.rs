@@ -0,0 +1,228 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, fmt, str::FromStr, time::{Duration, Instant}};
+use tokio::sync::Mutex;
+
+use super::alert::Severity;
+
+/// How long a command awaiting a second operator's confirmation stays pending.
+pub const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How hazardous it is to change a channel, as classified for each configuration.
+///
+/// Levels are kept in servo's own schema rather than in mappings, and are only
+/// sent to the flight computer as channel metadata if it is pushed. They drive
+/// warnings in GUIs, the severity commands touching the channel are audited
+/// with, and whether such commands need a second operator to confirm them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardLevel {
+	/// Changing the channel is routine.
+	#[default]
+	None,
+
+	/// Changing the channel deserves a second look, so GUIs warn before doing so.
+	Caution,
+
+	/// Changing the channel could hurt someone, so a second operator must confirm it.
+	Hazardous,
+}
+
+impl HazardLevel {
+	/// The name of the level, as stored in the `NodeMappings` table.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::None => "none",
+			Self::Caution => "caution",
+			Self::Hazardous => "hazardous",
+		}
+	}
+
+	/// The severity of audit entries for commands touching a channel of this level.
+	pub fn audit_severity(self) -> Severity {
+		match self {
+			Self::None => Severity::Info,
+			Self::Caution => Severity::Warning,
+			Self::Hazardous => Severity::Critical,
+		}
+	}
+
+	/// Whether commands touching a channel of this level must be confirmed by a second operator.
+	pub fn requires_confirmation(self) -> bool {
+		self >= Self::Hazardous
+	}
+}
+
+impl fmt::Display for HazardLevel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl FromStr for HazardLevel {
+	type Err = String;
+
+	fn from_str(level: &str) -> Result<Self, Self::Err> {
+		match level {
+			"none" => Ok(Self::None),
+			"caution" => Ok(Self::Caution),
+			"hazardous" => Ok(Self::Hazardous),
+			level => Err(format!("unrecognized hazard level '{level}', expected none, caution, or hazardous")),
+		}
+	}
+}
+
+/// The hazard levels of the channels of a configuration above [`HazardLevel::None`],
+/// or of the active configuration if none is given.
+pub fn levels(connection: &Connection, configuration_id: Option<&str>) -> rusqlite::Result<BTreeMap<String, HazardLevel>> {
+	connection
+		.prepare("
+			SELECT text_id, hazard_level FROM NodeMappings
+			WHERE hazard_level != 'none' AND IFNULL(configuration_id = ?1, active)
+		")?
+		.query_map([configuration_id], |row| {
+			let level = row.get::<_, String>(1)?;
+			Ok((row.get(0)?, level.parse().unwrap_or(HazardLevel::Hazardous)))
+		})?
+		.collect()
+}
+
+/// The hazard level of a channel in the active configuration.
+pub fn level(connection: &Connection, text_id: &str) -> rusqlite::Result<HazardLevel> {
+	let level = connection
+		.query_row(
+			"SELECT hazard_level FROM NodeMappings WHERE text_id = ?1 AND active",
+			[text_id],
+			|row| row.get::<_, String>(0),
+		)
+		.optional()?;
+
+	// a level this server does not recognize is treated as the most hazardous
+	Ok(level.map_or(HazardLevel::None, |level| level.parse().unwrap_or(HazardLevel::Hazardous)))
+}
+
+/// Sets the hazard levels of channels of a configuration, keyed by the text ID of each mapping.
+pub fn set_levels(connection: &Connection, configuration_id: &str, levels: &BTreeMap<String, HazardLevel>) -> rusqlite::Result<()> {
+	for (text_id, level) in levels {
+		connection.execute(
+			"UPDATE NodeMappings SET hazard_level = ?1 WHERE configuration_id = ?2 AND text_id = ?3",
+			params![level.as_str(), configuration_id, text_id],
+		)?;
+	}
+
+	Ok(())
+}
+
+/// A command touching a hazardous channel, held until a second operator confirms it.
+#[derive(Clone, Debug)]
+pub struct PendingCommand<C> {
+	/// The command, as it will be dispatched once confirmed.
+	pub command: C,
+
+	/// The user who requested the command, who may not also confirm it.
+	pub requested_by: String,
+
+	/// A description of the command, for the audit log.
+	pub description: String,
+
+	/// When the command stops waiting to be confirmed.
+	pub expires_at: Instant,
+}
+
+/// Commands awaiting confirmation by a second operator, keyed by their
+/// confirmation IDs, which enforce the two-person rule for hazardous channels.
+#[derive(Debug)]
+pub struct Confirmations<C> {
+	pending: Mutex<HashMap<String, PendingCommand<C>>>,
+}
+
+impl<C> Default for Confirmations<C> {
+	fn default() -> Self {
+		Confirmations { pending: Mutex::new(HashMap::new()) }
+	}
+}
+
+impl<C> Confirmations<C> {
+	/// Holds a command until it is confirmed, returning the ID it is confirmed by.
+	pub async fn request(&self, command: C, requested_by: &str, description: String) -> String {
+		let id = format!("{:08x}", rand::random::<u32>());
+		let mut pending = self.pending.lock().await;
+		let now = Instant::now();
+
+		pending.retain(|_, command| command.expires_at > now);
+		pending.insert(id.clone(), PendingCommand {
+			command,
+			requested_by: requested_by.to_owned(),
+			description,
+			expires_at: now + CONFIRMATION_TIMEOUT,
+		});
+
+		id
+	}
+
+	/// Releases a pending command to be dispatched once it is confirmed by a
+	/// user other than the one who requested it.
+	pub async fn confirm(&self, id: &str, confirmed_by: &str) -> Result<PendingCommand<C>, ConfirmationError> {
+		let mut pending = self.pending.lock().await;
+
+		let Some(command) = pending.get(id) else {
+			return Err(ConfirmationError::Unknown);
+		};
+
+		if command.expires_at <= Instant::now() {
+			pending.remove(id);
+			return Err(ConfirmationError::Expired);
+		}
+
+		if command.requested_by == confirmed_by {
+			return Err(ConfirmationError::SameOperator);
+		}
+
+		Ok(pending.remove(id).unwrap())
+	}
+}
+
+/// Why a command could not be confirmed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfirmationError {
+	/// No command awaits the confirmation, or it was already confirmed.
+	Unknown,
+
+	/// The command waited longer than [`CONFIRMATION_TIMEOUT`] and was dropped.
+	Expired,
+
+	/// The user confirming the command is the one who requested it.
+	SameOperator,
+}
+
+impl fmt::Display for ConfirmationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Unknown => write!(f, "no command awaits this confirmation"),
+			Self::Expired => write!(f, "the command was not confirmed in time, so it must be requested again"),
+			Self::SameOperator => write!(f, "a command must be confirmed by a different operator than the one who requested it"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn hazardous_commands_take_two_operators() {
+		let confirmations = Confirmations::default();
+		let id = confirmations.request("BBV.open()", "alice", "open BBV".to_owned()).await;
+
+		assert_eq!(confirmations.confirm(&id, "alice").await.unwrap_err(), ConfirmationError::SameOperator);
+		assert_eq!(confirmations.confirm("00000000", "bob").await.unwrap_err(), ConfirmationError::Unknown);
+		assert_eq!(confirmations.confirm(&id, "bob").await.unwrap().command, "BBV.open()");
+
+		// a command is only dispatched once
+		assert_eq!(confirmations.confirm(&id, "carol").await.unwrap_err(), ConfirmationError::Unknown);
+
+		assert!(HazardLevel::Hazardous.requires_confirmation());
+		assert!(!HazardLevel::Caution.requires_confirmation());
+		assert_eq!("caution".parse::<HazardLevel>(), Ok(HazardLevel::Caution));
+	}
+}
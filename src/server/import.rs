@@ -0,0 +1,394 @@
+use common::comm::{CompositeValveState, Measurement, Unit, ValveState, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use super::{interlock, units};
+
+#[cfg(feature = "hdf5")]
+use hdf5::types::VarLenUnicode;
+
+#[cfg(feature = "hdf5")]
+use std::{env, fs, process, sync::atomic::{AtomicU32, Ordering}};
+
+#[cfg(feature = "hdf5")]
+use super::units::{UnitSystem, UNITS};
+
+/// The formats which files may be imported from.
+#[cfg(feature = "hdf5")]
+pub const FORMATS: [&str; 2] = ["csv", "hdf5"];
+
+/// The formats which files may be imported from, which leave out HDF5 when built without it.
+#[cfg(not(feature = "hdf5"))]
+pub const FORMATS: [&str; 1] = ["csv"];
+
+/// Every state a valve may be in, so that states may be read back from their names and IDs.
+const VALVE_STATES: [ValveState; 5] = [
+	ValveState::Undetermined,
+	ValveState::Disconnected,
+	ValveState::Open,
+	ValveState::Closed,
+	ValveState::Fault,
+];
+
+/// The unit ID written to HDF5 exports where a sensor had no reading.
+#[cfg(feature = "hdf5")]
+const MISSING_UNIT: i8 = -69;
+
+/// The valve state ID written to HDF5 exports where a valve had no state.
+#[cfg(feature = "hdf5")]
+const MISSING_VALVE_STATE: u8 = 69;
+
+/// Numbers the temporary files HDF5 imports are read from, in case two imports overlap.
+#[cfg(feature = "hdf5")]
+static IMPORT_FILE_INDEX: AtomicU32 = AtomicU32::new(0);
+
+/// How the channels of an imported file map onto those of the vehicle.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ImportOptions {
+	/// The names channels are logged under, keyed by the names of the columns
+	/// or datasets they are read from, for files which name channels differently.
+	#[serde(default)]
+	pub rename: BTreeMap<String, String>,
+
+	/// The units of columns which hold bare numbers, such as those written by
+	/// a third-party DAQ, keyed by column. Any unit servo reports or converts
+	/// into may be given, such as `psi` or `bar`.
+	#[serde(default)]
+	pub units: BTreeMap<String, String>,
+
+	/// Seconds added to every time in the file, for files whose times count
+	/// from the start of a test rather than being Unix timestamps.
+	#[serde(default)]
+	pub time_offset: f64,
+}
+
+impl ImportOptions {
+	/// The name of the channel read from a column or dataset.
+	fn channel<'a>(&'a self, column: &'a str) -> &'a str {
+		self.rename.get(column).map_or(column, String::as_str)
+	}
+}
+
+/// A note read from an imported file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedNote {
+	/// When the note was entered, as a Unix timestamp.
+	pub created_at: f64,
+
+	/// Who entered the note, if known.
+	pub author: Option<String>,
+
+	/// The text of the note.
+	pub content: String,
+}
+
+/// Everything read from an imported file.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedData {
+	/// The snapshots of the file along with their times, oldest first.
+	pub snapshots: Vec<(f64, VehicleState)>,
+
+	/// The notes of the file, oldest first.
+	pub notes: Vec<ImportedNote>,
+}
+
+/// The format of a file, either as given or from its extension.
+pub fn detect_format(format: Option<&str>, file_name: Option<&str>) -> Result<&'static str, String> {
+	let format = match (format, file_name) {
+		(Some(format), _) => format.to_ascii_lowercase(),
+		(None, Some(name)) => match Path::new(name).extension().and_then(|extension| extension.to_str()) {
+			Some("h5" | "hdf" | "hdf5") => "hdf5".to_owned(),
+			Some(extension) => extension.to_ascii_lowercase(),
+			None => String::new(),
+		},
+		(None, None) => String::new(),
+	};
+
+	FORMATS
+		.into_iter()
+		.find(|candidate| *candidate == format)
+		.ok_or_else(|| format!("cannot import '{format}', expected a file in one of {}", FORMATS.join(", ")))
+}
+
+/// Reads a file written by a CSV export, or by any DAQ which writes a time
+/// column followed by a column for each channel.
+///
+/// Cells hold a reading with its unit, such as `512.3 psi` or `35.3 bar`, the
+/// name of a valve state, or a bare number if the unit of its column is given.
+/// A last column named `notes` is read as the notes entered at each row.
+pub fn parse_csv(text: &str, options: &ImportOptions) -> Result<ImportedData, String> {
+	let mut lines = text
+		.lines()
+		.enumerate()
+		.filter(|(_, line)| !line.trim().is_empty());
+
+	let (_, header) = lines.next().ok_or("file is empty")?;
+	let columns = split_csv_line(header);
+
+	if columns.len() < 2 {
+		return Err("expected a column of times followed by a column for each channel".to_owned());
+	}
+
+	let has_notes = columns.last().is_some_and(|column| column.trim() == "notes");
+	let mut imported = ImportedData::default();
+
+	for (index, line) in lines {
+		let number = index + 1;
+		let cells = split_csv_line(line);
+
+		if cells.len() > columns.len() {
+			return Err(format!("line {number} has {} fields, but the header has {}", cells.len(), columns.len()));
+		}
+
+		let timestamp = cells[0]
+			.trim()
+			.parse::<f64>()
+			.map_err(|_| format!("line {number} starts with '{}', which is not a time", cells[0]))?
+			+ options.time_offset;
+
+		let mut state = VehicleState::new();
+
+		for (position, (column, cell)) in columns.iter().zip(&cells).enumerate().skip(1) {
+			let (column, cell) = (column.trim(), cell.trim());
+
+			if cell.is_empty() {
+				continue;
+			}
+
+			if has_notes && position == columns.len() - 1 {
+				imported.notes.push(ImportedNote { created_at: timestamp, author: None, content: cell.to_owned() });
+				continue;
+			}
+
+			let channel = options.channel(column).to_owned();
+
+			if let Some(valve_state) = parse_valve_state(cell) {
+				// only the actual state of a valve is exported to CSV
+				state.valve_states.insert(channel, CompositeValveState { commanded: ValveState::Undetermined, actual: valve_state });
+				continue;
+			}
+
+			let measurement = parse_reading(cell, options.units.get(column).map(String::as_str))
+				.map_err(|error| format!("line {number}, column {column}: {error}"))?;
+
+			state.sensor_readings.insert(channel, measurement);
+		}
+
+		if !state.sensor_readings.is_empty() || !state.valve_states.is_empty() {
+			imported.snapshots.push((timestamp, state));
+		}
+	}
+
+	imported.snapshots.sort_by(|a, b| a.0.total_cmp(&b.0));
+	Ok(imported)
+}
+
+/// Reads a file written by an HDF5 export.
+///
+/// Readings which were exported converted into another unit system are
+/// converted back into the units they were reported in.
+#[cfg(feature = "hdf5")]
+pub fn parse_hdf5(content: &[u8], options: &ImportOptions) -> Result<ImportedData, String> {
+	// HDF5 files are only read from disk, so the content is written to a temporary file first
+	let path = env::temp_dir().join(format!(
+		"servo-import-{}-{}.hdf5",
+		process::id(),
+		IMPORT_FILE_INDEX.fetch_add(1, Ordering::Relaxed),
+	));
+
+	fs::write(&path, content).map_err(|error| format!("failed to write temporary file: {error}"))?;
+	let imported = read_hdf5(&path, options);
+	let _ = fs::remove_file(&path);
+
+	imported.map_err(|error| format!("invalid HDF5 export: {error}"))
+}
+
+/// Reads the groups of an HDF5 export at a path, in the layout written by `make_hdf5_file`.
+#[cfg(feature = "hdf5")]
+fn read_hdf5(path: &Path, options: &ImportOptions) -> Result<ImportedData, String> {
+	let file = hdf5::File::open(path).map_err(|error| error.to_string())?;
+	let error = |error: hdf5::Error| error.to_string();
+
+	let timestamps = file.group("metadata").and_then(|group| group.dataset("timestamps")?.read_raw::<f64>()).map_err(error)?;
+	let mut states = vec![VehicleState::new(); timestamps.len()];
+
+	let sensors = file.group("sensors").map_err(error)?;
+
+	let system = if sensors.attr_names().map_err(error)?.iter().any(|name| name == "unit_system") {
+		sensors
+			.attr("unit_system")
+			.and_then(|attribute| attribute.read_scalar::<VarLenUnicode>())
+			.map_err(error)?
+			.as_str()
+			.parse::<UnitSystem>()?
+	} else {
+		UnitSystem::Native
+	};
+
+	for name in sensors.member_names().map_err(error)? {
+		let group = sensors.group(&name).map_err(error)?;
+		let readings = group.dataset("readings").and_then(|dataset| dataset.read_raw::<f64>()).map_err(error)?;
+		let unit_ids = group.dataset("units").and_then(|dataset| dataset.read_raw::<i8>()).map_err(error)?;
+
+		if readings.len() != timestamps.len() || unit_ids.len() != timestamps.len() {
+			return Err(format!("sensor {name} does not have a reading for every timestamp"));
+		}
+
+		let channel = options.channel(&name);
+
+		for ((state, value), id) in states.iter_mut().zip(readings).zip(unit_ids) {
+			if id == MISSING_UNIT {
+				continue;
+			}
+
+			let unit = UNITS
+				.into_iter()
+				.find(|&unit| unit as i8 == id)
+				.ok_or_else(|| format!("sensor {name} has unrecognized unit ID {id}"))?;
+
+			let measurement = Measurement { value: system.revert(value, unit), unit };
+			state.sensor_readings.insert(channel.to_owned(), measurement);
+		}
+	}
+
+	let valves = file.group("valves").map_err(error)?;
+
+	for name in valves.member_names().map_err(error)? {
+		let ids = valves.dataset(&name).and_then(|dataset| dataset.read_raw::<u8>()).map_err(error)?;
+
+		if ids.len() != timestamps.len() {
+			return Err(format!("valve {name} does not have a state for every timestamp"));
+		}
+
+		let channel = options.channel(&name);
+
+		for (state, id) in states.iter_mut().zip(ids) {
+			if id == MISSING_VALVE_STATE {
+				continue;
+			}
+
+			let commanded = VALVE_STATES
+				.into_iter()
+				.find(|&valve_state| valve_state as u8 == id)
+				.ok_or_else(|| format!("valve {name} has unrecognized state ID {id}"))?;
+
+			// only the commanded state of a valve is exported to HDF5
+			state.valve_states.insert(channel.to_owned(), CompositeValveState { commanded, actual: ValveState::Undetermined });
+		}
+	}
+
+	let mut imported = ImportedData {
+		snapshots: timestamps
+			.into_iter()
+			.map(|timestamp| timestamp + options.time_offset)
+			.zip(states)
+			.filter(|(_, state)| !state.sensor_readings.is_empty() || !state.valve_states.is_empty())
+			.collect(),
+		notes: Vec::new(),
+	};
+
+	if file.link_exists("notes") {
+		let notes = file.group("notes").map_err(error)?;
+		let times = notes.dataset("timestamps").and_then(|dataset| dataset.read_raw::<f64>()).map_err(error)?;
+		let contents = notes.dataset("contents").and_then(|dataset| dataset.read_raw::<VarLenUnicode>()).map_err(error)?;
+		let authors = notes.dataset("authors").and_then(|dataset| dataset.read_raw::<VarLenUnicode>()).map_err(error)?;
+
+		imported.notes = times
+			.into_iter()
+			.zip(contents)
+			.zip(authors)
+			.map(|((created_at, content), author)| ImportedNote {
+				created_at: created_at + options.time_offset,
+				author: Some(author.as_str().to_owned()).filter(|author| !author.is_empty()),
+				content: content.as_str().to_owned(),
+			})
+			.collect();
+	}
+
+	imported.snapshots.sort_by(|a, b| a.0.total_cmp(&b.0));
+	Ok(imported)
+}
+
+/// Parses a reading written as its value and unit, or as a bare value in the given unit.
+fn parse_reading(cell: &str, column_unit: Option<&str>) -> Result<Measurement, String> {
+	let (value, symbol) = match cell.split_once(char::is_whitespace) {
+		Some((value, symbol)) => (value, Some(symbol.trim())),
+		None => (cell, None),
+	};
+
+	let value = value
+		.parse::<f64>()
+		.map_err(|_| format!("'{cell}' is neither a reading nor a valve state"))?;
+
+	let symbol = symbol
+		.or(column_unit)
+		.ok_or_else(|| "column holds bare numbers, so its unit must be given".to_owned())?;
+
+	interlock::parse_unit(symbol)
+		.map(|unit: Unit| Measurement { value, unit })
+		.or_else(|| units::parse_measurement(value, symbol))
+		.ok_or_else(|| format!("unrecognized unit '{symbol}'"))
+}
+
+/// Parses the name of a valve state, as it is exported.
+fn parse_valve_state(cell: &str) -> Option<ValveState> {
+	VALVE_STATES
+		.into_iter()
+		.find(|state| state.to_string().eq_ignore_ascii_case(cell))
+}
+
+/// Splits a line of CSV into its fields, unquoting any which are quoted.
+fn split_csv_line(line: &str) -> Vec<String> {
+	let mut fields = vec![String::new()];
+	let mut quoted = false;
+	let mut chars = line.chars().peekable();
+
+	while let Some(char) = chars.next() {
+		let field = fields.last_mut().unwrap();
+
+		match char {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				chars.next();
+				field.push('"');
+			},
+			'"' => quoted = !quoted,
+			',' if !quoted => fields.push(String::new()),
+			char => field.push(char),
+		}
+	}
+
+	fields
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn csv_exports_and_daq_logs_are_read() {
+		let export = "timestamp,KBPT,BBV,notes\n10.5,512 psi,Open,\n11,35 bar,,\"ignition, nominal\"\n";
+		let imported = parse_csv(export, &ImportOptions::default()).unwrap();
+
+		assert_eq!(imported.snapshots.len(), 2);
+		assert_eq!(imported.snapshots[0].1.valve_states["BBV"].actual, ValveState::Open);
+		assert!((imported.snapshots[1].1.sensor_readings["KBPT"].value - 35.0 * 14.503_773_773).abs() < 1e-6);
+		assert_eq!(imported.notes, [ImportedNote { created_at: 11.0, author: None, content: "ignition, nominal".to_owned() }]);
+
+		let daq = "time,ch0\n0,1.5\n0.1,1.6\n";
+		assert!(parse_csv(daq, &ImportOptions::default()).is_err());
+
+		let options = ImportOptions {
+			rename: BTreeMap::from([("ch0".to_owned(), "WTPT".to_owned())]),
+			units: BTreeMap::from([("ch0".to_owned(), "psi".to_owned())]),
+			time_offset: 1_700_000_000.0,
+		};
+
+		let imported = parse_csv(daq, &options).unwrap();
+		assert_eq!(imported.snapshots[1].0, 1_700_000_000.1);
+		assert_eq!(imported.snapshots[1].1.sensor_readings["WTPT"].unit, Unit::Psi);
+
+		#[cfg(feature = "hdf5")]
+		assert_eq!(detect_format(None, Some("run.h5")), Ok("hdf5"));
+		assert!(detect_format(None, Some("run.xlsx")).is_err());
+	}
+}
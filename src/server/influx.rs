@@ -0,0 +1,196 @@
+use common::comm::VehicleState;
+use jeflog::{pass, warn};
+use reqwest::header;
+use std::{fmt::Write, future::Future, sync::Arc, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use super::{config::InfluxConfig, note::Note, Shared};
+
+/// How long to wait before checking the configuration again while live pushing is disabled.
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Escapes a measurement, tag key, or tag value for line protocol.
+fn escape_tag(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace(',', "\\,")
+		.replace('=', "\\=")
+		.replace(' ', "\\ ")
+}
+
+/// Escapes a string field value for line protocol, excluding the surrounding quotes.
+fn escape_string(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Appends the lines of a vehicle state recorded at a Unix timestamp, returning how many were appended.
+///
+/// Readings which are not finite are skipped, since line protocol cannot represent them.
+pub fn write_lines(lines: &mut String, vehicle_state: &VehicleState, timestamp: f64) -> usize {
+	let nanoseconds = (timestamp * 1e9) as i64;
+	let mut count = 0;
+
+	for (channel, reading) in &vehicle_state.sensor_readings {
+		if !reading.value.is_finite() {
+			continue;
+		}
+
+		let _ = writeln!(
+			lines,
+			"sensor,channel={},unit={} value={} {nanoseconds}",
+			escape_tag(channel),
+			escape_tag(&reading.unit.to_string()),
+			reading.value,
+		);
+
+		count += 1;
+	}
+
+	for (channel, valve_state) in &vehicle_state.valve_states {
+		let _ = writeln!(
+			lines,
+			"valve,channel={} commanded=\"{}\",actual=\"{}\" {nanoseconds}",
+			escape_tag(channel),
+			escape_string(&valve_state.commanded.to_string()),
+			escape_string(&valve_state.actual.to_string()),
+		);
+
+		count += 1;
+	}
+
+	count
+}
+
+/// Appends an operator note as a line, so that it may be shown as an annotation.
+pub fn write_note(lines: &mut String, note: &Note) {
+	let author = note.author
+		.as_deref()
+		.filter(|author| !author.is_empty())
+		.map(|author| format!(",author={}", escape_tag(author)))
+		.unwrap_or_default();
+
+	// line protocol has no way to escape a newline, even in a string field
+	let _ = writeln!(
+		lines,
+		"note{author} text=\"{}\" {}",
+		escape_string(&note.content.replace('\n', " ")),
+		(note.created_at * 1e9) as i64,
+	);
+}
+
+/// Writes a batch of lines to the configured endpoint.
+pub async fn write(client: &reqwest::Client, config: &InfluxConfig, lines: String) -> anyhow::Result<()> {
+	let Some(url) = &config.url else {
+		anyhow::bail!("no Influx write endpoint is configured");
+	};
+
+	let mut request = client
+		.post(url)
+		.header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+		.body(lines);
+
+	if let Some(token) = &config.token {
+		request = request.header(header::AUTHORIZATION, format!("Token {token}"));
+	}
+
+	request.send().await?.error_for_status()?;
+	Ok(())
+}
+
+/// Continuously pushes each new vehicle state to the configured time-series database.
+///
+/// Lines are batched until the batch is full or the oldest line has waited for
+/// the configured flush interval. A batch which cannot be written is dropped
+/// rather than retried, so that an unreachable database cannot grow memory
+/// without bound; the gap may be filled afterwards with a backfill.
+pub fn push_live(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let client = reqwest::Client::new();
+		let mut lines = String::new();
+		let mut line_count = 0;
+		let mut batch_started = None;
+		let mut failing = false;
+
+		loop {
+			let config = shared.config.current();
+			let config = &config.influx;
+
+			if config.url.is_none() || !config.live {
+				lines.clear();
+				line_count = 0;
+				batch_started = None;
+
+				tokio::time::sleep(DISABLED_POLL_INTERVAL).await;
+				continue;
+			}
+
+			let flush_interval = Duration::from_secs_f64(config.flush_seconds);
+			let wait = batch_started.map_or(flush_interval, |started: Instant| flush_interval.saturating_sub(started.elapsed()));
+
+			if tokio::time::timeout(wait, shared.vehicle.1.notified()).await.is_ok() {
+				let snapshot = Arc::clone(&*shared.vehicle.0.lock().await);
+
+				let now = SystemTime::now()
+					.duration_since(UNIX_EPOCH)
+					.map_or(0.0, |duration| duration.as_secs_f64());
+
+				line_count += write_lines(&mut lines, &snapshot, now);
+				batch_started.get_or_insert_with(Instant::now);
+			}
+
+			let due = batch_started.is_some_and(|started| started.elapsed() >= flush_interval);
+
+			if line_count == 0 || (line_count < config.batch_lines && !due) {
+				continue;
+			}
+
+			match write(&client, config, std::mem::take(&mut lines)).await {
+				Ok(()) if failing => {
+					pass!("Resumed pushing vehicle state to Influx.");
+					failing = false;
+				},
+				Ok(()) => {},
+				Err(error) if !failing => {
+					warn!("Failed to push vehicle state to Influx, dropping batches until it recovers: {error}");
+					failing = true;
+				},
+				Err(_) => {},
+			}
+
+			line_count = 0;
+			batch_started = None;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+	use super::*;
+
+	#[test]
+	fn vehicle_state_is_written_as_lines() {
+		let mut vehicle_state = VehicleState::new();
+		vehicle_state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 14.5, unit: Unit::Psi });
+		vehicle_state.sensor_readings.insert("daq.tank temp".to_owned(), Measurement { value: f64::NAN, unit: Unit::Kelvin });
+		vehicle_state.sensor_readings.insert("a,b=c".to_owned(), Measurement { value: 1.0, unit: Unit::Volts });
+		vehicle_state.valve_states.insert("BBV".to_owned(), CompositeValveState {
+			commanded: ValveState::Open,
+			actual: ValveState::Closed,
+		});
+
+		let mut lines = String::new();
+		assert_eq!(write_lines(&mut lines, &vehicle_state, 1.5), 3);
+
+		let expected = [
+			format!("sensor,channel=KBPT,unit={} value=14.5 1500000000", Unit::Psi),
+			format!("sensor,channel=a\\,b\\=c,unit={} value=1 1500000000", Unit::Volts),
+			format!("valve,channel=BBV commanded=\"{}\",actual=\"{}\" 1500000000", ValveState::Open, ValveState::Closed),
+		];
+
+		let mut actual = lines.lines().collect::<Vec<_>>();
+		actual.sort();
+		assert_eq!(actual, expected);
+	}
+}
@@ -0,0 +1,320 @@
+use common::comm::{ChannelType, CompositeValveState, DataMessage, DataPoint, Measurement, NodeMapping, SensorType, Unit, ValveState, VehicleState};
+use jeflog::{pass, warn};
+use rusqlite::Connection;
+use std::{collections::HashMap, future::Future, sync::Arc, time::{Duration, Instant}};
+use tokio::{io, net::UdpSocket};
+
+use super::Shared;
+
+/// How often the active mappings are reloaded from the database while ingesting.
+const MAPPING_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The parts of an active node mapping needed to convert a raw data point into a measurement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngestMapping {
+	/// The name of the sensor or valve, as it appears in the vehicle state.
+	pub text_id: String,
+
+	/// The type of sensor, as stored in the `NodeMappings` table (such as `pt`).
+	pub sensor_type: String,
+
+	/// The value of the sensor at the top of its range.
+	pub max: Option<f64>,
+
+	/// The value of the sensor at the bottom of its range.
+	pub min: Option<f64>,
+
+	/// The offset subtracted from the sensor after conversion.
+	pub calibrated_offset: Option<f64>,
+
+	/// The valve current above which a valve is considered powered.
+	pub powered_threshold: Option<f64>,
+
+	/// Whether a valve is closed when unpowered.
+	pub normally_closed: Option<bool>,
+}
+
+/// Active mappings, keyed by board ID, channel, and sensor type.
+pub type MappingTable = HashMap<(String, u32, String), IngestMapping>;
+
+/// Loads the mappings of the active configuration for ingesting data points.
+pub fn load_mappings(connection: &Connection) -> rusqlite::Result<MappingTable> {
+	connection
+		.prepare("
+			SELECT
+				text_id,
+				board_id,
+				channel,
+				sensor_type,
+				max,
+				min,
+				calibrated_offset,
+				powered_threshold,
+				normally_closed
+			FROM NodeMappings WHERE active = TRUE
+		")?
+		.query_map([], |row| {
+			let board_id = row.get::<_, String>(1)?;
+			let channel = row.get::<_, u32>(2)?;
+
+			let mapping = IngestMapping {
+				text_id: row.get(0)?,
+				sensor_type: row.get(3)?,
+				max: row.get(4)?,
+				min: row.get(5)?,
+				calibrated_offset: row.get(6)?,
+				powered_threshold: row.get(7)?,
+				normally_closed: row.get(8)?,
+			};
+
+			Ok(((board_id, channel, mapping.sensor_type.clone()), mapping))
+		})?
+		.collect()
+}
+
+/// The mapping sensor type which reads from a given channel type.
+fn sensor_type_for(channel_type: ChannelType) -> &'static str {
+	match channel_type {
+		ChannelType::CurrentLoop => "pt",
+		ChannelType::DifferentialSignal => "load_cell",
+		ChannelType::RailVoltage => "rail_voltage",
+		ChannelType::RailCurrent => "rail_current",
+		ChannelType::Tc => "tc",
+		ChannelType::Rtd => "rtd",
+		ChannelType::ValveVoltage | ChannelType::ValveCurrent => "valve",
+	}
+}
+
+/// Converts a data point from a SAM board into named readings and merges them into the vehicle state.
+///
+/// Data points on channels without an active mapping are ignored.
+pub fn apply_data_point(state: &mut VehicleState, mappings: &MappingTable, board_id: &str, data_point: &DataPoint) {
+	let key = (board_id.to_owned(), data_point.channel, sensor_type_for(data_point.channel_type).to_owned());
+
+	let Some(mapping) = mappings.get(&key) else {
+		return;
+	};
+
+	let offset = mapping.calibrated_offset.unwrap_or(0.0);
+	let name = mapping.text_id.clone();
+
+	let measurement = match data_point.channel_type {
+		ChannelType::CurrentLoop => match (mapping.min, mapping.max) {
+			(Some(min), Some(max)) => Measurement { value: pt_pressure(data_point.value, min, max) - offset, unit: Unit::Psi },
+			_ => Measurement { value: data_point.value, unit: Unit::Volts },
+		},
+		ChannelType::DifferentialSignal => Measurement { value: data_point.value - offset, unit: Unit::Pounds },
+		ChannelType::RailVoltage => Measurement { value: data_point.value, unit: Unit::Volts },
+		ChannelType::RailCurrent => Measurement { value: data_point.value, unit: Unit::Amps },
+		ChannelType::Tc | ChannelType::Rtd => Measurement { value: data_point.value, unit: Unit::Kelvin },
+		ChannelType::ValveVoltage => {
+			state.sensor_readings.insert(format!("{name}_V"), Measurement { value: data_point.value, unit: Unit::Volts });
+			return;
+		},
+		ChannelType::ValveCurrent => {
+			state.sensor_readings.insert(format!("{name}_I"), Measurement { value: data_point.value, unit: Unit::Amps });
+
+			let actual = valve_state(data_point.value, mapping.powered_threshold, mapping.normally_closed);
+
+			state.valve_states
+				.entry(name)
+				.and_modify(|valve| valve.actual = actual)
+				.or_insert(CompositeValveState { commanded: ValveState::Undetermined, actual });
+
+			return;
+		},
+	};
+
+	state.sensor_readings.insert(name, measurement);
+}
+
+/// The readings a mapping adds to the vehicle state once data arrives on its
+/// channel, with the unit of each, as [`apply_data_point`] reports them.
+///
+/// A pressure transducer is only converted to psi once its range is known,
+/// and a valve reports its voltage and current as two readings of its own.
+pub fn expected_readings(mapping: &NodeMapping) -> Vec<(String, Unit)> {
+	let name = mapping.text_id.clone();
+
+	match mapping.sensor_type {
+		SensorType::Pt if mapping.min.is_some() && mapping.max.is_some() => vec![(name, Unit::Psi)],
+		SensorType::Pt => vec![(name, Unit::Volts)],
+		SensorType::LoadCell => vec![(name, Unit::Pounds)],
+		SensorType::RailVoltage => vec![(name, Unit::Volts)],
+		SensorType::RailCurrent => vec![(name, Unit::Amps)],
+		SensorType::Tc | SensorType::Rtd => vec![(name, Unit::Kelvin)],
+		SensorType::Valve => vec![(format!("{name}_V"), Unit::Volts), (format!("{name}_I"), Unit::Amps)],
+	}
+}
+
+/// Converts the voltage read from a pressure transducer into a pressure.
+///
+/// The transducers output a current loop read as 0.8 V at the bottom of their
+/// range and 4.0 V at the top.
+pub fn pt_pressure(voltage: f64, min: f64, max: f64) -> f64 {
+	(voltage - 0.8) / 3.2 * (max - min) + min
+}
+
+/// Determines the actual state of a valve from the current through it.
+pub fn valve_state(current: f64, powered_threshold: Option<f64>, normally_closed: Option<bool>) -> ValveState {
+	let Some(powered_threshold) = powered_threshold else {
+		return ValveState::Undetermined;
+	};
+
+	let powered = current > powered_threshold;
+
+	// a normally closed valve opens when powered, while a normally open valve closes.
+	if powered == normally_closed.unwrap_or(true) {
+		ValveState::Open
+	} else {
+		ValveState::Closed
+	}
+}
+
+/// Listens for `DataMessage`s sent directly from SAM boards, merging their
+/// data points into the vehicle state.
+///
+/// This lets servo act as the aggregator on a test stand with no flight
+/// computer present. Handshakes are answered with servo's own identity, as
+/// the flight computer would.
+pub fn receive_sam_data(shared: &Shared, port: u16) -> impl Future<Output = io::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let socket = UdpSocket::bind(("0.0.0.0", port)).await?;
+		let mut buffer = vec![0; 65_536];
+
+		let mut mappings = MappingTable::new();
+		let mut refreshed_at: Option<Instant> = None;
+
+		pass!("Listening for SAM data on port {port}.");
+
+		loop {
+			let (size, address) = socket.recv_from(&mut buffer).await?;
+
+			let message = match postcard::from_bytes::<DataMessage>(&buffer[..size]) {
+				Ok(message) => message,
+				Err(error) => {
+					warn!("Failed to deserialize SAM data message from {address}: {error}");
+					continue;
+				},
+			};
+
+			match message {
+				DataMessage::Identity(board_id) => {
+					pass!("SAM board {board_id} identified itself from {address}.");
+
+					let identity = DataMessage::Identity("servo".to_owned());
+
+					match postcard::to_allocvec(&identity) {
+						Ok(serialized) => {
+							if let Err(error) = socket.send_to(&serialized, address).await {
+								warn!("Failed to answer handshake from {board_id}: {error}");
+							}
+						},
+						Err(error) => warn!("Failed to serialize identity message: {error}"),
+					}
+				},
+				DataMessage::Sam(board_id, data_points) => {
+					if refreshed_at.is_none_or(|refreshed_at| refreshed_at.elapsed() >= MAPPING_REFRESH_INTERVAL) {
+						match load_mappings(&*shared.database.connection.lock().await) {
+							Ok(loaded) => mappings = loaded,
+							Err(error) => warn!("Failed to load mappings for SAM ingest: {error}"),
+						}
+
+						refreshed_at = Some(Instant::now());
+					}
+
+					let mut snapshot = shared.vehicle.0.lock().await;
+
+					// copied only if a consumer still holds the previous snapshot
+					let vehicle_state = Arc::make_mut(&mut snapshot);
+
+					for data_point in data_points.iter() {
+						apply_data_point(vehicle_state, &mappings, &board_id, data_point);
+					}
+
+					drop(snapshot);
+					shared.vehicle.1.notify_waiters();
+				},
+				// messages from other kinds of boards are not ingested directly
+				#[allow(unreachable_patterns)]
+				_ => {},
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pt_voltage_spans_range() {
+		let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+		assert!(close(pt_pressure(0.8, 0.0, 1000.0), 0.0));
+		assert!(close(pt_pressure(4.0, 0.0, 1000.0), 1000.0));
+		assert!(close(pt_pressure(2.4, 0.0, 1000.0), 500.0));
+	}
+
+	#[test]
+	fn valve_state_follows_power() {
+		assert_eq!(valve_state(0.1, Some(0.05), Some(true)), ValveState::Open);
+		assert_eq!(valve_state(0.0, Some(0.05), Some(true)), ValveState::Closed);
+		assert_eq!(valve_state(0.1, Some(0.05), Some(false)), ValveState::Closed);
+		assert_eq!(valve_state(0.0, Some(0.05), Some(false)), ValveState::Open);
+		assert_eq!(valve_state(0.1, None, Some(true)), ValveState::Undetermined);
+	}
+
+	#[test]
+	fn readings_are_expected_in_the_units_they_are_converted_to() {
+		let mapping = |text_id: &str, sensor_type, range: Option<(f64, f64)>| NodeMapping {
+			text_id: text_id.to_owned(),
+			board_id: "sam-01".to_owned(),
+			sensor_type,
+			channel: 1,
+			computer: common::comm::Computer::Flight,
+			max: range.map(|(_, max)| max),
+			min: range.map(|(min, _)| min),
+			calibrated_offset: None,
+			powered_threshold: None,
+			normally_closed: None,
+		};
+
+		assert_eq!(expected_readings(&mapping("KBPT", SensorType::Pt, Some((0.0, 1000.0)))), [("KBPT".to_owned(), Unit::Psi)]);
+		assert_eq!(expected_readings(&mapping("KBPT", SensorType::Pt, None)), [("KBPT".to_owned(), Unit::Volts)]);
+		assert_eq!(expected_readings(&mapping("TC1", SensorType::Tc, None)), [("TC1".to_owned(), Unit::Kelvin)]);
+
+		assert_eq!(
+			expected_readings(&mapping("BBV", SensorType::Valve, None)),
+			[("BBV_V".to_owned(), Unit::Volts), ("BBV_I".to_owned(), Unit::Amps)],
+		);
+	}
+
+	#[test]
+	fn unmapped_channels_are_ignored() {
+		let mut mappings = MappingTable::new();
+
+		mappings.insert(("sam-01".to_owned(), 1, "pt".to_owned()), IngestMapping {
+			text_id: "KBPT".to_owned(),
+			sensor_type: "pt".to_owned(),
+			max: Some(1000.0),
+			min: Some(0.0),
+			calibrated_offset: None,
+			powered_threshold: None,
+			normally_closed: None,
+		});
+
+		let data_point = |channel, channel_type| DataPoint { value: 2.4, timestamp: 0.0, channel, channel_type };
+		let mut state = VehicleState::new();
+
+		apply_data_point(&mut state, &mappings, "sam-01", &data_point(1, ChannelType::CurrentLoop));
+		apply_data_point(&mut state, &mappings, "sam-01", &data_point(2, ChannelType::CurrentLoop));
+		apply_data_point(&mut state, &mappings, "sam-02", &data_point(1, ChannelType::CurrentLoop));
+		apply_data_point(&mut state, &mappings, "sam-01", &data_point(1, ChannelType::Tc));
+
+		assert_eq!(state.sensor_readings.len(), 1);
+		assert!((state.sensor_readings["KBPT"].value - 500.0).abs() < 1e-9);
+	}
+}
@@ -0,0 +1,333 @@
+use common::comm::{Unit, VehicleState};
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "server")]
+use super::Shared;
+
+/// A comparison between a sensor reading and a threshold.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comparison {
+	/// The reading must be strictly less than the threshold.
+	Less,
+
+	/// The reading must be less than or equal to the threshold.
+	LessEqual,
+
+	/// The reading must be strictly greater than the threshold.
+	Greater,
+
+	/// The reading must be greater than or equal to the threshold.
+	GreaterEqual,
+}
+
+impl Comparison {
+	/// Applies the comparison to a reading and a threshold.
+	pub fn holds(self, reading: f64, threshold: f64) -> bool {
+		match self {
+			Self::Less => reading < threshold,
+			Self::LessEqual => reading <= threshold,
+			Self::Greater => reading > threshold,
+			Self::GreaterEqual => reading >= threshold,
+		}
+	}
+}
+
+impl fmt::Display for Comparison {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let symbol = match self {
+			Self::Less => "<",
+			Self::LessEqual => "<=",
+			Self::Greater => ">",
+			Self::GreaterEqual => ">=",
+		};
+
+		write!(f, "{symbol}")
+	}
+}
+
+/// A precondition which must hold before a sequence may be dispatched.
+///
+/// Conditions are written as text so that they may be stored alongside
+/// sequences and edited by operators. The supported forms are:
+///
+/// - `<sensor> <comparison> <value> [unit]`, such as `KBPT < 50 psi`
+/// - `flight link healthy`
+/// - `configuration <id> active`
+/// - `not <condition>`, which holds when the inner condition does not
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+	/// A sensor reading must compare favorably against a threshold.
+	Measurement {
+		/// The name of the sensor, as it appears in the vehicle state.
+		sensor: String,
+
+		/// How the reading is compared against the threshold.
+		comparison: Comparison,
+
+		/// The threshold against which the reading is compared.
+		threshold: f64,
+
+		/// The unit the reading must be reported in, if one was given.
+		unit: Option<Unit>,
+	},
+
+	/// The flight computer must be connected over a link which has not closed.
+	FlightLinkHealthy,
+
+	/// The given configuration must be the active one.
+	ConfigurationActive(String),
+
+	/// The inner condition must not hold.
+	Not(Box<Condition>),
+}
+
+/// The parts of the system state against which conditions are checked.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemState<'a> {
+	/// The latest state of the vehicle.
+	pub vehicle: &'a VehicleState,
+
+	/// Whether the flight computer is connected over a link which has not closed.
+	pub flight_link_healthy: bool,
+
+	/// The ID of the active configuration, if any.
+	pub active_configuration: Option<&'a str>,
+}
+
+impl Condition {
+	/// Checks the condition against the system state, describing why it does not hold if it fails.
+	pub fn check(&self, state: &SystemState) -> Result<(), String> {
+		match self {
+			Self::Measurement { sensor, comparison, threshold, unit } => {
+				let measurement = state.vehicle.sensor_readings
+					.get(sensor)
+					.ok_or(format!("no reading available for {sensor}"))?;
+
+				if unit.is_some_and(|unit| unit != measurement.unit) {
+					return Err(format!("{sensor} is reported in {}, not the unit required", measurement.unit));
+				}
+
+				if !comparison.holds(measurement.value, *threshold) {
+					return Err(format!("{sensor} is {measurement}, which is not {comparison} {threshold}"));
+				}
+			},
+			Self::FlightLinkHealthy => {
+				if !state.flight_link_healthy {
+					return Err("flight link is not healthy".to_owned());
+				}
+			},
+			Self::ConfigurationActive(configuration_id) => {
+				if state.active_configuration != Some(configuration_id) {
+					return Err(format!("configuration '{configuration_id}' is not active"));
+				}
+			},
+			Self::Not(condition) => {
+				if condition.check(state).is_ok() {
+					return Err(format!("{condition} holds"));
+				}
+			},
+		}
+
+		Ok(())
+	}
+}
+
+impl fmt::Display for Condition {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Measurement { sensor, comparison, threshold, unit: Some(unit) } => write!(f, "{sensor} {comparison} {threshold} {unit}"),
+			Self::Measurement { sensor, comparison, threshold, unit: None } => write!(f, "{sensor} {comparison} {threshold}"),
+			Self::FlightLinkHealthy => write!(f, "flight link healthy"),
+			Self::ConfigurationActive(configuration_id) => write!(f, "configuration {configuration_id} active"),
+			Self::Not(condition) => write!(f, "not {condition}"),
+		}
+	}
+}
+
+impl FromStr for Condition {
+	type Err = String;
+
+	fn from_str(condition: &str) -> Result<Self, Self::Err> {
+		let trimmed = condition.trim_start();
+
+		if let Some((not, inner)) = trimmed.split_once(char::is_whitespace) {
+			if not.eq_ignore_ascii_case("not") {
+				return Ok(Self::Not(Box::new(inner.parse()?)));
+			}
+		}
+
+		let words = condition.split_whitespace().collect::<Vec<_>>();
+
+		match words.as_slice() {
+			[flight, link, healthy]
+				if flight.eq_ignore_ascii_case("flight")
+				&& link.eq_ignore_ascii_case("link")
+				&& healthy.eq_ignore_ascii_case("healthy") => {
+				return Ok(Self::FlightLinkHealthy);
+			},
+			[configuration, id, active]
+				if configuration.eq_ignore_ascii_case("configuration")
+				&& active.eq_ignore_ascii_case("active") => {
+				return Ok(Self::ConfigurationActive((*id).to_owned()));
+			},
+			_ => {},
+		}
+
+		// two-character operators come first so that "<=" is not read as "<"
+		let (position, comparison, length) = [
+			("<=", Comparison::LessEqual),
+			(">=", Comparison::GreaterEqual),
+			("<", Comparison::Less),
+			(">", Comparison::Greater),
+		]
+			.into_iter()
+			.find_map(|(symbol, comparison)| {
+				condition
+					.find(symbol)
+					.map(|position| (position, comparison, symbol.len()))
+			})
+			.ok_or(format!("unrecognized condition '{condition}'"))?;
+
+		let sensor = condition[..position].trim();
+
+		if sensor.is_empty() || sensor.contains(char::is_whitespace) {
+			return Err(format!("condition '{condition}' must name a single sensor"));
+		}
+
+		let right = condition[position + length..].trim();
+		let unit_start = right
+			.find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+')))
+			.unwrap_or(right.len());
+
+		let threshold = right[..unit_start]
+			.parse::<f64>()
+			.map_err(|_| format!("condition '{condition}' has an invalid threshold"))?;
+
+		let unit = match right[unit_start..].trim() {
+			"" => None,
+			unit => Some(parse_unit(unit).ok_or(format!("condition '{condition}' has unrecognized unit '{unit}'"))?),
+		};
+
+		Ok(Self::Measurement {
+			sensor: sensor.to_owned(),
+			comparison,
+			threshold,
+			unit,
+		})
+	}
+}
+
+/// Evaluates a set of conditions against the current system state, returning
+/// a description of each condition which does not hold.
+///
+/// Conditions which can no longer be parsed are treated as failing.
+#[cfg(feature = "server")]
+pub async fn evaluate(shared: &Shared, conditions: &[String], active_configuration: Option<&str>) -> Vec<String> {
+	if conditions.is_empty() {
+		return Vec::new();
+	}
+
+	let vehicle = shared.vehicle.0.lock().await.clone();
+
+	let state = SystemState {
+		vehicle: &vehicle,
+		flight_link_healthy: flight_link_healthy(shared).await,
+		active_configuration,
+	};
+
+	conditions
+		.iter()
+		.filter_map(|condition| {
+			condition
+				.parse::<Condition>()
+				.and_then(|parsed| parsed.check(&state))
+				.err()
+		})
+		.collect()
+}
+
+/// Whether the flight computer is connected over a link which has not closed.
+#[cfg(feature = "server")]
+pub async fn flight_link_healthy(shared: &Shared) -> bool {
+	shared.flight.0
+		.lock()
+		.await
+		.as_ref()
+		.is_some_and(|flight| !flight.check_closed())
+}
+
+/// Parses the name of a unit as written by an operator, such as `psi` or `V`.
+pub fn parse_unit(unit: &str) -> Option<Unit> {
+	let unit = match unit.to_ascii_lowercase().as_str() {
+		"psi" => Unit::Psi,
+		"a" | "amps" => Unit::Amps,
+		"v" | "volts" => Unit::Volts,
+		"k" | "kelvin" => Unit::Kelvin,
+		"lb" | "lbs" | "lbf" | "pounds" => Unit::Pounds,
+		_ => return None,
+	};
+
+	Some(unit)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common::comm::Measurement;
+
+	#[test]
+	fn parses_conditions() {
+		assert_eq!(
+			"KBPT < 50 psi".parse::<Condition>(),
+			Ok(Condition::Measurement {
+				sensor: "KBPT".to_owned(),
+				comparison: Comparison::Less,
+				threshold: 50.0,
+				unit: Some(Unit::Psi),
+			}),
+		);
+
+		assert_eq!(
+			"WTPT>=-1.5".parse::<Condition>(),
+			Ok(Condition::Measurement {
+				sensor: "WTPT".to_owned(),
+				comparison: Comparison::GreaterEqual,
+				threshold: -1.5,
+				unit: None,
+			}),
+		);
+
+		assert_eq!("Flight Link Healthy".parse::<Condition>(), Ok(Condition::FlightLinkHealthy));
+		assert_eq!("configuration cold-flow active".parse::<Condition>(), Ok(Condition::ConfigurationActive("cold-flow".to_owned())));
+		assert_eq!("not flight link healthy".parse::<Condition>(), Ok(Condition::Not(Box::new(Condition::FlightLinkHealthy))));
+
+		assert!("KBPT is low".parse::<Condition>().is_err());
+		assert!("< 50".parse::<Condition>().is_err());
+		assert!("KBPT < fifty".parse::<Condition>().is_err());
+		assert!("KBPT < 50 furlongs".parse::<Condition>().is_err());
+	}
+
+	#[test]
+	fn checks_conditions_against_state() {
+		let mut vehicle = VehicleState::new();
+		vehicle.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 62.0, unit: Unit::Psi });
+
+		let state = SystemState {
+			vehicle: &vehicle,
+			flight_link_healthy: false,
+			active_configuration: Some("cold-flow"),
+		};
+
+		let check = |condition: &str| condition.parse::<Condition>().unwrap().check(&state);
+
+		assert!(check("KBPT > 50 psi").is_ok());
+		assert!(check("KBPT < 50 psi").is_err());
+		assert!(check("KBPT > 50 V").is_err());
+		assert!(check("WTPT < 50").is_err());
+		assert!(check("flight link healthy").is_err());
+		assert!(check("not flight link healthy").is_ok());
+		assert!(check("not KBPT > 50 psi").is_err());
+		assert!(check("configuration cold-flow active").is_ok());
+		assert!(check("configuration hotfire active").is_err());
+	}
+}
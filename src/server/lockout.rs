@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::sync::Mutex;
+
+use super::clock;
+
+/// A sequence which was dispatched to the flight computer and has not yet finished.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ActiveSequence {
+	/// When the sequence was dispatched, as a Unix timestamp.
+	pub started_at: f64,
+
+	/// The user who dispatched the sequence, if they were logged in.
+	pub started_by: Option<String>,
+
+	/// When the sequence is assumed to have finished if the flight computer has
+	/// not reported so, as a Unix timestamp.
+	pub expires_at: f64,
+}
+
+/// Tracks which sequences are running so that two are not dispatched at once.
+///
+/// Sequences running side by side may fight over the same valves, so a new
+/// sequence is refused while another is active. A sequence stays active until
+/// the flight computer reports that it finished, it is stopped, the vehicle is
+/// aborted, or it has been held for longer than the configured maximum, since
+/// a flight computer which never reports finishing would otherwise lock out
+/// every later sequence.
+#[derive(Debug, Default)]
+pub struct SequenceLockout {
+	active: Mutex<BTreeMap<String, ActiveSequence>>,
+}
+
+impl SequenceLockout {
+	/// The sequences which are running, keyed by name.
+	pub async fn active(&self) -> BTreeMap<String, ActiveSequence> {
+		let mut active = self.active.lock().await;
+		expire(&mut active);
+		active.clone()
+	}
+
+	/// Marks a sequence as running for at most `max_hold` if no other sequence is.
+	///
+	/// With `force`, the sequence is marked as running regardless, and the names
+	/// of the sequences it was allowed to run alongside are returned. Otherwise,
+	/// the names of the running sequences are returned as the error.
+	pub async fn claim(
+		&self,
+		name: &str,
+		started_by: Option<&str>,
+		max_hold: Duration,
+		force: bool,
+	) -> Result<Vec<String>, Vec<String>> {
+		let mut active = self.active.lock().await;
+		expire(&mut active);
+
+		let running = active.keys().cloned().collect::<Vec<_>>();
+
+		if !force && !running.is_empty() {
+			return Err(running);
+		}
+
+		let started_at = clock::now();
+
+		active.insert(name.to_owned(), ActiveSequence {
+			started_at,
+			started_by: started_by.map(str::to_owned),
+			expires_at: started_at + max_hold.as_secs_f64(),
+		});
+
+		Ok(running)
+	}
+
+	/// Marks a sequence as no longer running, returning whether it was.
+	pub async fn release(&self, name: &str) -> bool {
+		self.active.lock().await.remove(name).is_some()
+	}
+
+	/// Marks every sequence as no longer running, such as after an abort.
+	pub async fn clear(&self) {
+		self.active.lock().await.clear();
+	}
+}
+
+/// Releases the sequences which have been held for longer than they may be.
+fn expire(active: &mut BTreeMap<String, ActiveSequence>) {
+	let now = clock::now();
+	active.retain(|_, sequence| sequence.expires_at > now);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const HOLD: Duration = Duration::from_secs(60);
+
+	#[tokio::test]
+	async fn second_sequence_is_locked_out() {
+		let lockout = SequenceLockout::default();
+
+		assert_eq!(lockout.claim("press", Some("alice"), HOLD, false).await, Ok(Vec::new()));
+		assert_eq!(lockout.claim("fill", None, HOLD, false).await, Err(vec!["press".to_owned()]));
+		assert_eq!(lockout.claim("press", None, HOLD, false).await, Err(vec!["press".to_owned()]));
+		assert_eq!(lockout.claim("fill", None, HOLD, true).await, Ok(vec!["press".to_owned()]));
+
+		assert!(lockout.release("press").await);
+		assert!(!lockout.release("press").await);
+		assert_eq!(lockout.claim("vent", None, HOLD, false).await, Err(vec!["fill".to_owned()]));
+
+		lockout.clear().await;
+		assert_eq!(lockout.claim("vent", None, HOLD, false).await, Ok(Vec::new()));
+	}
+
+	#[tokio::test]
+	async fn sequences_are_released_after_their_hold() {
+		let lockout = SequenceLockout::default();
+
+		assert_eq!(lockout.claim("press", None, Duration::ZERO, false).await, Ok(Vec::new()));
+		assert!(lockout.active().await.is_empty());
+		assert_eq!(lockout.claim("fill", None, HOLD, false).await, Ok(Vec::new()));
+	}
+}
@@ -0,0 +1,83 @@
+use axum::{extract::{Request, State}, http::Method, middleware::Next, response::Response};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{error::{locked, ErrorCode}, Shared};
+
+/// Requests which may move something on the vehicle, so are refused in maintenance mode.
+const ACTUATION_REQUESTS: [&str; 7] = [
+	"/operator/command",
+	"/operator/command/confirm",
+	"/operator/run-sequence",
+	"/operator/sequences/run-adhoc",
+	"/operator/safe",
+	"/operator/abort",
+	"/operator/trigger",
+];
+
+/// gRPC methods which may move something on the vehicle.
+const ACTUATION_METHODS: [&str; 3] = [
+	"/servo.v1.Servo/SendCommand",
+	"/servo.v1.Servo/RunSequence",
+	"/servo.v1.Servo/Abort",
+];
+
+/// Whether the server is in maintenance mode, in which nothing may actuate the
+/// vehicle through the API while telemetry is still logged, forwarded, and exported.
+#[derive(Debug, Default)]
+pub struct MaintenanceMode {
+	enabled: AtomicBool,
+}
+
+impl MaintenanceMode {
+	/// Whether maintenance mode is on.
+	pub fn is_enabled(&self) -> bool {
+		self.enabled.load(Ordering::Relaxed)
+	}
+
+	/// Turns maintenance mode on or off, returning whether it was on before.
+	pub fn set(&self, enabled: bool) -> bool {
+		self.enabled.swap(enabled, Ordering::Relaxed)
+	}
+}
+
+/// Whether a request may actuate the vehicle.
+pub fn is_actuation(method: &Method, path: &str) -> bool {
+	if ACTUATION_METHODS.contains(&path) {
+		return true;
+	}
+
+	// triggers may be listed, and deleting one can only stop something from moving
+	*method != Method::GET && *method != Method::DELETE && ACTUATION_REQUESTS.contains(&path)
+}
+
+/// Middleware which refuses requests that could actuate the vehicle with a 423
+/// while the server is in maintenance mode, such as when people are hands-on
+/// the plumbing.
+///
+/// Safing from the TUI is left alone, since it takes someone at the server.
+pub async fn enforce_maintenance(State(shared): State<Shared>, request: Request, next: Next) -> super::Result<Response> {
+	if shared.maintenance.is_enabled() && is_actuation(request.method(), request.uri().path()) {
+		return Err(locked("the server is in maintenance mode, so nothing may be actuated").with_code(ErrorCode::MaintenanceMode));
+	}
+
+	Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_actuation_is_refused() {
+		assert!(is_actuation(&Method::POST, "/operator/command"));
+		assert!(is_actuation(&Method::POST, "/operator/abort"));
+		assert!(is_actuation(&Method::PUT, "/operator/trigger"));
+		assert!(is_actuation(&Method::POST, "/servo.v1.Servo/RunSequence"));
+
+		assert!(!is_actuation(&Method::GET, "/operator/trigger"));
+		assert!(!is_actuation(&Method::DELETE, "/operator/trigger"));
+		assert!(!is_actuation(&Method::POST, "/data/export"));
+		assert!(!is_actuation(&Method::POST, "/operator/stop-sequence"));
+		assert!(!is_actuation(&Method::POST, "/servo.v1.Servo/StreamState"));
+	}
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, time::{Duration, Instant}};
+use tokio::sync::Mutex;
+
+use super::{clock::ClockSkews, statistics::Statistics, telemetry::Arrival};
+
+/// The number of recent samples which latency statistics are computed over.
+const LATENCY_WINDOW: usize = 1000;
+
+/// Counters describing the health of the server, exposed at `/status/metrics`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+	/// Statistics on the vehicle state frames received from the flight computer.
+	pub telemetry: Mutex<TelemetryMetrics>,
+
+	/// The latest vehicle state frame received from the flight computer, from
+	/// which the latency of logging and forwarding it is measured.
+	pub latest_frame: Mutex<Option<FrameReceipt>>,
+
+	/// Recent latencies through the paths data and commands take through servo.
+	pub latency: Mutex<LatencyMetrics>,
+
+	/// The skews of the clocks of GUI clients and the flight computer from servo's.
+	pub clocks: Mutex<ClockSkews>,
+}
+
+/// When a vehicle state frame was received from the flight computer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FrameReceipt {
+	/// The number of frames received before this one, which distinguishes it
+	/// from vehicle state updated by other sources.
+	pub number: u64,
+
+	/// The instant the frame was received.
+	pub received_at: Instant,
+}
+
+impl Metrics {
+	/// Records that a vehicle state frame was received at an instant.
+	pub async fn frame_received(&self, received_at: Instant) {
+		let mut latest_frame = self.latest_frame.lock().await;
+		let number = latest_frame.map_or(0, |frame| frame.number + 1);
+
+		*latest_frame = Some(FrameReceipt { number, received_at });
+	}
+}
+
+/// Recent latencies through the paths data and commands take through servo.
+#[derive(Debug, Default)]
+pub struct LatencyMetrics {
+	/// From receiving a vehicle state frame to committing it to the database.
+	pub frame_to_commit: LatencyTracker,
+
+	/// From receiving a vehicle state frame to delivering it to a forwarding client.
+	pub frame_to_forward: LatencyTracker,
+
+	/// From receiving an operator command over HTTP to writing it to the flight computer.
+	pub command_to_write: LatencyTracker,
+}
+
+impl LatencyMetrics {
+	/// Statistics of the recent latencies through each path, in milliseconds.
+	pub fn report(&self) -> LatencyReport {
+		LatencyReport {
+			frame_to_commit_ms: self.frame_to_commit.statistics(),
+			frame_to_forward_ms: self.frame_to_forward.statistics(),
+			command_to_write_ms: self.command_to_write.statistics(),
+		}
+	}
+}
+
+/// Keeps the most recent latencies measured through a path.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+	samples: VecDeque<f64>,
+}
+
+impl LatencyTracker {
+	/// Records a latency, forgetting the oldest if the window is full.
+	pub fn record(&mut self, latency: Duration) {
+		if self.samples.len() == LATENCY_WINDOW {
+			self.samples.pop_front();
+		}
+
+		self.samples.push_back(latency.as_secs_f64() * 1000.0);
+	}
+
+	/// Statistics of the recent latencies in milliseconds, if any have been recorded.
+	pub fn statistics(&self) -> Option<Statistics> {
+		Statistics::compute(self.samples.iter().copied().collect())
+	}
+}
+
+/// Statistics of the recent latencies through each path, in milliseconds.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LatencyReport {
+	/// From receiving a vehicle state frame to committing it to the database.
+	pub frame_to_commit_ms: Option<Statistics>,
+
+	/// From receiving a vehicle state frame to delivering it to a forwarding client.
+	pub frame_to_forward_ms: Option<Statistics>,
+
+	/// From receiving an operator command over HTTP to writing it to the flight computer.
+	pub command_to_write_ms: Option<Statistics>,
+}
+
+/// Statistics on the vehicle state frames received from the flight computer.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TelemetryMetrics {
+	/// The number of frames received, sequenced or not.
+	pub frames_received: u64,
+
+	/// The number of frames received without a sequence number, for which loss cannot be known.
+	pub unsequenced_frames: u64,
+
+	/// The number of frames skipped over by a newer frame. Frames which arrive
+	/// late are still counted here, as they arrived too late to be used.
+	pub frames_lost: u64,
+
+	/// The number of frames which arrived after a newer frame, or were duplicates.
+	pub frames_out_of_order: u64,
+
+	/// The number of separate runs of lost frames.
+	pub gaps: u64,
+
+	/// The number of times the flight computer restarted its sequence numbers.
+	pub restarts: u64,
+
+	/// The number of frames larger than a datagram may be, which were truncated and quarantined.
+	#[serde(default)]
+	pub oversized_frames: u64,
+}
+
+impl TelemetryMetrics {
+	/// Records a received frame, along with how it arrived if it was sequenced.
+	pub fn record(&mut self, arrival: Option<Arrival>) {
+		self.frames_received += 1;
+
+		match arrival {
+			None => self.unsequenced_frames += 1,
+			Some(Arrival::InOrder) => {},
+			Some(Arrival::Gap { first_missing, last_missing }) => {
+				self.gaps += 1;
+				self.frames_lost += last_missing - first_missing + 1;
+			},
+			Some(Arrival::OutOfOrder) => self.frames_out_of_order += 1,
+			Some(Arrival::Restarted) => self.restarts += 1,
+		}
+	}
+
+	/// The fraction of sequenced frames which were lost.
+	pub fn loss_ratio(&self) -> f64 {
+		let sequenced = self.frames_received - self.unsequenced_frames;
+		let expected = sequenced + self.frames_lost;
+
+		if expected == 0 {
+			0.0
+		} else {
+			self.frames_lost as f64 / expected as f64
+		}
+	}
+}
@@ -1,114 +1,628 @@
+/// Aggregation of the snapshots between forwarding ticks into means and envelopes.
+pub mod aggregation;
+
+/// Alert rules evaluated against the system state, and the alerts they raise.
+pub mod alert;
+
+/// The hosts permitted to connect as a vehicle computer, and the pre-shared-key handshake they complete.
+pub mod allowlist;
+
+/// Detection of readings which depart from their channel's recent behavior.
+#[cfg(feature = "server")]
+pub mod anomaly;
+
+/// Request and response bodies of the HTTP API, shared by the route functions and the command line tools.
+pub mod api;
+
+/// Archives of everything recorded over a test, for review afterwards.
+#[cfg(feature = "server")]
+pub mod archive;
+
+/// Recording of audited actions, such as safing the vehicle.
+pub mod audit;
+
+/// Authentication components, including sessions and the middleware which validates them.
+#[cfg(feature = "server")]
+pub mod auth;
+
+/// The lease on command authority which a GUI holds to actuate the vehicle, renewed by heartbeats.
+#[cfg(feature = "server")]
+pub mod authority;
+
+/// Backs up the database either by streaming batches of changes to another server or by writing snapshots to files.
+#[cfg(feature = "server")]
+pub mod backup;
+
+/// Synthetic workloads for measuring the throughput of each stage of the data pipeline.
+#[cfg(feature = "server")]
+pub mod bench;
+
+/// Construction of a server with the addresses, storage, and features it runs with, for embedding it.
+#[cfg(feature = "server")]
+pub mod builder;
+
+/// Packaging of sequence bundles, which ship helper files alongside a script.
+#[cfg(feature = "server")]
+pub mod bundle;
+
+/// Capture rules, which mark windows of high interest to be kept at full rate.
+#[cfg(feature = "server")]
+pub mod capture;
+
+/// The catalog of channels, along with the display names and groups they are presented under.
+pub mod channel;
+
+/// Tracking of the skew of other participants' clocks from servo's.
+pub mod clock;
+
+/// Framing of the messages sent to the vehicle computers over the control link.
+pub mod codec;
+
+/// Conditional GETs, so that clients refreshing large resources are only sent them once they change.
+#[cfg(feature = "server")]
+pub mod conditional;
+
+/// Server configuration components, loaded from the Servo directory.
+pub mod config;
+
+/// Construction of the CORS policy from configuration.
+#[cfg(feature = "server")]
+pub mod cors;
+
 /// Server database components.
+#[cfg(feature = "server")]
 pub mod database;
 
+/// Deadbanding of channels before vehicle state is logged, to save storage on static channels.
+pub mod deadband;
+
+/// Decoders for inbound telemetry formats other than Postcard.
+#[cfg(feature = "server")]
+pub mod decoder;
+
+/// Advertisement of the server over mDNS, and discovery of advertised servers by clients.
+pub mod discovery;
+
 /// Server error components.
 pub mod error;
 
+/// Saved export presets, and the filtering and thinning of exported snapshots.
+pub mod export;
+
+/// Fault trees which suggest the probable causes of alerts.
+pub mod fault;
+
 /// Flight-related components such as the `FlightComputer` struct.
+#[cfg(feature = "server")]
 pub mod flight;
 
+/// The gRPC control API, served alongside the REST API for strongly-typed and streaming integrations.
+#[cfg(feature = "server")]
+pub mod grpc;
+
+/// Classification of channels by how hazardous they are to change, and the two-person rule for the most hazardous.
+pub mod hazard;
+
+/// Import of CSV and HDF5 files, such as earlier exports or third-party DAQ logs, as logged snapshots.
+pub mod import;
+
+/// Pushing of vehicle state to time-series databases in Influx line protocol.
+#[cfg(feature = "server")]
+pub mod influx;
+
+/// Ingestion of data sent directly from SAM boards, bypassing the flight computer.
+#[cfg(feature = "server")]
+pub mod ingest;
+
+/// Evaluation of the preconditions which must hold before a sequence is dispatched.
+pub mod interlock;
+
+/// Tracking of running sequences, so that conflicting sequences are not dispatched at once.
+#[cfg(feature = "server")]
+pub mod lockout;
+
+/// Maintenance mode, in which nothing may actuate the vehicle while telemetry continues.
+#[cfg(feature = "server")]
+pub mod maintenance;
+
+/// Counters describing the health of the server.
+pub mod metrics;
+
+/// Publishing of channels to an MQTT broker, for ground support tools which cannot consume the API.
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+
+/// The shift log of timestamped notes entered by operators.
+pub mod note;
+
+/// Delivery of alerts to operators through sounds, speech, shell hooks, email, and webhooks.
+#[cfg(feature = "server")]
+pub mod notification;
+
+/// The messages to the vehicle computers which have not reached their links, kept for operators to see.
+#[cfg(feature = "server")]
+pub mod outbox;
+
+/// Differences between pairs of channels, monitored for redundant sensors which disagree.
+#[cfg(feature = "server")]
+pub mod pair;
+
+/// Position fixes from GPS receivers, reported as scalar components.
+#[cfg(feature = "server")]
+pub mod position;
+
+/// Preflight checks of the environment servo runs in, reported as go or no-go.
+#[cfg(feature = "server")]
+pub mod preflight;
+
+/// Quarantine of telemetry frames which could not be deserialized, kept so that they may be diagnosed.
+pub mod quarantine;
+
+/// Recordings of raw telemetry frames, written as they are received and replayed offline to check the pipeline against golden output.
+pub mod recording;
+
+/// Data quality reports, summarizing how completely channels were logged over a test.
+pub mod report;
+
+/// Pruning of request logs and audit log entries once they are older than configured.
+pub mod retention;
+
 /// All server API route functions.
+#[cfg(feature = "server")]
 pub mod routes;
 
-use axum::Router;
-use common::comm::VehicleState;
-pub use database::Database;
+/// Descriptions of the enumerations and formats the server understands, for clients to read rather than hardcode.
+#[cfg(feature = "server")]
+pub mod schema;
+
+/// Selection of channels by name, glob, or regular expression, shared by every API which selects channels.
+pub mod selector;
+
+/// Serial links to the flight computer, such as the umbilical hardline.
+#[cfg(feature = "server")]
+pub mod serial;
+
+/// Symbolic execution of sequences, producing the timeline of commands they would send.
+pub mod simulation;
+
+/// Compressed encoding of the vehicle snapshots logged to the database.
+pub mod snapshot;
+
+/// Parameterized templates of common procedures, which are instantiated into full sequences.
+#[cfg(feature = "server")]
+pub mod snippet;
+
+/// Spectator mode, in which the API may be watched but nothing may be changed through it.
+#[cfg(feature = "server")]
+pub mod spectator;
+
+/// Hot-standby mirroring of a primary server, with promotion and fencing.
+#[cfg(feature = "server")]
+pub mod standby;
+
+/// Summary statistics, such as percentiles, of series of readings.
+pub mod statistics;
+
+/// Backends which logged vehicle snapshots are persisted to, selected by configuration.
+#[cfg(feature = "server")]
+pub mod storage;
+
+/// The last forwarding subscription of each client, resumed when it reconnects.
+#[cfg(feature = "server")]
+pub mod subscription;
+
+/// Supervision of the long-running tasks of the server, restarting them when they stop.
+#[cfg(feature = "server")]
+pub mod supervisor;
+
+/// Syncing of snapshots, configurations, and events from one server to another, skipping anything already there.
+#[cfg(feature = "server")]
+pub mod sync;
+
+/// Transports over which vehicle state frames are received from the flight computer.
+pub mod telemetry;
+
+/// Redlines of each channel, kept in version control as redline files and synced to the database.
+pub mod threshold;
+
+/// Request IDs assigned to each HTTP request, which tie logs, audit entries, and flight messages to it.
+#[cfg(feature = "server")]
+pub mod trace;
+
+/// Unit systems which measurements are converted into for presentation.
+pub mod units;
+
+/// Checkpointing and incremental vacuuming of the database while no test is running.
+#[cfg(feature = "server")]
+pub mod vacuum;
+
+/// Checks of the fields of requests, reported together as a single 422 response.
+pub mod validation;
+
+/// Multi-component channels, such as IMU acceleration and AHRS attitude, stored as scalar components.
+#[cfg(feature = "server")]
+pub mod vector;
+
+pub use config::Config;
 pub use error::{ServerError as Error, ServerResult as Result};
-pub use flight::FlightComputer;
-use tower_http::cors::{self, CorsLayer};
 
-use std::{io, net::SocketAddr, path::Path, sync::Arc};
-use tokio::{net::TcpListener, sync::{Mutex, Notify}};
+#[cfg(feature = "server")]
+pub use self::{
+	authority::CommandAuthority,
+	builder::{BindAddresses, Features, ServerBuilder},
+	conditional::ContentVersions,
+	config::SharedConfig,
+	database::Database,
+	flight::FlightComputer,
+	lockout::SequenceLockout,
+	metrics::Metrics,
+	outbox::Outbox,
+	recording::FrameRecorder,
+	maintenance::MaintenanceMode,
+	spectator::SpectatorMode,
+	standby::RoleState,
+	storage::Storage,
+	supervisor::Supervisor,
+};
+
+#[cfg(feature = "server")]
+use {
+	axum::{error_handling::HandleErrorLayer, extract::DefaultBodyLimit, middleware, Router},
+	common::comm::{Computer, VehicleState},
+	decoder::DecoderRegistry,
+	grpc::proto::servo_server::ServoServer,
+	hazard::Confirmations,
+	routes::OperatorCommandRequest,
+	std::{future::Future, io, net::SocketAddr, path::Path, sync::{Arc, PoisonError}, time::Duration},
+	supervisor::supervise,
+	tokio::{net::TcpListener, sync::{Mutex, Notify}, task::JoinSet},
+	tower::{limit::GlobalConcurrencyLimitLayer, ServiceBuilder},
+};
 
 /// Contains all of Servo's shared server state.
+#[cfg(feature = "server")]
 #[derive(Clone, Debug)]
 pub struct Shared {
+	/// The configuration in effect, which may be reloaded while the server runs.
+	pub config: Arc<SharedConfig>,
+
+	/// Counters describing the health of the server.
+	pub metrics: Arc<Metrics>,
+
+	/// The health of the long-running tasks of the server.
+	pub supervisor: Arc<Supervisor>,
+
+	/// The sequences which are running, which lock out other sequences.
+	pub lockout: Arc<SequenceLockout>,
+
+	/// Commands touching hazardous channels which await confirmation by a second operator.
+	pub confirmations: Arc<Confirmations<OperatorCommandRequest>>,
+
 	/// The database, a wrapper over `Arc<Mutex<SqlConnection>>`, so that it may
 	/// be accessed in route functions.
 	pub database: Database,
 
+	/// The backend logged vehicle snapshots are stored in, which is the database unless configured otherwise.
+	pub storage: Arc<dyn Storage>,
+
+	/// The addresses the server listens on.
+	pub addresses: BindAddresses,
+
 	/// The option for a flight computer.
 	pub flight: Arc<(Mutex<Option<FlightComputer>>, Notify)>,
 
 	/// The option for a ground computer.
 	pub ground: Arc<(Mutex<Option<FlightComputer>>, Notify)>,
 
+	/// Received telemetry frames waiting to be appended to the frame log.
+	pub recorder: Arc<FrameRecorder>,
+
+	/// The role of the server in a primary and standby pair.
+	pub role: Arc<RoleState>,
+
+	/// The lease on command authority, held by at most one GUI.
+	pub authority: Arc<CommandAuthority>,
+
+	/// The messages to the vehicle computers still being written, and those which failed.
+	pub outbox: Arc<Outbox>,
+
+	/// The entity tags of resources served conditionally, and when each last changed.
+	pub versions: Arc<ContentVersions>,
+
+	/// Whether the server is in maintenance mode.
+	pub maintenance: Arc<MaintenanceMode>,
+
+	/// Whether the server is in spectator mode.
+	pub spectator: Arc<SpectatorMode>,
+
 	/// The state of the vehicle, including both flight and ground components.
-	pub vehicle: Arc<(Mutex<VehicleState>, Notify)>,
+	///
+	/// Each update replaces the snapshot rather than modifying it in place,
+	/// so consumers share a snapshot by cloning the `Arc` rather than the state.
+	pub vehicle: Arc<(Mutex<Arc<VehicleState>>, Notify)>,
+}
+
+#[cfg(feature = "server")]
+impl Shared {
+	/// The connection to a computer of the vehicle, through which its commands and mappings are sent.
+	pub fn connection(&self, computer: Computer) -> &Arc<(Mutex<Option<FlightComputer>>, Notify)> {
+		match computer {
+			Computer::Flight => &self.flight,
+			Computer::Ground => &self.ground,
+		}
+	}
 }
 
 /// The server, constructed with all route functions ready.
+#[cfg(feature = "server")]
 #[derive(Clone, Debug)]
 pub struct Server {
 	/// The shared state of the server, to be passed to route functions.
 	pub shared: Shared,
-}
 
+	/// The optional parts of the server which run alongside the route functions.
+	pub features: Features,
 
-async fn wait_for_display_end(shutdown_future : tokio::task::JoinHandle<io::Result<()>>) {
-	let _ = shutdown_future.await;
+	/// The supervised background tasks, which are aborted when the server stops
+	/// running or once every clone of it has been dropped.
+	tasks: Arc<std::sync::Mutex<JoinSet<()>>>,
 }
 
+#[cfg(feature = "server")]
 impl Server {
+	/// Starts constructing a `Server`, with its database in memory and every feature enabled.
+	pub fn builder() -> ServerBuilder {
+		ServerBuilder::default()
+	}
+
 	/// Constructs a new `Server` and opens a `Database` based on the path given.
-	pub fn new(database_path: Option<&Path>) -> anyhow::Result<Self> {
-		let database;
+	pub fn new(database_path: Option<&Path>, config: impl Into<SharedConfig>) -> anyhow::Result<Self> {
+		let mut builder = Server::builder().config(config);
 
 		if let Some(path) = database_path {
-			database = Database::open(path)?;
-		} else {
-			database = Database::volatile()?;
+			builder = builder.database(path);
 		}
 
-		let shared = Shared {
-			database,
-			flight: Arc::new((Mutex::new(None), Notify::new())),
-			ground: Arc::new((Mutex::new(None), Notify::new())),
-			vehicle: Arc::new((Mutex::new(VehicleState::new()), Notify::new())),
-		};
+		builder.build()
+	}
+
+	/// Migrates storage, starts the background tasks of the enabled features,
+	/// and serves the route functions until the shutdown future completes.
+	///
+	/// This must be called within a multi-threaded Tokio runtime, which the
+	/// background tasks are spawned onto. They are stopped before this returns,
+	/// so that every port the server listened on is released.
+	pub async fn run(&self, shutdown: impl Future<Output = ()> + Send + 'static) -> anyhow::Result<()> {
+		self.shared.storage.migrate().await.map_err(|error| {
+			anyhow::anyhow!("failed to prepare {} storage: {error}", self.shared.storage.name())
+		})?;
+
+		self.spawn_tasks();
+		let served = self.serve(shutdown).await;
+		self.stop_tasks().await;
 
-		Ok(Server { shared })
+		served?;
+		Ok(())
 	}
 
-	/// Serves the route functions with permissive CORS; Exits when the shutdown_future returns via a graceful shutdown.
+	/// Spawns the supervised background tasks of the enabled features onto the current runtime.
+	///
+	/// The tasks are owned by the server, and run until [`Server::stop_tasks`] is
+	/// called or the last clone of the server is dropped.
+	pub fn spawn_tasks(&self) {
+		let mut tasks = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+		let tasks = &mut *tasks;
+		let shared = &self.shared;
+		let features = &self.features;
+		let config = shared.config.current();
+
+		if features.vehicle {
+			supervise(tasks, shared, "flight connection", flight::auto_connect);
+			supervise(tasks, shared, "telemetry (udp)", flight::receive_vehicle_state);
+			supervise(tasks, shared, "telemetry (tcp)", flight::receive_vehicle_state_stream);
+			supervise(tasks, shared, "frame recorder", recording::write_frame_log);
+
+			if config.flight.serial.device.is_some() {
+				supervise(tasks, shared, "flight connection (serial)", flight::connect_serial);
+			}
+		}
+
+		if features.standby && config.standby.primary.is_some() {
+			supervise(tasks, shared, "standby replication", standby::follow);
+		}
+
+		if features.discovery && config.discovery.advertise {
+			supervise(tasks, shared, "mdns advertisement", discovery::advertise);
+		}
+
+		if features.ingest {
+			let ingest_config = &config.ingest;
+
+			if ingest_config.sam_enabled {
+				let port = ingest_config.sam_port;
+				supervise(tasks, shared, "sam ingest", move |shared| ingest::receive_sam_data(shared, port));
+			}
+
+			decoder::spawn_sources(tasks, shared, &DecoderRegistry::default());
+		}
+
+		if features.logging {
+			supervise(tasks, shared, "vehicle state logger", |shared| shared.database.log_vehicle_state(shared));
+			supervise(tasks, shared, "snapshot recompression", snapshot::recompress);
+			supervise(tasks, shared, "database backups", backup::schedule);
+			supervise(tasks, shared, "log retention", retention::enforce);
+			supervise(tasks, shared, "database maintenance", vacuum::maintain);
+		}
+
+		if features.integrations {
+			supervise(tasks, shared, "influx push", influx::push_live);
+			#[cfg(feature = "mqtt")]
+			supervise(tasks, shared, "mqtt publisher", mqtt::publish);
+		}
+
+		if features.monitoring {
+			supervise(tasks, shared, "alert rules", alert::monitor);
+			supervise(tasks, shared, "system events", alert::monitor_system);
+			supervise(tasks, shared, "anomaly detection", anomaly::monitor);
+			supervise(tasks, shared, "channel pairs", pair::monitor);
+			supervise(tasks, shared, "capture rules", capture::monitor);
+		}
+
+		supervise(tasks, shared, "command authority", authority::watch);
+
+		#[cfg(unix)]
+		if features.reload_on_hangup {
+			supervise(tasks, shared, "config reload (SIGHUP)", config::reload_on_hangup);
+		}
+	}
+
+	/// Aborts the supervised background tasks and waits for them to stop.
+	pub async fn stop_tasks(&self) {
+		let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(PoisonError::into_inner));
+		tasks.shutdown().await;
+	}
+
+	/// Serves the route functions with the configured CORS policy; Exits when the shutdown_future returns via a graceful shutdown.
 	/// Of note is that this graceful shutdown can wait for outstanding requests to complete (such as an oversized export),
 	/// Which may delay the time it takes for the program to truly exit after the shutdown_future has returned.
-	pub async fn serve<'a>(&'a self, shutdown_future : tokio::task::JoinHandle<io::Result<()>>) -> io::Result<()> {
+	pub async fn serve(&self, shutdown_future: impl Future<Output = ()> + Send + 'static) -> io::Result<()> {
 		use axum::routing::{get, post, put, delete};
 
-		let cors = CorsLayer::new()
-			.allow_methods(cors::Any)
-			.allow_headers(cors::Any)
-			.allow_origin(cors::Any);
+		let config = self.shared.config.current();
+		let limits = &config.limits;
+
+		// exports, reports, archives, backfills, syncs, and imports can legitimately take far longer
+		// than any other request, so they get their own timeout rather than the default one.
+		let long_running = Router::new()
+			.route("/data/export", post(routes::export))
+			.route("/data/report", get(routes::get_report))
+			.route("/data/archive", get(routes::get_archive))
+			.route("/data/influx/backfill", post(routes::backfill_influx))
+			.route("/data/sync", post(routes::post_sync_batch).layer(DefaultBodyLimit::max(limits.sync_body_bytes)))
+			.route("/data/import", post(routes::import_data).layer(DefaultBodyLimit::max(limits.import_body_bytes)))
+			.layer(
+				ServiceBuilder::new()
+					.layer(HandleErrorLayer::new(error::handle_middleware_error))
+					.timeout(Duration::from_secs_f64(limits.export_timeout_seconds))
+			);
+
+		// gRPC and standby streams stay open for as long as the client wants them, so they have no timeout at all
+		let streams = Router::new()
+			.route_service(grpc::ROUTE, ServoServer::new(grpc::ServoService::new(&self.shared)))
+			.route("/standby/stream", get(routes::stream_to_standby));
 
 		let router = Router::new()
 			.route("/data/forward", get(routes::forward_data))
-			.route("/data/export", post(routes::export))
+			.route("/data/state", get(routes::get_vehicle_state))
+			.route("/data/pairs", get(routes::get_pairs))
+			.route("/data/stats", get(routes::get_stats))
+			.route("/data/run-sheet", get(routes::get_run_sheet))
+			.route("/data/captures", get(routes::get_captures))
+			.route("/data/bad-frames", get(routes::get_bad_frames))
+			.route("/data/channels", get(routes::get_channels))
+			.route("/data/track", get(routes::get_track))
+			.route("/data/export-presets", get(routes::get_export_presets))
+			.route("/data/sync", get(routes::get_sync_batch))
+			.route("/flight/info", get(routes::get_flight_info))
+			.route("/flight/sequence-finished", post(routes::report_sequence_finished))
+			.route("/status/metrics", get(routes::get_metrics))
+			.route("/status/alerts", get(routes::get_alerts))
+			.route("/status/tasks", get(routes::get_tasks))
+			.route("/status/sequences", get(routes::get_active_sequences))
+			.route("/status/clocks", get(routes::get_clocks))
+			.route("/status/outbox", get(routes::get_outbox))
+			.route("/auth/login", post(routes::login))
+			.route("/auth/logout", post(routes::logout))
 			.route("/admin/sql", post(routes::execute_sql))
+			.route("/admin/users", put(routes::set_user))
+			.route("/admin/sessions", get(routes::list_sessions))
+			.route("/admin/sessions", delete(routes::revoke_user_sessions))
+			.route("/admin/sessions/:session_id", delete(routes::revoke_session))
+			.route("/admin/reload", post(routes::reload_config))
+			.route("/admin/backup", post(routes::backup_database))
+			.route("/admin/logs", delete(routes::prune_logs))
+			.route("/admin/outbox", delete(routes::purge_outbox))
+			.route("/admin/backup/changes", post(routes::get_backup_changes))
+			.route("/admin/flight-allowlist", get(routes::get_flight_allowlist))
+			.route("/admin/flight-allowlist", post(routes::add_flight_allowlist_host))
+			.route("/admin/flight-allowlist", delete(routes::remove_flight_allowlist_host))
+			.route("/admin/maintenance", get(routes::get_maintenance_mode))
+			.route("/admin/maintenance", post(routes::set_maintenance_mode))
+			.route("/admin/spectator", get(routes::get_spectator_mode))
+			.route("/admin/spectator", put(routes::set_spectator_mode))
+			.route("/meta/schema", get(routes::get_schema))
+			.route("/notes", get(routes::get_notes))
+			.route("/notes", post(routes::post_note))
+			.route("/profiles/:username", get(routes::list_profiles))
+			.route("/profiles/:username/:name", get(routes::get_profile))
+			.route("/profiles/:username/:name", put(routes::save_profile))
+			.route("/profiles/:username/:name", delete(routes::delete_profile))
+			.route("/standby/status", get(routes::get_standby_status))
+			.route("/standby/promote", post(routes::promote))
+			.route("/standby/fence", post(routes::fence))
+			.route("/operator/authority", get(routes::hold_authority))
 			.route("/operator/command", post(routes::dispatch_operator_command))
+			.route("/operator/command/confirm", post(routes::confirm_operator_command))
+			.route("/operator/hazards", get(routes::get_hazards))
+			.route("/operator/hazards", put(routes::set_hazards))
 			.route("/operator/mappings", get(routes::get_mappings))
-			.route("/operator/mappings", post(routes::post_mappings))
-			.route("/operator/mappings", put(routes::put_mappings))
+			.route("/operator/mappings", post(routes::post_mappings).layer(DefaultBodyLimit::max(limits.mappings_body_bytes)))
+			.route("/operator/mappings", put(routes::put_mappings).layer(DefaultBodyLimit::max(limits.mappings_body_bytes)))
 			.route("/operator/mappings", delete(routes::delete_mappings))
+			.route("/operator/mappings/validate", post(routes::validate_mappings).layer(DefaultBodyLimit::max(limits.mappings_body_bytes)))
 			.route("/operator/active-configuration", get(routes::get_active_configuration))
 			.route("/operator/active-configuration", post(routes::activate_configuration))
 			.route("/operator/calibrate", post(routes::calibrate))
 			.route("/operator/sequence", get(routes::retrieve_sequences))
-			.route("/operator/sequence", put(routes::save_sequence))
+			.route("/operator/sequence", put(routes::save_sequence).layer(DefaultBodyLimit::max(limits.sequence_body_bytes)))
 			.route("/operator/sequence", delete(routes::delete_sequence))
+			.route("/operator/sequence-bundle", put(routes::save_sequence_bundle).layer(DefaultBodyLimit::max(limits.sequence_body_bytes)))
 			.route("/operator/run-sequence", post(routes::run_sequence))
+			.route("/operator/sequences/run-adhoc", post(routes::run_adhoc_sequence))
+			.route("/operator/sequences/simulate", post(routes::simulate_sequence))
+			.route("/operator/snippets", get(routes::get_snippets))
+			.route("/operator/snippets", put(routes::save_snippet))
+			.route("/operator/snippets", delete(routes::delete_snippet))
+			.route("/operator/snippets/instantiate", post(routes::instantiate_snippet))
+			.route("/operator/export-presets", put(routes::save_export_preset))
+			.route("/operator/export-presets", delete(routes::delete_export_preset))
+			.route("/operator/thresholds", get(routes::get_thresholds))
+			.route("/operator/thresholds", put(routes::set_thresholds))
+			.route("/operator/interlocks", get(routes::get_interlocks))
+			.route("/operator/interlocks", put(routes::set_interlocks))
+			.route("/operator/interlocks", delete(routes::delete_interlocks))
+			.route("/operator/safing-sequence", put(routes::set_safing_sequence))
+			.route("/operator/safing-sequence", delete(routes::clear_safing_sequence))
+			.route("/operator/safe", post(routes::safe))
+			.route("/operator/alert", post(routes::raise_alert))
 			.route("/operator/stop-sequence", post(routes::stop_sequence))
 			.route("/operator/abort", post(routes::abort))
 			.route("/operator/trigger", get(routes::get_triggers))
 			.route("/operator/trigger", put(routes::set_trigger))
 			.route("/operator/trigger", delete(routes::delete_trigger))
-			.layer(cors)
+			.layer(
+				ServiceBuilder::new()
+					.layer(HandleErrorLayer::new(error::handle_middleware_error))
+					.timeout(Duration::from_secs_f64(limits.request_timeout_seconds))
+			)
+			.merge(long_running)
+			.merge(streams)
+			.layer(middleware::from_fn_with_state(self.shared.clone(), standby::require_primary))
+			.layer(middleware::from_fn_with_state(self.shared.clone(), authority::require_authority))
+			.layer(middleware::from_fn_with_state(self.shared.clone(), maintenance::enforce_maintenance))
+			.layer(middleware::from_fn_with_state(self.shared.clone(), spectator::enforce_spectator))
+			.layer(GlobalConcurrencyLimitLayer::new(limits.max_concurrent_requests))
+			// routes with limits of their own override this one, since the innermost limit is used
+			.layer(DefaultBodyLimit::max(limits.max_body_bytes))
+			.layer(middleware::from_fn_with_state(self.shared.clone(), auth::authenticate))
+			.layer(cors::layer(&config.cors))
+			.layer(middleware::from_fn_with_state(self.shared.clone(), trace::assign_request_id))
 			.with_state(self.shared.clone())
 			.into_make_service_with_connect_info::<SocketAddr>();
 
-		let listener = TcpListener::bind("0.0.0.0:7200").await?;	// Get TCP Listener
+		let listener = TcpListener::bind(self.shared.addresses.http).await?;	// Get TCP Listener
 		axum::serve(listener, router)	// Launch listener with routings set before (Launch the web server with these routings on these ports / ip / hostname / etc on this listener)
-		.with_graceful_shutdown(wait_for_display_end(shutdown_future))	// Gracefully shut down when the shutdown_future has returned / finished. This is typically the TUI or an infinite hang / pending future
+		.with_graceful_shutdown(shutdown_future)	// Gracefully shut down when the shutdown_future has returned / finished. This is typically the TUI or an infinite hang / pending future
 		.await?;
 
 		Ok(())
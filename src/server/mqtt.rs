@@ -0,0 +1,171 @@
+use common::comm::VehicleState;
+use jeflog::{pass, warn};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet};
+use serde_json::json;
+use std::{future::Future, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+use tokio::time::MissedTickBehavior;
+
+use super::{config::MqttConfig, Shared};
+
+/// How long to wait before checking the configuration again while publishing is disabled.
+const DISABLED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long to wait before reconnecting after the connection to the broker fails.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// The number of messages which may wait to be sent to the broker. Messages
+/// beyond this are dropped rather than delaying the next vehicle state.
+const QUEUE_CAPACITY: usize = 4096;
+
+/// The topics and JSON payloads published for a vehicle state at a Unix timestamp.
+pub fn messages(config: &MqttConfig, vehicle_state: &VehicleState, timestamp: f64) -> Vec<(String, String)> {
+	let selected = |channel: &String| config.channels.is_empty() || config.channels.contains(channel);
+	let prefix = config.topic_prefix.trim_end_matches('/');
+
+	let sensors = vehicle_state.sensor_readings
+		.iter()
+		.filter(|(channel, _)| selected(channel))
+		.map(|(channel, reading)| {
+			let payload = json!({
+				"value": reading.value,
+				"unit": reading.unit.to_string(),
+				"timestamp": timestamp,
+			});
+
+			(format!("{prefix}/sensors/{channel}"), payload.to_string())
+		});
+
+	let valves = vehicle_state.valve_states
+		.iter()
+		.filter(|(channel, _)| selected(channel))
+		.map(|(channel, valve_state)| {
+			let payload = json!({
+				"commanded": valve_state.commanded.to_string(),
+				"actual": valve_state.actual.to_string(),
+				"timestamp": timestamp,
+			});
+
+			(format!("{prefix}/valves/{channel}"), payload.to_string())
+		});
+
+	sensors.chain(valves).collect()
+}
+
+/// Continuously publishes the selected channels to the configured MQTT broker.
+///
+/// The latest vehicle state is published at the configured rate, and only if
+/// it changed since it was last published. The connection is remade whenever
+/// the `[mqtt]` section of the configuration changes.
+pub fn publish(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		loop {
+			let config = shared.config.current().mqtt.clone();
+
+			let Some(host) = &config.host else {
+				tokio::time::sleep(DISABLED_POLL_INTERVAL).await;
+				continue;
+			};
+
+			let mut options = MqttOptions::new(&config.client_id, host, config.port);
+
+			if let (Some(username), Some(password)) = (&config.username, &config.password) {
+				options.set_credentials(username, password);
+			}
+
+			// an invalid level is rejected when the configuration is reloaded, but may still be loaded at startup
+			let qos = rumqttc::qos(config.qos).unwrap_or(rumqttc::QoS::AtMostOnce);
+			let (client, mut event_loop) = AsyncClient::new(options, QUEUE_CAPACITY);
+
+			let period = Duration::try_from_secs_f64(1.0 / config.rate_hz).unwrap_or(Duration::from_millis(100));
+			let mut interval = tokio::time::interval(period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+			let mut last_published: Option<Arc<VehicleState>> = None;
+			let mut connected = false;
+
+			loop {
+				tokio::select! {
+					event = event_loop.poll() => match event {
+						Ok(Event::Incoming(Packet::ConnAck(_))) => {
+							pass!("Connected to MQTT broker at \x1b[1m{host}:{}\x1b[0m.", config.port);
+							connected = true;
+						},
+						Ok(_) => {},
+						Err(error) => {
+							if connected {
+								warn!("Lost connection to MQTT broker: {error}");
+								connected = false;
+							}
+
+							tokio::time::sleep(RECONNECT_DELAY).await;
+						},
+					},
+					_ = interval.tick() => {
+						if shared.config.current().mqtt != config {
+							break;
+						}
+
+						if !connected {
+							continue;
+						}
+
+						let snapshot = Arc::clone(&*shared.vehicle.0.lock().await);
+
+						if last_published.as_ref().is_some_and(|last| Arc::ptr_eq(last, &snapshot)) {
+							continue;
+						}
+
+						let now = SystemTime::now()
+							.duration_since(UNIX_EPOCH)
+							.map_or(0.0, |duration| duration.as_secs_f64());
+
+						for (topic, payload) in messages(&config, &snapshot, now) {
+							if client.try_publish(topic, qos, false, payload).is_err() {
+								break;
+							}
+						}
+
+						last_published = Some(snapshot);
+					},
+				}
+			}
+
+			let _ = client.try_disconnect();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+	use super::*;
+
+	#[test]
+	fn selected_channels_are_published_per_topic() {
+		let mut vehicle_state = VehicleState::new();
+		vehicle_state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 14.5, unit: Unit::Psi });
+		vehicle_state.sensor_readings.insert("WTPT".to_owned(), Measurement { value: 3.0, unit: Unit::Psi });
+		vehicle_state.valve_states.insert("BBV".to_owned(), CompositeValveState {
+			commanded: ValveState::Open,
+			actual: ValveState::Closed,
+		});
+
+		let config = MqttConfig {
+			topic_prefix: "pad/servo/".to_owned(),
+			channels: vec!["KBPT".to_owned(), "BBV".to_owned()],
+			..MqttConfig::default()
+		};
+
+		let messages = messages(&config, &vehicle_state, 2.5);
+		let topics = messages.iter().map(|(topic, _)| topic.as_str()).collect::<Vec<_>>();
+		assert_eq!(topics, ["pad/servo/sensors/KBPT", "pad/servo/valves/BBV"]);
+
+		let sensor = serde_json::from_str::<serde_json::Value>(&messages[0].1).unwrap();
+		assert_eq!(sensor, json!({ "value": 14.5, "unit": Unit::Psi.to_string(), "timestamp": 2.5 }));
+
+		let valve = serde_json::from_str::<serde_json::Value>(&messages[1].1).unwrap();
+		assert_eq!(valve["actual"], ValveState::Closed.to_string());
+	}
+}
@@ -0,0 +1,101 @@
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+
+/// A timestamped free-text note entered by an operator, such as an observation
+/// made during a test which would otherwise go in a paper notebook.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Note {
+	/// The unique ID of the note.
+	pub note_id: i64,
+
+	/// Who entered the note, if known.
+	pub author: Option<String>,
+
+	/// The session the note was entered through, if it still exists.
+	pub session_id: Option<i64>,
+
+	/// The text of the note.
+	pub content: String,
+
+	/// When the note was entered, as a Unix timestamp.
+	pub created_at: f64,
+}
+
+/// Records a new note, returning it as stored.
+pub fn record(connection: &Connection, author: Option<&str>, session_id: Option<i64>, content: &str) -> rusqlite::Result<Note> {
+	connection.query_row(
+		"INSERT INTO Notes (author, session_id, content) VALUES (?1, ?2, ?3) RETURNING note_id, author, session_id, content, created_at",
+		params![author, session_id, content],
+		from_row,
+	)
+}
+
+/// Lists the notes entered between two Unix timestamps, oldest first.
+pub fn list(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<Note>> {
+	connection
+		.prepare("
+			SELECT note_id, author, session_id, content, created_at
+			FROM Notes
+			WHERE created_at >= ?1 AND created_at <= ?2
+			ORDER BY created_at, note_id
+		")?
+		.query_map([from, to], from_row)?
+		.collect()
+}
+
+/// Reads a note from a row of its columns, in the order they are declared.
+fn from_row(row: &Row) -> rusqlite::Result<Note> {
+	Ok(Note {
+		note_id: row.get(0)?,
+		author: row.get(1)?,
+		session_id: row.get(2)?,
+		content: row.get(3)?,
+		created_at: row.get(4)?,
+	})
+}
+
+/// The notes falling on each row of an export, where a note belongs to the
+/// first row recorded at or after it, joined by semicolons when several do.
+///
+/// Notes after the last row are left out, since they have nowhere to go.
+pub fn align(notes: &[Note], timestamps: &[f64]) -> Vec<Option<String>> {
+	let mut aligned = vec![None::<String>; timestamps.len()];
+
+	for note in notes {
+		let row = timestamps.partition_point(|&timestamp| timestamp < note.created_at);
+
+		if let Some(cell) = aligned.get_mut(row) {
+			match cell {
+				Some(cell) => {
+					cell.push_str("; ");
+					cell.push_str(&note.content);
+				},
+				None => *cell = Some(note.content.clone()),
+			}
+		}
+	}
+
+	aligned
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn notes_fall_on_the_next_row() {
+		let note = |created_at: f64, content: &str| Note {
+			note_id: 0,
+			author: None,
+			session_id: None,
+			content: content.to_owned(),
+			created_at,
+		};
+
+		let notes = [note(0.5, "chilldown"), note(2.0, "fill"), note(2.5, "leak at QD"), note(9.0, "too late")];
+		let aligned = align(&notes, &[1.0, 2.0, 3.0]);
+
+		assert_eq!(aligned, [Some("chilldown".to_owned()), Some("fill".to_owned()), Some("leak at QD".to_owned())]);
+		assert_eq!(align(&notes[1..3], &[3.0]), [Some("fill; leak at QD".to_owned())]);
+	}
+}
@@ -0,0 +1,133 @@
+use anyhow::bail;
+use jeflog::warn;
+use lettre::{message::Mailbox, transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+use serde_json::json;
+use std::process::Command;
+
+use super::{alert::Alert, config::{EmailSecurity, NotificationAction, NotificationConfig, WebhookFormat}};
+
+/// Notifies operators of an alert through each of the named actions.
+///
+/// Actions run in the background so that a slow webhook or a long sound cannot
+/// hold up the caller. Failures are logged rather than returned.
+pub fn notify(config: &NotificationConfig, action_names: &[String], alert: &Alert) {
+	for name in action_names {
+		let Some(action) = config.actions.get(name) else {
+			warn!("Notification action '{name}' is not defined.");
+			continue;
+		};
+
+		let name = name.clone();
+		let action = action.clone();
+		let alert = alert.clone();
+
+		tokio::spawn(async move {
+			if let Err(error) = perform(&action, &alert).await {
+				warn!("Notification action '{name}' failed: {error}");
+			}
+		});
+	}
+}
+
+/// Performs a single notification action for an alert.
+pub async fn perform(action: &NotificationAction, alert: &Alert) -> anyhow::Result<()> {
+	match action {
+		NotificationAction::Sound { file, player } => {
+			let mut command = Command::new(player);
+			command.arg(file);
+			run(command).await
+		},
+		NotificationAction::Speech { synthesizer } => {
+			let mut command = Command::new(synthesizer);
+			command.arg(format!("{}: {}", alert.rule, alert.message));
+			run(command).await
+		},
+		NotificationAction::Hook { command } => {
+			let mut command = shell(command);
+
+			command
+				.env("SERVO_ALERT_RULE", &alert.rule)
+				.env("SERVO_ALERT_SEVERITY", alert.severity.as_str())
+				.env("SERVO_ALERT_MESSAGE", &alert.message)
+				.env("SERVO_ALERT_RAISED_AT", alert.raised_at.to_string())
+				.env("SERVO_ALERT_CAUSES", alert.causes.join("; "));
+
+			run(command).await
+		},
+		NotificationAction::Email { host, port, security, username, password, from, to } => {
+			let mut message = Message::builder()
+				.from(from.parse::<Mailbox>()?)
+				.subject(format!("[servo] {} alert: {}", alert.severity, alert.rule));
+
+			for recipient in to {
+				message = message.to(recipient.parse::<Mailbox>()?);
+			}
+
+			let message = message.body(format!("{alert}\n\nRaised at {} (Unix time).\n", alert.raised_at))?;
+
+			let mut transport = match security {
+				EmailSecurity::StartTls => SmtpTransport::starttls_relay(host)?,
+				EmailSecurity::Tls => SmtpTransport::relay(host)?,
+				EmailSecurity::None => SmtpTransport::builder_dangerous(host),
+			};
+
+			if let Some(port) = port {
+				transport = transport.port(*port);
+			}
+
+			if let (Some(username), Some(password)) = (username, password) {
+				transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+			}
+
+			let transport = transport.build();
+			tokio::task::spawn_blocking(move || transport.send(&message)).await??;
+
+			Ok(())
+		},
+		NotificationAction::Webhook { url, format } => {
+			let text = alert.to_string();
+
+			let payload = match format {
+				WebhookFormat::Slack => json!({ "text": text }),
+				WebhookFormat::Discord => json!({ "content": text }),
+			};
+
+			reqwest::Client::new()
+				.post(url)
+				.json(&payload)
+				.send()
+				.await?
+				.error_for_status()?;
+
+			Ok(())
+		},
+	}
+}
+
+/// Builds a command which runs a line in the platform's shell.
+fn shell(line: &str) -> Command {
+	let mut command;
+
+	if cfg!(target_family = "windows") {
+		command = Command::new("cmd");
+		command.arg("/C");
+	} else {
+		command = Command::new("sh");
+		command.arg("-c");
+	}
+
+	command.arg(line);
+	command
+}
+
+/// Runs a command to completion off of the async runtime, failing if it exits unsuccessfully.
+async fn run(mut command: Command) -> anyhow::Result<()> {
+	let program = command.get_program().to_string_lossy().into_owned();
+	let status = tokio::task::spawn_blocking(move || command.status()).await??;
+
+	if !status.success() {
+		bail!("{program} exited with {status}");
+	}
+
+	Ok(())
+}
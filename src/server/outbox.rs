@@ -0,0 +1,166 @@
+use common::comm::Computer;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::{atomic::{AtomicU64, Ordering}, Mutex, PoisonError}};
+
+use super::clock;
+
+/// The most failed messages kept, beyond which the oldest are forgotten.
+const MAX_FAILED: usize = 256;
+
+/// Whether a message has yet to reach a computer, or never will.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+	/// The message is being written to the link.
+	Pending,
+
+	/// Writing the message to the link failed.
+	Failed,
+}
+
+/// A message to a vehicle computer which has not been written to its link.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OutboxEntry {
+	/// The ID of the message, unique while the server runs.
+	pub id: u64,
+
+	/// The computer the message is for.
+	pub computer: Computer,
+
+	/// What the message is, such as `sequence 'press'`.
+	pub description: String,
+
+	/// The ID of the request which sent the message, if one did.
+	pub request_id: Option<String>,
+
+	/// When the message was handed to the link, as a Unix timestamp.
+	pub queued_at: f64,
+
+	/// Whether the message is still being written or failed to be.
+	pub status: OutboxStatus,
+
+	/// Why the message failed, if it did.
+	pub error: Option<String>,
+}
+
+/// A message in the outbox, and how long it has waited.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutboxMessage {
+	/// The message itself.
+	#[serde(flatten)]
+	pub entry: OutboxEntry,
+
+	/// The number of seconds since the message was handed to the link.
+	pub age_seconds: f64,
+}
+
+/// The messages to the vehicle computers which have not reached their links,
+/// so that operators know when a click did not reach the vehicle.
+///
+/// Messages are forgotten as soon as they are written, so only those still
+/// being written and those which failed are kept.
+#[derive(Debug, Default)]
+pub struct Outbox {
+	next_id: AtomicU64,
+	entries: Mutex<BTreeMap<u64, OutboxEntry>>,
+}
+
+impl Outbox {
+	/// Notes that a message is being written to a computer, returning its ID.
+	pub fn begin(&self, computer: Computer, description: &str, request_id: Option<String>) -> u64 {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+		self.entries().insert(id, OutboxEntry {
+			id,
+			computer,
+			description: description.to_owned(),
+			request_id,
+			queued_at: clock::now(),
+			status: OutboxStatus::Pending,
+			error: None,
+		});
+
+		id
+	}
+
+	/// Notes whether a message was written, forgetting it if it was.
+	pub fn finish<E: ToString>(&self, id: u64, result: Result<(), E>) {
+		let mut entries = self.entries();
+
+		let Err(error) = result else {
+			entries.remove(&id);
+			return;
+		};
+
+		// the entry is gone if it was purged while being written.
+		if let Some(entry) = entries.get_mut(&id) {
+			entry.status = OutboxStatus::Failed;
+			entry.error = Some(error.to_string());
+		}
+
+		let failed = entries
+			.values()
+			.filter(|entry| entry.status == OutboxStatus::Failed)
+			.map(|entry| entry.id)
+			.collect::<Vec<_>>();
+
+		for id in &failed[..failed.len().saturating_sub(MAX_FAILED)] {
+			entries.remove(id);
+		}
+	}
+
+	/// The messages still being written and those which failed, oldest first.
+	pub fn messages(&self) -> Vec<OutboxMessage> {
+		let now = clock::now();
+
+		self.entries()
+			.values()
+			.map(|entry| OutboxMessage { entry: entry.clone(), age_seconds: (now - entry.queued_at).max(0.0) })
+			.collect()
+	}
+
+	/// Forgets the messages handed to a link at least a number of seconds ago,
+	/// returning how many were forgotten.
+	pub fn purge(&self, older_than_seconds: f64) -> usize {
+		let cutoff = clock::now() - older_than_seconds;
+		let mut entries = self.entries();
+		let before = entries.len();
+
+		entries.retain(|_, entry| entry.queued_at > cutoff);
+		before - entries.len()
+	}
+
+	fn entries(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, OutboxEntry>> {
+		self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use super::*;
+
+	#[test]
+	fn only_unwritten_messages_are_kept() {
+		let outbox = Outbox::default();
+
+		let mappings = outbox.begin(Computer::Flight, "2 mappings", None);
+		let abort = outbox.begin(Computer::Flight, "abort", Some("r-1".to_owned()));
+		let press = outbox.begin(Computer::Flight, "sequence 'press'", None);
+
+		outbox.finish(mappings, Ok::<_, io::Error>(()));
+		outbox.finish(abort, Err(io::Error::from(io::ErrorKind::BrokenPipe)));
+
+		let messages = outbox.messages();
+		assert_eq!(messages.iter().map(|message| message.entry.id).collect::<Vec<_>>(), [abort, press]);
+		assert_eq!(messages[0].entry.status, OutboxStatus::Failed);
+		assert_eq!(messages[1].entry.status, OutboxStatus::Pending);
+
+		assert_eq!(outbox.purge(3600.0), 0);
+		assert_eq!(outbox.purge(0.0), 2);
+
+		// a message purged while it was being written stays forgotten
+		outbox.finish(press, Err("timed out"));
+		assert!(outbox.messages().is_empty());
+	}
+}
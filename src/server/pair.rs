@@ -0,0 +1,152 @@
+use common::comm::{Unit, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::{Duration, Instant}};
+
+use super::{alert::{self, Alert, RuleTracker}, config::ChannelPairConfig, Shared};
+
+/// How often the configuration is checked while no pair has a tolerance.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The difference between a pair of channels in a vehicle state.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PairReading {
+	/// The name of the pair.
+	pub name: String,
+
+	/// The channel the second is subtracted from.
+	pub first: String,
+
+	/// The channel subtracted from the first.
+	pub second: String,
+
+	/// The first channel's reading less the second's, or `None` if either has
+	/// not been reported, or they were reported in different units.
+	pub difference: Option<f64>,
+
+	/// The unit of the difference, if it could be computed.
+	pub unit: Option<Unit>,
+
+	/// The largest difference in either direction before the channels disagree, if any.
+	pub tolerance: Option<f64>,
+
+	/// Whether the difference exceeds the tolerance.
+	pub disagrees: bool,
+}
+
+/// Computes the difference between a pair of channels in a vehicle state.
+pub fn read(pair: &ChannelPairConfig, state: &VehicleState) -> PairReading {
+	let first = state.sensor_readings.get(&pair.first);
+	let second = state.sensor_readings.get(&pair.second);
+
+	let (difference, unit) = match (first, second) {
+		(Some(first), Some(second)) if first.unit == second.unit && (first.value - second.value).is_finite() => {
+			(Some(first.value - second.value), Some(first.unit))
+		},
+		_ => (None, None),
+	};
+
+	PairReading {
+		name: pair.name.clone(),
+		first: pair.first.clone(),
+		second: pair.second.clone(),
+		difference,
+		unit,
+		tolerance: pair.tolerance,
+		disagrees: difference.zip(pair.tolerance).is_some_and(|(difference, tolerance)| difference.abs() > tolerance),
+	}
+}
+
+/// Continuously computes the difference of each pair with a tolerance, raising
+/// an alert named after the pair when its channels disagree beyond it for long
+/// enough.
+///
+/// Pairs are read from the configuration each time, so they may be changed by
+/// reloading it. A pair whose channels are missing never alerts.
+pub fn monitor(shared: &Shared) -> impl Future<Output = ()> {
+	let shared = shared.clone();
+
+	async move {
+		let mut trackers = BTreeMap::<String, RuleTracker>::new();
+
+		loop {
+			let config = shared.config.current();
+
+			if config.pairs.iter().all(|pair| pair.tolerance.is_none()) {
+				trackers.clear();
+
+				tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+				continue;
+			}
+
+			if tokio::time::timeout(IDLE_POLL_INTERVAL, shared.vehicle.1.notified()).await.is_err() {
+				continue;
+			}
+
+			let vehicle = Arc::clone(&*shared.vehicle.0.lock().await);
+			let now = Instant::now();
+			let mut raised = Vec::new();
+
+			trackers.retain(|name, _| config.pairs.iter().any(|pair| pair.name == *name));
+
+			for pair in config.pairs.iter().filter(|pair| pair.tolerance.is_some()) {
+				let reading = read(pair, &vehicle);
+				let hold_for = Duration::from_secs_f64(pair.for_seconds.max(0.0));
+				let cooldown = Duration::from_secs_f64(pair.cooldown_seconds.max(0.0));
+
+				if trackers.entry(pair.name.clone()).or_default().update(reading.disagrees, now, hold_for, cooldown) {
+					let message = format!(
+						"{} and {} differ by {:.3} {}, beyond the tolerance of {}",
+						pair.first,
+						pair.second,
+						reading.difference.unwrap_or_default(),
+						reading.unit.map(|unit| unit.to_string()).unwrap_or_default(),
+						pair.tolerance.unwrap_or_default(),
+					);
+
+					raised.push((Alert::new(&pair.name, pair.severity, message), pair.actions.clone()));
+				}
+			}
+
+			for (alert, actions) in raised {
+				alert::raise(&shared, alert, &actions).await;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::Measurement;
+	use crate::server::alert::Severity;
+	use super::*;
+
+	#[test]
+	fn redundant_sensors_disagree_beyond_tolerance() {
+		let pair = ChannelPairConfig {
+			name: "KBPT disagreement".to_owned(),
+			first: "KBPT".to_owned(),
+			second: "KBPT_B".to_owned(),
+			tolerance: Some(15.0),
+			severity: Severity::Warning,
+			for_seconds: 0.0,
+			cooldown_seconds: 60.0,
+			actions: Vec::new(),
+		};
+
+		let mut state = VehicleState::new();
+		state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 500.0, unit: Unit::Psi });
+		assert_eq!(read(&pair, &state).difference, None);
+
+		state.sensor_readings.insert("KBPT_B".to_owned(), Measurement { value: 490.0, unit: Unit::Psi });
+		let reading = read(&pair, &state);
+		assert_eq!((reading.difference, reading.disagrees), (Some(10.0), false));
+
+		state.sensor_readings.insert("KBPT_B".to_owned(), Measurement { value: 520.0, unit: Unit::Psi });
+		let reading = read(&pair, &state);
+		assert_eq!((reading.difference, reading.disagrees), (Some(-20.0), true));
+
+		// readings in different units are never compared
+		state.sensor_readings.insert("KBPT_B".to_owned(), Measurement { value: 5.0, unit: Unit::Volts });
+		assert!(!read(&pair, &state).disagrees);
+	}
+}
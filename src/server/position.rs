@@ -0,0 +1,89 @@
+use common::comm::VehicleState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A position fix from a GPS receiver.
+///
+/// `VehicleState` only carries scalar measurements, so a fix is reported as
+/// readings named `<receiver>.lat`, `<receiver>.lon`, `<receiver>.alt`, and
+/// `<receiver>.fix`, the last being the fix quality as reported in an NMEA GGA
+/// sentence. Latitude and longitude are in degrees and altitude in meters above
+/// mean sea level, whatever unit the readings claim.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PositionFix {
+	/// Degrees north of the equator.
+	pub latitude: f64,
+
+	/// Degrees east of the prime meridian.
+	pub longitude: f64,
+
+	/// Meters above mean sea level, if the receiver reports altitude.
+	pub altitude: Option<f64>,
+
+	/// The fix quality, where zero means the receiver has no fix.
+	pub fix_quality: u8,
+}
+
+impl PositionFix {
+	/// Whether the receiver has a valid fix with coordinates on the globe.
+	pub fn is_valid(&self) -> bool {
+		self.fix_quality > 0
+			&& (-90.0..=90.0).contains(&self.latitude)
+			&& (-180.0..=180.0).contains(&self.longitude)
+	}
+}
+
+/// Finds the position fix of every receiver reporting one in a vehicle state, keyed by receiver.
+///
+/// A receiver must report at least a latitude and longitude. If it does not
+/// report a fix quality, the fix is assumed to be a standard GPS fix.
+pub fn find_fixes(state: &VehicleState) -> BTreeMap<String, PositionFix> {
+	let reading = |receiver: &str, component: &str| {
+		state.sensor_readings
+			.get(&format!("{receiver}.{component}"))
+			.map(|measurement| measurement.value)
+	};
+
+	state.sensor_readings
+		.keys()
+		.filter_map(|name| name.strip_suffix(".lat"))
+		.filter_map(|receiver| {
+			let fix = PositionFix {
+				latitude: reading(receiver, "lat")?,
+				longitude: reading(receiver, "lon")?,
+				altitude: reading(receiver, "alt"),
+				fix_quality: reading(receiver, "fix").map_or(1, |quality| quality as u8),
+			};
+
+			Some((receiver.to_owned(), fix))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use common::comm::{Measurement, Unit};
+
+	#[test]
+	fn fixes_are_found_by_receiver() {
+		let mut state = VehicleState::new();
+
+		let mut insert = |name: &str, value: f64| {
+			state.sensor_readings.insert(name.to_owned(), Measurement { value, unit: Unit::Volts });
+		};
+
+		insert("GPS.lat", 35.35);
+		insert("GPS.lon", -117.81);
+		insert("GPS.alt", 620.0);
+		insert("GPS.fix", 2.0);
+		insert("BACKUP.lat", 35.0);
+
+		let fixes = find_fixes(&state);
+
+		assert_eq!(fixes.len(), 1);
+		assert_eq!(fixes["GPS"], PositionFix { latitude: 35.35, longitude: -117.81, altitude: Some(620.0), fix_quality: 2 });
+		assert!(fixes["GPS"].is_valid());
+		assert!(!PositionFix { fix_quality: 0, ..fixes["GPS"] }.is_valid());
+	}
+}
@@ -0,0 +1,525 @@
+use jeflog::{fail, pass, warn};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::{
+	fmt,
+	net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use sysinfo::{DiskExt, System, SystemExt};
+
+use super::{config::StorageBackend, decoder::DecoderRegistry, fault, interlock::Condition, telemetry, Config, Database};
+
+/// The earliest plausible Unix time, 2024-01-01. A clock reading earlier than
+/// this has almost certainly been reset, such as on a computer without an RTC.
+const EARLIEST_PLAUSIBLE_TIME: f64 = 1_704_067_200.0;
+
+/// How long to wait when checking whether an expected host is reachable.
+const HOST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The outcome of a single preflight check.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+	/// The check passed.
+	Go,
+
+	/// The check found something worth looking at, but nothing which prevents a test.
+	Warn,
+
+	/// The check failed, and the test should not proceed until it is resolved.
+	NoGo,
+}
+
+/// The result of a single preflight check.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Check {
+	/// What was checked, such as `port 7200/tcp`.
+	pub name: String,
+
+	/// The outcome of the check.
+	pub verdict: Verdict,
+
+	/// What was found.
+	pub detail: String,
+}
+
+impl Check {
+	fn new(name: impl fmt::Display, verdict: Verdict, detail: impl fmt::Display) -> Self {
+		Check {
+			name: name.to_string(),
+			verdict,
+			detail: detail.to_string(),
+		}
+	}
+}
+
+/// The results of every preflight check which was run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Report {
+	/// Each check, in the order it was run.
+	pub checks: Vec<Check>,
+}
+
+impl Report {
+	/// The overall verdict, which is the worst of any check.
+	pub fn verdict(&self) -> Verdict {
+		self.checks
+			.iter()
+			.map(|check| check.verdict)
+			.max()
+			.unwrap_or(Verdict::Go)
+	}
+
+	/// Logs each check and the overall verdict.
+	pub fn log(&self) {
+		for check in &self.checks {
+			match check.verdict {
+				Verdict::Go => pass!("\x1b[1m{}\x1b[0m: {}", check.name, check.detail),
+				Verdict::Warn => warn!("\x1b[1m{}\x1b[0m: {}", check.name, check.detail),
+				Verdict::NoGo => fail!("\x1b[1m{}\x1b[0m: {}", check.name, check.detail),
+			}
+		}
+
+		match self.verdict() {
+			Verdict::Go => pass!("Preflight: \x1b[1mGO\x1b[0m."),
+			Verdict::Warn => warn!("Preflight: \x1b[1mGO\x1b[0m with warnings."),
+			Verdict::NoGo => fail!("Preflight: \x1b[1mNO-GO\x1b[0m."),
+		}
+	}
+}
+
+/// Runs every preflight check against the Servo directory.
+///
+/// Nothing is modified: the database is migrated inside a transaction which is rolled back.
+pub fn run(servo_dir: &Path) -> Report {
+	let mut report = Report::default();
+
+	let config = match Config::load(&servo_dir.join("config.toml")) {
+		Ok(config) => {
+			report.checks.push(Check::new("config", Verdict::Go, "config.toml is valid"));
+			config
+		},
+		Err(error) => {
+			report.checks.push(Check::new("config", Verdict::NoGo, format!("config.toml is invalid: {error}")));
+			Config::default()
+		},
+	};
+
+	report.checks.extend(check_config(&config));
+
+	let database_path = servo_dir.join("database.sqlite");
+	report.checks.push(check_database(&database_path));
+	report.checks.extend(startup_checks(&config, &database_path).checks);
+	report.checks.extend(config.preflight.expected_hosts.iter().map(|host| check_host(host)));
+
+	report
+}
+
+/// Runs the checks which are cheap enough to run every time the server starts.
+pub fn startup_checks(config: &Config, database_path: &Path) -> Report {
+	let mut report = Report::default();
+
+	report.checks.extend(check_ports(config));
+
+	if let Some(minimum) = config.notifications.events.disk_free_minimum_megabytes {
+		report.checks.push(check_disk_space(database_path, minimum));
+	}
+
+	report.checks.push(check_clock(database_path));
+
+	if let Some(check) = check_datagram_size(config) {
+		report.checks.push(check);
+	}
+
+	report
+}
+
+/// Checks that telemetry datagrams as large as the network can carry unfragmented,
+/// such as jumbo frames, are accepted without being truncated.
+fn check_datagram_size(config: &Config) -> Option<Check> {
+	let limit = config.flight.max_datagram_size();
+	let payload = telemetry::largest_link_payload()?;
+
+	Some(if limit >= payload {
+		Check::new("telemetry datagrams", Verdict::Go, format!("accepted up to {limit} bytes"))
+	} else {
+		Check::new(
+			"telemetry datagrams",
+			Verdict::Warn,
+			format!("accepted up to {limit} bytes, but the network carries datagrams of up to {payload} bytes"),
+		)
+	})
+}
+
+/// Checks the parts of the configuration which can only be validated once it has been parsed.
+pub fn check_config(config: &Config) -> Vec<Check> {
+	let mut checks = Vec::new();
+	let notifications = &config.notifications;
+
+	for rule in &notifications.rules {
+		let name = format!("alert rule '{}'", rule.name);
+
+		if let Err(error) = rule.condition.parse::<Condition>() {
+			checks.push(Check::new(&name, Verdict::NoGo, format!("invalid condition: {error}")));
+		}
+
+		for action in &rule.actions {
+			if !notifications.actions.contains_key(action) {
+				checks.push(Check::new(&name, Verdict::NoGo, format!("undefined notification action '{action}'")));
+			}
+		}
+	}
+
+	for (cause, error) in fault::invalid_conditions(&notifications.causes) {
+		checks.push(Check::new(format!("cause '{cause}'"), Verdict::NoGo, format!("invalid condition: {error}")));
+	}
+
+	let anomalies = &notifications.anomalies;
+
+	if !(anomalies.half_life_seconds > 0.0 && anomalies.threshold_sigma > 0.0 && anomalies.min_deviation >= 0.0) {
+		checks.push(Check::new("anomaly detection", Verdict::NoGo, "half-life and threshold must be positive, and minimum deviation must not be negative"));
+	}
+
+	for action in &anomalies.actions {
+		if !notifications.actions.contains_key(action) {
+			checks.push(Check::new("anomaly detection", Verdict::NoGo, format!("undefined notification action '{action}'")));
+		}
+	}
+
+	for action in &notifications.events.actions {
+		if !notifications.actions.contains_key(action) {
+			checks.push(Check::new("system events", Verdict::NoGo, format!("undefined notification action '{action}'")));
+		}
+	}
+
+	for rule in &config.captures {
+		let name = format!("capture rule '{}'", rule.name);
+
+		if let Err(error) = rule.condition.parse::<Condition>() {
+			checks.push(Check::new(&name, Verdict::NoGo, format!("invalid condition: {error}")));
+		}
+
+		if !(rule.pre_seconds >= 0.0 && rule.post_seconds >= 0.0) {
+			checks.push(Check::new(&name, Verdict::NoGo, "window margins must not be negative"));
+		}
+	}
+
+	for pair in &config.pairs {
+		let name = format!("channel pair '{}'", pair.name);
+
+		if pair.first == pair.second {
+			checks.push(Check::new(&name, Verdict::NoGo, "a channel cannot be paired with itself"));
+		}
+
+		if pair.tolerance.is_some_and(|tolerance| !(tolerance >= 0.0 && tolerance.is_finite())) {
+			checks.push(Check::new(&name, Verdict::NoGo, "tolerance must be a non-negative number"));
+		}
+
+		for action in &pair.actions {
+			if !notifications.actions.contains_key(action) {
+				checks.push(Check::new(&name, Verdict::NoGo, format!("undefined notification action '{action}'")));
+			}
+		}
+	}
+
+	let valve_readings = &config.valve_readings;
+
+	if valve_readings.voltage_suffixes.iter().chain(&valve_readings.current_suffixes).any(|suffix| suffix.is_empty()) {
+		checks.push(Check::new("valve readings", Verdict::NoGo, "suffixes must not be empty"));
+	}
+
+	if let Some(suffix) = valve_readings.voltage_suffixes.iter().find(|suffix| valve_readings.current_suffixes.contains(suffix)) {
+		checks.push(Check::new("valve readings", Verdict::NoGo, format!("suffix '{suffix}' names both a voltage and a current")));
+	}
+
+	if !(config.forwarding.rate_hz > 0.0 && config.forwarding.rate_hz.is_finite()) {
+		checks.push(Check::new("forwarding", Verdict::NoGo, "rate must be a positive number of hertz"));
+	}
+
+	if !(config.forwarding.max_rate_hz > 0.0 && config.forwarding.max_rate_hz.is_finite()) {
+		checks.push(Check::new("forwarding", Verdict::NoGo, "maximum rate must be a positive number of hertz"));
+	} else if config.forwarding.rate_hz > config.forwarding.max_rate_hz {
+		checks.push(Check::new("forwarding", Verdict::Warn, format!(
+			"rate of {} Hz exceeds the maximum, so clients are sent vehicle state at {} Hz",
+			config.forwarding.rate_hz,
+			config.forwarding.max_rate_hz,
+		)));
+	}
+
+	if !(config.authority.lease_seconds > 0.0 && config.authority.lease_seconds.is_finite()) {
+		checks.push(Check::new("command authority", Verdict::NoGo, "lease must last a positive number of seconds"));
+	}
+
+	if let Some(url) = &config.influx.url {
+		if reqwest::Url::parse(url).is_err() {
+			checks.push(Check::new("influx", Verdict::NoGo, format!("invalid write endpoint '{url}'")));
+		}
+	}
+
+	if config.influx.batch_lines == 0 || !(config.influx.flush_seconds > 0.0 && config.influx.flush_seconds.is_finite()) {
+		checks.push(Check::new("influx", Verdict::NoGo, "batches must hold at least one line and be flushed after a positive number of seconds"));
+	}
+
+	if config.mqtt.qos > 2 {
+		checks.push(Check::new("mqtt", Verdict::NoGo, "quality of service must be 0, 1, or 2"));
+	}
+
+	if config.mqtt.host.is_some() && cfg!(not(feature = "mqtt")) {
+		checks.push(Check::new("mqtt", Verdict::NoGo, "servo was built without MQTT publishing"));
+	}
+
+	if !(config.mqtt.rate_hz > 0.0 && config.mqtt.rate_hz.is_finite()) {
+		checks.push(Check::new("mqtt", Verdict::NoGo, "rate must be a positive number of hertz"));
+	}
+
+	if let Some(url) = &config.standby.primary {
+		if reqwest::Url::parse(url).is_err() {
+			checks.push(Check::new("standby", Verdict::NoGo, format!("invalid primary URL '{url}'")));
+		}
+
+		if config.standby.key.is_none() {
+			checks.push(Check::new("standby", Verdict::NoGo, "a standby needs the standby key of its primary to mirror and fence it"));
+		}
+	}
+
+	if config.standby.promote_after_seconds.is_some_and(|seconds| !(seconds > 0.0 && seconds.is_finite())) {
+		checks.push(Check::new("standby", Verdict::NoGo, "primary must be silent for a positive number of seconds before promotion"));
+	}
+
+	if let Some(path) = config.client.ca_certificate.as_ref().filter(|path| !path.is_file()) {
+		checks.push(Check::new("client", Verdict::NoGo, format!("certificate '{}' does not exist", path.display())));
+	}
+
+	if let Some(path) = &config.recording.path {
+		let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+		if !directory.is_dir() {
+			checks.push(Check::new("recording", Verdict::NoGo, format!("directory of frame log '{}' does not exist", path.display())));
+		}
+	}
+
+	if config.storage.backend == StorageBackend::Postgres && config.storage.url.is_none() {
+		checks.push(Check::new("storage", Verdict::NoGo, "PostgreSQL storage requires a URL to connect to"));
+	}
+
+	if config.storage.backend == StorageBackend::Postgres && cfg!(not(feature = "postgres")) {
+		checks.push(Check::new("storage", Verdict::NoGo, "servo was built without PostgreSQL storage"));
+	}
+
+	if config.backups.interval_hours.is_some_and(|hours| !(hours > 0.0 && hours.is_finite())) {
+		checks.push(Check::new("backups", Verdict::NoGo, "backups must be scheduled a positive number of hours apart"));
+	}
+
+	if config.backups.keep == Some(0) {
+		checks.push(Check::new("backups", Verdict::NoGo, "at least one backup must be kept"));
+	}
+
+	for (log, days) in [("request logs", config.retention.request_log_days), ("audit log", config.retention.audit_log_days)] {
+		if days.is_some_and(|days| !(days >= 1.0 && days.is_finite())) {
+			checks.push(Check::new("retention", Verdict::NoGo, format!("{log} must be kept for at least a day")));
+		}
+	}
+
+	if config.flight.max_datagram_bytes == Some(0) {
+		checks.push(Check::new("telemetry datagrams", Verdict::NoGo, "maximum datagram size must be positive"));
+	}
+
+	if config.flight.allowed_hosts.iter().any(|host| host.trim().is_empty()) {
+		checks.push(Check::new("flight allowlist", Verdict::NoGo, "allowed hosts must not be blank"));
+	}
+
+	if config.flight.pre_shared_key.as_ref().is_some_and(|key| key.is_empty()) {
+		checks.push(Check::new("flight handshake", Verdict::NoGo, "pre-shared key must not be empty"));
+	}
+
+	if config.flight.max_sequence_seconds.is_some_and(|seconds| !(seconds > 0.0 && seconds.is_finite())) {
+		checks.push(Check::new("sequence lockout", Verdict::NoGo, "sequences must lock out others for a positive number of seconds"));
+	}
+
+	if config.flight.serial.device.is_some() && config.flight.serial.baud_rate == 0 {
+		checks.push(Check::new("flight serial link", Verdict::NoGo, "baud rate must be positive"));
+	}
+
+	for (name, channel) in &config.channels {
+		if channel.deadband.is_some_and(|deadband| !(deadband >= 0.0 && deadband.is_finite())) {
+			checks.push(Check::new(format!("channel '{name}'"), Verdict::NoGo, "deadband must be a non-negative number"));
+		}
+	}
+
+	let registry = DecoderRegistry::default();
+
+	for source in &config.ingest.decoders {
+		if registry.build(source).is_none() {
+			let name = format!("decoder '{}'", source.namespace);
+			checks.push(Check::new(name, Verdict::NoGo, format!("unregistered format '{}'", source.format)));
+		}
+	}
+
+	checks
+}
+
+/// Checks that every port servo listens on is free to bind.
+fn check_ports(config: &Config) -> Vec<Check> {
+	let mut ports = vec![
+		("HTTP API", 7200, false),
+		("flight connection", 5025, false),
+		("telemetry", 7201, true),
+		("telemetry stream", 7201, false),
+	];
+
+	if config.ingest.sam_enabled {
+		ports.push(("SAM ingest", config.ingest.sam_port, true));
+	}
+
+	for source in &config.ingest.decoders {
+		ports.push(("decoder", source.port, true));
+	}
+
+	ports
+		.into_iter()
+		.map(|(purpose, port, udp)| {
+			let name = format!("port {port}/{}", if udp { "udp" } else { "tcp" });
+
+			let bound = if udp {
+				UdpSocket::bind(("0.0.0.0", port)).map(drop)
+			} else {
+				TcpListener::bind(("0.0.0.0", port)).map(drop)
+			};
+
+			match bound {
+				Ok(()) => Check::new(name, Verdict::Go, format!("free for {purpose}")),
+				Err(error) => Check::new(name, Verdict::NoGo, format!("cannot be bound for {purpose}: {error}")),
+			}
+		})
+		.collect()
+}
+
+/// Checks that the database can be opened and migrated to the latest migration.
+fn check_database(database_path: &Path) -> Check {
+	if !database_path.exists() {
+		return match Database::volatile().map(|database| database.migrate()) {
+			Ok(Ok(())) => Check::new("database", Verdict::Go, "does not exist yet, but a new one migrates cleanly"),
+			Ok(Err(error)) => Check::new("database", Verdict::NoGo, format!("migrations fail on a new database: {error}")),
+			Err(error) => Check::new("database", Verdict::NoGo, format!("failed to open: {error}")),
+		};
+	}
+
+	let database = match Database::open(database_path) {
+		Ok(database) => database,
+		Err(error) => return Check::new("database", Verdict::NoGo, format!("failed to open: {error}")),
+	};
+
+	// migrations are transactional in SQLite, so this checks them without applying them
+	let began = database.connection.blocking_lock().execute_batch("BEGIN");
+
+	if let Err(error) = began {
+		return Check::new("database", Verdict::NoGo, format!("failed to begin trial migration: {error}"));
+	}
+
+	let result = database.migrate();
+	let rolled_back = database.connection.blocking_lock().execute_batch("ROLLBACK");
+
+	match (result, rolled_back) {
+		(Ok(()), Ok(())) => Check::new("database", Verdict::Go, "migrates cleanly"),
+		(Err(error), _) => Check::new("database", Verdict::NoGo, format!("failed to migrate: {error}")),
+		(_, Err(error)) => Check::new("database", Verdict::NoGo, format!("failed to roll back trial migration: {error}")),
+	}
+}
+
+/// Checks that the disk holding the database has at least the given free space.
+fn check_disk_space(database_path: &Path, minimum_megabytes: u64) -> Check {
+	match free_megabytes(database_path) {
+		Some(free) if free < minimum_megabytes => {
+			Check::new("disk space", Verdict::NoGo, format!("only {free} MB free, below the minimum of {minimum_megabytes} MB"))
+		},
+		Some(free) => Check::new("disk space", Verdict::Go, format!("{free} MB free")),
+		None => Check::new("disk space", Verdict::Warn, "could not find the disk holding the database"),
+	}
+}
+
+/// The free space in megabytes on the disk holding a path, if it can be found.
+pub fn free_megabytes(path: &Path) -> Option<u64> {
+	let mut system = System::new();
+	system.refresh_disks_list();
+
+	// the disk holding the path is the one with the most specific mount point
+	system.disks()
+		.iter()
+		.filter(|disk| path.starts_with(disk.mount_point()))
+		.max_by_key(|disk| disk.mount_point().as_os_str().len())
+		.map(|disk| disk.available_space() / 1_000_000)
+}
+
+/// Checks that the system clock has not been reset and is not behind the latest recorded data.
+fn check_clock(database_path: &Path) -> Check {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0.0, |duration| duration.as_secs_f64());
+
+	if now < EARLIEST_PLAUSIBLE_TIME {
+		return Check::new("clock", Verdict::NoGo, format!("reads Unix time {now:.0}, so it has likely been reset"));
+	}
+
+	let latest_recorded = Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+		.ok()
+		.and_then(|connection| {
+			connection
+				.query_row("SELECT MAX(recorded_at) FROM VehicleSnapshots", [], |row| row.get::<_, Option<f64>>(0))
+				.ok()
+				.flatten()
+		});
+
+	match latest_recorded {
+		Some(latest) if latest > now => {
+			Check::new("clock", Verdict::Warn, format!("is {:.0} s behind the latest recorded vehicle state", latest - now))
+		},
+		_ => Check::new("clock", Verdict::Go, "is plausible"),
+	}
+}
+
+/// Checks that an expected host resolves and, if a port is given, accepts connections.
+fn check_host(host: &str) -> Check {
+	let name = format!("host {host}");
+	let has_port = host.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+
+	let addresses = if has_port {
+		host.to_socket_addrs()
+	} else {
+		(host, 0).to_socket_addrs()
+	};
+
+	let address = match addresses.map(|mut addresses| addresses.find(SocketAddr::is_ipv4)) {
+		Ok(Some(address)) => address,
+		Ok(None) => return Check::new(name, Verdict::NoGo, "resolves to no IPv4 address"),
+		Err(error) => return Check::new(name, Verdict::NoGo, format!("does not resolve: {error}")),
+	};
+
+	if !has_port {
+		return Check::new(name, Verdict::Go, format!("resolves to {}", address.ip()));
+	}
+
+	match TcpStream::connect_timeout(&address, HOST_TIMEOUT) {
+		Ok(_) => Check::new(name, Verdict::Go, format!("accepts connections at {address}")),
+		Err(error) => Check::new(name, Verdict::NoGo, format!("is not reachable at {address}: {error}")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn report_takes_worst_verdict() {
+		let mut report = Report::default();
+		assert_eq!(report.verdict(), Verdict::Go);
+
+		report.checks.push(Check::new("a", Verdict::Go, ""));
+		report.checks.push(Check::new("b", Verdict::Warn, ""));
+		assert_eq!(report.verdict(), Verdict::Warn);
+
+		report.checks.push(Check::new("c", Verdict::NoGo, ""));
+		assert_eq!(report.verdict(), Verdict::NoGo);
+	}
+}
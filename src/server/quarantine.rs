@@ -0,0 +1,104 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// The number of bad frames kept, beyond which the oldest are deleted.
+pub const MAX_BAD_FRAMES: i64 = 1000;
+
+/// The number of bytes kept of each bad frame, so that a flood of large frames
+/// cannot fill the database. Beyond this, a frame is truncated.
+pub const MAX_FRAME_BYTES: usize = 64 * 1024;
+
+/// A telemetry frame which could not be deserialized, as stored in the `BadFrames` table.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BadFrame {
+	/// The unique ID of the frame, which increases with each bad frame received.
+	pub frame_id: i64,
+
+	/// When the frame was received, as a Unix timestamp.
+	pub received_at: f64,
+
+	/// The address the frame was sent from, if it is known.
+	pub source: Option<String>,
+
+	/// The size of the frame as received, which may exceed the bytes kept of it.
+	pub size: usize,
+
+	/// The bytes kept of the frame, encoded as base64.
+	pub frame: String,
+
+	/// Why the frame could not be deserialized.
+	pub error: String,
+}
+
+/// Quarantines a frame which could not be deserialized, deleting the oldest
+/// bad frames beyond the most recent [`MAX_BAD_FRAMES`].
+pub fn store(connection: &Connection, received_at: f64, source: Option<SocketAddr>, frame: &[u8], error: &str) -> rusqlite::Result<()> {
+	connection.execute(
+		"INSERT INTO BadFrames (received_at, source, size, frame, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+		params![
+			received_at,
+			source.map(|source| source.to_string()),
+			frame.len(),
+			&frame[..frame.len().min(MAX_FRAME_BYTES)],
+			error,
+		],
+	)?;
+
+	connection.execute(
+		"DELETE FROM BadFrames WHERE frame_id <= (SELECT MAX(frame_id) FROM BadFrames) - ?1",
+		[MAX_BAD_FRAMES],
+	)?;
+
+	Ok(())
+}
+
+/// Lists the most recent bad frames, newest first.
+pub fn recent(connection: &Connection, limit: usize) -> rusqlite::Result<Vec<BadFrame>> {
+	connection
+		.prepare("SELECT frame_id, received_at, source, size, frame, error FROM BadFrames ORDER BY frame_id DESC LIMIT ?1")?
+		.query_map([limit], |row| {
+			Ok(BadFrame {
+				frame_id: row.get(0)?,
+				received_at: row.get(1)?,
+				source: row.get(2)?,
+				size: row.get(3)?,
+				frame: base64::encode(row.get::<_, Vec<u8>>(4)?),
+				error: row.get(5)?,
+			})
+		})?
+		.collect()
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn only_recent_frames_are_kept() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+		let source = "10.0.0.2:5000".parse().ok();
+
+		for index in 0..MAX_BAD_FRAMES + 5 {
+			store(&connection, index as f64, source, b"bad frame", "unexpected end").unwrap();
+		}
+
+		store(&connection, 2000.0, None, &vec![0xff; MAX_FRAME_BYTES + 10], "too long").unwrap();
+
+		let count = connection.query_row("SELECT COUNT(*) FROM BadFrames", [], |row| row.get::<_, i64>(0)).unwrap();
+		assert_eq!(count, MAX_BAD_FRAMES);
+
+		let frames = recent(&connection, 2).unwrap();
+		assert_eq!(frames[0].size, MAX_FRAME_BYTES + 10);
+		assert_eq!(base64::decode(&frames[0].frame).unwrap().len(), MAX_FRAME_BYTES);
+		assert_eq!(frames[0].source, None);
+
+		assert_eq!(frames[1].received_at, (MAX_BAD_FRAMES + 4) as f64);
+		assert_eq!(frames[1].source.as_deref(), Some("10.0.0.2:5000"));
+		assert_eq!(base64::decode(&frames[1].frame).unwrap(), b"bad frame");
+	}
+}
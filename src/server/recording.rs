@@ -0,0 +1,752 @@
+use common::comm::VehicleState;
+use serde_json::{json, Value};
+use std::{
+	io::{self, Read, Seek, SeekFrom},
+	net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use super::{snapshot, telemetry::{self, Arrival, GapTracker}};
+
+#[cfg(feature = "server")]
+use {
+	jeflog::{pass, warn},
+	std::{
+		fs::{File, OpenOptions},
+		future::Future,
+		io::{BufWriter, Write},
+		path::{Path, PathBuf},
+		sync::atomic::{AtomicU64, Ordering},
+		time::Duration,
+	},
+	tokio::sync::{mpsc, Mutex},
+	super::Shared,
+};
+
+/// Marks the start of a frame log, servo's own format for recorded telemetry frames.
+///
+/// The magic is followed by one record per frame: the Unix timestamp at which it
+/// was received as a big-endian `f64`, the length of its source address as a
+/// `u8` followed by the address as text (empty if unknown), and the length of the
+/// frame as a big-endian `u32` followed by the frame itself.
+pub const FRAME_LOG_MAGIC: [u8; 8] = *b"SERVOFL1";
+
+/// The UDP port vehicle state frames are sent to, which is read from pcap captures by default.
+pub const TELEMETRY_PORT: u16 = 7201;
+
+/// The number of received frames which may wait to be appended to the frame log.
+#[cfg(feature = "server")]
+const RECORDER_QUEUE_CAPACITY: usize = 4096;
+
+/// How often the frame log is flushed to disk while frames are being recorded.
+#[cfg(feature = "server")]
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A telemetry frame exactly as it was received, before any decoding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+	/// When the frame was received, as a Unix timestamp.
+	pub received_at: f64,
+
+	/// The address the frame was sent from, if it was recorded.
+	pub source: Option<SocketAddr>,
+
+	/// The frame itself, including any sequence number header.
+	pub frame: Vec<u8>,
+}
+
+impl RecordedFrame {
+	/// Appends the frame to a frame log as a record.
+	pub fn write_record(&self, log: &mut Vec<u8>) {
+		let source = self.source.map(|source| source.to_string()).unwrap_or_default();
+
+		log.extend_from_slice(&self.received_at.to_be_bytes());
+		log.push(source.len() as u8);
+		log.extend_from_slice(source.as_bytes());
+		log.extend_from_slice(&(self.frame.len() as u32).to_be_bytes());
+		log.extend_from_slice(&self.frame);
+	}
+}
+
+/// A queue of received frames waiting to be appended to the frame log.
+///
+/// Frames are queued rather than written as they arrive so that a slow disk can
+/// never hold up telemetry. Frames arriving while the queue is full are dropped.
+#[cfg(feature = "server")]
+#[derive(Debug)]
+pub struct FrameRecorder {
+	sender: mpsc::Sender<RecordedFrame>,
+	receiver: Mutex<mpsc::Receiver<RecordedFrame>>,
+	dropped: AtomicU64,
+}
+
+#[cfg(feature = "server")]
+impl Default for FrameRecorder {
+	fn default() -> Self {
+		let (sender, receiver) = mpsc::channel(RECORDER_QUEUE_CAPACITY);
+
+		FrameRecorder {
+			sender,
+			receiver: Mutex::new(receiver),
+			dropped: AtomicU64::new(0),
+		}
+	}
+}
+
+#[cfg(feature = "server")]
+impl FrameRecorder {
+	/// Queues a received frame to be appended to the frame log.
+	pub fn record(&self, frame: RecordedFrame) {
+		if self.sender.try_send(frame).is_err() {
+			self.dropped.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+}
+
+/// Opens a frame log to append to, beginning it with [`FRAME_LOG_MAGIC`] if it is new.
+#[cfg(feature = "server")]
+fn open_frame_log(path: &Path) -> io::Result<BufWriter<File>> {
+	let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+	if file.metadata()?.len() == 0 {
+		file.write_all(&FRAME_LOG_MAGIC)?;
+	}
+
+	Ok(BufWriter::new(file))
+}
+
+/// Continuously appends queued frames to the configured frame log.
+///
+/// The log is reopened whenever its configured path changes, so recording may
+/// be started, stopped, or moved by reloading the configuration. The log is
+/// flushed each second, so a crash loses at most the last second of frames.
+#[cfg(feature = "server")]
+pub fn write_frame_log(shared: &Shared) -> impl Future<Output = io::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let mut receiver = shared.recorder.receiver.lock().await;
+		let mut log: Option<(PathBuf, BufWriter<File>)> = None;
+		let mut record = Vec::new();
+
+		let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+		loop {
+			tokio::select! {
+				Some(frame) = receiver.recv() => {
+					let Some(path) = shared.config.current().recording.path.clone() else {
+						log = None;
+						continue;
+					};
+
+					if log.as_ref().is_none_or(|(current, _)| *current != path) {
+						log = Some((path.clone(), open_frame_log(&path)?));
+						pass!("Recording telemetry frames to \x1b[1m{}\x1b[0m.", path.display());
+					}
+
+					if let Some((_, writer)) = &mut log {
+						record.clear();
+						frame.write_record(&mut record);
+						writer.write_all(&record)?;
+					}
+				},
+				_ = flush_interval.tick() => {
+					// frames stop arriving once recording is disabled, so the log must be closed here
+					let path = shared.config.current().recording.path.clone();
+
+					if log.as_ref().is_some_and(|(current, _)| path.as_ref() != Some(current)) {
+						log = None;
+					}
+
+					if let Some((_, writer)) = &mut log {
+						writer.flush()?;
+					}
+
+					let dropped = shared.recorder.dropped.swap(0, Ordering::Relaxed);
+
+					if dropped > 0 {
+						warn!("Dropped {dropped} telemetry frames which arrived faster than they could be recorded.");
+					}
+				},
+			}
+		}
+	}
+}
+
+/// The frames read from a recording.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+	/// Every frame of the recording, in the order it was received.
+	pub frames: Vec<RecordedFrame>,
+
+	/// The number of fragmented datagrams skipped, which cannot be replayed.
+	pub skipped_fragments: usize,
+}
+
+/// Reads a recording, detecting whether it is a frame log or a pcap capture.
+///
+/// Only UDP datagrams sent to the given port are read from a pcap capture.
+pub fn read(bytes: &[u8], port: u16) -> io::Result<Recording> {
+	if bytes.starts_with(&FRAME_LOG_MAGIC) {
+		read_frame_log(bytes)
+	} else {
+		read_pcap(bytes, port)
+	}
+}
+
+/// Reads the frames of a frame log.
+///
+/// The log is append-only, so a final record cut short by a crash while it was
+/// being written is ignored rather than failing the whole log.
+pub fn read_frame_log(bytes: &[u8]) -> io::Result<Recording> {
+	let records = bytes.strip_prefix(&FRAME_LOG_MAGIC).ok_or_else(|| invalid("not a frame log"))?;
+	let (frames, _) = read_records(records)?;
+
+	Ok(Recording { frames, skipped_fragments: 0 })
+}
+
+/// Reads the whole records at the start of bytes following the magic of a frame
+/// log, returning them with the number of bytes they took, so that a log still
+/// being written may be read as it grows.
+pub fn read_records(bytes: &[u8]) -> io::Result<(Vec<RecordedFrame>, usize)> {
+	let mut reader = ByteReader::new(bytes);
+	let mut frames = Vec::new();
+
+	loop {
+		let remaining = reader.bytes.len();
+
+		let Some(record) = read_record(&mut reader) else {
+			return Ok((frames, bytes.len() - remaining));
+		};
+
+		frames.push(record?);
+	}
+}
+
+/// Finds the offset in a frame log just past its last whole record, skipping
+/// over each frame rather than reading it, so that a log still being written
+/// may be followed from its end without starting partway through a record.
+pub fn last_record_end<R: Read + Seek>(log: &mut R) -> io::Result<u64> {
+	let length = log.seek(SeekFrom::End(0))?;
+	log.seek(SeekFrom::Start(0))?;
+
+	let mut magic = [0; FRAME_LOG_MAGIC.len()];
+	log.read_exact(&mut magic)?;
+
+	if magic != FRAME_LOG_MAGIC {
+		return Err(invalid("not a frame log"));
+	}
+
+	let mut end = magic.len() as u64;
+
+	// the timestamp and the length of the source address
+	while end + 9 <= length {
+		let mut header = [0; 9];
+		log.read_exact(&mut header)?;
+
+		let source_length = header[8] as u64;
+
+		if end + 9 + source_length + 4 > length {
+			break;
+		}
+
+		let mut frame_length = [0; 4];
+		log.seek(SeekFrom::Current(source_length as i64))?;
+		log.read_exact(&mut frame_length)?;
+
+		let record_end = end + 9 + source_length + 4 + u32::from_be_bytes(frame_length) as u64;
+
+		if record_end > length {
+			break;
+		}
+
+		log.seek(SeekFrom::Start(record_end))?;
+		end = record_end;
+	}
+
+	Ok(end)
+}
+
+/// Reads one record of a frame log, returning `None` if the log ends partway through it.
+fn read_record(reader: &mut ByteReader) -> Option<io::Result<RecordedFrame>> {
+	let received_at = f64::from_be_bytes(reader.array()?);
+	let source_length = reader.take(1)?[0] as usize;
+	let source = reader.take(source_length)?;
+	let frame_length = u32::from_be_bytes(reader.array()?) as usize;
+	let frame = reader.take(frame_length)?.to_vec();
+
+	let source = match source {
+		[] => None,
+		source => match std::str::from_utf8(source).ok().and_then(|source| source.parse().ok()) {
+			Some(source) => Some(source),
+			None => return Some(Err(invalid("frame log record has an invalid source address"))),
+		},
+	};
+
+	Some(Ok(RecordedFrame { received_at, source, frame }))
+}
+
+/// Reads the UDP datagrams sent to a port from a pcap capture, as recorded by tcpdump or Wireshark.
+///
+/// Ethernet (with or without VLAN tags), raw IP, BSD loopback, and Linux cooked
+/// captures are supported. Fragmented IPv4 datagrams are counted and skipped,
+/// since reassembling them is beyond an offline check.
+pub fn read_pcap(bytes: &[u8], port: u16) -> io::Result<Recording> {
+	let mut reader = ByteReader::new(bytes);
+	let magic = reader.array::<4>().ok_or_else(|| invalid("capture is empty"))?;
+
+	let (big_endian, nanoseconds) = match magic {
+		[0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+		[0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+		[0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+		[0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+		[0x0a, 0x0d, 0x0d, 0x0a] => return Err(invalid("pcapng captures are not supported; convert with `editcap -F pcap`")),
+		_ => return Err(invalid("capture is neither a frame log nor a pcap capture")),
+	};
+
+	let read_u32 = |reader: &mut ByteReader| -> io::Result<u32> {
+		let bytes = reader.array().ok_or_else(|| invalid("pcap capture is truncated"))?;
+		Ok(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+	};
+
+	// version, time zone, timestamp accuracy, and snapshot length are not needed
+	reader.take(16).ok_or_else(|| invalid("pcap capture is truncated"))?;
+	let link_type = read_u32(&mut reader)?;
+
+	let mut recording = Recording::default();
+
+	while !reader.is_empty() {
+		let seconds = read_u32(&mut reader)?;
+		let fraction = read_u32(&mut reader)?;
+		let captured_length = read_u32(&mut reader)? as usize;
+		let _original_length = read_u32(&mut reader)?;
+
+		let data = reader
+			.take(captured_length)
+			.ok_or_else(|| invalid("pcap capture is truncated"))?;
+
+		let received_at = seconds as f64 + fraction as f64 / if nanoseconds { 1e9 } else { 1e6 };
+
+		let Some(packet) = ip_packet(link_type, data, big_endian) else {
+			continue;
+		};
+
+		match udp_datagram(packet) {
+			Some(Datagram::Complete { source, destination_port, payload }) if destination_port == port => {
+				recording.frames.push(RecordedFrame { received_at, source: Some(source), frame: payload.to_vec() });
+			},
+			Some(Datagram::Fragment) => recording.skipped_fragments += 1,
+			_ => {},
+		}
+	}
+
+	Ok(recording)
+}
+
+/// Strips the link layer from a captured packet, returning its IP packet if it carries one.
+fn ip_packet(link_type: u32, data: &[u8], big_endian: bool) -> Option<&[u8]> {
+	const ETHERTYPE_IPV4: u16 = 0x0800;
+	const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+	let ethertype = |bytes: &[u8]| u16::from_be_bytes([bytes[0], bytes[1]]);
+
+	let packet = match link_type {
+		// Ethernet, skipping any VLAN tags
+		1 => {
+			let mut offset = 12;
+
+			while data.len() >= offset + 2 && matches!(ethertype(&data[offset..]), 0x8100 | 0x88a8) {
+				offset += 4;
+			}
+
+			if data.len() < offset + 2 || !matches!(ethertype(&data[offset..]), ETHERTYPE_IPV4 | ETHERTYPE_IPV6) {
+				return None;
+			}
+
+			&data[offset + 2..]
+		},
+		// BSD loopback, whose address family is in the byte order of the capturing host
+		0 => {
+			let family = data.get(..4)?;
+			let family = if big_endian { family[3] } else { family[0] };
+
+			// AF_INET is 2 everywhere, while AF_INET6 differs between BSDs
+			if !matches!(family, 2 | 24 | 28 | 30) {
+				return None;
+			}
+
+			&data[4..]
+		},
+		// raw IP, under each of the link types it has been assigned
+		12 | 14 | 101 | 228 | 229 => data,
+		// Linux cooked capture
+		113 if data.len() >= 16 && matches!(ethertype(&data[14..]), ETHERTYPE_IPV4 | ETHERTYPE_IPV6) => &data[16..],
+		// Linux cooked capture, version 2
+		276 if data.len() >= 20 && matches!(ethertype(data), ETHERTYPE_IPV4 | ETHERTYPE_IPV6) => &data[20..],
+		_ => return None,
+	};
+
+	Some(packet)
+}
+
+/// A UDP datagram read from an IP packet.
+enum Datagram<'a> {
+	/// A datagram contained entirely in its packet.
+	Complete {
+		/// The address the datagram was sent from.
+		source: SocketAddr,
+
+		/// The port the datagram was sent to.
+		destination_port: u16,
+
+		/// The payload of the datagram.
+		payload: &'a [u8],
+	},
+
+	/// A fragment of a datagram split across several packets.
+	Fragment,
+}
+
+/// Reads the UDP datagram carried by an IPv4 or IPv6 packet, if it carries one.
+fn udp_datagram(packet: &[u8]) -> Option<Datagram<'_>> {
+	const PROTOCOL_UDP: u8 = 17;
+
+	let (source, segment) = match packet.first()? >> 4 {
+		4 => {
+			let header_length = (packet.first()? & 0x0f) as usize * 4;
+			let total_length = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]) as usize;
+			let fragmentation = u16::from_be_bytes([*packet.get(6)?, *packet.get(7)?]);
+
+			if *packet.get(9)? != PROTOCOL_UDP {
+				return None;
+			}
+
+			// either more fragments follow, or this is not the first
+			if fragmentation & 0x3fff != 0 {
+				return Some(Datagram::Fragment);
+			}
+
+			let address: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+			(IpAddr::V4(Ipv4Addr::from(address)), packet.get(header_length..total_length.min(packet.len()))?)
+		},
+		6 => {
+			// extension headers are rare enough on a test stand network to not be followed
+			if *packet.get(6)? != PROTOCOL_UDP {
+				return None;
+			}
+
+			let payload_length = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]) as usize;
+			let address: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+			(IpAddr::V6(Ipv6Addr::from(address)), packet.get(40..(40 + payload_length).min(packet.len()))?)
+		},
+		_ => return None,
+	};
+
+	let source_port = u16::from_be_bytes([*segment.first()?, *segment.get(1)?]);
+	let destination_port = u16::from_be_bytes([*segment.get(2)?, *segment.get(3)?]);
+	let length = u16::from_be_bytes([*segment.get(4)?, *segment.get(5)?]) as usize;
+
+	Some(Datagram::Complete {
+		source: SocketAddr::new(source, source_port),
+		destination_port,
+		payload: segment.get(8..length.clamp(8, segment.len()))?,
+	})
+}
+
+/// A vehicle state produced by the pipeline while replaying a recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessedSnapshot {
+	/// When the frame which produced the snapshot was received, as a Unix timestamp.
+	pub received_at: f64,
+
+	/// The sequence number of the frame which produced the snapshot, if it was sequenced.
+	pub sequence_number: Option<u64>,
+
+	/// The vehicle state, as it would have been logged.
+	pub vehicle_state: VehicleState,
+}
+
+/// Counts of what happened to the frames of a replayed recording.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplaySummary {
+	/// The number of frames replayed.
+	pub frames: usize,
+
+	/// The number of snapshots produced.
+	pub snapshots: usize,
+
+	/// The number of frames missing according to their sequence numbers.
+	pub lost: u64,
+
+	/// The number of frames dropped for arriving out of order.
+	pub out_of_order: usize,
+
+	/// The number of times the sender restarted its sequence numbers.
+	pub restarts: usize,
+
+	/// The number of frames which could not be deserialized.
+	pub malformed: usize,
+}
+
+/// Replays recorded frames through the same decoding and logging as live
+/// telemetry, producing the snapshots which would have been logged.
+pub fn replay(frames: &[RecordedFrame]) -> anyhow::Result<(Vec<ProcessedSnapshot>, ReplaySummary)> {
+	let mut tracker = GapTracker::default();
+	let mut snapshots = Vec::new();
+	let mut summary = ReplaySummary { frames: frames.len(), ..ReplaySummary::default() };
+
+	for recorded in frames {
+		let (arrival, decoded) = telemetry::decode_frame(&mut tracker, &recorded.frame);
+
+		match arrival {
+			Some(Arrival::Gap { first_missing, last_missing }) => summary.lost += last_missing - first_missing + 1,
+			Some(Arrival::OutOfOrder) => summary.out_of_order += 1,
+			Some(Arrival::Restarted) => summary.restarts += 1,
+			_ => {},
+		}
+
+		let Some(Ok(vehicle_state)) = decoded else {
+			summary.malformed += usize::from(matches!(decoded, Some(Err(_))));
+			continue;
+		};
+
+		// logged snapshots are compressed, so they are read back the way an export would
+		let logged = snapshot::decode(&snapshot::encode(&vehicle_state)?, true)?;
+
+		snapshots.push(ProcessedSnapshot {
+			received_at: recorded.received_at,
+			sequence_number: telemetry::split_sequence_number(&recorded.frame).0,
+			vehicle_state: logged,
+		});
+	}
+
+	summary.snapshots = snapshots.len();
+	Ok((snapshots, summary))
+}
+
+/// Converts the value of a reading to JSON, writing values which are not finite as
+/// strings, since JSON cannot represent them as numbers.
+fn reading_value(value: f64) -> Value {
+	if value.is_finite() {
+		json!(value)
+	} else {
+		json!(value.to_string())
+	}
+}
+
+/// Converts a snapshot into a line of golden output.
+///
+/// Channels are sorted by name so that golden files diff cleanly when committed.
+pub fn golden_line(snapshot: &ProcessedSnapshot) -> Value {
+	let sensors = snapshot.vehicle_state.sensor_readings
+		.iter()
+		.map(|(channel, reading)| (channel.clone(), json!([reading_value(reading.value), reading.unit.to_string()])))
+		.collect::<serde_json::Map<_, _>>();
+
+	let valves = snapshot.vehicle_state.valve_states
+		.iter()
+		.map(|(channel, valve_state)| {
+			(channel.clone(), json!([valve_state.commanded.to_string(), valve_state.actual.to_string()]))
+		})
+		.collect::<serde_json::Map<_, _>>();
+
+	json!({
+		"received_at": snapshot.received_at,
+		"sequence_number": snapshot.sequence_number,
+		"sensors": sensors,
+		"valves": valves,
+	})
+}
+
+/// Compares produced snapshots against golden output, line by line, describing each difference.
+///
+/// Numbers are considered equal when they differ by no more than the tolerance.
+pub fn compare(actual: &[Value], golden: &[Value], tolerance: f64) -> Vec<String> {
+	let mut differences = Vec::new();
+
+	for (index, (actual, golden)) in actual.iter().zip(golden).enumerate() {
+		diff(&format!("snapshot {index}"), actual, golden, tolerance, &mut differences);
+	}
+
+	if actual.len() != golden.len() {
+		differences.push(format!("expected {} snapshots, but {} were produced", golden.len(), actual.len()));
+	}
+
+	differences
+}
+
+/// Describes each difference between two JSON values at a path.
+fn diff(path: &str, actual: &Value, expected: &Value, tolerance: f64, differences: &mut Vec<String>) {
+	match (actual, expected) {
+		(Value::Object(actual), Value::Object(expected)) => {
+			for (key, expected) in expected {
+				match actual.get(key) {
+					Some(actual) => diff(&format!("{path}.{key}"), actual, expected, tolerance, differences),
+					None => differences.push(format!("{path}.{key}: missing, expected {expected}")),
+				}
+			}
+
+			for (key, actual) in actual {
+				if !expected.contains_key(key) {
+					differences.push(format!("{path}.{key}: unexpected {actual}"));
+				}
+			}
+		},
+		(Value::Array(actual), Value::Array(expected)) if actual.len() == expected.len() => {
+			for (index, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+				diff(&format!("{path}[{index}]"), actual, expected, tolerance, differences);
+			}
+		},
+		(Value::Number(actual_number), Value::Number(expected_number)) => {
+			let (Some(actual_number), Some(expected_number)) = (actual_number.as_f64(), expected_number.as_f64()) else {
+				return;
+			};
+
+			if (actual_number - expected_number).abs() > tolerance {
+				differences.push(format!("{path}: expected {expected}, got {actual}"));
+			}
+		},
+		(actual, expected) if actual != expected => {
+			differences.push(format!("{path}: expected {expected}, got {actual}"));
+		},
+		_ => {},
+	}
+}
+
+/// Creates an error for a recording which cannot be read.
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads fields from the front of a byte slice.
+struct ByteReader<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+	fn new(bytes: &'a [u8]) -> Self {
+		ByteReader { bytes }
+	}
+
+	fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	/// Takes the next bytes, or `None` if there are too few left.
+	fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+		if self.bytes.len() < count {
+			return None;
+		}
+
+		let (taken, rest) = self.bytes.split_at(count);
+		self.bytes = rest;
+		Some(taken)
+	}
+
+	/// Takes the next bytes as an array, or `None` if there are too few left.
+	fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+		self.take(N)?.try_into().ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+
+	fn frame(sequence_number: u64, value: f64) -> Vec<u8> {
+		let mut vehicle_state = VehicleState::new();
+		vehicle_state.sensor_readings.insert("KBPT".to_owned(), Measurement { value, unit: Unit::Psi });
+
+		telemetry::sequenced_frame(sequence_number, &postcard::to_allocvec(&vehicle_state).unwrap())
+	}
+
+	#[test]
+	fn udp_datagrams_are_read_from_pcap_captures() {
+		let payload = frame(7, 14.5);
+
+		let mut udp = Vec::new();
+		udp.extend_from_slice(&5000u16.to_be_bytes());
+		udp.extend_from_slice(&TELEMETRY_PORT.to_be_bytes());
+		udp.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+		udp.extend_from_slice(&[0, 0]);
+		udp.extend_from_slice(&payload);
+
+		let mut ip = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 2, 10, 0, 0, 1];
+		ip[2..4].copy_from_slice(&(20 + udp.len() as u16).to_be_bytes());
+		ip.extend_from_slice(&udp);
+
+		// a VLAN-tagged Ethernet frame, padded as short frames are on the wire
+		let mut ethernet = vec![0; 12];
+		ethernet.extend_from_slice(&[0x81, 0x00, 0, 1, 0x08, 0x00]);
+		ethernet.extend_from_slice(&ip);
+		ethernet.extend_from_slice(&[0; 4]);
+
+		let mut capture = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+		capture.extend_from_slice(&[0; 8]);
+		capture.extend_from_slice(&65535u32.to_le_bytes());
+		capture.extend_from_slice(&1u32.to_le_bytes());
+
+		for _ in 0..2 {
+			capture.extend_from_slice(&100u32.to_le_bytes());
+			capture.extend_from_slice(&250_000u32.to_le_bytes());
+			capture.extend_from_slice(&(ethernet.len() as u32).to_le_bytes());
+			capture.extend_from_slice(&(ethernet.len() as u32).to_le_bytes());
+			capture.extend_from_slice(&ethernet);
+
+			// the second copy is a fragment, which cannot be replayed
+			ethernet[18 + 6] = 0x20;
+		}
+
+		let recording = read(&capture, TELEMETRY_PORT).unwrap();
+		assert_eq!(recording.skipped_fragments, 1);
+		assert_eq!(recording.frames, [RecordedFrame {
+			received_at: 100.25,
+			source: Some("10.0.0.2:5000".parse().unwrap()),
+			frame: payload,
+		}]);
+
+		assert!(read(&capture, 7202).unwrap().frames.is_empty());
+	}
+
+	#[test]
+	fn replayed_frame_logs_are_compared_against_golden_output() {
+		let frames = [frame(0, 14.5), frame(1, f64::NAN), b"garbage".to_vec(), frame(4, 15.0), frame(3, 16.0)];
+
+		let mut log = FRAME_LOG_MAGIC.to_vec();
+
+		for (index, frame) in frames.into_iter().enumerate() {
+			RecordedFrame { received_at: index as f64, source: None, frame }.write_record(&mut log);
+		}
+
+		// a record cut short by a crash is ignored
+		log.extend_from_slice(&[0x40, 0x10]);
+
+		let recording = read(&log, TELEMETRY_PORT).unwrap();
+		assert_eq!(last_record_end(&mut io::Cursor::new(&log)).unwrap(), log.len() as u64 - 2);
+
+		let (frames, consumed) = read_records(&log[FRAME_LOG_MAGIC.len()..]).unwrap();
+		assert_eq!(frames, recording.frames);
+		assert_eq!(consumed, log.len() - FRAME_LOG_MAGIC.len() - 2);
+
+		let (snapshots, summary) = replay(&recording.frames).unwrap();
+
+		assert_eq!(summary, ReplaySummary {
+			frames: 5,
+			snapshots: 3,
+			lost: 2,
+			out_of_order: 1,
+			restarts: 0,
+			malformed: 1,
+		});
+
+		let golden = snapshots.iter().map(golden_line).collect::<Vec<_>>();
+		assert_eq!(golden[1]["sensors"]["KBPT"], json!(["NaN", Unit::Psi.to_string()]));
+		assert!(compare(&golden, &golden, 0.0).is_empty());
+
+		let mut changed = golden.clone();
+		changed[2]["sensors"]["KBPT"][0] = json!(15.001);
+		assert_eq!(compare(&changed, &golden, 0.01), Vec::<String>::new());
+		assert_eq!(compare(&changed, &golden, 0.0), ["snapshot 2.sensors.KBPT[0]: expected 15.0, got 15.001"]);
+		assert_eq!(compare(&golden[..2], &golden, 0.0), ["expected 3 snapshots, but 2 were produced"]);
+	}
+}
@@ -0,0 +1,562 @@
+use common::comm::{Unit, VehicleState};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{alert::{Alert, Severity}, audit, deadband::MAX_HOLD, note::{self, Note}};
+
+/// The number of points in each channel's thumbnail plot.
+const THUMBNAIL_POINTS: usize = 120;
+
+/// The number of seconds without a logged snapshot after which logging is
+/// considered to have gone silent, which is longer than a quiet vehicle is
+/// ever held back by deadbands.
+const SILENCE_SECONDS: f64 = 2.0 * MAX_HOLD.as_secs_f64();
+
+/// A summary of the quality of the data logged over a time range, as reviewed
+/// after a test: how completely each channel was logged, where data was lost,
+/// and what happened along the way.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QualityReport {
+	/// The start of the time range, as a Unix timestamp.
+	pub from: f64,
+
+	/// The end of the time range, as a Unix timestamp.
+	pub to: f64,
+
+	/// The number of vehicle snapshots logged over the range.
+	pub snapshots: usize,
+
+	/// The quality of each channel which was logged, ordered by name.
+	pub channels: Vec<ChannelQuality>,
+
+	/// Stretches in which no snapshot was logged at all.
+	pub silences: Vec<Silence>,
+
+	/// Frames which the flight computer sent but servo never received.
+	pub gaps: Vec<TelemetryGap>,
+
+	/// Alerts raised over the range, oldest first.
+	pub alerts: Vec<Alert>,
+
+	/// Sequences sent to the flight computer over the range, oldest first.
+	pub sequences: Vec<SequenceRun>,
+}
+
+/// How completely a channel was logged, and the range of its readings.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChannelQuality {
+	/// The name of the channel.
+	pub channel: String,
+
+	/// The unit of the channel's latest reading.
+	pub unit: Unit,
+
+	/// The number of snapshots in which the channel was present.
+	pub readings: usize,
+
+	/// The fraction of snapshots in which the channel was present.
+	pub coverage: f64,
+
+	/// The smallest finite reading.
+	pub min: f64,
+
+	/// The largest finite reading.
+	pub max: f64,
+
+	/// The mean of the finite readings.
+	pub mean: f64,
+
+	/// The number of times the channel went missing from snapshots after it first appeared.
+	pub dropouts: usize,
+
+	/// The longest time the channel was missing for, in seconds.
+	pub longest_dropout_seconds: f64,
+
+	/// Up to a hundred or so `[timestamp, mean]` points spanning the range, for plotting.
+	pub thumbnail: Vec<[f64; 2]>,
+}
+
+/// A stretch in which no snapshot was logged.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Silence {
+	/// The time of the last snapshot before the silence, as a Unix timestamp.
+	pub started_at: f64,
+
+	/// The time of the first snapshot after the silence, as a Unix timestamp.
+	pub ended_at: f64,
+}
+
+/// A run of telemetry frames which were never received, as recorded in the `TelemetryGaps` table.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TelemetryGap {
+	/// The sequence number of the first missing frame.
+	pub first_missing: i64,
+
+	/// The sequence number of the last missing frame.
+	pub last_missing: i64,
+
+	/// When the last frame before the gap was received, as a Unix timestamp.
+	pub started_at: f64,
+
+	/// When the first frame after the gap was received, as a Unix timestamp.
+	pub ended_at: f64,
+}
+
+/// A sequence sent to the flight computer, as recorded in the audit log.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SequenceRun {
+	/// When the sequence was sent, as a Unix timestamp.
+	pub at: f64,
+
+	/// The user who ran the sequence, if the request was authenticated.
+	pub username: Option<String>,
+
+	/// What was run, and how.
+	pub detail: String,
+}
+
+/// What an event on the timeline of a run sheet was.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineKind {
+	/// A sequence was run or the vehicle was safed.
+	Sequence,
+
+	/// A command was sent to the vehicle, requested, or confirmed.
+	Command,
+
+	/// A safety check was deliberately overridden.
+	Override,
+
+	/// An alert was raised.
+	Alert,
+
+	/// An operator entered a note.
+	Note,
+}
+
+/// An event on the timeline of a run sheet.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TimelineEvent {
+	/// When the event happened, as a Unix timestamp.
+	pub at: f64,
+
+	/// What the event was.
+	pub kind: TimelineKind,
+
+	/// The user the event is attributed to, if any.
+	pub username: Option<String>,
+
+	/// A description of the event.
+	pub detail: String,
+}
+
+/// The record of a test which range safety asks for afterwards: everything
+/// done to the vehicle in order, the alerts raised, and the operators' notes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunSheet {
+	/// The start of the time range, as a Unix timestamp.
+	pub from: f64,
+
+	/// The end of the time range, as a Unix timestamp.
+	pub to: f64,
+
+	/// Every sequence run, command sent, override, alert, and note over the range, oldest first.
+	pub timeline: Vec<TimelineEvent>,
+
+	/// Sequences sent to the flight computer over the range, oldest first.
+	pub sequences: Vec<SequenceRun>,
+
+	/// Alerts raised over the range, oldest first.
+	pub alerts: Vec<Alert>,
+
+	/// Notes entered by operators over the range, oldest first.
+	pub notes: Vec<Note>,
+}
+
+/// The quality of each channel over a range, found by an [`Analyzer`].
+#[derive(Clone, Debug)]
+pub struct Analysis {
+	/// The quality of each channel, ordered by name.
+	pub channels: Vec<ChannelQuality>,
+
+	/// The times logging went silent, oldest first.
+	pub silences: Vec<Silence>,
+
+	/// The number of snapshots analyzed.
+	pub snapshots: usize,
+
+	/// The times of the first and last snapshots analyzed, if there were any.
+	pub bounds: Option<(f64, f64)>,
+}
+
+/// What is known of a channel part way through an analysis.
+#[derive(Clone, Debug)]
+struct ChannelTally {
+	readings: usize,
+	last_index: usize,
+	last_at: f64,
+	unit: Unit,
+	finite: usize,
+	min: f64,
+	max: f64,
+	sum: f64,
+	dropouts: usize,
+	longest_dropout_seconds: f64,
+
+	/// The sums of the times and values of finite readings in each thumbnail
+	/// bucket, along with how many there were.
+	buckets: Vec<(f64, f64, usize)>,
+}
+
+/// Summarizes the quality of each channel from the snapshots logged over a
+/// range, given in order a chunk at a time so that a long range need not be
+/// held in memory at once.
+#[derive(Clone, Debug)]
+pub struct Analyzer {
+	from: f64,
+	span: f64,
+	snapshots: usize,
+	bounds: Option<(f64, f64)>,
+	silences: Vec<Silence>,
+	channels: BTreeMap<String, ChannelTally>,
+}
+
+impl Analyzer {
+	/// Starts an analysis whose thumbnails span the given range, which should
+	/// be only the part of the range which has data.
+	pub fn new(from: f64, to: f64) -> Self {
+		Analyzer {
+			from,
+			span: (to - from).max(f64::EPSILON),
+			snapshots: 0,
+			bounds: None,
+			silences: Vec::new(),
+			channels: BTreeMap::new(),
+		}
+	}
+
+	/// Analyzes the next snapshots, which follow every snapshot analyzed so far.
+	pub fn add(&mut self, snapshots: &[(f64, VehicleState)]) {
+		for (recorded_at, state) in snapshots {
+			let index = self.snapshots;
+			self.snapshots += 1;
+
+			self.bounds = match self.bounds {
+				Some((first, last)) => {
+					if recorded_at - last > SILENCE_SECONDS {
+						self.silences.push(Silence { started_at: last, ended_at: *recorded_at });
+					}
+
+					Some((first, *recorded_at))
+				},
+				None => Some((*recorded_at, *recorded_at)),
+			};
+
+			for (channel, reading) in &state.sensor_readings {
+				let tally = match self.channels.get_mut(channel) {
+					Some(tally) => tally,
+					None => self.channels.entry(channel.clone()).or_insert(ChannelTally {
+						readings: 0,
+						last_index: index,
+						last_at: *recorded_at,
+						unit: reading.unit,
+						finite: 0,
+						min: f64::NAN,
+						max: f64::NAN,
+						sum: 0.0,
+						dropouts: 0,
+						longest_dropout_seconds: 0.0,
+						buckets: vec![(0.0, 0.0, 0); THUMBNAIL_POINTS],
+					}),
+				};
+
+				if tally.readings > 0 && index > tally.last_index + 1 {
+					tally.dropouts += 1;
+					tally.longest_dropout_seconds = tally.longest_dropout_seconds.max(recorded_at - tally.last_at);
+				}
+
+				tally.readings += 1;
+				tally.last_index = index;
+				tally.last_at = *recorded_at;
+				tally.unit = reading.unit;
+
+				if reading.value.is_finite() {
+					tally.finite += 1;
+					tally.min = tally.min.min(reading.value);
+					tally.max = tally.max.max(reading.value);
+					tally.sum += reading.value;
+
+					let bucket = (((recorded_at - self.from) / self.span * THUMBNAIL_POINTS as f64) as usize).min(THUMBNAIL_POINTS - 1);
+					tally.buckets[bucket].0 += recorded_at;
+					tally.buckets[bucket].1 += reading.value;
+					tally.buckets[bucket].2 += 1;
+				}
+			}
+		}
+	}
+
+	/// Finishes the analysis once every snapshot has been added.
+	pub fn finish(self) -> Analysis {
+		let snapshots = self.snapshots;
+		let last_snapshot_at = self.bounds.map_or(0.0, |(_, last)| last);
+
+		let channels = self.channels
+			.into_iter()
+			.map(|(channel, mut tally)| {
+				// a channel which stops being reported before the end has dropped out until the end
+				if tally.last_index + 1 < snapshots {
+					tally.dropouts += 1;
+					tally.longest_dropout_seconds = tally.longest_dropout_seconds.max(last_snapshot_at - tally.last_at);
+				}
+
+				ChannelQuality {
+					channel,
+					unit: tally.unit,
+					readings: tally.readings,
+					coverage: tally.readings as f64 / snapshots as f64,
+					min: tally.min,
+					max: tally.max,
+					mean: tally.sum / tally.finite.max(1) as f64,
+					dropouts: tally.dropouts,
+					longest_dropout_seconds: tally.longest_dropout_seconds,
+					thumbnail: tally.buckets
+						.into_iter()
+						.filter(|bucket| bucket.2 > 0)
+						.map(|(time, value, count)| [time / count as f64, value / count as f64])
+						.collect(),
+				}
+			})
+			.collect();
+
+		Analysis {
+			channels,
+			silences: self.silences,
+			snapshots,
+			bounds: self.bounds,
+		}
+	}
+}
+
+/// Summarizes the quality of each channel from the snapshots logged over a
+/// range, given in order along with the times they were logged at.
+pub fn analyze(snapshots: &[(f64, VehicleState)], from: f64, to: f64) -> Analysis {
+	// thumbnails span only the part of the range which has data
+	let from = snapshots.first().map_or(from, |snapshot| from.max(snapshot.0));
+	let to = snapshots.last().map_or(to, |snapshot| to.min(snapshot.0));
+
+	let mut analyzer = Analyzer::new(from, to);
+	analyzer.add(snapshots);
+	analyzer.finish()
+}
+
+/// Assembles the quality report of a time range from the analysis of the
+/// snapshots logged within it and the events recorded in the database.
+pub fn generate(connection: &Connection, analysis: Analysis, from: f64, to: f64) -> rusqlite::Result<QualityReport> {
+	let gaps: Vec<TelemetryGap> = connection
+		.prepare("
+			SELECT first_missing, last_missing, started_at, ended_at FROM TelemetryGaps
+			WHERE ended_at >= ?1 AND started_at <= ?2
+			ORDER BY started_at
+		")?
+		.query_map(params![from, to], |row| {
+			Ok(TelemetryGap {
+				first_missing: row.get(0)?,
+				last_missing: row.get(1)?,
+				started_at: row.get(2)?,
+				ended_at: row.get(3)?,
+			})
+		})?
+		.collect::<rusqlite::Result<_>>()?;
+
+	let alerts = alerts(connection, from, to)?;
+	let sequences = sequence_runs(connection, from, to)?;
+
+	// an open range is narrowed to what actually happened within it
+	let times = analysis.bounds
+		.into_iter()
+		.flat_map(|(first, last)| [first, last])
+		.chain(gaps.iter().flat_map(|gap| [gap.started_at, gap.ended_at]))
+		.chain(alerts.iter().map(|alert| alert.raised_at))
+		.chain(sequences.iter().map(|run| run.at));
+
+	let (start, end) = times.fold((f64::INFINITY, f64::NEG_INFINITY), |(start, end), time| (start.min(time), end.max(time)));
+
+	Ok(QualityReport {
+		from: if start.is_finite() { from.max(start) } else { from },
+		to: if end.is_finite() { to.min(end) } else { to },
+		snapshots: analysis.snapshots,
+		channels: analysis.channels,
+		silences: analysis.silences,
+		gaps,
+		alerts,
+		sequences,
+	})
+}
+
+/// Assembles the run sheet of a time range from the audit log, alerts, and notes.
+pub fn run_sheet(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<RunSheet> {
+	let sequences = sequence_runs(connection, from, to)?;
+	let alerts = alerts(connection, from, to)?;
+	let notes = note::list(connection, from, to)?;
+
+	let actions = audit::list(connection, from, to)?
+		.into_iter()
+		.filter_map(|entry| {
+			let kind = match entry.action.as_str() {
+				"run sequence" | "safe" => TimelineKind::Sequence,
+				"command" | "request command" | "confirm command" => TimelineKind::Command,
+				"override interlocks" | "override sequence lockout" => TimelineKind::Override,
+				_ => return None,
+			};
+
+			Some(TimelineEvent {
+				at: entry.recorded_at,
+				kind,
+				username: entry.username,
+				detail: entry.detail.unwrap_or(entry.action),
+			})
+		});
+
+	let raised = alerts.iter().map(|alert| TimelineEvent {
+		at: alert.raised_at,
+		kind: TimelineKind::Alert,
+		username: None,
+		detail: format!("{} alert '{}': {}", alert.severity, alert.rule, alert.message),
+	});
+
+	let entered = notes.iter().map(|note| TimelineEvent {
+		at: note.created_at,
+		kind: TimelineKind::Note,
+		username: note.author.clone(),
+		detail: note.content.clone(),
+	});
+
+	let mut timeline = actions.chain(raised).chain(entered).collect::<Vec<_>>();
+
+	// stable, so events at the same moment keep the order of their kinds above
+	timeline.sort_by(|a, b| a.at.total_cmp(&b.at));
+
+	// an open range is narrowed to what actually happened within it
+	let (start, end) = match (timeline.first(), timeline.last()) {
+		(Some(first), Some(last)) => (from.max(first.at), to.min(last.at)),
+		_ => (from, to),
+	};
+
+	Ok(RunSheet { from: start, to: end, timeline, sequences, alerts, notes })
+}
+
+/// Reads the alerts raised over a range, oldest first.
+fn alerts(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<Alert>> {
+	connection
+		.prepare("
+			SELECT rule, severity, message, raised_at, causes FROM Alerts
+			WHERE raised_at >= ?1 AND raised_at <= ?2
+			ORDER BY raised_at
+		")?
+		.query_map(params![from, to], |row| {
+			Ok(Alert {
+				rule: row.get(0)?,
+				severity: row.get::<_, String>(1)?.parse().unwrap_or(Severity::Warning),
+				message: row.get(2)?,
+				raised_at: row.get(3)?,
+				causes: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+			})
+		})?
+		.collect()
+}
+
+/// Reads the sequences run and safings over a range from the audit log, oldest first.
+fn sequence_runs(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<SequenceRun>> {
+	connection
+		.prepare("
+			SELECT recorded_at, username, detail FROM AuditLog
+			WHERE action IN ('run sequence', 'safe') AND recorded_at >= ?1 AND recorded_at <= ?2
+			ORDER BY recorded_at
+		")?
+		.query_map(params![from, to], |row| {
+			Ok(SequenceRun {
+				at: row.get(0)?,
+				username: row.get(1)?,
+				detail: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+			})
+		})?
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::Measurement;
+	use super::*;
+
+	#[test]
+	fn dropouts_and_silences_are_found() {
+		let state = |channels: &[(&str, f64)]| {
+			let mut state = VehicleState::new();
+
+			for &(channel, value) in channels {
+				state.sensor_readings.insert(channel.to_owned(), Measurement { value, unit: Unit::Psi });
+			}
+
+			state
+		};
+
+		let snapshots = vec![
+			(0.0, state(&[("KBPT", 10.0), ("WTPT", 1.0)])),
+			(0.5, state(&[("KBPT", 20.0)])),
+			(1.0, state(&[("KBPT", 30.0)])),
+			(1.5, state(&[("KBPT", 40.0), ("WTPT", 2.0)])),
+			(10.0, state(&[("KBPT", f64::NAN)])),
+		];
+
+		let Analysis { channels, silences, .. } = analyze(&snapshots, 0.0, 10.0);
+
+		assert_eq!(silences.len(), 1);
+		assert_eq!((silences[0].started_at, silences[0].ended_at), (1.5, 10.0));
+
+		let kbpt = &channels[0];
+		assert_eq!((kbpt.readings, kbpt.dropouts), (5, 0));
+		assert_eq!((kbpt.min, kbpt.max, kbpt.mean), (10.0, 40.0, 25.0));
+		assert_eq!(kbpt.coverage, 1.0);
+
+		// WTPT went missing in the middle, and again at the end
+		let wtpt = &channels[1];
+		assert_eq!((wtpt.readings, wtpt.dropouts), (2, 2));
+		assert_eq!(wtpt.longest_dropout_seconds, 8.5);
+		assert_eq!(wtpt.coverage, 0.4);
+		assert_eq!(wtpt.thumbnail, [[0.0, 1.0], [1.5, 2.0]]);
+
+		// analyzing a chunk at a time finds the same
+		let mut analyzer = Analyzer::new(0.0, 10.0);
+
+		for chunk in snapshots.chunks(2) {
+			analyzer.add(chunk);
+		}
+
+		let chunked = analyzer.finish();
+		assert_eq!(chunked.silences.len(), 1);
+		assert_eq!(chunked.bounds, Some((0.0, 10.0)));
+		assert_eq!(serde_json::to_value(&chunked.channels).unwrap(), serde_json::to_value(&channels).unwrap());
+	}
+
+	#[test]
+	#[cfg(feature = "server")]
+	fn run_sheets_list_what_was_done_in_order() {
+		let database = crate::server::Database::volatile().unwrap();
+		database.migrate().unwrap();
+		let connection = database.connection.blocking_lock();
+
+		audit::record(&connection, Some("alice"), "run sequence", "ran sequence 'hotfire'").unwrap();
+		audit::record(&connection, Some("alice"), "database backup", "/tmp/backup.sqlite").unwrap();
+		note::record(&connection, Some("bob"), None, "igniter lit").unwrap();
+
+		let sheet = run_sheet(&connection, 0.0, f64::MAX).unwrap();
+
+		// the backup has nothing to do with the vehicle, so it is left off the timeline
+		let timeline = sheet.timeline.iter().map(|event| (event.kind, event.detail.as_str())).collect::<Vec<_>>();
+		assert_eq!(timeline, [(TimelineKind::Sequence, "ran sequence 'hotfire'"), (TimelineKind::Note, "igniter lit")]);
+		assert_eq!(sheet.sequences.len(), 1);
+		assert_eq!(sheet.notes.len(), 1);
+	}
+}
@@ -0,0 +1,127 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use super::clock;
+
+#[cfg(feature = "server")]
+use {
+	jeflog::pass,
+	std::{future::Future, time::Duration},
+	super::Shared,
+};
+
+/// The age in seconds below which logs are never pruned, matching the trigger
+/// which protects the most recent request logs from tampering.
+pub const MIN_LOG_AGE: f64 = 86400.0;
+
+/// How often the retention policy is enforced.
+#[cfg(feature = "server")]
+const ENFORCEMENT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// The number of rows deleted from each log by a prune.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PrunedLogs {
+	/// The number of rows deleted from `RequestLogs`.
+	pub request_logs: usize,
+
+	/// The number of rows deleted from `AuditLog`.
+	pub audit_log: usize,
+}
+
+/// Deletes the request logs and audit log entries recorded before the given
+/// Unix timestamps, leaving a log untouched if its timestamp is `None`.
+///
+/// Logs from the last day are always kept, however early the timestamps are.
+pub fn prune(connection: &Connection, request_logs_before: Option<f64>, audit_log_before: Option<f64>) -> rusqlite::Result<PrunedLogs> {
+	let latest = clock::now() - MIN_LOG_AGE;
+	let mut pruned = PrunedLogs::default();
+
+	if let Some(before) = request_logs_before {
+		pruned.request_logs = connection.execute("DELETE FROM RequestLogs WHERE timestamp < ?1", [before.min(latest)])?;
+	}
+
+	if let Some(before) = audit_log_before {
+		pruned.audit_log = connection.execute("DELETE FROM AuditLog WHERE recorded_at < ?1", [before.min(latest)])?;
+	}
+
+	Ok(pruned)
+}
+
+/// Parses an age such as `30d`, `12h`, `90m`, `45s`, or `2w` into seconds.
+pub fn parse_age(text: &str) -> Result<f64, String> {
+	let (number, unit) = text.split_at(text.trim_end_matches(char::is_alphabetic).len());
+
+	let scale = match unit {
+		"s" => 1.0,
+		"m" => 60.0,
+		"h" => 3600.0,
+		"d" => 86400.0,
+		"w" => 604800.0,
+		_ => return Err(format!("'{text}' must end in s, m, h, d, or w")),
+	};
+
+	match number.parse::<f64>() {
+		Ok(number) if number >= 0.0 && number.is_finite() => Ok(number * scale),
+		_ => Err(format!("'{text}' must be a non-negative number followed by its unit")),
+	}
+}
+
+/// Prunes the logs each hour as configured, so that they do not grow unboundedly.
+///
+/// The configuration is read each time, so retention may be changed by reloading it.
+#[cfg(feature = "server")]
+pub fn enforce(shared: &Shared) -> impl Future<Output = anyhow::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		loop {
+			let config = shared.config.current().retention.clone();
+			let cutoff = |days: Option<f64>| days.map(|days| clock::now() - days * 86400.0);
+
+			let pruned = prune(
+				&*shared.database.connection.lock().await,
+				cutoff(config.request_log_days),
+				cutoff(config.audit_log_days),
+			)?;
+
+			if pruned != PrunedLogs::default() {
+				pass!("Pruned {} request logs and {} audit log entries.", pruned.request_logs, pruned.audit_log);
+			}
+
+			tokio::time::sleep(ENFORCEMENT_INTERVAL).await;
+		}
+	}
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn only_logs_older_than_a_day_are_pruned() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+		let now = clock::now();
+
+		for age in [0.0, 2.0 * MIN_LOG_AGE, 40.0 * MIN_LOG_AGE] {
+			connection.execute("INSERT INTO RequestLogs (endpoint, origin, timestamp) VALUES ('/data/forward', '127.0.0.1', ?1)", [now - age]).unwrap();
+			connection.execute("INSERT INTO AuditLog (action, detail, recorded_at) VALUES ('safe', '', ?1)", [now - age]).unwrap();
+		}
+
+		// the most recent request log is protected by its trigger as well
+		assert!(connection.execute("DELETE FROM RequestLogs", []).is_err());
+
+		let thirty_days = now - parse_age("30d").unwrap();
+		assert_eq!(prune(&connection, Some(thirty_days), None).unwrap(), PrunedLogs { request_logs: 1, audit_log: 0 });
+		assert_eq!(prune(&connection, Some(now), Some(now)).unwrap(), PrunedLogs { request_logs: 1, audit_log: 2 });
+
+		let count = |table: &str| connection.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get::<_, i64>(0)).unwrap();
+		assert_eq!((count("RequestLogs"), count("AuditLog")), (1, 1));
+
+		assert_eq!(parse_age("12h"), Ok(43200.0));
+		assert!(parse_age("30").is_err() && parse_age("-1d").is_err());
+	}
+}
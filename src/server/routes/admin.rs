@@ -1,6 +1,6 @@
-use axum::{extract::State, Json};
-use crate::server::{self, error::internal, Shared};
-use rusqlite::types::ValueRef;
+use axum::{extract::{Path, Query, State}, Json};
+use crate::server::{self, allowlist::{self, AllowedHost}, audit, auth::Session, backup::{self, BackupRequest, DatabaseBackup}, config::ReloadSummary, error::{bad_request, conflict, internal, not_found}, retention::{self, PrunedLogs}, validation::{Valid, Validate, Validator}, Shared};
+use rusqlite::{params, types::ValueRef};
 use serde::{Deserialize, Serialize};
 
 #[allow(missing_docs)]
@@ -9,6 +9,12 @@ pub struct ExecuteSqlRequest {
 	pub raw_sql: String
 }
 
+impl Validate for ExecuteSqlRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("raw_sql", &self.raw_sql);
+	}
+}
+
 #[allow(missing_docs)]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ExecuteSqlResponse {
@@ -19,8 +25,11 @@ pub struct ExecuteSqlResponse {
 /// A route function which executes an arbitrary SQL query
 pub async fn execute_sql(
 	State(shared): State<Shared>,
-	Json(request): Json<ExecuteSqlRequest>,
+	session: Session,
+	Valid(Json(request)): Valid<Json<ExecuteSqlRequest>>,
 ) -> server::Result<Json<ExecuteSqlResponse>> {
+	session.require_admin()?;
+
 	let database = shared.database
 		.connection
 		.lock()
@@ -62,3 +71,394 @@ pub async fn execute_sql(
 
 	Ok(Json(ExecuteSqlResponse { column_names, rows }))
 }
+
+/// Describes an active session as listed by an admin.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionInfo {
+	/// The unique ID of the session, used to revoke it.
+	pub session_id: i64,
+
+	/// The name of the user who owns the session.
+	pub username: String,
+
+	/// The IP address from which the session was created.
+	pub origin: String,
+
+	/// The hostname reported by the client when the session was created.
+	pub hostname: Option<String>,
+
+	/// The Unix timestamp at which the session was created.
+	pub created_at: f64,
+
+	/// The Unix timestamp of the last authenticated request made with the session.
+	pub last_active: f64,
+
+	/// The Unix timestamp at which the session expires.
+	pub expires_at: f64,
+}
+
+/// Route function which lists all active sessions.
+pub async fn list_sessions(
+	State(shared): State<Shared>,
+	session: Session,
+) -> server::Result<Json<Vec<SessionInfo>>> {
+	session.require_admin()?;
+
+	let sessions = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("
+			SELECT session_id, username, origin, hostname, created_at, last_active, expires_at
+			FROM Sessions
+			WHERE expires_at > unixepoch('now', 'subsec')
+			ORDER BY last_active DESC
+		")
+		.map_err(internal)?
+		.query_map([], |row| {
+			Ok(SessionInfo {
+				session_id: row.get(0)?,
+				username: row.get(1)?,
+				origin: row.get(2)?,
+				hostname: row.get(3)?,
+				created_at: row.get(4)?,
+				last_active: row.get(5)?,
+				expires_at: row.get(6)?,
+			})
+		})
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	Ok(Json(sessions))
+}
+
+/// Route function which revokes a single session by its ID.
+pub async fn revoke_session(
+	State(shared): State<Shared>,
+	session: Session,
+	Path(session_id): Path<i64>,
+) -> server::Result<()> {
+	session.require_admin()?;
+
+	let rows_deleted = shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Sessions WHERE session_id = ?1", [session_id])
+		.map_err(internal)?;
+
+	if rows_deleted == 0 {
+		return Err(not_found("session does not exist"));
+	}
+
+	Ok(())
+}
+
+/// Request struct for revoking every session belonging to a user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RevokeUserSessionsRequest {
+	/// The name of the user to be logged out everywhere.
+	pub username: String,
+}
+
+impl Validate for RevokeUserSessionsRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("username", &self.username);
+	}
+}
+
+/// Route function which revokes all sessions belonging to a user, logging them out everywhere.
+pub async fn revoke_user_sessions(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Json(request)): Valid<Json<RevokeUserSessionsRequest>>,
+) -> server::Result<()> {
+	session.require_admin()?;
+
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Sessions WHERE username = ?1", params![request.username])
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which reloads the configuration file without restarting the server.
+///
+/// An invalid configuration is rejected and the current one is kept.
+pub async fn reload_config(
+	State(shared): State<Shared>,
+	session: Session,
+) -> server::Result<Json<ReloadSummary>> {
+	session.require_admin()?;
+
+	let summary = shared.config
+		.reload()
+		.map_err(|error| bad_request(format!("configuration was not reloaded: {error}")))?;
+
+	Ok(Json(summary))
+}
+
+/// Response struct listing the hosts which may connect as a vehicle computer.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FlightAllowlistResponse {
+	/// The hosts allowed by the configuration, which can only be changed by editing it.
+	pub configured: Vec<String>,
+
+	/// The hosts added through the API.
+	pub added: Vec<AllowedHost>,
+
+	/// Whether a pre-shared-key handshake is required of connecting computers.
+	pub handshake_required: bool,
+}
+
+/// Request and query struct naming a host on the flight allowlist.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FlightAllowlistHost {
+	/// The IP address or hostname.
+	pub host: String,
+}
+
+impl Validate for FlightAllowlistHost {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("host", &self.host);
+	}
+}
+
+/// Route function which lists the hosts which may connect as a vehicle computer.
+///
+/// A connection from anywhere is accepted while no host is configured or added.
+pub async fn get_flight_allowlist(
+	State(shared): State<Shared>,
+	session: Session,
+) -> server::Result<Json<FlightAllowlistResponse>> {
+	session.require_admin()?;
+
+	let config = shared.config.current();
+
+	let added = allowlist::list(&*shared.database.connection.lock().await)
+		.map_err(internal)?;
+
+	Ok(Json(FlightAllowlistResponse {
+		configured: config.flight.allowed_hosts.clone(),
+		added,
+		handshake_required: config.flight.pre_shared_key.is_some(),
+	}))
+}
+
+/// Route function which permits a host to connect as a vehicle computer.
+pub async fn add_flight_allowlist_host(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Json(request)): Valid<Json<FlightAllowlistHost>>,
+) -> server::Result<()> {
+	session.require_admin()?;
+
+	let connection = shared.database.connection.lock().await;
+	let host = request.host.trim();
+
+	if !allowlist::add(&connection, host, Some(&session.username)).map_err(internal)? {
+		return Err(conflict(format!("host '{host}' is already on the allowlist")));
+	}
+
+	audit::record(&connection, Some(&session.username), "flight allowlist", &format!("added {host}"))
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which removes a host added through the API from the flight allowlist.
+///
+/// Connections already established are kept.
+pub async fn remove_flight_allowlist_host(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Query(query)): Valid<Query<FlightAllowlistHost>>,
+) -> server::Result<()> {
+	session.require_admin()?;
+
+	let connection = shared.database.connection.lock().await;
+	let host = query.host.trim();
+
+	if !allowlist::remove(&connection, host).map_err(internal)? {
+		return Err(not_found(format!("host '{host}' was not added to the allowlist")));
+	}
+
+	audit::record(&connection, Some(&session.username), "flight allowlist", &format!("removed {host}"))
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which snapshots the live database to a file under the
+/// configured backup directory without stopping writes.
+pub async fn backup_database(
+	State(shared): State<Shared>,
+	session: Session,
+) -> server::Result<Json<DatabaseBackup>> {
+	session.require_admin()?;
+
+	let backup = backup::snapshot(&shared)
+		.await
+		.map_err(internal)?;
+
+	audit::record(&*shared.database.connection.lock().await, Some(&session.username), "database backup", &backup.path.display().to_string())
+		.map_err(internal)?;
+
+	Ok(Json(backup))
+}
+
+/// Query struct for pruning the logs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PruneLogsQuery {
+	/// The Unix timestamp before which request logs and audit log entries are deleted.
+	pub before: f64,
+}
+
+impl Validate for PruneLogsQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.check(self.before.is_finite(), "before", "must be a finite Unix timestamp");
+	}
+}
+
+/// Route function which deletes the request logs and audit log entries recorded
+/// before a time, other than those from the last day, which are always kept.
+pub async fn prune_logs(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Query(query)): Valid<Query<PruneLogsQuery>>,
+) -> server::Result<Json<PrunedLogs>> {
+	session.require_admin()?;
+
+	let connection = shared.database.connection.lock().await;
+
+	let pruned = retention::prune(&connection, Some(query.before), Some(query.before))
+		.map_err(internal)?;
+
+	let detail = format!("{} request logs and {} audit log entries before {}", pruned.request_logs, pruned.audit_log, query.before);
+
+	audit::record(&connection, Some(&session.username), "log prune", &detail)
+		.map_err(internal)?;
+
+	Ok(Json(pruned))
+}
+
+/// Query struct for purging stale messages from the outbox.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PurgeOutboxQuery {
+	/// The number of seconds since being handed to the link after which a message is purged.
+	pub older_than: f64,
+}
+
+impl Validate for PurgeOutboxQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.check(self.older_than.is_finite() && self.older_than >= 0.0, "older_than", "must be a non-negative number of seconds");
+	}
+}
+
+/// Response struct reporting how many messages were purged from the outbox.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PurgedOutbox {
+	/// The number of messages purged.
+	pub purged: usize,
+}
+
+/// Route function which forgets the messages in the outbox handed to a link
+/// longer ago than a number of seconds, whether they failed or are stuck pending.
+///
+/// Purging a message does not stop it from being written if it is still pending.
+pub async fn purge_outbox(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Query(query)): Valid<Query<PurgeOutboxQuery>>,
+) -> server::Result<Json<PurgedOutbox>> {
+	session.require_admin()?;
+
+	let purged = shared.outbox.purge(query.older_than);
+	let detail = format!("{purged} messages older than {} seconds", query.older_than);
+
+	audit::record(&*shared.database.connection.lock().await, Some(&session.username), "outbox purge", &detail)
+		.map_err(internal)?;
+
+	Ok(Json(PurgedOutbox { purged }))
+}
+
+/// Route function which sends the next batch of changes needed to bring a
+/// backup up to date, serialized with Postcard since it is mostly snapshots.
+pub async fn get_backup_changes(
+	State(shared): State<Shared>,
+	session: Session,
+	Json(request): Json<BackupRequest>,
+) -> server::Result<Vec<u8>> {
+	session.require_admin()?;
+
+	let batch = backup::changes(&*shared.database.connection.lock().await, &request)
+		.map_err(conflict)?;
+
+	postcard::to_allocvec(&batch).map_err(internal)
+}
+
+/// Response and request struct describing whether the server is in maintenance mode.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MaintenanceStatus {
+	/// Whether maintenance mode is on, refusing every request which could actuate the vehicle.
+	pub enabled: bool,
+}
+
+/// Route function which returns whether the server is in maintenance mode, so
+/// that GUIs may disable the controls which would be refused.
+pub async fn get_maintenance_mode(State(shared): State<Shared>) -> server::Result<Json<MaintenanceStatus>> {
+	Ok(Json(MaintenanceStatus { enabled: shared.maintenance.is_enabled() }))
+}
+
+/// Route function which turns maintenance mode on or off.
+pub async fn set_maintenance_mode(
+	State(shared): State<Shared>,
+	session: Session,
+	Json(request): Json<MaintenanceStatus>,
+) -> server::Result<Json<MaintenanceStatus>> {
+	session.require_admin()?;
+
+	if shared.maintenance.set(request.enabled) != request.enabled {
+		let detail = if request.enabled { "enabled" } else { "disabled" };
+
+		audit::record(&*shared.database.connection.lock().await, Some(&session.username), "maintenance mode", detail)
+			.map_err(internal)?;
+	}
+
+	Ok(Json(request))
+}
+
+/// Response and request struct describing whether the server is in spectator mode.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpectatorStatus {
+	/// Whether spectator mode is on, refusing every request which would change anything.
+	pub enabled: bool,
+}
+
+/// Route function which returns whether the server is in spectator mode, so
+/// that GUIs may hide the controls which would be refused.
+pub async fn get_spectator_mode(State(shared): State<Shared>) -> server::Result<Json<SpectatorStatus>> {
+	Ok(Json(SpectatorStatus { enabled: shared.spectator.is_enabled() }))
+}
+
+/// Route function which turns spectator mode on or off.
+pub async fn set_spectator_mode(
+	State(shared): State<Shared>,
+	session: Session,
+	Json(request): Json<SpectatorStatus>,
+) -> server::Result<Json<SpectatorStatus>> {
+	session.require_admin()?;
+
+	if shared.spectator.set(request.enabled) != request.enabled {
+		let detail = if request.enabled { "enabled" } else { "disabled" };
+
+		audit::record(&*shared.database.connection.lock().await, Some(&session.username), "spectator mode", detail)
+			.map_err(internal)?;
+	}
+
+	Ok(Json(request))
+}
@@ -0,0 +1,91 @@
+use axum::{extract::{Query, State}, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{self, alert::{self, Alert, Severity}, error::{bad_request, internal}, validation::{Valid, Validate, Validator}, Shared};
+
+/// Query parameters for alert requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AlertsQuery {
+	/// The earliest time of an alert to include, as a Unix timestamp.
+	since: Option<f64>,
+
+	/// The maximum number of alerts to return, most recent first. Defaults to 100.
+	limit: Option<u32>,
+}
+
+impl Validate for AlertsQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.check(self.limit != Some(0), "limit", "must be at least 1");
+	}
+}
+
+/// Route function which returns the most recently raised alerts.
+pub async fn get_alerts(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<AlertsQuery>>,
+) -> server::Result<Json<Vec<Alert>>> {
+	let alerts = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("
+			SELECT rule, severity, message, raised_at, causes
+			FROM Alerts
+			WHERE ?1 IS NULL OR raised_at >= ?1
+			ORDER BY raised_at DESC
+			LIMIT ?2
+		")
+		.map_err(internal)?
+		.query_map(rusqlite::params![query.since, query.limit.unwrap_or(100)], |row| {
+			let severity = row.get::<_, String>(1)?
+				.parse::<Severity>()
+				.map_err(|error| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, error.into()))?;
+
+			Ok(Alert {
+				rule: row.get(0)?,
+				severity,
+				message: row.get(2)?,
+				raised_at: row.get(3)?,
+				causes: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+			})
+		})
+		.and_then(|iter| iter.collect::<Result<Vec<_>, rusqlite::Error>>())
+		.map_err(internal)?;
+
+	Ok(Json(alerts))
+}
+
+/// Request struct for raising an alert from outside of servo.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RaiseAlertRequest {
+	/// The name of the event, such as `checkout test failed`.
+	pub event: String,
+
+	/// How severe the alert is. Defaults to a warning.
+	#[serde(default)]
+	pub severity: Severity,
+
+	/// What caused the alert.
+	pub message: String,
+}
+
+impl Validate for RaiseAlertRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("event", &self.event);
+		validator.non_empty("message", &self.message);
+	}
+}
+
+/// Route function which raises an alert on behalf of an outside script, such
+/// as a scheduled checkout test, notifying operators through the system event actions.
+pub async fn raise_alert(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<RaiseAlertRequest>>,
+) -> server::Result<()> {
+	if request.event.trim().is_empty() {
+		return Err(bad_request("alert event must not be empty"));
+	}
+
+	alert::raise_event(&shared, &request.event, request.severity, request.message).await;
+	Ok(())
+}
@@ -0,0 +1,181 @@
+use axum::{extract::{ConnectInfo, State}, Json};
+use crate::server::{
+	self,
+	api::{LoginRequest, LoginResponse},
+	auth::{self, Session},
+	error::{internal, unauthorized, ErrorCode},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+impl Validate for LoginRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("username", &self.username);
+	}
+}
+
+/// Route function which checks a user's credentials and creates a new session.
+pub async fn login(
+	State(shared): State<Shared>,
+	ConnectInfo(peer): ConnectInfo<SocketAddr>,
+	Valid(Json(request)): Valid<Json<LoginRequest>>,
+) -> server::Result<Json<LoginResponse>> {
+	let (password_hash, salt, iterations, role) = shared.database
+		.connection
+		.lock()
+		.await
+		.query_row(
+			"SELECT password_hash, salt, iterations, role FROM Users WHERE username = ?1",
+			[&request.username],
+			|row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, u32>(2)?, row.get::<_, String>(3)?)),
+		)
+		.optional()
+		.map_err(internal)?
+		.ok_or(unauthorized("invalid username or password").with_code(ErrorCode::InvalidCredentials))?;
+
+	// stretching takes a while, so it is done off of the runtime and without the database
+	let password = request.password.clone();
+	let rehash = tokio::task::spawn_blocking(move || {
+		if !auth::verify_password(&password, &salt, iterations, &password_hash) {
+			return None;
+		}
+
+		// passwords hashed with fewer iterations than now required are upgraded on login
+		let rehash = (iterations < auth::PASSWORD_ITERATIONS).then(|| {
+			let salt = auth::generate_salt();
+			(auth::hash_password(&password, &salt, auth::PASSWORD_ITERATIONS), salt)
+		});
+
+		Some(rehash)
+	})
+	.await
+	.map_err(internal)?
+	.ok_or(unauthorized("invalid username or password").with_code(ErrorCode::InvalidCredentials))?;
+
+	let database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	if let Some((password_hash, salt)) = rehash {
+		database
+			.execute(
+				"UPDATE Users SET password_hash = ?1, salt = ?2, iterations = ?3 WHERE username = ?4",
+				params![password_hash, salt, auth::PASSWORD_ITERATIONS, request.username],
+			)
+			.map_err(internal)?;
+	}
+
+	let token = auth::generate_token();
+
+	let expires_at = database
+		.query_row("
+			INSERT INTO Sessions (token_hash, username, origin, hostname, expires_at)
+			VALUES (?1, ?2, ?3, ?4, unixepoch('now', 'subsec') + ?5)
+			RETURNING expires_at
+		", params![
+			auth::hash_token(&token),
+			request.username,
+			peer.ip().to_string(),
+			request.hostname,
+			shared.config.current().sessions.expiry_minutes * 60.0,
+		], |row| row.get(0))
+		.map_err(internal)?;
+
+	Ok(Json(LoginResponse { token, role, expires_at }))
+}
+
+/// Route function which ends the session used to make the request.
+pub async fn logout(State(shared): State<Shared>, session: Session) -> server::Result<()> {
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Sessions WHERE session_id = ?1", [session.session_id])
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Request struct for creating or updating a user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetUserRequest {
+	/// The unique name of the user.
+	pub username: String,
+
+	/// The plaintext password of the user, which is salted and hashed before storage.
+	pub password: String,
+
+	/// The role of the user, `admin`, `operator`, or `spectator`. Defaults to `operator`.
+	pub role: Option<String>,
+}
+
+impl Validate for SetUserRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("username", &self.username);
+		validator.non_empty("password", &self.password);
+
+		if let Some(role) = &self.role {
+			validator.one_of("role", role, &["admin", "operator", "spectator"]);
+		}
+	}
+}
+
+/// Route function which creates a new user or updates an existing one.
+///
+/// Requires an admin session, unless no users exist yet, in which case the
+/// request is allowed so that the first admin account may be created.
+pub async fn set_user(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<SetUserRequest>>,
+) -> server::Result<()> {
+	// stretching takes a while, so it is done off of the runtime and before the database is locked
+	let password = request.password.clone();
+	let (password_hash, salt) = tokio::task::spawn_blocking(move || {
+		let salt = auth::generate_salt();
+		(auth::hash_password(&password, &salt, auth::PASSWORD_ITERATIONS), salt)
+	})
+	.await
+	.map_err(internal)?;
+
+	let database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let user_count = database
+		.query_row("SELECT COUNT(*) FROM Users", [], |row| row.get::<_, i64>(0))
+		.map_err(internal)?;
+
+	if user_count > 0 {
+		session
+			.ok_or(unauthorized("authentication required"))?
+			.require_admin()?;
+	}
+
+	let role = request.role.as_deref().unwrap_or("operator");
+
+	database
+		.execute("
+			INSERT INTO Users (username, password_hash, salt, iterations, role)
+			VALUES (?1, ?2, ?3, ?4, ?5)
+			ON CONFLICT (username) DO UPDATE SET
+				password_hash = excluded.password_hash,
+				salt = excluded.salt,
+				iterations = excluded.iterations,
+				role = excluded.role
+		", params![
+			request.username,
+			password_hash,
+			salt,
+			auth::PASSWORD_ITERATIONS,
+			role,
+		])
+		.map_err(internal)?;
+
+	Ok(())
+}
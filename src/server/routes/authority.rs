@@ -0,0 +1,90 @@
+use axum::{extract::{ws, State, WebSocketUpgrade}, response::Response};
+use futures_util::{SinkExt, StreamExt};
+use jeflog::{pass, warn};
+use std::time::Duration;
+
+use crate::server::{self, audit, auth::Session, authority::LeaseStatus, error::forbidden, Shared};
+
+/// Route function which accepts a WebSocket over which a GUI holds the lease on
+/// command authority.
+///
+/// The lease is taken when the socket opens, unless another session holds it,
+/// and every message received renews it. A GUI gives up the lease by sending
+/// `release`; a socket which is simply closed or goes quiet lets it expire, in
+/// case the console froze or lost its connection mid-command.
+pub async fn hold_authority(
+	ws: WebSocketUpgrade,
+	State(shared): State<Shared>,
+	session: Session,
+) -> server::Result<Response> {
+	if session.is_spectator() {
+		return Err(forbidden("spectators may not hold command authority"));
+	}
+
+	Ok(ws.on_upgrade(move |socket| async move {
+		let (mut writer, mut reader) = socket.split();
+		let lease_seconds = shared.config.current().authority.lease_seconds;
+		let duration = Duration::from_secs_f64(lease_seconds);
+
+		let acquired = shared.authority.acquire(&session, duration).await;
+
+		let holder = match &acquired {
+			Ok(()) => session.username.clone(),
+			Err(holder) => holder.clone(),
+		};
+
+		let status = LeaseStatus { held: acquired.is_ok(), holder: Some(holder), lease_seconds };
+
+		if let Ok(status) = serde_json::to_string(&status) {
+			let _ = writer.send(ws::Message::Text(status)).await;
+		}
+
+		if acquired.is_err() {
+			let _ = writer.close().await;
+			return;
+		}
+
+		pass!("Command authority taken by \x1b[1m{}\x1b[0m.", session.username);
+
+		let _ = audit::record(
+			&*shared.database.connection.lock().await,
+			Some(&session.username),
+			"take authority",
+			"took the lease on command authority",
+		);
+
+		while let Some(Ok(message)) = reader.next().await {
+			match message {
+				ws::Message::Close(_) => break,
+				ws::Message::Text(text) if text.trim() == "release" => {
+					shared.authority.release(session.session_id).await;
+					pass!("Command authority released by \x1b[1m{}\x1b[0m.", session.username);
+
+					let _ = audit::record(
+						&*shared.database.connection.lock().await,
+						Some(&session.username),
+						"release authority",
+						"released the lease on command authority",
+					);
+
+					break;
+				},
+				_ => {
+					if !shared.authority.renew(session.session_id, duration).await {
+						warn!("Heartbeat from \x1b[1m{}\x1b[0m arrived after its command authority lease expired.", session.username);
+
+						let status = LeaseStatus { held: false, holder: shared.authority.holder().await, lease_seconds };
+
+						if let Ok(status) = serde_json::to_string(&status) {
+							let _ = writer.send(ws::Message::Text(status)).await;
+						}
+
+						break;
+					}
+				},
+			}
+		}
+
+		let _ = writer.close().await;
+	}))
+}
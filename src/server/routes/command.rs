@@ -1,50 +1,247 @@
 use axum::{extract::State, Json};
-use common::comm::Sequence;
-use crate::server::{self, Shared, error::{bad_request, internal}};
+use common::comm::{Computer, FlightControlMessage, Sequence, SensorType};
+use crate::server::{
+	self,
+	audit,
+	auth::Session,
+	error::{bad_request, forbidden, internal, not_found, unauthorized, ErrorCode},
+	flight,
+	hazard::{self, ConfirmationError, HazardLevel},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 
 /// Request struct containing all necessary information to execute a command.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OperatorCommandRequest {
-	command: String,
-	target: Option<String>,
-	state: Option<String>,
+	/// The command to run, such as `click_valve`.
+	pub command: String,
+
+	/// The valve targeted by the command, if any.
+	pub target: Option<String>,
+
+	/// The state the target is set to, if any, either `open` or `closed`.
+	pub state: Option<String>,
+}
+
+impl Validate for OperatorCommandRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.one_of("command", &self.command, &["click_valve"]);
+
+		if self.command == "click_valve" {
+			validator.check(self.target.as_deref().is_some_and(|target| !target.trim().is_empty()), "target", "is required");
+
+			match &self.state {
+				Some(state) => validator.one_of("state", state, &["open", "closed"]),
+				None => validator.error("state", "is required"),
+			}
+		}
+	}
+}
+
+/// Response struct describing how an operator command was handled.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OperatorCommandResponse {
+	/// The ID a second operator confirms the command with, if it touches a
+	/// hazardous channel and was held rather than dispatched.
+	pub awaiting_confirmation: Option<String>,
+
+	/// The hazard level of the channel targeted by the command.
+	pub hazard_level: HazardLevel,
 }
 
 /// Route handler to dispatch a single manual operator command
+///
+/// The target of a command must be mapped as a valve in the active configuration,
+/// both so that a typo is caught before it reaches the flight computer and so
+/// that nothing other than a valve name is formatted into the script.
+///
+/// A command targeting a channel classified as hazardous is not dispatched, but
+/// held until a different operator confirms it through `/operator/command/confirm`.
 pub async fn dispatch_operator_command(
 	State(shared): State<Shared>,
-	Json(request): Json<OperatorCommandRequest>,
-) -> server::Result<()> {
-	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-		let command = match request.command.as_str() {
-			"click_valve" => {
-				let target = request.target
-					.clone()
-					.ok_or(bad_request("must supply target name"))?;
-	
-				let script = match request.state.as_deref() {
-					Some("open") => format!("{target}.open()"),
-					Some("closed") => format!("{target}.close()"),
-					None => Err(bad_request("valve state is required"))?,
-					_ => Err(bad_request("unrecognized state identifier"))?,
-				};
-	
-				common::comm::FlightControlMessage::Sequence(Sequence { name: "command".to_owned(), script })
-			},
-			_ => return Err(bad_request("unrecognized command identifier")),
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<OperatorCommandRequest>>,
+) -> server::Result<Json<OperatorCommandResponse>> {
+	let received_at = Instant::now();
+
+	// commands are sent to the computer the target valve is attached to
+	let (computer, hazard_level) = match &request.target {
+		Some(target) => (validate_valve(&shared, target).await?, target_hazard(&shared, target).await?),
+		None => (Computer::Flight, HazardLevel::None),
+	};
+
+	let script = command_script(&request)?;
+	let username = session.as_ref().map(|session| session.username.as_str());
+
+	if hazard_level.requires_confirmation() {
+		let Some(username) = username else {
+			return Err(unauthorized("a hazardous command must be requested by a logged in operator"));
 		};
-	
-		let serialized = postcard::to_allocvec(&command)
-			.map_err(internal)?;
-	
-		flight
-			.send_bytes(&serialized)
-			.await
-			.map_err(internal)?;
-	} else {
-		return Err(internal("flight computer not connected"));
+
+		let id = shared.confirmations
+			.request(request, username, script.clone())
+			.await;
+
+		audit::record_with_severity(
+			&*shared.database.connection.lock().await,
+			Some(username),
+			"request command",
+			&format!("requested {hazard_level} command `{script}`, awaiting confirmation {id}"),
+			hazard_level.audit_severity(),
+		)
+		.map_err(internal)?;
+
+		return Ok(Json(OperatorCommandResponse { awaiting_confirmation: Some(id), hazard_level }));
+	}
+
+	send_script(&shared, computer, &script, received_at).await?;
+
+	if hazard_level > HazardLevel::None {
+		audit::record_with_severity(
+			&*shared.database.connection.lock().await,
+			username,
+			"command",
+			&format!("sent {hazard_level} command `{script}`"),
+			hazard_level.audit_severity(),
+		)
+		.map_err(internal)?;
+	}
+
+	Ok(Json(OperatorCommandResponse { awaiting_confirmation: None, hazard_level }))
+}
+
+/// Request struct for confirming a command held for a second operator.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfirmCommandRequest {
+	/// The ID returned when the command was requested.
+	pub confirmation_id: String,
+}
+
+impl Validate for ConfirmCommandRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("confirmation_id", &self.confirmation_id);
+	}
+}
+
+/// Route handler which dispatches a command held for confirmation, as long as
+/// the confirming user is not the one who requested it.
+///
+/// The target is checked again since the active configuration may have changed
+/// while the command was pending.
+pub async fn confirm_operator_command(
+	State(shared): State<Shared>,
+	session: Session,
+	Valid(Json(request)): Valid<Json<ConfirmCommandRequest>>,
+) -> server::Result<Json<OperatorCommandResponse>> {
+	let received_at = Instant::now();
+
+	let pending = shared.confirmations
+		.confirm(&request.confirmation_id, &session.username)
+		.await
+		.map_err(|error| match error {
+			ConfirmationError::Unknown => not_found(error),
+			ConfirmationError::Expired => not_found(error).with_code(ErrorCode::ConfirmationExpired),
+			ConfirmationError::SameOperator => forbidden(error).with_code(ErrorCode::SameOperator),
+		})?;
+
+	let (computer, hazard_level) = match &pending.command.target {
+		Some(target) => (validate_valve(&shared, target).await?, target_hazard(&shared, target).await?),
+		None => (Computer::Flight, HazardLevel::None),
+	};
+
+	send_script(&shared, computer, &pending.description, received_at).await?;
+
+	audit::record_with_severity(
+		&*shared.database.connection.lock().await,
+		Some(&session.username),
+		"confirm command",
+		&format!("confirmed {hazard_level} command `{}` requested by {}", pending.description, pending.requested_by),
+		hazard_level.audit_severity(),
+	)
+	.map_err(internal)?;
+
+	Ok(Json(OperatorCommandResponse { awaiting_confirmation: None, hazard_level }))
+}
+
+/// Formats the script an operator command runs on the flight computer.
+fn command_script(request: &OperatorCommandRequest) -> server::Result<String> {
+	match request.command.as_str() {
+		"click_valve" => {
+			let target = request.target
+				.as_deref()
+				.ok_or(bad_request("must supply target name"))?;
+
+			match request.state.as_deref() {
+				Some("open") => Ok(format!("{target}.open()")),
+				Some("closed") => Ok(format!("{target}.close()")),
+				None => Err(bad_request("valve state is required")),
+				_ => Err(bad_request("unrecognized state identifier")),
+			}
+		},
+		_ => Err(bad_request("unrecognized command identifier")),
 	}
+}
+
+/// Sends the script of an operator command to a computer as a sequence.
+async fn send_script(shared: &Shared, computer: Computer, script: &str, received_at: Instant) -> server::Result<()> {
+	let mut connection = shared.connection(computer).0.lock().await;
+
+	let Some(flight) = connection.as_mut() else {
+		return Err(flight::not_connected(computer));
+	};
 
+	let message = FlightControlMessage::Sequence(Sequence { name: "command".to_owned(), script: script.to_owned() });
+
+	flight
+		.send_message(&message, &format!("command `{script}`"))
+		.await
+		.map_err(internal)?;
+
+	shared.metrics.latency.lock().await.command_to_write.record(received_at.elapsed());
 	Ok(())
 }
+
+/// Looks up the hazard level of a command target in the active configuration.
+async fn target_hazard(shared: &Shared, target: &str) -> server::Result<HazardLevel> {
+	hazard::level(&*shared.database.connection.lock().await, target).map_err(internal)
+}
+
+/// Checks that a command target is mapped as a valve in the active configuration,
+/// returning the computer it is attached to.
+async fn validate_valve(shared: &Shared, target: &str) -> server::Result<Computer> {
+	let mappings = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("SELECT text_id, sensor_type, computer FROM NodeMappings WHERE active = TRUE ORDER BY text_id")
+		.map_err(internal)?
+		.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, SensorType>(1)?, row.get::<_, Computer>(2)?)))
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	if let Some((_, sensor_type, computer)) = mappings.iter().find(|(text_id, _, _)| text_id == target) {
+		if !matches!(sensor_type, SensorType::Valve) {
+			return Err(bad_request(format!(
+				"'{target}' is mapped as a {sensor_type:?} sensor, so its state cannot be changed",
+			)));
+		}
+
+		return Ok(*computer);
+	}
+
+	let valves = mappings
+		.iter()
+		.filter(|(_, sensor_type, _)| matches!(sensor_type, SensorType::Valve))
+		.map(|(text_id, _, _)| text_id.as_str())
+		.collect::<Vec<_>>();
+
+	if valves.is_empty() {
+		return Err(bad_request(format!("'{target}' is not a valve; the active configuration has no valves")));
+	}
+
+	Err(bad_request(format!("'{target}' is not a valve; valid valves are {}", valves.join(", "))))
+}
@@ -1,27 +1,127 @@
-use axum::{extract::{ws, ConnectInfo, State, WebSocketUpgrade}, http::header, response::{IntoResponse, Response}, Json};
+use axum::{body::Body, extract::{ws, ConnectInfo, Query, State, WebSocketUpgrade}, http::header, response::{IntoResponse, Response}, Json};
 use common::comm::VehicleState;
-use crate::server::{self, error::{bad_request, internal}, Shared};
+use crate::server::{self, api::{BackfillRequest, BackfillResponse, SnapshotKey, StatsResponse}, aggregation::{AggregatedState, Aggregator}, archive::{self, ArchiveManifest}, audit, capture::{self, CaptureWindow}, channel, clock, conditional::Preconditions, config::ChannelConfig, error::{bad_request, internal, not_found}, export::{self, Downsampler, ExportPreset}, influx, note::{self, Note}, pair::{self, PairReading}, position::PositionFix, auth::Session, quarantine::{self, BadFrame}, report::{self, QualityReport, RunSheet}, selector::{self, Selection}, subscription::{self, Subscription}, statistics::Statistics, storage::{SnapshotRange, Storage}, units::UnitSystem, validation::{Valid, Validate, Validator}, Shared};
 use futures_util::{SinkExt, StreamExt};
-use hdf5::DatasetBuilder;
 use jeflog::warn;
+use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use tokio::{fs, time::MissedTickBehavior};
-use std::{collections::HashSet, net::SocketAddr, path::Path, sync::atomic::{AtomicU32, Ordering}, time::Duration};
+use serde_json::json;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, time::{Interval, MissedTickBehavior}};
+use std::{collections::{BTreeMap, HashSet}, env, io, net::SocketAddr, path::PathBuf, process, ptr, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex as StdMutex, PoisonError, Weak}, time::Duration};
+
+#[cfg(feature = "hdf5")]
+use crate::server::vector;
+
+#[cfg(feature = "hdf5")]
+use hdf5::{types::VarLenUnicode, DatasetBuilder};
+
+#[cfg(feature = "hdf5")]
+use std::{path::Path, sync::atomic::AtomicU32};
+
+#[cfg(feature = "hdf5")]
+use tokio::fs;
 
 /// Request struct for export requests.
+///
+/// Options which are left out are taken from the named preset, if any.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ExportRequest {
-	format: String,
+	format: Option<String>,
+
+	/// Several formats to export at once from the same snapshots, returned
+	/// together as a zip archive. Takes precedence over `format`.
+	#[serde(default)]
+	formats: Vec<String>,
+
 	from: f64,
 	to: f64,
+
+	/// The name of a saved export preset whose options are used.
+	#[serde(default)]
+	preset: Option<String>,
+
+	/// The selectors of the sensors and valves to export, such as `FU_*`, or every channel if empty.
+	#[serde(default)]
+	channels: Option<Vec<String>>,
+
+	/// The rate in hertz to downsample the export to, aggregating each interval
+	/// into the mean, minimum, and maximum of each sensor.
+	#[serde(default)]
+	downsample_hz: Option<f64>,
+
+	/// The unit system readings are converted into.
+	#[serde(default)]
+	units: Option<UnitSystem>,
+}
+
+impl Validate for ExportRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(Some(self.from), Some(self.to));
+
+		if let Some(format) = &self.format {
+			validator.one_of("format", format, &export::FORMATS);
+		}
+
+		for (i, format) in self.formats.iter().enumerate() {
+			validator.one_of(&format!("formats[{i}]"), format, &export::FORMATS);
+
+			if let Some(first) = self.formats[..i].iter().position(|other| other == format) {
+				validator.error(&format!("formats[{i}]"), format!("duplicates formats[{first}]"));
+			}
+		}
+
+		if let Some(preset) = &self.preset {
+			validator.non_empty("preset", preset);
+		}
+
+		if let Some(channels) = &self.channels {
+			validator.selectors("channels", channels);
+		}
+
+		if let Some(rate) = self.downsample_hz {
+			validator.positive("downsample_hz", rate);
+		}
+	}
 }
 
 // An integer used to create unique filenames for exports in case two exports overlap in time
 // Atomic to be safe
+#[cfg(feature = "hdf5")]
 static EXPORT_FILE_INDEX_ATOMIC: AtomicU32 = AtomicU32::new(0);
 
+/// The display name and group of a channel, if configured, as the string attributes written to exports.
+#[cfg(feature = "hdf5")]
+fn channel_attributes(channels: &BTreeMap<String, ChannelConfig>, name: &str) -> hdf5::Result<Vec<(&'static str, VarLenUnicode)>> {
+	let Some(channel) = channels.get(name) else {
+		return Ok(Vec::new());
+	};
+
+	[("display_name", &channel.display_name), ("group", &channel.group)]
+		.into_iter()
+		.filter_map(|(attribute, value)| Some((attribute, value.as_deref()?)))
+		.map(|(attribute, value)| {
+			let value = value.parse::<VarLenUnicode>().map_err(|error| error.to_string())?;
+			Ok((attribute, value))
+		})
+		.collect()
+}
+
+/// A unit symbol or system name as an HDF5 string attribute.
+#[cfg(feature = "hdf5")]
+fn unit_attribute(text: &str) -> hdf5::Result<VarLenUnicode> {
+	Ok(text.parse::<VarLenUnicode>().map_err(|error| error.to_string())?)
+}
+
 /// A function that creates an HDF5 file at a given path containing the timestamps, sensor, and valve values as specified in sensor_names and valve_names in each vehicle state
-pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_states: &[(f64, VehicleState)], path: &Path) -> hdf5::Result<()>{
+///
+/// The display name and group of each configured channel are written as attributes of its group or dataset.
+/// Operator notes are written to the notes group with their own timestamps.
+///
+/// Readings converted into another unit system keep the IDs of the units they were
+/// reported in, and the symbol of the unit they were converted into is written as the
+/// `unit` attribute of each sensor group.
+#[cfg(feature = "hdf5")]
+pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_states: &[(f64, VehicleState)], channels: &BTreeMap<String, ChannelConfig>, notes: &[Note], units: UnitSystem, path: &Path) -> hdf5::Result<()>{
 	// Create the HDF5 file
 	let file = hdf5::File::create(path)?;
 	
@@ -45,9 +145,14 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 		.with_data(&timestamps_vec)
 		.create("timestamps")?;
 		
+	if units != UnitSystem::Native {
+		sensors_group.new_attr::<VarLenUnicode>().create("unit_system")?.write_scalar(&unit_attribute(units.as_str())?)?;
+	}
+
 	for name in sensor_names {
 		let mut reading_vec = Vec::with_capacity(vehicle_states.len());
 		let mut unit_vec = Vec::with_capacity(vehicle_states.len());
+		let mut converted_symbol = None;
 		
 		// Yes I know iterating through the vehicle states for every sensor / valve is dumb,
 		// but I'm avoiding storing the entirety of the vehicle state in memory twice, so each
@@ -57,7 +162,9 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 			// Put in bad data if nothing is found
 			match value {
 				Some(x) =>  { 
-					reading_vec.push(x.value);
+					let (value, symbol) = units.convert(x.value, x.unit);
+					converted_symbol.get_or_insert(symbol);
+					reading_vec.push(value);
 					let id = (x.unit as i8).try_into()?; // Should never panic unless absurd amounts of units are added
 					unit_vec.push(id);
 					},
@@ -70,6 +177,14 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 			};
 		}
 		let curr_sensor_group = sensors_group.create_group(name.as_str())?;
+
+		for (attribute, value) in channel_attributes(channels, name)? {
+			curr_sensor_group.new_attr::<VarLenUnicode>().create(attribute)?.write_scalar(&value)?;
+		}
+
+		if let Some(symbol) = converted_symbol.filter(|_| units != UnitSystem::Native) {
+			curr_sensor_group.new_attr::<VarLenUnicode>().create("unit")?.write_scalar(&unit_attribute(symbol)?)?;
+		}
 		
 		// Make datasets
 		curr_sensor_group.new_dataset_builder()
@@ -83,6 +198,44 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 			.create("units")?;
 	}
 
+	// Multi-component channels are additionally written as single datasets with one row per
+	// timestamp and one column per component, in the order given by VectorKind::components.
+	// This means a quaternion has 4 columns (w, x, y, z) and a vector has 3 (x, y, z).
+	let vectors_group = file.create_group("vectors")?;
+
+	for channel in vector::find_channels(sensor_names) {
+		let component_count = channel.kind.components().len();
+		let mut component_vec = Vec::with_capacity(vehicle_states.len() * component_count);
+		let mut unit_vec = Vec::with_capacity(vehicle_states.len());
+
+		for (_, state) in vehicle_states {
+			match channel.read(state) {
+				Some((values, unit)) => {
+					component_vec.extend(values.into_iter().map(|value| units.convert(value, unit).0));
+					unit_vec.push(unit as i8);
+				},
+				// Same garbage data as a missing scalar reading
+				None => {
+					component_vec.resize(component_vec.len() + component_count, -6942069420.0);
+					unit_vec.push(-69);
+				},
+			};
+		}
+
+		let curr_vector_group = vectors_group.create_group(channel.name.as_str())?;
+
+		curr_vector_group.new_dataset::<f64>()
+			.shape([vehicle_states.len(), component_count])
+			.deflate(9)
+			.create("components")?
+			.write_raw(&component_vec)?;
+
+		curr_vector_group.new_dataset_builder()
+			.deflate(9)
+			.with_data(&unit_vec)
+			.create("units")?;
+	}
+
 	// A vector of all the possible ValveStates seen. Used to create the attributes that indicate what each value of ValveState means.
 	// Likely more efficient as a simple vector, since ValveState has few possible elements. Will check later.
 	// I was originally going to make this a single attribute in the metadata category, but you can't iterate through an enum, 
@@ -119,10 +272,14 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 		}
 		
 		// Make dataset
-		valves_group.new_dataset_builder()
+		let valve_dataset = valves_group.new_dataset_builder()
 			.deflate(9)
 			.with_data(&state_vec)
 			.create(name.as_str())?;
+
+		for (attribute, value) in channel_attributes(channels, name)? {
+			valve_dataset.new_attr::<VarLenUnicode>().create(attribute)?.write_scalar(&value)?;
+		}
 	}
 	
 	// Put an attribute of what id each valve state is represented by into the valve state id's metadata group
@@ -135,57 +292,447 @@ pub fn make_hdf5_file(sensor_names: &[String], valve_names: &[String], vehicle_s
 		}
 	}
 	
+	let notes_group = file.create_group("notes")?;
+
+	let note_timestamps = notes
+		.iter()
+		.map(|note| note.created_at)
+		.collect::<Vec<_>>();
+
+	let note_text = |text: &str| text.parse::<VarLenUnicode>().map_err(|error| error.to_string());
+
+	let note_contents = notes
+		.iter()
+		.map(|note| note_text(&note.content))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let note_authors = notes
+		.iter()
+		.map(|note| note_text(note.author.as_deref().unwrap_or_default()))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	DatasetBuilder::new(&notes_group)
+		.with_data(&note_timestamps)
+		.create("timestamps")?;
+
+	DatasetBuilder::new(&notes_group)
+		.with_data(&note_contents)
+		.create("contents")?;
+
+	DatasetBuilder::new(&notes_group)
+		.with_data(&note_authors)
+		.create("authors")?;
+
 	// Close the file
 	file.close()?;
 	
 	Ok(())
 }
 
+/// A function that creates CSV content containing the timestamps, sensor, and valve values as specified in sensor_names and valve_names in each vehicle state
+///
+/// If there are any operator notes, they are written to a last column, on the row following each note.
+/// Readings are written converted into the given unit system.
+pub fn make_csv(sensor_names: &[String], valve_names: &[String], vehicle_states: &[(f64, VehicleState)], notes: &[Note], units: UnitSystem) -> String {
+	let columns = CsvColumns {
+		sensor_names: sensor_names.to_vec(),
+		valve_names: valve_names.to_vec(),
+		notes: !notes.is_empty(),
+	};
+
+	let mut content = columns.header();
+
+	let timestamps = vehicle_states
+		.iter()
+		.map(|(timestamp, _)| *timestamp)
+		.collect::<Vec<_>>();
+
+	let aligned_notes = note::align(notes, &timestamps);
+
+	for ((timestamp, state), row_notes) in vehicle_states.iter().zip(aligned_notes) {
+		columns.write_row(&mut content, *timestamp, state, row_notes.as_deref(), units);
+	}
+
+	content
+}
+
+/// The columns of a CSV export after its timestamp.
+#[derive(Clone, Debug, Default)]
+struct CsvColumns {
+	sensor_names: Vec<String>,
+	valve_names: Vec<String>,
+
+	/// Whether there is a last column of operator notes.
+	notes: bool,
+}
+
+impl CsvColumns {
+	/// The header line naming each column.
+	fn header(&self) -> String {
+		let mut header = self.sensor_names
+			.iter()
+			.chain(self.valve_names.iter())
+			.fold("timestamp".to_owned(), |header, name| header + "," + name);
+
+		if self.notes {
+			header += ",notes";
+		}
+
+		header + "\n"
+	}
+
+	/// Writes the row of a snapshot, along with the notes which fall on it.
+	fn write_row(&self, content: &mut String, timestamp: f64, state: &VehicleState, row_notes: Option<&str>, units: UnitSystem) {
+		// first column is the timestamp
+		*content += &timestamp.to_string();
+
+		for name in &self.sensor_names {
+			let reading = state.sensor_readings.get(name);
+			*content += ",";
+
+			// currently, if there is no data here, the column is empty.
+			// we may want to change this.
+			if let Some(reading) = reading {
+				if units == UnitSystem::Native {
+					*content += &reading.to_string();
+				} else {
+					*content += &units.format(reading);
+				}
+			}
+		}
+
+		for name in &self.valve_names {
+			let valve_state = state.valve_states.get(name);
+			*content += ",";
+
+			// see comment in sensor readings above.
+			if let Some(valve_state) = valve_state {
+				*content += &valve_state.actual.to_string();
+			}
+		}
+
+		if self.notes {
+			*content += ",";
+
+			// notes are free text, so they are quoted in case of commas
+			if let Some(row_notes) = row_notes {
+				*content += &format!("\"{}\"", row_notes.replace('"', "\"\"").replace('\n', " "));
+			}
+		}
+
+		*content += "\n";
+	}
+}
+
+/// Adds the name of every sensor and valve in some snapshots to those already seen.
+fn collect_channel_names(sensor_names: &mut HashSet<String>, valve_names: &mut HashSet<String>, vehicle_states: &[(f64, VehicleState)]) {
+	for (_, state) in vehicle_states {
+		for name in state.sensor_readings.keys() {
+			// yes, a HashSet will not allow duplicate items even with a plain
+			// insert, but the .clone() incurs a notable performance penalty,
+			// and if it was just .insert(name.clone()) here, then it would clone
+			// name every time despite the fact that it will rarely actually
+			// need to be inserted. the same applies for valve_states.
+			if !sensor_names.contains(name) {
+				sensor_names.insert(name.clone());
+			}
+		}
+
+		for name in state.valve_states.keys() {
+			if !valve_names.contains(name) {
+				valve_names.insert(name.clone());
+			}
+		}
+	}
+}
+
+/// The number of snapshots read from the database at a time while streaming an export.
+const EXPORT_CHUNK_SNAPSHOTS: usize = 1000;
+
+/// The formats which are streamed as they are written. Others, such as HDF5,
+/// are written whole before being sent.
+const STREAMED_FORMATS: [&str; 2] = ["csv", "influx"];
+
+/// An export streamed a chunk of snapshots at a time, so that exporting a long
+/// range never holds every snapshot in it, nor the whole export, in memory.
+struct ExportStream {
+	storage: Arc<dyn Storage>,
+	format: String,
+	from: f64,
+	to: f64,
+
+	/// The key of the last snapshot read, after which the next chunk starts.
+	after: Option<SnapshotKey>,
+
+	downsampler: Option<Downsampler>,
+	channels: Selection,
+	units: UnitSystem,
+
+	/// The columns of a CSV export, found before any row is written.
+	columns: CsvColumns,
+
+	/// The notes which have yet to be written, since they fall after every snapshot written so far.
+	notes: Vec<Note>,
+
+	/// Whether the header, if the format has one, has been written.
+	started: bool,
+
+	/// The times of the first and last snapshots, found along with the columns of a CSV export.
+	bounds: Option<(f64, f64)>,
+
+	/// Analyzes the quality of the snapshots as they are written, such as for the report of an archive.
+	analyzer: Option<report::Analyzer>,
+}
+
+impl ExportStream {
+	/// Reads the next chunk of snapshots, filtered and downsampled as requested,
+	/// or `None` once every snapshot in the range has been read.
+	async fn next_chunk(&mut self) -> anyhow::Result<Option<Vec<(f64, VehicleState)>>> {
+		let range = SnapshotRange {
+			from: Some(self.from),
+			to: Some(self.to),
+			after: self.after,
+			limit: Some(EXPORT_CHUNK_SNAPSHOTS),
+		};
+
+		let chunk = self.storage.keyed_snapshots(range).await?;
+		let last = chunk.last().map(|&(key, _)| key);
+		let mut chunk = chunk.into_iter().map(|(key, state)| (key.recorded_at, state)).collect::<Vec<_>>();
+
+		match last {
+			Some(last) => {
+				self.after = Some(last);
+				export::retain_channels(&mut chunk, &self.channels);
+
+				if let Some(downsampler) = &mut self.downsampler {
+					downsampler.downsample_chunk(&mut chunk);
+				}
+			},
+			// the last interval is only complete once every snapshot has been read
+			None => match self.downsampler.as_mut().and_then(Downsampler::finish) {
+				Some(last) => chunk.push(last),
+				None => return Ok(None),
+			},
+		}
+
+		Ok(Some(chunk))
+	}
+
+	/// Reads through every snapshot once to find the columns of a CSV export,
+	/// then starts again from the first.
+	async fn find_columns(&mut self) -> anyhow::Result<()> {
+		let (after, downsampler) = (self.after, self.downsampler.clone());
+		let mut sensor_names = HashSet::new();
+		let mut valve_names = HashSet::new();
+		self.bounds = None;
+
+		while let Some(chunk) = self.next_chunk().await? {
+			collect_channel_names(&mut sensor_names, &mut valve_names, &chunk);
+
+			if let (Some(first), Some(last)) = (chunk.first(), chunk.last()) {
+				self.bounds = Some((self.bounds.map_or(first.0, |bounds| bounds.0), last.0));
+			}
+		}
+
+		self.columns.sensor_names = sensor_names.into_iter().collect();
+		self.columns.valve_names = valve_names.into_iter().collect();
+		(self.after, self.downsampler) = (after, downsampler);
+
+		Ok(())
+	}
+
+	/// Writes the next part of the export, or `None` once all of it has been written.
+	async fn next_content(&mut self) -> anyhow::Result<Option<String>> {
+		let mut content = String::new();
+
+		if !self.started {
+			self.started = true;
+
+			if self.format == "csv" {
+				content = self.columns.header();
+			}
+		}
+
+		while content.is_empty() {
+			let Some(chunk) = self.next_chunk().await? else {
+				// influx notes are written after every snapshot, as in a whole export
+				if self.format == "influx" {
+					for note in self.notes.drain(..) {
+						influx::write_note(&mut content, &note);
+					}
+				}
+
+				return Ok((!content.is_empty()).then_some(content));
+			};
+
+			if let Some(analyzer) = &mut self.analyzer {
+				analyzer.add(&chunk);
+			}
+
+			match self.format.as_str() {
+				"csv" => {
+					let timestamps = chunk.iter().map(|(timestamp, _)| *timestamp).collect::<Vec<_>>();
+
+					// notes after the last row of this chunk may fall on a row of the next
+					let (notes, later) = match timestamps.last() {
+						Some(&last) => std::mem::take(&mut self.notes).into_iter().partition(|note| note.created_at <= last),
+						None => (Vec::new(), std::mem::take(&mut self.notes)),
+					};
+
+					self.notes = later;
+
+					for ((timestamp, state), row_notes) in chunk.iter().zip(note::align(&notes, &timestamps)) {
+						self.columns.write_row(&mut content, *timestamp, state, row_notes.as_deref(), self.units);
+					}
+				},
+				_ => {
+					for (timestamp, state) in &chunk {
+						influx::write_lines(&mut content, state, *timestamp);
+					}
+				},
+			}
+		}
+
+		Ok(Some(content))
+	}
+}
+
 /// Route function which exports all vehicle data from the database into a specified format.
+///
+/// CSV and Influx exports are streamed as a chunked response, reading the
+/// snapshots a chunk at a time, so that long ranges may be exported without
+/// holding them all in memory. When several formats are requested, snapshots
+/// are read and decoded once and each format is written from them, returned
+/// together as a zip archive.
 pub async fn export(
 	State(shared): State<Shared>,
-	Json(request): Json<ExportRequest>,
-) -> server::Result<impl IntoResponse> {
+	Valid(Json(request)): Valid<Json<ExportRequest>>,
+) -> server::Result<Response> {
 	let database = shared.database
 		.connection
 		.lock()
 		.await;
 
-	let vehicle_states = database
-		.prepare("SELECT recorded_at, vehicle_state FROM VehicleSnapshots WHERE recorded_at >= ?1 AND recorded_at <= ?2")
-		.map_err(internal)?
-		.query_map([request.from, request.to], |row| {
-			let vehicle_state = postcard::from_bytes::<VehicleState>(&row.get::<_, Vec<u8>>(1)?)
-				.map_err(|error| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Blob, Box::new(error)))?;
+	let preset = match &request.preset {
+		Some(name) => export::load_preset(&database, name)
+			.map_err(internal)?
+			.ok_or_else(|| not_found(format!("export preset '{name}' does not exist")))?,
+		None => ExportPreset::default(),
+	};
+
+	let formats = if request.formats.is_empty() {
+		let format = request.format
+			.or(preset.format)
+			.ok_or_else(|| bad_request("export format must be given, either directly or by a preset"))?;
+
+		vec![format]
+	} else {
+		request.formats
+	};
+
+	let channels = Selection::parse(request.channels.unwrap_or(preset.channels)).map_err(bad_request)?;
+	let downsample_hz = request.downsample_hz.or(preset.downsample_hz);
+	let units = request.units.unwrap_or(preset.units);
+
+	for format in &formats {
+		export::check_format(format).map_err(bad_request)?;
+
+		// influx lines are tagged with the unit each reading was reported in
+		if format == "influx" && units != UnitSystem::Native {
+			return Err(bad_request("influx exports are always in the units readings were reported in"));
+		}
+	}
 
-			Ok((row.get::<_, f64>(0)?, vehicle_state))
-		})
-		.and_then(|iter| iter.collect::<Result<Vec<_>, rusqlite::Error>>())
+	if let Some(rate) = downsample_hz {
+		export::check_rate(rate).map_err(bad_request)?;
+	}
+
+	let notes = note::list(&database, request.from, request.to).map_err(internal)?;
+	drop(database);
+
+	if let [format] = formats.as_slice() {
+		if STREAMED_FORMATS.contains(&format.as_str()) {
+			let mut stream = ExportStream {
+				storage: shared.storage.clone(),
+				format: format.clone(),
+				from: request.from,
+				to: request.to,
+				after: None,
+				downsampler: downsample_hz.map(Downsampler::new),
+				channels,
+				units,
+				columns: CsvColumns { notes: !notes.is_empty(), ..CsvColumns::default() },
+				notes,
+				started: false,
+				bounds: None,
+				analyzer: None,
+			};
+
+			if format == "csv" {
+				stream.find_columns().await.map_err(internal)?;
+			}
+
+			let content_type = if format == "csv" { "text/csv; charset=utf-8" } else { "text/plain; charset=utf-8" };
+
+			let body = Body::from_stream(futures_util::stream::try_unfold(stream, |mut stream| async move {
+				match stream.next_content().await {
+					Ok(content) => Ok(content.map(|content| (content, stream))),
+					Err(error) => {
+						warn!("Failed to stream an export: {error}");
+						Err(error)
+					},
+				}
+			}));
+
+			return Ok(([(header::CONTENT_TYPE, content_type)], body).into_response());
+		}
+	}
+
+	let mut vehicle_states = shared.storage
+		.snapshots(SnapshotRange::between(request.from, request.to))
+		.await
 		.map_err(internal)?;
 
-	match request.format.as_str() {
+	export::retain_channels(&mut vehicle_states, &channels);
+
+	if let Some(rate) = downsample_hz {
+		export::downsample(&mut vehicle_states, rate);
+	}
+
+	let config = shared.config.current();
+	let channels = &config.channels;
+
+	if let [format] = formats.as_slice() {
+		let (content_type, content) = render_export(format, &vehicle_states, &notes, units, channels).await?;
+		return Ok(([(header::CONTENT_TYPE, content_type)], content).into_response());
+	}
+
+	let mut files = Vec::with_capacity(formats.len());
+
+	for format in &formats {
+		let (_, content) = render_export(format, &vehicle_states, &notes, units, channels).await?;
+		files.push((format!("export.{}", export::extension(format)), content));
+	}
+
+	let archive = export::archive(&files).map_err(internal)?;
+	Ok(([(header::CONTENT_TYPE, "application/zip")], archive).into_response())
+}
+
+/// Writes snapshots in one export format, returning the content type of the
+/// format along with the written content.
+async fn render_export(
+	format: &str,
+	vehicle_states: &[(f64, VehicleState)],
+	notes: &[Note],
+	units: UnitSystem,
+	#[cfg_attr(not(feature = "hdf5"), allow(unused_variables))]
+	channels: &BTreeMap<String, ChannelConfig>,
+) -> server::Result<(&'static str, Vec<u8>)> {
+	match format {
 		"csv" => {
 			let mut sensor_names = HashSet::new();
 			let mut valve_names = HashSet::new();
-
-			for (_, state) in &vehicle_states {
-				for name in state.sensor_readings.keys() {
-					// yes, a HashSet will not allow duplicate items even with a plain
-					// insert, but the .clone() incurs a notable performance penalty,
-					// and if it was just .insert(name.clone()) here, then it would clone
-					// name every time despite the fact that it will rarely actually
-					// need to be inserted. the same applies for valve_states.
-					if !sensor_names.contains(name) {
-						sensor_names.insert(name.clone());
-					}
-				}
-
-				for name in state.valve_states.keys() {
-					if !valve_names.contains(name) {
-						valve_names.insert(name.clone());
-					}
-				}
-			}
+			collect_channel_names(&mut sensor_names, &mut valve_names, vehicle_states);
 
 			let sensor_names = sensor_names
 				.into_iter()
@@ -195,44 +742,24 @@ pub async fn export(
 				.into_iter()
 				.collect::<Vec<_>>();
 
-			let header = sensor_names
-				.iter()
-				.chain(valve_names.iter())
-				.fold("timestamp".to_owned(), |header, name| header + "," + name);
+			let content = make_csv(&sensor_names, &valve_names, vehicle_states, notes, units);
 
-			let mut content = header + "\n";
+			Ok(("text/csv; charset=utf-8", content.into_bytes()))
+		},
+		"influx" => {
+			let mut content = String::new();
 
 			for (timestamp, state) in vehicle_states {
-				// first column is the timestamp
-				content += &timestamp.to_string();
-
-				for name in &sensor_names {
-					let reading = state.sensor_readings.get(name);
-					content += ",";
-
-					// currently, if there is no data here, the column is empty.
-					// we may want to change this.
-					if let Some(reading) = reading {
-						content += &reading.to_string();
-					}
-				}
-
-				for name in &valve_names {
-					let valve_state = state.valve_states.get(name);
-					content += ",";
-
-					// see comment in sensor readings above.
-					if let Some(valve_state) = valve_state {
-						content += &valve_state.actual.to_string();
-					}
-				}
+				influx::write_lines(&mut content, state, *timestamp);
+			}
 
-				content += "\n";
+			for note in notes {
+				influx::write_note(&mut content, note);
 			}
 
-			let headers = [(header::CONTENT_TYPE, "text/csv; charset=utf-8")];
-			Ok((headers, content.into_response()))
+			Ok(("text/plain; charset=utf-8", content.into_bytes()))
 		},
+		#[cfg(feature = "hdf5")]
 		"hdf5" => {
 			// Generally a modified version of the csv export section
 			
@@ -240,7 +767,7 @@ pub async fn export(
 			let mut sensor_names = HashSet::new();
 			let mut valve_names = HashSet::new();
 
-			for (_, state) in &vehicle_states {
+			for (_, state) in vehicle_states {
 				for name in state.sensor_readings.keys() {
 					// yes, a HashSet will not allow duplicate items even with a plain
 					// insert, but the .clone() incurs a notable performance penalty,
@@ -294,7 +821,7 @@ pub async fn export(
 			// Prob can convert to just being str code. Will check later.
 			let path = servo_dir.join((String::from("ExportFile") + &file_index + &String::from(".hdf5")).as_str());
 
-			make_hdf5_file(&sensor_names, &valve_names, &vehicle_states, &path)
+			make_hdf5_file(&sensor_names, &valve_names, vehicle_states, channels, notes, units, &path)
 				.map_err(internal)?;
 
 			let content = fs::read(&path).await
@@ -305,41 +832,876 @@ pub async fn export(
 				warn!("Failed to remove temporary HDF5 file at {path:?}: {error}");
 			}
 
-			let headers = [(header::CONTENT_TYPE, "application/x-hdf")];
-			Ok((headers, content.into_response()))
+			Ok(("application/x-hdf", content))
 		},
 		_ => Err(bad_request("invalid export format")),
 	}
 }
 
+/// Query parameters for track requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrackQuery {
+	/// The earliest time of a fix to include, as a Unix timestamp.
+	from: Option<f64>,
+
+	/// The latest time of a fix to include, as a Unix timestamp.
+	to: Option<f64>,
+
+	/// The GPS receiver to include fixes from, or every receiver if absent.
+	receiver: Option<String>,
+}
+
+impl Validate for TrackQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which returns the logged position fixes within a time range as GeoJSON.
+///
+/// The response is a `FeatureCollection` with one feature per receiver: a
+/// `LineString` through its fixes in order, or a `Point` if it only has one.
+/// Each feature's properties list the timestamp and fix quality of every fix.
+pub async fn get_track(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<TrackQuery>>,
+) -> server::Result<impl IntoResponse> {
+	let database = shared.database.connection.lock().await;
+
+	let fixes = database
+		.prepare("
+			SELECT receiver, recorded_at, latitude, longitude, altitude, fix_quality
+			FROM PositionFixes
+			WHERE (?1 IS NULL OR recorded_at >= ?1)
+				AND (?2 IS NULL OR recorded_at <= ?2)
+				AND (?3 IS NULL OR receiver = ?3)
+			ORDER BY recorded_at
+		")
+		.map_err(internal)?
+		.query_map(params![query.from, query.to, query.receiver], |row| {
+			let fix = PositionFix {
+				latitude: row.get(2)?,
+				longitude: row.get(3)?,
+				altitude: row.get(4)?,
+				fix_quality: row.get(5)?,
+			};
+
+			Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, fix))
+		})
+		.and_then(|iter| iter.collect::<Result<Vec<_>, rusqlite::Error>>())
+		.map_err(internal)?;
+
+	drop(database);
+
+	let mut tracks = BTreeMap::<String, Vec<(f64, PositionFix)>>::new();
+
+	for (receiver, recorded_at, fix) in fixes {
+		tracks.entry(receiver).or_default().push((recorded_at, fix));
+	}
+
+	let features = tracks
+		.into_iter()
+		.map(|(receiver, fixes)| {
+			// GeoJSON positions are longitude first
+			let mut coordinates = fixes
+				.iter()
+				.map(|(_, fix)| match fix.altitude {
+					Some(altitude) => json!([fix.longitude, fix.latitude, altitude]),
+					None => json!([fix.longitude, fix.latitude]),
+				})
+				.collect::<Vec<_>>();
+
+			let geometry = if coordinates.len() == 1 {
+				json!({ "type": "Point", "coordinates": coordinates.remove(0) })
+			} else {
+				json!({ "type": "LineString", "coordinates": coordinates })
+			};
+
+			json!({
+				"type": "Feature",
+				"geometry": geometry,
+				"properties": {
+					"receiver": receiver,
+					"timestamps": fixes.iter().map(|(recorded_at, _)| *recorded_at).collect::<Vec<_>>(),
+					"fix_quality": fixes.iter().map(|(_, fix)| fix.fix_quality).collect::<Vec<_>>(),
+				},
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let headers = [(header::CONTENT_TYPE, "application/geo+json")];
+	Ok((headers, Json(json!({ "type": "FeatureCollection", "features": features }))))
+}
+
+/// Route function which lists every channel which has been reported or configured,
+/// along with the display name and group it is presented under and the valve
+/// each voltage or current reading belongs to, or responds with `304 Not
+/// Modified` if the client's copy is current.
+pub async fn get_channels(State(shared): State<Shared>, preconditions: Preconditions) -> Response {
+	let vehicle = shared.vehicle.0.lock().await.clone();
+	let config = shared.config.current();
+	let catalog = channel::catalog(&config.channels, &config.valve_readings, &vehicle);
+
+	shared.versions.respond("channels", &preconditions, &catalog)
+}
+
+/// Query parameters for capture window requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CapturesQuery {
+	/// The earliest time a window may end to be included, as a Unix timestamp.
+	from: Option<f64>,
+
+	/// The latest time a window may start to be included, as a Unix timestamp.
+	to: Option<f64>,
+
+	/// The capture rule to include windows of, or every rule if absent.
+	rule: Option<String>,
+}
+
+impl Validate for CapturesQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which lists the capture windows overlapping a time range.
+pub async fn get_captures(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<CapturesQuery>>,
+) -> server::Result<Json<Vec<CaptureWindow>>> {
+	let windows = capture::list(
+		&*shared.database.connection.lock().await,
+		query.from,
+		query.to,
+		query.rule.as_deref(),
+	)
+	.map_err(internal)?;
+
+	Ok(Json(windows))
+}
+
+/// Query parameters for bad frame requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BadFramesQuery {
+	/// The number of most recent bad frames to include, which defaults to 20.
+	limit: Option<usize>,
+}
+
+impl Validate for BadFramesQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.check(self.limit != Some(0), "limit", "must be at least 1");
+	}
+}
+
+/// Route function which lists the most recent vehicle state frames which could not be deserialized, newest first.
+pub async fn get_bad_frames(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<BadFramesQuery>>,
+) -> server::Result<Json<Vec<BadFrame>>> {
+	let frames = quarantine::recent(&*shared.database.connection.lock().await, query.limit.unwrap_or(20))
+		.map_err(internal)?;
+
+	Ok(Json(frames))
+}
+
+/// The number of snapshots read from the database at a time during a backfill.
+const BACKFILL_CHUNK_SNAPSHOTS: usize = 1000;
+
+impl Validate for BackfillRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which pushes the logged vehicle snapshots within a time range
+/// to the configured time-series database in Influx line protocol.
+///
+/// Snapshots are read in chunks so that the database is not held for the whole
+/// backfill, and are written in batches of the configured size.
+pub async fn backfill_influx(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<BackfillRequest>>,
+) -> server::Result<Json<BackfillResponse>> {
+	let config = shared.config.current();
+	let config = &config.influx;
+
+	if config.url.is_none() {
+		return Err(bad_request("no Influx write endpoint is configured"));
+	}
+
+	let client = reqwest::Client::new();
+	let mut response = BackfillResponse::default();
+	let mut after = None;
+
+	loop {
+		let range = SnapshotRange {
+			from: request.from,
+			to: request.to,
+			after,
+			limit: Some(BACKFILL_CHUNK_SNAPSHOTS),
+		};
+
+		let chunk = shared.storage.keyed_snapshots(range).await.map_err(internal)?;
+
+		let Some(&(last, _)) = chunk.last() else {
+			break;
+		};
+
+		after = Some(last);
+
+		let mut lines = String::new();
+		let mut batch_lines = 0;
+
+		for (key, state) in &chunk {
+			batch_lines += influx::write_lines(&mut lines, state, key.recorded_at);
+
+			if batch_lines >= config.batch_lines {
+				influx::write(&client, config, std::mem::take(&mut lines)).await
+					.map_err(|error| internal(format!("failed to write to Influx: {error}")))?;
+
+				response.lines += batch_lines;
+				batch_lines = 0;
+			}
+		}
+
+		if batch_lines > 0 {
+			influx::write(&client, config, lines).await
+				.map_err(|error| internal(format!("failed to write to Influx: {error}")))?;
+
+			response.lines += batch_lines;
+		}
+
+		response.snapshots += chunk.len();
+	}
+
+	Ok(Json(response))
+}
+
+/// Query parameters for statistics requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatsQuery {
+	/// The sensor channel to compute statistics of.
+	channel: String,
+
+	/// The earliest time of a reading to include, as a Unix timestamp.
+	from: Option<f64>,
+
+	/// The latest time of a reading to include, as a Unix timestamp.
+	to: Option<f64>,
+}
+
+impl Validate for StatsQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("channel", &self.channel);
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which computes statistics of a sensor channel from the logged
+/// vehicle snapshots within a time range.
+pub async fn get_stats(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<StatsQuery>>,
+) -> server::Result<Json<StatsResponse>> {
+	let range = SnapshotRange { from: query.from, to: query.to, ..SnapshotRange::default() };
+
+	let readings = shared.storage
+		.snapshots(range)
+		.await
+		.map_err(internal)?
+		.into_iter()
+		.map(|(_, vehicle_state)| {
+			vehicle_state.sensor_readings.get(&query.channel).map(|reading| (reading.value, reading.unit))
+		});
+
+	let readings = readings.flatten().collect::<Vec<_>>();
+
+	let Some(&(_, unit)) = readings.last() else {
+		return Err(not_found(format!("no readings of '{}' in the given time range", query.channel)));
+	};
+
+	let statistics = Statistics::compute(readings.into_iter().map(|(value, _)| value).collect())
+		.ok_or_else(|| not_found(format!("no finite readings of '{}' in the given time range", query.channel)))?;
+
+	Ok(Json(StatsResponse { channel: query.channel, unit, statistics }))
+}
+
+/// Query parameters for data quality report requests.
+///
+/// The range is either the lifetime of a session, from login to its last
+/// request, or given directly, defaulting to all logged data.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReportQuery {
+	/// The ID of the session whose lifetime the report covers.
+	session: Option<i64>,
+
+	/// The start of the range, as a Unix timestamp.
+	from: Option<f64>,
+
+	/// The end of the range, as a Unix timestamp.
+	to: Option<f64>,
+}
+
+impl Validate for ReportQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which generates a data quality report over a time range:
+/// each channel's coverage, dropouts, and range of readings, along with the
+/// telemetry gaps, alerts, and sequences which fell within it.
+pub async fn get_report(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<ReportQuery>>,
+) -> server::Result<Json<QualityReport>> {
+	let (from, to) = report_range(&shared, &query).await?;
+
+	let snapshots = shared.storage
+		.snapshots(SnapshotRange::between(from, to))
+		.await
+		.map_err(internal)?;
+
+	let analysis = report::analyze(&snapshots, from, to);
+
+	let report = report::generate(&*shared.database.connection.lock().await, analysis, from, to)
+		.map_err(internal)?;
+
+	Ok(Json(report))
+}
+
+/// Route function which assembles the run sheet of a session or time range:
+/// every sequence run, command sent, override, alert, and note in order.
+///
+/// The range is given as for data quality reports.
+pub async fn get_run_sheet(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<ReportQuery>>,
+) -> server::Result<Json<RunSheet>> {
+	let (from, to) = report_range(&shared, &query).await?;
+
+	let sheet = report::run_sheet(&*shared.database.connection.lock().await, from, to)
+		.map_err(internal)?;
+
+	Ok(Json(sheet))
+}
+
+/// Resolves the time range covered by a report or archive, which is either the
+/// lifetime of a session or given directly.
+async fn report_range(shared: &Shared, query: &ReportQuery) -> server::Result<(f64, f64)> {
+	let (from, to) = match query.session {
+		Some(session_id) => {
+			if query.from.is_some() || query.to.is_some() {
+				return Err(bad_request("a report covers either a session or a time range, not both"));
+			}
+
+			shared.database
+				.connection
+				.lock()
+				.await
+				.query_row(
+					"SELECT created_at, last_active FROM Sessions WHERE session_id = ?1",
+					[session_id],
+					|row| Ok((row.get(0)?, row.get(1)?)),
+				)
+				.optional()
+				.map_err(internal)?
+				.ok_or_else(|| not_found(format!("no session with ID {session_id}")))?
+		},
+		None => (query.from.unwrap_or(0.0), query.to.unwrap_or_else(clock::now)),
+	};
+
+	if from > to {
+		return Err(bad_request("the start of the range must not be after its end"));
+	}
+
+	Ok((from, to))
+}
+
+/// The number of bytes of an archive's data read back from disk at a time while it is streamed.
+const ARCHIVE_READ_BYTES: usize = 64 * 1024;
+
+/// Distinguishes the data spooled by archives made at the same time.
+static ARCHIVE_FILE_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary file, which is removed when dropped.
+struct TemporaryFile(PathBuf);
+
+impl Drop for TemporaryFile {
+	fn drop(&mut self) {
+		if let Err(error) = std::fs::remove_file(&self.0) {
+			warn!("Failed to remove temporary file at {}: {error}", self.0.display());
+		}
+	}
+}
+
+/// An archive compressed as it is streamed, with its data read back from where it was spooled.
+struct ArchiveStream {
+	/// The archive, which is taken once it has been finished.
+	tarball: Option<archive::Tarball>,
+
+	/// The spooled CSV data, whose header has already been written to the archive.
+	data: tokio::fs::File,
+	data_size: u64,
+
+	/// The files which follow the data, as pairs of names and contents.
+	files: Vec<(String, Vec<u8>)>,
+
+	/// The file the data was spooled to, which is removed once the archive is sent or abandoned.
+	_spooled: TemporaryFile,
+}
+
+impl ArchiveStream {
+	/// Compresses the next part of the archive, or `None` once all of it has been sent.
+	async fn next_part(&mut self) -> io::Result<Option<Vec<u8>>> {
+		let Some(mut tarball) = self.tarball.take() else {
+			return Ok(None);
+		};
+
+		let mut buffer = vec![0; ARCHIVE_READ_BYTES];
+
+		loop {
+			let read = self.data.read(&mut buffer).await?;
+
+			if read == 0 {
+				break;
+			}
+
+			tarball.write(&buffer[..read])?;
+			let compressed = tarball.take();
+
+			// the compressor holds on to what it is given until it has enough to compress
+			if !compressed.is_empty() {
+				self.tarball = Some(tarball);
+				return Ok(Some(compressed));
+			}
+		}
+
+		tarball.end_file(self.data_size)?;
+
+		for (name, content) in &self.files {
+			tarball.add_file(name, content)?;
+		}
+
+		let mut compressed = tarball.take();
+		compressed.extend(tarball.finish()?);
+		Ok(Some(compressed))
+	}
+}
+
+/// Route function which packs the complete record of a session or time range
+/// into a gzipped tarball for review after a test: the data as CSV, the
+/// configuration and mappings, the sequences run exactly as they were sent, the
+/// audit log, alerts, and shift log notes, and the versions of the software involved.
+///
+/// The range is given as for data quality reports, except that a time range
+/// must have a start. The data is read a chunk at a time and spooled to disk,
+/// since its size must be known before it is written into the archive, and
+/// the archive is compressed as it is streamed.
+pub async fn get_archive(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<ReportQuery>>,
+) -> server::Result<Response> {
+	// otherwise an archive would read through every snapshot ever logged
+	if query.session.is_none() && query.from.is_none() {
+		return Err(bad_request("an archive covers either a session or a time range with a start"));
+	}
+
+	let (from, to) = report_range(&shared, &query).await?;
+
+	let flight_info = shared.flight.0
+		.lock()
+		.await
+		.as_ref()
+		.and_then(|flight| flight.info());
+
+	let database = shared.database.connection.lock().await;
+	let notes = note::list(&database, from, to).map_err(internal)?;
+	let events = audit::list(&database, from, to).map_err(internal)?;
+	let runs = archive::runs(&database, from, to).map_err(internal)?;
+	let mappings = super::load_mappings(&database).map_err(internal)?;
+	let versions = archive::versions(&database, flight_info).map_err(internal)?;
+	drop(database);
+
+	let mut stream = ExportStream {
+		storage: shared.storage.clone(),
+		format: "csv".to_owned(),
+		from,
+		to,
+		after: None,
+		downsampler: None,
+		channels: Selection::default(),
+		units: UnitSystem::Native,
+		columns: CsvColumns { notes: !notes.is_empty(), ..CsvColumns::default() },
+		notes: notes.clone(),
+		started: false,
+		bounds: None,
+		analyzer: None,
+	};
+
+	stream.find_columns().await.map_err(internal)?;
+
+	// thumbnails span only the part of the range which has data
+	let (first, last) = stream.bounds.unwrap_or((from, to));
+	stream.analyzer = Some(report::Analyzer::new(first, last));
+
+	let spooled = TemporaryFile(env::temp_dir().join(format!(
+		"servo-archive-{}-{}.csv",
+		process::id(),
+		ARCHIVE_FILE_INDEX.fetch_add(1, Ordering::Relaxed),
+	)));
+
+	let mut file = tokio::fs::File::create(&spooled.0).await.map_err(internal)?;
+	let mut data_size = 0;
+
+	while let Some(content) = stream.next_content().await.map_err(internal)? {
+		file.write_all(content.as_bytes()).await.map_err(internal)?;
+		data_size += content.len() as u64;
+	}
+
+	file.flush().await.map_err(internal)?;
+	drop(file);
+
+	let analysis = stream.analyzer
+		.take()
+		.unwrap_or_else(|| report::Analyzer::new(from, to))
+		.finish();
+
+	let report = report::generate(&*shared.database.connection.lock().await, analysis, from, to)
+		.map_err(internal)?;
+
+	fn json(value: &impl Serialize) -> server::Result<Vec<u8>> {
+		serde_json::to_vec_pretty(value).map_err(internal)
+	}
+
+	// credentials are left out, as archives are passed around for review
+	let config = shared.config.current().redacted();
+
+	let mut files = vec![
+		("config.toml".to_owned(), toml::to_string(&config).map_err(internal)?.into_bytes()),
+		("mappings.json".to_owned(), json(&mappings)?),
+		("sequences.json".to_owned(), json(&runs)?),
+		("events.json".to_owned(), json(&events)?),
+		("alerts.json".to_owned(), json(&report.alerts)?),
+		("report.json".to_owned(), json(&report)?),
+		("notes.json".to_owned(), json(&notes)?),
+	];
+
+	for (index, run) in runs.iter().enumerate() {
+		files.push((archive::script_path(index, run), run.script.clone().into_bytes()));
+	}
+
+	let created_at = clock::now();
+
+	let manifest = ArchiveManifest {
+		session_id: query.session,
+		from,
+		to,
+		created_at,
+		versions,
+		files: ["data.csv".to_owned()]
+			.into_iter()
+			.chain(files.iter().map(|(name, _)| name.clone()))
+			.collect(),
+	};
+
+	let mut tarball = archive::Tarball::new(created_at);
+	tarball.add_file("manifest.json", &json(&manifest)?).map_err(internal)?;
+	tarball.start_file("data.csv", data_size).map_err(internal)?;
+
+	let archive = ArchiveStream {
+		tarball: Some(tarball),
+		data: tokio::fs::File::open(&spooled.0).await.map_err(internal)?,
+		data_size,
+		files,
+		_spooled: spooled,
+	};
+
+	let body = Body::from_stream(futures_util::stream::try_unfold(archive, |mut archive| async move {
+		match archive.next_part().await {
+			Ok(part) => Ok(part.map(|part| (part, archive))),
+			Err(error) => {
+				warn!("Failed to stream an archive: {error}");
+				Err(error)
+			},
+		}
+	}));
+
+	Ok(([(header::CONTENT_TYPE, "application/gzip")], body).into_response())
+}
+
+/// Query parameters for vehicle state requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StateQuery {
+	/// A comma-separated list of the selectors of the channels to include, such
+	/// as `FU_*,KBPT`, or every channel if absent.
+	channels: Option<String>,
+}
+
+impl Validate for StateQuery {
+	fn validate(&self, validator: &mut Validator) {
+		if let Some(channels) = &self.channels {
+			validator.selectors("channels", &selector::split(channels));
+		}
+	}
+}
+
+/// Route function which returns the latest vehicle state, for consumers which
+/// need a single reading rather than a forwarding stream.
+///
+/// Requested channels which have not been reported are omitted rather than rejected.
+pub async fn get_vehicle_state(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<StateQuery>>,
+) -> server::Result<Json<VehicleState>> {
+	let snapshot = shared.vehicle.0
+		.lock()
+		.await
+		.clone();
+
+	let mut state = Arc::unwrap_or_clone(snapshot);
+
+	if let Some(channels) = query.channels {
+		Selection::parse_list(&channels)
+			.map_err(bad_request)?
+			.retain(&mut state);
+	}
+
+	Ok(Json(state))
+}
+
+/// Route function which returns the current difference between each configured
+/// pair of channels, and whether it exceeds the pair's tolerance.
+pub async fn get_pairs(State(shared): State<Shared>) -> server::Result<Json<Vec<PairReading>>> {
+	let vehicle = shared.vehicle.0
+		.lock()
+		.await
+		.clone();
+
+	let readings = shared.config
+		.current()
+		.pairs
+		.iter()
+		.map(|pair| pair::read(pair, &vehicle))
+		.collect();
+
+	Ok(Json(readings))
+}
+
+/// Creates the interval at which vehicle state is forwarded to a client.
+fn forwarding_interval(rate_hz: f64) -> Interval {
+	// an invalid rate is rejected when the configuration is reloaded, but may still be loaded at startup
+	let period = Duration::try_from_secs_f64(1.0 / rate_hz)
+		.unwrap_or(Duration::from_millis(100))
+		.max(Duration::from_millis(1));
+
+	let mut interval = tokio::time::interval(period);
+	interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+	interval
+}
+
+/// Query parameters for forwarding requests.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ForwardQuery {
+	/// Whether to send the display names and groups of channels before any vehicle
+	/// state, and again whenever they change, as `{"channels": {...}}` messages.
+	metadata: bool,
+
+	/// The number of times per second to send vehicle state, which is capped at the
+	/// configured maximum, or the configured rate if absent.
+	rate_hz: Option<f64>,
+
+	/// A comma-separated list of the selectors of the channels to forward, such
+	/// as `FU_*,*_PT`, or every channel if absent.
+	channels: Option<String>,
+
+	/// Whether to send the mean of each sensor's readings since the last message
+	/// rather than its latest, with their minimum, maximum, and number under
+	/// `envelopes`, so that fast transients are not lost between messages.
+	aggregate: bool,
+
+	/// Whether to resume the last subscription of the same user and host,
+	/// taking any options which are not given from it, so that a client which
+	/// reconnects gets the same stream without asking for it again.
+	resume: bool,
+}
+
+impl Validate for ForwardQuery {
+	fn validate(&self, validator: &mut Validator) {
+		if let Some(rate_hz) = self.rate_hz {
+			validator.positive("rate_hz", rate_hz);
+		}
+
+		if let Some(channels) = &self.channels {
+			validator.selectors("channels", &selector::split(channels));
+		}
+	}
+}
+
+// The JSON of the latest snapshot forwarded to any client, so that each snapshot is serialized
+// once however many clients it is forwarded to, at whatever rates. The snapshot is held weakly
+// so that the cache does not force the next update to copy it.
+static FORWARDED_JSON: StdMutex<Option<(Weak<VehicleState>, String)>> = StdMutex::new(None);
+
+/// Serializes a vehicle state snapshot to be forwarded, reusing the JSON if it was already serialized.
+fn forwarded_json(snapshot: &Arc<VehicleState>) -> serde_json::Result<String> {
+	let mut cache = FORWARDED_JSON.lock().unwrap_or_else(PoisonError::into_inner);
+
+	if let Some((cached, json)) = &*cache {
+		if ptr::eq(cached.as_ptr(), Arc::as_ptr(snapshot)) {
+			return Ok(json.clone());
+		}
+	}
+
+	let json = serde_json::to_string(&**snapshot)?;
+	*cache = Some((Arc::downgrade(snapshot), json.clone()));
+
+	Ok(json)
+}
+
+/// Serializes the channel metadata message sent to forwarding clients which ask for it.
+fn channel_metadata(channels: &BTreeMap<String, ChannelConfig>) -> serde_json::Result<String> {
+	serde_json::to_string(&json!({ "channels": channels }))
+}
+
+/// Serializes only the selected channels of a vehicle state snapshot to be forwarded.
+fn retained_json(snapshot: &VehicleState, channels: &Selection) -> serde_json::Result<String> {
+	let mut state = snapshot.clone();
+	channels.retain(&mut state);
+	serde_json::to_string(&state)
+}
+
+/// Serializes the aggregate of the snapshots over a forwarding interval, keeping only the selected channels.
+fn aggregated_json(mut aggregated: AggregatedState, channels: Option<&Selection>) -> serde_json::Result<String> {
+	if let Some(channels) = channels {
+		aggregated.retain(channels);
+	}
+
+	serde_json::to_string(&aggregated)
+}
+
+/// Resolves what a forwarding client is subscribed to, taking what it left out
+/// from its last subscription if it asked to resume, and saves the result so
+/// that it may be resumed in turn.
+async fn resolve_subscription(shared: &Shared, username: Option<&str>, host: &str, query: ForwardQuery) -> Subscription {
+	let database = shared.database.connection.lock().await;
+
+	let previous = if query.resume {
+		subscription::load(&database, username, host).unwrap_or_else(|error| {
+			warn!("Failed to load the forwarding subscription of \x1b[1m{host}\x1b[0m: {error}");
+			None
+		})
+	} else {
+		None
+	};
+
+	let previous = previous.unwrap_or_default();
+
+	let channels = query.channels.as_deref().map(selector::split);
+
+	let subscription = Subscription {
+		channels: channels.or(previous.channels),
+		rate_hz: query.rate_hz.or(previous.rate_hz),
+		metadata: query.metadata || previous.metadata,
+		aggregate: query.aggregate || previous.aggregate,
+	};
+
+	if let Err(error) = subscription::save(&database, username, host, &subscription) {
+		warn!("Failed to save the forwarding subscription of \x1b[1m{host}\x1b[0m: {error}");
+	}
+
+	subscription
+}
+
 /// Route function which accepts a WebSocket connection and begins forwarding vehicle state data.
+///
+/// Each client's subscription is saved under the user it is logged in as and
+/// the host it connected from, so that it may be resumed after reconnecting.
 pub async fn forward_data(
 	ws: WebSocketUpgrade,
 	State(shared): State<Shared>,
+	session: Option<Session>,
 	ConnectInfo(peer): ConnectInfo<SocketAddr>,
+	Valid(Query(query)): Valid<Query<ForwardQuery>>,
 ) -> Response {
+	let username = session.as_ref().map(|session| session.username.as_str());
+	let subscription = resolve_subscription(&shared, username, &peer.ip().to_string(), query).await;
+
 	ws.on_upgrade(move |socket| async move {
 		let vehicle = shared.vehicle.clone();
+		let config = shared.config.clone();
+		let metrics = shared.metrics.clone();
 		let (mut writer, mut reader) = socket.split();
 
 		// spawn separate task for forwarding while the "main" task waits
 		// until it can abort this task when the user wants to close
 		let forwarding_handle = tokio::spawn(async move {
-			let (vehicle_state, _) = vehicle.as_ref();
+			let (vehicle_state, updated) = vehicle.as_ref();
+
+			// setup forwarding agent to send vehicle state at the requested or configured rate
+			// (10Hz by default), which is never more than the configured maximum
+			let mut rate_hz = config.current().forwarding.rate_hz(subscription.rate_hz);
+			let mut interval = forwarding_interval(rate_hz);
+
+			if let Some(requested_hz) = subscription.rate_hz.filter(|&requested_hz| requested_hz > rate_hz) {
+				warn!("Peer \x1b[1m{peer}\x1b[0m asked for vehicle state at {requested_hz} Hz, but is capped at {rate_hz} Hz.");
+			}
+
+			// no channel metadata has been sent yet, so the first comparison always differs
+			let mut sent_channels = None;
+			let mut last_frame = None;
+			let mut aggregator = subscription.aggregate.then(Aggregator::default);
 
-			// setup forwarding agent to send vehicle state every 100ms (10Hz)
-			let mut interval = tokio::time::interval(Duration::from_millis(100));
-			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			// selectors were validated when subscribed, so only a corrupted resumed subscription is rejected here
+			let retained = match subscription.channels.map(Selection::parse).transpose() {
+				Ok(retained) => retained,
+				Err(error) => {
+					warn!("Peer \x1b[1m{peer}\x1b[0m subscribed with invalid channels: {error}");
+					_ = writer.close().await;
+					return;
+				},
+			};
 
 			loop {
+				if subscription.metadata {
+					let channels = config.current().channels.clone();
+
+					if sent_channels.as_ref() != Some(&channels) {
+						let metadata = match channel_metadata(&channels) {
+							Ok(metadata) => metadata,
+							Err(error) => {
+								warn!("Failed to serialize channel metadata into JSON: {error}");
+								break;
+							},
+						};
+
+						if writer.send(ws::Message::Text(metadata)).await.is_err() {
+							warn!("Forwarding connection with peer \x1b[1m{}\x1b[0m severed.", peer);
+							_ = writer.close().await;
+							break;
+						}
+
+						sent_channels = Some(channels);
+					}
+				}
+
+				let frame = *metrics.latest_frame.lock().await;
 				let vehicle_state = vehicle_state
 					.lock()
 					.await
 					.clone();
 
 				// serialize vehicle state into JSON so it is easily digestible by the GUI.
-				// vehicle state comes in as postcard and gets reserialized here. overhead isn't bad.
-				let json = match serde_json::to_string(&vehicle_state) {
+				// vehicle state comes in as postcard and gets reserialized here, but only once per
+				// snapshot unless the client only subscribed to some channels.
+				let json = match (&mut aggregator, &retained) {
+					(Some(aggregator), retained) => aggregated_json(aggregator.finish(&vehicle_state), retained.as_ref()),
+					(None, Some(channels)) => retained_json(&vehicle_state, channels),
+					(None, None) => forwarded_json(&vehicle_state),
+				};
+
+				let json = match json {
 					Ok(json) => json,
 					Err(error) => {
 						warn!("Failed to serialize vehicle state into JSON: {error}");
@@ -347,7 +1709,7 @@ pub async fn forward_data(
 					},
 				};
 
-				// drop vehicle state before sending to prevent unecessarily holding lock
+				// drop the snapshot before sending so that it may be updated in place
 				drop(vehicle_state);
 
 				// attempt to forward vehicle state and break if connection is severed.
@@ -357,8 +1719,36 @@ pub async fn forward_data(
 					break;
 				}
 
-				// wait for 100ms to retransmit vehicle state
-				interval.tick().await;
+				// the same frame is sent on each tick until a new one arrives, but its latency is only counted once
+				if let Some(frame) = frame.filter(|frame| Some(frame.number) != last_frame) {
+					metrics.latency.lock().await.frame_to_forward.record(frame.received_at.elapsed());
+					last_frame = Some(frame.number);
+				}
+
+				// pick up a new rate or maximum if the configuration was reloaded
+				let configured_rate_hz = config.current().forwarding.rate_hz(subscription.rate_hz);
+
+				if configured_rate_hz != rate_hz {
+					rate_hz = configured_rate_hz;
+					interval = forwarding_interval(rate_hz);
+				}
+
+				// wait for the next tick to retransmit vehicle state, aggregating every
+				// snapshot in between if the client asked for them to be
+				match &mut aggregator {
+					Some(aggregator) => loop {
+						tokio::select! {
+							_ = interval.tick() => break,
+							_ = updated.notified() => {
+								let snapshot = vehicle.0.lock().await.clone();
+								aggregator.add(&snapshot);
+							},
+						}
+					},
+					None => {
+						interval.tick().await;
+					},
+				}
 			}
 		});
 
@@ -374,11 +1764,13 @@ pub async fn forward_data(
 #[cfg(test)]
 mod tests {
 	use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+	use crate::server::{snapshot, storage::SqliteStorage, validation, Database};
 	use rand::{Rng, RngCore};
 	use std::collections::HashMap;
 	use super::*;
 
   #[test]
+	#[cfg(feature = "hdf5")]
 	fn test_hdf5_file_creation() {
 		// Do the same test a few times just cause this does use RNG
 		for _ in 0..8 {
@@ -454,7 +1846,7 @@ mod tests {
 				time += 0.1;
 			}
 
-			make_hdf5_file(&sensor_names, &valve_names, &vehicle_states, path)
+			make_hdf5_file(&sensor_names, &valve_names, &vehicle_states, &BTreeMap::new(), &[], UnitSystem::Native, path)
 				.expect("HDF5 should not error out when making this basic dataset");
 
 			let file = hdf5::File::open(path).expect("File should exist after make_hdf5_file runs, as make_hdf5_file literally makes"); // 
@@ -514,4 +1906,146 @@ mod tests {
 			let _ = std::fs::remove_file(path).expect("You should be able to delete the HDF5 file after closing it ");
 		}
 	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn streamed_exports_match_whole_exports() {
+		let database = Database::volatile().unwrap();
+		tokio::task::block_in_place(|| database.migrate()).unwrap();
+
+		let storage = Arc::new(SqliteStorage::new(database));
+		let time = |index: usize| 1.0 + index as f64 * 0.01;
+		let mut vehicle_states = Vec::new();
+
+		// a channel which only appears in a later chunk still gets a column
+		for index in 0..2 * EXPORT_CHUNK_SNAPSHOTS + 10 {
+			let mut state = VehicleState::new();
+			state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: index as f64, unit: Unit::Psi });
+
+			if index > EXPORT_CHUNK_SNAPSHOTS {
+				state.valve_states.insert("BBV".to_owned(), CompositeValveState { commanded: ValveState::Open, actual: ValveState::Open });
+			}
+
+			let timestamp = time(index);
+			storage.insert_snapshot(timestamp, snapshot::encode(&state).unwrap()).await.unwrap();
+			vehicle_states.push((timestamp, state));
+		}
+
+		let note = |created_at: f64, content: &str| Note { note_id: 0, author: None, session_id: None, content: content.to_owned(), created_at };
+
+		// the second note falls after the last row of the first chunk, so on the first row of the next
+		let notes = vec![note(0.0, "chilldown"), note(time(EXPORT_CHUNK_SNAPSHOTS) - 0.005, "fill")];
+
+		let mut stream = ExportStream {
+			storage,
+			format: "csv".to_owned(),
+			from: 0.0,
+			to: f64::MAX,
+			after: None,
+			downsampler: None,
+			channels: Selection::default(),
+			units: UnitSystem::Native,
+			columns: CsvColumns { notes: true, ..CsvColumns::default() },
+			notes: notes.clone(),
+			started: false,
+			bounds: None,
+			analyzer: None,
+		};
+
+		stream.find_columns().await.unwrap();
+		assert_eq!(stream.bounds, Some((time(0), time(2 * EXPORT_CHUNK_SNAPSHOTS + 9))));
+
+		let mut streamed = String::new();
+
+		while let Some(content) = stream.next_content().await.unwrap() {
+			streamed += &content;
+		}
+
+		let columns = stream.columns;
+		assert_eq!(streamed, make_csv(&columns.sensor_names, &columns.valve_names, &vehicle_states, &notes, UnitSystem::Native));
+		assert!(streamed.lines().nth(EXPORT_CHUNK_SNAPSHOTS + 1).unwrap().ends_with(",\"fill\""));
+
+		// intervals which span two chunks are aggregated as a whole
+		let mut stream = ExportStream {
+			storage: stream.storage,
+			after: None,
+			downsampler: Some(Downsampler::new(3.0)),
+			columns: CsvColumns { notes: true, ..CsvColumns::default() },
+			notes: notes.clone(),
+			started: false,
+			..stream
+		};
+
+		stream.find_columns().await.unwrap();
+
+		let mut streamed = String::new();
+
+		while let Some(content) = stream.next_content().await.unwrap() {
+			streamed += &content;
+		}
+
+		export::downsample(&mut vehicle_states, 3.0);
+
+		let columns = stream.columns;
+		assert!(columns.sensor_names.iter().any(|name| name == "KBPT.max"));
+		assert_eq!(streamed, make_csv(&columns.sensor_names, &columns.valve_names, &vehicle_states, &notes, UnitSystem::Native));
+	}
+	#[tokio::test(flavor = "multi_thread")]
+	async fn snapshots_tied_across_chunks_are_all_exported() {
+		let database = Database::volatile().unwrap();
+		tokio::task::block_in_place(|| database.migrate()).unwrap();
+
+		let storage = Arc::new(SqliteStorage::new(database));
+
+		// the snapshots around the end of the first chunk were all logged at once
+		for index in 0..EXPORT_CHUNK_SNAPSHOTS + 10 {
+			let mut state = VehicleState::new();
+			state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: index as f64, unit: Unit::Psi });
+
+			let timestamp = 1.0 + index.clamp(EXPORT_CHUNK_SNAPSHOTS - 5, EXPORT_CHUNK_SNAPSHOTS + 5) as f64;
+			storage.insert_snapshot(timestamp, snapshot::encode(&state).unwrap()).await.unwrap();
+		}
+
+		let mut stream = ExportStream {
+			storage,
+			format: "csv".to_owned(),
+			from: 0.0,
+			to: f64::MAX,
+			after: None,
+			downsampler: None,
+			channels: Selection::default(),
+			units: UnitSystem::Native,
+			columns: CsvColumns::default(),
+			notes: Vec::new(),
+			started: false,
+			bounds: None,
+			analyzer: None,
+		};
+
+		stream.find_columns().await.unwrap();
+
+		let mut rows = 0;
+
+		while let Some(chunk) = stream.next_chunk().await.unwrap() {
+			rows += chunk.len();
+		}
+
+		assert_eq!(rows, EXPORT_CHUNK_SNAPSHOTS + 10);
+	}
+
+	#[test]
+	fn duplicate_export_formats_are_rejected() {
+		let request = |formats: &[&str]| ExportRequest {
+			format: None,
+			formats: formats.iter().map(|format| format.to_string()).collect(),
+			from: 0.0,
+			to: 1.0,
+			preset: None,
+			channels: None,
+			downsample_hz: None,
+			units: None,
+		};
+
+		assert!(validation::validate(&request(&["csv", "influx"])).is_ok());
+		assert!(validation::validate(&request(&["csv", "influx", "csv"])).is_err());
+	}
 }
@@ -0,0 +1,89 @@
+use axum::{extract::{Query, State}, Json};
+use jeflog::warn;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::server::{self, api::FlightInfoResponse, validation::{Valid, Validate, Validator}, Shared};
+
+/// How long a request waits for the flight computer to answer a query of its software information.
+const INFO_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Query struct for getting the software information of the flight computer.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct FlightInfoQuery {
+	/// The comma-separated names of the runtime parameters to read, or all of them if omitted.
+	pub parameters: Option<String>,
+}
+
+/// Route function which queries the connected flight computer for its software
+/// information and runtime parameters over the control link.
+///
+/// If the flight computer does not answer in time, the last information it
+/// reported is returned instead.
+pub async fn get_flight_info(
+	State(shared): State<Shared>,
+	Query(query): Query<FlightInfoQuery>,
+) -> server::Result<Json<FlightInfoResponse>> {
+	let parameters = query.parameters
+		.iter()
+		.flat_map(|parameters| parameters.split(','))
+		.map(str::trim)
+		.filter(|name| !name.is_empty())
+		.map(str::to_owned)
+		.collect();
+
+	let query = match shared.flight.0.lock().await.as_mut() {
+		Some(flight) => Some(flight.query_info(parameters).await),
+		None => None,
+	};
+
+	// the lock is not held while waiting, so that commands are not held up behind the answer.
+	match query {
+		Some(Ok(mut reports)) => {
+			let _ = tokio::time::timeout(INFO_QUERY_TIMEOUT, reports.changed()).await;
+		},
+		Some(Err(error)) => warn!("Failed to query flight computer for its software information: {error}"),
+		None => {},
+	};
+
+	let flight = shared.flight.0.lock().await;
+	let expected_version = shared.config.current().flight.expected_version.clone();
+	let info = flight.as_ref().and_then(|flight| flight.info());
+
+	let version_mismatch = match (&expected_version, &info) {
+		(Some(expected), Some(info)) => *expected != info.version,
+		_ => false,
+	};
+
+	Ok(Json(FlightInfoResponse {
+		connected: flight.is_some(),
+		connected_seconds: flight.as_ref().map(|flight| flight.connected_seconds()),
+		servo_version: env!("CARGO_PKG_VERSION").to_owned(),
+		expected_version,
+		info,
+		version_mismatch,
+	}))
+}
+
+/// Request struct through which the flight computer reports that a sequence finished.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SequenceFinishedRequest {
+	/// The name of the sequence which finished.
+	pub name: String,
+}
+
+impl Validate for SequenceFinishedRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
+/// Route function through which the flight computer reports that a sequence
+/// finished, releasing its lock on dispatching other sequences.
+pub async fn report_sequence_finished(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SequenceFinishedRequest>>,
+) -> server::Result<()> {
+	shared.lockout.release(&request.name).await;
+	Ok(())
+}
@@ -0,0 +1,83 @@
+use axum::{extract::{Query, State}, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::server::{
+	self,
+	audit,
+	auth::Session,
+	error::internal,
+	hazard::{self, HazardLevel},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+/// Query struct for getting the hazard levels of a configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HazardQuery {
+	/// The configuration whose hazard levels are listed, or the active one if omitted.
+	pub configuration_id: Option<String>,
+}
+
+/// Route function which lists every channel of a configuration classified above
+/// [`HazardLevel::None`], so that GUIs can warn before changing them.
+pub async fn get_hazards(
+	State(shared): State<Shared>,
+	Query(query): Query<HazardQuery>,
+) -> server::Result<Json<BTreeMap<String, HazardLevel>>> {
+	let levels = hazard::levels(&*shared.database.connection.lock().await, query.configuration_id.as_deref())
+		.map_err(internal)?;
+
+	Ok(Json(levels))
+}
+
+/// Request struct for setting the hazard levels of channels of a configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetHazardsRequest {
+	/// The configuration whose channels are classified.
+	pub configuration_id: String,
+
+	/// The hazard level of each channel to set, keyed by text ID.
+	pub levels: BTreeMap<String, HazardLevel>,
+}
+
+impl Validate for SetHazardsRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("configuration_id", &self.configuration_id);
+		validator.check(self.levels.keys().all(|text_id| !text_id.trim().is_empty()), "levels", "must not classify a channel with an empty name");
+	}
+}
+
+/// Route function which sets the hazard levels of channels of a configuration.
+///
+/// Channels not named in the request keep their levels. Every change is audited
+/// since it decides which commands need a second operator.
+pub async fn set_hazards(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<SetHazardsRequest>>,
+) -> server::Result<()> {
+	let database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	hazard::set_levels(&database, &request.configuration_id, &request.levels)
+		.map_err(internal)?;
+
+	let levels = request.levels
+		.iter()
+		.map(|(text_id, level)| format!("{text_id} {level}"))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	audit::record(
+		&database,
+		session.as_ref().map(|session| session.username.as_str()),
+		"classify hazards",
+		&format!("classified channels of configuration '{}': {levels}", request.configuration_id),
+	)
+	.map_err(internal)?;
+
+	Ok(())
+}
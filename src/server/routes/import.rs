@@ -0,0 +1,105 @@
+use axum::{extract::State, Json};
+use rusqlite::params;
+use std::collections::BTreeSet;
+
+use crate::server::{
+	self,
+	api::{ImportRequest, ImportResponse},
+	audit,
+	auth::Session,
+	error::{bad_request, internal},
+	import,
+	sync,
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+impl Validate for ImportRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("content", &self.content);
+		validator.check(self.file_name.is_some() || self.format.is_some(), "format", "must be given when the file name is not");
+
+		if let Some(format) = &self.format {
+			validator.one_of("format", format, &import::FORMATS);
+		}
+	}
+}
+
+/// Route function which logs the vehicle data of a CSV or HDF5 file, such as
+/// an earlier export or the log of a third-party DAQ, so that it is served by
+/// the same history, statistics, and export routes as data logged live.
+///
+/// Importing a file again only logs the snapshots which are not logged already.
+pub async fn import_data(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<ImportRequest>>,
+) -> server::Result<Json<ImportResponse>> {
+	let format = import::detect_format(request.format.as_deref(), request.file_name.as_deref())
+		.map_err(bad_request)?;
+
+	let content = base64::decode(&request.content)
+		.map_err(|error| bad_request(format!("file content is not valid Base64: {error}")))?;
+
+	let options = request.options;
+
+	let imported = tokio::task::spawn_blocking(move || match format {
+		"csv" => {
+			let text = String::from_utf8(content).map_err(|_| "CSV file is not valid UTF-8".to_owned())?;
+			import::parse_csv(&text, &options)
+		},
+		#[cfg(feature = "hdf5")]
+		"hdf5" => import::parse_hdf5(&content, &options),
+		_ => Err(format!("cannot import '{format}'")),
+	})
+	.await
+	.map_err(internal)?
+	.map_err(bad_request)?;
+
+	let channels = imported.snapshots
+		.iter()
+		.flat_map(|(_, state)| state.sensor_readings.keys().chain(state.valve_states.keys()))
+		.collect::<BTreeSet<_>>()
+		.into_iter()
+		.cloned()
+		.collect();
+
+	let mut response = ImportResponse {
+		channels,
+		from: imported.snapshots.first().map(|snapshot| snapshot.0),
+		to: imported.snapshots.last().map(|snapshot| snapshot.0),
+		..ImportResponse::default()
+	};
+
+	let total = imported.snapshots.len();
+	response.snapshots = sync::insert_new(&*shared.storage, imported.snapshots)
+		.await
+		.map_err(internal)?;
+
+	response.skipped = total - response.snapshots;
+
+	let database = shared.database.connection.lock().await;
+
+	for note in &imported.notes {
+		response.notes += database
+			.execute(
+				"INSERT INTO Notes (author, content, created_at)
+				SELECT ?1, ?2, ?3
+				WHERE NOT EXISTS (SELECT 1 FROM Notes WHERE content = ?2 AND created_at = ?3)",
+				params![note.author, note.content, note.created_at],
+			)
+			.map_err(internal)?;
+	}
+
+	let detail = format!(
+		"{} snapshots and {} notes from {}",
+		response.snapshots,
+		response.notes,
+		request.file_name.as_deref().unwrap_or("a file"),
+	);
+
+	let username = session.as_ref().map(|session| session.username.as_str());
+	audit::record(&database, username, "import", &detail).map_err(internal)?;
+
+	Ok(Json(response))
+}
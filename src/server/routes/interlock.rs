@@ -0,0 +1,127 @@
+use axum::{extract::State, Json};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use crate::server::{self, error::{bad_request, internal}, interlock::Condition, validation::{Valid, Validate, Validator}, Shared};
+
+/// A single precondition attached to a sequence.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Interlock {
+	/// The name of the sequence which the condition guards.
+	pub sequence_name: String,
+
+	/// The condition which must hold before the sequence is dispatched, such as `KBPT < 50 psi`.
+	pub condition: String,
+}
+
+/// Route function which returns every interlock in the database.
+pub async fn get_interlocks(State(shared): State<Shared>) -> server::Result<Json<Vec<Interlock>>> {
+	let interlocks = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("SELECT sequence_name, condition FROM Interlocks ORDER BY sequence_name")
+		.map_err(internal)?
+		.query_map([], |row| {
+			Ok(Interlock {
+				sequence_name: row.get(0)?,
+				condition: row.get(1)?,
+			})
+		})
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	Ok(Json(interlocks))
+}
+
+/// Request struct for setting the interlocks of a sequence.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetInterlocksRequest {
+	/// The name of the sequence which the conditions guard.
+	pub sequence_name: String,
+
+	/// The conditions which must all hold before the sequence is dispatched.
+	pub conditions: Vec<String>,
+}
+
+impl Validate for SetInterlocksRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("sequence_name", &self.sequence_name);
+
+		for (i, condition) in self.conditions.iter().enumerate() {
+			validator.non_empty(&format!("conditions[{i}]"), condition);
+		}
+	}
+}
+
+/// Route function which replaces the interlocks of a sequence.
+///
+/// Every condition is parsed before any are saved, so a typo is rejected
+/// rather than stored as an interlock which can never be satisfied.
+pub async fn set_interlocks(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SetInterlocksRequest>>,
+) -> server::Result<()> {
+	for condition in &request.conditions {
+		condition
+			.parse::<Condition>()
+			.map_err(bad_request)?;
+	}
+
+	let mut database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let transaction = database
+		.transaction()
+		.map_err(internal)?;
+
+	transaction
+		.execute("DELETE FROM Interlocks WHERE sequence_name = ?1", [&request.sequence_name])
+		.map_err(internal)?;
+
+	for condition in &request.conditions {
+		transaction
+			.execute(
+				"INSERT OR IGNORE INTO Interlocks (sequence_name, condition) VALUES (?1, ?2)",
+				params![request.sequence_name, condition.trim()],
+			)
+			.map_err(internal)?;
+	}
+
+	transaction
+		.commit()
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Request struct for removing every interlock of a sequence.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeleteInterlocksRequest {
+	/// The name of the sequence whose interlocks are removed.
+	pub sequence_name: String,
+}
+
+impl Validate for DeleteInterlocksRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("sequence_name", &self.sequence_name);
+	}
+}
+
+/// Route function which removes every interlock of a sequence.
+pub async fn delete_interlocks(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<DeleteInterlocksRequest>>,
+) -> server::Result<()> {
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Interlocks WHERE sequence_name = ?1", [&request.sequence_name])
+		.map_err(internal)?;
+
+	Ok(())
+}
@@ -1,11 +1,10 @@
-use axum::{extract::State, Json};
-use common::comm::NodeMapping;
-use rusqlite::params;
+use axum::{extract::State, response::Response, Json};
+use common::comm::{NodeMapping, SensorType, Unit};
+use rusqlite::{params, Connection, Transaction};
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::server::{self, error::{bad_request, internal, not_found}, Shared};
+use crate::server::{self, api::{ActiveConfiguration, SetMappingsRequest}, conditional::Preconditions, error::{bad_request, internal, not_found}, flight, hazard, ingest, validation::{self, Valid, Validate, ValidationReport, Validator}, Shared};
 
 /// Request struct for getting mappings.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -14,14 +13,21 @@ pub struct GetMappingResponse {
 	pub mappings: Vec<NodeMapping>
 }
 
-/// A route function which retrieves the current stored mappings.
-pub async fn get_mappings(State(shared): State<Shared>) -> server::Result<Json<JsonValue>> {
-	let database = shared.database
-		.connection
-		.lock()
-		.await;
+/// A route function which retrieves the current stored mappings, or responds
+/// with `304 Not Modified` if the client's copy is current.
+pub async fn get_mappings(State(shared): State<Shared>, preconditions: Preconditions) -> server::Result<Response> {
+	let configurations = load_mappings(&*shared.database.connection.lock().await)
+		.map_err(internal)?;
+
+	Ok(shared.versions.respond("mappings", &preconditions, &configurations))
+}
 
-	let mappings = database
+/// Loads every stored mapping, grouped by configuration.
+///
+/// Configurations are kept in order so that the same mappings always serialize
+/// the same way, as entity tags and archives rely on.
+pub fn load_mappings(connection: &Connection) -> rusqlite::Result<BTreeMap<String, Vec<NodeMapping>>> {
+	let mappings = connection
 		.prepare("
 			SELECT
 				configuration_id,
@@ -36,8 +42,7 @@ pub async fn get_mappings(State(shared): State<Shared>) -> server::Result<Json<J
 				powered_threshold,
 				normally_closed
 			FROM NodeMappings
-		")
-		.map_err(internal)?
+		")?
 		.query_and_then([], |row| {
 			let configuration_id = row.get(0)?;
 
@@ -55,12 +60,10 @@ pub async fn get_mappings(State(shared): State<Shared>) -> server::Result<Json<J
 			};
 
 			Ok((configuration_id, mapping))
-		})
-		.map_err(internal)?
-		.collect::<rusqlite::Result<Vec<(String, NodeMapping)>>>()
-		.map_err(internal)?;
+		})?
+		.collect::<rusqlite::Result<Vec<(String, NodeMapping)>>>()?;
 
-	let mut configurations = HashMap::<String, Vec<NodeMapping>>::new();
+	let mut configurations = BTreeMap::<String, Vec<NodeMapping>>::new();
 
 	for (configuration_id, mapping) in mappings {
 		if let Some(config) = configurations.get_mut(&configuration_id) {
@@ -70,140 +73,262 @@ pub async fn get_mappings(State(shared): State<Shared>) -> server::Result<Json<J
 		}
 	}
 
-	Ok(Json(serde_json::to_value(&configurations).unwrap()))
+	Ok(configurations)
+}
+
+impl Validate for SetMappingsRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("configuration_id", &self.configuration_id);
+
+		// the index of the first mapping to report each reading
+		let mut readings = HashMap::<String, usize>::new();
+
+		for (i, mapping) in self.mappings.iter().enumerate() {
+			validator.nested(&format!("mappings[{i}]"), mapping);
+
+			for (reading, _) in ingest::expected_readings(mapping) {
+				match readings.get(&reading) {
+					Some(&first) if self.mappings[first].text_id == mapping.text_id => {
+						validator.error(&format!("mappings[{i}].text_id"), format!("duplicates mappings[{first}]"));
+						break;
+					},
+					Some(&first) => validator.error(&format!("mappings[{i}].text_id"), format!("reports {reading}, as mappings[{first}] does")),
+					None => _ = readings.insert(reading, i),
+				}
+			}
+
+			let same_channel = self.mappings[..i].iter().position(|other| {
+				other.board_id == mapping.board_id
+					&& other.channel == mapping.channel
+					&& other.sensor_type == mapping.sensor_type
+			});
+
+			if let Some(first) = same_channel {
+				validator.error(&format!("mappings[{i}].channel"), format!("is already read by mappings[{first}]"));
+			}
+		}
+	}
 }
 
-/// Request struct for setting a mapping.
+impl Validate for NodeMapping {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("text_id", &self.text_id);
+		validator.non_empty("board_id", &self.board_id);
+
+		let is_pt = matches!(self.sensor_type, SensorType::Pt);
+		let is_valve = matches!(self.sensor_type, SensorType::Valve);
+
+		match (self.min, self.max) {
+			(Some(min), Some(max)) if is_pt => validator.check(min < max, "max", format!("must be above min ({max} <= {min})")),
+			(Some(min), Some(max)) => validator.check(min <= max, "max", format!("must not be below min ({max} < {min})")),
+			(Some(_), None) if is_pt => validator.error("max", "must be set along with min to convert to psi"),
+			(None, Some(_)) if is_pt => validator.error("min", "must be set along with max to convert to psi"),
+			(None, None) if is_pt => validator.warn("min", "is not set, so the transducer is reported in volts rather than psi"),
+			_ => {},
+		}
+
+		if !is_pt && (self.min.is_some() || self.max.is_some()) {
+			validator.warn("max", "is ignored, since only pressure transducers are scaled by a range");
+		}
+
+		if self.calibrated_offset.is_some() && !matches!(self.sensor_type, SensorType::Pt | SensorType::LoadCell) {
+			validator.warn("calibrated_offset", "is ignored, since only pressure transducers and load cells are offset");
+		}
+
+		if let Some(powered_threshold) = self.powered_threshold {
+			validator.check(powered_threshold >= 0.0 && powered_threshold.is_finite(), "powered_threshold", "must be a current of at least zero amps");
+		}
+
+		if is_valve && self.powered_threshold.is_none() {
+			validator.warn("powered_threshold", "is not set, so the actual state of the valve is undetermined");
+		}
+
+		if !is_valve && (self.powered_threshold.is_some() || self.normally_closed.is_some()) {
+			validator.warn("powered_threshold", "is ignored, since only valves are powered");
+		}
+	}
+}
+
+/// The result of checking mappings without setting them.
 #[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SetMappingsRequest {
-	/// An ID uniquely identifying the configuration being set or modified
-	pub configuration_id: String,
+pub struct MappingReport {
+	/// The problems which would reject the mappings, and the warnings which would not.
+	#[serde(flatten)]
+	pub report: ValidationReport,
+
+	/// The unit of each reading the mappings would add to the vehicle state, keyed by
+	/// its name, with a valve's voltage and current reported under `_V` and `_I`.
+	pub units: BTreeMap<String, Unit>,
+}
 
-	/// Array of all mappings in no specific order
-	pub mappings: Vec<NodeMapping>
+/// A route function which checks mappings without setting them, reporting
+/// every problem and warning along with the units the mappings will be read in.
+pub async fn validate_mappings(Json(request): Json<SetMappingsRequest>) -> Json<MappingReport> {
+	let units = request.mappings
+		.iter()
+		.flat_map(ingest::expected_readings)
+		.collect();
+
+	Json(MappingReport {
+		report: validation::report(&request),
+		units,
+	})
+}
+
+/// Inserts a mapping into a configuration, as the active configuration.
+const INSERT_MAPPING: &str = "
+	INSERT INTO NodeMappings (
+		configuration_id,
+		text_id,
+		board_id,
+		sensor_type,
+		channel,
+		computer,
+		max,
+		min,
+		calibrated_offset,
+		powered_threshold,
+		normally_closed,
+		active
+	) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, TRUE)
+";
+
+/// Inserts a mapping into a configuration or updates the one with the same text ID.
+///
+/// The hazard level of an updated mapping is left alone, since it is servo's
+/// own and is set separately.
+const UPSERT_MAPPING: &str = "
+	INSERT INTO NodeMappings (
+		configuration_id,
+		text_id,
+		board_id,
+		sensor_type,
+		channel,
+		computer,
+		max,
+		min,
+		calibrated_offset,
+		powered_threshold,
+		normally_closed,
+		active
+	) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, TRUE)
+	ON CONFLICT (configuration_id, text_id) DO UPDATE SET
+		board_id = excluded.board_id,
+		sensor_type = excluded.sensor_type,
+		channel = excluded.channel,
+		computer = excluded.computer,
+		max = excluded.max,
+		min = excluded.min,
+		calibrated_offset = excluded.calibrated_offset,
+		powered_threshold = excluded.powered_threshold,
+		normally_closed = excluded.normally_closed,
+		active = excluded.active
+";
+
+/// Writes every mapping of a request with one statement, either [`INSERT_MAPPING`] or [`UPSERT_MAPPING`].
+fn write_mappings(transaction: &Transaction, sql: &str, request: &SetMappingsRequest) -> rusqlite::Result<()> {
+	let mut statement = transaction.prepare(sql)?;
+
+	for mapping in &request.mappings {
+		statement.execute(params![
+			request.configuration_id,
+			mapping.text_id,
+			mapping.board_id,
+			mapping.sensor_type,
+			mapping.channel,
+			mapping.computer,
+			mapping.max,
+			mapping.min,
+			mapping.calibrated_offset,
+			mapping.powered_threshold,
+			mapping.normally_closed,
+		])?;
+	}
+
+	Ok(())
 }
 
 /// A route function which deletes and replaces a previous configuration
+///
+/// Channels which remain in the configuration keep their hazard levels unless
+/// the request sets them anew. The configuration is replaced in one transaction,
+/// so if any mapping fails to be written, the previous configuration is kept and
+/// nothing is sent to the flight computer.
 pub async fn post_mappings(
 	State(shared): State<Shared>,
-	Json(request): Json<SetMappingsRequest>,
+	Valid(Json(request)): Valid<Json<SetMappingsRequest>>,
 ) -> server::Result<()> {
-	let database = shared.database
-		.connection
-		.lock()
-		.await;
+	// the transaction borrows the connection, so it is scoped to end before any await below.
+	{
+		let mut database = shared.database
+			.connection
+			.lock()
+			.await;
+
+		let transaction = database
+			.transaction()
+			.map_err(internal)?;
 
-	database
-		.execute("DELETE FROM NodeMappings WHERE configuration_id = ?1", [&request.configuration_id])
-		.map_err(internal)?;
+		let mut hazard_levels = hazard::levels(&transaction, Some(&request.configuration_id))
+			.map_err(internal)?;
 
-	for mapping in &request.mappings {
-		database
-			.execute("
-				INSERT INTO NodeMappings (
-					configuration_id,
-					text_id,
-					board_id,
-					sensor_type,
-					channel,
-					computer,
-					max,
-					min,
-					calibrated_offset,
-					powered_threshold,
-					normally_closed,
-					active
-				) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, TRUE)
-			", params![
-				request.configuration_id,
-				mapping.text_id,
-				mapping.board_id,
-				mapping.sensor_type,
-				mapping.channel,
-				mapping.computer,
-				mapping.max,
-				mapping.min,
-				mapping.calibrated_offset,
-				mapping.powered_threshold,
-				mapping.normally_closed,
-			])
+		hazard_levels.extend(request.hazard_levels.clone());
+
+		transaction
+			.execute("DELETE FROM NodeMappings WHERE configuration_id = ?1", [&request.configuration_id])
 			.map_err(internal)?;
-	}
 
-	drop(database);
+		write_mappings(&transaction, INSERT_MAPPING, &request)
+			.map_err(bad_request)?;
 
-	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-		flight
-			.send_mappings()
-			.await
+		hazard::set_levels(&transaction, &request.configuration_id, &hazard_levels)
+			.map_err(internal)?;
+
+		transaction
+			.commit()
 			.map_err(internal)?;
 	}
 
+	flight::send_mappings_to_all(&shared)
+		.await
+		.map_err(internal)?;
+
 	Ok(())
 }
 
-/// A route function which inserts a new mapping or updates an existing one
+/// A route function which inserts new mappings or updates existing ones
+///
+/// Every mapping is written in one transaction, so if any fails, none are kept
+/// and nothing is sent to the flight computer.
 pub async fn put_mappings(
 	State(shared): State<Shared>,
-	Json(request): Json<SetMappingsRequest>,
+	Valid(Json(request)): Valid<Json<SetMappingsRequest>>,
 ) -> server::Result<()> {
-	let database = shared.database
-		.connection
-		.lock()
-		.await;
-
-	for mapping in &request.mappings {
-		database
-			.execute("
-				INSERT INTO NodeMappings (
-					configuration_id,
-					text_id,
-					board_id,
-					sensor_types,
-					channel,
-					computer,
-					max,
-					min,
-					calibrated_offset,
-					powered_threshold,
-					normally_closed,
-					active
-				) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, TRUE)
-				ON CONFLICT (configuration_id, text_id) DO UPDATE SET
-					board_id = excluded.board_id,
-					channel = excluded.channel,
-					sensor_types = excluded.sensor_types,
-					computer = excluded.computer,
-					scale = excluded.scale,
-					offset = excluded.offset,
-					powered_threshold = excluded.powered_threshold,
-					normally_closed = excluded.normally_closed,
-					active = excluded.active
-			", params![
-				request.configuration_id,
-				mapping.text_id,
-				mapping.board_id,
-				mapping.sensor_type,
-				mapping.channel,
-				mapping.computer,
-				mapping.max,
-				mapping.min,
-				mapping.calibrated_offset,
-				mapping.powered_threshold,
-				mapping.normally_closed,
-			])
+	{
+		let mut database = shared.database
+			.connection
+			.lock()
+			.await;
+
+		let transaction = database
+			.transaction()
 			.map_err(internal)?;
-	}
 
-	drop(database);
+		write_mappings(&transaction, UPSERT_MAPPING, &request)
+			.map_err(bad_request)?;
 
-	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-		flight
-			.send_mappings()
-			.await
+		hazard::set_levels(&transaction, &request.configuration_id, &request.hazard_levels)
+			.map_err(internal)?;
+
+		transaction
+			.commit()
 			.map_err(internal)?;
 	}
 
+	flight::send_mappings_to_all(&shared)
+		.await
+		.map_err(internal)?;
+
 	Ok(())
 }
 
@@ -218,52 +343,66 @@ pub struct DeleteMappingsRequest {
 	pub mappings: Option<Vec<NodeMapping>>,
 }
 
+impl Validate for DeleteMappingsRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("configuration_id", &self.configuration_id);
+	}
+}
+
 /// A route function which deletes the specified mappings.
 pub async fn delete_mappings(
 	State(shared): State<Shared>,
-	Json(request): Json<DeleteMappingsRequest>,
+	Valid(Json(request)): Valid<Json<DeleteMappingsRequest>>,
 ) -> server::Result<()> {
-	let database = shared.database
-		.connection
-		.lock()
-		.await;
+	{
+		let mut database = shared.database
+			.connection
+			.lock()
+			.await;
+
+		let transaction = database
+			.transaction()
+			.map_err(internal)?;
 
-	// if the mappings are specified, then only delete them
-	// if not, then delete all mappings for that configuration (thus deleting the config)
-	if let Some(mappings) = &request.mappings {
-		for mapping in mappings {
-			database
-				.execute(
-					"DELETE FROM NodeMappings WHERE configuration_id = ?1 AND text_id = ?2",
-					params![request.configuration_id, mapping.text_id]
-				)
+		// if the mappings are specified, then only delete them
+		// if not, then delete all mappings for that configuration (thus deleting the config)
+		if let Some(mappings) = &request.mappings {
+			for mapping in mappings {
+				transaction
+					.execute(
+						"DELETE FROM NodeMappings WHERE configuration_id = ?1 AND text_id = ?2",
+						params![request.configuration_id, mapping.text_id]
+					)
+					.map_err(internal)?;
+			}
+		} else {
+			transaction
+				.execute("DELETE FROM NodeMappings WHERE configuration_id = ?1", params![request.configuration_id])
 				.map_err(internal)?;
 		}
-	} else {
-		database
-			.execute("DELETE FROM NodeMappings WHERE configuration_id = ?1", params![request.configuration_id])
-			.map_err(internal)?;
-	}
 
-	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-		flight.send_mappings()
-			.await
+		transaction
+			.commit()
 			.map_err(internal)?;
 	}
 
+	flight::send_mappings_to_all(&shared)
+		.await
+		.map_err(internal)?;
+
 	Ok(())
 }
 
-/// Request/response struct for getting and setting the active configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct ActiveConfiguration {
-	configuration_id: String
+impl Validate for ActiveConfiguration {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("configuration_id", &self.configuration_id);
+	}
 }
 
 /// A route function which activates a particular configuration
 pub async fn activate_configuration(
 	State(shared): State<Shared>,
-	Json(request): Json<ActiveConfiguration>,
+	Valid(Json(request)): Valid<Json<ActiveConfiguration>>,
 ) -> server::Result<()> {
 	let database = shared.database
 		.connection
@@ -281,12 +420,9 @@ pub async fn activate_configuration(
 	drop(database);
 
 	if rows_updated > 0 {
-		if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-			flight
-				.send_mappings()
-				.await
-				.map_err(internal)?;
-		}
+		flight::send_mappings_to_all(&shared)
+			.await
+			.map_err(internal)?;
 
 	} else {
 		return Err(bad_request("configuration_id does not exist"));
@@ -348,11 +484,12 @@ pub async fn calibrate(State(shared): State<Shared>) -> server::Result<Json<Cali
 		}
 	}
 
-	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
-		flight.send_mappings()
-			.await
-			.map_err(internal)?;
-	}
+	// sending mappings reads them from the database, so it must be unlocked first
+	drop(database);
+
+	flight::send_mappings_to_all(&shared)
+		.await
+		.map_err(internal)?;
 	
 
 	Ok(Json(updated))
@@ -0,0 +1,10 @@
+use axum::Json;
+
+use crate::server::{self, schema::Schema};
+
+/// Route function which describes the units, valve states, sensor and channel
+/// types, and data formats this server understands, along with how each
+/// variant is encoded, so that clients need not hardcode them.
+pub async fn get_schema() -> server::Result<Json<Schema>> {
+	Ok(Json(Schema::current()))
+}
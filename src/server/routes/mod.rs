@@ -1,24 +1,88 @@
 /// Route functions requiring admin privilages for execution.
 pub mod admin;
 
+/// Route functions for listing and raising alerts.
+pub mod alert;
+
+/// Route functions for logging in, logging out, and managing users.
+pub mod auth;
+
+/// Route functions for holding the lease on command authority over a WebSocket.
+pub mod authority;
+
 /// Route functions related to operator commands.
 pub mod command;
 
 /// Route functions for fetching and manipulating data about the flight computer.
 pub mod data;
 
+/// Route functions for querying the software running on the flight computer.
+pub mod flight;
+
+/// Route functions for getting and setting the hazard level of each channel.
+pub mod hazard;
+
+/// Route functions for importing files of vehicle data, such as earlier exports, into the database.
+pub mod import;
+
+/// Route functions for getting and setting the interlocks which guard sequences.
+pub mod interlock;
+
 /// Route functions for getting and setting node mappings.
 pub mod mappings;
 
+/// Route functions describing the server itself to clients.
+pub mod meta;
+
+/// Route functions for entering and listing operator notes in the shift log.
+pub mod note;
+
+/// Route functions for listing, saving, and deleting saved export presets.
+pub mod preset;
+
+/// Route functions for storing GUI profiles, such as dashboard layouts, for each user.
+pub mod profile;
+
 /// Route functions for setting and sending sequences.
 pub mod sequence;
 
+/// Route functions for listing, saving, and instantiating sequence snippets.
+pub mod snippet;
+
+/// Route functions for mirroring, promoting, and fencing servers in a primary and standby pair.
+pub mod standby;
+
+/// Route functions for reporting the status and metrics of the server.
+pub mod status;
+
+/// Route functions for syncing snapshots, configurations, and events between servers.
+pub mod sync;
+
+/// Route functions for getting and replacing the redlines of each channel.
+pub mod threshold;
+
 /// Route functions for setting and deleting triggers.
 pub mod trigger;
 
 pub use admin::*;
+pub use alert::*;
+pub use auth::*;
+pub use authority::*;
 pub use command::*;
 pub use data::*;
+pub use flight::*;
+pub use hazard::*;
+pub use import::*;
+pub use interlock::*;
 pub use mappings::*;
+pub use meta::*;
+pub use note::*;
+pub use preset::*;
+pub use profile::*;
 pub use sequence::*;
+pub use snippet::*;
+pub use standby::*;
+pub use status::*;
+pub use sync::*;
+pub use threshold::*;
 pub use trigger::*;
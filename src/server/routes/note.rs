@@ -0,0 +1,85 @@
+use axum::{extract::{Query, State}, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+	self,
+	api::NoteRequest,
+	auth::Session,
+	error::{bad_request, internal},
+	note::{self, Note},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+/// The longest note accepted, in bytes, which is far more than a shift log entry needs.
+const MAX_NOTE_LENGTH: usize = 4096;
+
+impl Validate for NoteRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("content", &self.content);
+	}
+}
+
+/// Query parameters for listing notes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NotesQuery {
+	/// The earliest time of a note to include, as a Unix timestamp.
+	pub from: Option<f64>,
+
+	/// The latest time of a note to include, as a Unix timestamp.
+	pub to: Option<f64>,
+}
+
+impl Validate for NotesQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+	}
+}
+
+/// Route function which enters a note into the shift log, attached to the
+/// session used to make the request, if any.
+pub async fn post_note(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<NoteRequest>>,
+) -> server::Result<Json<Note>> {
+	let content = request.content.trim();
+
+	if content.is_empty() {
+		return Err(bad_request("note must not be empty"));
+	}
+
+	if content.len() > MAX_NOTE_LENGTH {
+		return Err(bad_request(format!("note must not be longer than {MAX_NOTE_LENGTH} bytes")));
+	}
+
+	let author = match &session {
+		Some(session) => Some(session.username.as_str()),
+		None => request.author.as_deref(),
+	};
+
+	let note = note::record(
+		&*shared.database.connection.lock().await,
+		author,
+		session.as_ref().map(|session| session.session_id),
+		content,
+	)
+	.map_err(internal)?;
+
+	Ok(Json(note))
+}
+
+/// Route function which lists the notes in the shift log in the order they were entered.
+pub async fn get_notes(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<NotesQuery>>,
+) -> server::Result<Json<Vec<Note>>> {
+	let notes = note::list(
+		&*shared.database.connection.lock().await,
+		query.from.unwrap_or(f64::NEG_INFINITY),
+		query.to.unwrap_or(f64::INFINITY),
+	)
+	.map_err(internal)?;
+
+	Ok(Json(notes))
+}
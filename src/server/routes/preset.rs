@@ -0,0 +1,61 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+	self,
+	error::{internal, not_found},
+	export::{self, ExportPreset},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+/// Request struct for deleting an export preset.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeleteExportPresetRequest {
+	/// The name of the preset to delete.
+	pub name: String,
+}
+
+impl Validate for DeleteExportPresetRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
+/// Route function which lists the saved export presets.
+pub async fn get_export_presets(State(shared): State<Shared>) -> server::Result<Json<Vec<ExportPreset>>> {
+	let presets = export::list_presets(&*shared.database.connection.lock().await)
+		.map_err(internal)?;
+
+	Ok(Json(presets))
+}
+
+/// Route function which saves an export preset, replacing any of the same name.
+pub async fn save_export_preset(
+	State(shared): State<Shared>,
+	Valid(Json(preset)): Valid<Json<ExportPreset>>,
+) -> server::Result<()> {
+	export::save_preset(&*shared.database.connection.lock().await, &preset)
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which deletes an export preset.
+pub async fn delete_export_preset(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<DeleteExportPresetRequest>>,
+) -> server::Result<()> {
+	let rows_deleted = shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM ExportPresets WHERE name = ?1", [&request.name])
+		.map_err(internal)?;
+
+	if rows_deleted == 0 {
+		return Err(not_found(format!("export preset '{}' does not exist", request.name)));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,121 @@
+use axum::{extract::{Path, State}, Json};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{self, auth::Session, error::{forbidden, internal, not_found}, Shared};
+
+/// Summary of a profile stored for a user, as listed without its content.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfileSummary {
+	/// The name of the profile, such as `hotfire-dashboard`.
+	pub name: String,
+
+	/// When the profile was last saved, as a Unix timestamp.
+	pub updated_at: f64,
+}
+
+/// Route function which lists the profiles stored for a user.
+pub async fn list_profiles(
+	State(shared): State<Shared>,
+	Path(username): Path<String>,
+) -> server::Result<Json<Vec<ProfileSummary>>> {
+	let profiles = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("SELECT name, updated_at FROM Profiles WHERE username = ?1 ORDER BY name")
+		.map_err(internal)?
+		.query_map([&username], |row| {
+			Ok(ProfileSummary {
+				name: row.get(0)?,
+				updated_at: row.get(1)?,
+			})
+		})
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	Ok(Json(profiles))
+}
+
+/// Route function which returns a profile stored for a user, such as a GUI
+/// dashboard layout, exactly as it was saved.
+///
+/// Profiles may be read by anyone, so that a layout can be shared between users.
+pub async fn get_profile(
+	State(shared): State<Shared>,
+	Path((username, name)): Path<(String, String)>,
+) -> server::Result<Json<serde_json::Value>> {
+	let content = shared.database
+		.connection
+		.lock()
+		.await
+		.query_row(
+			"SELECT content FROM Profiles WHERE username = ?1 AND name = ?2",
+			params![username, name],
+			|row| row.get::<_, String>(0),
+		)
+		.optional()
+		.map_err(internal)?
+		.ok_or(not_found(format!("user '{username}' has no profile '{name}'")))?;
+
+	Ok(Json(serde_json::from_str(&content).map_err(internal)?))
+}
+
+/// Route function which saves a profile for a user, replacing any of the same name.
+///
+/// The content may be any JSON, since its structure belongs to the GUI.
+pub async fn save_profile(
+	State(shared): State<Shared>,
+	session: Session,
+	Path((username, name)): Path<(String, String)>,
+	Json(content): Json<serde_json::Value>,
+) -> server::Result<()> {
+	require_owner(&session, &username)?;
+
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute("
+			INSERT INTO Profiles (username, name, content)
+			VALUES (?1, ?2, ?3)
+			ON CONFLICT (username, name) DO UPDATE SET
+				content = excluded.content,
+				updated_at = unixepoch('now', 'subsec')
+		", params![username, name, content.to_string()])
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which deletes a profile stored for a user.
+pub async fn delete_profile(
+	State(shared): State<Shared>,
+	session: Session,
+	Path((username, name)): Path<(String, String)>,
+) -> server::Result<()> {
+	require_owner(&session, &username)?;
+
+	let rows_deleted = shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Profiles WHERE username = ?1 AND name = ?2", params![username, name])
+		.map_err(internal)?;
+
+	if rows_deleted == 0 {
+		return Err(not_found(format!("user '{username}' has no profile '{name}'")));
+	}
+
+	Ok(())
+}
+
+/// Returns a 403 error unless the session belongs to the user or to an admin.
+fn require_owner(session: &Session, username: &str) -> server::Result<()> {
+	if session.username == username || session.is_admin() {
+		Ok(())
+	} else {
+		Err(forbidden("profiles may only be changed by their owner or an admin"))
+	}
+}
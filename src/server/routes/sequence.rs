@@ -1,52 +1,61 @@
-use axum::{extract::State, Json};
-use common::comm::Sequence;
-use rusqlite::params;
+use axum::{extract::State, response::Response, Json};
+use common::comm::{Computer, Sequence};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
-use crate::server::{self, error::{bad_request, internal}, Shared};
+use crate::server::{
+	self,
+	api::{RetrieveSequenceResponse, RunAdhocSequenceResponse, SequenceWithConfiguration},
+	archive,
+	audit,
+	auth::Session,
+	bundle::{self, PackagedFile},
+	conditional::Preconditions,
+	error::{bad_request, conflict, internal, not_found, ErrorCode},
+	flight,
+	interlock,
+	simulation::{self, SimulationOptions, SimulationReport},
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
 
-/// Used in sequences response struct to attach the configuration ID.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SequenceWithConfiguration {
-	/// The name of the sequence.
-	pub name: String,
-
-	/// The Python sequence script.
-	pub script: String,
-
-	/// The ID of the configuration associated with the sequence.
-	pub configuration_id: Option<String>,
-}
-
-/// Response struct for getting the sequences stored in the database.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct RetrieveSequenceResponse {
-	/// The collection of all sequences present on the control server.
-	pub sequences: Vec<SequenceWithConfiguration>
-}
-
-/// Route function to retrieve all sequences from the database.
-pub async fn retrieve_sequences(State(shared): State<Shared>) -> server::Result<Json<RetrieveSequenceResponse>> {
+/// Route function to retrieve all sequences from the database, or to respond
+/// with `304 Not Modified` if the client's copy is current.
+pub async fn retrieve_sequences(State(shared): State<Shared>, preconditions: Preconditions) -> server::Result<Response> {
 	let sequences = shared.database
 		.connection
 		.lock()
 		.await
-		.prepare("SELECT name, script, configuration_id FROM Sequences")
+		.prepare("SELECT name, script, configuration_id, safing FROM Sequences")
 		.map_err(internal)?
 		.query_map([], |row| {
 			Ok(SequenceWithConfiguration {
 				name: row.get(0)?,
 				script: row.get(1)?,
 				configuration_id: row.get(2)?,
+				safing: row.get(3)?,
 			})
 		})
 		.map_err(internal)?
 		.collect::<Result<Vec<_>, _>>()
 		.map_err(internal)?;
 
-	Ok(Json(RetrieveSequenceResponse { sequences }))
+	Ok(shared.versions.respond("sequences", &preconditions, &RetrieveSequenceResponse { sequences }))
 }
 
+/// Inserts a sequence or updates an existing one with the same name.
+///
+/// An upsert is used rather than `INSERT OR REPLACE` so that re-uploading a
+/// sequence does not clear its designation as a safing sequence. Moving a
+/// safing sequence onto a configuration which already has one violates the
+/// `SafingSequences` index and is rejected.
+const UPSERT_SEQUENCE: &str = "
+	INSERT INTO Sequences (name, configuration_id, script) VALUES (?1, ?2, ?3)
+	ON CONFLICT (name) DO UPDATE SET
+		configuration_id = excluded.configuration_id,
+		script = excluded.script
+";
+
 /// Request struct for saving a sequence without running it.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SaveSequenceRequest {
@@ -60,10 +69,20 @@ pub struct SaveSequenceRequest {
 	pub script: String,
 }
 
+impl Validate for SaveSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+
+		if let Some(configuration_id) = &self.configuration_id {
+			validator.non_empty("configuration_id", configuration_id);
+		}
+	}
+}
+
 /// A route function which saves a sequence without running it.
 pub async fn save_sequence(
 	State(shared): State<Shared>,
-	Json(request): Json<SaveSequenceRequest>,
+	Valid(Json(request)): Valid<Json<SaveSequenceRequest>>,
 ) -> server::Result<()> {
 	let decoded_script = base64::decode(&request.script)
 		.map_err(bad_request)
@@ -72,16 +91,26 @@ pub async fn save_sequence(
 				.map_err(bad_request)
 		})?;
 
-	shared.database
+	let database = shared.database
 		.connection
 		.lock()
-		.await
+		.await;
+
+	database
 		.execute(
-			"INSERT OR REPLACE INTO Sequences (name, configuration_id, script) VALUES (?1, ?2, ?3)",
+			UPSERT_SEQUENCE,
 			params![request.name, request.configuration_id, decoded_script]
 		)
+		.map_err(bad_request)?;
+
+	// a plain script replaces any bundle previously saved under the same name,
+	// so its helper files must not be shipped along with the new script.
+	database
+		.execute("DELETE FROM SequenceFiles WHERE sequence_name = ?1", [&request.name])
 		.map_err(internal)?;
 
+	drop(database);
+
 	// if the incoming sequence is the abort sequence, immediately send it over to
 	// flight to be saved, _not run_.
 	if request.name == "abort" {
@@ -100,6 +129,109 @@ pub async fn save_sequence(
 	Ok(())
 }
 
+/// Request struct for saving a sequence bundle, a script along with the helper files it depends on.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SaveSequenceBundleRequest {
+	/// The unique name of the sequence that identifies it.
+	pub name: String,
+
+	/// The ID of the associated configuration (provides extra check).
+	pub configuration_id: Option<String>,
+
+	/// The Base64-encoded entry point script of the sequence.
+	pub script: String,
+
+	/// The helper modules and data files which the script depends on.
+	pub files: Vec<PackagedFile>,
+}
+
+impl Validate for SaveSequenceBundleRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+
+		if let Some(configuration_id) = &self.configuration_id {
+			validator.non_empty("configuration_id", configuration_id);
+		}
+
+		for (i, file) in self.files.iter().enumerate() {
+			validator.non_empty(&format!("files[{i}].path"), &file.path);
+		}
+	}
+}
+
+/// A route function which saves a sequence bundle without running it.
+///
+/// The bundle is shipped to the flight computer as a unit whenever the sequence is run.
+pub async fn save_sequence_bundle(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SaveSequenceBundleRequest>>,
+) -> server::Result<()> {
+	let decoded_script = base64::decode(&request.script)
+		.map_err(bad_request)
+		.and_then(|bytes| {
+			String::from_utf8(bytes)
+				.map_err(bad_request)
+		})?;
+
+	for file in &request.files {
+		bundle::validate_path(&file.path).map_err(bad_request)?;
+
+		base64::decode(&file.contents)
+			.map_err(|error| bad_request(format!("packaged file '{}' is not valid Base64: {error}", file.path)))?;
+	}
+
+	{
+		let mut database = shared.database
+			.connection
+			.lock()
+			.await;
+
+		let transaction = database
+			.transaction()
+			.map_err(internal)?;
+
+		transaction
+			.execute(
+				UPSERT_SEQUENCE,
+				params![request.name, request.configuration_id, decoded_script]
+			)
+			.map_err(bad_request)?;
+
+		transaction
+			.execute("DELETE FROM SequenceFiles WHERE sequence_name = ?1", [&request.name])
+			.map_err(internal)?;
+
+		for file in &request.files {
+			transaction
+				.execute(
+					"INSERT INTO SequenceFiles (sequence_name, path, contents) VALUES (?1, ?2, ?3)",
+					params![request.name, file.path, file.contents]
+				)
+				.map_err(internal)?;
+		}
+
+		transaction
+			.commit()
+			.map_err(internal)?;
+	}
+
+	// see save_sequence: the abort sequence is stored on flight as soon as it is saved.
+	if request.name == "abort" {
+		if let Some(flight) = shared.flight.0.lock().await.as_mut() {
+			let sequence = Sequence {
+				name: request.name,
+				script: bundle::package_script(&decoded_script, &request.files),
+			};
+
+			flight.send_sequence(sequence)
+				.await
+				.map_err(internal)?;
+		}
+	}
+
+	Ok(())
+}
+
 /// Request struct to delete a sequence from the database.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeleteSequenceRequest {
@@ -107,18 +239,34 @@ pub struct DeleteSequenceRequest {
 	pub name: String
 }
 
+impl Validate for DeleteSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
 /// Route function to delete a sequence from the database.
 pub async fn delete_sequence(
 	State(shared): State<Shared>,
-	Json(request): Json<DeleteSequenceRequest>,
+	Valid(Json(request)): Valid<Json<DeleteSequenceRequest>>,
 ) -> server::Result<()> {
-	shared.database
+	let database = shared.database
 		.connection
 		.lock()
-		.await
-		.execute("DELETE FROM Sequences WHERE text_id = ?1", [&request.name])
+		.await;
+
+	database
+		.execute("DELETE FROM Sequences WHERE name = ?1", [&request.name])
 		.map_err(bad_request)?;
 
+	database
+		.execute("DELETE FROM SequenceFiles WHERE sequence_name = ?1", [&request.name])
+		.map_err(internal)?;
+
+	database
+		.execute("DELETE FROM Interlocks WHERE sequence_name = ?1", [&request.name])
+		.map_err(internal)?;
+
 	Ok(())
 }
 
@@ -129,28 +277,468 @@ pub struct RunSequenceRequest {
 	pub name: String,
 
 	/// Force the sequence to be executed, even if the configuration IDs do not match.
+	///
+	/// Combined with an admin session, this also overrides failing interlocks
+	/// and allows the sequence to run alongside sequences which are already running.
 	pub force: Option<bool>,
 }
 
+impl Validate for RunSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
 /// Route function which receives a sequence and sends it directly to the flight computer.
+///
+/// Sequences associated with a configuration are refused unless that configuration
+/// is active, since their valve and sensor names may mean something else otherwise.
+/// This check may be skipped with `force`.
+///
+/// The interlocks of the sequence are then evaluated, and the sequence is refused
+/// if any fail. Overriding them requires both `force` and an admin session, and
+/// every override is recorded in the audit log.
+///
+/// Finally, the sequence is refused while another sequence is running, since the
+/// two may fight over the same valves. This lockout is overridden the same way as
+/// interlocks.
+///
+/// The abort sequence skips all of these checks, so that nothing can keep the
+/// vehicle from being aborted, and releases every lock.
 pub async fn run_sequence(
 	State(shared): State<Shared>,
-	Json(request): Json<RunSequenceRequest>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<RunSequenceRequest>>,
 ) -> server::Result<()> {
-	// TODO: Add check for active configuration against the configuration_id in the database
+	let database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let (sequence, configuration_id) = load_sequence(&database, &request.name)?;
+	drop(database);
+
+	run_loaded_sequence(&shared, session, sequence, configuration_id, request.force.unwrap_or(false)).await
+}
 
-	let sequence = shared.database
+/// Runs a sequence which has already been loaded, after checking its
+/// configuration, interlocks, and lockout as described by [`run_sequence`].
+async fn run_loaded_sequence(
+	shared: &Shared,
+	session: Option<Session>,
+	sequence: Sequence,
+	configuration_id: Option<String>,
+	force: bool,
+) -> server::Result<()> {
+	let name = sequence.name.clone();
+
+	// nothing may stand in the way of aborting, least of all the failing interlock it is meant to answer
+	if name == "abort" {
+		dispatch_sequence(shared, sequence).await?;
+		shared.lockout.clear().await;
+		return Ok(());
+	}
+
+	let database = shared.database
 		.connection
 		.lock()
+		.await;
+
+	let active_configuration = active_configuration(&database)?;
+
+	if !force {
+		if let Some(configuration_id) = configuration_id {
+			if active_configuration.as_ref() != Some(&configuration_id) {
+				return Err(bad_request(format!(
+					"sequence '{name}' belongs to configuration '{configuration_id}', which is not active",
+				)));
+			}
+		}
+	}
+
+	let conditions = database
+		.prepare("SELECT condition FROM Interlocks WHERE sequence_name = ?1")
+		.map_err(internal)?
+		.query_map([&name], |row| row.get::<_, String>(0))
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	drop(database);
+
+	let failures = interlock::evaluate(shared, &conditions, active_configuration.as_deref()).await;
+
+	if !failures.is_empty() {
+		let Some(admin) = session.as_ref().filter(|session| force && session.is_admin()) else {
+			return Err(
+				conflict(format!("interlocks not satisfied: {}", failures.join("; ")))
+					.with_code(ErrorCode::InterlocksNotSatisfied)
+					.with_context("failures", &failures)
+			);
+		};
+
+		audit::record(
+			&*shared.database.connection.lock().await,
+			Some(&admin.username),
+			"override interlocks",
+			&format!("overrode failing interlocks of sequence '{name}': {}", failures.join("; ")),
+		)
+		.map_err(internal)?;
+	}
+
+	let username = session.as_ref().map(|session| session.username.as_str());
+	let override_lockout = force && session.as_ref().is_some_and(Session::is_admin);
+
+	let running = shared.lockout
+		.claim(&name, username, shared.config.current().flight.max_sequence_hold(), override_lockout)
 		.await
-		.query_row("SELECT script FROM Sequences WHERE name = ?1", [&request.name], |row| {
-			Ok(Sequence {
-				name: request.name.clone(),
-				script: row.get(0)?,
-			})
+		.map_err(|running| {
+			conflict(format!(
+				"sequence '{name}' cannot run while '{}' is running; stop it or abort first",
+				running.join("', '"),
+			))
+			.with_code(ErrorCode::SequenceLockedOut)
+			.with_context("running", running)
+		})?;
+
+	if !running.is_empty() {
+		audit::record(
+			&*shared.database.connection.lock().await,
+			username,
+			"override sequence lockout",
+			&format!("ran sequence '{name}' while '{}' was running", running.join("', '")),
+		)
+		.map_err(internal)?;
+	}
+
+	let script = sequence.script.clone();
+	let result = dispatch_sequence(shared, sequence).await;
+
+	if result.is_err() {
+		shared.lockout.release(&name).await;
+		return result;
+	}
+
+	let database = shared.database.connection.lock().await;
+
+	// every run is recorded so that reports after a test can list what was run and when,
+	// and archives can include exactly what was sent
+	archive::record_run(&database, &name, &script, username).map_err(internal)?;
+
+	audit::record(
+		&database,
+		username,
+		"run sequence",
+		&format!("ran sequence '{name}'"),
+	)
+	.map_err(internal)?;
+
+	Ok(())
+}
+
+/// The prefix of the names which ad-hoc scripts are stored under, keeping them
+/// apart from the sequences uploaded to be kept.
+pub const ADHOC_PREFIX: &str = "adhoc-";
+
+/// Request struct for saving and running a script in one call.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunAdhocSequenceRequest {
+	/// The name to store the script under, which is prefixed with [`ADHOC_PREFIX`].
+	pub name: String,
+
+	/// The Base64-encoded script to run.
+	pub script: String,
+
+	/// Overrides failing interlocks and the sequence lockout, as with [`run_sequence`].
+	pub force: Option<bool>,
+}
+
+impl Validate for RunAdhocSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+		validator.non_empty("script", &self.script);
+	}
+}
+
+/// Route function which runs a script and stores it as an ad-hoc sequence in one call.
+///
+/// The script is run under its name with [`ADHOC_PREFIX`], and is checked just
+/// as [`run_sequence`] checks a stored sequence. It is run exactly as received
+/// rather than reloaded, so that an upload from another client cannot change
+/// what is run in between.
+///
+/// Only once the script has been sent to the flight computer is it stored, as
+/// the next version of its name, so that a refused script is never kept and no
+/// version which was run is ever replaced.
+pub async fn run_adhoc_sequence(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Valid(Json(request)): Valid<Json<RunAdhocSequenceRequest>>,
+) -> server::Result<Json<RunAdhocSequenceResponse>> {
+	let script = base64::decode(&request.script)
+		.map_err(bad_request)
+		.and_then(|bytes| {
+			String::from_utf8(bytes)
+				.map_err(bad_request)
+		})?;
+
+	if script.trim().is_empty() {
+		return Err(bad_request("script must not be blank"));
+	}
+
+	let name = format!("{ADHOC_PREFIX}{}", request.name.trim_start_matches(ADHOC_PREFIX));
+	let username = session.as_ref().map(|session| session.username.clone());
+
+	let sequence = Sequence { name: name.clone(), script: script.clone() };
+	run_loaded_sequence(&shared, session, sequence, None, request.force.unwrap_or(false)).await?;
+
+	let version = shared.database
+		.connection
+		.lock()
+		.await
+		.query_row("
+			INSERT INTO AdhocSequences (name, version, script, username)
+			SELECT ?1, COALESCE(MAX(version), 0) + 1, ?2, ?3 FROM AdhocSequences WHERE name = ?1
+			RETURNING version
+		", params![name, script, username], |row| row.get(0))
+		.map_err(internal)?;
+
+	Ok(Json(RunAdhocSequenceResponse { name, version }))
+}
+
+/// Request struct for simulating a sequence without running it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SimulateSequenceRequest {
+	/// The name of a stored sequence to simulate.
+	pub name: Option<String>,
+
+	/// A Base64-encoded script to simulate instead of a stored sequence.
+	pub script: Option<String>,
+
+	/// The assumed sensor ranges and the threshold for flagging long waits.
+	#[serde(flatten)]
+	pub options: SimulationOptions,
+}
+
+impl Validate for SimulateSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.check(self.name.is_some() != self.script.is_some(), "name", "exactly one of name and script must be given");
+	}
+}
+
+/// Route function which simulates a sequence, returning the timeline of
+/// commands it would send and how long it would take to run.
+///
+/// Nothing is sent to the flight computer. Helper modules of a bundle are not simulated.
+pub async fn simulate_sequence(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SimulateSequenceRequest>>,
+) -> server::Result<Json<SimulationReport>> {
+	let script = match (request.script, request.name) {
+		(Some(script), _) => base64::decode(script)
+			.map_err(bad_request)
+			.and_then(|bytes| String::from_utf8(bytes).map_err(bad_request))?,
+		(None, Some(name)) => shared.database
+			.connection
+			.lock()
+			.await
+			.query_row("SELECT script FROM Sequences WHERE name = ?1", [&name], |row| row.get(0))
+			.optional()
+			.map_err(internal)?
+			.ok_or_else(|| not_found(format!("sequence '{name}' does not exist")))?,
+		(None, None) => return Err(bad_request("either a sequence name or a script is required")),
+	};
+
+	let report = simulation::simulate(&script, &request.options)
+		.map_err(|error| bad_request(format!("sequence cannot be simulated: {error}")))?;
+
+	Ok(Json(report))
+}
+
+/// Request struct for designating a sequence as the safing sequence of its configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SafingSequenceRequest {
+	/// The name of the sequence, as recorded in the database.
+	pub name: String,
+}
+
+impl Validate for SafingSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
+/// Route function which designates a sequence as the safing sequence of its configuration,
+/// replacing any safing sequence previously designated for that configuration.
+pub async fn set_safing_sequence(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SafingSequenceRequest>>,
+) -> server::Result<()> {
+	let mut database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let transaction = database
+		.transaction()
+		.map_err(internal)?;
+
+	let configuration_id = transaction
+		.query_row("SELECT configuration_id FROM Sequences WHERE name = ?1", [&request.name], |row| row.get::<_, Option<String>>(0))
+		.optional()
+		.map_err(internal)?
+		.ok_or(not_found(format!("sequence '{}' does not exist", request.name)))?;
+
+	transaction
+		.execute("UPDATE Sequences SET safing = FALSE WHERE safing AND configuration_id IS ?1", [&configuration_id])
+		.map_err(internal)?;
+
+	transaction
+		.execute("UPDATE Sequences SET safing = TRUE WHERE name = ?1", [&request.name])
+		.map_err(internal)?;
+
+	transaction
+		.commit()
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which removes the safing designation from a sequence.
+pub async fn clear_safing_sequence(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<SafingSequenceRequest>>,
+) -> server::Result<()> {
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute("UPDATE Sequences SET safing = FALSE WHERE name = ?1", [&request.name])
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Response struct naming the safing sequence which was run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SafeResponse {
+	/// The name of the safing sequence sent to the flight computer.
+	pub name: String,
+}
+
+/// Route function which immediately runs the safing sequence of the active configuration.
+pub async fn safe(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+) -> server::Result<Json<SafeResponse>> {
+	let username = session.map(|session| session.username);
+	let name = run_safing_sequence(&shared, username.as_deref(), "api").await?;
+
+	Ok(Json(SafeResponse { name }))
+}
+
+/// Runs the safing sequence of the active configuration, returning its name.
+///
+/// When things go wrong, the path to safe must be as short as possible, so the
+/// configuration check applied to other sequences is skipped. A safing sequence
+/// with no configuration is used if the active configuration has none of its own.
+/// Every attempt is recorded in the audit log, along with where it came from.
+pub async fn run_safing_sequence(
+	shared: &Shared,
+	username: Option<&str>,
+	source: &str,
+) -> server::Result<String> {
+	let database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let active_configuration = active_configuration(&database)?;
+
+	let name = database
+		.query_row("
+			SELECT name FROM Sequences
+			WHERE safing AND (configuration_id IS ?1 OR configuration_id IS NULL)
+			ORDER BY configuration_id IS NULL
+			LIMIT 1
+		", [&active_configuration], |row| row.get::<_, String>(0))
+		.optional()
+		.map_err(internal)?
+		.ok_or(not_found("no safing sequence designated for the active configuration"))?;
+
+	let (sequence, _) = load_sequence(&database, &name)?;
+	drop(database);
+
+	// the path to safe is never locked out, but other sequences are locked out while it runs.
+	let script = sequence.script.clone();
+	let result = dispatch_sequence(shared, sequence).await;
+
+	if result.is_ok() {
+		let max_hold = shared.config.current().flight.max_sequence_hold();
+		let _ = shared.lockout.claim(&name, username, max_hold, true).await;
+	}
+
+	let outcome = if result.is_ok() { "sent" } else { "failed to send" };
+	let database = shared.database.connection.lock().await;
+
+	if result.is_ok() {
+		archive::record_run(&database, &name, &script, username).map_err(internal)?;
+	}
+
+	audit::record(
+		&database,
+		username,
+		"safe",
+		&format!(
+			"{outcome} safing sequence '{name}' from {source} with active configuration '{}'",
+			active_configuration.as_deref().unwrap_or("none"),
+		),
+	)
+	.map_err(internal)?;
+
+	result.map(|_| name)
+}
+
+/// Loads a sequence and its bundled files from the database, packaged to be sent
+/// to the flight computer, along with the ID of its configuration.
+fn load_sequence(connection: &Connection, name: &str) -> server::Result<(Sequence, Option<String>)> {
+	let (script, configuration_id) = connection
+		.query_row("SELECT script, configuration_id FROM Sequences WHERE name = ?1", [name], |row| {
+			Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
 		})
 		.map_err(bad_request)?;
 
+	let files = connection
+		.prepare("SELECT path, contents FROM SequenceFiles WHERE sequence_name = ?1")
+		.map_err(internal)?
+		.query_map([name], |row| {
+			Ok(PackagedFile {
+				path: row.get(0)?,
+				contents: row.get(1)?,
+			})
+		})
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	let sequence = Sequence {
+		name: name.to_owned(),
+		script: bundle::package_script(&script, &files),
+	};
+
+	Ok((sequence, configuration_id))
+}
+
+/// Returns the ID of the active configuration, if any.
+fn active_configuration(connection: &Connection) -> server::Result<Option<String>> {
+	connection
+		.query_row("SELECT configuration_id FROM NodeMappings WHERE active = TRUE LIMIT 1", [], |row| row.get(0))
+		.optional()
+		.map_err(internal)
+}
+
+/// Sends a sequence to the flight computer to be run.
+async fn dispatch_sequence(shared: &Shared, sequence: Sequence) -> server::Result<()> {
 	if let Some(flight) = shared.flight.0.lock().await.as_mut() {
 		// special case for abort sequence, because sending it over just saves it
 		// so we need to send an actual abort control message if we want to run it
@@ -167,7 +755,7 @@ pub async fn run_sequence(
 			.await
 			.map_err(internal)?;
 	} else {
-		return Err(internal("flight computer not connected"));
+		return Err(flight::not_connected(Computer::Flight));
 	}
 
 	Ok(())
@@ -180,20 +768,27 @@ pub struct StopSequenceRequest {
 	pub name: String
 }
 
+impl Validate for StopSequenceRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
 /// Route function which instructs the flight computer to stop a sequence.
 pub async fn stop_sequence(
 	State(shared): State<Shared>,
-	Json(request): Json<StopSequenceRequest>,
+	Valid(Json(request)): Valid<Json<StopSequenceRequest>>,
 ) -> server::Result<()> {
 	shared.flight.0
 		.lock()
 		.await
 		.as_mut()
-		.ok_or(internal("flight computer not connected"))?
-		.stop_sequence(request.name)
+		.ok_or(flight::not_connected(Computer::Flight))?
+		.stop_sequence(request.name.clone())
 		.await
 		.map_err(internal)?;
 
+	shared.lockout.release(&request.name).await;
 	Ok(())
 }
 
@@ -203,10 +798,88 @@ pub async fn abort(State(shared): State<Shared>) -> server::Result<()> {
 		.lock()
 		.await
 		.as_mut()
-		.ok_or(internal("flight computer not connected"))?
+		.ok_or(flight::not_connected(Computer::Flight))?
 		.abort()
 		.await
 		.map_err(internal)?;
 
+	shared.lockout.clear().await;
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Server;
+	use super::*;
+
+	/// Runs a sequence stored under the given configuration with a single interlock,
+	/// with no flight computer connected.
+	async fn run_guarded(name: &str, configuration_id: Option<&str>, condition: &str) -> ErrorCode {
+		let server = tokio::task::block_in_place(|| Server::builder().build()).unwrap();
+		let shared = server.shared;
+
+		{
+			let database = shared.database.connection.lock().await;
+
+			database
+				.execute(
+					"INSERT INTO Sequences (name, configuration_id, script) VALUES (?1, ?2, 'pass')",
+					params![name, configuration_id],
+				)
+				.unwrap();
+
+			database
+				.execute("INSERT INTO Interlocks (sequence_name, condition) VALUES (?1, ?2)", [name, condition])
+				.unwrap();
+		}
+
+		let request = RunSequenceRequest { name: name.to_owned(), force: None };
+
+		run_sequence(State(shared), None, Valid::new(Json(request)).unwrap())
+			.await
+			.unwrap_err()
+			.code()
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn abort_is_dispatched_despite_failing_checks() {
+		// reaching the flight computer, which is not connected, means every check was passed over
+		assert_eq!(run_guarded("abort", None, "KBPT < 50 psi").await, ErrorCode::ComputerNotConnected);
+		assert_eq!(run_guarded("abort", Some("hotfire"), "flight link healthy").await, ErrorCode::ComputerNotConnected);
+
+		assert_eq!(run_guarded("purge", None, "KBPT < 50 psi").await, ErrorCode::InterlocksNotSatisfied);
+		assert_eq!(run_guarded("purge", Some("hotfire"), "flight link healthy").await, ErrorCode::BadRequest);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn refused_adhoc_scripts_are_not_stored() {
+		let server = tokio::task::block_in_place(|| Server::builder().build()).unwrap();
+		let shared = server.shared;
+
+		shared.database.connection
+			.lock()
+			.await
+			.execute("INSERT INTO Interlocks (sequence_name, condition) VALUES ('adhoc-purge', 'KBPT < 50 psi')", [])
+			.unwrap();
+
+		let request = RunAdhocSequenceRequest {
+			name: "purge".to_owned(),
+			script: base64::encode("pass"),
+			force: None,
+		};
+
+		let error = run_adhoc_sequence(State(shared.clone()), None, Valid::new(Json(request)).unwrap())
+			.await
+			.unwrap_err();
+
+		assert_eq!(error.code(), ErrorCode::InterlocksNotSatisfied);
+
+		let stored = shared.database.connection
+			.lock()
+			.await
+			.query_row("SELECT COUNT(*) FROM AdhocSequences", [], |row| row.get::<_, i64>(0))
+			.unwrap();
+
+		assert_eq!(stored, 0);
+	}
+}
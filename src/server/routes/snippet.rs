@@ -0,0 +1,148 @@
+use axum::{extract::State, Json};
+use rusqlite::{params, types::Type, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::server::{
+	self,
+	error::{bad_request, internal, not_found},
+	simulation::{self, SimulationOptions},
+	snippet::Snippet,
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+/// Request struct for deleting a snippet.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeleteSnippetRequest {
+	/// The name of the snippet to delete.
+	pub name: String,
+}
+
+impl Validate for DeleteSnippetRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
+/// Request struct for instantiating a snippet into a full sequence.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstantiateSnippetRequest {
+	/// The name of the snippet to instantiate.
+	pub snippet: String,
+
+	/// The value of each parameter, where parameters which are left out take their defaults.
+	#[serde(default)]
+	pub arguments: BTreeMap<String, String>,
+}
+
+impl Validate for InstantiateSnippetRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("snippet", &self.snippet);
+	}
+}
+
+/// Response struct containing the sequence a snippet was instantiated into.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstantiateSnippetResponse {
+	/// The Python sequence script, ready to be saved as a sequence.
+	pub script: String,
+}
+
+/// Reads a snippet from a row of the `Snippets` table.
+fn snippet_from_row(row: &Row) -> rusqlite::Result<Snippet> {
+	let parameters = row.get::<_, String>(2)?;
+
+	Ok(Snippet {
+		name: row.get(0)?,
+		description: row.get(1)?,
+		parameters: serde_json::from_str(&parameters)
+			.map_err(|error| rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(error)))?,
+		template: row.get(3)?,
+	})
+}
+
+/// Route function which lists the snippets which sequences may be instantiated from.
+pub async fn get_snippets(State(shared): State<Shared>) -> server::Result<Json<Vec<Snippet>>> {
+	let snippets = shared.database
+		.connection
+		.lock()
+		.await
+		.prepare("SELECT name, description, parameters, template FROM Snippets ORDER BY name")
+		.map_err(internal)?
+		.query_map([], snippet_from_row)
+		.map_err(internal)?
+		.collect::<rusqlite::Result<Vec<_>>>()
+		.map_err(internal)?;
+
+	Ok(Json(snippets))
+}
+
+/// Route function which saves a snippet, replacing any of the same name.
+pub async fn save_snippet(
+	State(shared): State<Shared>,
+	Valid(Json(snippet)): Valid<Json<Snippet>>,
+) -> server::Result<()> {
+	let parameters = serde_json::to_string(&snippet.parameters).map_err(internal)?;
+
+	shared.database
+		.connection
+		.lock()
+		.await
+		.execute(
+			"INSERT OR REPLACE INTO Snippets (name, description, parameters, template) VALUES (?1, ?2, ?3, ?4)",
+			params![snippet.name, snippet.description, parameters, snippet.template],
+		)
+		.map_err(internal)?;
+
+	Ok(())
+}
+
+/// Route function which deletes a snippet. Sequences already instantiated from it are kept.
+pub async fn delete_snippet(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<DeleteSnippetRequest>>,
+) -> server::Result<()> {
+	let rows_deleted = shared.database
+		.connection
+		.lock()
+		.await
+		.execute("DELETE FROM Snippets WHERE name = ?1", [&request.name])
+		.map_err(internal)?;
+
+	if rows_deleted == 0 {
+		return Err(not_found(format!("snippet '{}' does not exist", request.name)));
+	}
+
+	Ok(())
+}
+
+/// Route function which instantiates a snippet into a full sequence script
+/// without saving it, so that it may be reviewed before it is saved.
+///
+/// The script is simulated to check that the arguments produced a sequence
+/// which parses, so that a typo in an argument is caught here.
+pub async fn instantiate_snippet(
+	State(shared): State<Shared>,
+	Valid(Json(request)): Valid<Json<InstantiateSnippetRequest>>,
+) -> server::Result<Json<InstantiateSnippetResponse>> {
+	let snippet = shared.database
+		.connection
+		.lock()
+		.await
+		.query_row(
+			"SELECT name, description, parameters, template FROM Snippets WHERE name = ?1",
+			[&request.snippet],
+			snippet_from_row,
+		)
+		.optional()
+		.map_err(internal)?
+		.ok_or_else(|| not_found(format!("snippet '{}' does not exist", request.snippet)))?;
+
+	let script = snippet.instantiate(&request.arguments).map_err(bad_request)?;
+
+	simulation::simulate(&script, &SimulationOptions::default())
+		.map_err(|error| bad_request(format!("instantiated sequence is invalid: {error}")))?;
+
+	Ok(Json(InstantiateSnippetResponse { script }))
+}
@@ -0,0 +1,101 @@
+use axum::{body::Body, extract::State, http::HeaderMap, response::Response, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+	self,
+	api::StandbyStatus,
+	auth::{self, Session},
+	error::{conflict, internal, unauthorized},
+	standby,
+	Shared,
+};
+
+/// Request struct for fencing a former primary.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FenceRequest {
+	/// The term at which the standby sending the request was promoted.
+	pub term: u64,
+}
+
+/// Checks that a request to mirror or fence this server comes from an admin,
+/// or from a standby sending the standby key this server is configured with.
+fn require_peer(shared: &Shared, session: Option<&Session>, headers: &HeaderMap) -> server::Result<()> {
+	let config = shared.config.current();
+
+	let key = headers
+		.get(standby::KEY_HEADER)
+		.and_then(|value| value.to_str().ok());
+
+	// the keys are compared by their hashes, so that the comparison takes the same time however much of them matches
+	if let (Some(key), Some(expected)) = (key, &config.standby.key) {
+		if auth::hash_token(key) == auth::hash_token(expected) {
+			return Ok(());
+		}
+	}
+
+	match session {
+		Some(session) => session.require_admin(),
+		None => Err(unauthorized("an admin session or the standby key is required")),
+	}
+}
+
+/// Route function which streams vehicle state and configuration to a standby,
+/// for as long as it stays connected.
+pub async fn stream_to_standby(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	headers: HeaderMap,
+) -> server::Result<Response> {
+	require_peer(&shared, session.as_ref(), &headers)?;
+
+	if !shared.role.is_primary() {
+		return Err(conflict("only the primary may be mirrored"));
+	}
+
+	Ok(Response::new(Body::from_stream(standby::stream_to_standby(&shared))))
+}
+
+/// Route function which returns the role of the server.
+pub async fn get_standby_status(State(shared): State<Shared>) -> server::Result<Json<StandbyStatus>> {
+	Ok(Json(StandbyStatus {
+		role: shared.role.role(),
+		term: shared.role.term(),
+		primary: shared.config.current().standby.primary.clone(),
+	}))
+}
+
+/// Route function which promotes a standby to primary, so that it takes over the flight computer.
+pub async fn promote(State(shared): State<Shared>, session: Session) -> server::Result<Json<StandbyStatus>> {
+	session.require_admin()?;
+
+	if shared.role.is_primary() {
+		return Err(conflict("server is already the primary"));
+	}
+
+	standby::promote(&shared, "promoted by an operator")
+		.await
+		.map_err(internal)?;
+
+	get_standby_status(State(shared)).await
+}
+
+/// Route function through which a promoted standby fences this server, so that
+/// it cannot act as the primary alongside the standby.
+pub async fn fence(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	headers: HeaderMap,
+	Json(request): Json<FenceRequest>,
+) -> server::Result<Json<StandbyStatus>> {
+	require_peer(&shared, session.as_ref(), &headers)?;
+
+	if !standby::fence(&shared, request.term).await {
+		return Err(conflict(format!(
+			"term {} is no later than this server's term of {}",
+			request.term,
+			shared.role.term(),
+		)));
+	}
+
+	get_standby_status(State(shared)).await
+}
@@ -0,0 +1,66 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::server::{self, api::{ClocksResponse, MetricsResponse}, clock, lockout::ActiveSequence, outbox::{OutboxMessage, OutboxStatus}, supervisor::TaskHealth, Shared};
+
+/// Route function which returns the current server metrics.
+pub async fn get_metrics(State(shared): State<Shared>) -> server::Result<Json<MetricsResponse>> {
+	let telemetry = shared.metrics.telemetry.lock().await.clone();
+	let telemetry_loss_ratio = telemetry.loss_ratio();
+	let latency = shared.metrics.latency.lock().await.report();
+
+	Ok(Json(MetricsResponse { telemetry, telemetry_loss_ratio, latency }))
+}
+
+/// Route function which returns the health of each long-running server task, keyed by name.
+pub async fn get_tasks(State(shared): State<Shared>) -> server::Result<Json<BTreeMap<String, TaskHealth>>> {
+	Ok(Json(shared.supervisor.health().await))
+}
+
+/// Route function which returns the sequences currently running, keyed by name.
+pub async fn get_active_sequences(State(shared): State<Shared>) -> server::Result<Json<BTreeMap<String, ActiveSequence>>> {
+	Ok(Json(shared.lockout.active().await))
+}
+
+/// Route function which returns the skew of the clocks of GUI clients and the flight computer.
+pub async fn get_clocks(State(shared): State<Shared>) -> server::Result<Json<ClocksResponse>> {
+	let servo_time = clock::now();
+	let threshold_seconds = shared.config.current().notifications.events.clock_skew_seconds;
+	let participants = shared.metrics.clocks.lock().await.current(servo_time);
+
+	let drifted = participants
+		.iter()
+		.filter(|(_, skew)| skew.drifted(threshold_seconds))
+		.map(|(participant, _)| participant.clone())
+		.collect();
+
+	Ok(Json(ClocksResponse { servo_time, threshold_seconds, participants, drifted }))
+}
+
+/// Response struct listing the messages to the vehicle computers which have not reached their links.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutboxResponse {
+	/// The number of messages still being written.
+	pub pending: usize,
+
+	/// The number of messages which failed to be written.
+	pub failed: usize,
+
+	/// Every message still being written or which failed, oldest first.
+	pub messages: Vec<OutboxMessage>,
+}
+
+/// Route function which returns the messages to the vehicle computers which are
+/// still being written and those which failed, so that a command which never
+/// reached the vehicle does not go unnoticed.
+pub async fn get_outbox(State(shared): State<Shared>) -> server::Result<Json<OutboxResponse>> {
+	let messages = shared.outbox.messages();
+
+	let failed = messages
+		.iter()
+		.filter(|message| message.entry.status == OutboxStatus::Failed)
+		.count();
+
+	Ok(Json(OutboxResponse { pending: messages.len() - failed, failed, messages }))
+}
@@ -0,0 +1,93 @@
+use axum::{extract::{Query, State}, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+	self,
+	api::{SnapshotKey, SyncBatch, SyncCounts},
+	audit,
+	auth::Session,
+	error::internal,
+	storage::SnapshotRange,
+	sync,
+	validation::{Valid, Validate, Validator},
+	Shared,
+};
+
+/// Query parameters for fetching a batch to sync to another server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SyncQuery {
+	/// The earliest time of a snapshot or event to include, as a Unix timestamp.
+	pub from: Option<f64>,
+
+	/// The latest time of a snapshot or event to include, as a Unix timestamp.
+	pub to: Option<f64>,
+
+	/// The time of the last snapshot of the previous batch, which snapshots must
+	/// be strictly later than. Tables and events come only with the first batch.
+	pub after: Option<f64>,
+
+	/// The ID of the last snapshot of the previous batch, as given by the batch,
+	/// so that snapshots logged at the same time as it which did not fit in the
+	/// batch are not skipped.
+	pub after_id: Option<i64>,
+
+	/// The most snapshots to include, up to the size of a full batch.
+	pub limit: Option<usize>,
+}
+
+impl Validate for SyncQuery {
+	fn validate(&self, validator: &mut Validator) {
+		validator.ordered(self.from, self.to);
+		validator.check(self.limit != Some(0), "limit", "must be at least 1");
+		validator.check(self.after_id.is_none() || self.after.is_some(), "after_id", "must be given along with after");
+	}
+}
+
+/// Route function which returns a batch of snapshots, configurations,
+/// sequences, and events, as pulled by another server with `servo sync`.
+pub async fn get_sync_batch(
+	State(shared): State<Shared>,
+	Valid(Query(query)): Valid<Query<SyncQuery>>,
+) -> server::Result<Json<SyncBatch>> {
+	let range = SnapshotRange {
+		from: query.from,
+		to: query.to,
+		after: query.after.map(|recorded_at| match query.after_id {
+			Some(id) => SnapshotKey { recorded_at, id },
+			None => SnapshotKey::after_time(recorded_at),
+		}),
+		limit: query.limit,
+	};
+
+	let batch = sync::collect(&shared.database, &*shared.storage, range)
+		.await
+		.map_err(internal)?;
+
+	Ok(Json(batch))
+}
+
+/// Route function which merges a batch pushed by another server, skipping
+/// anything this server already has, and returns how much of it was new.
+pub async fn post_sync_batch(
+	State(shared): State<Shared>,
+	session: Session,
+	Json(batch): Json<SyncBatch>,
+) -> server::Result<Json<SyncCounts>> {
+	session.require_admin()?;
+
+	let counts = sync::merge(&shared.database, &*shared.storage, batch)
+		.await
+		.map_err(internal)?;
+
+	let detail = format!(
+		"{} snapshots, {} rows of configuration, and {} events",
+		counts.snapshots,
+		counts.rows,
+		counts.events,
+	);
+
+	audit::record(&*shared.database.connection.lock().await, Some(&session.username), "sync", &detail)
+		.map_err(internal)?;
+
+	Ok(Json(counts))
+}
@@ -0,0 +1,55 @@
+use axum::{extract::State, Json};
+
+use crate::server::{
+	self,
+	api::ThresholdChanges,
+	audit,
+	auth::Session,
+	error::{bad_request, internal},
+	threshold::{self, Thresholds},
+	Shared,
+};
+
+/// Route function which retrieves the redlines of every channel.
+pub async fn get_thresholds(State(shared): State<Shared>) -> server::Result<Json<Thresholds>> {
+	let thresholds = threshold::load(&*shared.database.connection.lock().await).map_err(internal)?;
+	Ok(Json(thresholds))
+}
+
+/// Route function which replaces the redlines of every channel, such as with
+/// those of a redline file, removing those of channels left out.
+pub async fn set_thresholds(
+	State(shared): State<Shared>,
+	session: Option<Session>,
+	Json(thresholds): Json<Thresholds>,
+) -> server::Result<Json<ThresholdChanges>> {
+	let problems = threshold::validate(&thresholds);
+
+	if !problems.is_empty() {
+		return Err(bad_request(format!("redlines are invalid: {}", problems.join("; "))));
+	}
+
+	let mut database = shared.database
+		.connection
+		.lock()
+		.await;
+
+	let previous = threshold::load(&database).map_err(internal)?;
+	let changes = threshold::diff(&previous, &thresholds);
+
+	if changes.is_empty() {
+		return Ok(Json(ThresholdChanges { changes }));
+	}
+
+	threshold::replace(&mut database, &thresholds).map_err(internal)?;
+
+	audit::record(
+		&database,
+		session.as_ref().map(|session| session.username.as_str()),
+		"set redlines",
+		&changes.join("; "),
+	)
+	.map_err(internal)?;
+
+	Ok(Json(ThresholdChanges { changes }))
+}
@@ -3,7 +3,14 @@ use common::comm::Trigger;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 
-use crate::server::{self, error::internal, Shared};
+use crate::server::{self, error::internal, validation::{Valid, Validate, Validator}, Shared};
+
+impl Validate for Trigger {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+		validator.non_empty("condition", &self.condition);
+	}
+}
 
 /// Route function which returns all existing triggers in the database.
 pub async fn get_triggers(State(shared): State<Shared>) -> server::Result<Json<Vec<Trigger>>> {
@@ -31,7 +38,7 @@ pub async fn get_triggers(State(shared): State<Shared>) -> server::Result<Json<V
 }
 
 /// Route function which creates or updates a trigger in the database and on the flight computer.
-pub async fn set_trigger(State(shared): State<Shared>, Json(request): Json<Trigger>) -> server::Result<()> {
+pub async fn set_trigger(State(shared): State<Shared>, Valid(Json(request)): Valid<Json<Trigger>>) -> server::Result<()> {
 	let database = shared.database
 		.connection
 		.lock()
@@ -72,8 +79,14 @@ pub struct DeleteTriggerRequest {
 	pub name: String
 }
 
+impl Validate for DeleteTriggerRequest {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+	}
+}
+
 /// Route function which deletes a trigger from the database and sets it inactive on the flight computer.
-pub async fn delete_trigger(State(shared): State<Shared>, Json(request): Json<DeleteTriggerRequest>) -> server::Result<()> {
+pub async fn delete_trigger(State(shared): State<Shared>, Valid(Json(request)): Valid<Json<DeleteTriggerRequest>>) -> server::Result<()> {
 	let database = shared.database
 		.connection
 		.lock()
@@ -0,0 +1,112 @@
+use common::comm::{ChannelType, Computer, SensorType, Unit, ValveState};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{export, hazard::HazardLevel, import, units::UnitSystem};
+
+/// The largest Postcard encoding of a variant which fits in a single byte, since
+/// Postcard encodes the index of a variant as a varint.
+const MAX_SINGLE_BYTE_INDEX: u8 = 0x7f;
+
+/// A variant of an enumeration, as written in the REST API and as encoded by Postcard.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EnumVariant {
+	/// The name of the variant as written in JSON, such as `open`.
+	pub name: String,
+
+	/// The integer the variant is encoded as by Postcard, which is its index,
+	/// as sent between servo, the flight computer, and the SAM boards.
+	pub encoding: u32,
+}
+
+/// The enumerations and formats this server understands, so that clients such as
+/// the GUI read them from the server rather than keeping copies which drift.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Schema {
+	/// The version of servo describing itself.
+	pub version: String,
+
+	/// The units readings may be reported in.
+	pub units: Vec<EnumVariant>,
+
+	/// The systems of units measurements may be presented in.
+	pub unit_systems: Vec<EnumVariant>,
+
+	/// The states a valve may be commanded to or found in.
+	pub valve_states: Vec<EnumVariant>,
+
+	/// The types of sensor a channel may be mapped as.
+	pub sensor_types: Vec<EnumVariant>,
+
+	/// The types of channel a SAM board may report data points from.
+	pub channel_types: Vec<EnumVariant>,
+
+	/// The computers of the vehicle which channels may be mapped to.
+	pub computers: Vec<EnumVariant>,
+
+	/// The levels of hazard a channel may be classified at, from least to most hazardous.
+	pub hazard_levels: Vec<EnumVariant>,
+
+	/// The formats data may be exported in.
+	pub export_formats: Vec<String>,
+
+	/// The formats data may be imported from.
+	pub import_formats: Vec<String>,
+}
+
+impl Schema {
+	/// Describes the enumerations of the common crate and the formats of this
+	/// server, as they were compiled into it.
+	pub fn current() -> Self {
+		Schema {
+			version: env!("CARGO_PKG_VERSION").to_owned(),
+			units: variants::<Unit>(),
+			unit_systems: variants::<UnitSystem>(),
+			valve_states: variants::<ValveState>(),
+			sensor_types: variants::<SensorType>(),
+			channel_types: variants::<ChannelType>(),
+			computers: variants::<Computer>(),
+			hazard_levels: variants::<HazardLevel>(),
+			export_formats: export::FORMATS.map(str::to_owned).to_vec(),
+			import_formats: import::FORMATS.map(str::to_owned).to_vec(),
+		}
+	}
+}
+
+/// Lists the unit variants of an enumeration in order, by decoding each index in
+/// turn until one is not a variant, so that the list follows the enumeration as
+/// compiled in rather than being kept by hand.
+pub fn variants<T: DeserializeOwned + Serialize>() -> Vec<EnumVariant> {
+	(0..=MAX_SINGLE_BYTE_INDEX)
+		.map_while(|index| {
+			let variant = postcard::from_bytes::<T>(&[index]).ok()?;
+
+			let name = match serde_json::to_value(&variant).ok()? {
+				serde_json::Value::String(name) => name,
+				_ => return None,
+			};
+
+			Some(EnumVariant { name, encoding: u32::from(index) })
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn variants_follow_their_postcard_encoding() {
+		let states = variants::<ValveState>();
+
+		assert!(!states.is_empty());
+
+		for state in &states {
+			let variant = serde_json::from_value::<ValveState>(serde_json::Value::String(state.name.clone())).unwrap();
+			assert_eq!(postcard::to_allocvec(&variant).unwrap(), [state.encoding as u8]);
+		}
+
+		let levels = variants::<HazardLevel>().into_iter().map(|level| level.name).collect::<Vec<_>>();
+		assert_eq!(levels.first().map(String::as_str), Some("none"));
+		assert_eq!(levels.len(), 3);
+	}
+}
@@ -0,0 +1,166 @@
+use common::comm::VehicleState;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+
+/// The most memory a compiled selector may take, so that a pathological
+/// regular expression in a request cannot exhaust the server.
+const MAX_COMPILED_SIZE: usize = 1 << 20;
+
+/// A set of channels chosen by name, glob, or regular expression, written the
+/// same way in every API which selects channels:
+///
+/// - `KBPT` selects exactly the channel named `KBPT`.
+/// - `FU_*` and `*_PT` are globs over the whole name, where `*` stands for any
+///   run of characters and `?` for any single character.
+/// - `/^(FU|OX)_PT\d$/` is a regular expression between slashes, which selects
+///   every channel it matches anywhere in its name unless anchored.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+	/// The channels selected by exact name.
+	names: HashSet<String>,
+
+	/// The globs and regular expressions, each compiled into a regular expression.
+	patterns: Vec<Regex>,
+}
+
+impl Selection {
+	/// Parses a list of selectors, rejecting the first which is not valid.
+	pub fn parse<S: AsRef<str>>(selectors: impl IntoIterator<Item = S>) -> Result<Self, String> {
+		let mut selection = Selection::default();
+
+		for selector in selectors {
+			let selector = selector.as_ref().trim();
+
+			if selector.is_empty() {
+				return Err("channel selector must not be empty".to_owned());
+			}
+
+			if let Some(regex) = selector.strip_prefix('/').and_then(|selector| selector.strip_suffix('/')) {
+				let regex = RegexBuilder::new(regex)
+					.size_limit(MAX_COMPILED_SIZE)
+					.build()
+					.map_err(|error| format!("invalid regular expression '{selector}': {error}"))?;
+
+				selection.patterns.push(regex);
+			} else if selector.contains(['*', '?']) {
+				selection.patterns.push(glob(selector)?);
+			} else {
+				selection.names.insert(selector.to_owned());
+			}
+		}
+
+		Ok(selection)
+	}
+
+	/// Parses a comma-separated list of selectors, as given in a query string or on the command line.
+	pub fn parse_list(list: &str) -> Result<Self, String> {
+		Self::parse(split(list))
+	}
+
+	/// Whether no channel could be selected.
+	pub fn is_empty(&self) -> bool {
+		self.names.is_empty() && self.patterns.is_empty()
+	}
+
+	/// Whether a channel is selected.
+	pub fn matches(&self, channel: &str) -> bool {
+		self.names.contains(channel) || self.patterns.iter().any(|pattern| pattern.is_match(channel))
+	}
+
+	/// Keeps only the selected sensors and valves of a vehicle state.
+	pub fn retain(&self, vehicle_state: &mut VehicleState) {
+		vehicle_state.sensor_readings.retain(|name, _| self.matches(name));
+		vehicle_state.valve_states.retain(|name, _| self.matches(name));
+	}
+}
+
+/// Splits a comma-separated list of selectors, leaving the commas within a
+/// regular expression, such as in `/PT{1,2}/`, where they are.
+pub fn split(list: &str) -> Vec<String> {
+	let mut selectors = Vec::new();
+	let mut selector = String::new();
+	let mut in_regex = false;
+	let mut escaped = false;
+
+	for character in list.chars() {
+		match character {
+			',' if !in_regex => {
+				selectors.push(std::mem::take(&mut selector));
+				continue;
+			},
+			'/' if !escaped && (in_regex || selector.trim().is_empty()) => in_regex = !in_regex,
+			_ => {},
+		}
+
+		escaped = in_regex && character == '\\' && !escaped;
+		selector.push(character);
+	}
+
+	selectors.push(selector);
+
+	selectors
+		.into_iter()
+		.map(|selector| selector.trim().to_owned())
+		.filter(|selector| !selector.is_empty())
+		.collect()
+}
+
+/// Compiles a glob into a regular expression matching whole channel names.
+fn glob(selector: &str) -> Result<Regex, String> {
+	let mut regex = String::from("^");
+
+	for character in selector.chars() {
+		match character {
+			'*' => regex.push_str(".*"),
+			'?' => regex.push('.'),
+			_ => regex.push_str(&regex::escape(character.encode_utf8(&mut [0; 4]))),
+		}
+	}
+
+	regex.push('$');
+
+	Regex::new(&regex).map_err(|error| format!("invalid glob '{selector}': {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+	use super::*;
+
+	#[test]
+	fn names_globs_and_regexes_select_channels() {
+		let selection = Selection::parse(["KBPT", "FU_*", "*_PT", "OX_T?", "/^WT(PT|TC)\\d$/"]).unwrap();
+
+		for channel in ["KBPT", "FU_MAIN", "FU_", "OX_PT", "OX_T1", "WTPT2"] {
+			assert!(selection.matches(channel), "{channel} should be selected");
+		}
+
+		for channel in ["KBPT2", "XFU_MAIN", "OX_PT_2", "OX_T12", "WTPT", "AWTTC1"] {
+			assert!(!selection.matches(channel), "{channel} should not be selected");
+		}
+
+		// characters special to regular expressions are literal in globs and names
+		let selection = Selection::parse(["A.B*"]).unwrap();
+		assert!(selection.matches("A.B_1") && !selection.matches("AXB_1"));
+
+		assert!(Selection::parse(["/(/"]).is_err());
+		assert!(Selection::parse([" "]).is_err());
+		assert!(Selection::parse(Vec::<String>::new()).unwrap().is_empty());
+	}
+
+	#[test]
+	fn lists_split_outside_of_regexes() {
+		assert_eq!(split("FU_*, KBPT,,/PT{1,2}/,*_TC"), ["FU_*", "KBPT", "/PT{1,2}/", "*_TC"]);
+		assert_eq!(split("/a\\/b,c/,d"), ["/a\\/b,c/", "d"]);
+		assert!(split(" , ").is_empty());
+
+		let mut vehicle_state = VehicleState::new();
+		vehicle_state.sensor_readings.insert("FU_PT".to_owned(), Measurement { value: 1.0, unit: Unit::Psi });
+		vehicle_state.sensor_readings.insert("OX_PT".to_owned(), Measurement { value: 2.0, unit: Unit::Psi });
+		vehicle_state.valve_states.insert("FU_MV".to_owned(), CompositeValveState { commanded: ValveState::Open, actual: ValveState::Open });
+
+		Selection::parse_list("FU_*").unwrap().retain(&mut vehicle_state);
+		assert!(vehicle_state.sensor_readings.keys().eq(["FU_PT"]));
+		assert!(vehicle_state.valve_states.keys().eq(["FU_MV"]));
+	}
+}
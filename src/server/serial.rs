@@ -0,0 +1,54 @@
+use std::{fs::{File, OpenOptions}, io, path::Path, process::Command};
+
+/// Opens a serial device for reading and writing raw bytes at a baud rate.
+///
+/// The device is set up with the system `stty`, as the standard library cannot
+/// set the line discipline or baud rate of a terminal device itself.
+pub fn open(device: &Path, baud_rate: u32) -> io::Result<File> {
+	let port = OpenOptions::new()
+		.read(true)
+		.write(true)
+		.open(device)?;
+
+	let mut command = Command::new("stty");
+
+	// BSD stty, as on macOS, takes the device with -f rather than -F.
+	if cfg!(target_os = "macos") {
+		command.arg("-f");
+	} else {
+		command.arg("-F");
+	}
+
+	let output = command
+		.arg(device)
+		.args(stty_arguments(baud_rate))
+		.output()?;
+
+	if !output.status.success() {
+		return Err(io::Error::other(format!(
+			"failed to configure {}: {}",
+			device.display(),
+			String::from_utf8_lossy(&output.stderr).trim(),
+		)));
+	}
+
+	Ok(port)
+}
+
+/// The arguments to `stty` which make a device pass bytes through untouched at a baud rate.
+fn stty_arguments(baud_rate: u32) -> Vec<String> {
+	// raw turns off every translation of bytes, and -echo stops the bytes received
+	// from being sent back, which would otherwise garble the other direction.
+	vec!["raw".to_owned(), "-echo".to_owned(), "clocal".to_owned(), baud_rate.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn devices_are_set_to_raw_at_the_baud_rate() {
+		assert_eq!(stty_arguments(115_200), ["raw", "-echo", "clocal", "115200"]);
+		assert!(open(Path::new("/dev/does-not-exist"), 9600).is_err());
+	}
+}
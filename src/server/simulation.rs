@@ -0,0 +1,1817 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt, rc::Rc};
+
+/// The most statements executed before a simulation gives up.
+const MAX_STEPS: usize = 1_000_000;
+
+/// The most iterations of a single loop before it is assumed to never exit.
+const MAX_LOOP_ITERATIONS: usize = 100_000;
+
+/// The most nested function calls, which bounds recursion.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// The most actions recorded in a timeline.
+const MAX_TIMELINE_ENTRIES: usize = 10_000;
+
+/// Options which describe the conditions a sequence is simulated under.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SimulationOptions {
+	/// The assumed range of each sensor, as `[min, max]`. Sensors without an
+	/// assumption make every condition which reads them indeterminate.
+	pub assumptions: BTreeMap<String, [f64; 2]>,
+
+	/// Waits at least this many seconds long are flagged in the report.
+	pub long_wait_seconds: f64,
+}
+
+impl Default for SimulationOptions {
+	fn default() -> Self {
+		SimulationOptions {
+			assumptions: BTreeMap::new(),
+			long_wait_seconds: 60.0,
+		}
+	}
+}
+
+/// An action a sequence would take on the vehicle.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimulatedAction {
+	/// Opens a valve.
+	Open {
+		/// The name of the valve.
+		valve: String,
+	},
+
+	/// Closes a valve.
+	Close {
+		/// The name of the valve.
+		valve: String,
+	},
+
+	/// Waits a fixed amount of time.
+	Wait {
+		/// The number of seconds waited.
+		seconds: f64,
+	},
+
+	/// Waits until a condition holds or a timeout passes.
+	WaitUntil {
+		/// The number of seconds waited under the assumptions.
+		seconds: f64,
+
+		/// Whether the condition is met under the assumptions, or `None` if it cannot be determined.
+		condition_met: Option<bool>,
+	},
+
+	/// Aborts, ending the sequence.
+	Abort,
+}
+
+/// A single action along with when the sequence would take it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TimelineEntry {
+	/// The number of seconds after the start of the sequence at which the action begins.
+	pub at_seconds: f64,
+
+	/// The line of the script which takes the action.
+	pub line: usize,
+
+	/// The action taken.
+	pub action: SimulatedAction,
+}
+
+/// Something in a sequence worth reviewing before it runs, or which could not be simulated.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SimulationWarning {
+	/// The line of the script the warning is about.
+	pub line: usize,
+
+	/// What was found.
+	pub message: String,
+}
+
+/// The outcome of simulating a sequence.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SimulationReport {
+	/// Every action the sequence would take, in order.
+	pub timeline: Vec<TimelineEntry>,
+
+	/// The number of seconds the sequence would take to run.
+	pub total_seconds: f64,
+
+	/// Whether the simulation reached the end of the sequence, rather than giving up.
+	pub completed: bool,
+
+	/// Long waits, assumptions made, and anything which could not be simulated.
+	pub warnings: Vec<SimulationWarning>,
+}
+
+/// An error which prevents a script from being simulated at all.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SimulationError {
+	/// The line on which the error was found.
+	pub line: usize,
+
+	/// What went wrong.
+	pub message: String,
+}
+
+impl fmt::Display for SimulationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl std::error::Error for SimulationError {}
+
+/// Symbolically executes a sequence, returning the timeline of actions it would take.
+///
+/// Only the subset of Python used by sequences is understood: valve commands,
+/// waits, arithmetic, conditionals, loops, and functions. Sensor readings are
+/// ranges taken from the assumptions, so a condition may be true, false, or
+/// indeterminate. Indeterminate branches are assumed to be taken, and each
+/// assumption made is reported as a warning.
+pub fn simulate(script: &str, options: &SimulationOptions) -> Result<SimulationReport, SimulationError> {
+	let tokens = tokenize(script)?;
+	let program = Parser { tokens, position: 0 }.parse_program()?;
+
+	let mut simulator = Simulator {
+		options,
+		now: 0.0,
+		line: 1,
+		steps: 0,
+		call_depth: 0,
+		stopped: false,
+		gave_up: false,
+		timeline: Vec::new(),
+		warnings: Vec::new(),
+		warned: HashSet::new(),
+		globals: HashMap::new(),
+		locals: Vec::new(),
+		functions: HashMap::new(),
+	};
+
+	simulator.execute_block(&program);
+
+	Ok(SimulationReport {
+		timeline: simulator.timeline,
+		total_seconds: simulator.now,
+		completed: !simulator.gave_up,
+		warnings: simulator.warnings,
+	})
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+	Name(String),
+	Number(f64),
+	Str,
+	Op(&'static str),
+	Newline,
+	Indent,
+	Dedent,
+	End,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+	kind: TokenKind,
+	line: usize,
+}
+
+const OPERATORS: [&str; 48] = [
+	"**=", "//=", ">>=", "<<=", "...",
+	"**", "//", "==", "!=", "<=", ">=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=", "->", ":=", "<<", ">>",
+	"+", "-", "*", "/", "%", "<", ">", "=", "(", ")", "[", "]", "{", "}", ",", ":", ".", ";", "@", "&", "|", "^", "~", "!",
+];
+
+/// Splits a script into tokens, including the indentation tokens which delimit blocks.
+fn tokenize(script: &str) -> Result<Vec<Token>, SimulationError> {
+	let chars = script.chars().collect::<Vec<_>>();
+	let error = |line: usize, message: &str| SimulationError { line, message: message.to_owned() };
+
+	let mut tokens = Vec::new();
+	let mut indents = vec![0];
+	let mut position = 0;
+	let mut line = 1;
+	let mut depth = 0usize;
+	let mut at_line_start = true;
+
+	while position < chars.len() {
+		if at_line_start && depth == 0 {
+			let mut column = 0;
+
+			while position < chars.len() && matches!(chars[position], ' ' | '\t') {
+				column = if chars[position] == '\t' { (column / 8 + 1) * 8 } else { column + 1 };
+				position += 1;
+			}
+
+			// blank and comment-only lines do not affect indentation
+			if position >= chars.len() || matches!(chars[position], '\n' | '\r' | '#') {
+				while position < chars.len() && chars[position] != '\n' {
+					position += 1;
+				}
+
+				position += 1;
+				line += 1;
+				continue;
+			}
+
+			at_line_start = false;
+
+			if column > *indents.last().unwrap_or(&0) {
+				indents.push(column);
+				tokens.push(Token { kind: TokenKind::Indent, line });
+			}
+
+			while column < *indents.last().unwrap_or(&0) {
+				indents.pop();
+				tokens.push(Token { kind: TokenKind::Dedent, line });
+			}
+
+			if column != *indents.last().unwrap_or(&0) {
+				return Err(error(line, "unindent does not match any outer indentation level"));
+			}
+		}
+
+		let c = chars[position];
+
+		if c == '\n' {
+			if depth == 0 {
+				tokens.push(Token { kind: TokenKind::Newline, line });
+				at_line_start = true;
+			}
+
+			position += 1;
+			line += 1;
+		} else if c == '\\' && chars.get(position + 1) == Some(&'\n') {
+			position += 2;
+			line += 1;
+		} else if c.is_whitespace() {
+			position += 1;
+		} else if c == '#' {
+			while position < chars.len() && chars[position] != '\n' {
+				position += 1;
+			}
+		} else if let Some(prefix_length) = string_prefix(&chars[position..]) {
+			let start_line = line;
+			position += prefix_length;
+
+			let quote = chars[position];
+			let triple = chars.get(position + 1) == Some(&quote) && chars.get(position + 2) == Some(&quote);
+			position += if triple { 3 } else { 1 };
+
+			loop {
+				let Some(&c) = chars.get(position) else {
+					return Err(error(start_line, "unterminated string"));
+				};
+
+				if c == '\\' {
+					line += usize::from(chars.get(position + 1) == Some(&'\n'));
+					position += 2;
+				} else if c == quote && (!triple || (chars.get(position + 1) == Some(&quote) && chars.get(position + 2) == Some(&quote))) {
+					position += if triple { 3 } else { 1 };
+					break;
+				} else if c == '\n' {
+					if !triple {
+						return Err(error(start_line, "unterminated string"));
+					}
+
+					line += 1;
+					position += 1;
+				} else {
+					position += 1;
+				}
+			}
+
+			tokens.push(Token { kind: TokenKind::Str, line: start_line });
+		} else if c.is_ascii_digit() || (c == '.' && chars.get(position + 1).is_some_and(char::is_ascii_digit)) {
+			let start = position;
+
+			while position < chars.len() {
+				let c = chars[position];
+				let exponent_sign = matches!(c, '+' | '-') && matches!(chars[position - 1], 'e' | 'E');
+
+				if c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign {
+					position += 1;
+				} else {
+					break;
+				}
+			}
+
+			let text = chars[start..position]
+				.iter()
+				.filter(|&&c| c != '_')
+				.collect::<String>();
+
+			let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+				i64::from_str_radix(hex, 16).ok().map(|value| value as f64)
+			} else {
+				text.trim_end_matches(['j', 'J']).parse::<f64>().ok()
+			};
+
+			let value = value.ok_or_else(|| error(line, &format!("invalid number '{text}'")))?;
+			tokens.push(Token { kind: TokenKind::Number(value), line });
+		} else if c.is_alphabetic() || c == '_' {
+			let start = position;
+
+			while position < chars.len() && (chars[position].is_alphanumeric() || chars[position] == '_') {
+				position += 1;
+			}
+
+			let name = chars[start..position].iter().collect::<String>();
+			tokens.push(Token { kind: TokenKind::Name(name), line });
+		} else {
+			let operator = OPERATORS
+				.iter()
+				.find(|operator| operator.chars().enumerate().all(|(i, c)| chars.get(position + i) == Some(&c)))
+				.ok_or_else(|| error(line, &format!("unexpected character '{c}'")))?;
+
+			match *operator {
+				"(" | "[" | "{" => depth += 1,
+				")" | "]" | "}" => depth = depth.saturating_sub(1),
+				_ => {},
+			}
+
+			position += operator.len();
+			tokens.push(Token { kind: TokenKind::Op(operator), line });
+		}
+	}
+
+	tokens.push(Token { kind: TokenKind::Newline, line });
+
+	for _ in 1..indents.len() {
+		tokens.push(Token { kind: TokenKind::Dedent, line });
+	}
+
+	tokens.push(Token { kind: TokenKind::End, line });
+	Ok(tokens)
+}
+
+/// The length of the prefix before the opening quote if the characters start a string literal.
+fn string_prefix(chars: &[char]) -> Option<usize> {
+	let prefix_length = chars
+		.iter()
+		.take(3)
+		.take_while(|c| matches!(c.to_ascii_lowercase(), 'r' | 'b' | 'f' | 'u'))
+		.count();
+
+	matches!(chars.get(prefix_length), Some('"' | '\'')).then_some(prefix_length)
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+	Number(f64),
+	Opaque,
+	Name(String),
+	List(Vec<Expr>),
+	Lambda(Box<Expr>),
+	Unary(&'static str, Box<Expr>),
+	Binary(&'static str, Box<Expr>, Box<Expr>),
+	Compare(Box<Expr>, Vec<(&'static str, Expr)>),
+	And(Box<Expr>, Box<Expr>),
+	Or(Box<Expr>, Box<Expr>),
+	Not(Box<Expr>),
+	Conditional { condition: Box<Expr>, then: Box<Expr>, otherwise: Box<Expr> },
+	Attribute(Box<Expr>, String),
+	Call { function: Box<Expr>, args: Vec<Expr>, keywords: Vec<(String, Expr)> },
+}
+
+#[derive(Debug)]
+struct FunctionDef {
+	parameters: Vec<(String, Option<Expr>)>,
+	body: Vec<Statement>,
+}
+
+#[derive(Debug)]
+enum StatementKind {
+	Expr(Expr),
+	Assign { target: Option<String>, operator: Option<&'static str>, value: Expr },
+	If { branches: Vec<(Expr, Vec<Statement>)>, otherwise: Vec<Statement> },
+	While { condition: Expr, body: Vec<Statement> },
+	For { variable: Option<String>, iterable: Expr, body: Vec<Statement> },
+	Def { name: String, function: Rc<FunctionDef> },
+	Try { body: Vec<Statement>, finally: Vec<Statement> },
+	Block(Vec<Statement>),
+	Return(Option<Expr>),
+	Raise,
+	Break,
+	Continue,
+	Ignored,
+}
+
+#[derive(Debug)]
+struct Statement {
+	line: usize,
+	kind: StatementKind,
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	position: usize,
+}
+
+impl Parser {
+	fn peek(&self) -> &TokenKind {
+		&self.tokens[self.position.min(self.tokens.len() - 1)].kind
+	}
+
+	fn line(&self) -> usize {
+		self.tokens[self.position.min(self.tokens.len() - 1)].line
+	}
+
+	fn advance(&mut self) -> TokenKind {
+		let kind = self.peek().clone();
+		self.position += 1;
+		kind
+	}
+
+	fn error<T>(&self, message: impl ToString) -> Result<T, SimulationError> {
+		Err(SimulationError { line: self.line(), message: message.to_string() })
+	}
+
+	fn at_op(&self, operator: &str) -> bool {
+		matches!(self.peek(), TokenKind::Op(op) if *op == operator)
+	}
+
+	fn at_name(&self, name: &str) -> bool {
+		matches!(self.peek(), TokenKind::Name(n) if n == name)
+	}
+
+	fn eat_op(&mut self, operator: &str) -> bool {
+		let at = self.at_op(operator);
+		self.position += usize::from(at);
+		at
+	}
+
+	fn eat_name(&mut self, name: &str) -> bool {
+		let at = self.at_name(name);
+		self.position += usize::from(at);
+		at
+	}
+
+	fn expect_op(&mut self, operator: &str) -> Result<(), SimulationError> {
+		if self.eat_op(operator) {
+			Ok(())
+		} else {
+			self.error(format!("expected '{operator}'"))
+		}
+	}
+
+	fn expect_name(&mut self) -> Result<String, SimulationError> {
+		match self.advance() {
+			TokenKind::Name(name) => Ok(name),
+			_ => {
+				self.position -= 1;
+				self.error("expected a name")
+			},
+		}
+	}
+
+	/// Skips tokens up to the next top-level `:`, such as the header of a `with` statement.
+	fn skip_to_colon(&mut self) -> Result<(), SimulationError> {
+		let mut depth = 0usize;
+
+		loop {
+			match self.peek() {
+				TokenKind::Op(":") if depth == 0 => return Ok(()),
+				TokenKind::Op("(" | "[" | "{") => depth += 1,
+				TokenKind::Op(")" | "]" | "}") => depth = depth.saturating_sub(1),
+				TokenKind::Newline | TokenKind::End => return self.error("expected ':'"),
+				_ => {},
+			}
+
+			self.position += 1;
+		}
+	}
+
+	/// Skips the rest of a simple statement, such as an import.
+	fn skip_statement(&mut self) {
+		while !matches!(self.peek(), TokenKind::Newline | TokenKind::End | TokenKind::Op(";")) {
+			self.position += 1;
+		}
+	}
+
+	fn parse_program(&mut self) -> Result<Vec<Statement>, SimulationError> {
+		let mut statements = Vec::new();
+
+		while *self.peek() != TokenKind::End {
+			if matches!(self.peek(), TokenKind::Newline | TokenKind::Indent | TokenKind::Dedent) {
+				if *self.peek() == TokenKind::Indent {
+					return self.error("unexpected indent");
+				}
+
+				self.position += 1;
+				continue;
+			}
+
+			statements.extend(self.parse_statement()?);
+		}
+
+		Ok(statements)
+	}
+
+	/// Parses the block following a `:`, either indented on the following lines or on the same line.
+	fn parse_suite(&mut self) -> Result<Vec<Statement>, SimulationError> {
+		self.expect_op(":")?;
+
+		if *self.peek() != TokenKind::Newline {
+			return self.parse_simple_statements();
+		}
+
+		self.position += 1;
+
+		if *self.peek() != TokenKind::Indent {
+			return self.error("expected an indented block");
+		}
+
+		self.position += 1;
+		let mut statements = Vec::new();
+
+		while !matches!(self.peek(), TokenKind::Dedent | TokenKind::End) {
+			if *self.peek() == TokenKind::Newline {
+				self.position += 1;
+				continue;
+			}
+
+			statements.extend(self.parse_statement()?);
+		}
+
+		self.position += 1;
+		Ok(statements)
+	}
+
+	fn parse_statement(&mut self) -> Result<Vec<Statement>, SimulationError> {
+		let line = self.line();
+
+		let keyword = match self.peek() {
+			TokenKind::Name(name) => name.clone(),
+			_ => return self.parse_simple_statements(),
+		};
+
+		let kind = match keyword.as_str() {
+			"def" => {
+				self.position += 1;
+				let name = self.expect_name()?;
+				self.expect_op("(")?;
+
+				let mut parameters = Vec::new();
+
+				while !self.eat_op(")") {
+					// variadic parameters are accepted but never bound
+					let variadic = self.eat_op("*") || self.eat_op("**");
+
+					if self.at_op(",") {
+						self.position += 1;
+						continue;
+					}
+
+					let parameter = self.expect_name()?;
+
+					if self.eat_op(":") {
+						self.parse_expr()?;
+					}
+
+					let default = if self.eat_op("=") { Some(self.parse_expr()?) } else { None };
+
+					if !variadic {
+						parameters.push((parameter, default));
+					}
+
+					if !self.eat_op(",") {
+						self.expect_op(")")?;
+						break;
+					}
+				}
+
+				if self.eat_op("->") {
+					self.parse_expr()?;
+				}
+
+				let body = self.parse_suite()?;
+				StatementKind::Def { name, function: Rc::new(FunctionDef { parameters, body }) }
+			},
+			"if" => {
+				self.position += 1;
+				let mut branches = vec![(self.parse_expr()?, self.parse_suite()?)];
+				let mut otherwise = Vec::new();
+
+				loop {
+					if self.eat_name("elif") {
+						branches.push((self.parse_expr()?, self.parse_suite()?));
+					} else if self.eat_name("else") {
+						otherwise = self.parse_suite()?;
+						break;
+					} else {
+						break;
+					}
+				}
+
+				StatementKind::If { branches, otherwise }
+			},
+			"while" => {
+				self.position += 1;
+				let condition = self.parse_expr()?;
+				let body = self.parse_suite()?;
+
+				if self.eat_name("else") {
+					self.parse_suite()?;
+				}
+
+				StatementKind::While { condition, body }
+			},
+			"for" => {
+				self.position += 1;
+
+				let variable = match (self.advance(), self.peek()) {
+					(TokenKind::Name(name), TokenKind::Name(keyword)) if keyword == "in" => Some(name),
+					_ => None,
+				};
+
+				while !self.at_name("in") {
+					if matches!(self.peek(), TokenKind::Newline | TokenKind::End) {
+						return self.error("expected 'in'");
+					}
+
+					self.position += 1;
+				}
+
+				self.position += 1;
+				let iterable = self.parse_expr()?;
+				let body = self.parse_suite()?;
+
+				if self.eat_name("else") {
+					self.parse_suite()?;
+				}
+
+				StatementKind::For { variable, iterable, body }
+			},
+			"try" => {
+				self.position += 1;
+				let body = self.parse_suite()?;
+				let mut finally = Vec::new();
+
+				loop {
+					if self.eat_name("except") {
+						// exceptions are never raised in simulation, so handlers never run
+						self.skip_to_colon()?;
+						self.parse_suite()?;
+					} else if self.eat_name("else") {
+						self.parse_suite()?;
+					} else if self.eat_name("finally") {
+						finally = self.parse_suite()?;
+						break;
+					} else {
+						break;
+					}
+				}
+
+				StatementKind::Try { body, finally }
+			},
+			"with" => {
+				self.position += 1;
+				self.skip_to_colon()?;
+				StatementKind::Block(self.parse_suite()?)
+			},
+			"class" => {
+				self.position += 1;
+				self.skip_to_colon()?;
+				self.parse_suite()?;
+				StatementKind::Ignored
+			},
+			_ => return self.parse_simple_statements(),
+		};
+
+		Ok(vec![Statement { line, kind }])
+	}
+
+	/// Parses one or more simple statements separated by `;` through the end of the line.
+	fn parse_simple_statements(&mut self) -> Result<Vec<Statement>, SimulationError> {
+		let mut statements = vec![self.parse_simple_statement()?];
+
+		while self.eat_op(";") {
+			if matches!(self.peek(), TokenKind::Newline | TokenKind::End) {
+				break;
+			}
+
+			statements.push(self.parse_simple_statement()?);
+		}
+
+		match self.peek() {
+			TokenKind::Newline => self.position += 1,
+			TokenKind::End | TokenKind::Dedent => {},
+			_ => return self.error("unexpected token"),
+		}
+
+		Ok(statements)
+	}
+
+	fn parse_simple_statement(&mut self) -> Result<Statement, SimulationError> {
+		let line = self.line();
+
+		let keyword = match self.peek() {
+			TokenKind::Name(name) => Some(name.clone()),
+			_ => None,
+		};
+
+		let kind = match keyword.as_deref() {
+			Some("pass") => {
+				self.position += 1;
+				StatementKind::Ignored
+			},
+			Some("break") => {
+				self.position += 1;
+				StatementKind::Break
+			},
+			Some("continue") => {
+				self.position += 1;
+				StatementKind::Continue
+			},
+			Some("return") => {
+				self.position += 1;
+				let value = if matches!(self.peek(), TokenKind::Newline | TokenKind::End | TokenKind::Op(";")) {
+					None
+				} else {
+					Some(self.parse_expr_list()?)
+				};
+
+				StatementKind::Return(value)
+			},
+			Some("raise") => {
+				self.skip_statement();
+				StatementKind::Raise
+			},
+			Some("import" | "from" | "global" | "nonlocal" | "assert" | "del") => {
+				self.skip_statement();
+				StatementKind::Ignored
+			},
+			_ => {
+				let expr = self.parse_expr_list()?;
+
+				if self.at_op(":") {
+					// an annotated assignment, such as `x: float = 1.0`
+					self.position += 1;
+					self.parse_expr()?;
+				}
+
+				let operator = match self.peek() {
+					TokenKind::Op("=") => Some(None),
+					TokenKind::Op(op) if op.len() >= 2 && op.ends_with('=') && !matches!(*op, "==" | "!=" | "<=" | ">=" | ":=") => {
+						Some(Some(&op[..op.len() - 1]))
+					},
+					_ => None,
+				};
+
+				match operator {
+					Some(operator) => {
+						self.position += 1;
+						let mut value = self.parse_expr_list()?;
+
+						// chained assignments, such as `a = b = 0`, only bind the first target
+						while self.eat_op("=") {
+							value = self.parse_expr_list()?;
+						}
+
+						let target = match expr {
+							Expr::Name(name) => Some(name),
+							_ => None,
+						};
+
+						StatementKind::Assign { target, operator: operator.map(intern), value }
+					},
+					None => StatementKind::Expr(expr),
+				}
+			},
+		};
+
+		Ok(Statement { line, kind })
+	}
+
+	/// Parses an expression, treating a comma-separated list as an opaque tuple.
+	fn parse_expr_list(&mut self) -> Result<Expr, SimulationError> {
+		let first = self.parse_expr()?;
+
+		if !self.at_op(",") {
+			return Ok(first);
+		}
+
+		while self.eat_op(",") {
+			if matches!(self.peek(), TokenKind::Newline | TokenKind::End | TokenKind::Op("=" | ")" | ";")) {
+				break;
+			}
+
+			self.parse_expr()?;
+		}
+
+		Ok(Expr::Opaque)
+	}
+
+	fn parse_expr(&mut self) -> Result<Expr, SimulationError> {
+		if self.eat_name("lambda") {
+			self.skip_to_colon()?;
+			self.position += 1;
+			return Ok(Expr::Lambda(Box::new(self.parse_expr()?)));
+		}
+
+		let expr = self.parse_or()?;
+
+		if self.eat_name("if") {
+			let condition = self.parse_or()?;
+
+			if !self.eat_name("else") {
+				return self.error("expected 'else'");
+			}
+
+			let otherwise = self.parse_expr()?;
+
+			return Ok(Expr::Conditional {
+				condition: Box::new(condition),
+				then: Box::new(expr),
+				otherwise: Box::new(otherwise),
+			});
+		}
+
+		Ok(expr)
+	}
+
+	fn parse_or(&mut self) -> Result<Expr, SimulationError> {
+		let mut expr = self.parse_and()?;
+
+		while self.eat_name("or") {
+			expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+		}
+
+		Ok(expr)
+	}
+
+	fn parse_and(&mut self) -> Result<Expr, SimulationError> {
+		let mut expr = self.parse_not()?;
+
+		while self.eat_name("and") {
+			expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+		}
+
+		Ok(expr)
+	}
+
+	fn parse_not(&mut self) -> Result<Expr, SimulationError> {
+		if self.eat_name("not") {
+			return Ok(Expr::Not(Box::new(self.parse_not()?)));
+		}
+
+		self.parse_comparison()
+	}
+
+	fn parse_comparison(&mut self) -> Result<Expr, SimulationError> {
+		let first = self.parse_binary(0)?;
+		let mut comparisons = Vec::new();
+
+		loop {
+			let operator = match self.peek() {
+				TokenKind::Op(op @ ("<" | ">" | "<=" | ">=" | "==" | "!=")) => *op,
+				TokenKind::Name(name) if name == "in" => "in",
+				TokenKind::Name(name) if name == "is" => "is",
+				TokenKind::Name(name) if name == "not" => "not in",
+				_ => break,
+			};
+
+			self.position += 1;
+
+			if operator == "not in" && !self.eat_name("in") {
+				return self.error("expected 'in'");
+			}
+
+			if operator == "is" {
+				self.eat_name("not");
+			}
+
+			comparisons.push((operator, self.parse_binary(0)?));
+		}
+
+		if comparisons.is_empty() {
+			Ok(first)
+		} else {
+			Ok(Expr::Compare(Box::new(first), comparisons))
+		}
+	}
+
+	/// Parses binary operators by precedence, from bitwise or up through multiplication.
+	fn parse_binary(&mut self, level: usize) -> Result<Expr, SimulationError> {
+		const LEVELS: [&[&str]; 6] = [
+			&["|"],
+			&["^"],
+			&["&"],
+			&["<<", ">>"],
+			&["+", "-"],
+			&["*", "/", "//", "%", "@"],
+		];
+
+		if level == LEVELS.len() {
+			return self.parse_unary();
+		}
+
+		let mut expr = self.parse_binary(level + 1)?;
+
+		while let TokenKind::Op(op) = self.peek() {
+			let op = *op;
+
+			if !LEVELS[level].contains(&op) {
+				break;
+			}
+
+			self.position += 1;
+			expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_binary(level + 1)?));
+		}
+
+		Ok(expr)
+	}
+
+	fn parse_unary(&mut self) -> Result<Expr, SimulationError> {
+		match self.peek() {
+			TokenKind::Op(op @ ("-" | "+" | "~")) => {
+				let op = *op;
+				self.position += 1;
+				Ok(Expr::Unary(op, Box::new(self.parse_unary()?)))
+			},
+			_ => self.parse_power(),
+		}
+	}
+
+	fn parse_power(&mut self) -> Result<Expr, SimulationError> {
+		let base = self.parse_postfix()?;
+
+		if self.eat_op("**") {
+			return Ok(Expr::Binary("**", Box::new(base), Box::new(self.parse_unary()?)));
+		}
+
+		Ok(base)
+	}
+
+	fn parse_postfix(&mut self) -> Result<Expr, SimulationError> {
+		let mut expr = self.parse_atom()?;
+
+		loop {
+			if self.eat_op(".") {
+				expr = Expr::Attribute(Box::new(expr), self.expect_name()?);
+			} else if self.eat_op("(") {
+				let mut args = Vec::new();
+				let mut keywords = Vec::new();
+
+				while !self.eat_op(")") {
+					let unpacked = self.eat_op("*") || self.eat_op("**");
+
+					let keyword = match (self.peek().clone(), &self.tokens.get(self.position + 1).map(|token| &token.kind)) {
+						(TokenKind::Name(name), Some(TokenKind::Op("="))) => Some(name),
+						_ => None,
+					};
+
+					if let Some(keyword) = keyword {
+						self.position += 2;
+						keywords.push((keyword, self.parse_expr()?));
+					} else {
+						let arg = self.parse_expr()?;
+
+						// a generator expression as the only argument, such as `any(x for x in y)`
+						if self.at_name("for") {
+							let mut depth = 0usize;
+
+							while depth > 0 || !self.at_op(")") {
+								match self.advance() {
+									TokenKind::Op("(" | "[" | "{") => depth += 1,
+									TokenKind::Op(")" | "]" | "}") => depth -= 1,
+									TokenKind::End => return self.error("expected ')'"),
+									_ => {},
+								}
+							}
+
+							args.push(Expr::Opaque);
+							continue;
+						}
+
+						args.push(if unpacked { Expr::Opaque } else { arg });
+					}
+
+					if !self.eat_op(",") {
+						self.expect_op(")")?;
+						break;
+					}
+				}
+
+				expr = Expr::Call { function: Box::new(expr), args, keywords };
+			} else if self.at_op("[") {
+				self.skip_bracketed()?;
+				expr = Expr::Opaque;
+			} else {
+				return Ok(expr);
+			}
+		}
+	}
+
+	/// Skips a bracketed expression, such as a subscript or a dictionary.
+	fn skip_bracketed(&mut self) -> Result<(), SimulationError> {
+		let mut depth = 0usize;
+
+		loop {
+			match self.advance() {
+				TokenKind::Op("(" | "[" | "{") => depth += 1,
+				TokenKind::Op(")" | "]" | "}") => {
+					depth -= 1;
+
+					if depth == 0 {
+						return Ok(());
+					}
+				},
+				TokenKind::End => return self.error("unclosed bracket"),
+				_ => {},
+			}
+		}
+	}
+
+	fn parse_atom(&mut self) -> Result<Expr, SimulationError> {
+		match self.peek().clone() {
+			TokenKind::Number(value) => {
+				self.position += 1;
+				Ok(Expr::Number(value))
+			},
+			TokenKind::Str => {
+				// adjacent strings are concatenated
+				while *self.peek() == TokenKind::Str {
+					self.position += 1;
+				}
+
+				Ok(Expr::Opaque)
+			},
+			TokenKind::Name(name) => {
+				self.position += 1;
+				Ok(Expr::Name(name))
+			},
+			TokenKind::Op("(") => {
+				self.position += 1;
+
+				if self.eat_op(")") {
+					return Ok(Expr::Opaque);
+				}
+
+				let expr = self.parse_expr_list()?;
+				self.expect_op(")")?;
+				Ok(expr)
+			},
+			TokenKind::Op("[") => {
+				let start = self.position;
+				self.position += 1;
+				let mut items = Vec::new();
+
+				while !self.eat_op("]") {
+					items.push(self.parse_expr()?);
+
+					// a list comprehension is opaque
+					if self.at_name("for") {
+						self.position = start;
+						self.skip_bracketed()?;
+						return Ok(Expr::Opaque);
+					}
+
+					if !self.eat_op(",") {
+						self.expect_op("]")?;
+						break;
+					}
+				}
+
+				Ok(Expr::List(items))
+			},
+			TokenKind::Op("{") => {
+				self.skip_bracketed()?;
+				Ok(Expr::Opaque)
+			},
+			TokenKind::Op("...") => {
+				self.position += 1;
+				Ok(Expr::Opaque)
+			},
+			_ => self.error("expected an expression"),
+		}
+	}
+}
+
+/// Maps an augmented assignment operator onto the static string of its binary operator.
+fn intern(operator: &str) -> &'static str {
+	OPERATORS
+		.iter()
+		.find(|candidate| **candidate == operator)
+		.copied()
+		.unwrap_or("?")
+}
+
+/// The value of an expression, with numbers as ranges so that sensors may be assumed to be anywhere within one.
+#[derive(Clone, Debug)]
+enum Value {
+	Number(f64, f64),
+	Bool(Option<bool>),
+	Device(String),
+	List(Vec<Value>),
+	Lambda(Rc<Expr>),
+	None,
+	Opaque,
+	Unknown,
+}
+
+impl Value {
+	fn exact(value: f64) -> Self {
+		Value::Number(value, value)
+	}
+
+	fn truthy(&self) -> Option<bool> {
+		match self {
+			Value::Number(min, max) if *min == 0.0 && *max == 0.0 => Some(false),
+			Value::Number(min, max) if *min > 0.0 || *max < 0.0 => Some(true),
+			Value::Number(..) => None,
+			Value::Bool(value) => *value,
+			Value::List(items) => Some(!items.is_empty()),
+			Value::None => Some(false),
+			Value::Device(_) | Value::Lambda(_) | Value::Opaque => Some(true),
+			Value::Unknown => None,
+		}
+	}
+
+	fn as_exact(&self) -> Option<f64> {
+		match self {
+			Value::Number(min, max) if min == max => Some(*min),
+			_ => None,
+		}
+	}
+}
+
+fn not(value: Option<bool>) -> Option<bool> {
+	value.map(|value| !value)
+}
+
+fn arithmetic(operator: &str, left: &Value, right: &Value) -> Value {
+	let (Value::Number(a, b), Value::Number(c, d)) = (left, right) else {
+		return Value::Unknown;
+	};
+
+	let bounds = |values: [f64; 4]| {
+		let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+		let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+		Value::Number(min, max)
+	};
+
+	match operator {
+		"+" => Value::Number(a + c, b + d),
+		"-" => Value::Number(a - d, b - c),
+		"*" => bounds([a * c, a * d, b * c, b * d]),
+		"/" if *c > 0.0 || *d < 0.0 => bounds([a / c, a / d, b / c, b / d]),
+		"//" if *c > 0.0 || *d < 0.0 => bounds([(a / c).floor(), (a / d).floor(), (b / c).floor(), (b / d).floor()]),
+		"%" if a == b && c == d && *c != 0.0 => Value::exact(a - c * (a / c).floor()),
+		"**" if a == b && c == d => Value::exact(a.powf(*c)),
+		_ => Value::Unknown,
+	}
+}
+
+fn compare(operator: &str, left: &Value, right: &Value) -> Option<bool> {
+	if let (Value::Number(a, b), Value::Number(c, d)) = (left, right) {
+		let (a, b, c, d) = (*a, *b, *c, *d);
+
+		return match operator {
+			"<" if b < c => Some(true),
+			"<" if a >= d => Some(false),
+			"<=" if b <= c => Some(true),
+			"<=" if a > d => Some(false),
+			">" if a > d => Some(true),
+			">" if b <= c => Some(false),
+			">=" if a >= d => Some(true),
+			">=" if b < c => Some(false),
+			"==" if a == b && c == d => Some(a == c),
+			"==" if b < c || a > d => Some(false),
+			"!=" if a == b && c == d => Some(a != c),
+			"!=" if b < c || a > d => Some(true),
+			_ => None,
+		};
+	}
+
+	match (operator, left, right) {
+		("==", Value::Device(a), Value::Device(b)) => Some(a == b),
+		("!=", Value::Device(a), Value::Device(b)) => Some(a != b),
+		("==" | "is", Value::None, Value::None) => Some(true),
+		_ => None,
+	}
+}
+
+/// What a statement does to the flow of execution.
+enum Flow {
+	Normal,
+	Break,
+	Continue,
+	Return(Value),
+}
+
+struct Simulator<'a> {
+	options: &'a SimulationOptions,
+	now: f64,
+	line: usize,
+	steps: usize,
+	call_depth: usize,
+	stopped: bool,
+	gave_up: bool,
+	timeline: Vec<TimelineEntry>,
+	warnings: Vec<SimulationWarning>,
+	warned: HashSet<SimulationWarning>,
+	globals: HashMap<String, Value>,
+	locals: Vec<HashMap<String, Value>>,
+	functions: HashMap<String, Rc<FunctionDef>>,
+}
+
+impl Simulator<'_> {
+	fn warn(&mut self, message: impl Into<String>) {
+		let warning = SimulationWarning { line: self.line, message: message.into() };
+
+		if self.warned.insert(warning.clone()) {
+			self.warnings.push(warning);
+		}
+	}
+
+	fn give_up(&mut self, message: impl Into<String>) {
+		self.warn(message);
+		self.stopped = true;
+		self.gave_up = true;
+	}
+
+	fn record(&mut self, action: SimulatedAction) {
+		if self.timeline.len() == MAX_TIMELINE_ENTRIES {
+			self.warn(format!("the timeline is truncated after {MAX_TIMELINE_ENTRIES} actions"));
+		}
+
+		if self.timeline.len() < MAX_TIMELINE_ENTRIES {
+			self.timeline.push(TimelineEntry { at_seconds: self.now, line: self.line, action });
+		}
+	}
+
+	fn wait(&mut self, duration: &Value) {
+		let seconds = match duration {
+			Value::Number(min, max) => {
+				if min != max {
+					self.warn(format!("wait lasts between {min} and {max} s, so the longest is assumed"));
+				}
+
+				*max
+			},
+			_ => {
+				self.warn("wait duration cannot be determined, so it is assumed to be 0 s");
+				0.0
+			},
+		};
+
+		if seconds < 0.0 {
+			self.warn(format!("waits a negative duration of {seconds} s"));
+			return;
+		}
+
+		if seconds >= self.options.long_wait_seconds {
+			self.warn(format!("waits {seconds} s"));
+		}
+
+		self.record(SimulatedAction::Wait { seconds });
+		self.now += seconds;
+	}
+
+	fn wait_until(&mut self, condition: &Expr, timeout: Option<Value>) {
+		let condition_met = match self.evaluate(condition) {
+			Value::Lambda(body) => self.evaluate(&body).truthy(),
+			value => value.truthy(),
+		};
+
+		let timeout = timeout.and_then(|timeout| match timeout {
+			Value::Number(_, max) => Some(max),
+			_ => None,
+		});
+
+		let seconds = match (condition_met, timeout) {
+			(Some(true), _) => 0.0,
+			(Some(false), Some(timeout)) => {
+				self.warn(format!("condition is never met under the assumptions, so the wait times out after {timeout} s"));
+				timeout
+			},
+			(Some(false), None) => {
+				self.record(SimulatedAction::WaitUntil { seconds: f64::INFINITY, condition_met });
+				self.give_up("condition is never met under the assumptions and there is no timeout, so the sequence waits forever");
+				return;
+			},
+			(None, Some(timeout)) => {
+				self.warn(format!("whether the condition is met depends on sensors outside the assumptions, so the full timeout of {timeout} s is assumed"));
+				timeout
+			},
+			(None, None) => {
+				self.warn("whether the condition is met depends on sensors outside the assumptions, so it is assumed to be met immediately");
+				0.0
+			},
+		};
+
+		if seconds >= self.options.long_wait_seconds {
+			self.warn(format!("waits up to {seconds} s"));
+		}
+
+		self.record(SimulatedAction::WaitUntil { seconds, condition_met });
+		self.now += seconds;
+	}
+
+	fn lookup(&mut self, name: &str) -> Value {
+		if let Some(value) = self.locals.last().and_then(|scope| scope.get(name)).or_else(|| self.globals.get(name)) {
+			return value.clone();
+		}
+
+		if let Some([min, max]) = self.options.assumptions.get(name) {
+			return Value::Number(min.min(*max), min.max(*max));
+		}
+
+		if self.functions.contains_key(name) {
+			return Value::Opaque;
+		}
+
+		match name {
+			"True" => Value::Bool(Some(true)),
+			"False" => Value::Bool(Some(false)),
+			"None" => Value::None,
+			"s" => Value::exact(1.0),
+			"ms" => Value::exact(1e-3),
+			"us" => Value::exact(1e-6),
+			"min" | "max" | "abs" | "float" | "int" | "round" | "range" | "print" | "len" | "str" | "time" => Value::Opaque,
+			_ => Value::Device(name.to_owned()),
+		}
+	}
+
+	fn assign(&mut self, name: String, value: Value) {
+		match self.locals.last_mut() {
+			Some(scope) => scope.insert(name, value),
+			None => self.globals.insert(name, value),
+		};
+	}
+
+	fn evaluate(&mut self, expr: &Expr) -> Value {
+		match expr {
+			Expr::Number(value) => Value::exact(*value),
+			Expr::Opaque => Value::Opaque,
+			Expr::Name(name) => self.lookup(name),
+			Expr::List(items) => Value::List(items.iter().map(|item| self.evaluate(item)).collect()),
+			Expr::Lambda(body) => Value::Lambda(Rc::new((**body).clone())),
+			Expr::Unary(op, operand) => match (*op, self.evaluate(operand)) {
+				("-", Value::Number(min, max)) => Value::Number(-max, -min),
+				("+", value @ Value::Number(..)) => value,
+				_ => Value::Unknown,
+			},
+			Expr::Binary(op, left, right) => {
+				let left = self.evaluate(left);
+				let right = self.evaluate(right);
+				arithmetic(op, &left, &right)
+			},
+			Expr::Compare(first, comparisons) => {
+				let mut left = self.evaluate(first);
+				let mut result = Some(true);
+
+				for (op, right) in comparisons {
+					let right = self.evaluate(right);
+					let outcome = compare(op, &left, &right);
+
+					result = match (result, outcome) {
+						(Some(false), _) | (_, Some(false)) => Some(false),
+						(Some(true), Some(true)) => Some(true),
+						_ => None,
+					};
+
+					left = right;
+				}
+
+				Value::Bool(result)
+			},
+			Expr::And(left, right) => {
+				let left = self.evaluate(left).truthy();
+
+				if left == Some(false) {
+					return Value::Bool(Some(false));
+				}
+
+				match (left, self.evaluate(right).truthy()) {
+					(_, Some(false)) => Value::Bool(Some(false)),
+					(Some(true), right) => Value::Bool(right),
+					_ => Value::Bool(None),
+				}
+			},
+			Expr::Or(left, right) => {
+				let left = self.evaluate(left).truthy();
+
+				if left == Some(true) {
+					return Value::Bool(Some(true));
+				}
+
+				match (left, self.evaluate(right).truthy()) {
+					(_, Some(true)) => Value::Bool(Some(true)),
+					(Some(false), right) => Value::Bool(right),
+					_ => Value::Bool(None),
+				}
+			},
+			Expr::Not(operand) => Value::Bool(not(self.evaluate(operand).truthy())),
+			Expr::Conditional { condition, then, otherwise } => match self.evaluate(condition).truthy() {
+				Some(true) => self.evaluate(then),
+				Some(false) => self.evaluate(otherwise),
+				None => Value::Unknown,
+			},
+			Expr::Attribute(..) => Value::Unknown,
+			Expr::Call { function, args, keywords } => self.call(function, args, keywords),
+		}
+	}
+
+	fn call(&mut self, function: &Expr, args: &[Expr], keywords: &[(String, Expr)]) -> Value {
+		let argument = |index: usize, keyword: &str| {
+			args.get(index).or_else(|| keywords.iter().find(|(name, _)| name == keyword).map(|(_, expr)| expr))
+		};
+
+		if let Expr::Attribute(object, method) = function {
+			if let Expr::Name(module) = &**object {
+				if module == "time" && method == "sleep" {
+					if let Some(duration) = argument(0, "seconds") {
+						let duration = self.evaluate(duration);
+						self.wait(&duration);
+					}
+
+					return Value::None;
+				}
+			}
+
+			let object = self.evaluate(object);
+
+			return match (object, method.as_str()) {
+				(Value::Device(valve), "open") => {
+					self.record(SimulatedAction::Open { valve });
+					Value::None
+				},
+				(Value::Device(valve), "close") => {
+					self.record(SimulatedAction::Close { valve });
+					Value::None
+				},
+				_ => {
+					self.warn(format!("call to '.{method}()' is not simulated"));
+					Value::Unknown
+				},
+			};
+		}
+
+		let Expr::Name(name) = function else {
+			self.warn("call is not simulated");
+			return Value::Unknown;
+		};
+
+		match name.as_str() {
+			"wait_for" | "sleep" => {
+				if let Some(duration) = argument(0, "duration") {
+					let duration = self.evaluate(duration);
+					self.wait(&duration);
+				}
+
+				Value::None
+			},
+			"wait_until" => {
+				let timeout = argument(1, "timeout").map(|timeout| self.evaluate(timeout));
+
+				if let Some(condition) = argument(0, "condition") {
+					self.wait_until(condition, timeout);
+				}
+
+				Value::None
+			},
+			"abort" => {
+				self.record(SimulatedAction::Abort);
+				self.stopped = true;
+				Value::None
+			},
+			"range" => {
+				let bounds = args
+					.iter()
+					.map(|arg| self.evaluate(arg).as_exact())
+					.collect::<Option<Vec<_>>>();
+
+				self.range(bounds.as_deref().unwrap_or_default())
+			},
+			"print" => {
+				for arg in args {
+					self.evaluate(arg);
+				}
+
+				Value::None
+			},
+			"abs" | "float" | "int" | "round" if args.len() == 1 => match (name.as_str(), self.evaluate(&args[0])) {
+				("abs", Value::Number(min, max)) if min >= 0.0 => Value::Number(min, max),
+				("abs", Value::Number(min, max)) if max <= 0.0 => Value::Number(-max, -min),
+				("abs", Value::Number(min, max)) => Value::Number(0.0, max.max(-min)),
+				("float", value @ Value::Number(..)) => value,
+				("int", Value::Number(min, max)) => Value::Number(min.trunc(), max.trunc()),
+				("round", Value::Number(min, max)) => Value::Number(min.round(), max.round()),
+				_ => Value::Unknown,
+			},
+			"min" | "max" if !args.is_empty() => {
+				let values = args.iter().map(|arg| self.evaluate(arg)).collect::<Vec<_>>();
+				let pick = if name == "min" { f64::min } else { f64::max };
+
+				values
+					.iter()
+					.try_fold(None, |bounds: Option<(f64, f64)>, value| match value {
+						Value::Number(low, high) => Some(Some(match bounds {
+							Some((min, max)) => (pick(min, *low), pick(max, *high)),
+							None => (*low, *high),
+						})),
+						_ => None,
+					})
+					.flatten()
+					.map_or(Value::Unknown, |(min, max)| Value::Number(min, max))
+			},
+			_ => match self.functions.get(name).cloned() {
+				Some(function) => self.call_function(name, &function, args, keywords),
+				None => {
+					for arg in args {
+						self.evaluate(arg);
+					}
+
+					self.warn(format!("call to '{name}()' is not simulated"));
+					Value::Unknown
+				},
+			},
+		}
+	}
+
+	fn call_function(&mut self, name: &str, function: &FunctionDef, args: &[Expr], keywords: &[(String, Expr)]) -> Value {
+		if self.call_depth == MAX_CALL_DEPTH {
+			self.give_up(format!("calls to '{name}()' nest more than {MAX_CALL_DEPTH} deep"));
+			return Value::Unknown;
+		}
+
+		let mut scope = HashMap::new();
+
+		for (index, (parameter, default)) in function.parameters.iter().enumerate() {
+			let value = match args.get(index) {
+				Some(arg) => self.evaluate(arg),
+				None => match keywords.iter().find(|(keyword, _)| keyword == parameter) {
+					Some((_, arg)) => self.evaluate(arg),
+					None => default.as_ref().map_or(Value::Unknown, |default| self.evaluate(default)),
+				},
+			};
+
+			scope.insert(parameter.clone(), value);
+		}
+
+		let line = self.line;
+		self.locals.push(scope);
+		self.call_depth += 1;
+
+		let flow = self.execute_block(&function.body);
+
+		self.call_depth -= 1;
+		self.locals.pop();
+		self.line = line;
+
+		match flow {
+			Flow::Return(value) => value,
+			_ => Value::None,
+		}
+	}
+
+	fn execute_block(&mut self, statements: &[Statement]) -> Flow {
+		for statement in statements {
+			if self.stopped {
+				break;
+			}
+
+			match self.execute(statement) {
+				Flow::Normal => {},
+				flow => return flow,
+			}
+		}
+
+		Flow::Normal
+	}
+
+	fn execute(&mut self, statement: &Statement) -> Flow {
+		self.line = statement.line;
+		self.steps += 1;
+
+		if self.steps > MAX_STEPS {
+			self.give_up(format!("simulation stopped after {MAX_STEPS} statements"));
+			return Flow::Normal;
+		}
+
+		match &statement.kind {
+			StatementKind::Expr(expr) => {
+				self.evaluate(expr);
+			},
+			StatementKind::Assign { target, operator, value } => {
+				let mut value = self.evaluate(value);
+
+				if let (Some(target), Some(operator)) = (target, operator) {
+					let current = self.lookup(target);
+					value = arithmetic(operator, &current, &value);
+				}
+
+				if let Some(target) = target {
+					self.assign(target.clone(), value);
+				}
+			},
+			StatementKind::If { branches, otherwise } => {
+				for (condition, body) in branches {
+					match self.evaluate(condition).truthy() {
+						Some(true) => return self.execute_block(body),
+						Some(false) => {},
+						None => {
+							self.warn("condition depends on sensors outside the assumptions, so its branch is assumed to be taken");
+							return self.execute_block(body);
+						},
+					}
+				}
+
+				return self.execute_block(otherwise);
+			},
+			StatementKind::While { condition, body } => {
+				let line = statement.line;
+
+				for _ in 0..MAX_LOOP_ITERATIONS {
+					self.line = line;
+
+					match self.evaluate(condition).truthy() {
+						Some(true) => {},
+						Some(false) => return Flow::Normal,
+						None => {
+							self.warn("loop condition depends on sensors outside the assumptions, so the loop is assumed to run once");
+
+							return match self.execute_block(body) {
+								flow @ Flow::Return(_) => flow,
+								_ => Flow::Normal,
+							};
+						},
+					}
+
+					match self.execute_block(body) {
+						Flow::Break => return Flow::Normal,
+						flow @ Flow::Return(_) => return flow,
+						_ => {},
+					}
+
+					if self.stopped {
+						return Flow::Normal;
+					}
+				}
+
+				self.line = line;
+				self.give_up("loop never exits under the assumptions");
+			},
+			StatementKind::For { variable, iterable, body } => {
+				let Value::List(items) = self.evaluate(iterable) else {
+					self.warn("loop iterates over values which cannot be determined, so it is assumed to run once");
+
+					if let Some(variable) = variable {
+						self.assign(variable.clone(), Value::Unknown);
+					}
+
+					return match self.execute_block(body) {
+						flow @ Flow::Return(_) => flow,
+						_ => Flow::Normal,
+					};
+				};
+
+				for item in items {
+					if let Some(variable) = variable {
+						self.assign(variable.clone(), item);
+					}
+
+					match self.execute_block(body) {
+						Flow::Break => break,
+						flow @ Flow::Return(_) => return flow,
+						_ => {},
+					}
+
+					if self.stopped {
+						break;
+					}
+				}
+			},
+			StatementKind::Def { name, function } => {
+				self.functions.insert(name.clone(), function.clone());
+			},
+			StatementKind::Try { body, finally } => {
+				let flow = self.execute_block(body);
+				let stopped = self.stopped;
+
+				// a finally block still runs after an abort
+				self.stopped = self.gave_up;
+				let finally_flow = self.execute_block(finally);
+				self.stopped |= stopped;
+
+				return match finally_flow {
+					Flow::Normal => flow,
+					finally_flow => finally_flow,
+				};
+			},
+			StatementKind::Block(body) => return self.execute_block(body),
+			StatementKind::Return(value) => {
+				let value = value.as_ref().map_or(Value::None, |value| self.evaluate(value));
+				return Flow::Return(value);
+			},
+			StatementKind::Raise => {
+				self.warn("raises an exception, which ends the sequence");
+				self.stopped = true;
+			},
+			StatementKind::Break => return Flow::Break,
+			StatementKind::Continue => return Flow::Continue,
+			StatementKind::Ignored => {},
+		}
+
+		Flow::Normal
+	}
+
+	/// The values of a `range(...)` call, which are only known if every bound is exact.
+	fn range(&mut self, bounds: &[f64]) -> Value {
+		let (start, stop, step) = match *bounds {
+			[stop] => (0.0, stop, 1.0),
+			[start, stop] => (start, stop, 1.0),
+			[start, stop, step] if step != 0.0 => (start, stop, step),
+			_ => return Value::Unknown,
+		};
+
+		let count = ((stop - start) / step).ceil().max(0.0) as usize;
+
+		if count > MAX_LOOP_ITERATIONS {
+			self.warn(format!("loop runs {count} times, so only the first {MAX_LOOP_ITERATIONS} are simulated"));
+		}
+
+		Value::List((0..count.min(MAX_LOOP_ITERATIONS)).map(|i| Value::exact(start + i as f64 * step)).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn options(assumptions: &[(&str, [f64; 2])]) -> SimulationOptions {
+		SimulationOptions {
+			assumptions: assumptions.iter().map(|(name, range)| (name.to_string(), *range)).collect(),
+			..SimulationOptions::default()
+		}
+	}
+
+	#[test]
+	fn timeline_follows_commands_and_waits() {
+		let script = "
+# fill then fire
+def pulse(valve, duration=0.5):
+    valve.open()
+    wait_for(duration)
+    valve.close()
+
+BBV.open()
+wait_for(2 * s)
+for _ in range(3):
+    pulse(IGN, duration=250 * ms)
+time.sleep(600)
+";
+
+		let report = simulate(script, &SimulationOptions::default()).unwrap();
+
+		assert!(report.completed);
+		assert_eq!(report.total_seconds, 2.0 + 3.0 * 0.25 + 600.0);
+		assert_eq!(report.timeline.len(), 1 + 1 + 3 * 3 + 1);
+		assert_eq!(report.timeline[0].action, SimulatedAction::Open { valve: "BBV".to_owned() });
+		assert_eq!(report.timeline[2].at_seconds, 2.0);
+		assert_eq!(report.timeline[2].line, 4);
+
+		assert_eq!(report.warnings, [SimulationWarning { line: 12, message: "waits 600 s".to_owned() }]);
+	}
+
+	#[test]
+	fn conditions_use_assumed_sensor_ranges() {
+		let script = "
+if KBPT > 900:
+    abort()
+wait_until(lambda: KBPT > 400, timeout=30)
+while KBPT < 100:
+    wait_for(0.1)
+SBV.open()
+";
+
+		let report = simulate(script, &options(&[("KBPT", [450.0, 600.0])])).unwrap();
+		assert!(report.completed);
+		assert!(report.warnings.is_empty());
+		assert_eq!(report.timeline.len(), 2);
+
+		// the tank never pressurizes, so the wait times out and the loop never exits
+		let report = simulate(script, &options(&[("KBPT", [0.0, 50.0])])).unwrap();
+		assert!(!report.completed);
+		assert_eq!(report.warnings.last().unwrap().line, 5);
+
+		// without assumptions, each branch is assumed to be taken and reported
+		let report = simulate(script, &SimulationOptions::default()).unwrap();
+		assert_eq!(report.timeline.last().unwrap().action, SimulatedAction::Abort);
+		assert_eq!(report.warnings[0].line, 2);
+	}
+
+	#[test]
+	fn syntax_errors_are_reported_by_line() {
+		let error = simulate("BBV.open()\nif KBPT > 5\n    abort()\n", &SimulationOptions::default()).unwrap_err();
+		assert_eq!(error.line, 2);
+	}
+}
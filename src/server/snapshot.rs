@@ -0,0 +1,132 @@
+use common::comm::VehicleState;
+use rusqlite::{types::Type, Row};
+use std::io;
+
+#[cfg(feature = "server")]
+use {
+	jeflog::pass,
+	rusqlite::params,
+	std::{future::Future, time::Duration},
+	super::Shared,
+};
+
+/// The zstd level snapshots are compressed at. Every snapshot is compressed as
+/// it is logged, so speed is favored over ratio; vehicle states are repetitive
+/// enough that low levels already compress them several times over.
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// The number of snapshots recompressed at once, so that the logger is not held up for long.
+#[cfg(feature = "server")]
+const RECOMPRESSION_BATCH: usize = 256;
+
+/// How long to wait before checking for uncompressed snapshots again once there are none.
+#[cfg(feature = "server")]
+const RECOMPRESSION_IDLE: Duration = Duration::from_secs(60);
+
+/// Serializes a vehicle state with Postcard and compresses it, as it is logged in the `VehicleSnapshots` table.
+pub fn encode(vehicle_state: &VehicleState) -> anyhow::Result<Vec<u8>> {
+	let serialized = postcard::to_allocvec(vehicle_state)?;
+	Ok(compress(&serialized)?)
+}
+
+/// Compresses a vehicle state which was already serialized with Postcard.
+pub fn compress(serialized: &[u8]) -> io::Result<Vec<u8>> {
+	zstd::bulk::compress(serialized, COMPRESSION_LEVEL)
+}
+
+/// Decodes a logged vehicle state, decompressing it first if it was compressed.
+pub fn decode(blob: &[u8], compressed: bool) -> anyhow::Result<VehicleState> {
+	if compressed {
+		Ok(postcard::from_bytes(&zstd::stream::decode_all(blob)?)?)
+	} else {
+		Ok(postcard::from_bytes(blob)?)
+	}
+}
+
+/// Decodes the vehicle state of a row which selects `vehicle_state, compressed`
+/// from the `VehicleSnapshots` table, starting at the given column.
+pub fn from_row(row: &Row, column: usize) -> rusqlite::Result<VehicleState> {
+	let blob = row.get::<_, Vec<u8>>(column)?;
+	let compressed = row.get::<_, bool>(column + 1)?;
+
+	decode(&blob, compressed)
+		.map_err(|error| rusqlite::Error::FromSqlConversionFailure(column, Type::Blob, error.into()))
+}
+
+/// Continuously compresses any snapshots which were logged uncompressed, such
+/// as those logged before compression was introduced.
+///
+/// Snapshots are recompressed in small batches so that the database is never
+/// held for long while the vehicle state is being logged.
+#[cfg(feature = "server")]
+pub fn recompress(shared: &Shared) -> impl Future<Output = anyhow::Result<()>> {
+	let connection = shared.database.connection.clone();
+
+	async move {
+		let mut recompressed = 0;
+
+		loop {
+			let batch = connection
+				.lock()
+				.await
+				.prepare("SELECT snapshot_id, vehicle_state FROM VehicleSnapshots WHERE NOT compressed LIMIT ?1")?
+				.query_map([RECOMPRESSION_BATCH], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			if batch.is_empty() {
+				if recompressed > 0 {
+					pass!("Recompressed {recompressed} vehicle snapshots.");
+					recompressed = 0;
+				}
+
+				tokio::time::sleep(RECOMPRESSION_IDLE).await;
+				continue;
+			}
+
+			let batch = batch
+				.into_iter()
+				.map(|(snapshot_id, serialized)| Ok((snapshot_id, compress(&serialized)?)))
+				.collect::<io::Result<Vec<_>>>()?;
+
+			{
+				let mut connection = connection.lock().await;
+				let transaction = connection.transaction()?;
+
+				for (snapshot_id, compressed) in &batch {
+					transaction.execute(
+						"UPDATE VehicleSnapshots SET vehicle_state = ?2, compressed = TRUE WHERE snapshot_id = ?1 AND NOT compressed",
+						params![snapshot_id, compressed],
+					)?;
+				}
+
+				transaction.commit()?;
+			}
+
+			recompressed += batch.len();
+			tokio::task::yield_now().await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+
+	#[test]
+	fn snapshots_round_trip_compressed_or_not() {
+		let mut vehicle_state = VehicleState::new();
+
+		for index in 0..64 {
+			vehicle_state.sensor_readings.insert(format!("PT{index:02}"), Measurement { value: 14.7, unit: Unit::Psi });
+		}
+
+		let serialized = postcard::to_allocvec(&vehicle_state).unwrap();
+		let encoded = encode(&vehicle_state).unwrap();
+
+		assert!(encoded.len() < serialized.len());
+		assert_eq!(decode(&encoded, true).unwrap(), vehicle_state);
+		assert_eq!(decode(&serialized, false).unwrap(), vehicle_state);
+		assert!(decode(&serialized, true).is_err());
+	}
+}
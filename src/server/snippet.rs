@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::validation::{self, Validate, Validator};
+
+/// A parameter of a snippet, which is substituted wherever `{{name}}` appears in its template.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnippetParameter {
+	/// The name of the parameter, which must be a valid identifier.
+	pub name: String,
+
+	/// What the parameter controls, as shown in the GUI.
+	#[serde(default)]
+	pub description: String,
+
+	/// The value used when the parameter is not given, or `None` if it must be given.
+	#[serde(default)]
+	pub default: Option<String>,
+}
+
+/// A parameterized template of a common procedure, such as a purge or a leak
+/// check, which is instantiated into a full sequence.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Snippet {
+	/// The unique name of the snippet, such as `purge`.
+	pub name: String,
+
+	/// What the snippet does, as shown in the GUI.
+	#[serde(default)]
+	pub description: String,
+
+	/// The parameters substituted into the template.
+	#[serde(default)]
+	pub parameters: Vec<SnippetParameter>,
+
+	/// The Python sequence script, with `{{name}}` wherever a parameter is substituted.
+	pub template: String,
+}
+
+impl Validate for Snippet {
+	fn validate(&self, validator: &mut Validator) {
+		validator.non_empty("name", &self.name);
+		validator.non_empty("template", &self.template);
+
+		// every parameter has a distinct, valid name, and the template only refers to declared parameters
+		for (index, parameter) in self.parameters.iter().enumerate() {
+			let field = format!("parameters[{index}].name");
+			let declared_before = self.parameters[..index].iter().any(|other| other.name == parameter.name);
+
+			validator.check(is_identifier(&parameter.name), &field, "must be a valid identifier");
+			validator.check(!declared_before, &field, "is declared twice");
+		}
+
+		match placeholders(&self.template) {
+			Ok(placeholders) => {
+				for placeholder in placeholders {
+					let declared = self.parameters.iter().any(|parameter| parameter.name == placeholder);
+					validator.check(declared, "template", format!("refers to undeclared parameter '{placeholder}'"));
+				}
+			},
+			Err(error) => validator.error("template", error),
+		}
+	}
+}
+
+impl Snippet {
+
+	/// Substitutes arguments into the template, falling back on the defaults of
+	/// parameters which are not given, and returns the resulting script.
+	///
+	/// Arguments may not span lines, so that a value cannot break the
+	/// indentation of the template around it.
+	pub fn instantiate(&self, arguments: &BTreeMap<String, String>) -> Result<String, String> {
+		validation::validate(self).map_err(|error| error.to_string())?;
+
+		if let Some(unknown) = arguments.keys().find(|name| !self.parameters.iter().any(|parameter| &parameter.name == *name)) {
+			return Err(format!("snippet '{}' has no parameter '{unknown}'", self.name));
+		}
+
+		let mut values = BTreeMap::new();
+
+		for parameter in &self.parameters {
+			let value = arguments
+				.get(&parameter.name)
+				.or(parameter.default.as_ref())
+				.ok_or_else(|| format!("parameter '{}' is required", parameter.name))?;
+
+			let value = value.trim();
+
+			if value.is_empty() || value.contains(['\n', '\r']) {
+				return Err(format!("value of parameter '{}' must be a single, non-empty line", parameter.name));
+			}
+
+			values.insert(parameter.name.as_str(), value);
+		}
+
+		let mut script = String::with_capacity(self.template.len());
+		let mut rest = self.template.as_str();
+
+		while let Some(start) = rest.find("{{") {
+			script.push_str(&rest[..start]);
+
+			// placeholders were checked by validate, so each is closed and declared
+			let end = rest[start..].find("}}").unwrap_or(rest.len() - start) + start;
+			script.push_str(values[rest[start + 2..end].trim()]);
+			rest = &rest[end + 2..];
+		}
+
+		script.push_str(rest);
+		Ok(script)
+	}
+}
+
+/// The names of the parameters a template refers to, in the order they appear.
+fn placeholders(template: &str) -> Result<Vec<&str>, String> {
+	let mut names = Vec::new();
+	let mut rest = template;
+
+	while let Some(start) = rest.find("{{") {
+		let Some(length) = rest[start..].find("}}") else {
+			return Err("has a '{{' which is never closed".to_owned());
+		};
+
+		names.push(rest[start + 2..start + length].trim());
+		rest = &rest[start + length + 2..];
+	}
+
+	Ok(names)
+}
+
+/// Whether a name is a valid Python identifier, limited to ASCII.
+fn is_identifier(name: &str) -> bool {
+	name.chars().next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+		&& name.chars().all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn arguments_and_defaults_are_substituted() {
+		let snippet = Snippet {
+			name: "cycle".to_owned(),
+			description: String::new(),
+			parameters: vec![
+				SnippetParameter { name: "valve".to_owned(), description: String::new(), default: None },
+				SnippetParameter { name: "cycles".to_owned(), description: String::new(), default: Some("3".to_owned()) },
+			],
+			template: "for _ in range({{cycles}}):\n    {{ valve }}.open()\n    {{valve}}.close()\n".to_owned(),
+		};
+
+		let arguments = BTreeMap::from([("valve".to_owned(), "BBV".to_owned())]);
+		let script = snippet.instantiate(&arguments).unwrap();
+		assert_eq!(script, "for _ in range(3):\n    BBV.open()\n    BBV.close()\n");
+
+		assert!(snippet.instantiate(&BTreeMap::new()).is_err());
+		assert!(snippet.instantiate(&BTreeMap::from([("valve".to_owned(), "BBV\nabort()".to_owned())])).is_err());
+		assert!(snippet.instantiate(&BTreeMap::from([("vlave".to_owned(), "BBV".to_owned())])).is_err());
+
+		let undeclared = Snippet { template: "{{valve}}.open()\n{{duration}}".to_owned(), ..snippet.clone() };
+		assert!(validation::validate(&undeclared).is_err());
+
+		let unclosed = Snippet { template: "{{valve.open()".to_owned(), ..snippet.clone() };
+		assert!(validation::validate(&unclosed).is_err());
+
+		let duplicated = Snippet { parameters: vec![snippet.parameters[0].clone(), snippet.parameters[0].clone()], ..snippet };
+		assert!(validation::validate(&duplicated).is_err());
+	}
+}
@@ -0,0 +1,82 @@
+use axum::{extract::{Request, State}, http::Method, middleware::Next, response::Response};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{auth::Session, error::{forbidden, ErrorCode}, Shared};
+
+/// Requests which are sent with a mutating method but change nothing, or which
+/// must keep working for the server to run, so are allowed in spectator mode.
+const READ_ONLY_REQUESTS: [&str; 8] = [
+	"/data/export",
+	"/operator/sequences/simulate",
+	"/operator/snippets/instantiate",
+	"/admin/backup/changes",
+	"/admin/spectator",
+	"/auth/login",
+	"/auth/logout",
+	"/flight/sequence-finished",
+];
+
+/// Whether the server is in spectator mode, in which nothing may be changed
+/// through the API while data may still be streamed and exported.
+#[derive(Debug, Default)]
+pub struct SpectatorMode {
+	enabled: AtomicBool,
+}
+
+impl SpectatorMode {
+	/// Whether spectator mode is on.
+	pub fn is_enabled(&self) -> bool {
+		self.enabled.load(Ordering::Relaxed)
+	}
+
+	/// Turns spectator mode on or off, returning whether it was on before.
+	pub fn set(&self, enabled: bool) -> bool {
+		self.enabled.swap(enabled, Ordering::Relaxed)
+	}
+}
+
+/// Whether a request may change the server or command the vehicle.
+fn is_mutating(method: &Method, path: &str) -> bool {
+	if path.starts_with("/servo.v1.Servo/") {
+		return !path.ends_with("/GetState") && !path.ends_with("/StreamState");
+	}
+
+	!matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) && !READ_ONLY_REQUESTS.contains(&path)
+}
+
+/// Middleware which refuses requests that change anything with a 403, either
+/// from users with the spectator role or from anyone while the server is in
+/// spectator mode.
+///
+/// Admins may still turn spectator mode off through `/admin/spectator`.
+pub async fn enforce_spectator(State(shared): State<Shared>, request: Request, next: Next) -> super::Result<Response> {
+	if !is_mutating(request.method(), request.uri().path()) {
+		return Ok(next.run(request).await);
+	}
+
+	if request.extensions().get::<Session>().is_some_and(Session::is_spectator) {
+		return Err(forbidden("spectators may not change anything").with_code(ErrorCode::SpectatorMode));
+	}
+
+	if shared.spectator.is_enabled() {
+		return Err(forbidden("the server is in spectator mode, so nothing may be changed").with_code(ErrorCode::SpectatorMode));
+	}
+
+	Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_mutating_requests_are_refused() {
+		assert!(!is_mutating(&Method::GET, "/data/forward"));
+		assert!(!is_mutating(&Method::POST, "/data/export"));
+		assert!(!is_mutating(&Method::POST, "/servo.v1.Servo/StreamState"));
+
+		assert!(is_mutating(&Method::POST, "/operator/command"));
+		assert!(is_mutating(&Method::PUT, "/profiles/alice/layout"));
+		assert!(is_mutating(&Method::POST, "/servo.v1.Servo/Abort"));
+	}
+}
@@ -0,0 +1,621 @@
+use axum::{extract::{Request, State}, http::Method, middleware::Next, response::Response};
+use common::comm::VehicleState;
+use futures_util::{stream, Stream};
+use jeflog::{fail, pass, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	convert::Infallible,
+	future::Future,
+	sync::{PoisonError, RwLock},
+	time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+use super::{api::{Field, Role, TableDump}, error::{conflict, ErrorCode}, telemetry::MAX_FRAME_SIZE, Shared};
+
+/// The tables mirrored from a primary to its standbys: everything operators
+/// configure, but nothing recorded during a test, which arrives as snapshots.
+///
+/// Only the tables which changed on the primary are sent, as counted in
+/// `MirrorVersions`, and their rows are upserted by primary key. Sessions on
+/// the standby thus only end when their user is removed from the primary.
+pub const MIRRORED_TABLES: [&str; 11] = ["NodeMappings", "Sequences", "SequenceFiles", "Snippets", "ExportPresets", "Thresholds", "Triggers", "Interlocks", "Users", "Profiles", "FlightAllowlist"];
+
+/// The header in which a standby sends the standby key to its primary.
+pub const KEY_HEADER: &str = "x-servo-standby-key";
+
+/// How often a primary sends heartbeats and checks the mirrored tables for changes.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a standby waits to hear from its primary before reconnecting.
+const SILENCE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a standby waits before reconnecting to its primary, and a promoted
+/// standby waits between attempts to fence its former primary.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of messages which may wait to be streamed to a standby before
+/// the primary waits for it to catch up.
+const STREAM_CAPACITY: usize = 256;
+
+/// The role of the server, along with its term.
+///
+/// The term is the number of promotions the server knows of, and only ever
+/// increases. A server fences itself on learning of a term above its own.
+#[derive(Debug)]
+pub struct RoleState {
+	current: RwLock<(Role, u64)>,
+}
+
+impl Default for RoleState {
+	fn default() -> Self {
+		RoleState { current: RwLock::new((Role::Primary, 0)) }
+	}
+}
+
+impl RoleState {
+	/// The current role of the server.
+	pub fn role(&self) -> Role {
+		self.current.read().unwrap_or_else(PoisonError::into_inner).0
+	}
+
+	/// The current term of the server.
+	pub fn term(&self) -> u64 {
+		self.current.read().unwrap_or_else(PoisonError::into_inner).1
+	}
+
+	/// Whether the server is the primary, and so may talk to the flight computer.
+	pub fn is_primary(&self) -> bool {
+		self.role() == Role::Primary
+	}
+
+	fn set(&self, role: Role, term: u64) {
+		*self.current.write().unwrap_or_else(PoisonError::into_inner) = (role, term);
+	}
+}
+
+/// A message streamed from a primary to its standbys.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ReplicationMessage {
+	/// Sent every second, so that a standby can tell a quiet primary from a dead one.
+	Heartbeat {
+		/// The term of the primary.
+		term: u64,
+	},
+
+	/// Every mirrored table when a standby connects, and afterwards those which changed.
+	Tables(Vec<TableDump>),
+
+	/// A new vehicle state, sent as the primary receives it.
+	Snapshot(VehicleState),
+}
+
+impl ReplicationMessage {
+	/// Serializes the message with Postcard, preceded by its length as a big-endian `u32`.
+	pub fn to_frame(&self) -> postcard::Result<Vec<u8>> {
+		let payload = postcard::to_allocvec(self)?;
+
+		let mut frame = Vec::with_capacity(4 + payload.len());
+		frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+		frame.extend_from_slice(&payload);
+		Ok(frame)
+	}
+}
+
+/// Splits the bytes of a replication stream back into messages, however they were chunked.
+#[derive(Debug, Default)]
+pub struct MessageReader {
+	buffer: Vec<u8>,
+}
+
+impl MessageReader {
+	/// Adds bytes received from the stream.
+	pub fn extend(&mut self, bytes: &[u8]) {
+		self.buffer.extend_from_slice(bytes);
+	}
+
+	/// Takes the next complete message, if one has been received.
+	pub fn next_message(&mut self) -> anyhow::Result<Option<ReplicationMessage>> {
+		let Some(prefix) = self.buffer.get(..4) else {
+			return Ok(None);
+		};
+
+		let size = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+
+		if size > MAX_FRAME_SIZE {
+			anyhow::bail!("replication message of {size} bytes exceeds the maximum of {MAX_FRAME_SIZE}");
+		}
+
+		if self.buffer.len() < 4 + size {
+			return Ok(None);
+		}
+
+		let message = postcard::from_bytes(&self.buffer[4..4 + size])?;
+		self.buffer.drain(..4 + size);
+		Ok(Some(message))
+	}
+}
+
+/// Reads every row of the given mirrored tables.
+pub fn dump_tables(connection: &Connection, names: &[&str]) -> rusqlite::Result<Vec<TableDump>> {
+	names
+		.iter()
+		.map(|&name| {
+			let mut statement = connection.prepare(&format!("SELECT * FROM {name}"))?;
+
+			let columns = statement
+				.column_names()
+				.into_iter()
+				.map(str::to_owned)
+				.collect::<Vec<_>>();
+
+			let rows = statement
+				.query_map([], |row| (0..columns.len()).map(|column| Ok(Field::from(row.get_ref(column)?))).collect())?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			Ok(TableDump { name: name.to_owned(), columns, rows })
+		})
+		.collect()
+}
+
+/// Reads the mirrored tables whose versions differ from those last sent,
+/// along with the versions of every mirrored table.
+///
+/// Only the small `MirrorVersions` table is read when nothing has changed.
+pub fn changed_tables(connection: &Connection, sent: &BTreeMap<String, i64>) -> rusqlite::Result<(BTreeMap<String, i64>, Vec<TableDump>)> {
+	let versions = connection
+		.prepare("SELECT name, version FROM MirrorVersions")?
+		.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+		.collect::<rusqlite::Result<BTreeMap<String, i64>>>()?;
+
+	let changed = MIRRORED_TABLES
+		.into_iter()
+		// nothing has been sent to a standby which just connected, so it is sent everything
+		.filter(|&name| sent.is_empty() || sent.get(name) != versions.get(name))
+		.collect::<Vec<_>>();
+
+	Ok((versions, dump_tables(connection, &changed)?))
+}
+
+/// Brings mirrored tables in line with those of the primary, all at once.
+///
+/// Rows are upserted by primary key and only those missing from the primary
+/// are deleted, so that rows which merely changed, such as a user whose
+/// password was rehashed, keep whatever references them on the standby.
+pub fn apply_tables(connection: &mut Connection, tables: &[TableDump]) -> anyhow::Result<()> {
+	let transaction = connection.transaction()?;
+
+	for table in tables {
+		// the name is interpolated into SQL, so it must be one this server expects
+		if !MIRRORED_TABLES.contains(&table.name.as_str()) {
+			anyhow::bail!("primary sent unexpected table '{}'", table.name);
+		}
+
+		let primary_key = transaction
+			.prepare("SELECT name FROM pragma_table_info(?1) WHERE pk > 0 ORDER BY pk")?
+			.query_map([&table.name], |row| row.get::<_, String>(0))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+
+		let key_indices = primary_key
+			.iter()
+			.map(|key| {
+				table.columns
+					.iter()
+					.position(|column| column == key)
+					.ok_or_else(|| anyhow::anyhow!("primary sent table '{}' without its key column '{key}'", table.name))
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+
+		let quote = |column: &String| format!("\"{}\"", column.replace('"', "\"\""));
+
+		let columns = table.columns
+			.iter()
+			.map(quote)
+			.collect::<Vec<_>>();
+
+		let placeholders = (1..=columns.len())
+			.map(|index| format!("?{index}"))
+			.collect::<Vec<_>>();
+
+		let updates = table.columns
+			.iter()
+			.filter(|column| !primary_key.contains(column))
+			.map(|column| format!("{0} = excluded.{0}", quote(column)))
+			.collect::<Vec<_>>();
+
+		let conflict = match (primary_key.is_empty(), updates.is_empty()) {
+			(true, _) => String::new(),
+			(false, true) => format!("ON CONFLICT ({}) DO NOTHING", primary_key.iter().map(quote).collect::<Vec<_>>().join(", ")),
+			(false, false) => format!(
+				"ON CONFLICT ({}) DO UPDATE SET {}",
+				primary_key.iter().map(quote).collect::<Vec<_>>().join(", "),
+				updates.join(", "),
+			),
+		};
+
+		// a table without a key cannot be matched row for row, so it is replaced whole
+		if primary_key.is_empty() {
+			transaction.execute(&format!("DELETE FROM {}", table.name), [])?;
+		} else {
+			let keys = table.rows
+				.iter()
+				.map(|row| key_indices.iter().map(|&index| row[index].clone()).collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+
+			let key_columns = primary_key.iter().map(quote).collect::<Vec<_>>();
+
+			let existing = transaction
+				.prepare(&format!("SELECT {} FROM {}", key_columns.join(", "), table.name))?
+				.query_map([], |row| (0..key_columns.len()).map(|column| Ok(Field::from(row.get_ref(column)?))).collect::<rusqlite::Result<Vec<_>>>())?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			let condition = key_columns
+				.iter()
+				.enumerate()
+				.map(|(index, column)| format!("{column} = ?{}", index + 1))
+				.collect::<Vec<_>>();
+
+			let mut delete = transaction.prepare(&format!("DELETE FROM {} WHERE {}", table.name, condition.join(" AND ")))?;
+
+			for key in existing.iter().filter(|key| !keys.contains(key)) {
+				delete.execute(rusqlite::params_from_iter(key))?;
+			}
+		}
+
+		let mut insert = transaction.prepare(&format!(
+			"INSERT INTO {} ({}) VALUES ({}) {conflict}",
+			table.name,
+			columns.join(", "),
+			placeholders.join(", "),
+		))?;
+
+		for row in &table.rows {
+			insert.execute(rusqlite::params_from_iter(row))?;
+		}
+	}
+
+	transaction.commit()?;
+	Ok(())
+}
+
+/// Sets the role of the server as it starts: a standby if it is configured
+/// with a primary to mirror, and otherwise the primary, at its latest term.
+///
+/// Must be called outside of the runtime, after the database is migrated.
+pub fn initialize(shared: &Shared) -> rusqlite::Result<()> {
+	let term = shared.database.connection
+		.blocking_lock()
+		.query_row("SELECT COALESCE(MAX(term), 0) FROM Promotions", [], |row| row.get::<_, u64>(0))?;
+
+	match &shared.config.current().standby.primary {
+		Some(primary) => {
+			pass!("Starting as a standby of \x1b[1m{primary}\x1b[0m.");
+			shared.role.set(Role::Standby, term);
+		},
+		None => shared.role.set(Role::Primary, term),
+	}
+
+	Ok(())
+}
+
+/// Promotes the server to primary at a new term, recording why.
+pub async fn promote(shared: &Shared, reason: &str) -> rusqlite::Result<u64> {
+	let term = shared.role.term() + 1;
+
+	shared.database.connection
+		.lock()
+		.await
+		.execute("INSERT INTO Promotions (term, reason) VALUES (?1, ?2)", params![term, reason])?;
+
+	shared.role.set(Role::Primary, term);
+	warn!("Promoted to primary at term {term}: {reason}.");
+
+	Ok(term)
+}
+
+/// Fences the server if it is the primary and a standby has been promoted at a
+/// later term, dropping any connections to the vehicle.
+///
+/// Returns whether the term was accepted, which it is not if it is no later
+/// than the server's own, since that standby was promoted before this server.
+pub async fn fence(shared: &Shared, term: u64) -> bool {
+	if term <= shared.role.term() {
+		return false;
+	}
+
+	if shared.role.role() == Role::Standby {
+		shared.role.set(Role::Standby, term);
+		return true;
+	}
+
+	shared.role.set(Role::Fenced, term);
+	*shared.flight.0.lock().await = None;
+	*shared.ground.0.lock().await = None;
+
+	fail!("Fenced by a standby promoted at term {term}. The flight computer and operator commands are refused until servo is restarted as a standby.");
+	true
+}
+
+/// Streams replication messages to a standby for as long as it stays
+/// connected and this server remains the primary.
+pub fn stream_to_standby(shared: &Shared) -> impl Stream<Item = Result<Vec<u8>, Infallible>> {
+	let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+	let shared = shared.clone();
+
+	tokio::spawn(async move {
+		if let Err(error) = send_messages(&shared, &sender).await {
+			warn!("Stopped streaming to standby: {error}");
+		}
+	});
+
+	stream::unfold(receiver, |mut receiver| async move {
+		receiver.recv().await.map(|frame| (Ok(frame), receiver))
+	})
+}
+
+/// Sends replication messages until the standby disconnects, returning early on failure.
+async fn send_messages(shared: &Shared, sender: &mpsc::Sender<Vec<u8>>) -> anyhow::Result<()> {
+	let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+	let mut sent_versions = BTreeMap::new();
+
+	loop {
+		let mut messages = Vec::new();
+
+		tokio::select! {
+			_ = shared.vehicle.1.notified() => {
+				let snapshot = shared.vehicle.0.lock().await.clone();
+				messages.push(ReplicationMessage::Snapshot((*snapshot).clone()));
+			},
+			_ = heartbeat.tick() => {
+				if !shared.role.is_primary() {
+					return Ok(());
+				}
+
+				let (versions, tables) = changed_tables(&*shared.database.connection.lock().await, &sent_versions)?;
+
+				if !tables.is_empty() {
+					messages.push(ReplicationMessage::Tables(tables));
+				}
+
+				sent_versions = versions;
+
+				messages.push(ReplicationMessage::Heartbeat { term: shared.role.term() });
+			},
+		}
+
+		for message in messages {
+			// the standby disconnecting is the usual way for the stream to end
+			if sender.send(message.to_frame()?).await.is_err() {
+				return Ok(());
+			}
+		}
+	}
+}
+
+/// Continuously mirrors the configured primary while this server is its standby.
+///
+/// If automatic promotion is configured, the standby promotes itself once the
+/// primary has been silent for long enough. Only a primary which has been heard
+/// from at least once can be judged dead, so a standby started while its primary
+/// is down must be promoted by an operator. Once promoted, the standby keeps
+/// trying to fence its former primary, so that it cannot act as a primary again
+/// if it recovers.
+pub fn follow(shared: &Shared) -> impl Future<Output = anyhow::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let client = reqwest::Client::new();
+		let mut last_heard = None;
+		let mut fenced = false;
+
+		loop {
+			let config = shared.config.current().standby.clone();
+
+			let Some(primary) = &config.primary else {
+				return Ok(());
+			};
+
+			let primary = primary.trim_end_matches('/');
+
+			if shared.role.role() != Role::Standby {
+				if !fenced {
+					fenced = fence_primary(&client, primary, config.key.as_deref(), shared.role.term()).await;
+
+					if fenced {
+						pass!("Fenced former primary at \x1b[1m{primary}\x1b[0m.");
+					}
+				}
+
+				tokio::time::sleep(RETRY_INTERVAL).await;
+				continue;
+			}
+
+			let heard_before = last_heard;
+
+			// failing to reconnect to a primary which is already lost is not worth repeating
+			if let Err(error) = mirror(&shared, &client, primary, config.key.as_deref(), &mut last_heard).await {
+				if last_heard != heard_before {
+					warn!("Lost replication stream from primary: {error}");
+				}
+			}
+
+			if let (Some(after), Some(heard)) = (config.promote_after_seconds, last_heard) {
+				let silent = heard.elapsed().as_secs_f64();
+
+				if silent >= after {
+					promote(&shared, &format!("primary at {primary} was silent for {silent:.1} s")).await?;
+					continue;
+				}
+			}
+
+			tokio::time::sleep(RETRY_INTERVAL).await;
+		}
+	}
+}
+
+/// Mirrors the primary until its stream ends or it goes silent.
+async fn mirror(shared: &Shared, client: &reqwest::Client, primary: &str, key: Option<&str>, last_heard: &mut Option<Instant>) -> anyhow::Result<()> {
+	let mut response = with_key(client.get(format!("{primary}/standby/stream")), key)
+		.send()
+		.await?
+		.error_for_status()?;
+
+	let mut reader = MessageReader::default();
+	let mut announced = false;
+
+	loop {
+		let chunk = tokio::time::timeout(SILENCE_TIMEOUT, response.chunk())
+			.await
+			.map_err(|_| anyhow::anyhow!("primary went silent"))??;
+
+		let Some(chunk) = chunk else {
+			anyhow::bail!("primary closed the stream");
+		};
+
+		reader.extend(&chunk);
+
+		while let Some(message) = reader.next_message()? {
+			if !announced {
+				pass!("Mirroring primary at \x1b[1m{primary}\x1b[0m.");
+				announced = true;
+			}
+
+			*last_heard = Some(Instant::now());
+
+			match message {
+				ReplicationMessage::Heartbeat { term } => {
+					if term > shared.role.term() {
+						shared.role.set(Role::Standby, term);
+					}
+				},
+				ReplicationMessage::Tables(tables) => {
+					apply_tables(&mut *shared.database.connection.lock().await, &tables)?;
+				},
+				ReplicationMessage::Snapshot(vehicle_state) => {
+					*shared.vehicle.0.lock().await = vehicle_state.into();
+					shared.vehicle.1.notify_waiters();
+				},
+			}
+		}
+	}
+}
+
+/// Asks a former primary to fence itself, returning whether it accepted.
+async fn fence_primary(client: &reqwest::Client, primary: &str, key: Option<&str>, term: u64) -> bool {
+	with_key(client.post(format!("{primary}/standby/fence")), key)
+		.json(&serde_json::json!({ "term": term }))
+		.send()
+		.await
+		.is_ok_and(|response| response.status().is_success())
+}
+
+/// Sends the standby key with a request to the primary, if one is configured.
+fn with_key(request: reqwest::RequestBuilder, key: Option<&str>) -> reqwest::RequestBuilder {
+	match key {
+		Some(key) => request.header(KEY_HEADER, key),
+		None => request,
+	}
+}
+
+/// Middleware which refuses commands to the vehicle unless this server is the primary,
+/// so that a standby or fenced server can never command the vehicle alongside it.
+pub async fn require_primary(State(shared): State<Shared>, request: Request, next: Next) -> super::Result<Response> {
+	let path = request.uri().path();
+
+	let commanding = (path.starts_with("/operator/") && request.method() != Method::GET)
+		|| (path.starts_with("/servo.v1.Servo/") && !path.ends_with("/GetState") && !path.ends_with("/StreamState"));
+
+	match shared.role.role() {
+		Role::Primary => Ok(next.run(request).await),
+		_ if !commanding => Ok(next.run(request).await),
+		Role::Standby => Err(conflict("this server is a standby, so commands must be sent to the primary").with_code(ErrorCode::NotPrimary)),
+		Role::Fenced => Err(conflict("this server was fenced by a promoted standby, so commands must be sent to it").with_code(ErrorCode::NotPrimary)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn mirrored_tables_survive_a_chunked_stream() {
+		let primary = Database::volatile().unwrap();
+		primary.migrate().unwrap();
+
+		primary.connection.blocking_lock().execute_batch("
+			INSERT INTO Triggers (name, condition, script, active) VALUES ('vent', 'KBPT > 800', 'BBV.open()', TRUE);
+			INSERT INTO Sequences (name, configuration_id, script) VALUES ('fill', NULL, 'print(\"it''s \\\"quoted\\\"\")');
+		").unwrap();
+
+		let tables = dump_tables(&primary.connection.blocking_lock(), &MIRRORED_TABLES).unwrap();
+
+		let mut stream = ReplicationMessage::Heartbeat { term: 3 }.to_frame().unwrap();
+		stream.extend(ReplicationMessage::Tables(tables.clone()).to_frame().unwrap());
+
+		let mut reader = MessageReader::default();
+		let mut messages = Vec::new();
+
+		for chunk in stream.chunks(7) {
+			reader.extend(chunk);
+
+			while let Some(message) = reader.next_message().unwrap() {
+				messages.push(message);
+			}
+		}
+
+		assert_eq!(messages, [ReplicationMessage::Heartbeat { term: 3 }, ReplicationMessage::Tables(tables.clone())]);
+
+		let standby = Database::volatile().unwrap();
+		standby.migrate().unwrap();
+		standby.connection.blocking_lock().execute("INSERT INTO Triggers (name, condition, script) VALUES ('stale', 'true', '')", []).unwrap();
+
+		apply_tables(&mut standby.connection.blocking_lock(), &tables).unwrap();
+		assert_eq!(dump_tables(&standby.connection.blocking_lock(), &MIRRORED_TABLES).unwrap(), tables);
+
+		let unexpected = TableDump { name: "VehicleSnapshots".to_owned(), columns: Vec::new(), rows: Vec::new() };
+		assert!(apply_tables(&mut standby.connection.blocking_lock(), &[unexpected]).is_err());
+	}
+
+	#[test]
+	fn standby_sessions_survive_changes_on_the_primary() {
+		let primary = Database::volatile().unwrap();
+		primary.migrate().unwrap();
+
+		let standby = Database::volatile().unwrap();
+		standby.migrate().unwrap();
+
+		primary.connection.blocking_lock().execute_batch("
+			INSERT INTO Users (username, password_hash, salt, role) VALUES ('admin', 'hash', 'salt', 'admin');
+		").unwrap();
+
+		let (sent, tables) = changed_tables(&primary.connection.blocking_lock(), &BTreeMap::new()).unwrap();
+		assert_eq!(tables.len(), MIRRORED_TABLES.len());
+		apply_tables(&mut standby.connection.blocking_lock(), &tables).unwrap();
+
+		standby.connection.blocking_lock().execute_batch("
+			INSERT INTO Sessions (token_hash, username, origin, expires_at) VALUES ('token', 'admin', 'standby', unixepoch() + 3600);
+		").unwrap();
+
+		// saving a layout and rehashing a password on login must not log the admin out of the standby
+		primary.connection.blocking_lock().execute_batch("
+			INSERT INTO Profiles (username, name, content) VALUES ('admin', 'layout', '{}');
+			UPDATE Users SET password_hash = 'stretched', iterations = 600000 WHERE username = 'admin';
+		").unwrap();
+
+		let (_, tables) = changed_tables(&primary.connection.blocking_lock(), &sent).unwrap();
+		let names = tables.iter().map(|table| table.name.as_str()).collect::<Vec<_>>();
+		assert_eq!(names, ["Users", "Profiles"]);
+
+		apply_tables(&mut standby.connection.blocking_lock(), &tables).unwrap();
+
+		let standby = standby.connection.blocking_lock();
+		let sessions = standby.query_row("SELECT COUNT(*) FROM Sessions", [], |row| row.get::<_, i64>(0)).unwrap();
+		let hash = standby.query_row("SELECT password_hash FROM Users WHERE username = 'admin'", [], |row| row.get::<_, String>(0)).unwrap();
+
+		assert_eq!(sessions, 1);
+		assert_eq!(hash, "stretched");
+	}
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+/// Summary statistics of a series of readings.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Statistics {
+	/// The number of readings.
+	pub count: usize,
+
+	/// The smallest reading.
+	pub min: f64,
+
+	/// The largest reading.
+	pub max: f64,
+
+	/// The arithmetic mean of the readings.
+	pub mean: f64,
+
+	/// The population standard deviation of the readings.
+	pub stddev: f64,
+
+	/// The 5th percentile.
+	pub p5: f64,
+
+	/// The 25th percentile.
+	pub p25: f64,
+
+	/// The median.
+	pub p50: f64,
+
+	/// The 75th percentile.
+	pub p75: f64,
+
+	/// The 95th percentile.
+	pub p95: f64,
+
+	/// The 99th percentile.
+	pub p99: f64,
+}
+
+impl Statistics {
+	/// Computes the statistics of a series of readings, ignoring any which are not finite.
+	///
+	/// Returns `None` if there are no finite readings.
+	pub fn compute(mut values: Vec<f64>) -> Option<Self> {
+		values.retain(|value| value.is_finite());
+
+		if values.is_empty() {
+			return None;
+		}
+
+		values.sort_by(f64::total_cmp);
+
+		let count = values.len();
+		let mean = values.iter().sum::<f64>() / count as f64;
+		let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count as f64;
+
+		Some(Statistics {
+			count,
+			min: values[0],
+			max: values[count - 1],
+			mean,
+			stddev: variance.sqrt(),
+			p5: percentile(&values, 5.0),
+			p25: percentile(&values, 25.0),
+			p50: percentile(&values, 50.0),
+			p75: percentile(&values, 75.0),
+			p95: percentile(&values, 95.0),
+			p99: percentile(&values, 99.0),
+		})
+	}
+}
+
+/// The given percentile of sorted values, interpolating linearly between the closest ranks.
+fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+	let rank = percentile / 100.0 * (sorted.len() - 1) as f64;
+	let lower = rank.floor() as usize;
+	let upper = rank.ceil() as usize;
+
+	sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn statistics_of_readings() {
+		assert_eq!(Statistics::compute(vec![f64::NAN]), None);
+
+		let stats = Statistics::compute(vec![4.0, 1.0, f64::NAN, 3.0, 2.0, 5.0]).unwrap();
+		assert_eq!(stats.count, 5);
+		assert_eq!((stats.min, stats.max, stats.mean), (1.0, 5.0, 3.0));
+		assert!((stats.stddev - 2.0_f64.sqrt()).abs() < 1e-12);
+		assert_eq!((stats.p25, stats.p50, stats.p75), (2.0, 3.0, 4.0));
+		assert!((stats.p95 - 4.8).abs() < 1e-12);
+	}
+}
@@ -0,0 +1,215 @@
+use common::comm::VehicleState;
+use rusqlite::params;
+use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc};
+
+use super::{api::SnapshotKey, config::{StorageBackend, StorageConfig}, snapshot, Database};
+
+/// Storage of snapshots in PostgreSQL, for long-term archive servers.
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStorage;
+
+/// A future returned by a storage backend, boxed so that the backend may be
+/// chosen by configuration when the server starts.
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send + 'a>>;
+
+/// The bounds of a query of logged snapshots, each of which is optional.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SnapshotRange {
+	/// The earliest time of a snapshot to include, as a Unix timestamp.
+	pub from: Option<f64>,
+
+	/// The latest time of a snapshot to include, as a Unix timestamp.
+	pub to: Option<f64>,
+
+	/// The key of a snapshot which snapshots must come strictly after, for paging through a range.
+	pub after: Option<SnapshotKey>,
+
+	/// The most snapshots to read, counting from the earliest.
+	pub limit: Option<usize>,
+}
+
+impl SnapshotRange {
+	/// Every snapshot logged between two times, inclusive.
+	pub fn between(from: f64, to: f64) -> Self {
+		SnapshotRange {
+			from: Some(from),
+			to: Some(to),
+			..SnapshotRange::default()
+		}
+	}
+}
+
+/// A backend which the vehicle snapshots logged by servo are persisted to and read back from.
+///
+/// Operational state, such as users, mappings, and sequences, always lives in
+/// the SQLite database beside the server. Snapshots are what grow without bound
+/// and what the whole team queries after a test, so they may instead be kept
+/// by a backend suited to a long-term archive.
+pub trait Storage: Debug + Send + Sync {
+	/// The name of the backend, as it is configured.
+	fn name(&self) -> &'static str;
+
+	/// Prepares the backend to be used, such as by applying its migrations.
+	fn migrate(&self) -> StorageFuture<'_, ()>;
+
+	/// Appends a snapshot, encoded by [`snapshot::encode`], logged at the given time.
+	fn insert_snapshot(&self, recorded_at: f64, encoded: Vec<u8>) -> StorageFuture<'_, ()>;
+
+	/// Reads the snapshots within a range along with their keys, in the order of their keys.
+	fn keyed_snapshots(&self, range: SnapshotRange) -> StorageFuture<'_, Vec<(SnapshotKey, VehicleState)>>;
+
+	/// Reads the snapshots within a range along with the times they were logged, oldest first.
+	fn snapshots(&self, range: SnapshotRange) -> StorageFuture<'_, Vec<(f64, VehicleState)>> {
+		let snapshots = self.keyed_snapshots(range);
+
+		Box::pin(async move {
+			Ok(snapshots.await?.into_iter().map(|(key, state)| (key.recorded_at, state)).collect())
+		})
+	}
+}
+
+/// Opens the configured storage backend, given the SQLite database of the server.
+pub fn open(config: &StorageConfig, database: &Database) -> anyhow::Result<Arc<dyn Storage>> {
+	match config.backend {
+		StorageBackend::Sqlite => Ok(Arc::new(SqliteStorage::new(database.clone()))),
+		#[cfg(feature = "postgres")]
+		StorageBackend::Postgres => {
+			let url = config.url
+				.as_deref()
+				.ok_or_else(|| anyhow::anyhow!("PostgreSQL storage requires a URL to connect to"))?;
+
+			Ok(Arc::new(PostgresStorage::new(url)))
+		},
+		#[cfg(not(feature = "postgres"))]
+		StorageBackend::Postgres => Err(anyhow::anyhow!("servo was built without PostgreSQL storage")),
+	}
+}
+
+/// Storage of snapshots in the `VehicleSnapshots` table of the server's own
+/// SQLite database, which is the default.
+#[derive(Clone, Debug)]
+pub struct SqliteStorage {
+	database: Database,
+}
+
+impl SqliteStorage {
+	/// Stores snapshots in the given database, which is migrated along with the rest of it.
+	pub fn new(database: Database) -> Self {
+		SqliteStorage { database }
+	}
+}
+
+impl Storage for SqliteStorage {
+	fn name(&self) -> &'static str {
+		"sqlite"
+	}
+
+	fn migrate(&self) -> StorageFuture<'_, ()> {
+		// the snapshot table is created by the migrations of the database itself
+		Box::pin(async { Ok(()) })
+	}
+
+	fn insert_snapshot(&self, recorded_at: f64, encoded: Vec<u8>) -> StorageFuture<'_, ()> {
+		Box::pin(async move {
+			self.database.connection
+				.lock()
+				.await
+				.execute(
+					"INSERT INTO VehicleSnapshots (recorded_at, vehicle_state, compressed) VALUES (?1, ?2, TRUE)",
+					params![recorded_at, encoded],
+				)?;
+
+			Ok(())
+		})
+	}
+
+	fn keyed_snapshots(&self, range: SnapshotRange) -> StorageFuture<'_, Vec<(SnapshotKey, VehicleState)>> {
+		Box::pin(async move {
+			// a negative limit is no limit at all in SQLite
+			let limit = range.limit.map_or(-1, |limit| limit as i64);
+			let (after, after_id) = (range.after.map(|key| key.recorded_at), range.after.map(|key| key.id));
+
+			let snapshots = self.database.connection
+				.lock()
+				.await
+				.prepare_cached("
+					SELECT recorded_at, snapshot_id, vehicle_state, compressed FROM VehicleSnapshots
+					WHERE (?1 IS NULL OR recorded_at >= ?1)
+						AND (?2 IS NULL OR recorded_at <= ?2)
+						AND (?3 IS NULL OR (recorded_at, snapshot_id) > (?3, ?4))
+					ORDER BY recorded_at, snapshot_id
+					LIMIT ?5
+				")?
+				.query_map(params![range.from, range.to, after, after_id, limit], |row| {
+					let key = SnapshotKey { recorded_at: row.get(0)?, id: row.get(1)? };
+					Ok((key, snapshot::from_row(row, 2)?))
+				})?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			Ok(snapshots)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn snapshots_are_paged_through_in_order() {
+		let database = Database::volatile().unwrap();
+		tokio::task::block_in_place(|| database.migrate()).unwrap();
+
+		let storage = SqliteStorage::new(database);
+
+		for index in [3, 1, 2, 4] {
+			let mut state = VehicleState::new();
+			state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: index as f64, unit: Unit::Psi });
+			storage.insert_snapshot(index as f64, snapshot::encode(&state).unwrap()).await.unwrap();
+		}
+
+		let times = |snapshots: Vec<(f64, VehicleState)>| snapshots.into_iter().map(|(time, _)| time).collect::<Vec<_>>();
+
+		assert_eq!(times(storage.snapshots(SnapshotRange::between(2.0, 3.0)).await.unwrap()), [2.0, 3.0]);
+
+		let page = SnapshotRange { after: Some(SnapshotKey::after_time(1.0)), limit: Some(2), ..SnapshotRange::default() };
+		let snapshots = storage.snapshots(page).await.unwrap();
+		assert_eq!(snapshots[1].1.sensor_readings["KBPT"].value, 3.0);
+		assert_eq!(times(snapshots), [2.0, 3.0]);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn snapshots_logged_at_the_same_time_are_not_skipped() {
+		let database = Database::volatile().unwrap();
+		tokio::task::block_in_place(|| database.migrate()).unwrap();
+
+		let storage = SqliteStorage::new(database);
+
+		for (recorded_at, value) in [(1.0, 1.0), (2.0, 2.0), (2.0, 3.0), (2.0, 4.0), (3.0, 5.0)] {
+			let mut state = VehicleState::new();
+			state.sensor_readings.insert("KBPT".to_owned(), Measurement { value, unit: Unit::Psi });
+			storage.insert_snapshot(recorded_at, snapshot::encode(&state).unwrap()).await.unwrap();
+		}
+
+		// pages of two split the snapshots logged at the same time
+		let mut after = None;
+		let mut values = Vec::new();
+
+		loop {
+			let page = storage.keyed_snapshots(SnapshotRange { after, limit: Some(2), ..SnapshotRange::default() }).await.unwrap();
+
+			let Some(&(last, _)) = page.last() else {
+				break;
+			};
+
+			after = Some(last);
+			values.extend(page.into_iter().map(|(_, state)| state.sensor_readings["KBPT"].value));
+		}
+
+		assert_eq!(values, [1.0, 2.0, 3.0, 4.0, 5.0]);
+	}
+}
@@ -0,0 +1,163 @@
+use anyhow::anyhow;
+use common::comm::VehicleState;
+use include_dir::{include_dir, Dir};
+use sqlx::{postgres::PgPoolOptions, Executor, PgConnection, PgPool, Row};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use super::{SnapshotRange, Storage, StorageFuture};
+use crate::server::{api::SnapshotKey, snapshot};
+
+/// The migrations of the PostgreSQL schema, which are separate from those of
+/// the SQLite database since only snapshots are stored in PostgreSQL.
+const MIGRATIONS: Dir = include_dir!("./src/migrations/postgres");
+
+/// The most connections opened to the PostgreSQL server at once.
+const MAX_CONNECTIONS: u32 = 8;
+
+/// How long to wait for a connection before giving up, such as when the archive server is unreachable.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An arbitrary key for the advisory lock held while migrating, so that
+/// servers which start at the same time do not migrate over each other.
+const MIGRATION_LOCK: i64 = 7201;
+
+/// Applies every migration which has not yet been applied to the database.
+async fn apply_migrations(connection: &mut PgConnection) -> anyhow::Result<()> {
+	let mut migrations = MIGRATIONS
+		.dirs()
+		.filter_map(|directory| {
+			let migration = directory.path().file_name()?.to_str()?.parse::<i32>().ok()?;
+			let sql = directory.get_file(directory.path().join("up.sql"))?.contents_utf8()?;
+			Some((migration, sql))
+		})
+		.collect::<Vec<_>>();
+
+	migrations.sort_by_key(|(migration, _)| *migration);
+
+	sqlx::query("SELECT pg_advisory_xact_lock($1)")
+		.bind(MIGRATION_LOCK)
+		.execute(&mut *connection)
+		.await?;
+
+	connection
+		.execute("CREATE TABLE IF NOT EXISTS migrations (migration_id INTEGER PRIMARY KEY, applied_at TIMESTAMPTZ NOT NULL DEFAULT now())")
+		.await?;
+
+	let current = sqlx::query_scalar::<_, Option<i32>>("SELECT MAX(migration_id) FROM migrations")
+		.fetch_one(&mut *connection)
+		.await?
+		.unwrap_or(0);
+
+	for (migration, sql) in migrations.into_iter().filter(|(migration, _)| *migration > current) {
+		connection
+			.execute(sql)
+			.await
+			.map_err(|error| anyhow!("migration {migration} failed: {error}"))?;
+
+		sqlx::query("INSERT INTO migrations (migration_id) VALUES ($1)")
+			.bind(migration)
+			.execute(&mut *connection)
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Storage of snapshots in a PostgreSQL database, such as that of a long-term
+/// archive server which several servers stream their snapshots to.
+///
+/// The connection is opened when the storage is migrated, which happens as the
+/// server starts, so that an unreachable database stops the server right away.
+#[derive(Debug)]
+pub struct PostgresStorage {
+	url: String,
+	pool: OnceCell<PgPool>,
+}
+
+impl PostgresStorage {
+	/// Stores snapshots in the PostgreSQL database at a URL such as `postgres://servo@archive.local/servo`.
+	pub fn new(url: &str) -> Self {
+		PostgresStorage {
+			url: url.to_owned(),
+			pool: OnceCell::new(),
+		}
+	}
+
+	/// The pool of connections to the database, once it has been connected to.
+	fn pool(&self) -> anyhow::Result<&PgPool> {
+		self.pool
+			.get()
+			.ok_or_else(|| anyhow!("PostgreSQL storage is used before it was migrated"))
+	}
+}
+
+impl Storage for PostgresStorage {
+	fn name(&self) -> &'static str {
+		"postgres"
+	}
+
+	fn migrate(&self) -> StorageFuture<'_, ()> {
+		Box::pin(async move {
+			if self.pool.get().is_none() {
+				let pool = PgPoolOptions::new()
+					.max_connections(MAX_CONNECTIONS)
+					.acquire_timeout(CONNECT_TIMEOUT)
+					.connect(&self.url)
+					.await?;
+
+				// another migration may have connected in the meantime, in which case its pool is used
+				let _ = self.pool.set(pool);
+			}
+
+			let pool = self.pool()?;
+
+			let mut transaction = pool.begin().await?;
+			apply_migrations(&mut transaction).await?;
+			transaction.commit().await?;
+			Ok(())
+		})
+	}
+
+	fn insert_snapshot(&self, recorded_at: f64, encoded: Vec<u8>) -> StorageFuture<'_, ()> {
+		Box::pin(async move {
+			sqlx::query("INSERT INTO vehicle_snapshots (recorded_at, vehicle_state) VALUES ($1, $2)")
+				.bind(recorded_at)
+				.bind(encoded)
+				.execute(self.pool()?)
+				.await?;
+
+			Ok(())
+		})
+	}
+
+	fn keyed_snapshots(&self, range: SnapshotRange) -> StorageFuture<'_, Vec<(SnapshotKey, VehicleState)>> {
+		Box::pin(async move {
+			// a null limit is no limit at all in PostgreSQL
+			let rows = sqlx::query("
+				SELECT recorded_at, snapshot_id, vehicle_state FROM vehicle_snapshots
+				WHERE ($1::DOUBLE PRECISION IS NULL OR recorded_at >= $1)
+					AND ($2::DOUBLE PRECISION IS NULL OR recorded_at <= $2)
+					AND ($3::DOUBLE PRECISION IS NULL OR (recorded_at, snapshot_id) > ($3, $4::BIGINT))
+				ORDER BY recorded_at, snapshot_id
+				LIMIT $5
+			")
+				.bind(range.from)
+				.bind(range.to)
+				.bind(range.after.map(|key| key.recorded_at))
+				.bind(range.after.map(|key| key.id))
+				.bind(range.limit.map(|limit| limit as i64))
+				.fetch_all(self.pool()?)
+				.await?;
+
+			rows
+				.iter()
+				.map(|row| {
+					let key = SnapshotKey { recorded_at: row.try_get(0)?, id: row.try_get(1)? };
+					let vehicle_state = snapshot::decode(row.try_get::<&[u8], _>(2)?, true)?;
+					Ok((key, vehicle_state))
+				})
+				.collect()
+		})
+	}
+}
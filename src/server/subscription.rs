@@ -0,0 +1,72 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// What a client asked to be forwarded, kept so that it may resume the same
+/// stream after reconnecting, such as after a Wi-Fi blip mid-test.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Subscription {
+	/// The channels forwarded, or every channel if `None`.
+	pub channels: Option<Vec<String>>,
+
+	/// The rate in hertz asked for, or the configured rate if `None`.
+	pub rate_hz: Option<f64>,
+
+	/// Whether channel metadata is sent along with vehicle state.
+	pub metadata: bool,
+
+	/// Whether each sensor reading sent is the mean of those since the last, with their envelope.
+	#[serde(default)]
+	pub aggregate: bool,
+}
+
+/// Saves the subscription of a client, keyed by the user it is logged in as,
+/// if any, and the host it connected from, replacing any earlier subscription.
+pub fn save(connection: &Connection, username: Option<&str>, host: &str, subscription: &Subscription) -> anyhow::Result<()> {
+	connection.execute(
+		"INSERT OR REPLACE INTO ForwardingSubscriptions (username, host, subscription, updated_at) VALUES (?1, ?2, ?3, unixepoch('now', 'subsec'))",
+		params![username.unwrap_or_default(), host, serde_json::to_string(subscription)?],
+	)?;
+
+	Ok(())
+}
+
+/// Loads the last subscription of a client, if it has ever subscribed.
+pub fn load(connection: &Connection, username: Option<&str>, host: &str) -> anyhow::Result<Option<Subscription>> {
+	let subscription = connection
+		.query_row(
+			"SELECT subscription FROM ForwardingSubscriptions WHERE username = ?1 AND host = ?2",
+			params![username.unwrap_or_default(), host],
+			|row| row.get::<_, String>(0),
+		)
+		.optional()?;
+
+	Ok(subscription.map(|subscription| serde_json::from_str(&subscription)).transpose()?)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn subscriptions_are_kept_per_user_and_host() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+
+		let subscription = Subscription {
+			channels: Some(vec!["KBPT".to_owned(), "FMPT".to_owned()]),
+			rate_hz: Some(20.0),
+			metadata: true,
+			aggregate: true,
+		};
+
+		save(&connection, Some("jeff"), "10.0.0.5", &Subscription::default()).unwrap();
+		save(&connection, Some("jeff"), "10.0.0.5", &subscription).unwrap();
+
+		assert_eq!(load(&connection, Some("jeff"), "10.0.0.5").unwrap(), Some(subscription));
+		assert_eq!(load(&connection, None, "10.0.0.5").unwrap(), None);
+		assert_eq!(load(&connection, Some("jeff"), "10.0.0.6").unwrap(), None);
+	}
+}
@@ -0,0 +1,210 @@
+use jeflog::{fail, pass};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, collections::BTreeMap, fmt, future::Future, time::{Duration, Instant}};
+use tokio::{sync::Mutex, task::JoinSet};
+
+use super::{clock, Shared};
+
+/// The delay before a task is first restarted after it stops.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest delay before a task is restarted, however often it has stopped.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How long a task must run before stopping for its backoff to start over.
+const STABLE_RUNTIME: Duration = Duration::from_secs(60);
+
+/// Whether a supervised task is running.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+	/// The task is running.
+	Running,
+
+	/// The task stopped and is waiting out its backoff before restarting.
+	Restarting,
+}
+
+/// The health of a single supervised task.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskHealth {
+	/// Whether the task is running.
+	pub status: TaskStatus,
+
+	/// The number of times the task has been restarted.
+	pub restarts: u32,
+
+	/// When the task was last started, as a Unix timestamp.
+	pub started_at: f64,
+
+	/// Why the task last stopped, if it ever has.
+	pub last_failure: Option<String>,
+
+	/// When the task last stopped, as a Unix timestamp.
+	pub last_failed_at: Option<f64>,
+}
+
+/// Tracks the health of the long-running tasks of the server.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+	tasks: Mutex<BTreeMap<String, TaskHealth>>,
+}
+
+impl Supervisor {
+	/// The health of every supervised task, keyed by name.
+	pub async fn health(&self) -> BTreeMap<String, TaskHealth> {
+		self.tasks.lock().await.clone()
+	}
+
+	async fn started(&self, name: &str) {
+		let mut tasks = self.tasks.lock().await;
+		let now = clock::now();
+
+		tasks
+			.entry(name.to_owned())
+			.and_modify(|health| {
+				health.status = TaskStatus::Running;
+				health.restarts += 1;
+				health.started_at = now;
+			})
+			.or_insert(TaskHealth {
+				status: TaskStatus::Running,
+				restarts: 0,
+				started_at: now,
+				last_failure: None,
+				last_failed_at: None,
+			});
+	}
+
+	async fn stopped(&self, name: &str, failure: String) {
+		if let Some(health) = self.tasks.lock().await.get_mut(name) {
+			health.status = TaskStatus::Restarting;
+			health.last_failure = Some(failure);
+			health.last_failed_at = Some(clock::now());
+		}
+	}
+}
+
+/// Describes why a task stopped from the value it returned.
+///
+/// Supervised tasks are meant to run for as long as the server does, so
+/// returning at all is treated as a failure.
+pub trait TaskOutcome {
+	/// Describes how the task stopped, such as `exited` or `failed: address in use`.
+	fn describe(self) -> String;
+}
+
+impl TaskOutcome for () {
+	fn describe(self) -> String {
+		"exited".to_owned()
+	}
+}
+
+impl<T, E: fmt::Display> TaskOutcome for Result<T, E> {
+	fn describe(self) -> String {
+		match self {
+			Ok(_) => "exited".to_owned(),
+			Err(error) => format!("failed: {error}"),
+		}
+	}
+}
+
+/// The exponentially increasing delay between restarts of a task which keeps stopping.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+	next: Duration,
+}
+
+impl Default for Backoff {
+	fn default() -> Self {
+		Backoff { next: MIN_BACKOFF }
+	}
+}
+
+impl Backoff {
+	/// Returns the delay before restarting a task which ran for the given time before stopping.
+	pub fn after_stopping(&mut self, ran_for: Duration) -> Duration {
+		if ran_for >= STABLE_RUNTIME {
+			self.next = MIN_BACKOFF;
+		}
+
+		let delay = self.next;
+		self.next = (self.next * 2).min(MAX_BACKOFF);
+		delay
+	}
+}
+
+/// Spawns a long-running task which is restarted with backoff whenever it
+/// returns or panics, recording its health in the server's supervisor.
+///
+/// The task is constructed anew from the shared state each time it starts, and
+/// is spawned into the given set, so that aborting the set stops it for good.
+pub fn supervise<F, Fut>(tasks: &mut JoinSet<()>, shared: &Shared, name: &str, task: F)
+where
+	F: Fn(&Shared) -> Fut + Send + 'static,
+	Fut: Future + Send + 'static,
+	Fut::Output: TaskOutcome + Send + 'static,
+{
+	let shared = shared.clone();
+	let name = name.to_owned();
+
+	tasks.spawn(async move {
+		let mut backoff = Backoff::default();
+
+		loop {
+			shared.supervisor.started(&name).await;
+			let started = Instant::now();
+
+			// the task runs in a set of its own, which aborts it if the supervisor is aborted.
+			let mut running = JoinSet::new();
+			running.spawn(task(&shared));
+
+			let failure = match running.join_next().await.expect("the task was just spawned") {
+				Ok(outcome) => outcome.describe(),
+				Err(error) if error.is_panic() => format!("panicked: {}", panic_message(error.into_panic())),
+				Err(error) => format!("was cancelled: {error}"),
+			};
+
+			let delay = backoff.after_stopping(started.elapsed());
+
+			fail!("Task '{name}' {failure}. Restarting in {} s.", delay.as_secs());
+			shared.supervisor.stopped(&name, failure).await;
+
+			tokio::time::sleep(delay).await;
+			pass!("Restarting task '{name}'.");
+		}
+	});
+}
+
+/// Extracts the message from a panic payload, which is a string unless the panic was raised unusually.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		(*message).to_owned()
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message.clone()
+	} else {
+		"unknown panic".to_owned()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backoff_doubles_until_task_is_stable() {
+		let mut backoff = Backoff::default();
+		let brief = Duration::from_millis(10);
+
+		assert_eq!(backoff.after_stopping(brief), Duration::from_secs(1));
+		assert_eq!(backoff.after_stopping(brief), Duration::from_secs(2));
+		assert_eq!(backoff.after_stopping(brief), Duration::from_secs(4));
+
+		for _ in 0..10 {
+			backoff.after_stopping(brief);
+		}
+
+		assert_eq!(backoff.after_stopping(brief), MAX_BACKOFF);
+		assert_eq!(backoff.after_stopping(STABLE_RUNTIME), MIN_BACKOFF);
+	}
+}
@@ -0,0 +1,311 @@
+use common::comm::VehicleState;
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+
+use super::{
+	alert::{Alert, Severity},
+	api::{Field, SyncBatch, SyncCounts, SyncedSnapshot, TableDump},
+	report::TelemetryGap,
+	snapshot,
+	storage::{SnapshotRange, Storage},
+	Database,
+};
+
+/// The tables synced between servers: the configurations of node mappings and
+/// the sequences run against them, which are merged row by row rather than
+/// replaced as they are when mirroring to a standby.
+pub const SYNCED_TABLES: [&str; 3] = ["NodeMappings", "Sequences", "SequenceFiles"];
+
+/// Columns which are never synced, because they are the receiving server's own
+/// choice of which configuration is active and which sequence safes the vehicle.
+const LOCAL_COLUMNS: [&str; 2] = ["active", "safing"];
+
+/// The most snapshots sent in one batch, which keeps a batch of even large
+/// vehicle states well within the default limit on sync batches.
+pub const BATCH_SNAPSHOTS: usize = 2000;
+
+/// The fingerprint of a snapshot, which identifies it by the time it was
+/// logged and a hash of its contents.
+///
+/// Readings are hashed in order of their names, so that the same vehicle state
+/// has the same fingerprint on every server no matter how its maps are ordered.
+pub fn fingerprint(recorded_at: f64, vehicle_state: &VehicleState) -> anyhow::Result<[u8; 32]> {
+	let sensor_readings = vehicle_state.sensor_readings.iter().collect::<BTreeMap<_, _>>();
+	let valve_states = vehicle_state.valve_states.iter().collect::<BTreeMap<_, _>>();
+	let serialized = postcard::to_allocvec(&(recorded_at.to_bits(), sensor_readings, valve_states))?;
+
+	Ok(Sha256::digest(&serialized).into())
+}
+
+/// Collects a batch of the snapshots in a range, along with the tables and
+/// events of the whole range when `after` is not set, as it is for the first batch.
+pub async fn collect(
+	database: &Database,
+	storage: &dyn Storage,
+	range: SnapshotRange,
+) -> anyhow::Result<SyncBatch> {
+	let keyed = storage
+		.keyed_snapshots(SnapshotRange { limit: Some(range.limit.unwrap_or(BATCH_SNAPSHOTS).min(BATCH_SNAPSHOTS)), ..range })
+		.await?;
+
+	let last = keyed.last().map(|&(key, _)| key);
+
+	let snapshots = keyed
+		.into_iter()
+		.map(|(key, vehicle_state)| {
+			Ok(SyncedSnapshot {
+				recorded_at: key.recorded_at,
+				vehicle_state: base64::encode(snapshot::encode(&vehicle_state)?),
+			})
+		})
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	let mut batch = SyncBatch { snapshots, last, ..SyncBatch::default() };
+
+	if range.after.is_none() {
+		let connection = database.connection.lock().await;
+		let from = range.from.unwrap_or(f64::NEG_INFINITY);
+		let to = range.to.unwrap_or(f64::INFINITY);
+
+		batch.tables = dump_tables(&connection)?;
+		batch.alerts = list_alerts(&connection, from, to)?;
+		batch.notes = super::note::list(&connection, from, to)?;
+		batch.gaps = list_gaps(&connection, from, to)?;
+	}
+
+	Ok(batch)
+}
+
+/// Merges a batch into this server, skipping snapshots and events it already
+/// has, and returns how much of the batch was new.
+///
+/// Snapshots are the same if they were logged at the same time with the same
+/// contents, as told by their [`fingerprint`]s.
+pub async fn merge(
+	database: &Database,
+	storage: &dyn Storage,
+	batch: SyncBatch,
+) -> anyhow::Result<SyncCounts> {
+	let mut counts = SyncCounts::default();
+
+	let snapshots = batch.snapshots
+		.iter()
+		.map(|synced| Ok((synced.recorded_at, snapshot::decode(&base64::decode(&synced.vehicle_state)?, true)?)))
+		.collect::<anyhow::Result<Vec<_>>>()?;
+
+	counts.snapshots = insert_new(storage, snapshots).await?;
+
+	let mut connection = database.connection.lock().await;
+	let transaction = connection.transaction()?;
+
+	for table in &batch.tables {
+		counts.rows += merge_table(&transaction, table)?;
+	}
+
+	for alert in &batch.alerts {
+		counts.events += transaction.execute(
+			"INSERT INTO Alerts (rule, severity, message, raised_at, causes)
+			SELECT ?1, ?2, ?3, ?4, ?5
+			WHERE NOT EXISTS (SELECT 1 FROM Alerts WHERE rule = ?1 AND message = ?3 AND raised_at = ?4)",
+			params![alert.rule, alert.severity.to_string(), alert.message, alert.raised_at, serde_json::to_string(&alert.causes)?],
+		)?;
+	}
+
+	// the session a note was entered through belongs to the server it came from
+	for note in &batch.notes {
+		counts.events += transaction.execute(
+			"INSERT INTO Notes (author, content, created_at)
+			SELECT ?1, ?2, ?3
+			WHERE NOT EXISTS (SELECT 1 FROM Notes WHERE content = ?2 AND created_at = ?3)",
+			params![note.author, note.content, note.created_at],
+		)?;
+	}
+
+	for gap in &batch.gaps {
+		counts.events += transaction.execute(
+			"INSERT INTO TelemetryGaps (first_missing, last_missing, started_at, ended_at)
+			SELECT ?1, ?2, ?3, ?4
+			WHERE NOT EXISTS (SELECT 1 FROM TelemetryGaps WHERE first_missing = ?1 AND last_missing = ?2 AND started_at = ?3)",
+			params![gap.first_missing, gap.last_missing, gap.started_at, gap.ended_at],
+		)?;
+	}
+
+	transaction.commit()?;
+	Ok(counts)
+}
+
+/// Logs whichever of the given snapshots are not logged already, as told by
+/// their [`fingerprint`]s, and returns the number which were new.
+pub async fn insert_new(storage: &dyn Storage, snapshots: Vec<(f64, VehicleState)>) -> anyhow::Result<usize> {
+	let (from, to) = snapshots
+		.iter()
+		.fold((f64::INFINITY, f64::NEG_INFINITY), |(from, to), snapshot| (from.min(snapshot.0), to.max(snapshot.0)));
+
+	let mut known = HashSet::new();
+
+	if from <= to {
+		for (recorded_at, vehicle_state) in storage.snapshots(SnapshotRange::between(from, to)).await? {
+			known.insert(fingerprint(recorded_at, &vehicle_state)?);
+		}
+	}
+
+	let mut inserted = 0;
+
+	for (recorded_at, vehicle_state) in snapshots {
+		if known.insert(fingerprint(recorded_at, &vehicle_state)?) {
+			storage.insert_snapshot(recorded_at, snapshot::encode(&vehicle_state)?).await?;
+			inserted += 1;
+		}
+	}
+
+	Ok(inserted)
+}
+
+/// Dumps every row of the synced tables, leaving out the columns which are local to each server.
+fn dump_tables(connection: &Connection) -> rusqlite::Result<Vec<TableDump>> {
+	SYNCED_TABLES
+		.iter()
+		.map(|&name| {
+			let mut statement = connection.prepare(&format!("SELECT * FROM {name}"))?;
+
+			let columns = statement
+				.column_names()
+				.into_iter()
+				.map(str::to_owned)
+				.collect::<Vec<_>>();
+
+			let synced = columns
+				.iter()
+				.map(|column| !LOCAL_COLUMNS.contains(&column.as_str()))
+				.collect::<Vec<_>>();
+
+			let rows = statement
+				.query_map([], |row| {
+					(0..columns.len())
+						.filter(|&column| synced[column])
+						.map(|column| Ok(Field::from(row.get_ref(column)?)))
+						.collect()
+				})?
+				.collect::<rusqlite::Result<Vec<_>>>()?;
+
+			let columns = columns
+				.into_iter()
+				.zip(synced)
+				.filter_map(|(column, synced)| synced.then_some(column))
+				.collect();
+
+			Ok(TableDump { name: name.to_owned(), columns, rows })
+		})
+		.collect()
+}
+
+/// Inserts the rows of a synced table, updating those which already exist,
+/// and returns the number of rows changed.
+fn merge_table(connection: &Connection, table: &TableDump) -> anyhow::Result<usize> {
+	// the names are interpolated into SQL, so they must be ones this server expects
+	if !SYNCED_TABLES.contains(&table.name.as_str()) {
+		anyhow::bail!("unexpected table '{}' in sync", table.name);
+	}
+
+	if table.columns.iter().any(|column| LOCAL_COLUMNS.contains(&column.as_str())) {
+		anyhow::bail!("table '{}' includes a column which is not synced", table.name);
+	}
+
+	let columns = table.columns
+		.iter()
+		.map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+		.collect::<Vec<_>>();
+
+	let placeholders = (1..=columns.len())
+		.map(|index| format!("?{index}"))
+		.collect::<Vec<_>>();
+
+	let updates = columns
+		.iter()
+		.map(|column| format!("{column} = excluded.{column}"))
+		.collect::<Vec<_>>();
+
+	let excluded = columns
+		.iter()
+		.map(|column| format!("excluded.{column}"))
+		.collect::<Vec<_>>();
+
+	// rows which are already the same are left alone, so that they are not counted as changed
+	let mut insert = connection.prepare(&format!(
+		"INSERT INTO {} ({}) VALUES ({}) ON CONFLICT DO UPDATE SET {} WHERE ({}) IS NOT ({})",
+		table.name,
+		columns.join(", "),
+		placeholders.join(", "),
+		updates.join(", "),
+		columns.join(", "),
+		excluded.join(", "),
+	))?;
+
+	let mut changed = 0;
+
+	for row in &table.rows {
+		changed += insert.execute(rusqlite::params_from_iter(row))?;
+	}
+
+	Ok(changed)
+}
+
+/// Lists the alerts raised between two Unix timestamps, oldest first.
+fn list_alerts(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<Alert>> {
+	connection
+		.prepare("SELECT rule, severity, message, raised_at, causes FROM Alerts WHERE raised_at >= ?1 AND raised_at <= ?2 ORDER BY raised_at")?
+		.query_map(params![from, to], |row| {
+			Ok(Alert {
+				rule: row.get(0)?,
+				severity: row.get::<_, String>(1)?.parse().unwrap_or(Severity::Warning),
+				message: row.get(2)?,
+				raised_at: row.get(3)?,
+				causes: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+			})
+		})?
+		.collect()
+}
+
+/// Lists the telemetry gaps overlapping two Unix timestamps, oldest first.
+fn list_gaps(connection: &Connection, from: f64, to: f64) -> rusqlite::Result<Vec<TelemetryGap>> {
+	connection
+		.prepare("SELECT first_missing, last_missing, started_at, ended_at FROM TelemetryGaps WHERE ended_at >= ?1 AND started_at <= ?2 ORDER BY started_at")?
+		.query_map(params![from, to], |row| {
+			Ok(TelemetryGap {
+				first_missing: row.get(0)?,
+				last_missing: row.get(1)?,
+				started_at: row.get(2)?,
+				ended_at: row.get(3)?,
+			})
+		})?
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{Measurement, Unit};
+	use super::*;
+
+	#[test]
+	fn fingerprints_ignore_map_order() {
+		let readings = [("KBPT", 1.0), ("WTPT", 2.0), ("FMPT", 3.0), ("OMPT", f64::NAN)];
+
+		let mut forward = VehicleState::new();
+		let mut backward = VehicleState::new();
+
+		for &(name, value) in &readings {
+			forward.sensor_readings.insert(name.to_owned(), Measurement { value, unit: Unit::Psi });
+		}
+
+		for &(name, value) in readings.iter().rev() {
+			backward.sensor_readings.insert(name.to_owned(), Measurement { value, unit: Unit::Psi });
+		}
+
+		assert_eq!(fingerprint(10.0, &forward).unwrap(), fingerprint(10.0, &backward).unwrap());
+		assert_ne!(fingerprint(10.0, &forward).unwrap(), fingerprint(10.5, &forward).unwrap());
+
+		backward.sensor_readings.get_mut("KBPT").unwrap().value = 1.5;
+		assert_ne!(fingerprint(10.0, &forward).unwrap(), fingerprint(10.0, &backward).unwrap());
+	}
+}
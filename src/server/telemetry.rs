@@ -0,0 +1,333 @@
+use common::comm::VehicleState;
+use std::{fs, future::Future, net::SocketAddr};
+use tokio::{io::{self, AsyncRead, AsyncReadExt}, net::{TcpStream, UdpSocket}};
+
+/// The largest vehicle state frame accepted over a stream transport, so that a
+/// corrupt length prefix cannot make servo allocate an absurd buffer.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// The largest payload of a UDP datagram over IPv4, which is the largest frame
+/// which can be sent as a single datagram, fragmented or not.
+pub const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// The size of the IPv4 and UDP headers, which a link's MTU must also carry.
+pub const DATAGRAM_HEADER_SIZE: usize = 28;
+
+/// Windows' `WSAEMSGSIZE`, returned when a datagram is larger than the buffer it is received into.
+const WINDOWS_MESSAGE_TOO_LONG: i32 = 10040;
+
+/// Marks a frame which begins with a sequence number header.
+///
+/// `VehicleState` itself carries no sequence number, so a sequenced frame is the
+/// magic, followed by a big-endian `u64` sequence number, followed by the usual
+/// Postcard-serialized `VehicleState`. Frames without the magic are still accepted.
+pub const SEQUENCE_MAGIC: [u8; 4] = *b"YJSQ";
+
+/// How far behind the latest sequence number a frame may be before its
+/// sender is assumed to have restarted rather than the frame having been
+/// reordered in transit.
+pub const REORDER_WINDOW: u64 = 1024;
+
+/// Splits the sequence number header from a frame, if it has one.
+pub fn split_sequence_number(frame: &[u8]) -> (Option<u64>, &[u8]) {
+	let header_size = SEQUENCE_MAGIC.len() + 8;
+
+	if frame.len() < header_size || frame[..SEQUENCE_MAGIC.len()] != SEQUENCE_MAGIC {
+		return (None, frame);
+	}
+
+	let mut sequence_number = [0; 8];
+	sequence_number.copy_from_slice(&frame[SEQUENCE_MAGIC.len()..header_size]);
+
+	(Some(u64::from_be_bytes(sequence_number)), &frame[header_size..])
+}
+
+/// Prepends a sequence number header to a serialized vehicle state.
+pub fn sequenced_frame(sequence_number: u64, payload: &[u8]) -> Vec<u8> {
+	let mut frame = Vec::with_capacity(SEQUENCE_MAGIC.len() + 8 + payload.len());
+	frame.extend_from_slice(&SEQUENCE_MAGIC);
+	frame.extend_from_slice(&sequence_number.to_be_bytes());
+	frame.extend_from_slice(payload);
+	frame
+}
+
+/// How a sequenced frame arrived relative to the frames before it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Arrival {
+	/// The frame directly follows the previous one, or is the first frame.
+	InOrder,
+
+	/// One or more frames were skipped before this one.
+	Gap {
+		/// The sequence number of the first missing frame.
+		first_missing: u64,
+
+		/// The sequence number of the last missing frame.
+		last_missing: u64,
+	},
+
+	/// The frame is older than or a duplicate of one already received.
+	OutOfOrder,
+
+	/// The sender restarted its count, such as after a reboot.
+	Restarted,
+}
+
+/// Tracks the sequence numbers received over a transport to account for lost frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GapTracker {
+	last: Option<u64>,
+}
+
+impl GapTracker {
+	/// Records the arrival of a sequence number, describing how it relates to those before it.
+	pub fn record(&mut self, sequence_number: u64) -> Arrival {
+		let Some(last) = self.last else {
+			self.last = Some(sequence_number);
+			return Arrival::InOrder;
+		};
+
+		// the first frames after a restart may be lost, so any jump backwards
+		// further than reordering could explain is taken as a restart
+		if (sequence_number == 0 && last != 0) || last.saturating_sub(sequence_number) > REORDER_WINDOW {
+			self.last = Some(sequence_number);
+			return Arrival::Restarted;
+		}
+
+		if sequence_number <= last {
+			return Arrival::OutOfOrder;
+		}
+
+		self.last = Some(sequence_number);
+
+		if sequence_number == last + 1 {
+			Arrival::InOrder
+		} else {
+			Arrival::Gap {
+				first_missing: last + 1,
+				last_missing: sequence_number - 1,
+			}
+		}
+	}
+}
+
+/// Decodes the vehicle state of a received frame, recording its sequence number if it has one.
+///
+/// No state is decoded from an out-of-order frame, since it is older than the
+/// current state and must not replace it. Both live telemetry and replayed
+/// recordings are decoded here, so that the two cannot diverge.
+pub fn decode_frame(tracker: &mut GapTracker, frame: &[u8]) -> (Option<Arrival>, Option<postcard::Result<VehicleState>>) {
+	let (sequence_number, payload) = split_sequence_number(frame);
+	let arrival = sequence_number.map(|sequence_number| tracker.record(sequence_number));
+
+	if arrival == Some(Arrival::OutOfOrder) {
+		return (arrival, None);
+	}
+
+	(arrival, Some(postcard::from_bytes(payload)))
+}
+
+/// A frame received over a telemetry transport.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+	/// The address the frame was sent from, if it is known.
+	pub source: Option<SocketAddr>,
+
+	/// The bytes of the frame, which are only its beginning if it was truncated.
+	pub bytes: &'a [u8],
+
+	/// Whether the frame was larger than the transport could receive, so that
+	/// the rest of it was lost.
+	pub truncated: bool,
+}
+
+/// A source of serialized vehicle state frames.
+///
+/// The flight computer chooses a transport per connection: it may send each
+/// frame as a UDP datagram, or connect over TCP and send length-prefixed frames
+/// when the network is too lossy for UDP. Length-prefixed frames are also sent
+/// over the serial link, when one is configured.
+pub trait TelemetryTransport: Send {
+	/// Receives the next frame, returning `None` once the transport has closed.
+	fn receive_frame(&mut self) -> impl Future<Output = io::Result<Option<Frame<'_>>>> + Send;
+}
+
+/// Receives frames as individual UDP datagrams, which may be lost or reordered.
+///
+/// The buffer holds one byte more than the largest datagram accepted, so that
+/// a datagram which overflows it is noticed rather than silently cut short,
+/// which is all that Unix systems otherwise do.
+#[derive(Debug)]
+pub struct UdpTransport {
+	socket: UdpSocket,
+	buffer: Vec<u8>,
+	max_datagram_size: usize,
+}
+
+impl UdpTransport {
+	/// Wraps a bound UDP socket which accepts datagrams of up to the given size.
+	pub fn new(socket: UdpSocket, max_datagram_size: usize) -> Self {
+		UdpTransport {
+			socket,
+			buffer: vec![0; max_datagram_size + 1],
+			max_datagram_size,
+		}
+	}
+}
+
+impl TelemetryTransport for UdpTransport {
+	async fn receive_frame(&mut self) -> io::Result<Option<Frame<'_>>> {
+		match self.socket.recv_from(&mut self.buffer).await {
+			// if the datagram size is zero, the connection has been closed
+			Ok((0, _)) => Ok(None),
+			Ok((datagram_size, source)) => Ok(Some(Frame {
+				source: Some(source),
+				bytes: &self.buffer[..datagram_size.min(self.max_datagram_size)],
+				truncated: datagram_size > self.max_datagram_size,
+			})),
+			// Windows fills the buffer with the beginning of the datagram, but
+			// reports an error rather than where it came from.
+			Err(error) if error.raw_os_error() == Some(WINDOWS_MESSAGE_TOO_LONG) => Ok(Some(Frame {
+				source: None,
+				bytes: &self.buffer[..self.max_datagram_size],
+				truncated: true,
+			})),
+			Err(error) => Err(error),
+		}
+	}
+}
+
+/// The largest datagram payload which fits within the MTU of any network
+/// interface other than loopback, as discovered from the operating system.
+///
+/// Returns `None` where interfaces cannot be enumerated, which is anywhere but Linux.
+pub fn largest_link_payload() -> Option<usize> {
+	fs::read_dir("/sys/class/net")
+		.ok()?
+		.flatten()
+		.filter(|interface| interface.file_name() != "lo")
+		.filter_map(|interface| fs::read_to_string(interface.path().join("mtu")).ok())
+		.filter_map(|mtu| mtu.trim().parse::<usize>().ok())
+		.map(|mtu| mtu.saturating_sub(DATAGRAM_HEADER_SIZE))
+		.max()
+}
+
+/// Receives frames over a byte stream, each preceded by its length as a
+/// big-endian `u32`, so that no frame is lost while the stream is up.
+#[derive(Debug)]
+pub struct StreamTransport<S> {
+	stream: S,
+	peer: Option<SocketAddr>,
+	buffer: Vec<u8>,
+}
+
+/// Receives frames over a TCP connection from the flight computer.
+pub type TcpTransport = StreamTransport<TcpStream>;
+
+impl TcpTransport {
+	/// Wraps a TCP stream connected to a peer.
+	pub fn new(stream: TcpStream, peer: SocketAddr) -> Self {
+		StreamTransport {
+			stream,
+			peer: Some(peer),
+			buffer: Vec::new(),
+		}
+	}
+}
+
+impl<S> StreamTransport<S> {
+	/// Wraps a stream which has no network address, such as a serial port.
+	pub fn unaddressed(stream: S) -> Self {
+		StreamTransport {
+			stream,
+			peer: None,
+			buffer: Vec::new(),
+		}
+	}
+}
+
+impl<S: AsyncRead + Send + Unpin> TelemetryTransport for StreamTransport<S> {
+	async fn receive_frame(&mut self) -> io::Result<Option<Frame<'_>>> {
+		let mut prefix = [0; 4];
+
+		match self.stream.read_exact(&mut prefix).await {
+			Ok(_) => {},
+			Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+			Err(error) => return Err(error),
+		}
+
+		let frame_size = u32::from_be_bytes(prefix) as usize;
+
+		if frame_size > MAX_FRAME_SIZE {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("vehicle state frame of {frame_size} bytes exceeds the maximum of {MAX_FRAME_SIZE}"),
+			));
+		}
+
+		self.buffer.resize(frame_size, 0);
+		self.stream.read_exact(&mut self.buffer).await?;
+
+		Ok(Some(Frame {
+			source: self.peer,
+			bytes: &self.buffer,
+			truncated: false,
+		}))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sequence_numbers_round_trip() {
+		let frame = sequenced_frame(42, b"state");
+		assert_eq!(split_sequence_number(&frame), (Some(42), &b"state"[..]));
+		assert_eq!(split_sequence_number(b"state"), (None, &b"state"[..]));
+	}
+
+	#[test]
+	fn gaps_are_accounted_for() {
+		let mut tracker = GapTracker::default();
+
+		assert_eq!(tracker.record(5), Arrival::InOrder);
+		assert_eq!(tracker.record(6), Arrival::InOrder);
+		assert_eq!(tracker.record(9), Arrival::Gap { first_missing: 7, last_missing: 8 });
+		assert_eq!(tracker.record(8), Arrival::OutOfOrder);
+		assert_eq!(tracker.record(9), Arrival::OutOfOrder);
+		assert_eq!(tracker.record(0), Arrival::Restarted);
+		assert_eq!(tracker.record(1), Arrival::InOrder);
+	}
+
+	#[test]
+	fn restarts_are_noticed_when_the_first_frames_are_lost() {
+		let mut tracker = GapTracker::default();
+
+		assert_eq!(tracker.record(5000), Arrival::InOrder);
+		assert_eq!(tracker.record(5000 - REORDER_WINDOW), Arrival::OutOfOrder);
+		assert_eq!(tracker.record(3), Arrival::Restarted);
+		assert_eq!(tracker.record(4), Arrival::InOrder);
+	}
+
+	#[tokio::test]
+	async fn oversized_datagrams_are_noticed() {
+		let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		sender.connect(receiver.local_addr().unwrap()).await.unwrap();
+
+		let mut transport = UdpTransport::new(receiver, 64);
+
+		sender.send(&[1; 100]).await.unwrap();
+		sender.send(&[2; 64]).await.unwrap();
+
+		let frame = transport.receive_frame().await.unwrap().unwrap();
+		assert!(frame.truncated);
+		assert_eq!(frame.bytes, [1; 64]);
+
+		let frame = transport.receive_frame().await.unwrap().unwrap();
+		assert!(!frame.truncated);
+		assert_eq!(frame.bytes, [2; 64]);
+		assert_eq!(frame.source, Some(sender.local_addr().unwrap()));
+	}
+}
@@ -0,0 +1,236 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::interlock;
+
+/// The redlines of a channel, beyond which a reading is out of limits.
+///
+/// Limits which are `None` are not checked, so a channel which may only run
+/// high need only set its high limits.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Threshold {
+	/// The unit the limits are in, such as `psi`, which readings must match.
+	pub unit: Option<String>,
+
+	/// The reading below which the channel is critically low.
+	pub low_critical: Option<f64>,
+
+	/// The reading below which the channel is low.
+	pub low_warning: Option<f64>,
+
+	/// The reading above which the channel is high.
+	pub high_warning: Option<f64>,
+
+	/// The reading above which the channel is critically high.
+	pub high_critical: Option<f64>,
+
+	/// Why the limits are what they are, such as the rating of a tank.
+	pub description: Option<String>,
+}
+
+impl Threshold {
+	/// The limits which are set, named and in ascending order as they must be.
+	fn limits(&self) -> Vec<(&'static str, f64)> {
+		[
+			("low_critical", self.low_critical),
+			("low_warning", self.low_warning),
+			("high_warning", self.high_warning),
+			("high_critical", self.high_critical),
+		]
+			.into_iter()
+			.filter_map(|(name, limit)| Some((name, limit?)))
+			.collect()
+	}
+}
+
+/// The redlines of every channel which has them, keyed by channel name, as
+/// written in a redline file:
+///
+/// ```toml
+/// [KBPT]
+/// unit = "psi"
+/// high_warning = 800
+/// high_critical = 900
+/// description = "burst disk is rated for 1000 psi"
+/// ```
+pub type Thresholds = BTreeMap<String, Threshold>;
+
+/// Parses a redline file.
+pub fn parse(text: &str) -> anyhow::Result<Thresholds> {
+	Ok(toml::from_str(text)?)
+}
+
+/// Writes redlines in the format of a redline file.
+pub fn to_toml(thresholds: &Thresholds) -> anyhow::Result<String> {
+	Ok(toml::to_string(thresholds)?)
+}
+
+/// Checks redlines for problems, returning a description of each.
+pub fn validate(thresholds: &Thresholds) -> Vec<String> {
+	let mut problems = Vec::new();
+
+	for (channel, threshold) in thresholds {
+		if channel.is_empty() || channel.contains(char::is_whitespace) {
+			problems.push(format!("'{channel}' is not a valid channel name"));
+		}
+
+		if let Some(unit) = threshold.unit.as_deref().filter(|unit| interlock::parse_unit(unit).is_none()) {
+			problems.push(format!("{channel} has unrecognized unit '{unit}'"));
+		}
+
+		let limits = threshold.limits();
+
+		if limits.is_empty() {
+			problems.push(format!("{channel} has no limits"));
+		}
+
+		for (name, limit) in &limits {
+			if !limit.is_finite() {
+				problems.push(format!("{channel} {name} must be a finite number"));
+			}
+		}
+
+		for pair in limits.windows(2) {
+			let [(lower_name, lower), (upper_name, upper)] = pair else { continue };
+
+			if lower > upper {
+				problems.push(format!("{channel} {lower_name} of {lower} is above its {upper_name} of {upper}"));
+			}
+		}
+	}
+
+	problems
+}
+
+/// Describes how redlines would change if replaced, one line per channel changed.
+pub fn diff(old: &Thresholds, new: &Thresholds) -> Vec<String> {
+	let mut changes = Vec::new();
+
+	for channel in old.keys() {
+		if !new.contains_key(channel) {
+			changes.push(format!("removed {channel}"));
+		}
+	}
+
+	for (channel, threshold) in new {
+		let Some(previous) = old.get(channel) else {
+			changes.push(format!("added {channel}"));
+			continue;
+		};
+
+		if previous == threshold {
+			continue;
+		}
+
+		let describe = |value: &Option<String>| value.as_deref().unwrap_or("none").to_owned();
+		let number = |value: Option<f64>| value.map_or("none".to_owned(), |value| value.to_string());
+
+		let fields = [
+			("unit", describe(&previous.unit), describe(&threshold.unit)),
+			("low_critical", number(previous.low_critical), number(threshold.low_critical)),
+			("low_warning", number(previous.low_warning), number(threshold.low_warning)),
+			("high_warning", number(previous.high_warning), number(threshold.high_warning)),
+			("high_critical", number(previous.high_critical), number(threshold.high_critical)),
+			("description", describe(&previous.description), describe(&threshold.description)),
+		];
+
+		let fields = fields
+			.into_iter()
+			.filter(|(_, before, after)| before != after)
+			.map(|(name, before, after)| format!("{name} {before} → {after}"))
+			.collect::<Vec<_>>();
+
+		changes.push(format!("changed {channel}: {}", fields.join(", ")));
+	}
+
+	changes
+}
+
+/// Reads every channel's redlines from the `Thresholds` table.
+pub fn load(connection: &Connection) -> rusqlite::Result<Thresholds> {
+	connection
+		.prepare("
+			SELECT channel, unit, low_critical, low_warning, high_warning, high_critical, description
+			FROM Thresholds
+		")?
+		.query_map([], |row| {
+			Ok((row.get(0)?, Threshold {
+				unit: row.get(1)?,
+				low_critical: row.get(2)?,
+				low_warning: row.get(3)?,
+				high_warning: row.get(4)?,
+				high_critical: row.get(5)?,
+				description: row.get(6)?,
+			}))
+		})?
+		.collect()
+}
+
+/// Replaces every channel's redlines all at once, so that channels left out are removed.
+pub fn replace(connection: &mut Connection, thresholds: &Thresholds) -> rusqlite::Result<()> {
+	let transaction = connection.transaction()?;
+	transaction.execute("DELETE FROM Thresholds", [])?;
+
+	for (channel, threshold) in thresholds {
+		transaction.execute("
+			INSERT INTO Thresholds (channel, unit, low_critical, low_warning, high_warning, high_critical, description)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+		", params![
+			channel,
+			threshold.unit,
+			threshold.low_critical,
+			threshold.low_warning,
+			threshold.high_warning,
+			threshold.high_critical,
+			threshold.description,
+		])?;
+	}
+
+	transaction.commit()
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn redline_files_round_trip_through_the_database() {
+		let file = parse(r#"
+			[KBPT]
+			unit = "psi"
+			high_warning = 800
+			high_critical = 900
+
+			[WTPT]
+			low_critical = 10
+			high_critical = 5
+		"#).unwrap();
+
+		assert_eq!(validate(&file), ["WTPT low_critical of 10 is above its high_critical of 5"]);
+		assert!(parse("[KBPT]\nhigh_critcal = 900").is_err());
+
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let mut connection = database.connection.blocking_lock();
+		replace(&mut connection, &file).unwrap();
+
+		let stored = load(&connection).unwrap();
+		assert_eq!(stored, file);
+		assert_eq!(parse(&to_toml(&stored).unwrap()).unwrap(), file);
+
+		let mut edited = file.clone();
+		edited.remove("WTPT");
+		edited.get_mut("KBPT").unwrap().high_critical = Some(950.0);
+		edited.insert("FUPT".to_owned(), Threshold { high_critical: Some(600.0), ..Threshold::default() });
+
+		assert_eq!(diff(&stored, &edited), [
+			"removed WTPT",
+			"added FUPT",
+			"changed KBPT: high_critical 900 → 950",
+		]);
+	}
+}
@@ -0,0 +1,117 @@
+use axum::{
+	extract::{ConnectInfo, Request, State},
+	http::HeaderValue,
+	middleware::Next,
+	response::Response,
+};
+use jeflog::warn;
+use rand::RngCore;
+use rusqlite::params;
+use std::{net::SocketAddr, time::Instant};
+
+use super::Shared;
+
+/// The header carrying the ID of a request, which is returned on every response.
+///
+/// Clients may supply their own ID in the header so that it matches their own
+/// logs, and one is generated for any request which does not.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The longest request ID accepted from a client.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+tokio::task_local! {
+	static REQUEST_ID: String;
+}
+
+/// The ID of the request being handled, if called while handling one.
+///
+/// Work spawned onto other tasks by a route function does not keep the ID.
+pub fn current() -> Option<String> {
+	REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Generates a new random request ID of sixteen hexadecimal digits.
+pub fn generate() -> String {
+	format!("{:016x}", rand::thread_rng().next_u64())
+}
+
+/// Whether an ID supplied by a client is short and plain enough to be logged as is.
+fn is_valid(request_id: &str) -> bool {
+	!request_id.is_empty()
+		&& request_id.len() <= MAX_REQUEST_ID_LENGTH
+		&& request_id.chars().all(|character| character.is_ascii_alphanumeric() || "-_.".contains(character))
+}
+
+/// Middleware which assigns an ID to every request, returns it in the
+/// `X-Request-Id` header, and records the request in the `RequestLogs` table.
+///
+/// The ID is available through [`current`] while the request is handled, so
+/// audit entries and messages sent to the flight computer can refer to it.
+/// Requests which fail are also logged to the console, so that a failure
+/// reported by a GUI can be found by its ID.
+pub async fn assign_request_id(State(shared): State<Shared>, request: Request, next: Next) -> Response {
+	let request_id = request
+		.headers()
+		.get(REQUEST_ID_HEADER)
+		.and_then(|value| value.to_str().ok())
+		.filter(|request_id| is_valid(request_id))
+		.map_or_else(generate, str::to_owned);
+
+	let method = request.method().to_string();
+	let path = request.uri().path().to_owned();
+
+	let origin = request
+		.extensions()
+		.get::<ConnectInfo<SocketAddr>>()
+		.map_or("unknown".to_owned(), |ConnectInfo(peer)| peer.ip().to_string());
+
+	let started_at = Instant::now();
+	let mut response = REQUEST_ID.scope(request_id.clone(), next.run(request)).await;
+	let duration_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+
+	if let Ok(value) = HeaderValue::from_str(&request_id) {
+		response.headers_mut().insert(REQUEST_ID_HEADER, value);
+	}
+
+	let status = response.status().as_u16();
+
+	if response.status().is_client_error() || response.status().is_server_error() {
+		warn!("request_id={request_id} method={method} path={path} origin={origin} status={status} duration_ms={duration_ms:.1}");
+	}
+
+	// the response need not wait on the database, which may be busy logging vehicle state
+	tokio::spawn(async move {
+		let result = shared.database.connection
+			.lock()
+			.await
+			.execute(
+				"INSERT INTO RequestLogs (endpoint, origin, status_code, request_id, method, duration_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+				params![path, origin, status, request_id, method, duration_ms],
+			);
+
+		if let Err(error) = result {
+			warn!("Failed to record request {request_id}: {error}");
+		}
+	});
+
+	response
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn request_ids_are_scoped_to_their_request() {
+		assert!(is_valid(&generate()));
+		assert!(!is_valid("has spaces"));
+		assert!(!is_valid(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+
+		assert_eq!(current(), None);
+
+		let inner = REQUEST_ID.scope("gui-42".to_owned(), async { current() }).await;
+		assert_eq!(inner.as_deref(), Some("gui-42"));
+		assert_eq!(current(), None);
+	}
+}
@@ -0,0 +1,142 @@
+use common::comm::{Measurement, Unit};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Pounds per square inch in one bar.
+const PSI_PER_BAR: f64 = 14.503_773_773;
+
+/// Newtons in one pound of force.
+const NEWTONS_PER_POUND: f64 = 4.448_221_615;
+
+/// Every unit which readings may be reported in.
+pub const UNITS: [Unit; 5] = [Unit::Amps, Unit::Psi, Unit::Volts, Unit::Kelvin, Unit::Pounds];
+
+/// A system of units which measurements are presented in.
+///
+/// Measurements are always stored in the units they are reported in, so
+/// conversion only ever happens on the way out, such as when exporting or
+/// displaying them. Quantities which are the same in every system, such as
+/// volts and amps, are never converted.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+	/// The units measurements are reported in: psi, Kelvin, and pounds.
+	#[default]
+	Native,
+
+	/// Bar, degrees Celsius, and newtons, as propulsion engineers often think in.
+	Metric,
+
+	/// Kilopascals, Kelvin, and newtons.
+	Si,
+}
+
+impl UnitSystem {
+	/// Every unit system.
+	pub const ALL: [Self; 3] = [Self::Native, Self::Metric, Self::Si];
+
+	/// The name of the unit system, as it is written in requests and configuration.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Native => "native",
+			Self::Metric => "metric",
+			Self::Si => "si",
+		}
+	}
+
+	/// Converts a reported value into this system, returning it along with the
+	/// symbol of the unit it is now in.
+	pub fn convert(self, value: f64, unit: Unit) -> (f64, &'static str) {
+		match (self, unit) {
+			(Self::Metric, Unit::Psi) => (value / PSI_PER_BAR, "bar"),
+			(Self::Si, Unit::Psi) => (value / PSI_PER_BAR * 100.0, "kPa"),
+			(Self::Metric, Unit::Kelvin) => (value - 273.15, "°C"),
+			(Self::Metric | Self::Si, Unit::Pounds) => (value * NEWTONS_PER_POUND, "N"),
+			(_, unit) => (value, symbol(unit)),
+		}
+	}
+
+	/// Converts a value in this system back into the unit it was reported in,
+	/// undoing [`convert`](Self::convert).
+	///
+	/// Every conversion is linear, so it is undone from where it takes zero and one.
+	pub fn revert(self, value: f64, unit: Unit) -> f64 {
+		let offset = self.convert(0.0, unit).0;
+		(value - offset) / (self.convert(1.0, unit).0 - offset)
+	}
+
+	/// Converts a measurement into this system, formatted as its value and unit symbol.
+	pub fn format(self, measurement: &Measurement) -> String {
+		let (value, symbol) = self.convert(measurement.value, measurement.unit);
+		format!("{value} {symbol}")
+	}
+}
+
+impl fmt::Display for UnitSystem {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl FromStr for UnitSystem {
+	type Err = String;
+
+	fn from_str(system: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|candidate| candidate.as_str() == system)
+			.ok_or_else(|| format!("unrecognized unit system '{system}', expected native, metric, or si"))
+	}
+}
+
+/// Reads a value written in the symbol of a unit of any system, such as `bar`
+/// or `°C`, as the measurement it would have been reported as.
+pub fn parse_measurement(value: f64, symbol: &str) -> Option<Measurement> {
+	UnitSystem::ALL
+		.into_iter()
+		.flat_map(|system| UNITS.map(|unit| (system, unit)))
+		.find(|&(system, unit)| system.convert(0.0, unit).1.eq_ignore_ascii_case(symbol))
+		.map(|(system, unit)| Measurement { value: system.revert(value, unit), unit })
+}
+
+/// The symbol of a unit as it is reported.
+pub fn symbol(unit: Unit) -> &'static str {
+	match unit {
+		Unit::Amps => "A",
+		Unit::Psi => "psi",
+		Unit::Volts => "V",
+		Unit::Kelvin => "K",
+		Unit::Pounds => "lbf",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_quantities_which_differ_are_converted() {
+		let close = |(value, symbol): (f64, &str), expected: (f64, &str)| {
+			assert!((value - expected.0).abs() < 1e-6, "{value} {symbol} is not {} {}", expected.0, expected.1);
+			assert_eq!(symbol, expected.1);
+		};
+
+		close(UnitSystem::Metric.convert(14.503_773_773, Unit::Psi), (1.0, "bar"));
+		close(UnitSystem::Si.convert(14.503_773_773, Unit::Psi), (100.0, "kPa"));
+		close(UnitSystem::Metric.convert(273.15, Unit::Kelvin), (0.0, "°C"));
+		close(UnitSystem::Si.convert(273.15, Unit::Kelvin), (273.15, "K"));
+		close(UnitSystem::Si.convert(1.0, Unit::Pounds), (4.448_221_615, "N"));
+		close(UnitSystem::Metric.convert(24.0, Unit::Volts), (24.0, "V"));
+		close(UnitSystem::Native.convert(500.0, Unit::Psi), (500.0, "psi"));
+
+		assert!((UnitSystem::Metric.revert(0.0, Unit::Kelvin) - 273.15).abs() < 1e-9);
+		assert!((UnitSystem::Si.revert(100.0, Unit::Psi) - 14.503_773_773).abs() < 1e-6);
+
+		let parsed = parse_measurement(1.0, "bar").unwrap();
+		assert!((parsed.value - 14.503_773_773).abs() < 1e-6 && parsed.unit == Unit::Psi);
+		assert!(parse_measurement(1.0, "furlongs").is_none());
+
+		assert_eq!("metric".parse::<UnitSystem>(), Ok(UnitSystem::Metric));
+		assert!("imperial".parse::<UnitSystem>().is_err());
+	}
+}
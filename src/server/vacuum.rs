@@ -0,0 +1,219 @@
+use jeflog::{pass, warn};
+use rusqlite::Connection;
+use std::{future::Future, time::{Duration, Instant}};
+
+use super::Shared;
+
+/// How often telemetry is checked for whether it has gone quiet.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The value of `PRAGMA auto_vacuum` for a database which is vacuumed incrementally.
+const INCREMENTAL: i64 = 2;
+
+/// What a maintenance run did to the database.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaintenanceRun {
+	/// The number of pages moved from the write-ahead log into the database.
+	pub checkpointed_pages: i64,
+
+	/// The number of free pages released back to the file system.
+	pub freed_pages: i64,
+}
+
+/// Prepares a database to be vacuumed incrementally. This only takes effect on
+/// a database without tables, so existing databases must be [`convert`]ed.
+pub fn prepare(connection: &Connection) -> rusqlite::Result<()> {
+	connection.pragma_update(None, "auto_vacuum", "INCREMENTAL")
+}
+
+/// Fully vacuums a database which cannot yet be vacuumed incrementally, so that
+/// it can be from then on, returning whether it had to.
+///
+/// A full vacuum rewrites the whole database, holding it for minutes if it is
+/// large, so this is only done by `servo db convert` while the server is stopped.
+pub fn convert(connection: &Connection) -> rusqlite::Result<bool> {
+	if is_incremental(connection)? {
+		return Ok(false);
+	}
+
+	prepare(connection)?;
+	connection.execute_batch("VACUUM")?;
+
+	Ok(true)
+}
+
+/// Moves the write-ahead log into the database and truncates it, returning the
+/// number of pages moved, which is zero if the database is not in WAL mode.
+fn checkpoint(connection: &Connection) -> rusqlite::Result<i64> {
+	connection
+		.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get::<_, i64>(2))
+		.map(|pages| pages.max(0))
+}
+
+/// Releases up to a number of free pages back to the file system, returning how many were.
+fn vacuum_step(connection: &Connection, pages: u32) -> rusqlite::Result<i64> {
+	let free_pages = || connection.pragma_query_value(None, "freelist_count", |row| row.get::<_, i64>(0));
+
+	let before = free_pages()?;
+
+	// the pragma releases a page each time it is stepped, so it is stepped until done
+	let mut statement = connection.prepare(&format!("PRAGMA incremental_vacuum({pages})"))?;
+	let mut rows = statement.query([])?;
+	while rows.next()?.is_some() {}
+
+	Ok(before - free_pages()?)
+}
+
+/// How long telemetry has been quiet, judged by the number of frames received each time it is polled.
+#[derive(Clone, Copy, Debug)]
+struct Activity {
+	frames_received: u64,
+	polled_at: Instant,
+	quiet_since: Instant,
+}
+
+impl Activity {
+	fn new(frames_received: u64, now: Instant) -> Self {
+		Activity { frames_received, polled_at: now, quiet_since: now }
+	}
+
+	/// Notes the frames received by now and whether a sequence is running,
+	/// returning how long telemetry has been quiet with no sequence running.
+	fn observe(&mut self, frames_received: u64, sequence_running: bool, max_rate_hz: f64, now: Instant) -> Duration {
+		let elapsed = now.saturating_duration_since(self.polled_at).as_secs_f64();
+		let frames = frames_received.saturating_sub(self.frames_received);
+
+		if sequence_running || frames as f64 > max_rate_hz * elapsed {
+			self.quiet_since = now;
+		}
+
+		self.frames_received = frames_received;
+		self.polled_at = now;
+
+		now.saturating_duration_since(self.quiet_since)
+	}
+}
+
+/// Whether a database can be vacuumed incrementally.
+fn is_incremental(connection: &Connection) -> rusqlite::Result<bool> {
+	Ok(connection.pragma_query_value(None, "auto_vacuum", |row| row.get::<_, i64>(0))? == INCREMENTAL)
+}
+
+/// Checkpoints and incrementally vacuums the database as configured, once
+/// telemetry has been quiet and no sequence has run for long enough, so that
+/// the database stays compact without stalling the writes of a test.
+///
+/// The vacuum gives way as soon as a sequence starts or telemetry picks up, and
+/// the configuration is read each time, so maintenance may be changed by reloading it.
+/// Only a database which was created or [`convert`]ed to be vacuumed incrementally
+/// has free pages released; any other is only checkpointed.
+pub fn maintain(shared: &Shared) -> impl Future<Output = anyhow::Result<()>> {
+	let shared = shared.clone();
+
+	async move {
+		let frames_received = || async { shared.metrics.telemetry.lock().await.frames_received };
+		let sequence_running = || async { !shared.lockout.active().await.is_empty() };
+
+		let mut activity = Activity::new(frames_received().await, Instant::now());
+		let mut last_run = None::<Instant>;
+		let mut warned = false;
+
+		loop {
+			tokio::time::sleep(POLL_INTERVAL).await;
+
+			let config = shared.config.current().vacuum.clone();
+			let quiet = activity.observe(frames_received().await, sequence_running().await, config.max_idle_rate_hz, Instant::now());
+
+			let due = last_run.is_none_or(|last_run| last_run.elapsed().as_secs_f64() >= config.interval_hours * 3600.0);
+
+			if !config.enabled || !due || quiet.as_secs_f64() < config.idle_seconds {
+				continue;
+			}
+
+			last_run = Some(Instant::now());
+
+			let mut run = MaintenanceRun::default();
+
+			let incremental = {
+				let connection = shared.database.connection.lock().await;
+				run.checkpointed_pages = checkpoint(&connection)?;
+				is_incremental(&connection)?
+			};
+
+			if !incremental && !warned {
+				warn!("The database cannot release free pages until it is converted with `servo db convert` while the server is stopped.");
+				warned = true;
+			}
+
+			if incremental {
+				loop {
+					let freed = vacuum_step(&*shared.database.connection.lock().await, config.pages_per_step.max(1))?;
+					run.freed_pages += freed;
+
+					if freed == 0 {
+						break;
+					}
+
+					tokio::task::yield_now().await;
+
+					let quiet = activity.observe(frames_received().await, sequence_running().await, config.max_idle_rate_hz, Instant::now());
+
+					if quiet.is_zero() {
+						break;
+					}
+				}
+			}
+
+			// the freed pages are only truncated from the file once the log is checkpointed again
+			if run.freed_pages > 0 {
+				run.checkpointed_pages += checkpoint(&*shared.database.connection.lock().await)?;
+			}
+
+			if run.checkpointed_pages > 0 || run.freed_pages > 0 {
+				pass!("Checkpointed {} pages of the database and freed {} pages.", run.checkpointed_pages, run.freed_pages);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::server::Database;
+	use super::*;
+
+	#[test]
+	fn free_pages_are_released_in_steps() {
+		let database = Database::volatile().unwrap();
+		database.migrate().unwrap();
+
+		let connection = database.connection.blocking_lock();
+		assert!(!convert(&connection).unwrap());
+
+		for _ in 0..200 {
+			connection.execute("INSERT INTO VehicleSnapshots (vehicle_state) VALUES (zeroblob(4096))", []).unwrap();
+		}
+
+		connection.execute("DELETE FROM VehicleSnapshots", []).unwrap();
+
+		assert_eq!(vacuum_step(&connection, 16).unwrap(), 16);
+
+		while vacuum_step(&connection, 16).unwrap() > 0 {}
+		assert_eq!(connection.pragma_query_value(None, "freelist_count", |row| row.get::<_, i64>(0)).unwrap(), 0);
+
+		// an in-memory database has no write-ahead log to checkpoint
+		assert_eq!(checkpoint(&connection).unwrap(), 0);
+	}
+
+	#[test]
+	fn telemetry_is_quiet_below_the_idle_rate() {
+		let start = Instant::now();
+		let mut activity = Activity::new(0, start);
+		let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+		assert_eq!(activity.observe(5, false, 1.0, at(10)), Duration::from_secs(10));
+		assert_eq!(activity.observe(500, false, 1.0, at(20)), Duration::ZERO);
+		assert_eq!(activity.observe(505, false, 1.0, at(30)), Duration::from_secs(10));
+		assert_eq!(activity.observe(505, true, 1.0, at(40)), Duration::ZERO);
+		assert_eq!(activity.observe(505, false, 1.0, at(100)), Duration::from_secs(60));
+	}
+}
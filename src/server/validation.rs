@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+
+use super::{error::{ErrorCode, ServerError}, selector::Selection};
+
+#[cfg(feature = "server")]
+use axum::{
+	async_trait,
+	extract::{rejection::{JsonRejection, QueryRejection}, FromRequest, FromRequestParts, Query, Request},
+	http::{request::Parts, StatusCode},
+	Json,
+};
+
+#[cfg(feature = "server")]
+use serde::de::DeserializeOwned;
+
+/// A request whose fields are checked beyond what deserializing them checks,
+/// such as names which must not be empty or ranges which must be in order.
+pub trait Validate {
+	/// Records every problem with the request, so that all are reported at once.
+	fn validate(&self, validator: &mut Validator);
+}
+
+#[cfg(feature = "server")]
+impl<T: Validate> Validate for Json<T> {
+	fn validate(&self, validator: &mut Validator) {
+		self.0.validate(validator);
+	}
+}
+
+#[cfg(feature = "server")]
+impl<T: Validate> Validate for Query<T> {
+	fn validate(&self, validator: &mut Validator) {
+		self.0.validate(validator);
+	}
+}
+
+/// A problem with a single field of a request.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FieldError {
+	/// The path of the field, such as `to` or `mappings[2].text_id`.
+	pub field: String,
+
+	/// What is wrong with the field.
+	pub message: String,
+}
+
+/// Every problem found with a request, for endpoints which check a request
+/// without acting on it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ValidationReport {
+	/// Problems which would cause the request to be rejected.
+	pub errors: Vec<FieldError>,
+
+	/// Suspicious fields which would be accepted, but likely not do what was meant.
+	pub warnings: Vec<FieldError>,
+}
+
+/// Collects the problems with the fields of a request.
+#[derive(Debug, Default)]
+pub struct Validator {
+	prefix: String,
+	errors: Vec<FieldError>,
+	warnings: Vec<FieldError>,
+}
+
+impl Validator {
+	/// Records a problem with a field.
+	pub fn error(&mut self, field: &str, message: impl ToString) {
+		let error = self.field_error(field, message);
+		self.errors.push(error);
+	}
+
+	/// Records a field which is accepted but suspicious, which does not reject the request.
+	pub fn warn(&mut self, field: &str, message: impl ToString) {
+		let warning = self.field_error(field, message);
+		self.warnings.push(warning);
+	}
+
+	fn field_error(&self, field: &str, message: impl ToString) -> FieldError {
+		FieldError {
+			field: format!("{}{field}", self.prefix),
+			message: message.to_string(),
+		}
+	}
+
+	/// Records a problem with a field unless the condition holds.
+	pub fn check(&mut self, condition: bool, field: &str, message: impl ToString) {
+		if !condition {
+			self.error(field, message);
+		}
+	}
+
+	/// Checks that a name or other text field is not empty or only whitespace.
+	pub fn non_empty(&mut self, field: &str, value: &str) {
+		self.check(!value.trim().is_empty(), field, "must not be empty");
+	}
+
+	/// Checks that a value is one of those which are known, such as a format.
+	pub fn one_of(&mut self, field: &str, value: &str, known: &[&str]) {
+		self.check(known.contains(&value), field, format!("'{value}' is not one of {}", known.join(", ")));
+	}
+
+	/// Checks that a number is finite and positive, such as a rate.
+	pub fn positive(&mut self, field: &str, value: f64) {
+		self.check(value > 0.0 && value.is_finite(), field, "must be a positive number");
+	}
+
+	/// Checks that each of a list of channel selectors is a name, glob, or regular expression.
+	pub fn selectors(&mut self, field: &str, selectors: &[String]) {
+		for (i, selector) in selectors.iter().enumerate() {
+			let field = format!("{field}[{i}]");
+
+			if selector.trim().is_empty() {
+				self.error(&field, "must not be empty");
+			} else if let Err(error) = Selection::parse([selector]) {
+				self.error(&field, error);
+			}
+		}
+	}
+
+	/// Checks that the start of a time range is not after its end, where either may be open.
+	pub fn ordered(&mut self, from: Option<f64>, to: Option<f64>) {
+		for (field, time) in [("from", from), ("to", to)] {
+			if let Some(time) = time {
+				self.check(time.is_finite(), field, "must be a finite Unix timestamp");
+			}
+		}
+
+		if let (Some(from), Some(to)) = (from, to) {
+			self.check(from <= to, "to", format!("must not be before from ({to} < {from})"));
+		}
+	}
+
+	/// Checks a nested value, such as each element of a list, prefixing its fields with the path to it.
+	pub fn nested(&mut self, path: &str, value: &impl Validate) {
+		let nested = format!("{}{path}.", self.prefix);
+		let prefix = std::mem::replace(&mut self.prefix, nested);
+		value.validate(self);
+		self.prefix = prefix;
+	}
+
+	/// Returns every problem and warning recorded, rather than rejecting the request.
+	pub fn report(self) -> ValidationReport {
+		ValidationReport {
+			errors: self.errors,
+			warnings: self.warnings,
+		}
+	}
+
+	/// Returns every problem recorded as a single 422 error, if there were any.
+	/// Warnings are left out, since they do not reject the request.
+	pub fn finish(self) -> super::Result<()> {
+		if self.errors.is_empty() {
+			return Ok(());
+		}
+
+		let message = self.errors
+			.iter()
+			.map(|error| format!("{} {}", error.field, error.message))
+			.collect::<Vec<_>>()
+			.join("; ");
+
+		Err(
+			ServerError::new(ErrorCode::ValidationFailed, format!("invalid request: {message}"))
+				.with_context("fields", self.errors)
+		)
+	}
+}
+
+/// Checks a request, returning every problem with it as a single 422 error.
+pub fn validate(value: &impl Validate) -> super::Result<()> {
+	let mut validator = Validator::default();
+	value.validate(&mut validator);
+	validator.finish()
+}
+
+/// Checks a request, returning every problem and warning rather than rejecting it.
+pub fn report(value: &impl Validate) -> ValidationReport {
+	let mut validator = Validator::default();
+	value.validate(&mut validator);
+	validator.report()
+}
+
+/// An extractor which validates a JSON body or query string after deserializing
+/// it, as in `Valid(Json(request)): Valid<Json<ExportRequest>>`.
+///
+/// A body or query which cannot be deserialized is rejected the same way, with
+/// a coded error rather than the plain text axum responds with.
+#[cfg(feature = "server")]
+#[derive(Clone, Copy, Debug)]
+pub struct Valid<E>(pub E);
+
+#[cfg(feature = "server")]
+impl<E: Validate> Valid<E> {
+	/// Validates a request which did not come through the REST API, such as one from gRPC.
+	pub fn new(inner: E) -> super::Result<Self> {
+		validate(&inner)?;
+		Ok(Valid(inner))
+	}
+}
+
+#[cfg(feature = "server")]
+#[async_trait]
+impl<S, T> FromRequest<S> for Valid<Json<T>>
+where
+	S: Send + Sync,
+	T: DeserializeOwned + Validate,
+{
+	type Rejection = ServerError;
+
+	async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+		let json = Json::<T>::from_request(request, state)
+			.await
+			.map_err(|rejection: JsonRejection| {
+				let code = match rejection.status() {
+					StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::ValidationFailed,
+					StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+					_ => ErrorCode::BadRequest,
+				};
+
+				ServerError::new(code, rejection.body_text())
+			})?;
+
+		Valid::new(json)
+	}
+}
+
+#[cfg(feature = "server")]
+#[async_trait]
+impl<S, T> FromRequestParts<S> for Valid<Query<T>>
+where
+	S: Send + Sync,
+	T: DeserializeOwned + Validate,
+{
+	type Rejection = ServerError;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let query = Query::<T>::from_request_parts(parts, state)
+			.await
+			.map_err(|rejection: QueryRejection| ServerError::new(ErrorCode::ValidationFailed, rejection.body_text()))?;
+
+		Valid::new(query)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Mapping {
+		text_id: String,
+	}
+
+	impl Validate for Mapping {
+		fn validate(&self, validator: &mut Validator) {
+			validator.non_empty("text_id", &self.text_id);
+		}
+	}
+
+	struct Request {
+		from: f64,
+		to: f64,
+		mappings: Vec<Mapping>,
+	}
+
+	impl Validate for Request {
+		fn validate(&self, validator: &mut Validator) {
+			validator.ordered(Some(self.from), Some(self.to));
+
+			for (i, mapping) in self.mappings.iter().enumerate() {
+				validator.nested(&format!("mappings[{i}]"), mapping);
+			}
+		}
+	}
+
+	#[test]
+	fn every_problem_is_reported_by_field() {
+		let request = Request {
+			from: 10.0,
+			to: 5.0,
+			mappings: vec![Mapping { text_id: "KBPT".to_owned() }, Mapping { text_id: " ".to_owned() }],
+		};
+
+		let error = validate(&request).unwrap_err();
+		assert_eq!(error.code(), ErrorCode::ValidationFailed);
+
+		let body = error.body();
+		let fields = body.context["fields"].as_array().unwrap();
+
+		assert_eq!(fields.len(), 2);
+		assert_eq!(fields[0]["field"], "to");
+		assert_eq!(fields[1]["field"], "mappings[1].text_id");
+
+		assert!(validate(&Request { from: 0.0, to: 0.0, mappings: Vec::new() }).is_ok());
+	}
+}
@@ -0,0 +1,147 @@
+use common::comm::{Measurement, Unit, VehicleState};
+use std::collections::{BTreeMap, HashMap};
+
+/// The kind of multi-component channel, such as an IMU's acceleration or an AHRS attitude.
+///
+/// `VehicleState` only carries scalar measurements, so a multi-component channel
+/// is stored as one reading per component, named `<channel>.<component>`. An
+/// acceleration `IMU_ACCEL` is stored as `IMU_ACCEL.x`, `IMU_ACCEL.y`, and
+/// `IMU_ACCEL.z`, and an attitude quaternion `AHRS_ATT` as `AHRS_ATT.w` through
+/// `AHRS_ATT.z`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VectorKind {
+	/// A three-component vector, such as acceleration or angular rate.
+	Vector3,
+
+	/// A unit quaternion, such as attitude, with its scalar part first.
+	Quaternion,
+}
+
+impl VectorKind {
+	/// The names of the components of this kind, in storage order.
+	pub fn components(self) -> &'static [&'static str] {
+		match self {
+			Self::Vector3 => &["x", "y", "z"],
+			Self::Quaternion => &["w", "x", "y", "z"],
+		}
+	}
+
+	/// The kind of channel with the given number of components, if any.
+	pub fn with_length(length: usize) -> Option<Self> {
+		match length {
+			3 => Some(Self::Vector3),
+			4 => Some(Self::Quaternion),
+			_ => None,
+		}
+	}
+}
+
+/// A multi-component channel found among the readings of a vehicle state.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VectorChannel {
+	/// The name of the channel, without a component suffix.
+	pub name: String,
+
+	/// Which components the channel has.
+	pub kind: VectorKind,
+}
+
+impl VectorChannel {
+	/// The names of the component readings of this channel, in storage order.
+	pub fn component_names(&self) -> Vec<String> {
+		self.kind
+			.components()
+			.iter()
+			.map(|component| format!("{}.{component}", self.name))
+			.collect()
+	}
+
+	/// Reads the components of this channel from a vehicle state, if every one is present.
+	pub fn read(&self, state: &VehicleState) -> Option<(Vec<f64>, Unit)> {
+		let mut unit = None;
+
+		let values = self.component_names()
+			.iter()
+			.map(|name| {
+				let measurement = state.sensor_readings.get(name)?;
+				unit = Some(measurement.unit);
+				Some(measurement.value)
+			})
+			.collect::<Option<Vec<f64>>>()?;
+
+		Some((values, unit?))
+	}
+}
+
+/// Stores the components of a multi-component channel into a vehicle state.
+///
+/// Returns `None` without modifying the state if the number of values does not
+/// match any kind of channel.
+pub fn insert(state: &mut VehicleState, name: &str, values: &[f64], unit: Unit) -> Option<VectorKind> {
+	let kind = VectorKind::with_length(values.len())?;
+
+	for (component, value) in kind.components().iter().zip(values) {
+		state.sensor_readings.insert(format!("{name}.{component}"), Measurement { value: *value, unit });
+	}
+
+	Some(kind)
+}
+
+/// Groups reading names into the multi-component channels they make up.
+///
+/// A channel is only found if all of its components are present, and a
+/// quaternion takes precedence over a vector with the same name. Names which
+/// are not part of a channel are left alone.
+pub fn find_channels<'a>(names: impl IntoIterator<Item = &'a String>) -> Vec<VectorChannel> {
+	let mut components = HashMap::<&str, Vec<&str>>::new();
+
+	for name in names {
+		if let Some((channel, component)) = name.rsplit_once('.') {
+			components.entry(channel).or_default().push(component);
+		}
+	}
+
+	let mut channels = BTreeMap::new();
+
+	for (channel, present) in components {
+		let kind = [VectorKind::Quaternion, VectorKind::Vector3]
+			.into_iter()
+			.find(|kind| kind.components().iter().all(|component| present.contains(component)));
+
+		if let Some(kind) = kind {
+			channels.insert(channel.to_owned(), kind);
+		}
+	}
+
+	channels
+		.into_iter()
+		.map(|(name, kind)| VectorChannel { name, kind })
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn channels_are_grouped_from_components() {
+		let mut state = VehicleState::new();
+
+		insert(&mut state, "AHRS_ATT", &[1.0, 0.0, 0.0, 0.0], Unit::Volts);
+		insert(&mut state, "IMU_ACCEL", &[0.0, 0.0, 9.8], Unit::Volts);
+		state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 50.0, unit: Unit::Psi });
+		state.sensor_readings.insert("daq.KBPT".to_owned(), Measurement { value: 50.0, unit: Unit::Psi });
+		state.sensor_readings.insert("PARTIAL.x".to_owned(), Measurement { value: 1.0, unit: Unit::Volts });
+
+		assert!(insert(&mut state, "BAD", &[1.0, 2.0], Unit::Volts).is_none());
+
+		let channels = find_channels(state.sensor_readings.keys());
+
+		assert_eq!(channels, vec![
+			VectorChannel { name: "AHRS_ATT".to_owned(), kind: VectorKind::Quaternion },
+			VectorChannel { name: "IMU_ACCEL".to_owned(), kind: VectorKind::Vector3 },
+		]);
+
+		assert_eq!(channels[1].read(&state).map(|(values, _)| values), Some(vec![0.0, 0.0, 9.8]));
+	}
+}
@@ -0,0 +1,34 @@
+use clap::ArgMatches;
+use jeflog::{fail, pass};
+use std::{fs::File, path::PathBuf, time::Duration};
+
+use super::{client::{http_client, read_error, server_url}, report::target_query};
+
+/// Tool function which downloads the complete record of a session or time range
+/// as a gzipped tarball, for a post-test review package.
+pub fn archive(args: &ArgMatches) -> anyhow::Result<()> {
+	let target = args.get_one::<String>("target").unwrap();
+	let query = target_query(target)?;
+
+	let output = args
+		.get_one::<PathBuf>("output")
+		.cloned()
+		.unwrap_or_else(|| PathBuf::from(format!("archive-{}.tar.gz", target.replace("..", "-"))));
+
+	let mut response = http_client()?
+		.get(format!("{}/data/archive", server_url()))
+		.query(&query)
+		.timeout(Duration::from_secs(3600))
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	// archives of long tests are large, so they are written out as they arrive
+	let size = response.copy_to(&mut File::create(&output)?)?;
+	pass!("Wrote the archive of {target} to {} ({size} bytes).", output.display());
+
+	Ok(())
+}
@@ -0,0 +1,31 @@
+use clap::ArgMatches;
+use crate::server::api::{BackfillRequest, BackfillResponse};
+use jeflog::{fail, pass};
+use std::time::Duration;
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which has the control server push its logged vehicle snapshots
+/// within a time range to the configured time-series database.
+pub fn backfill(args: &ArgMatches) -> anyhow::Result<()> {
+	let request = BackfillRequest {
+		from: args.get_one::<f64>("from").copied(),
+		to: args.get_one::<f64>("to").copied(),
+	};
+
+	let response = http_client()?
+		.post(format!("{}/data/influx/backfill", server_url()))
+		.json(&request)
+		.timeout(Duration::from_secs(3600))
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let backfill: BackfillResponse = response.json()?;
+	pass!("Pushed \x1b[1m{}\x1b[0m snapshots as {} lines.", backfill.snapshots, backfill.lines);
+
+	Ok(())
+}
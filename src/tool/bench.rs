@@ -0,0 +1,64 @@
+use clap::ArgMatches;
+use crate::server::bench::{Workload, CHANNEL_COUNTS, EXPORT_SNAPSHOTS};
+use std::{hint::black_box, time::{Duration, Instant}};
+
+#[cfg(feature = "hdf5")]
+use std::{env, fs};
+
+/// Runs an operation repeatedly for at least the given time, returning the
+/// number of iterations and the mean time taken by each.
+fn measure<T>(budget: Duration, mut operation: impl FnMut() -> anyhow::Result<T>) -> anyhow::Result<(u32, Duration)> {
+	let start = Instant::now();
+	let mut iterations = 0;
+
+	while iterations == 0 || start.elapsed() < budget {
+		black_box(operation()?);
+		iterations += 1;
+	}
+
+	Ok((iterations, start.elapsed() / iterations))
+}
+
+/// Tool function which measures each stage of the data pipeline locally at
+/// representative channel counts.
+///
+/// This is a quick check for development; the criterion benchmarks under
+/// `benches/` measure the same workloads with statistical rigor.
+pub fn bench(args: &ArgMatches) -> anyhow::Result<()> {
+	let budget = Duration::try_from_secs_f64(*args.get_one::<f64>("time").unwrap())?;
+	let channel_counts = match args.get_one::<usize>("channels") {
+		Some(&channels) => vec![channels],
+		None => CHANNEL_COUNTS.to_vec(),
+	};
+
+	#[cfg(feature = "hdf5")]
+	let hdf5_path = env::temp_dir().join(format!("servo-bench-{}.hdf5", std::process::id()));
+
+	println!("\x1b[1m{:<24} {:>8} {:>12} {:>14} {:>18}\x1b[0m", "stage", "channels", "iterations", "mean", "snapshots/s");
+
+	for channels in channel_counts {
+		let workload = Workload::new(channels)?;
+
+		let stages = [
+			("postcard decode", 1, measure(budget, || Ok(workload.decode()?))?),
+			("state merge", 1, measure(budget, || Ok(workload.merge()))?),
+			("snapshot insert", 1, measure(budget, || workload.insert())?),
+			("csv export", EXPORT_SNAPSHOTS, measure(budget, || Ok(workload.export_csv()))?),
+			#[cfg(feature = "hdf5")]
+			("hdf5 export", EXPORT_SNAPSHOTS, measure(budget, || Ok(workload.export_hdf5(&hdf5_path)?))?),
+			("forward serialization", 1, measure(budget, || Ok(workload.serialize_forwarded()?))?),
+		];
+
+		for (stage, snapshots, (iterations, mean)) in stages {
+			let rate = snapshots as f64 / mean.as_secs_f64();
+			println!("{stage:<24} {channels:>8} {iterations:>12} {:>14} {rate:>18.0}", format!("{mean:.2?}"));
+		}
+	}
+
+	#[cfg(feature = "hdf5")]
+	if hdf5_path.exists() {
+		fs::remove_file(&hdf5_path)?;
+	}
+
+	Ok(())
+}
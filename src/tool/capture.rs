@@ -0,0 +1,155 @@
+use clap::ArgMatches;
+use common::comm::VehicleState;
+use crate::server::{quarantine::BadFrame, recording, telemetry};
+use jeflog::{fail, pass};
+use std::{fs, path::{Path, PathBuf}};
+
+use super::client::{http_client, read_error, server_url};
+
+/// The number of bytes shown of each frame which could not be decoded, unless every byte is asked for.
+pub(super) const HEXDUMP_BYTES: usize = 64;
+
+/// Tool function which examines telemetry frames recorded to a frame log or pcap capture.
+pub fn capture(args: &ArgMatches) -> anyhow::Result<()> {
+	match args.subcommand() {
+		Some(("decode", args)) => decode(
+			args.get_one::<PathBuf>("capture_path").unwrap(),
+			*args.get_one::<u16>("port").unwrap(),
+			args.get_flag("failed"),
+		),
+		Some(("bad-frames", args)) => bad_frames(
+			*args.get_one::<usize>("limit").unwrap(),
+			args.get_flag("full"),
+			args.get_one::<PathBuf>("save").map(PathBuf::as_path),
+		),
+		_ => unreachable!("clap requires a capture subcommand"),
+	}
+}
+
+/// Decodes each recorded frame on its own, regardless of the frames around it,
+/// printing a line for each and a hexdump of those which fail.
+fn decode(capture_path: &Path, port: u16, failed_only: bool) -> anyhow::Result<()> {
+	let recording = recording::read(&fs::read(capture_path)?, port)?;
+	let mut malformed = 0;
+
+	for recorded in &recording.frames {
+		let (sequence_number, payload) = telemetry::split_sequence_number(&recorded.frame);
+
+		let sequence_number = sequence_number.map_or("-".to_owned(), |sequence_number| sequence_number.to_string());
+		let source = recorded.source.map_or("unknown".to_owned(), |source| source.to_string());
+		let prefix = format!("{:.6}  {source:<21}  #{sequence_number:<8} {:>6} bytes", recorded.received_at, recorded.frame.len());
+
+		match postcard::from_bytes::<VehicleState>(payload) {
+			Ok(vehicle_state) if !failed_only => println!(
+				"{prefix}  \x1b[32mok\x1b[0m  {} sensors, {} valves",
+				vehicle_state.sensor_readings.len(),
+				vehicle_state.valve_states.len(),
+			),
+			Ok(_) => {},
+			Err(error) => {
+				malformed += 1;
+				println!("{prefix}  \x1b[31;1merror\x1b[0m  {error}");
+
+				for line in hexdump(&recorded.frame[..recorded.frame.len().min(HEXDUMP_BYTES)]) {
+					println!("    {line}");
+				}
+
+				if recorded.frame.len() > HEXDUMP_BYTES {
+					println!("    ...and {} more bytes", recorded.frame.len() - HEXDUMP_BYTES);
+				}
+			},
+		}
+	}
+
+	println!(
+		"\n\x1b[1m{}\x1b[0m frames, \x1b[1m{}\x1b[0m decoded, \x1b[1m{malformed}\x1b[0m malformed.",
+		recording.frames.len(),
+		recording.frames.len() - malformed,
+	);
+
+	Ok(())
+}
+
+/// Displays the most recent frames quarantined by the control server for
+/// failing to deserialize, optionally saving each to a directory.
+fn bad_frames(limit: usize, full: bool, save_directory: Option<&Path>) -> anyhow::Result<()> {
+	let response = http_client()?
+		.get(format!("{}/data/bad-frames", server_url()))
+		.query(&[("limit", limit)])
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let frames: Vec<BadFrame> = response.json()?;
+
+	if frames.is_empty() {
+		pass!("No frames have failed to decode.");
+		return Ok(());
+	}
+
+	if let Some(directory) = save_directory {
+		fs::create_dir_all(directory)?;
+	}
+
+	for frame in frames.iter().rev() {
+		let bytes = base64::decode(&frame.frame)?;
+		let source = frame.source.as_deref().unwrap_or("unknown");
+
+		println!(
+			"\x1b[1m#{}\x1b[0m  {:.6}  {source}  {} bytes  \x1b[31;1m{}\x1b[0m",
+			frame.frame_id,
+			frame.received_at,
+			frame.size,
+			frame.error,
+		);
+
+		let shown = if full { bytes.len() } else { bytes.len().min(HEXDUMP_BYTES) };
+
+		for line in hexdump(&bytes[..shown]) {
+			println!("    {line}");
+		}
+
+		if frame.size > shown {
+			println!("    ...and {} more bytes", frame.size - shown);
+		}
+
+		if let Some(directory) = save_directory {
+			let path = directory.join(format!("bad-frame-{}", frame.frame_id));
+			fs::write(path.with_extension("bin"), &bytes)?;
+			fs::write(path.with_extension("txt"), hexdump(&bytes).join("\n") + "\n")?;
+		}
+
+		println!();
+	}
+
+	if let Some(directory) = save_directory {
+		pass!("Saved {} frames to \x1b[1m{}\x1b[0m.", frames.len(), directory.display());
+	}
+
+	Ok(())
+}
+
+/// Formats bytes as the lines of a hexdump, sixteen bytes to a line with their offset and any printable ASCII.
+pub(super) fn hexdump(bytes: &[u8]) -> Vec<String> {
+	bytes
+		.chunks(16)
+		.enumerate()
+		.map(|(index, chunk)| {
+			let hex = chunk
+				.iter()
+				.map(|byte| format!("{byte:02x}"))
+				.collect::<Vec<_>>()
+				.join(" ");
+
+			let ascii = chunk
+				.iter()
+				.map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+				.collect::<String>();
+
+			format!("{:08x}  {hex:<47}  |{ascii}|", index * 16)
+		})
+		.collect()
+}
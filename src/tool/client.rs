@@ -0,0 +1,286 @@
+use anyhow::{anyhow, Context};
+use clap::ArgMatches;
+use crate::server::{config::ClientConfig, discovery, error::{ErrorBody, ErrorCode}, Config};
+use jeflog::warn;
+use reqwest::{blocking::{Client, Response}, header::{self, HeaderMap, HeaderValue}, Certificate};
+use serde::{Deserialize, Serialize};
+use std::{
+	env,
+	fs,
+	io,
+	net::{Ipv4Addr, SocketAddr, TcpStream},
+	path::{Path, PathBuf},
+	sync::OnceLock,
+	time::Duration,
+};
+
+/// The environment variable which overrides the configured server.
+const SERVER_VARIABLE: &str = "SERVO_SERVER";
+
+/// The environment variable which overrides the configured session token.
+const TOKEN_VARIABLE: &str = "SERVO_TOKEN";
+
+/// The address a server on this machine listens on.
+const LOCAL_SERVER: &str = "http://localhost:7200";
+
+/// How long to wait for a server on this machine to accept a connection
+/// before looking for one on the network.
+const LOCAL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long to browse the network for an advertised server.
+const BROWSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The file in the Servo directory holding the session saved by `servo login`.
+const SESSION_FILE: &str = "session.json";
+
+/// How the command line tools reach the server, resolved once per command.
+static CLIENT: OnceLock<ClientConfig> = OnceLock::new();
+
+/// The path of the saved session, known once the client is configured.
+static SESSION_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// A session saved by `servo login`, which is only sent to the server it was created on.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SavedSession {
+	/// The base URL of the server which created the session.
+	pub server: String,
+
+	/// The user the session belongs to.
+	pub username: String,
+
+	/// The bearer token of the session.
+	pub token: String,
+
+	/// The Unix timestamp at which the session expires, unless renewed by activity.
+	pub expires_at: f64,
+}
+
+/// Resolves the server the command line tools send requests to, along with
+/// the token and certificate they use, from the `--server` and `--token`
+/// flags, then the `SERVO_SERVER` and `SERVO_TOKEN` environment variables,
+/// then the `[client]` section of `config.toml`.
+pub fn configure_client(matches: &ArgMatches, servo_dir: &Path) -> anyhow::Result<()> {
+	let mut config = match Config::load(&servo_dir.join("config.toml")) {
+		Ok(config) => config.client,
+		Err(error) => {
+			warn!("Ignoring config.toml, which is invalid: {error}");
+			ClientConfig::default()
+		},
+	};
+
+	// global flags given after a subcommand are only found in its matches
+	let flag = |name: &str| {
+		let mut matches = matches;
+
+		while let Some((_, subcommand)) = matches.subcommand() {
+			matches = subcommand;
+		}
+
+		matches.get_one::<String>(name).cloned()
+	};
+
+	if let Some(server) = flag("server").or_else(|| env::var(SERVER_VARIABLE).ok()) {
+		config.server = Some(server);
+	}
+
+	if let Some(token) = flag("token").or_else(|| env::var(TOKEN_VARIABLE).ok()) {
+		config.token = Some(token);
+	}
+
+	if let Some(server) = &config.server {
+		config.server = Some(normalize_server(server)?);
+	}
+
+	let _ = CLIENT.set(config);
+	let _ = SESSION_PATH.set(servo_dir.join(SESSION_FILE));
+	Ok(())
+}
+
+/// Normalizes the address of a server into a base URL, assuming HTTP when no
+/// scheme is given and dropping any trailing slashes.
+pub fn normalize_server(server: &str) -> anyhow::Result<String> {
+	let mut server = server.to_owned();
+
+	if !server.contains("://") {
+		server.insert_str(0, "http://");
+	}
+
+	let trimmed = server.trim_end_matches('/').len();
+	server.truncate(trimmed);
+
+	reqwest::Url::parse(&server).map_err(|error| anyhow!("invalid server URL '{server}': {error}"))?;
+	Ok(server)
+}
+
+/// The session saved by `servo login`, if there is one.
+pub fn saved_session() -> anyhow::Result<Option<SavedSession>> {
+	let Some(path) = SESSION_PATH.get() else {
+		return Ok(None);
+	};
+
+	match fs::read_to_string(path) {
+		Ok(contents) => serde_json::from_str(&contents)
+			.map(Some)
+			.with_context(|| format!("saved session {} is invalid, so log in again", path.display())),
+		Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(error) => Err(error.into()),
+	}
+}
+
+/// Saves a session so that later commands send its token, readable only by the user.
+pub fn save_session(session: &SavedSession) -> anyhow::Result<()> {
+	let path = SESSION_PATH.get().ok_or_else(|| anyhow!("client has not been configured"))?;
+	let contents = serde_json::to_string_pretty(session)?;
+
+	#[cfg(target_family = "unix")]
+	{
+		use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+		// the mode only applies to a new file, so an old one is replaced
+		let _ = fs::remove_file(path);
+
+		fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.mode(0o600)
+			.open(path)?
+			.write_all(contents.as_bytes())?;
+	}
+
+	#[cfg(not(target_family = "unix"))]
+	fs::write(path, contents)?;
+
+	Ok(())
+}
+
+/// Deletes the saved session, returning whether there was one.
+pub fn remove_session() -> anyhow::Result<bool> {
+	let Some(path) = SESSION_PATH.get() else {
+		return Ok(false);
+	};
+
+	match fs::remove_file(path) {
+		Ok(()) => Ok(true),
+		Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(error) => Err(error.into()),
+	}
+}
+
+/// The base URL of the server, such as `http://localhost:7200`.
+///
+/// Without a configured server, one running on this machine is used if there
+/// is one. Otherwise, the network is browsed for an advertised server,
+/// preferring the primary, and failing that, requests still go to this machine.
+pub fn server_url() -> &'static str {
+	static URL: OnceLock<String> = OnceLock::new();
+
+	URL.get_or_init(|| {
+		if let Some(server) = CLIENT.get().and_then(|config| config.server.clone()) {
+			return server;
+		}
+
+		let local = SocketAddr::from((Ipv4Addr::LOCALHOST, 7200));
+
+		if TcpStream::connect_timeout(&local, LOCAL_TIMEOUT).is_ok() {
+			return LOCAL_SERVER.to_owned();
+		}
+
+		match discovery::discover(BROWSE_TIMEOUT) {
+			Ok(servers) => {
+				let server = servers
+					.iter()
+					.find(|server| server.role.as_deref() == Some("primary"))
+					.or(servers.first());
+
+				if let Some(server) = server {
+					eprintln!("Using servo at {} ({}), found on the network.", server.url(), server.name);
+					return server.url();
+				}
+			},
+			Err(error) => warn!("Failed to browse the network for servo: {error}"),
+		}
+
+		LOCAL_SERVER.to_owned()
+	})
+}
+
+/// Builds an HTTP client which sends the session token with every request and
+/// trusts the configured certificate, for servers behind TLS.
+///
+/// Without a token given by flag, environment, or configuration, the token of
+/// the session saved by `servo login` is sent, if it was created on the same server.
+pub fn http_client() -> anyhow::Result<Client> {
+	let token = CLIENT.get().and_then(|config| config.token.clone());
+	client_for(server_url(), token)
+}
+
+/// Builds an HTTP client for a server other than the configured one, such as
+/// the remote server of `servo sync`, which sends the given token, or failing
+/// that, the token of the session saved by `servo login` if it was created there.
+pub fn client_for(server: &str, token: Option<String>) -> anyhow::Result<Client> {
+	let config = CLIENT.get().cloned().unwrap_or_default();
+	let mut headers = HeaderMap::new();
+
+	let token = match token {
+		Some(token) => Some(token),
+		None => saved_session()?
+			.filter(|session| session.server == server)
+			.map(|session| session.token),
+	};
+
+	if let Some(token) = &token {
+		let mut value = HeaderValue::from_str(&format!("Bearer {token}")).context("session token is not a valid header")?;
+		value.set_sensitive(true);
+		headers.insert(header::AUTHORIZATION, value);
+	}
+
+	let mut builder = Client::builder().default_headers(headers);
+
+	if let Some(path) = &config.ca_certificate {
+		let pem = fs::read(path).with_context(|| format!("failed to read certificate {}", path.display()))?;
+		builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+	}
+
+	Ok(builder.build()?)
+}
+
+/// Reads the error a server responded with, falling back on the raw body as
+/// the message for servers which predate error codes.
+pub fn read_error(response: Response) -> ErrorBody {
+	let code = match response.status().as_u16() {
+		400 => ErrorCode::BadRequest,
+		401 => ErrorCode::Unauthenticated,
+		403 => ErrorCode::Forbidden,
+		404 => ErrorCode::NotFound,
+		408 => ErrorCode::Timeout,
+		409 => ErrorCode::Conflict,
+		423 => ErrorCode::Locked,
+		_ => ErrorCode::Internal,
+	};
+
+	let status = response.status();
+	let text = response.text().unwrap_or_default();
+
+	serde_json::from_str(&text).unwrap_or_else(|_| ErrorBody {
+		code,
+		message: if text.is_empty() { status.to_string() } else { text },
+		context: Default::default(),
+	})
+}
+
+/// Extends responses with the errors servers describe in their bodies.
+pub trait ResponseExt: Sized {
+	/// Returns the error the server responded with if the request failed, like
+	/// `error_for_status`, but keeping the code and message of the error.
+	fn or_server_error(self) -> Result<Self, ErrorBody>;
+}
+
+impl ResponseExt for Response {
+	fn or_server_error(self) -> Result<Self, ErrorBody> {
+		if self.status().is_success() {
+			Ok(self)
+		} else {
+			Err(read_error(self))
+		}
+	}
+}
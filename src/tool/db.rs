@@ -0,0 +1,110 @@
+use clap::ArgMatches;
+use crate::server::{backup::{self, BackupBatch, BackupRequest}, vacuum, Database};
+use jeflog::{pass, warn};
+use std::{path::{Path, PathBuf}, thread, time::Duration};
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which maintains the database of the control server.
+pub fn db(servo_dir: &Path, args: &ArgMatches) -> anyhow::Result<()> {
+	match args.subcommand() {
+		Some(("convert", args)) => convert(
+			&args.get_one::<PathBuf>("database").cloned().unwrap_or_else(|| servo_dir.join("database.sqlite")),
+		),
+		Some(("backup", args)) => backup(
+			args.get_one::<PathBuf>("destination").unwrap(),
+			args.get_flag("follow"),
+			*args.get_one::<f64>("interval").unwrap(),
+			*args.get_one::<usize>("batch").unwrap(),
+		),
+		_ => unreachable!("clap requires a db subcommand"),
+	}
+}
+
+/// Backs up the database of the control server to another database, such as
+/// one on an attached drive, resuming wherever a previous backup to it left off.
+///
+/// When following, the backup is kept up to date until interrupted, and
+/// failures such as the server restarting are retried rather than fatal.
+fn backup(destination: &Path, follow: bool, interval: f64, max_rows: usize) -> anyhow::Result<()> {
+	let database = Database::open(destination)?;
+	database.migrate()?;
+
+	let client = http_client()?;
+	let interval = Duration::from_secs_f64(interval);
+	let mut up_to_date = false;
+
+	loop {
+		let result = backup_batch(&client, &database, max_rows);
+
+		match result {
+			Ok(batch) => {
+				let rows = batch.appended.iter().map(|table| table.rows.len()).sum::<usize>();
+
+				if !batch.complete {
+					println!("Backed up {rows} rows, catching up...");
+					up_to_date = false;
+					continue;
+				}
+
+				if !follow {
+					pass!("Backed up to \x1b[1m{}\x1b[0m.", destination.display());
+					return Ok(());
+				}
+
+				if !up_to_date {
+					pass!("Backup at \x1b[1m{}\x1b[0m is up to date, following changes...", destination.display());
+					up_to_date = true;
+				} else if rows > 0 {
+					let plural = if rows == 1 { "" } else { "s" };
+					println!("Backed up {rows} new row{plural}.");
+				}
+			},
+			Err(error) if follow => {
+				warn!("Failed to back up, retrying: {error}");
+				up_to_date = false;
+			},
+			Err(error) => return Err(error),
+		}
+
+		thread::sleep(interval);
+	}
+}
+
+/// Converts a database created before incremental vacuuming so that its free
+/// pages can be released by the server's maintenance from then on.
+///
+/// This rewrites the whole database, which holds it for as long as that takes,
+/// so it is only done on request and should be done while the server is stopped.
+fn convert(path: &Path) -> anyhow::Result<()> {
+	let database = Database::open(path)?;
+
+	println!("Vacuuming \x1b[1m{}\x1b[0m, which may take several minutes for a large database...", path.display());
+
+	if vacuum::convert(&database.connection.blocking_lock())? {
+		pass!("Converted the database, which may now be vacuumed incrementally.");
+	} else {
+		pass!("The database can already be vacuumed incrementally.");
+	}
+
+	Ok(())
+}
+
+/// Fetches and applies the next batch of changes to the backup.
+fn backup_batch(client: &reqwest::blocking::Client, database: &Database, max_rows: usize) -> anyhow::Result<BackupBatch> {
+	let cursors = backup::prepare(&database.connection.blocking_lock())?;
+
+	let response = client
+		.post(format!("{}/admin/backup/changes", server_url()))
+		.json(&BackupRequest { cursors, max_rows })
+		.send()?;
+
+	if !response.status().is_success() {
+		anyhow::bail!("{}", read_error(response));
+	}
+
+	let batch = postcard::from_bytes(&response.bytes()?)?;
+	backup::apply(&mut database.connection.blocking_lock(), &batch)?;
+
+	Ok(batch)
+}
@@ -1,14 +1,67 @@
 use clap::ArgMatches;
-use common::comm::{ChannelType, DataMessage, DataPoint, Measurement, Unit, ValveState, VehicleState, CompositeValveState};
-use jeflog::fail;
-use std::{borrow::Cow, net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket}, thread, time::Duration};
+use crate::server::{allowlist, api::MetricsResponse, clock, codec::{self, FrameDecoder, InfoQuery, InfoReport, ParameterValue}, metrics::TelemetryMetrics, simulation::{self, SimulatedAction, SimulationOptions}, telemetry};
+use common::comm::{ChannelType, Computer, DataMessage, DataPoint, FlightControlMessage, Measurement, Unit, ValveState, VehicleState, CompositeValveState};
+use jeflog::{fail, pass, warn};
+use std::{borrow::Cow, collections::BTreeMap, io::{Read, Write}, net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket}, path::PathBuf, sync::mpsc, thread, time::{Duration, Instant}};
 
+use super::{client::server_url, physics::{PhysicsModel, PhysicsState}};
 
-pub fn emulate_flight() -> anyhow::Result<()> {
-	let _flight = TcpStream::connect("localhost:5025")?;
+/// How often the load generator reports the throughput achieved by the server.
+const LOAD_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The sending side of a vehicle state telemetry transport.
+enum TelemetrySender {
+	Udp(UdpSocket),
+	Tcp(TcpStream),
+}
+
+impl TelemetrySender {
+	fn connect(transport: &str) -> anyhow::Result<Self> {
+		if transport == "tcp" {
+			return Ok(TelemetrySender::Tcp(TcpStream::connect("localhost:7201")?));
+		}
+
+		let socket = UdpSocket::bind("0.0.0.0:0")?;
+		socket.connect("localhost:7201")?;
+
+		Ok(TelemetrySender::Udp(socket))
+	}
+
+	fn send(&mut self, frame: &[u8]) -> anyhow::Result<()> {
+		match self {
+			TelemetrySender::Udp(socket) => {
+				socket.send(frame)?;
+			},
+			TelemetrySender::Tcp(stream) => {
+				stream.write_all(&(frame.len() as u32).to_be_bytes())?;
+				stream.write_all(frame)?;
+			},
+		}
+
+		Ok(())
+	}
+}
 
-	let data_socket = UdpSocket::bind("0.0.0.0:0")?;
-	data_socket.connect("localhost:7201")?;
+/// Connects to servo as the flight computer, identifying itself as one and
+/// answering the pre-shared-key handshake if a key is given.
+fn connect_flight(key: Option<&str>) -> anyhow::Result<TcpStream> {
+	let mut flight = TcpStream::connect("localhost:5025")?;
+	flight.write_all(&postcard::to_allocvec(&Computer::Flight)?)?;
+
+	if let Some(key) = key {
+		let mut challenge = [0; allowlist::CHALLENGE_SIZE];
+		flight.read_exact(&mut challenge)?;
+		flight.write_all(&allowlist::respond(key, &challenge))?;
+	}
+
+	Ok(flight)
+}
+
+pub fn emulate_flight(transport: &str, key: Option<&str>) -> anyhow::Result<()> {
+	// commands are ignored, but queries are still answered while they are read
+	let _commands = receive_commands(connect_flight(key)?);
+
+	let mut data_socket = TelemetrySender::connect(transport)?;
 
 	let mut mock_vehicle_state = VehicleState::new();
 	mock_vehicle_state.valve_states.insert("BBV".to_owned(), CompositeValveState { commanded: ValveState::Closed, actual: ValveState::Closed });
@@ -20,7 +73,7 @@ pub fn emulate_flight() -> anyhow::Result<()> {
 	let mut raw = postcard::to_allocvec(&mock_vehicle_state)?;
 	postcard::from_bytes::<VehicleState>(&raw).unwrap();
 
-	loop {
+	for sequence_number in 0.. {
 		mock_vehicle_state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: rand::random::<f64>() * 120.0, unit: Unit::Psi });
 		mock_vehicle_state.sensor_readings.insert("WTPT".to_owned(), Measurement { value: rand::random::<f64>() * 1000.0, unit: Unit::Psi });
 		mock_vehicle_state.sensor_readings.insert("BBV_V".to_owned(), Measurement { value: 2.2, unit: Unit::Volts });
@@ -31,9 +84,297 @@ pub fn emulate_flight() -> anyhow::Result<()> {
 		mock_vehicle_state.sensor_readings.insert("BAD_I".to_owned(), Measurement { value: 0.0, unit: Unit::Amps });
 		raw = postcard::to_allocvec(&mock_vehicle_state)?;
 
-		data_socket.send(&raw)?;
+		data_socket.send(&telemetry::sequenced_frame(sequence_number, &raw))?;
 		thread::sleep(Duration::from_millis(10));
 	}
+
+	Ok(())
+}
+
+/// Emulates a flight computer whose tanks fill and blow down as its valves are
+/// commanded, so that sequences run against it see plausible readings.
+///
+/// Sequences sent by servo are simulated to find when they open and close
+/// valves, which are then actuated at those times in the physics model.
+pub fn emulate_physics(transport: &str, model: PhysicsModel, key: Option<&str>) -> anyhow::Result<()> {
+	let commands = receive_commands(connect_flight(key)?);
+
+	let mut data_socket = TelemetrySender::connect(transport)?;
+
+	pass!("Emulating {} tanks and {} valves. Press Ctrl-C to stop.", model.tanks.len(), model.valves.len());
+
+	let mut physics = PhysicsState::new(model);
+	let mut vehicle_state = VehicleState::new();
+	let mut pending = Vec::<(Instant, String, SimulatedAction)>::new();
+	let mut last_step = Instant::now();
+
+	physics.write(&mut vehicle_state);
+
+	for sequence_number in 0.. {
+		for message in commands.try_iter() {
+			match message {
+				FlightControlMessage::Sequence(sequence) => {
+					// waits on conditions are simulated against the readings as they are now
+					let options = SimulationOptions {
+						assumptions: vehicle_state.sensor_readings
+							.iter()
+							.map(|(name, reading)| (name.clone(), [reading.value, reading.value]))
+							.collect(),
+						..SimulationOptions::default()
+					};
+
+					match simulation::simulate(&sequence.script, &options) {
+						Ok(report) => {
+							let received_at = Instant::now();
+
+							pending.extend(report.timeline.into_iter().map(|entry| {
+								(received_at + Duration::from_secs_f64(entry.at_seconds), sequence.name.clone(), entry.action)
+							}));
+
+							pass!("Running sequence {} over {:.1} seconds.", sequence.name, report.total_seconds);
+						},
+						Err(error) => warn!("Sequence {} could not be simulated: {error}", sequence.name),
+					}
+				},
+				FlightControlMessage::StopSequence(name) => pending.retain(|(_, sequence, _)| *sequence != name),
+				FlightControlMessage::Abort => pending.clear(),
+				_ => {},
+			}
+		}
+
+		let now = Instant::now();
+
+		pending.retain(|(at, sequence, action)| {
+			if *at > now {
+				return true;
+			}
+
+			let (valve, open) = match action {
+				SimulatedAction::Open { valve } => (valve, true),
+				SimulatedAction::Close { valve } => (valve, false),
+				_ => return false,
+			};
+
+			if !physics.set_valve(valve, open) {
+				warn!("Sequence {sequence} commanded {valve}, which is not in the physics model.");
+			}
+
+			false
+		});
+
+		physics.step(now.duration_since(last_step).as_secs_f64());
+		physics.write(&mut vehicle_state);
+		last_step = now;
+
+		let raw = postcard::to_allocvec(&vehicle_state)?;
+		data_socket.send(&telemetry::sequenced_frame(sequence_number, &raw))?;
+		thread::sleep(Duration::from_millis(10));
+	}
+
+	Ok(())
+}
+
+/// Reads the messages servo sends to the flight computer on another thread,
+/// answering its queries of software information there.
+fn receive_commands(mut stream: TcpStream) -> mpsc::Receiver<FlightControlMessage> {
+	let (sender, receiver) = mpsc::channel();
+	let started = Instant::now();
+
+	thread::spawn(move || {
+		let mut decoder = FrameDecoder::default();
+		let mut chunk = [0; 4096];
+
+		while let Ok(read @ 1..) = stream.read(&mut chunk) {
+			decoder.push(&chunk[..read]);
+
+			// a read may hold several messages or part of one
+			loop {
+				let message = match decoder.next_message() {
+					Ok(Some((codec::CONTROL_MESSAGE, message))) => message,
+					Ok(Some((codec::INFO_QUERY, query))) => {
+						if let Err(error) = answer_query(&mut stream, &query, started) {
+							warn!("Failed to answer a query from servo: {error}");
+						}
+
+						continue;
+					},
+					Ok(Some(_)) => continue,
+					Ok(None) => break,
+					Err(error) => {
+						warn!("Dropping the connection to servo, whose messages cannot be framed: {error}");
+						return;
+					},
+				};
+
+				match postcard::from_bytes::<FlightControlMessage>(&message) {
+					Ok(message) => {
+						if sender.send(message).is_err() {
+							return;
+						}
+					},
+					Err(error) => warn!("Discarding a message from servo which could not be read: {error}"),
+				}
+			}
+		}
+
+		warn!("Lost the connection to servo.");
+	});
+
+	receiver
+}
+
+/// Answers a query from servo with the software information of the emulator,
+/// which has been running since `started`.
+fn answer_query(stream: &mut TcpStream, query: &[u8], started: Instant) -> anyhow::Result<()> {
+	let query = postcard::from_bytes::<InfoQuery>(query)?;
+
+	let mut parameters = BTreeMap::from([
+		("emulated".to_owned(), ParameterValue::Bool(true)),
+		("telemetry_period_ms".to_owned(), ParameterValue::Integer(10)),
+	]);
+
+	if !query.parameters.is_empty() {
+		parameters.retain(|name, _| query.parameters.contains(name));
+	}
+
+	let report = InfoReport {
+		version: env!("CARGO_PKG_VERSION").to_owned(),
+		build_hash: "emulated".to_owned(),
+		uptime_seconds: started.elapsed().as_secs_f64(),
+		reported_at: clock::now(),
+		parameters,
+	};
+
+	stream.write_all(&codec::encode(codec::INFO_REPORT, &postcard::to_allocvec(&report)?)?)?;
+	Ok(())
+}
+
+/// Emulates a flight computer sending synthetic vehicle states with a number of
+/// channels at a fixed rate, to stress-test the server.
+///
+/// Each second, the frames sent are compared against the server's telemetry
+/// metrics to report the throughput it achieved and the frames it dropped.
+pub fn emulate_load(transport: &str, channels: usize, rate: f64, duration: Option<f64>) -> anyhow::Result<()> {
+	let _flight = TcpStream::connect("localhost:5025")?;
+
+	let mut data_socket = TelemetrySender::connect(transport)?;
+	let client = reqwest::blocking::Client::new();
+	let period = Duration::try_from_secs_f64(1.0 / rate)?;
+	let duration = duration.map(Duration::try_from_secs_f64).transpose()?;
+
+	// every tenth channel is a valve, roughly matching the proportion on the vehicle
+	let units = [Unit::Psi, Unit::Kelvin, Unit::Pounds, Unit::Volts, Unit::Amps];
+	let mut vehicle_state = VehicleState::new();
+
+	for channel in 0..channels {
+		let name = format!("LOAD{channel:04}");
+
+		if channel % 10 == 9 {
+			vehicle_state.valve_states.insert(name, CompositeValveState { commanded: ValveState::Closed, actual: ValveState::Closed });
+		} else {
+			vehicle_state.sensor_readings.insert(name, Measurement { value: 0.0, unit: units[channel % units.len()] });
+		}
+	}
+
+	pass!("Sending {channels} channels at {rate} Hz. Press Ctrl-C to stop.");
+
+	let start = Instant::now();
+	let baseline = server_telemetry(&client);
+	let mut last_report = (start, 0, baseline.clone());
+	let mut sent = 0;
+
+	for sequence_number in 0_u64.. {
+		let scheduled = start + period.mul_f64(sequence_number as f64);
+		let now = Instant::now();
+
+		if duration.is_some_and(|duration| now - start >= duration) {
+			break;
+		}
+
+		if scheduled > now {
+			thread::sleep(scheduled - now);
+		}
+
+		for reading in vehicle_state.sensor_readings.values_mut() {
+			reading.value = rand::random::<f64>() * 1000.0;
+		}
+
+		let raw = postcard::to_allocvec(&vehicle_state)?;
+		data_socket.send(&telemetry::sequenced_frame(sequence_number, &raw))?;
+		sent += 1;
+
+		let (reported_at, reported_sent, reported_telemetry) = &last_report;
+
+		if reported_at.elapsed() >= LOAD_REPORT_INTERVAL {
+			let telemetry = server_telemetry(&client);
+			report_load(reported_at.elapsed(), sent - reported_sent, reported_telemetry, &telemetry);
+			last_report = (Instant::now(), sent, telemetry);
+		}
+	}
+
+	let elapsed = start.elapsed();
+
+	// give the server a moment to process the last frames before the final report
+	thread::sleep(Duration::from_millis(500));
+
+	if let (Some(baseline), Some(telemetry)) = (baseline, server_telemetry(&client)) {
+		let received = telemetry.frames_received - baseline.frames_received;
+		let lost = telemetry.frames_lost - baseline.frames_lost;
+
+		pass!(
+			"Sent {sent} frames in {:.1} seconds; the server received {received} ({:.2}% dropped) and detected {lost} lost.",
+			elapsed.as_secs_f64(),
+			drop_percent(sent, received),
+		);
+	}
+
+	Ok(())
+}
+
+/// Fetches the telemetry metrics of the server, warning if they are unavailable.
+fn server_telemetry(client: &reqwest::blocking::Client) -> Option<TelemetryMetrics> {
+	let response = client
+		.get(format!("{}/status/metrics", server_url()))
+		.send()
+		.and_then(|response| response.error_for_status())
+		.and_then(|response| response.json::<MetricsResponse>());
+
+	match response {
+		Ok(metrics) => Some(metrics.telemetry),
+		Err(error) => {
+			warn!("Failed to fetch server metrics: {error}");
+			None
+		},
+	}
+}
+
+/// Prints the throughput achieved over an interval in which a number of frames were sent.
+fn report_load(elapsed: Duration, sent: u64, before: &Option<TelemetryMetrics>, after: &Option<TelemetryMetrics>) {
+	let seconds = elapsed.as_secs_f64();
+	let sent_rate = sent as f64 / seconds;
+
+	let (Some(before), Some(after)) = (before, after) else {
+		println!("sent {sent_rate:>8.1} frames/s");
+		return;
+	};
+
+	let received = after.frames_received - before.frames_received;
+	let lost = after.frames_lost - before.frames_lost;
+
+	println!(
+		"sent {sent_rate:>8.1} frames/s   received {:>8.1} frames/s   dropped {:>6.2}%   lost {lost}",
+		received as f64 / seconds,
+		drop_percent(sent, received),
+	);
+}
+
+/// The percentage of frames sent which the server did not receive.
+fn drop_percent(sent: u64, received: u64) -> f64 {
+	if sent == 0 {
+		return 0.0;
+	}
+
+	sent.saturating_sub(received) as f64 / sent as f64 * 100.0
 }
 
 pub fn emulate_sam(flight: SocketAddr) -> anyhow::Result<()> {
@@ -72,9 +413,24 @@ pub fn emulate_sam(flight: SocketAddr) -> anyhow::Result<()> {
 /// Tool function which emulates different components of the software stack.
 pub fn emulate(args: &ArgMatches) -> anyhow::Result<()> {
 	let component = args.get_one::<String>("component").unwrap();
+	let key = args.get_one::<String>("key").map(String::as_str);
 
 	match component.as_str() {
-		"flight" => emulate_flight(),
+		"flight" if args.contains_id("physics") => {
+			let model = match args.get_one::<PathBuf>("physics") {
+				Some(path) => PhysicsModel::load(path)?,
+				None => PhysicsModel::default(),
+			};
+
+			emulate_physics(args.get_one::<String>("transport").unwrap(), model, key)
+		},
+		"flight" => emulate_flight(args.get_one::<String>("transport").unwrap(), key),
+		"load" => emulate_load(
+			args.get_one::<String>("transport").unwrap(),
+			*args.get_one::<usize>("channels").unwrap(),
+			*args.get_one::<f64>("rate").unwrap(),
+			args.get_one::<f64>("duration").copied(),
+		),
 		"sam" => emulate_sam("localhost:4573".to_socket_addrs()?.find(|addr| addr.is_ipv4()).unwrap()),
 		other => {
 			fail!("Unrecognized emulator component '{other}'.");
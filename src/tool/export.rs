@@ -1,37 +1,121 @@
+use anyhow::anyhow;
+use clap::ArgMatches;
+use crate::server::{export::ExportPreset, selector::{self, Selection}, units::UnitSystem};
+use jeflog::{fail, pass};
 use serde_json::json;
-use std::{fs, path::PathBuf, time::Duration};
+use std::{fs::File, path::PathBuf, time::Duration};
+
+use super::client::{http_client, read_error, server_url, ResponseExt};
 
 /// Function for requesting all data between two timestamps as stored on the ground server.
 /// Used in the export command line routing.
-pub fn export(from: Option<f64>, to: Option<f64>, output_path: &str) -> anyhow::Result<()> {
-	let output_path = PathBuf::from(output_path);
+///
+/// Options left out are taken from the preset, if one is given, and the format
+/// otherwise falls back to the extension of the output file. With `--save-preset`,
+/// the options are saved as a preset on the server instead of being exported.
+pub fn export(args: &ArgMatches) -> anyhow::Result<()> {
+	let channels = args
+		.get_one::<String>("channels")
+		.map(|channels| selector::split(channels));
+
+	if let Some(Err(error)) = channels.as_ref().map(Selection::parse) {
+		fail!("Failed to parse channels: {error}");
+		return Ok(());
+	}
+
+	let rate = args.get_one::<f64>("rate").copied();
+	let units = args.get_one::<UnitSystem>("units").copied();
+	let client = http_client()?;
+
+	if let Some(name) = args.get_one::<String>("save_preset") {
+		if args.get_many::<String>("format").is_some_and(|formats| formats.len() > 1) {
+			fail!("A preset may only decide a single format.");
+			return Ok(());
+		}
 
-	let from = from.unwrap_or(0.0);
-	let to = to.unwrap_or(f64::MAX);
+		let preset = ExportPreset {
+			name: name.clone(),
+			description: args.get_one::<String>("description").cloned().unwrap_or_default(),
+			format: args.get_one::<String>("format").cloned(),
+			channels: channels.unwrap_or_default(),
+			downsample_hz: rate,
+			units: units.unwrap_or_default(),
+		};
+
+		let response = client.put(format!("{}/operator/export-presets", server_url()))
+			.json(&preset)
+			.send()?;
+
+		if !response.status().is_success() {
+			fail!("{}", read_error(response));
+			return Ok(());
+		}
+
+		pass!("Saved export preset '{name}'.");
+		return Ok(());
+	}
 
-	let export_format = output_path
-		.extension()
-		.unwrap()
-		.to_string_lossy();
+	let output_path = PathBuf::from(
+		args.get_one::<String>("output_path").ok_or_else(|| anyhow!("an output path is required"))?
+	);
 
-	let client = reqwest::blocking::Client::new();
-	let export_content = client.post("http://localhost:7200/data/export")
+	let from = args.get_one::<f64>("from").copied().unwrap_or(0.0);
+	let to = args.get_one::<f64>("to").copied().unwrap_or(f64::MAX);
+	let preset = args.get_one::<String>("preset");
+
+	// a preset which decides its own format takes precedence over the file extension
+	let preset_format = match preset {
+		Some(name) => client.get(format!("{}/data/export-presets", server_url()))
+			.send()?
+			.or_server_error()?
+			.json::<Vec<ExportPreset>>()?
+			.into_iter()
+			.find(|candidate| &candidate.name == name)
+			.ok_or_else(|| anyhow!("export preset '{name}' does not exist"))?
+			.format,
+		None => None,
+	};
+
+	let mut formats = args
+		.get_many::<String>("format")
+		.unwrap_or_default()
+		.cloned()
+		.collect::<Vec<_>>();
+
+	if formats.is_empty() && output_path.extension().is_some_and(|extension| extension == "zip") {
+		fail!("Choose the formats packed into the archive with --format, such as --format csv,hdf5.");
+		return Ok(());
+	}
+
+	// several formats are exported together as a zip archive
+	let export_formats = if formats.len() > 1 { formats.clone() } else { Vec::new() };
+
+	let export_format = formats
+		.pop()
+		.or(preset_format)
+		.or_else(|| output_path.extension().map(|extension| extension.to_string_lossy().into_owned()));
+
+	let mut export_content = client.post(format!("{}/data/export", server_url()))
 		.json(&json!({
 			"format": export_format,
+			"formats": export_formats,
 			"from": from,
-			"to": to
+			"to": to,
+			"preset": preset,
+			"channels": channels,
+			"downsample_hz": rate,
+			"units": units,
 		}))
 		.timeout(Duration::from_secs(3600))
 		.send()?;
 
-	// Either write the file as text if it's a csv, or bytes if it's a file (assumed for all other returns)
-	if export_format == std::string::String::from("csv") {
-		let text = export_content.text()?;
-		fs::write(output_path, text)?;
-	} else {
-		let bytes = export_content.bytes()?;
-		fs::write(output_path, bytes)?;
+	if !export_content.status().is_success() {
+		fail!("{}", read_error(export_content));
+		return Ok(());
 	}
 
+	// the export is streamed by the server, so it is written as it arrives rather than held whole
+	export_content.copy_to(&mut File::create(&output_path)?)?;
+
 	Ok(())
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, bail};
+use clap::ArgMatches;
+use crate::server::{api::{ImportRequest, ImportResponse}, import::{self, ImportOptions}};
+use jeflog::{fail, pass};
+use std::{collections::BTreeMap, fs, path::PathBuf, time::Duration};
+
+use super::client::{http_client, read_error, server_url};
+
+/// The most bytes of CSV sent in one request. Larger files are split between
+/// rows and sent in parts, so that each part stays within the server's limit
+/// on request bodies once encoded in Base64.
+const CSV_PART_BYTES: usize = 8 * 1024 * 1024;
+
+/// Tool function which imports a CSV or HDF5 file of vehicle data, such as an
+/// earlier export or the log of a third-party DAQ, into the control server.
+pub fn import(args: &ArgMatches) -> anyhow::Result<()> {
+	let path = args.get_one::<PathBuf>("path").unwrap();
+	let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+	let format = import::detect_format(args.get_one::<String>("format").map(String::as_str), file_name.as_deref())
+		.map_err(|error| anyhow!(error))?;
+
+	let options = ImportOptions {
+		rename: pairs(args, "rename")?,
+		units: pairs(args, "unit")?,
+		time_offset: args.get_one::<f64>("time_offset").copied().unwrap_or_default(),
+	};
+
+	let content = fs::read(path)?;
+
+	let parts = if format == "csv" {
+		split_csv(&content)
+	} else {
+		vec![content]
+	};
+
+	let client = http_client()?;
+	let mut total = ImportResponse::default();
+
+	for part in parts {
+		let request = ImportRequest {
+			file_name: file_name.clone(),
+			format: Some(format.to_owned()),
+			content: base64::encode(part),
+			options: options.clone(),
+		};
+
+		let response = client
+			.post(format!("{}/data/import", server_url()))
+			.json(&request)
+			.timeout(Duration::from_secs(3600))
+			.send()?;
+
+		if !response.status().is_success() {
+			fail!("{}", read_error(response));
+			return Ok(());
+		}
+
+		let imported: ImportResponse = response.json()?;
+
+		total.snapshots += imported.snapshots;
+		total.skipped += imported.skipped;
+		total.notes += imported.notes;
+		total.from = total.from.or(imported.from);
+		total.to = imported.to.or(total.to);
+
+		for channel in imported.channels {
+			if !total.channels.contains(&channel) {
+				total.channels.push(channel);
+			}
+		}
+	}
+
+	pass!(
+		"Imported \x1b[1m{}\x1b[0m snapshots of {} channels and {} notes from {}.",
+		total.snapshots,
+		total.channels.len(),
+		total.notes,
+		path.display(),
+	);
+
+	if let (Some(from), Some(to)) = (total.from, total.to) {
+		pass!("The data spans Unix time {from:.3} to {to:.3}.");
+	}
+
+	if total.skipped > 0 {
+		pass!("Skipped {} snapshots which were already logged.", total.skipped);
+	}
+
+	Ok(())
+}
+
+/// Reads a repeated flag of `KEY=VALUE` pairs into a map.
+fn pairs(args: &ArgMatches, name: &str) -> anyhow::Result<BTreeMap<String, String>> {
+	args.get_many::<String>(name)
+		.into_iter()
+		.flatten()
+		.map(|pair| match pair.split_once('=') {
+			Some((key, value)) => Ok((key.to_owned(), value.to_owned())),
+			None => bail!("expected --{name} to be given as COLUMN=VALUE, not '{pair}'"),
+		})
+		.collect()
+}
+
+/// Splits a CSV file between rows into parts of about [`CSV_PART_BYTES`],
+/// each of which starts with the header.
+fn split_csv(content: &[u8]) -> Vec<Vec<u8>> {
+	let header_end = content
+		.iter()
+		.position(|&byte| byte == b'\n')
+		.map_or(content.len(), |index| index + 1);
+
+	let (header, mut rows) = content.split_at(header_end);
+	let mut parts = Vec::new();
+
+	while !rows.is_empty() || parts.is_empty() {
+		let mut end = rows.len().min(CSV_PART_BYTES);
+
+		if end < rows.len() {
+			end = rows[..end]
+				.iter()
+				.rposition(|&byte| byte == b'\n')
+				.map_or(end, |index| index + 1);
+		}
+
+		let mut part = header.to_vec();
+		part.extend_from_slice(&rows[..end]);
+		parts.push(part);
+		rows = &rows[end..];
+	}
+
+	parts
+}
@@ -1,17 +1,20 @@
-use std::net::ToSocketAddrs;
+use std::net::{IpAddr, ToSocketAddrs};
 
 use anyhow::anyhow;
 use clap::ArgMatches;
 use jeflog::{fail, pass, warn};
 
-/// Tool function which locates all known hostnames on the network.
-pub fn locate(args: &ArgMatches) -> anyhow::Result<()> {
-	let mut prefixes = vec![("server", 3), ("flight", 2), ("ground", 2), ("gui", 6), ("sam", 6)];
+/// The hostname prefixes of each subsystem, along with how many of each there may be.
+pub(super) const SUBSYSTEMS: [(&str, u32); 5] = [("server", 3), ("flight", 2), ("ground", 2), ("gui", 6), ("sam", 6)];
+
+/// The known hostnames of a subsystem, or of every subsystem if none is given.
+pub(super) fn known_hostnames(subsystem: Option<&str>) -> anyhow::Result<Vec<String>> {
+	let mut prefixes = SUBSYSTEMS.to_vec();
 
-	if let Some(subsystem) = args.get_one::<String>("subsystem") {
+	if let Some(subsystem) = subsystem {
 		let chosen = prefixes
 			.iter()
-			.position(|(prefix, _)| subsystem == prefix);
+			.position(|(prefix, _)| subsystem == *prefix);
 
 		if let Some(chosen) = chosen {
 			prefixes = vec![prefixes[chosen]];
@@ -20,23 +23,36 @@ pub fn locate(args: &ArgMatches) -> anyhow::Result<()> {
 		}
 	}
 
-	for (prefix, count) in prefixes {
-		for i in 1..=count {
-			let hostname = format!("{prefix}-{i:0>2}.local");
-
-			if let Ok(mut socket_addresses) = (hostname.as_str(), 1).to_socket_addrs() {
-				let ip = socket_addresses
-					.find(|address| address.is_ipv4())
-					.map(|ipv4| ipv4.ip());
-
-				if let Some(ip) = ip {
-					pass!("Located \x1b[1m{hostname}\x1b[0m at \x1b[1m{ip}\x1b[0m.");
-				} else {
-					warn!("Located \x1b[1m{hostname}\x1b[0m at an IPv6 address.");
-				}
-			} else {
-				fail!("Failed to locate \x1b[1m{hostname}\x1b[0m.");
-			}
+	Ok(prefixes
+		.into_iter()
+		.flat_map(|(prefix, count)| (1..=count).map(move |i| format!("{prefix}-{i:0>2}.local")))
+		.collect())
+}
+
+/// Resolves a hostname to an IP address, preferring IPv4.
+pub(super) fn resolve(hostname: &str) -> Option<IpAddr> {
+	let addresses = (hostname, 1)
+		.to_socket_addrs()
+		.ok()?
+		.map(|address| address.ip())
+		.collect::<Vec<_>>();
+
+	addresses
+		.iter()
+		.find(|ip| ip.is_ipv4())
+		.or(addresses.first())
+		.copied()
+}
+
+/// Tool function which locates all known hostnames on the network.
+pub fn locate(args: &ArgMatches) -> anyhow::Result<()> {
+	let hostnames = known_hostnames(args.get_one::<String>("subsystem").map(String::as_str))?;
+
+	for hostname in hostnames {
+		match resolve(&hostname) {
+			Some(ip) if ip.is_ipv4() => pass!("Located \x1b[1m{hostname}\x1b[0m at \x1b[1m{ip}\x1b[0m."),
+			Some(_) => warn!("Located \x1b[1m{hostname}\x1b[0m at an IPv6 address."),
+			None => fail!("Failed to locate \x1b[1m{hostname}\x1b[0m."),
 		}
 	}
 
@@ -0,0 +1,98 @@
+use anyhow::anyhow;
+use clap::ArgMatches;
+use crate::server::api::{LoginRequest, LoginResponse};
+use jeflog::{fail, pass, warn};
+use std::{env, io::{self, Write}};
+use sysinfo::{System, SystemExt};
+
+use super::client::{self, http_client, read_error, server_url, SavedSession};
+
+/// Tool function which logs into the control server, saving the session so
+/// that later commands are authenticated as the user.
+pub fn login(args: &ArgMatches) -> anyhow::Result<()> {
+	let username = match args.get_one::<String>("username") {
+		Some(username) => username.clone(),
+		None => prompt_username()?,
+	};
+
+	if username.is_empty() {
+		return Err(anyhow!("username must not be empty"));
+	}
+
+	let password = rpassword::prompt_password(format!("Password for {username}: "))?;
+
+	let request = LoginRequest {
+		username: username.clone(),
+		password,
+		hostname: System::new().host_name(),
+	};
+
+	let response = http_client()?
+		.post(format!("{}/auth/login", server_url()))
+		.json(&request)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let response: LoginResponse = response.json()?;
+
+	client::save_session(&SavedSession {
+		server: server_url().to_owned(),
+		username: username.clone(),
+		token: response.token,
+		expires_at: response.expires_at,
+	})?;
+
+	pass!("Logged into {} as {username} ({}).", server_url(), response.role);
+	Ok(())
+}
+
+/// Tool function which ends the session saved by `servo login` and forgets it.
+pub fn logout() -> anyhow::Result<()> {
+	let Some(session) = client::saved_session()? else {
+		warn!("Not logged in.");
+		return Ok(());
+	};
+
+	// the saved token is only sent to the server it was created on
+	let response = http_client()?
+		.post(format!("{}/auth/logout", session.server))
+		.bearer_auth(&session.token)
+		.send();
+
+	match response {
+		Ok(response) if response.status().is_success() => {},
+		Ok(response) => warn!("Server did not end the session: {}", read_error(response)),
+		Err(error) => warn!("Failed to reach {} to end the session: {error}", session.server),
+	}
+
+	client::remove_session()?;
+	pass!("Logged {} out of {}.", session.username, session.server);
+
+	Ok(())
+}
+
+/// Asks for a username, suggesting the name of the user running servo.
+fn prompt_username() -> anyhow::Result<String> {
+	let suggestion = env::var("USER").or_else(|_| env::var("USERNAME")).ok();
+
+	match &suggestion {
+		Some(suggestion) => print!("Username [{suggestion}]: "),
+		None => print!("Username: "),
+	}
+
+	io::stdout().flush()?;
+
+	let mut username = String::new();
+	io::stdin().read_line(&mut username)?;
+
+	let username = username.trim();
+
+	Ok(match suggestion {
+		Some(suggestion) if username.is_empty() => suggestion,
+		_ => username.to_owned(),
+	})
+}
@@ -0,0 +1,31 @@
+use clap::ArgMatches;
+use crate::server::{clock, retention::PrunedLogs};
+use jeflog::{fail, pass};
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which maintains the request logs and audit log of the control server.
+pub fn logs(args: &ArgMatches) -> anyhow::Result<()> {
+	match args.subcommand() {
+		Some(("prune", args)) => prune(*args.get_one::<f64>("before").unwrap()),
+		_ => unreachable!("clap requires a logs subcommand"),
+	}
+}
+
+/// Prunes the logs older than the given number of seconds.
+fn prune(age: f64) -> anyhow::Result<()> {
+	let response = http_client()?
+		.delete(format!("{}/admin/logs", server_url()))
+		.query(&[("before", clock::now() - age)])
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let pruned: PrunedLogs = response.json()?;
+	pass!("Pruned {} request logs and {} audit log entries.", pruned.request_logs, pruned.audit_log);
+
+	Ok(())
+}
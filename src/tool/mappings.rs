@@ -0,0 +1,74 @@
+use anyhow::anyhow;
+use clap::ArgMatches;
+use common::comm::NodeMapping;
+use crate::{interface, server::api::{ActiveConfiguration, SetMappingsRequest}};
+use jeflog::{pass, warn};
+use reqwest::StatusCode;
+use std::collections::HashMap;
+
+use super::client::{http_client, read_error, server_url, ResponseExt};
+
+/// Tool function which manages the mappings stored on the control server.
+pub fn mappings(args: &ArgMatches) -> anyhow::Result<()> {
+	match args.subcommand() {
+		Some(("edit", args)) => edit(args.get_one::<String>("configuration").cloned()),
+		_ => unreachable!("clap requires a mappings subcommand"),
+	}
+}
+
+/// Opens a configuration's mappings in the terminal editor, pushing them to
+/// the server each time they are saved. Edits the active configuration if
+/// none is given.
+fn edit(configuration_id: Option<String>) -> anyhow::Result<()> {
+	let client = http_client()?;
+
+	let configuration_id = match configuration_id {
+		Some(configuration_id) => configuration_id,
+		None => {
+			let response = client
+				.get(format!("{}/operator/active-configuration", server_url()))
+				.send()?;
+
+			if response.status() == StatusCode::NOT_FOUND {
+				return Err(anyhow!("no configuration is active, so name the configuration to edit"));
+			}
+
+			response.or_server_error()?.json::<ActiveConfiguration>()?.configuration_id
+		},
+	};
+
+	let mut configurations: HashMap<String, Vec<NodeMapping>> = client
+		.get(format!("{}/operator/mappings", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	let mappings = configurations.remove(&configuration_id).unwrap_or_default();
+
+	let saved = interface::edit_mappings(&configuration_id, mappings, |mappings| {
+		let request = SetMappingsRequest {
+			configuration_id: configuration_id.clone(),
+			mappings: mappings.to_vec(),
+			hazard_levels: Default::default(),
+		};
+
+		let response = client
+			.post(format!("{}/operator/mappings", server_url()))
+			.json(&request)
+			.send()?;
+
+		if !response.status().is_success() {
+			return Err(anyhow!("{}", read_error(response)));
+		}
+
+		Ok(())
+	})?;
+
+	if saved {
+		pass!("Saved configuration \x1b[1m{configuration_id}\x1b[0m.");
+	} else {
+		warn!("Left configuration \x1b[1m{configuration_id}\x1b[0m unchanged.");
+	}
+
+	Ok(())
+}
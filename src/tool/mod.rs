@@ -1,19 +1,79 @@
+mod archive;
+mod backfill;
+#[cfg(feature = "server")]
+mod bench;
+mod capture;
 mod clean;
+mod client;
+#[cfg(feature = "server")]
+mod db;
 mod deploy;
 mod emulate;
 mod export;
+mod import;
 mod locate;
+mod login;
+mod logs;
+#[cfg(feature = "tui")]
+mod mappings;
+mod note;
+mod pdf;
+mod physics;
+mod ping;
+#[cfg(feature = "server")]
+mod preflight;
+mod process;
+mod promote;
+mod report;
 mod run;
+mod safe;
+mod sequence;
+#[cfg(feature = "server")]
 mod serve;
+mod sniff;
 mod sql;
+mod stats;
+mod status;
+mod sync;
+mod tail;
+mod thresholds;
 mod upload;
 
+pub use archive::archive;
+pub use backfill::backfill;
+#[cfg(feature = "server")]
+pub use bench::bench;
+pub use capture::capture;
 pub use clean::clean;
+pub use client::configure_client;
+#[cfg(feature = "server")]
+pub use db::db;
 pub use deploy::deploy;
 pub use emulate::emulate;
 pub use export::export;
+pub use import::import;
 pub use locate::locate;
+pub use login::{login, logout};
+pub use logs::logs;
+#[cfg(feature = "tui")]
+pub use mappings::mappings;
+pub use note::note;
+pub use ping::ping;
+#[cfg(feature = "server")]
+pub use preflight::preflight;
+pub use process::process;
+pub use promote::promote;
+pub use report::report;
 pub use run::run;
+pub use safe::safe;
+pub use sequence::sequence;
+#[cfg(feature = "server")]
 pub use serve::serve;
+pub use sniff::sniff;
 pub use sql::sql;
+pub use stats::stats;
+pub use status::status;
+pub use sync::sync;
+pub use tail::tail;
+pub use thresholds::thresholds;
 pub use upload::upload;
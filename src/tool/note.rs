@@ -0,0 +1,71 @@
+use clap::ArgMatches;
+use crate::server::{api::NoteRequest, note::Note};
+use jeflog::{fail, pass};
+use std::env;
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which enters a note into the shift log, or lists the shift log.
+pub fn note(args: &ArgMatches) -> anyhow::Result<()> {
+	let client = http_client()?;
+
+	if let Some(words) = args.get_many::<String>("content") {
+		let request = NoteRequest {
+			content: words.cloned().collect::<Vec<_>>().join(" "),
+			author: env::var("USER").or_else(|_| env::var("USERNAME")).ok(),
+		};
+
+		let response = client
+			.post(format!("{}/notes", server_url()))
+			.json(&request)
+			.send()?;
+
+		if !response.status().is_success() {
+			fail!("{}", read_error(response));
+			return Ok(());
+		}
+
+		let note: Note = response.json()?;
+		pass!("Entered note {} at {}.", note.note_id, time_of_day(note.created_at));
+		return Ok(());
+	}
+
+	let mut query = Vec::new();
+
+	if let Some(from) = args.get_one::<f64>("from") {
+		query.push(("from", from.to_string()));
+	}
+
+	if let Some(to) = args.get_one::<f64>("to") {
+		query.push(("to", to.to_string()));
+	}
+
+	let response = client
+		.get(format!("{}/notes", server_url()))
+		.query(&query)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let notes: Vec<Note> = response.json()?;
+
+	if notes.is_empty() {
+		println!("The shift log is empty.");
+	}
+
+	for note in notes {
+		let author = note.author.as_deref().unwrap_or("unknown");
+		println!("\x1b[1m{}\x1b[0m  {author:<12} {}", time_of_day(note.created_at), note.content);
+	}
+
+	Ok(())
+}
+
+/// Formats the time of day of a Unix timestamp, such as "14:03:27 UTC".
+fn time_of_day(timestamp: f64) -> String {
+	let seconds = timestamp.max(0.0) as u64 % 86_400;
+	format!("{:02}:{:02}:{:02} UTC", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
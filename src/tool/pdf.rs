@@ -0,0 +1,201 @@
+/// The number of characters which fit on a line, in 9-point Courier between the margins.
+pub(super) const LINE_WIDTH: usize = 96;
+
+/// The width and height of a US Letter page, in points.
+const PAGE_SIZE: (f64, f64) = (612.0, 792.0);
+
+/// The margin around the text of each page, in points.
+const MARGIN: f64 = 40.0;
+
+/// The size of the text, and the distance between the baselines of its lines, in points.
+const FONT_SIZE: f64 = 9.0;
+const LEADING: f64 = 11.0;
+
+/// The number of lines on a page, leaving two for the page number at its foot.
+const LINES_PER_PAGE: usize = ((PAGE_SIZE.1 - 2.0 * MARGIN) / LEADING) as usize - 2;
+
+/// A line of text in a document, either regular or bold.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(super) struct Line {
+	/// The text of the line, which should be at most [`LINE_WIDTH`] characters.
+	pub(super) text: String,
+
+	/// Whether the line is set in bold.
+	pub(super) bold: bool,
+}
+
+impl Line {
+	/// A line of regular text.
+	pub(super) fn regular(text: impl Into<String>) -> Self {
+		Line { text: text.into(), bold: false }
+	}
+
+	/// A line of bold text.
+	pub(super) fn bold(text: impl Into<String>) -> Self {
+		Line { text: text.into(), bold: true }
+	}
+}
+
+/// Typesets lines of monospaced text as a PDF, breaking them across numbered
+/// pages, so that tables laid out in characters stay aligned when printed.
+///
+/// Only the standard Courier fonts are used, so nothing is embedded. Characters
+/// outside of Latin-1 are replaced, since those fonts cannot show them.
+pub(super) fn typeset(lines: &[Line]) -> Vec<u8> {
+	let pages = lines.chunks(LINES_PER_PAGE).collect::<Vec<_>>();
+	let pages = if pages.is_empty() { vec![&[][..]] } else { pages };
+
+	// the catalog, the page tree, and the two fonts come first, then each page and its contents
+	let mut objects = vec![
+		b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+		format!(
+			"<< /Type /Pages /Kids [{}] /Count {} >>",
+			(0..pages.len()).map(|page| format!("{} 0 R", 5 + 2 * page)).collect::<Vec<_>>().join(" "),
+			pages.len(),
+		).into_bytes(),
+		b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+		b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+	];
+
+	for (index, page) in pages.iter().enumerate() {
+		let mut content = format!("BT\n{LEADING} TL\n{MARGIN} {} Td\n", PAGE_SIZE.1 - MARGIN - FONT_SIZE).into_bytes();
+
+		for line in page.iter() {
+			content.extend_from_slice(format!("/F{} {FONT_SIZE} Tf (", if line.bold { 2 } else { 1 }).as_bytes());
+			content.extend(encode(&line.text));
+			content.extend_from_slice(b") Tj T*\n");
+		}
+
+		// the page number is centered beneath the text
+		let footer = format!("Page {} of {}", index + 1, pages.len());
+
+		content.extend_from_slice(format!(
+			"ET\nBT\n/F1 {FONT_SIZE} Tf {:.1} {MARGIN} Td ({footer}) Tj\nET\n",
+			(PAGE_SIZE.0 - footer.len() as f64 * FONT_SIZE * 0.6) / 2.0,
+		).as_bytes());
+
+		objects.push(format!(
+			"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+			PAGE_SIZE.0,
+			PAGE_SIZE.1,
+			6 + 2 * index,
+		).into_bytes());
+
+		let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+		stream.extend(content);
+		stream.extend_from_slice(b"\nendstream");
+		objects.push(stream);
+	}
+
+	let mut document = b"%PDF-1.4\n".to_vec();
+	let mut offsets = Vec::with_capacity(objects.len());
+
+	for (index, object) in objects.iter().enumerate() {
+		offsets.push(document.len());
+		document.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+		document.extend_from_slice(object);
+		document.extend_from_slice(b"\nendobj\n");
+	}
+
+	let xref = document.len();
+	document.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+
+	for offset in offsets {
+		document.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+	}
+
+	document.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", objects.len() + 1).as_bytes());
+	document
+}
+
+/// Encodes text as a PDF string in Latin-1, escaping the characters which delimit it.
+fn encode(text: &str) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(text.len());
+
+	for character in text.chars() {
+		match character {
+			'(' | ')' | '\\' => bytes.extend_from_slice(&[b'\\', character as u8]),
+			'\u{2012}'..='\u{2015}' | '\u{2212}' => bytes.push(b'-'),
+			' '..='~' | '\u{a0}'..='\u{ff}' => bytes.push(character as u32 as u8),
+			_ => bytes.push(b'?'),
+		}
+	}
+
+	bytes
+}
+
+/// Wraps text into lines of at most a number of characters, breaking between
+/// words where possible and within them where not.
+pub(super) fn wrap(text: &str, width: usize) -> Vec<String> {
+	let width = width.max(1);
+	let mut lines = Vec::new();
+
+	for paragraph in text.lines() {
+		let mut line = String::new();
+
+		for word in paragraph.split_whitespace() {
+			let mut word = word.chars().collect::<Vec<_>>();
+			let length = line.chars().count();
+
+			if length > 0 && length + 1 + word.len() <= width {
+				line.push(' ');
+				line.extend(&word);
+				continue;
+			}
+
+			if length > 0 {
+				lines.push(std::mem::take(&mut line));
+			}
+
+			while word.len() > width {
+				lines.push(word.drain(..width).collect());
+			}
+
+			line.extend(word);
+		}
+
+		lines.push(line);
+	}
+
+	if lines.is_empty() {
+		lines.push(String::new());
+	}
+
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cross_references_point_at_their_objects() {
+		let lines = (0..LINES_PER_PAGE + 1)
+			.map(|index| if index == 0 { Line::bold("Run sheet (T−0)") } else { Line::regular(format!("line {index}")) })
+			.collect::<Vec<_>>();
+
+		let document = typeset(&lines);
+		let text = String::from_utf8_lossy(&document);
+
+		assert!(text.starts_with("%PDF-1.4\n") && text.ends_with("%%EOF\n"));
+		assert!(text.contains("/Count 2") && text.contains("(Page 2 of 2)"));
+		assert!(text.contains("(Run sheet \\(T-0\\)) Tj"));
+
+		let xref = text.rfind("\nxref\n").unwrap() + 1;
+		let startxref = text[text.rfind("startxref\n").unwrap() + 10..].lines().next().unwrap();
+		assert_eq!(startxref.parse::<usize>().unwrap(), xref);
+
+		// each entry after the free one is the offset of the object numbered after it
+		for (index, entry) in text[xref..].lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+			let offset = entry[..10].parse::<usize>().unwrap();
+			assert!(text[offset..].starts_with(&format!("{} 0 obj\n", index + 1)));
+		}
+	}
+
+	#[test]
+	fn text_is_wrapped_between_words() {
+		assert_eq!(wrap("the quick brown fox", 9), ["the quick", "brown fox"]);
+		assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+		assert_eq!(wrap("", 4), [""]);
+	}
+}
@@ -0,0 +1,361 @@
+use common::comm::{CompositeValveState, Measurement, Unit, ValveState, VehicleState};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The ratio of specific heats of the gas in the tanks, which is that of nitrogen.
+const HEAT_CAPACITY_RATIO: f64 = 1.4;
+
+/// The name a valve uses as its downstream side to vent to the atmosphere.
+const AMBIENT: &str = "ambient";
+
+/// The largest fraction of a tank's pressure difference with its neighbors
+/// which may flow in one integration step, above which a step is subdivided.
+const MAX_STEP_FRACTION: f64 = 0.2;
+
+/// A lumped model of the tanks of a vehicle and the valves between them, in
+/// which tanks fill and blow down as valves are opened and closed. Pressures
+/// are absolute, so a vented tank settles at the pressure of the atmosphere.
+///
+/// Flow through an open valve is proportional to the pressure difference
+/// across it. Gas entering a tank carries its energy with it, so a filling
+/// tank warms while a draining one cools as its gas expands, and each relaxes
+/// back to ambient temperature over time.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicsModel {
+	/// The absolute pressure of the atmosphere, in psi, which tanks vent to.
+	pub ambient_psi: f64,
+
+	/// The temperature of the atmosphere and the tank walls, in Kelvin.
+	pub ambient_kelvin: f64,
+
+	/// The number of seconds over which a tank's temperature relaxes most of
+	/// the way back to ambient.
+	pub thermal_seconds: f64,
+
+	/// The standard deviation of noise added to each pressure reading, in psi.
+	pub noise_psi: f64,
+
+	/// The tanks of the vehicle, keyed by name.
+	#[serde(default)]
+	pub tanks: BTreeMap<String, TankModel>,
+
+	/// The valves between tanks, keyed by the name the valve is commanded by.
+	#[serde(default)]
+	pub valves: BTreeMap<String, ValveModel>,
+}
+
+/// A volume of gas whose pressure and temperature are reported by sensors.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TankModel {
+	/// The sensor reporting the pressure of the tank.
+	pub pressure_sensor: String,
+
+	/// The sensor reporting the temperature of the tank, if it has one.
+	pub temperature_sensor: Option<String>,
+
+	/// The volume of the tank in liters. Larger tanks fill and drain more slowly.
+	pub volume_liters: f64,
+
+	/// The absolute pressure of the tank when the emulator starts, in psi, or
+	/// that of the atmosphere if not given.
+	pub initial_psi: Option<f64>,
+
+	/// Whether the tank is held at its initial pressure, such as a regulated supply.
+	#[serde(default)]
+	pub regulated: bool,
+}
+
+/// A valve between two tanks, or between a tank and the atmosphere.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValveModel {
+	/// The tank on the upstream side of the valve.
+	pub upstream: String,
+
+	/// The tank on the downstream side of the valve, or `ambient` for a vent.
+	pub downstream: String,
+
+	/// The flow through the valve when open, in liter-psi per second for each
+	/// psi of difference across it.
+	pub conductance: f64,
+
+	/// Whether the valve is open when the emulator starts.
+	#[serde(default)]
+	pub open: bool,
+}
+
+impl Default for PhysicsModel {
+	/// A supply which fills a tank through `SWV`, and a vent `BBV` which blows it
+	/// down, reported through the sensors the plain flight emulator sends.
+	fn default() -> Self {
+		PhysicsModel {
+			ambient_psi: 14.7,
+			ambient_kelvin: 293.15,
+			thermal_seconds: 30.0,
+			noise_psi: 0.5,
+			tanks: BTreeMap::from([
+				("supply".to_owned(), TankModel {
+					pressure_sensor: "WTPT".to_owned(),
+					temperature_sensor: None,
+					volume_liters: 50.0,
+					initial_psi: Some(1000.0),
+					regulated: true,
+				}),
+				("tank".to_owned(), TankModel {
+					pressure_sensor: "KBPT".to_owned(),
+					temperature_sensor: Some("KBTC".to_owned()),
+					volume_liters: 20.0,
+					initial_psi: None,
+					regulated: false,
+				}),
+			]),
+			valves: BTreeMap::from([
+				("SWV".to_owned(), ValveModel {
+					upstream: "supply".to_owned(),
+					downstream: "tank".to_owned(),
+					conductance: 2.0,
+					open: false,
+				}),
+				("BBV".to_owned(), ValveModel {
+					upstream: "tank".to_owned(),
+					downstream: AMBIENT.to_owned(),
+					conductance: 4.0,
+					open: false,
+				}),
+			]),
+		}
+	}
+}
+
+impl PhysicsModel {
+	/// Loads a model from a TOML file, checking that its valves connect known tanks.
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let model: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+		for (name, valve) in &model.valves {
+			for side in [&valve.upstream, &valve.downstream] {
+				if side != AMBIENT && !model.tanks.contains_key(side) {
+					anyhow::bail!("valve {name} connects to unknown tank '{side}'");
+				}
+			}
+		}
+
+		for (name, tank) in &model.tanks {
+			if !(tank.volume_liters > 0.0 && tank.volume_liters.is_finite()) {
+				anyhow::bail!("tank {name} must have a positive volume");
+			}
+		}
+
+		Ok(model)
+	}
+}
+
+/// The state of a [`PhysicsModel`] as the emulator runs.
+#[derive(Clone, Debug)]
+pub struct PhysicsState {
+	model: PhysicsModel,
+	pressures: BTreeMap<String, f64>,
+	temperatures: BTreeMap<String, f64>,
+	open: BTreeMap<String, bool>,
+}
+
+impl PhysicsState {
+	/// Starts a model from its initial pressures and valve states.
+	pub fn new(model: PhysicsModel) -> Self {
+		let pressures = model.tanks
+			.iter()
+			.map(|(name, tank)| (name.clone(), tank.initial_psi.unwrap_or(model.ambient_psi)))
+			.collect();
+
+		let temperatures = model.tanks
+			.keys()
+			.map(|name| (name.clone(), model.ambient_kelvin))
+			.collect();
+
+		let open = model.valves
+			.iter()
+			.map(|(name, valve)| (name.clone(), valve.open))
+			.collect();
+
+		PhysicsState { model, pressures, temperatures, open }
+	}
+
+	/// Opens or closes a valve, returning whether the model has a valve of that name.
+	pub fn set_valve(&mut self, valve: &str, open: bool) -> bool {
+		match self.open.get_mut(valve) {
+			Some(state) => {
+				*state = open;
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// The pressure of a tank, or of the atmosphere.
+	fn pressure(&self, tank: &str) -> f64 {
+		self.pressures.get(tank).copied().unwrap_or(self.model.ambient_psi)
+	}
+
+	/// The temperature of a tank, or of the atmosphere.
+	fn temperature(&self, tank: &str) -> f64 {
+		self.temperatures.get(tank).copied().unwrap_or(self.model.ambient_kelvin)
+	}
+
+	/// Advances the model by a number of seconds.
+	pub fn step(&mut self, seconds: f64) {
+		// the fastest tank to equalize bounds the step which keeps integration stable
+		let fastest = self.model.valves
+			.values()
+			.flat_map(|valve| {
+				[&valve.upstream, &valve.downstream]
+					.into_iter()
+					.filter_map(|side| self.model.tanks.get(side))
+					.map(move |tank| HEAT_CAPACITY_RATIO * valve.conductance / tank.volume_liters)
+			})
+			.fold(0.0, f64::max);
+
+		let substeps = (seconds * fastest / MAX_STEP_FRACTION).ceil().clamp(1.0, 1000.0);
+		let dt = seconds / substeps;
+
+		for _ in 0..substeps as usize {
+			self.substep(dt);
+		}
+	}
+
+	fn substep(&mut self, dt: f64) {
+		// the change in each tank's pressure-volume and in its amount of gas,
+		// which is in liter-psi per Kelvin and so proportional to moles
+		let mut changes = BTreeMap::<&str, (f64, f64)>::new();
+
+		for (name, valve) in &self.model.valves {
+			if !self.open[name] {
+				continue;
+			}
+
+			// liter-psi passing from upstream to downstream, which is negative if it flows back
+			let flow = valve.conductance * (self.pressure(&valve.upstream) - self.pressure(&valve.downstream)) * dt;
+			let source = if flow >= 0.0 { &valve.upstream } else { &valve.downstream };
+
+			// gas carries its enthalpy with it, so the tank it enters gains more energy than its flow
+			let energy = HEAT_CAPACITY_RATIO * flow;
+			let amount = flow / self.temperature(source);
+
+			let upstream = changes.entry(&valve.upstream).or_default();
+			upstream.0 -= energy;
+			upstream.1 -= amount;
+
+			let downstream = changes.entry(&valve.downstream).or_default();
+			downstream.0 += energy;
+			downstream.1 += amount;
+		}
+
+		let relaxation = (dt / self.model.thermal_seconds.max(f64::EPSILON)).min(1.0);
+
+		for (name, tank) in &self.model.tanks {
+			if tank.regulated {
+				continue;
+			}
+
+			let pressure = self.pressures[name];
+			let temperature = self.temperatures[name];
+			let (energy, amount) = changes.get(name.as_str()).copied().unwrap_or_default();
+
+			let gas = pressure * tank.volume_liters / temperature + amount;
+			let pressure = (pressure + energy / tank.volume_liters).max(0.0);
+
+			let temperature = if gas > f64::EPSILON {
+				pressure * tank.volume_liters / gas
+			} else {
+				temperature
+			};
+
+			// the walls then warm or cool the gas at constant volume, which moves its pressure with it
+			let relaxed = temperature + (self.model.ambient_kelvin - temperature) * relaxation;
+
+			self.pressures.insert(name.clone(), pressure * relaxed / temperature);
+			self.temperatures.insert(name.clone(), relaxed);
+		}
+	}
+
+	/// Writes the readings of every sensor and the state of every valve into a vehicle state.
+	pub fn write(&self, vehicle_state: &mut VehicleState) {
+		for (name, tank) in &self.model.tanks {
+			let noise = self.model.noise_psi * gaussian();
+
+			vehicle_state.sensor_readings.insert(tank.pressure_sensor.clone(), Measurement {
+				value: self.pressures[name] + noise,
+				unit: Unit::Psi,
+			});
+
+			if let Some(sensor) = &tank.temperature_sensor {
+				vehicle_state.sensor_readings.insert(sensor.clone(), Measurement {
+					value: self.temperatures[name],
+					unit: Unit::Kelvin,
+				});
+			}
+		}
+
+		for (name, open) in &self.open {
+			let state = if *open { ValveState::Open } else { ValveState::Closed };
+			vehicle_state.valve_states.insert(name.clone(), CompositeValveState { commanded: state, actual: state });
+
+			vehicle_state.sensor_readings.insert(format!("{name}_V"), Measurement { value: if *open { 24.0 } else { 0.0 }, unit: Unit::Volts });
+			vehicle_state.sensor_readings.insert(format!("{name}_I"), Measurement { value: if *open { 0.1 } else { 0.0 }, unit: Unit::Amps });
+		}
+	}
+}
+
+/// A sample of the standard normal distribution, by the Box-Muller transform.
+fn gaussian() -> f64 {
+	let uniform = 1.0 - rand::random::<f64>();
+	(-2.0 * uniform.ln()).sqrt() * (std::f64::consts::TAU * rand::random::<f64>()).cos()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tanks_fill_and_blow_down_through_open_valves() {
+		let model = PhysicsModel { noise_psi: 0.0, ..PhysicsModel::default() };
+		let mut physics = PhysicsState::new(model);
+
+		// nothing moves while every valve is closed
+		physics.step(10.0);
+		assert_eq!(physics.pressure("tank"), 14.7);
+
+		assert!(physics.set_valve("SWV", true));
+		assert!(!physics.set_valve("NOPE", true));
+
+		for _ in 0..300 {
+			physics.step(0.1);
+		}
+
+		// filling compresses the gas in the tank, which heats it
+		let filled = physics.pressure("tank");
+		assert!(filled > 900.0 && filled < 1000.0, "tank filled to {filled} psi");
+		assert!(physics.temperatures["tank"] > 293.15);
+		assert_eq!(physics.pressure("supply"), 1000.0);
+
+		// once the tank has settled back to ambient temperature, it is vented
+		physics.set_valve("SWV", false);
+		physics.step(300.0);
+		physics.set_valve("BBV", true);
+		physics.step(1.0);
+
+		// blowing down expands the gas, which cools it
+		let vented = physics.pressure("tank");
+		assert!(vented < filled && vented > 14.7, "tank vented to {vented} psi");
+		assert!(physics.temperatures["tank"] < 293.15);
+
+		physics.step(300.0);
+		assert!((physics.pressure("tank") - 14.7).abs() < 0.1);
+
+		let mut vehicle_state = VehicleState::new();
+		physics.write(&mut vehicle_state);
+		assert_eq!(vehicle_state.valve_states["BBV"].actual, ValveState::Open);
+		assert_eq!(vehicle_state.sensor_readings["KBTC"].unit, Unit::Kelvin);
+	}
+}
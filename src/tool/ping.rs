@@ -0,0 +1,169 @@
+use clap::ArgMatches;
+use std::{
+	net::{IpAddr, SocketAddr, TcpStream},
+	process::Command,
+	thread,
+	time::{Duration, Instant},
+};
+
+use super::locate::{known_hostnames, resolve};
+
+/// The ports servo listens on, checked on servers and on hosts given by name or address.
+const SERVO_PORTS: [(&str, u16); 2] = [("servo", 7200), ("flight", 5025)];
+
+/// The port deployments are made over, checked on the vehicle computers.
+const SSH_PORTS: [(&str, u16); 1] = [("ssh", 22)];
+
+/// The reachability of a host, as measured by ICMP echoes and TCP connections.
+struct PingReport {
+	hostname: String,
+	ip: Option<IpAddr>,
+
+	/// The round trip times of the echoes which were answered, in milliseconds,
+	/// or `None` if the system `ping` could not be run.
+	echoes: Option<Vec<f64>>,
+
+	/// The time taken to connect to each port, or `None` if it could not be connected to.
+	ports: Vec<(&'static str, u16, Option<Duration>)>,
+}
+
+/// The ports worth checking on a host, based on the subsystem its hostname belongs to.
+fn ports_of(hostname: &str) -> &'static [(&'static str, u16)] {
+	match hostname.split('-').next() {
+		Some("flight" | "ground") => &SSH_PORTS,
+		Some("gui" | "sam") => &[],
+		_ => &SERVO_PORTS,
+	}
+}
+
+/// Sends echoes to a host with the system `ping`, since sending ICMP directly
+/// requires privileges, and returns the round trip times of those answered.
+fn echo(ip: IpAddr, count: u32, timeout: Duration) -> Option<Vec<f64>> {
+	let mut command = Command::new("ping");
+
+	if cfg!(target_family = "windows") {
+		command.args(["-n", &count.to_string(), "-w", &timeout.as_millis().to_string()]);
+	} else if cfg!(target_os = "macos") {
+		command.args(["-c", &count.to_string(), "-W", &timeout.as_millis().to_string()]);
+	} else {
+		command.args(["-c", &count.to_string(), "-W", &timeout.as_secs().max(1).to_string()]);
+	}
+
+	let output = command.arg(ip.to_string()).output().ok()?;
+
+	Some(String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.filter_map(round_trip_time)
+		.collect())
+}
+
+/// Parses the round trip time from a line of `ping` output, such as
+/// `64 bytes from 10.0.0.2: icmp_seq=1 ttl=64 time=0.412 ms` or `time<1ms`.
+fn round_trip_time(line: &str) -> Option<f64> {
+	let start = line.find("time=").or_else(|| line.find("time<"))? + 5;
+
+	let digits = line[start..]
+		.chars()
+		.take_while(|character| character.is_ascii_digit() || *character == '.')
+		.collect::<String>();
+
+	digits.parse().ok()
+}
+
+/// Measures the reachability of a host.
+fn ping_host(hostname: String, ports: &[(&'static str, u16)], count: u32, timeout: Duration) -> PingReport {
+	let Some(ip) = hostname.parse().ok().or_else(|| resolve(&hostname)) else {
+		return PingReport { hostname, ip: None, echoes: None, ports: Vec::new() };
+	};
+
+	let ports = ports
+		.iter()
+		.map(|&(name, port)| {
+			let started_at = Instant::now();
+			let connected = TcpStream::connect_timeout(&SocketAddr::new(ip, port), timeout).is_ok();
+			(name, port, connected.then(|| started_at.elapsed()))
+		})
+		.collect();
+
+	PingReport { echoes: echo(ip, count, timeout), hostname, ip: Some(ip), ports }
+}
+
+/// Formats a column of the table, colored green if the check passed and red if it did not.
+fn cell(text: String, passed: bool) -> String {
+	let color = if passed { 32 } else { 31 };
+	format!("\x1b[{color}m{text:<16}\x1b[0m")
+}
+
+/// Tool function which measures the reachability of hosts over ICMP, along
+/// with the responsiveness of the servo and flight ports, and prints a table
+/// of latency and loss.
+pub fn ping(args: &ArgMatches) -> anyhow::Result<()> {
+	let count = *args.get_one::<u32>("count").unwrap();
+	let timeout = Duration::from_secs_f64(*args.get_one::<f64>("timeout").unwrap());
+
+	let hostnames = match args.get_one::<String>("host") {
+		Some(host) => vec![host.clone()],
+		None => known_hostnames(None)?,
+	};
+
+	// the hosts are pinged at once, since each waits on its own echoes
+	let reports = thread::scope(|scope| {
+		let handles = hostnames
+			.into_iter()
+			.map(|hostname| {
+				let ports = ports_of(&hostname);
+				scope.spawn(move || ping_host(hostname, ports, count, timeout))
+			})
+			.collect::<Vec<_>>();
+
+		handles
+			.into_iter()
+			.filter_map(|handle| handle.join().ok())
+			.collect::<Vec<_>>()
+	});
+
+	println!("\x1b[1m{:<20} {:<16} {:<16} {:<16} ports\x1b[0m", "host", "address", "latency", "loss");
+
+	for report in reports {
+		let Some(ip) = report.ip else {
+			println!("{:<20} {}", report.hostname, cell("unresolved".to_owned(), false));
+			continue;
+		};
+
+		let (latency, loss) = match &report.echoes {
+			Some(echoes) if !echoes.is_empty() => {
+				let mean = echoes.iter().sum::<f64>() / echoes.len() as f64;
+				let loss = 100.0 * (1.0 - echoes.len() as f64 / count as f64);
+				(cell(format!("{mean:.2} ms"), true), cell(format!("{loss:.0}%"), loss == 0.0))
+			},
+			Some(_) => (cell("no reply".to_owned(), false), cell("100%".to_owned(), false)),
+			None => (cell("ping unavailable".to_owned(), false), cell("-".to_owned(), false)),
+		};
+
+		let ports = report.ports
+			.iter()
+			.map(|(name, port, connected)| match connected {
+				Some(elapsed) => format!("\x1b[32m{name}:{port} {:.2} ms\x1b[0m", elapsed.as_secs_f64() * 1000.0),
+				None => format!("\x1b[31m{name}:{port} closed\x1b[0m"),
+			})
+			.collect::<Vec<_>>()
+			.join("  ");
+
+		println!("{:<20} {:<16} {latency} {loss} {ports}", report.hostname, ip.to_string());
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip_times_are_parsed_from_each_platform() {
+		assert_eq!(round_trip_time("64 bytes from 10.0.0.2: icmp_seq=1 ttl=64 time=0.412 ms"), Some(0.412));
+		assert_eq!(round_trip_time("Reply from 10.0.0.2: bytes=32 time=3ms TTL=128"), Some(3.0));
+		assert_eq!(round_trip_time("Reply from 10.0.0.2: bytes=32 time<1ms TTL=128"), Some(1.0));
+		assert_eq!(round_trip_time("Request timeout for icmp_seq 0"), None);
+	}
+}
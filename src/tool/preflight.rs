@@ -0,0 +1,22 @@
+use crate::server::preflight::{self, Verdict};
+use clap::ArgMatches;
+use std::{path::Path, process};
+
+/// Tool function which runs every preflight check and prints a go/no-go report.
+///
+/// Exits with a nonzero status on a no-go, so that it may gate scripts.
+pub fn preflight(servo_dir: &Path, args: &ArgMatches) -> anyhow::Result<()> {
+	let report = preflight::run(servo_dir);
+
+	if args.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&report)?);
+	} else {
+		report.log();
+	}
+
+	if report.verdict() == Verdict::NoGo {
+		process::exit(1);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,77 @@
+use clap::ArgMatches;
+use crate::server::recording;
+use jeflog::{fail, pass, warn};
+use std::{fs, path::PathBuf, process};
+
+/// The number of differences from golden output printed before the rest are only counted.
+const DIFFERENCES_SHOWN: usize = 20;
+
+/// Tool function which replays a recorded capture through the telemetry
+/// pipeline offline, writing or comparing against golden output.
+///
+/// Exits with a nonzero status if the output differs from the golden output,
+/// so that it may gate pipeline refactors.
+pub fn process(args: &ArgMatches) -> anyhow::Result<()> {
+	let capture_path = args.get_one::<PathBuf>("capture_path").unwrap();
+	let port = *args.get_one::<u16>("port").unwrap();
+	let tolerance = *args.get_one::<f64>("tolerance").unwrap();
+
+	let recording = recording::read(&fs::read(capture_path)?, port)?;
+
+	if recording.skipped_fragments > 0 {
+		warn!("Skipped {} fragmented datagrams, which cannot be replayed.", recording.skipped_fragments);
+	}
+
+	let (snapshots, summary) = recording::replay(&recording.frames)?;
+
+	println!(
+		"Replayed {} frames into {} snapshots ({} lost, {} out of order, {} restarts, {} malformed).",
+		summary.frames,
+		summary.snapshots,
+		summary.lost,
+		summary.out_of_order,
+		summary.restarts,
+		summary.malformed,
+	);
+
+	let lines = snapshots.iter().map(recording::golden_line).collect::<Vec<_>>();
+
+	if let Some(output_path) = args.get_one::<PathBuf>("output") {
+		let output = lines
+			.iter()
+			.map(|line| line.to_string() + "\n")
+			.collect::<String>();
+
+		fs::write(output_path, output)?;
+		pass!("Wrote golden output to \x1b[1m{}\x1b[0m.", output_path.display());
+	}
+
+	let Some(golden_path) = args.get_one::<PathBuf>("golden") else {
+		return Ok(());
+	};
+
+	let golden = fs::read_to_string(golden_path)?
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(serde_json::from_str)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let differences = recording::compare(&lines, &golden, tolerance);
+
+	if differences.is_empty() {
+		pass!("Output matches golden output at \x1b[1m{}\x1b[0m.", golden_path.display());
+		return Ok(());
+	}
+
+	for difference in differences.iter().take(DIFFERENCES_SHOWN) {
+		println!("  {difference}");
+	}
+
+	if differences.len() > DIFFERENCES_SHOWN {
+		println!("  ...and {} more", differences.len() - DIFFERENCES_SHOWN);
+	}
+
+	let plural = if differences.len() == 1 { "" } else { "s" };
+	fail!("Output has {} difference{plural} from golden output.", differences.len());
+	process::exit(1);
+}
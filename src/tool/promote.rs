@@ -0,0 +1,22 @@
+use crate::server::api::StandbyStatus;
+use jeflog::{fail, pass};
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which promotes the standby server running on this machine to
+/// primary, so that it takes over the flight computer and fences the old primary.
+pub fn promote() -> anyhow::Result<()> {
+	let response = http_client()?
+		.post(format!("{}/standby/promote", server_url()))
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let status: StandbyStatus = response.json()?;
+	pass!("Promoted to primary at term {}.", status.term);
+
+	Ok(())
+}
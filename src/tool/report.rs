@@ -0,0 +1,577 @@
+use anyhow::anyhow;
+use clap::ArgMatches;
+use crate::server::report::{ChannelQuality, QualityReport, RunSheet};
+use jeflog::{fail, pass};
+use std::{fmt::Write, fs, path::PathBuf, time::Duration};
+
+use super::{client::{http_client, read_error, server_url}, pdf::{self, Line}};
+
+/// The width and height of each channel's thumbnail plot, in pixels.
+const PLOT_SIZE: (f64, f64) = (240.0, 48.0);
+
+/// The title of data quality reports.
+const TITLE: &str = "Data quality report";
+
+/// The title of run sheets.
+const RUN_SHEET_TITLE: &str = "Run sheet";
+
+/// The narrowest a column of a table in a PDF is squeezed to.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// A cell of a table in the report.
+enum Cell {
+	/// Plain text.
+	Text(String),
+
+	/// An SVG image, inlined in HTML and embedded as a data URI in Markdown.
+	Plot(String),
+}
+
+/// A titled table in the report.
+struct Section {
+	title: &'static str,
+	headers: &'static [&'static str],
+	rows: Vec<Vec<Cell>>,
+	empty: &'static str,
+}
+
+/// Tool function which generates a data quality report of a session or time
+/// range, written as Markdown or HTML depending on the output file's extension.
+pub fn report(args: &ArgMatches) -> anyhow::Result<()> {
+	let query = target_query(args.get_one::<String>("target").unwrap())?;
+	let output = args.get_one::<PathBuf>("output");
+
+	if args.get_flag("run_sheet") {
+		return run_sheet(&query, output, args.get_one::<f64>("t_zero").copied());
+	}
+
+	let response = http_client()?
+		.get(format!("{}/data/report", server_url()))
+		.query(&query)
+		.timeout(Duration::from_secs(3600))
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let report: QualityReport = response.json()?;
+	let html = output.is_some_and(|path| path.extension().is_some_and(|extension| extension == "html" || extension == "htm"));
+
+	let document = if html {
+		render_html(TITLE, &summary(&report), sections(&report))?
+	} else {
+		render_markdown(TITLE, &summary(&report), sections(&report))?
+	};
+
+	match output {
+		Some(path) => {
+			fs::write(path, document)?;
+			pass!("Wrote the report of {} channels to {}.", report.channels.len(), path.display());
+		},
+		None => print!("{document}"),
+	}
+
+	Ok(())
+}
+
+/// Generates the run sheet of a session or time range, written as a PDF, HTML,
+/// or Markdown depending on the output file's extension.
+fn run_sheet(query: &[(&str, String)], output: Option<&PathBuf>, t_zero: Option<f64>) -> anyhow::Result<()> {
+	let response = http_client()?
+		.get(format!("{}/data/run-sheet", server_url()))
+		.query(query)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let sheet: RunSheet = response.json()?;
+	let t_zero = t_zero.unwrap_or(sheet.from);
+
+	let summary = format!(
+		"Covers {:.1} s from {} {} to {} UTC, with T-0 at {} UTC. {} sequences were run, {} alerts were raised, and {} notes were entered.",
+		sheet.to - sheet.from,
+		date(sheet.from),
+		utc(sheet.from),
+		utc(sheet.to),
+		utc(t_zero),
+		sheet.sequences.len(),
+		sheet.alerts.len(),
+		sheet.notes.len(),
+	);
+
+	let extension = output.and_then(|path| path.extension()).and_then(|extension| extension.to_str());
+	let sections = run_sheet_sections(&sheet, t_zero);
+
+	let document = match extension {
+		Some("pdf") => render_pdf(RUN_SHEET_TITLE, &summary, sections),
+		Some("html" | "htm") => render_html(RUN_SHEET_TITLE, &summary, sections)?.into_bytes(),
+		_ => render_markdown(RUN_SHEET_TITLE, &summary, sections)?.into_bytes(),
+	};
+
+	match output {
+		Some(path) => {
+			fs::write(path, document)?;
+			pass!("Wrote the run sheet of {} events to {}.", sheet.timeline.len(), path.display());
+		},
+		None => print!("{}", String::from_utf8_lossy(&document)),
+	}
+
+	Ok(())
+}
+
+/// Parses a session ID or a range of Unix timestamps such as
+/// `1700000000..1700003600` into the query of a report or archive.
+pub(super) fn target_query(target: &str) -> anyhow::Result<Vec<(&'static str, String)>> {
+	match target.split_once("..") {
+		Some((from, to)) => {
+			let mut query = Vec::new();
+
+			for (name, bound) in [("from", from), ("to", to)] {
+				if !bound.is_empty() {
+					let bound = bound
+						.parse::<f64>()
+						.map_err(|_| anyhow!("'{bound}' is not a Unix timestamp"))?;
+
+					query.push((name, bound.to_string()));
+				}
+			}
+
+			Ok(query)
+		},
+		None => {
+			let session = target
+				.parse::<i64>()
+				.map_err(|_| anyhow!("expected a session ID or a range of Unix timestamps such as 1700000000..1700003600"))?;
+
+			Ok(vec![("session", session.to_string())])
+		},
+	}
+}
+
+/// The sections of the report, in the order they are written.
+fn sections(report: &QualityReport) -> Vec<Section> {
+	let at = |timestamp: f64| format!("T+{:.1} s", timestamp - report.from);
+
+	let channels = report.channels
+		.iter()
+		.map(|channel| vec![
+			Cell::Text(channel.channel.clone()),
+			Cell::Text(channel.unit.to_string()),
+			Cell::Text(format!("{:.1}%", channel.coverage * 100.0)),
+			Cell::Text(channel.dropouts.to_string()),
+			Cell::Text(format!("{:.1} s", channel.longest_dropout_seconds)),
+			Cell::Text(format!("{:.3}", channel.min)),
+			Cell::Text(format!("{:.3}", channel.max)),
+			Cell::Text(format!("{:.3}", channel.mean)),
+			Cell::Plot(plot(channel)),
+		])
+		.collect();
+
+	let silences = report.silences
+		.iter()
+		.map(|silence| vec![
+			Cell::Text(at(silence.started_at)),
+			Cell::Text(at(silence.ended_at)),
+			Cell::Text(format!("{:.1} s", silence.ended_at - silence.started_at)),
+		])
+		.collect();
+
+	let gaps = report.gaps
+		.iter()
+		.map(|gap| vec![
+			Cell::Text(at(gap.started_at)),
+			Cell::Text(format!("{}–{}", gap.first_missing, gap.last_missing)),
+			Cell::Text((gap.last_missing - gap.first_missing + 1).to_string()),
+		])
+		.collect();
+
+	let alerts = report.alerts
+		.iter()
+		.map(|alert| vec![
+			Cell::Text(at(alert.raised_at)),
+			Cell::Text(alert.severity.to_string()),
+			Cell::Text(alert.rule.clone()),
+			Cell::Text(alert.message.clone()),
+			Cell::Text(alert.causes.join("; ")),
+		])
+		.collect();
+
+	let sequences = report.sequences
+		.iter()
+		.map(|run| vec![
+			Cell::Text(at(run.at)),
+			Cell::Text(run.username.clone().unwrap_or_default()),
+			Cell::Text(run.detail.clone()),
+		])
+		.collect();
+
+	vec![
+		Section {
+			title: "Channels",
+			headers: &["Channel", "Unit", "Coverage", "Dropouts", "Longest dropout", "Min", "Max", "Mean", "Plot"],
+			rows: channels,
+			empty: "No channels were logged.",
+		},
+		Section {
+			title: "Logging silences",
+			headers: &["From", "To", "Duration"],
+			rows: silences,
+			empty: "Snapshots were logged throughout.",
+		},
+		Section {
+			title: "Telemetry gaps",
+			headers: &["At", "Frames", "Count"],
+			rows: gaps,
+			empty: "No telemetry frames were lost.",
+		},
+		Section {
+			title: "Alerts",
+			headers: &["At", "Severity", "Rule", "Message", "Probable cause"],
+			rows: alerts,
+			empty: "No alerts were raised.",
+		},
+		Section {
+			title: "Sequences",
+			headers: &["At", "User", "Detail"],
+			rows: sequences,
+			empty: "No sequences were run.",
+		},
+	]
+}
+
+/// The sections of a run sheet, in the order they are written, with times
+/// given both on the clock and relative to T-0.
+fn run_sheet_sections(sheet: &RunSheet, t_zero: f64) -> Vec<Section> {
+	let at = |timestamp: f64| Cell::Text(format!("{} ({})", utc(timestamp), countdown(timestamp - t_zero)));
+
+	let timeline = sheet.timeline
+		.iter()
+		.map(|event| vec![
+			at(event.at),
+			Cell::Text(format!("{:?}", event.kind).to_lowercase()),
+			Cell::Text(event.username.clone().unwrap_or_default()),
+			Cell::Text(event.detail.clone()),
+		])
+		.collect();
+
+	let sequences = sheet.sequences
+		.iter()
+		.map(|run| vec![
+			at(run.at),
+			Cell::Text(run.username.clone().unwrap_or_default()),
+			Cell::Text(run.detail.clone()),
+		])
+		.collect();
+
+	let alerts = sheet.alerts
+		.iter()
+		.map(|alert| vec![
+			at(alert.raised_at),
+			Cell::Text(alert.severity.to_string()),
+			Cell::Text(alert.rule.clone()),
+			Cell::Text(alert.message.clone()),
+			Cell::Text(alert.causes.join("; ")),
+		])
+		.collect();
+
+	let notes = sheet.notes
+		.iter()
+		.map(|note| vec![
+			at(note.created_at),
+			Cell::Text(note.author.clone().unwrap_or_default()),
+			Cell::Text(note.content.clone()),
+		])
+		.collect();
+
+	vec![
+		Section {
+			title: "Timeline",
+			headers: &["At", "Kind", "User", "Event"],
+			rows: timeline,
+			empty: "Nothing happened.",
+		},
+		Section {
+			title: "Sequences",
+			headers: &["At", "User", "Detail"],
+			rows: sequences,
+			empty: "No sequences were run.",
+		},
+		Section {
+			title: "Alerts",
+			headers: &["At", "Severity", "Rule", "Message", "Probable cause"],
+			rows: alerts,
+			empty: "No alerts were raised.",
+		},
+		Section {
+			title: "Operator notes",
+			headers: &["At", "Author", "Note"],
+			rows: notes,
+			empty: "No notes were entered.",
+		},
+	]
+}
+
+/// Formats a Unix timestamp as the UTC time of day, such as `14:03:22.5`.
+fn utc(timestamp: f64) -> String {
+	let seconds = timestamp.rem_euclid(86_400.0);
+	let whole = seconds as u64;
+
+	format!("{:02}:{:02}:{:04.1}", whole / 3600, whole / 60 % 60, seconds % 60.0)
+}
+
+/// Formats a Unix timestamp as its UTC date, such as `2024-03-09`.
+fn date(timestamp: f64) -> String {
+	// converts days since the epoch to a civil date in the proleptic Gregorian calendar
+	let days = (timestamp / 86_400.0).floor() as i64 + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let shifted_month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+	let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+	format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats a number of seconds from T-0 as a countdown, such as `T-10.0 s` or `T+2.5 s`.
+fn countdown(seconds: f64) -> String {
+	if seconds < 0.0 {
+		format!("T-{:.1} s", -seconds)
+	} else {
+		format!("T+{seconds:.1} s")
+	}
+}
+
+/// A summary of the range the report covers.
+fn summary(report: &QualityReport) -> String {
+	format!(
+		"Covers {:.1} s from Unix time {:.3} to {:.3}, over which {} snapshots were logged.",
+		report.to - report.from,
+		report.from,
+		report.to,
+		report.snapshots,
+	)
+}
+
+/// Writes a document as Markdown, with plots embedded as data URIs.
+fn render_markdown(title: &str, summary: &str, sections: Vec<Section>) -> anyhow::Result<String> {
+	let mut document = String::new();
+	writeln!(document, "# {title}\n\n{summary}\n")?;
+
+	for section in sections {
+		writeln!(document, "## {}\n", section.title)?;
+
+		if section.rows.is_empty() {
+			writeln!(document, "{}\n", section.empty)?;
+			continue;
+		}
+
+		writeln!(document, "| {} |", section.headers.join(" | "))?;
+		writeln!(document, "|{}", " --- |".repeat(section.headers.len()))?;
+
+		for row in section.rows {
+			let cells = row
+				.into_iter()
+				.map(|cell| match cell {
+					Cell::Text(text) => text.replace('|', "\\|"),
+					Cell::Plot(svg) => format!("![plot](data:image/svg+xml;base64,{})", base64::encode(svg)),
+				})
+				.collect::<Vec<_>>();
+
+			writeln!(document, "| {} |", cells.join(" | "))?;
+		}
+
+		writeln!(document)?;
+	}
+
+	Ok(document)
+}
+
+/// Writes a document as a standalone HTML page, with plots inlined.
+fn render_html(title: &str, summary: &str, sections: Vec<Section>) -> anyhow::Result<String> {
+	let mut document = format!(
+		"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}{}{}",
+		escape(title),
+		"<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse}",
+		"td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}th{background:#f4f4f4}</style>\n",
+		"</head>\n<body>\n",
+	);
+
+	writeln!(document, "<h1>{}</h1>\n<p>{}</p>", escape(title), escape(summary))?;
+
+	for section in sections {
+		writeln!(document, "<h2>{}</h2>", section.title)?;
+
+		if section.rows.is_empty() {
+			writeln!(document, "<p>{}</p>", section.empty)?;
+			continue;
+		}
+
+		document.push_str("<table>\n<tr>");
+
+		for header in section.headers {
+			write!(document, "<th>{header}</th>")?;
+		}
+
+		document.push_str("</tr>\n");
+
+		for row in section.rows {
+			document.push_str("<tr>");
+
+			for cell in row {
+				match cell {
+					Cell::Text(text) => write!(document, "<td>{}</td>", escape(&text))?,
+					Cell::Plot(svg) => write!(document, "<td>{svg}</td>")?,
+				}
+			}
+
+			document.push_str("</tr>\n");
+		}
+
+		document.push_str("</table>\n");
+	}
+
+	document.push_str("</body>\n</html>\n");
+	Ok(document)
+}
+
+/// Typesets a document as a printable PDF, with each table laid out in
+/// monospaced columns wrapped to fit the page. Plots are left out.
+fn render_pdf(title: &str, summary: &str, sections: Vec<Section>) -> Vec<u8> {
+	let mut lines = vec![Line::bold(title), Line::default()];
+	lines.extend(pdf::wrap(summary, pdf::LINE_WIDTH).into_iter().map(Line::regular));
+
+	for section in sections {
+		lines.extend([Line::default(), Line::bold(section.title), Line::default()]);
+
+		if section.rows.is_empty() {
+			lines.push(Line::regular(section.empty));
+			continue;
+		}
+
+		let rows = section.rows
+			.into_iter()
+			.map(|row| {
+				row
+					.into_iter()
+					.map(|cell| match cell {
+						Cell::Text(text) => text,
+						Cell::Plot(_) => "(plot)".to_owned(),
+					})
+					.collect::<Vec<_>>()
+			})
+			.collect::<Vec<_>>();
+
+		let mut widths = section.headers
+			.iter()
+			.enumerate()
+			.map(|(column, header)| {
+				rows
+					.iter()
+					.map(|row| row[column].chars().count())
+					.fold(header.len(), usize::max)
+			})
+			.collect::<Vec<_>>();
+
+		// the widest columns give way until the table fits between the margins
+		let available = pdf::LINE_WIDTH - 2 * (widths.len() - 1);
+
+		while widths.iter().sum::<usize>() > available {
+			let widest = (0..widths.len()).max_by_key(|&column| widths[column]).unwrap();
+
+			if widths[widest] <= MIN_COLUMN_WIDTH {
+				break;
+			}
+
+			widths[widest] -= 1;
+		}
+
+		let headers = section.headers.iter().map(|header| header.to_string()).collect::<Vec<_>>();
+		lines.extend(table_lines(&widths, &headers).into_iter().map(Line::bold));
+		lines.push(Line::regular(widths.iter().map(|&width| "-".repeat(width)).collect::<Vec<_>>().join("  ")));
+
+		for row in &rows {
+			lines.extend(table_lines(&widths, row).into_iter().map(Line::regular));
+		}
+	}
+
+	pdf::typeset(&lines)
+}
+
+/// Lays out a row of a table as lines, wrapping each cell to its column.
+fn table_lines(widths: &[usize], cells: &[String]) -> Vec<String> {
+	let wrapped = cells
+		.iter()
+		.zip(widths)
+		.map(|(cell, &width)| pdf::wrap(cell, width))
+		.collect::<Vec<_>>();
+
+	let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+
+	(0..height)
+		.map(|line| {
+			wrapped
+				.iter()
+				.zip(widths)
+				.map(|(cell, &width)| format!("{:<width$}", cell.get(line).map_or("", String::as_str)))
+				.collect::<Vec<_>>()
+				.join("  ")
+				.trim_end()
+				.to_owned()
+		})
+		.collect()
+}
+
+/// Draws a channel's thumbnail as an SVG line plot scaled to its range.
+fn plot(channel: &ChannelQuality) -> String {
+	let (width, height) = PLOT_SIZE;
+	let points = &channel.thumbnail;
+
+	let (start, end) = match (points.first(), points.last()) {
+		(Some(first), Some(last)) => (first[0], last[0]),
+		_ => (0.0, 0.0),
+	};
+
+	let time_span = (end - start).max(f64::EPSILON);
+	let value_span = channel.max - channel.min;
+
+	// a channel which held still is drawn as a flat line through the middle
+	let flat = value_span.is_nan() || value_span <= 1e-9 * channel.max.abs().max(1.0);
+
+	let coordinates = points
+		.iter()
+		.map(|[time, value]| {
+			let x = (time - start) / time_span * width;
+
+			let y = if flat {
+				height / 2.0
+			} else {
+				(height - (value - channel.min) / value_span * (height - 2.0) - 1.0).clamp(1.0, height - 1.0)
+			};
+
+			format!("{x:.1},{y:.1}")
+		})
+		.collect::<Vec<_>>()
+		.join(" ");
+
+	format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\
+		<polyline fill=\"none\" stroke=\"#2563eb\" stroke-width=\"1.5\" points=\"{coordinates}\"/></svg>"
+	)
+}
+
+/// Escapes text for inclusion in HTML.
+fn escape(text: &str) -> String {
+	text
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
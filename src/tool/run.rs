@@ -1,17 +1,137 @@
+use clap::ArgMatches;
+use crate::server::{api::RunAdhocSequenceResponse, simulation::{SimulatedAction, SimulationReport}};
+use jeflog::{fail, pass, warn};
 use serde_json::json;
+use std::{collections::BTreeMap, fs, path::Path};
 
-/// Tool function used to send a sequence to be run on the flight computer.
-pub fn run(sequence: &str) -> anyhow::Result<()> {
-	let client = reqwest::blocking::Client::new();
-	let response = client
-		.post("http://localhost:7200/operator/run-sequence")
-		.json(&json!({
-			"name": sequence,
-			"force": true
-		}))
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function used to send a sequence to be run on the flight computer, or to simulate it.
+///
+/// The path may be a local script, which is run and stored on the server as a
+/// new version of an ad-hoc sequence in one request, or the name of a stored sequence.
+pub fn run(args: &ArgMatches) -> anyhow::Result<()> {
+	let sequence = args.get_one::<String>("path").unwrap();
+
+	if args.get_flag("simulate") {
+		let assumptions = args
+			.get_many::<String>("assume")
+			.unwrap_or_default()
+			.map(|assumption| parse_assumption(assumption))
+			.collect::<anyhow::Result<BTreeMap<_, _>>>()?;
+
+		return simulate(sequence, assumptions);
+	}
+
+	let path = Path::new(sequence);
+
+	// a local script is run and stored in one request, so that nothing can replace
+	// it on the server between uploading it and running it.
+	let response = if path.is_file() {
+		let name = path
+			.file_stem()
+			.expect("given path does not have a file stem")
+			.to_string_lossy()
+			.into_owned();
+
+		http_client()?
+			.post(format!("{}/operator/sequences/run-adhoc", server_url()))
+			.json(&json!({
+				"name": name,
+				"script": base64::encode(fs::read(path)?),
+				"force": true
+			}))
+			.send()?
+	} else if path.is_dir() {
+		fail!("Sequence bundles cannot be run directly. Upload \x1b[1m{sequence}\x1b[0m with servo upload, then run it by name.");
+		return Ok(());
+	} else {
+		http_client()?
+			.post(format!("{}/operator/run-sequence", server_url()))
+			.json(&json!({
+				"name": sequence,
+				"force": true
+			}))
+			.send()?
+	};
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	if path.is_file() {
+		let stored: RunAdhocSequenceResponse = response.json()?;
+		pass!("Sent \x1b[1m{sequence}\x1b[0m to be run, stored as version {} of \x1b[1m{}\x1b[0m.", stored.version, stored.name);
+	} else {
+		pass!("Sent \x1b[1m{sequence}\x1b[0m to be run.");
+	}
+
+	Ok(())
+}
+
+/// Parses an assumption such as `KBPT=400:600` or `KBPT=500` into a channel and its range.
+fn parse_assumption(assumption: &str) -> anyhow::Result<(String, [f64; 2])> {
+	let (channel, range) = assumption
+		.split_once('=')
+		.ok_or_else(|| anyhow::anyhow!("assumption '{assumption}' must be written as CHANNEL=MIN:MAX or CHANNEL=VALUE"))?;
+
+	let (min, max) = range.split_once(':').unwrap_or((range, range));
+	Ok((channel.trim().to_owned(), [min.trim().parse()?, max.trim().parse()?]))
+}
+
+/// Simulates a stored sequence, or a local script if the path exists, and displays the timeline.
+fn simulate(sequence: &str, assumptions: BTreeMap<String, [f64; 2]>) -> anyhow::Result<()> {
+	let path = Path::new(sequence);
+
+	let request = if path.exists() {
+		let script_path = if path.is_dir() { path.join("main.py") } else { path.to_owned() };
+		json!({ "script": base64::encode(fs::read(script_path)?), "assumptions": assumptions })
+	} else {
+		json!({ "name": sequence, "assumptions": assumptions })
+	};
+
+	let response = http_client()?
+		.post(format!("{}/operator/sequences/simulate", server_url()))
+		.json(&request)
 		.send()?;
 
-	println!("{response:#?}");
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let report: SimulationReport = response.json()?;
+
+	for entry in &report.timeline {
+		let action = match &entry.action {
+			SimulatedAction::Open { valve } => format!("open \x1b[1m{valve}\x1b[0m"),
+			SimulatedAction::Close { valve } => format!("close \x1b[1m{valve}\x1b[0m"),
+			SimulatedAction::Wait { seconds } => format!("wait {seconds} s"),
+			SimulatedAction::WaitUntil { seconds, condition_met } => {
+				let outcome = match condition_met {
+					Some(true) => "met",
+					Some(false) => "not met",
+					None => "indeterminate",
+				};
+
+				format!("wait until condition ({outcome}) for {seconds} s")
+			},
+			SimulatedAction::Abort => "\x1b[1mabort\x1b[0m".to_owned(),
+		};
+
+		println!("  T+{:>9.3} s  line {:<4} {action}", entry.at_seconds, entry.line);
+	}
+
+	for warning in &report.warnings {
+		warn!("Line {}: {}.", warning.line, warning.message);
+	}
+
+	if report.completed {
+		pass!("Sequence takes \x1b[1m{:.3} s\x1b[0m with \x1b[1m{}\x1b[0m actions.", report.total_seconds, report.timeline.len());
+	} else {
+		fail!("Simulation stopped after \x1b[1m{:.3} s\x1b[0m before reaching the end of the sequence.", report.total_seconds);
+	}
 
 	Ok(())
 }
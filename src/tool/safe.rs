@@ -0,0 +1,13 @@
+use super::client::{http_client, server_url};
+
+/// Tool function used to immediately run the safing sequence of the active configuration.
+pub fn safe() -> anyhow::Result<()> {
+	let client = http_client()?;
+	let response = client
+		.post(format!("{}/operator/safe", server_url()))
+		.send()?;
+
+	println!("{response:#?}");
+
+	Ok(())
+}
@@ -0,0 +1,386 @@
+use clap::ArgMatches;
+use crate::server::api::{RetrieveSequenceResponse, SequenceWithConfiguration};
+use jeflog::pass;
+use std::{fs, io::{self, IsTerminal}, path::{Path, PathBuf}};
+
+use super::client::{http_client, server_url, ResponseExt};
+
+/// The number of unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+const KEYWORDS: [&str; 34] = [
+	"and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else",
+	"except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not",
+	"or", "pass", "raise", "return", "try", "while", "with", "yield", "match", "case",
+];
+
+const CONSTANTS: [&str; 4] = ["True", "False", "None", "self"];
+
+const RESET: &str = "\x1b[0m";
+const COMMENT: &str = "\x1b[90m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const KEYWORD: &str = "\x1b[35;1m";
+const CONSTANT: &str = "\x1b[33m";
+const DEFINITION: &str = "\x1b[34;1m";
+
+/// Tool function which displays or compares sequences stored on the control server.
+pub fn sequence(args: &ArgMatches) -> anyhow::Result<()> {
+	match args.subcommand() {
+		Some(("show", args)) => show(args.get_one::<String>("name").unwrap()),
+		Some(("diff", args)) => diff(
+			args.get_one::<String>("name").unwrap(),
+			args.get_one::<PathBuf>("local_path").unwrap(),
+		),
+		_ => unreachable!("clap requires a sequence subcommand"),
+	}
+}
+
+/// Displays a stored sequence with syntax highlighting and line numbers.
+fn show(name: &str) -> anyhow::Result<()> {
+	let sequence = fetch(name)?;
+	let color = io::stdout().is_terminal();
+
+	if let Some(configuration_id) = &sequence.configuration_id {
+		pass!(
+			"Sequence \x1b[1m{name}\x1b[0m belongs to configuration \x1b[1m{configuration_id}\x1b[0m{}.",
+			if sequence.safing { " as its safing sequence" } else { "" },
+		);
+	}
+
+	let lines = highlight(&sequence.script, color);
+	let width = lines.len().to_string().len();
+
+	for (number, line) in lines.iter().enumerate() {
+		if color {
+			println!("{COMMENT}{:>width$} │{RESET} {line}", number + 1);
+		} else {
+			println!("{:>width$} │ {line}", number + 1);
+		}
+	}
+
+	Ok(())
+}
+
+/// Displays a unified diff from a stored sequence to a local copy of it.
+///
+/// A local bundle directory is compared by its `main.py`.
+fn diff(name: &str, local_path: &Path) -> anyhow::Result<()> {
+	let sequence = fetch(name)?;
+
+	let local_path = if local_path.is_dir() {
+		local_path.join("main.py")
+	} else {
+		local_path.to_owned()
+	};
+
+	let local = fs::read_to_string(&local_path)?;
+	let server_lines = sequence.script.lines().collect::<Vec<_>>();
+	let local_lines = local.lines().collect::<Vec<_>>();
+
+	let hunks = unified_diff(&server_lines, &local_lines, DIFF_CONTEXT);
+
+	if hunks.is_empty() {
+		pass!("Sequence \x1b[1m{name}\x1b[0m on the server matches \x1b[1m{}\x1b[0m.", local_path.display());
+		return Ok(());
+	}
+
+	let color = io::stdout().is_terminal();
+	let paint = |code: &str, text: String| if color { format!("{code}{text}{RESET}") } else { text };
+
+	println!("{}", paint("\x1b[1m", format!("--- server/{name}")));
+	println!("{}", paint("\x1b[1m", format!("+++ {}", local_path.display())));
+
+	for hunk in hunks {
+		println!("{}", paint(NUMBER, hunk.header()));
+
+		for line in hunk.lines {
+			match line {
+				DiffLine::Context(text) => println!(" {text}"),
+				DiffLine::Removed(text) => println!("{}", paint("\x1b[31m", format!("-{text}"))),
+				DiffLine::Added(text) => println!("{}", paint("\x1b[32m", format!("+{text}"))),
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Fetches a single sequence by name from the control server.
+fn fetch(name: &str) -> anyhow::Result<SequenceWithConfiguration> {
+	let response: RetrieveSequenceResponse = http_client()?
+		.get(format!("{}/operator/sequence", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	response.sequences
+		.into_iter()
+		.find(|sequence| sequence.name == name)
+		.ok_or_else(|| anyhow::anyhow!("no sequence named '{name}' is stored on the control server"))
+}
+
+/// A single line of a unified diff.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DiffLine<'a> {
+	Context(&'a str),
+	Removed(&'a str),
+	Added(&'a str),
+}
+
+/// A run of changes along with the unchanged lines surrounding them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Hunk<'a> {
+	old_start: usize,
+	new_start: usize,
+	lines: Vec<DiffLine<'a>>,
+}
+
+impl Hunk<'_> {
+	/// The `@@ -a,b +c,d @@` header of the hunk.
+	fn header(&self) -> String {
+		let old_count = self.lines.iter().filter(|line| !matches!(line, DiffLine::Added(_))).count();
+		let new_count = self.lines.iter().filter(|line| !matches!(line, DiffLine::Removed(_))).count();
+
+		// by convention, an empty range starts at the line before it
+		let old_start = if old_count == 0 { self.old_start - 1 } else { self.old_start };
+		let new_start = if new_count == 0 { self.new_start - 1 } else { self.new_start };
+
+		format!("@@ -{old_start},{old_count} +{new_start},{new_count} @@")
+	}
+}
+
+/// Computes the hunks of a unified diff between two sequences of lines.
+fn unified_diff<'a>(old: &[&'a str], new: &[&'a str], context: usize) -> Vec<Hunk<'a>> {
+	// longest common subsequence lengths of every pair of suffixes
+	let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			common[i][j] = if old[i] == new[j] {
+				common[i + 1][j + 1] + 1
+			} else {
+				common[i + 1][j].max(common[i][j + 1])
+			};
+		}
+	}
+
+	// each line along with its 1-based line numbers in the old and new text
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old[i] == new[j] {
+			lines.push((DiffLine::Context(old[i]), i + 1, j + 1));
+			i += 1;
+			j += 1;
+		} else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+			lines.push((DiffLine::Removed(old[i]), i + 1, j + 1));
+			i += 1;
+		} else {
+			lines.push((DiffLine::Added(new[j]), i + 1, j + 1));
+			j += 1;
+		}
+	}
+
+	let changes = lines
+		.iter()
+		.enumerate()
+		.filter(|(_, (line, _, _))| !matches!(line, DiffLine::Context(_)))
+		.map(|(index, _)| index)
+		.collect::<Vec<_>>();
+
+	let mut hunks = Vec::new();
+	let mut index = 0;
+
+	while index < changes.len() {
+		let start = changes[index].saturating_sub(context);
+		let mut end = changes[index];
+
+		// changes separated by at most twice the context share a hunk, since their context would touch
+		while index + 1 < changes.len() && changes[index + 1] - end <= 2 * context + 1 {
+			index += 1;
+			end = changes[index];
+		}
+
+		let end = (end + context + 1).min(lines.len());
+		let (_, old_start, new_start) = lines[start];
+
+		hunks.push(Hunk {
+			old_start,
+			new_start,
+			lines: lines[start..end].iter().map(|(line, _, _)| *line).collect(),
+		});
+
+		index += 1;
+	}
+
+	hunks
+}
+
+/// Highlights a Python script line by line with ANSI escape codes, or only
+/// splits it into lines if `color` is false.
+fn highlight(script: &str, color: bool) -> Vec<String> {
+	if !color {
+		return script.lines().map(str::to_owned).collect();
+	}
+
+	// the closing delimiter of a triple-quoted string continuing from a previous line
+	let mut open_string: Option<&str> = None;
+	let mut highlighted = Vec::new();
+
+	for line in script.lines() {
+		let mut output = String::new();
+		let mut rest = line;
+		let mut after_def = false;
+
+		while !rest.is_empty() {
+			if let Some(delimiter) = open_string {
+				let (text, closed) = match rest.find(delimiter) {
+					Some(end) => (&rest[..end + delimiter.len()], true),
+					None => (rest, false),
+				};
+
+				output.push_str(&format!("{STRING}{text}{RESET}"));
+				rest = &rest[text.len()..];
+
+				if closed {
+					open_string = None;
+				}
+
+				continue;
+			}
+
+			let first = rest.chars().next().unwrap();
+
+			if first == '#' {
+				output.push_str(&format!("{COMMENT}{rest}{RESET}"));
+				break;
+			}
+
+			if let Some(prefix_length) = string_start(rest) {
+				let quote_start = &rest[prefix_length..];
+				let delimiter = if quote_start.starts_with("\"\"\"") {
+					"\"\"\""
+				} else if quote_start.starts_with("'''") {
+					"'''"
+				} else if quote_start.starts_with('"') {
+					"\""
+				} else {
+					"'"
+				};
+
+				let body = &rest[prefix_length + delimiter.len()..];
+
+				match find_closing(body, delimiter) {
+					Some(end) => {
+						let length = prefix_length + delimiter.len() + end + delimiter.len();
+						output.push_str(&format!("{STRING}{}{RESET}", &rest[..length]));
+						rest = &rest[length..];
+					},
+					None => {
+						output.push_str(&format!("{STRING}{rest}{RESET}"));
+
+						if delimiter.len() == 3 {
+							open_string = Some(delimiter);
+						}
+
+						break;
+					},
+				}
+
+				continue;
+			}
+
+			if first.is_alphabetic() || first == '_' {
+				let length = rest
+					.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+					.unwrap_or(rest.len());
+
+				let word = &rest[..length];
+
+				if after_def {
+					output.push_str(&format!("{DEFINITION}{word}{RESET}"));
+					after_def = false;
+				} else if KEYWORDS.contains(&word) {
+					output.push_str(&format!("{KEYWORD}{word}{RESET}"));
+					after_def = word == "def" || word == "class";
+				} else if CONSTANTS.contains(&word) {
+					output.push_str(&format!("{CONSTANT}{word}{RESET}"));
+				} else {
+					output.push_str(word);
+				}
+
+				rest = &rest[length..];
+				continue;
+			}
+
+			if first.is_ascii_digit() {
+				let length = rest
+					.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+					.unwrap_or(rest.len());
+
+				output.push_str(&format!("{NUMBER}{}{RESET}", &rest[..length]));
+				rest = &rest[length..];
+				continue;
+			}
+
+			output.push(first);
+			rest = &rest[first.len_utf8()..];
+		}
+
+		highlighted.push(output);
+	}
+
+	highlighted
+}
+
+/// The length of the prefix before the opening quote if the text starts with
+/// a string literal, such as `f` in `f"..."`.
+fn string_start(text: &str) -> Option<usize> {
+	let prefix_length = text
+		.find(|c: char| !matches!(c.to_ascii_lowercase(), 'r' | 'b' | 'f' | 'u'))
+		.unwrap_or(text.len());
+
+	(prefix_length <= 2 && text[prefix_length..].starts_with(['"', '\''])).then_some(prefix_length)
+}
+
+/// Finds the closing delimiter of a string, skipping escaped characters.
+fn find_closing(body: &str, delimiter: &str) -> Option<usize> {
+	let mut escaped = false;
+
+	for (index, c) in body.char_indices() {
+		if escaped {
+			escaped = false;
+		} else if c == '\\' {
+			escaped = true;
+		} else if body[index..].starts_with(delimiter) {
+			return Some(index);
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn diff_groups_nearby_changes_into_hunks() {
+		let old = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l"];
+		let new = ["a", "B", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m"];
+
+		let hunks = unified_diff(&old, &new, 2);
+		assert_eq!(hunks.len(), 2);
+
+		assert_eq!(hunks[0].header(), "@@ -1,4 +1,4 @@");
+		assert_eq!(hunks[0].lines[1], DiffLine::Removed("b"));
+		assert_eq!(hunks[0].lines[2], DiffLine::Added("B"));
+
+		assert_eq!(hunks[1].header(), "@@ -11,2 +11,3 @@");
+		assert_eq!(hunks[1].lines.last(), Some(&DiffLine::Added("m")));
+
+		assert!(unified_diff(&old, &old, 2).is_empty());
+	}
+}
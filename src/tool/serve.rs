@@ -1,8 +1,11 @@
 use clap::ArgMatches;
-use crate::{interface, server::{flight, Server}};
+use crate::server::{preflight::{self, Verdict}, Server, SharedConfig};
 use std::path::Path;
 use std::io;
 
+#[cfg(feature = "tui")]
+use crate::interface;
+
 /// Function used to convert std::future::pending to a join handle in the serve functions selection of a shutdown task
 /// (In theory this can be converted to checking a value in shared every X unit of time if we wish to allow the GUI to shut down servo 
 /// and thus the command simple set the value to quit / true / etc)
@@ -22,15 +25,32 @@ pub fn serve(servo_dir: &Path, args: &ArgMatches) -> anyhow::Result<()> {
 		.copied()
 		.unwrap_or(false);
 
+	#[cfg(feature = "tui")]
 	let quiet = args.get_one::<bool>("quiet")
 		.copied()
 		.unwrap_or(false);
 
-
+	let config = SharedConfig::load(&servo_dir.join("config.toml"))?;
 	let database_path = servo_dir.join("database.sqlite");
-	let server = Server::new((!volatile).then_some(&database_path))?;
 
-	server.shared.database.migrate()?;
+	#[cfg(feature = "tui")]
+	let tui_config = interface::TuiConfig::load(&servo_dir.join("tui.toml"))?;
+
+	let mut server = Server::builder().config(config);
+
+	if !volatile {
+		server = server.database(&database_path);
+	}
+
+	let server = server.build()?;
+
+	// only the quick checks are run here, and problems are reported without
+	// stopping the server, since it may be needed to resolve them.
+	let preflight = preflight::startup_checks(&server.shared.config.current(), &database_path);
+
+	if preflight.verdict() != Verdict::Go {
+		preflight.log();
+	}
 
 	tokio::runtime::Builder::new_multi_thread()
 		.worker_threads(10)
@@ -38,21 +58,20 @@ pub fn serve(servo_dir: &Path, args: &ArgMatches) -> anyhow::Result<()> {
 		.build()
 		.unwrap()
 		.block_on(async move {
-			tokio::spawn(flight::auto_connect(&server.shared));
-			tokio::spawn(flight::receive_vehicle_state(&server.shared));
-			tokio::spawn(server.shared.database.log_vehicle_state(&server.shared));
-
 			// The task that, once finished, will signal the server to terminate.
 			// Set to the TUI if it is launched, otherwise set to an infinitely hanging await that should(?) consume no resources
-			let shutdown_task: tokio::task::JoinHandle<io::Result<()>>;
-			
-			if !quiet {
-				shutdown_task = tokio::spawn(interface::display(server.shared.clone())); // Launch the TUI
+			#[cfg(feature = "tui")]
+			let shutdown_task = if !quiet {
+				tokio::spawn(interface::display(server.shared.clone(), tui_config)) // Launch the TUI
 			} else {
-				shutdown_task = tokio::spawn(infinite_hang()); // infinite runtime
-			}
+				tokio::spawn(infinite_hang()) // infinite runtime
+			};
+
+			// without the terminal interface, the server always runs as if quiet
+			#[cfg(not(feature = "tui"))]
+			let shutdown_task = tokio::spawn(infinite_hang());
 
-			server.serve(shutdown_task).await
+			server.run(async move { let _ = shutdown_task.await; }).await
 		})?;
 
 	Ok(())
@@ -0,0 +1,289 @@
+use clap::ArgMatches;
+use common::comm::VehicleState;
+use crate::server::{clock, config::Config, recording::{self, RecordedFrame, FRAME_LOG_MAGIC}, selector::Selection, telemetry};
+use jeflog::{fail, pass, warn};
+use rusqlite::{Connection, OpenFlags};
+use std::{
+	collections::{HashMap, HashSet},
+	fs::File,
+	io::{self, Read, Seek, SeekFrom},
+	net::UdpSocket,
+	path::{Path, PathBuf},
+	thread,
+	time::Duration,
+};
+
+use super::capture::{hexdump, HEXDUMP_BYTES};
+
+/// How often a tapped frame log is checked for newly recorded frames.
+const TAP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The largest datagram which may be received, which is the largest UDP allows.
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// Which readings of each frame are shown.
+#[derive(Clone, Debug, Default)]
+struct Filter {
+	/// The channels shown, or every channel if none are selected.
+	channels: Selection,
+
+	/// The boards whose channels are shown, or every board if empty.
+	boards: HashSet<String>,
+
+	/// The board each mapped channel is on, by its text ID.
+	board_of: HashMap<String, String>,
+}
+
+impl Filter {
+	fn shows(&self, channel: &str) -> bool {
+		(self.channels.is_empty() || self.channels.matches(channel))
+			&& (self.boards.is_empty() || self.board_of.get(channel).is_some_and(|board| self.boards.contains(board)))
+	}
+
+	/// The lines describing the readings of a vehicle state which are shown, sorted by channel.
+	fn lines(&self, vehicle_state: &VehicleState) -> Vec<String> {
+		let mut lines = vehicle_state.sensor_readings
+			.iter()
+			.filter(|(channel, _)| self.shows(channel))
+			.map(|(channel, measurement)| (channel, measurement.to_string()))
+			.chain(
+				vehicle_state.valve_states
+					.iter()
+					.filter(|(channel, _)| self.shows(channel))
+					.map(|(channel, state)| (channel, format!("{} (commanded {})", state.actual, state.commanded)))
+			)
+			.collect::<Vec<_>>();
+
+		lines.sort();
+
+		lines
+			.into_iter()
+			.map(|(channel, value)| format!("{channel:<16} {value}"))
+			.collect()
+	}
+}
+
+/// Where frames are sniffed from.
+enum Tap {
+	/// The telemetry port, bound while the server is not running.
+	Socket(UdpSocket),
+
+	/// A frame log being written by a running server.
+	FrameLog {
+		file: File,
+
+		/// The offset of the first byte not yet read from the log.
+		position: u64,
+
+		/// Bytes read from the log which do not yet make up a whole record.
+		pending: Vec<u8>,
+	},
+}
+
+impl Tap {
+	/// Follows a frame log from the end of its last whole record, waiting for it to be created.
+	fn frame_log(path: &Path) -> io::Result<Self> {
+		// the server only creates the log once it receives a frame
+		let mut file = loop {
+			match File::open(path) {
+				Ok(file) => break file,
+				Err(error) if error.kind() == io::ErrorKind::NotFound => thread::sleep(TAP_INTERVAL),
+				Err(error) => return Err(error),
+			}
+		};
+
+		// a log which has not had its magic written yet is read from its start
+		let position = if file.metadata()?.len() < FRAME_LOG_MAGIC.len() as u64 {
+			0
+		} else {
+			recording::last_record_end(&mut file)?
+		};
+
+		Ok(Tap::FrameLog { file, position, pending: Vec::new() })
+	}
+
+	/// Waits for the next frames to arrive.
+	fn next_frames(&mut self) -> io::Result<Vec<RecordedFrame>> {
+		match self {
+			Tap::Socket(socket) => {
+				let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+				let (size, source) = socket.recv_from(&mut buffer)?;
+				buffer.truncate(size);
+
+				Ok(vec![RecordedFrame { received_at: clock::now(), source: Some(source), frame: buffer }])
+			},
+			Tap::FrameLog { file, position, pending } => {
+				thread::sleep(TAP_INTERVAL);
+
+				// a log shorter than what was read has been replaced, so it is read again from its start
+				if file.metadata()?.len() < *position {
+					*position = 0;
+					pending.clear();
+				}
+
+				file.seek(SeekFrom::Start(*position))?;
+				*position += file.read_to_end(pending)? as u64;
+
+				// everything read is still pending only while the magic has yet to be stripped
+				if *position == pending.len() as u64 {
+					if pending.len() < FRAME_LOG_MAGIC.len() {
+						return Ok(Vec::new());
+					}
+
+					if !pending.starts_with(&FRAME_LOG_MAGIC) {
+						return Err(io::Error::new(io::ErrorKind::InvalidData, "tapped file is not a frame log"));
+					}
+
+					pending.drain(..FRAME_LOG_MAGIC.len());
+				}
+
+				let (frames, consumed) = recording::read_records(pending)?;
+				pending.drain(..consumed);
+
+				Ok(frames)
+			},
+		}
+	}
+}
+
+/// Tool function which decodes and prints telemetry frames as they arrive, for
+/// debugging the telemetry sent by the flight computer.
+///
+/// Frames are received on the telemetry port if it is free. Otherwise, the
+/// server is running, so the frame log it records to is tapped instead.
+pub fn sniff(servo_dir: &Path, args: &ArgMatches) -> anyhow::Result<()> {
+	let port = *args.get_one::<u16>("port").unwrap();
+
+	let channels = match Selection::parse(args.get_many::<String>("channel").unwrap_or_default()) {
+		Ok(channels) => channels,
+		Err(error) => {
+			fail!("Failed to parse channels: {error}");
+			return Ok(());
+		},
+	};
+
+	let mut filter = Filter {
+		channels,
+		boards: args.get_many::<String>("board").unwrap_or_default().cloned().collect(),
+		board_of: HashMap::new(),
+	};
+
+	if !filter.boards.is_empty() {
+		filter.board_of = mapped_boards(&servo_dir.join("database.sqlite"))?;
+	}
+
+	let mut tap = if let Some(path) = args.get_one::<PathBuf>("capture") {
+		pass!("Tapping frame log \x1b[1m{}\x1b[0m.", path.display());
+		Tap::frame_log(path)?
+	} else {
+		match UdpSocket::bind(("0.0.0.0", port)) {
+			Ok(socket) => {
+				pass!("Listening for telemetry on UDP port \x1b[1m{port}\x1b[0m.");
+				Tap::Socket(socket)
+			},
+			Err(error) if error.kind() == io::ErrorKind::AddrInUse => {
+				let recording = Config::load(&servo_dir.join("config.toml"))?.recording;
+
+				let Some(path) = recording.path else {
+					fail!("Port {port} is in use, likely by the server, which is not recording a frame log to tap. Set \x1b[1mpath\x1b[0m under \x1b[1m[recording]\x1b[0m in config.toml, or pass --capture.");
+					return Ok(());
+				};
+
+				pass!("Port {port} is in use, so tapping frame log \x1b[1m{}\x1b[0m instead.", path.display());
+				Tap::frame_log(&path)?
+			},
+			Err(error) => return Err(error.into()),
+		}
+	};
+
+	loop {
+		for recorded in tap.next_frames()? {
+			print_frame(&recorded, &filter);
+		}
+	}
+}
+
+/// Prints a frame with those of its readings which are shown, or why it could not be decoded.
+fn print_frame(recorded: &RecordedFrame, filter: &Filter) {
+	let (sequence_number, payload) = telemetry::split_sequence_number(&recorded.frame);
+
+	let sequence_number = sequence_number.map_or("-".to_owned(), |sequence_number| sequence_number.to_string());
+	let source = recorded.source.map_or("unknown".to_owned(), |source| source.to_string());
+	let prefix = format!("{:.6}  {source:<21}  #{sequence_number:<8} {:>6} bytes", recorded.received_at, recorded.frame.len());
+
+	match postcard::from_bytes::<VehicleState>(payload) {
+		Ok(vehicle_state) => {
+			let lines = filter.lines(&vehicle_state);
+
+			// frames without any of the filtered channels are not worth a line each
+			if lines.is_empty() && !(filter.channels.is_empty() && filter.boards.is_empty()) {
+				return;
+			}
+
+			println!("\x1b[1m{prefix}\x1b[0m");
+
+			for line in lines {
+				println!("    {line}");
+			}
+		},
+		Err(error) => {
+			println!("\x1b[1m{prefix}\x1b[0m  \x1b[31;1merror\x1b[0m  {error}");
+
+			for line in hexdump(&recorded.frame[..recorded.frame.len().min(HEXDUMP_BYTES)]) {
+				println!("    {line}");
+			}
+
+			if recorded.frame.len() > HEXDUMP_BYTES {
+				println!("    ...and {} more bytes", recorded.frame.len() - HEXDUMP_BYTES);
+			}
+		},
+	}
+}
+
+/// Reads the board each active mapping is on from the local database, by its text ID.
+fn mapped_boards(database_path: &Path) -> anyhow::Result<HashMap<String, String>> {
+	let connection = Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+	let boards = connection
+		.prepare("SELECT text_id, CAST(board_id AS TEXT) FROM NodeMappings WHERE active = TRUE")?
+		.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+		.collect::<Result<HashMap<_, _>, _>>()?;
+
+	if boards.is_empty() {
+		warn!("No mappings are active, so no channel is known to be on any board.");
+	}
+
+	Ok(boards)
+}
+
+#[cfg(test)]
+mod tests {
+	use common::comm::{CompositeValveState, Measurement, Unit, ValveState};
+	use super::*;
+
+	#[test]
+	fn readings_are_filtered_by_channel_and_board() {
+		let mut vehicle_state = VehicleState::new();
+		vehicle_state.sensor_readings.insert("KBPT".to_owned(), Measurement { value: 14.5, unit: Unit::Psi });
+		vehicle_state.sensor_readings.insert("FMPT".to_owned(), Measurement { value: 2.0, unit: Unit::Psi });
+		vehicle_state.valve_states.insert("BBV".to_owned(), CompositeValveState { commanded: ValveState::Open, actual: ValveState::Closed });
+
+		let mut filter = Filter::default();
+		assert_eq!(filter.lines(&vehicle_state).len(), 3);
+
+		filter.boards.insert("1".to_owned());
+		filter.board_of.insert("KBPT".to_owned(), "1".to_owned());
+		filter.board_of.insert("BBV".to_owned(), "1".to_owned());
+		filter.board_of.insert("FMPT".to_owned(), "2".to_owned());
+
+		let lines = filter.lines(&vehicle_state);
+		assert_eq!(lines.len(), 2);
+		assert!(lines[0].starts_with("BBV ") && lines[1].starts_with("KBPT "));
+
+		filter.channels = Selection::parse(["FM*"]).unwrap();
+		assert!(filter.lines(&vehicle_state).is_empty());
+
+		filter.boards.clear();
+		assert_eq!(filter.lines(&vehicle_state).len(), 1);
+	}
+}
@@ -1,3 +1,4 @@
+use super::client::{http_client, server_url, ResponseExt};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -12,12 +13,12 @@ pub fn sql(sql: &str) -> anyhow::Result<()> {
 		"raw_sql": sql
 	});
 
-	let client = reqwest::blocking::Client::new();
+	let client = http_client()?;
 	let response: SqlResponse = serde_json::from_str(
-		&client.post(format!("http://localhost:7200/admin/sql"))
+		&client.post(format!("{}/admin/sql", server_url()))
 			.json(&request)
 			.send()?
-			.error_for_status()?
+			.or_server_error()?
 			.text()?
 	)?;
 
@@ -0,0 +1,59 @@
+use clap::ArgMatches;
+use crate::server::api::StatsResponse;
+use jeflog::fail;
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which displays statistics of a sensor channel over a time window,
+/// as computed by the control server.
+pub fn stats(args: &ArgMatches) -> anyhow::Result<()> {
+	let channel = args.get_one::<String>("channel").unwrap();
+	let mut query = vec![("channel", channel.clone())];
+
+	if let Some(from) = args.get_one::<f64>("from") {
+		query.push(("from", from.to_string()));
+	}
+
+	if let Some(to) = args.get_one::<f64>("to") {
+		query.push(("to", to.to_string()));
+	}
+
+	let response = http_client()?
+		.get(format!("{}/data/stats", server_url()))
+		.query(&query)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let stats: StatsResponse = response.json()?;
+
+	if args.get_flag("json") {
+		println!("{}", serde_json::to_string_pretty(&stats)?);
+		return Ok(());
+	}
+
+	let statistics = &stats.statistics;
+	let unit = stats.unit;
+
+	println!("\x1b[1m{}\x1b[0m ({} readings)", stats.channel, statistics.count);
+	println!("  min     {:.4} {unit}", statistics.min);
+	println!("  max     {:.4} {unit}", statistics.max);
+	println!("  mean    {:.4} {unit}", statistics.mean);
+	println!("  stddev  {:.4} {unit}", statistics.stddev);
+
+	for (name, value) in [
+		("p5", statistics.p5),
+		("p25", statistics.p25),
+		("p50", statistics.p50),
+		("p75", statistics.p75),
+		("p95", statistics.p95),
+		("p99", statistics.p99),
+	] {
+		println!("  {name:<7} {value:.4} {unit}");
+	}
+
+	Ok(())
+}
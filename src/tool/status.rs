@@ -0,0 +1,103 @@
+use crate::server::api::{ClocksResponse, FlightInfoResponse, MetricsResponse};
+use jeflog::{fail, pass, warn};
+
+use super::client::{http_client, server_url, ResponseExt};
+
+/// Tool function which displays the status of the control server and the flight computer.
+pub fn status() -> anyhow::Result<()> {
+	let client = http_client()?;
+	let flight: FlightInfoResponse = client
+		.get(format!("{}/flight/info", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	let metrics: MetricsResponse = client
+		.get(format!("{}/status/metrics", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	pass!("Servo is running version \x1b[1m{}\x1b[0m.", flight.servo_version);
+
+	let telemetry = &metrics.telemetry;
+	let loss_percent = metrics.telemetry_loss_ratio * 100.0;
+
+	if telemetry.frames_lost > 0 {
+		warn!(
+			"Lost \x1b[1m{}\x1b[0m vehicle state frames ({loss_percent:.2}%) in {} gaps out of {} received.",
+			telemetry.frames_lost,
+			telemetry.gaps,
+			telemetry.frames_received,
+		);
+	} else {
+		pass!("Received \x1b[1m{}\x1b[0m vehicle state frames with no loss detected.", telemetry.frames_received);
+	}
+
+	if telemetry.oversized_frames > 0 {
+		warn!(
+			"Truncated \x1b[1m{}\x1b[0m oversized vehicle state frames, which were quarantined.",
+			telemetry.oversized_frames,
+		);
+	}
+
+	let latency = &metrics.latency;
+
+	for (path, statistics) in [
+		("frame to database", &latency.frame_to_commit_ms),
+		("frame to forwarding", &latency.frame_to_forward_ms),
+		("command to flight", &latency.command_to_write_ms),
+	] {
+		if let Some(statistics) = statistics {
+			println!("  {path} latency: {:.1} ms median, {:.1} ms p99, {:.1} ms max", statistics.p50, statistics.p99, statistics.max);
+		}
+	}
+
+	let clocks: ClocksResponse = client
+		.get(format!("{}/status/clocks", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	for participant in &clocks.drifted {
+		warn!("Clock of \x1b[1m{participant}\x1b[0m is {}.", clocks.participants[participant].describe());
+	}
+
+	if clocks.drifted.is_empty() && !clocks.participants.is_empty() {
+		let count = clocks.participants.len();
+		let plural = if count == 1 { "" } else { "s" };
+		pass!("No drift among the clocks of {count} reporting participant{plural}.");
+	}
+
+	let Some(connected_seconds) = flight.connected_seconds else {
+		fail!("Flight computer is not connected.");
+		return Ok(());
+	};
+
+	pass!("Flight computer has been connected for \x1b[1m{connected_seconds:.0}\x1b[0m seconds.");
+
+	let Some(info) = flight.info else {
+		warn!("Flight computer has not reported its software information.");
+		return Ok(());
+	};
+
+	if flight.version_mismatch {
+		let expected = flight.expected_version.unwrap_or_default();
+		warn!("Flight is running version \x1b[1m{}\x1b[0m, but servo expects \x1b[1m{expected}\x1b[0m.", info.version);
+	} else {
+		pass!("Flight is running version \x1b[1m{}\x1b[0m.", info.version);
+	}
+
+	println!("  build hash: {}", info.build_hash);
+	println!("  uptime: {:.0} seconds", info.uptime_seconds);
+
+	if !info.parameters.is_empty() {
+		println!("  parameters:");
+
+		for (name, value) in &info.parameters {
+			println!("    {name} = {value}");
+		}
+	}
+
+	Ok(())
+}
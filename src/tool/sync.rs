@@ -0,0 +1,116 @@
+use clap::ArgMatches;
+use crate::server::api::{SnapshotKey, SyncBatch, SyncCounts};
+use jeflog::{fail, pass};
+use reqwest::blocking::Client;
+use std::time::Duration;
+
+use super::client::{client_for, http_client, normalize_server, read_error, server_url};
+
+/// How long to wait for a single batch to be read or merged.
+const BATCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Tool function which pushes the snapshots logged within a time range, along
+/// with configurations, sequences, and events, from the configured server to a
+/// remote one, or pulls them from the remote server with `--pull`.
+///
+/// Whatever the receiving server already has is skipped, so syncing the same
+/// range again only sends what is new.
+pub fn sync(args: &ArgMatches) -> anyhow::Result<()> {
+	let remote = normalize_server(args.get_one::<String>("remote").unwrap())?;
+	let remote_client = client_for(&remote, args.get_one::<String>("remote_token").cloned())?;
+	let local_client = http_client()?;
+
+	let (source, destination) = if args.get_flag("pull") {
+		((&remote_client, remote.as_str()), (&local_client, server_url()))
+	} else {
+		((&local_client, server_url()), (&remote_client, remote.as_str()))
+	};
+
+	let mut query = Vec::new();
+
+	for bound in ["from", "to"] {
+		if let Some(value) = args.get_one::<f64>(bound) {
+			query.push((bound, value.to_string()));
+		}
+	}
+
+	let mut totals = SyncCounts::default();
+	let mut after = None::<SnapshotKey>;
+
+	loop {
+		let mut page = query.clone();
+
+		if let Some(after) = after {
+			page.push(("after", after.recorded_at.to_string()));
+			page.push(("after_id", after.id.to_string()));
+		}
+
+		let Some(batch) = fetch(source, &page)? else {
+			return Ok(());
+		};
+
+		// servers which do not give the key of the last snapshot are paged through by time alone
+		let last = batch.last.or_else(|| {
+			batch.snapshots
+				.last()
+				.map(|snapshot| SnapshotKey::after_time(snapshot.recorded_at))
+		});
+
+		// the first batch carries tables and events, so it is sent even without snapshots
+		if last.is_some() || after.is_none() {
+			let Some(counts) = send(destination, &batch)? else {
+				return Ok(());
+			};
+
+			totals.add(counts);
+		}
+
+		match last {
+			Some(last) => after = Some(last),
+			None => break,
+		}
+	}
+
+	pass!(
+		"Synced \x1b[1m{}\x1b[0m new snapshots, {} rows of configuration, and {} events from {} to {}.",
+		totals.snapshots,
+		totals.rows,
+		totals.events,
+		source.1,
+		destination.1,
+	);
+
+	Ok(())
+}
+
+/// Fetches a batch from the source server, or fails and returns `None` if it refused.
+fn fetch((client, server): (&Client, &str), query: &[(&str, String)]) -> anyhow::Result<Option<SyncBatch>> {
+	let response = client
+		.get(format!("{server}/data/sync"))
+		.query(query)
+		.timeout(BATCH_TIMEOUT)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("Failed to read from {server}: {}", read_error(response));
+		return Ok(None);
+	}
+
+	Ok(Some(response.json()?))
+}
+
+/// Sends a batch to the destination server, or fails and returns `None` if it refused.
+fn send((client, server): (&Client, &str), batch: &SyncBatch) -> anyhow::Result<Option<SyncCounts>> {
+	let response = client
+		.post(format!("{server}/data/sync"))
+		.json(batch)
+		.timeout(BATCH_TIMEOUT)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("Failed to sync to {server}: {}", read_error(response));
+		return Ok(None);
+	}
+
+	Ok(Some(response.json()?))
+}
@@ -0,0 +1,67 @@
+use clap::ArgMatches;
+use common::comm::VehicleState;
+use jeflog::fail;
+use std::{thread, time::Duration};
+
+use super::client::{http_client, read_error, server_url};
+
+/// Tool function which prints the latest vehicle state held by the control
+/// server, once or repeatedly at an interval.
+///
+/// Channels are filtered by the server, so a selector it rejects is reported
+/// as its error rather than matching nothing.
+pub fn tail(args: &ArgMatches) -> anyhow::Result<()> {
+	let client = http_client()?;
+	let channels = args.get_many::<String>("channel")
+		.map(|channels| channels.cloned().collect::<Vec<_>>().join(","));
+
+	let interval = Duration::try_from_secs_f64(*args.get_one::<f64>("interval").unwrap())?;
+
+	loop {
+		let mut request = client.get(format!("{}/data/state", server_url()));
+
+		if let Some(channels) = &channels {
+			request = request.query(&[("channels", channels)]);
+		}
+
+		let response = request.send()?;
+
+		if !response.status().is_success() {
+			fail!("{}", read_error(response));
+			return Ok(());
+		}
+
+		let vehicle_state: VehicleState = response.json()?;
+
+		for line in lines(&vehicle_state) {
+			println!("{line}");
+		}
+
+		if args.get_flag("once") {
+			return Ok(());
+		}
+
+		println!();
+		thread::sleep(interval);
+	}
+}
+
+/// The lines describing each reading of a vehicle state, sorted by channel.
+fn lines(vehicle_state: &VehicleState) -> Vec<String> {
+	let mut readings = vehicle_state.sensor_readings
+		.iter()
+		.map(|(channel, measurement)| (channel, measurement.to_string()))
+		.chain(
+			vehicle_state.valve_states
+				.iter()
+				.map(|(channel, state)| (channel, format!("{} (commanded {})", state.actual, state.commanded)))
+		)
+		.collect::<Vec<_>>();
+
+	readings.sort();
+
+	readings
+		.into_iter()
+		.map(|(channel, value)| format!("{channel:<16} {value}"))
+		.collect()
+}
@@ -0,0 +1,132 @@
+use anyhow::anyhow;
+use clap::ArgMatches;
+use crate::server::{api::ThresholdChanges, threshold::{self, Thresholds}};
+use jeflog::{fail, pass, warn};
+use std::{fs, path::{Path, PathBuf}};
+
+use super::client::{http_client, read_error, server_url, ResponseExt};
+
+/// Tool function which syncs redline files with the redlines stored on the control server.
+pub fn thresholds(args: &ArgMatches) -> anyhow::Result<()> {
+	let path = |args: &ArgMatches| args.get_one::<PathBuf>("path").unwrap().clone();
+
+	match args.subcommand() {
+		Some(("pull", args)) => pull(args.get_one::<PathBuf>("path").map(PathBuf::as_path)),
+		Some(("push", args)) => push(&path(args)),
+		Some(("validate", args)) => validate(&path(args)),
+		Some(("diff", args)) => diff(&path(args)),
+		_ => unreachable!("clap requires a thresholds subcommand"),
+	}
+}
+
+/// Reads and validates a redline file, reporting each problem with it.
+fn read(path: &Path) -> anyhow::Result<Thresholds> {
+	let text = fs::read_to_string(path)
+		.map_err(|error| anyhow!("failed to read {}: {error}", path.display()))?;
+
+	let thresholds = threshold::parse(&text)
+		.map_err(|error| anyhow!("{} is not a valid redline file: {error}", path.display()))?;
+
+	let problems = threshold::validate(&thresholds);
+
+	if !problems.is_empty() {
+		for problem in &problems {
+			fail!("{problem}");
+		}
+
+		let plural = if problems.len() == 1 { "" } else { "s" };
+		return Err(anyhow!("{} has {} problem{plural}", path.display(), problems.len()));
+	}
+
+	Ok(thresholds)
+}
+
+/// Fetches the redlines stored on the server.
+fn fetch() -> anyhow::Result<Thresholds> {
+	let thresholds = http_client()?
+		.get(format!("{}/operator/thresholds", server_url()))
+		.send()?
+		.or_server_error()?
+		.json()?;
+
+	Ok(thresholds)
+}
+
+/// Writes the redlines stored on the server to a file, or prints them if no file is given.
+fn pull(path: Option<&Path>) -> anyhow::Result<()> {
+	let thresholds = fetch()?;
+	let text = threshold::to_toml(&thresholds)?;
+
+	let Some(path) = path else {
+		print!("{text}");
+		return Ok(());
+	};
+
+	fs::write(path, text)?;
+
+	let plural = if thresholds.len() == 1 { "" } else { "s" };
+	pass!("Pulled redlines of {} channel{plural} into {}.", thresholds.len(), path.display());
+
+	Ok(())
+}
+
+/// Replaces the redlines stored on the server with those of a file.
+fn push(path: &Path) -> anyhow::Result<()> {
+	let thresholds = read(path)?;
+
+	let response = http_client()?
+		.put(format!("{}/operator/thresholds", server_url()))
+		.json(&thresholds)
+		.send()?;
+
+	if !response.status().is_success() {
+		fail!("{}", read_error(response));
+		return Ok(());
+	}
+
+	let ThresholdChanges { changes } = response.json()?;
+
+	if changes.is_empty() {
+		pass!("Redlines on the server already match {}.", path.display());
+		return Ok(());
+	}
+
+	for change in &changes {
+		println!("  {change}");
+	}
+
+	let plural = if changes.len() == 1 { "" } else { "s" };
+	pass!("Pushed {} with {} change{plural}.", path.display(), changes.len());
+
+	Ok(())
+}
+
+/// Checks a redline file for problems without sending it to the server.
+fn validate(path: &Path) -> anyhow::Result<()> {
+	let thresholds = read(path)?;
+
+	let plural = if thresholds.len() == 1 { "" } else { "s" };
+	pass!("{} is valid, with redlines of {} channel{plural}.", path.display(), thresholds.len());
+
+	Ok(())
+}
+
+/// Shows how pushing a redline file would change the redlines stored on the server.
+fn diff(path: &Path) -> anyhow::Result<()> {
+	let thresholds = read(path)?;
+	let changes = threshold::diff(&fetch()?, &thresholds);
+
+	if changes.is_empty() {
+		pass!("Redlines on the server match {}.", path.display());
+		return Ok(());
+	}
+
+	for change in &changes {
+		println!("  {change}");
+	}
+
+	let plural = if changes.len() == 1 { "" } else { "s" };
+	warn!("Pushing {} would make {} change{plural}.", path.display(), changes.len());
+
+	Ok(())
+}
@@ -1,8 +1,17 @@
+use super::client::{http_client, server_url};
 use std::{fs, path::Path};
 use serde_json::json;
 
 /// Tool function used to upload a sequence to be stored on the control server.
+///
+/// If the given path is a directory, it is uploaded as a bundle: `main.py` is the
+/// entry point, and every other file in the directory is shipped alongside it so
+/// that the sequence may import helper modules or read data files.
 pub fn upload(sequence_path: &Path) -> anyhow::Result<()> {
+	if sequence_path.is_dir() {
+		return upload_bundle(sequence_path);
+	}
+
 	let name = sequence_path
 		.file_stem()
 		.expect("given path does not have a file stem")
@@ -11,8 +20,8 @@ pub fn upload(sequence_path: &Path) -> anyhow::Result<()> {
 
 	let script = base64::encode(fs::read(sequence_path)?);
 
-	let client = reqwest::blocking::Client::new();
-	let response = client.put("http://localhost:7200/operator/sequence")
+	let client = http_client()?;
+	let response = client.put(format!("{}/operator/sequence", server_url()))
 		.json(&json!({
 			"name": name,
 			"script": script
@@ -23,3 +32,63 @@ pub fn upload(sequence_path: &Path) -> anyhow::Result<()> {
 
  	Ok(())
 }
+
+/// Uploads a directory containing `main.py` and its helper files as a sequence bundle.
+fn upload_bundle(bundle_path: &Path) -> anyhow::Result<()> {
+	let name = bundle_path
+		.canonicalize()?
+		.file_name()
+		.expect("given path does not have a directory name")
+		.to_string_lossy()
+		.into_owned();
+
+	let entry_point = bundle_path.join("main.py");
+
+	if !entry_point.is_file() {
+		anyhow::bail!("sequence bundle '{}' does not contain a main.py", bundle_path.display());
+	}
+
+	let script = base64::encode(fs::read(&entry_point)?);
+
+	let mut files = Vec::new();
+	collect_files(bundle_path, "", &mut files)?;
+
+	let client = http_client()?;
+	let response = client.put(format!("{}/operator/sequence-bundle", server_url()))
+		.json(&json!({
+			"name": name,
+			"script": script,
+			"files": files,
+		}))
+		.send()?;
+
+	println!("{response:#?}");
+
+	Ok(())
+}
+
+/// Recursively collects the files of a bundle other than its entry point,
+/// skipping hidden files and Python bytecode caches.
+fn collect_files(directory: &Path, prefix: &str, files: &mut Vec<serde_json::Value>) -> anyhow::Result<()> {
+	for entry in fs::read_dir(directory)? {
+		let entry = entry?;
+		let file_name = entry.file_name().to_string_lossy().into_owned();
+
+		if file_name.starts_with('.') || file_name == "__pycache__" {
+			continue;
+		}
+
+		let relative_path = format!("{prefix}{file_name}");
+
+		if entry.file_type()?.is_dir() {
+			collect_files(&entry.path(), &format!("{relative_path}/"), files)?;
+		} else if relative_path != "main.py" {
+			files.push(json!({
+				"path": relative_path,
+				"contents": base64::encode(fs::read(entry.path())?),
+			}));
+		}
+	}
+
+	Ok(())
+}